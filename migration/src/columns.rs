@@ -0,0 +1,86 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_query::extension::postgres::Type;
+use sea_orm_migration::sea_orm::DbBackend;
+
+// SQLite only allows AUTOINCREMENT on an INTEGER PRIMARY KEY, so there the numeric id is the
+// primary key and uuid is only unique. Entities use uuid as their primary key on every backend
+pub fn id_column<T: IntoIden>(manager: &SchemaManager, column: T) -> ColumnDef {
+    let mut id = ColumnDef::new(column);
+    id.integer().not_null().auto_increment();
+
+    match manager.get_database_backend() {
+        DbBackend::Sqlite => id.primary_key(),
+        _ => id.unique_key(),
+    };
+
+    id
+}
+
+pub fn uuid_column<T: IntoIden>(manager: &SchemaManager, column: T) -> ColumnDef {
+    let mut uuid = ColumnDef::new(column);
+    uuid.string().not_null().unique_key();
+
+    if manager.get_database_backend() != DbBackend::Sqlite {
+        uuid.primary_key();
+    }
+
+    uuid
+}
+
+// A native ENUM on MySQL and Postgres, where the type has to exist first (see
+// create_enum_type). SQLite has no enums so the value is stored as a plain string
+pub fn enum_column<T, N, I, V>(
+    manager: &SchemaManager,
+    column: T,
+    name: N,
+    variants: I,
+) -> ColumnDef
+where
+    T: IntoIden,
+    N: IntoIden,
+    I: IntoIterator<Item = V>,
+    V: IntoIden,
+{
+    let mut enum_column = ColumnDef::new(column);
+
+    match manager.get_database_backend() {
+        DbBackend::Sqlite => enum_column.string(),
+        _ => enum_column.enumeration(name, variants),
+    };
+
+    enum_column
+}
+
+// Postgres enums are standalone types named after the entity's enum_name, other backends
+// declare the variants on the column itself
+pub async fn create_enum_type<N, I, V>(
+    manager: &SchemaManager<'_>,
+    name: N,
+    variants: I,
+) -> Result<(), DbErr>
+where
+    N: IntoIden,
+    I: IntoIterator<Item = V>,
+    V: IntoIden,
+{
+    if manager.get_database_backend() != DbBackend::Postgres {
+        return Ok(());
+    }
+
+    manager
+        .create_type(Type::create().as_enum(name).values(variants).to_owned())
+        .await
+}
+
+pub async fn drop_enum_type<N: IntoIden>(
+    manager: &SchemaManager<'_>,
+    name: N,
+) -> Result<(), DbErr> {
+    if manager.get_database_backend() != DbBackend::Postgres {
+        return Ok(());
+    }
+
+    manager
+        .drop_type(Type::drop().if_exists().name(name).to_owned())
+        .await
+}
@@ -3,6 +3,49 @@ pub use sea_orm_migration::prelude::*;
 mod m20231003_223905_user;
 mod m20231004_112043_wallet;
 mod m20231004_154313_transaction;
+mod m20231004_160000_user_portable;
+mod m20231004_160100_wallet_portable;
+mod m20231004_160200_transaction_portable;
+mod m20261019_090000_scheduled_transfer;
+mod m20261019_100000_payment_request;
+mod m20261019_110000_bill;
+mod m20261019_110100_bill_participant;
+mod m20261019_120000_wallet_hold;
+mod m20261019_120100_transfer_batch;
+mod m20261019_120200_transfer_batch_item;
+mod m20261019_130000_transaction_reversed_status;
+mod m20261019_130100_user_role;
+mod m20261019_130200_transaction_reversal;
+mod m20261019_140000_dispute;
+mod m20261019_150000_wallet_freeze;
+mod m20261019_160000_payment_method;
+mod m20261019_170000_virtual_account;
+mod m20261019_180000_outbox;
+mod m20261019_190000_user_locale;
+mod m20261019_200000_user_phone_number;
+mod m20261019_200100_device_token;
+mod m20261019_200200_notification_preference;
+mod m20261019_210000_webhook_subscription;
+mod m20261019_210100_webhook_delivery;
+mod m20261019_220000_api_key;
+mod m20261019_230000_merchant;
+mod m20261019_230100_payment_link;
+mod m20261019_230200_payment_link_payment;
+mod m20261019_235000_invoice;
+mod m20261019_235100_invoice_item;
+mod m20261019_235200_invoice_payment;
+mod m20261019_235300_qr_code;
+mod m20261019_235400_escrow_account;
+mod m20261019_235500_escrow;
+mod m20261019_235600_virtual_card;
+mod m20261019_235700_card_authorization;
+mod m20261020_090000_transaction_partially_reversed_status;
+mod m20261020_100000_drop_webhook_response_body;
+mod m20261020_110000_boolean_flags;
+mod columns;
+mod mysql_only;
+
+use mysql_only::MySqlOnly;
 
 pub struct Migrator;
 
@@ -10,9 +53,48 @@ pub struct Migrator;
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
         vec![
-            Box::new(m20231003_223905_user::Migration),
-            Box::new(m20231004_112043_wallet::Migration),
-            Box::new(m20231004_154313_transaction::Migration),
+            Box::new(MySqlOnly(m20231003_223905_user::Migration)),
+            Box::new(MySqlOnly(m20231004_112043_wallet::Migration)),
+            Box::new(MySqlOnly(m20231004_154313_transaction::Migration)),
+            Box::new(m20231004_160000_user_portable::Migration),
+            Box::new(m20231004_160100_wallet_portable::Migration),
+            Box::new(m20231004_160200_transaction_portable::Migration),
+            Box::new(m20261019_090000_scheduled_transfer::Migration),
+            Box::new(m20261019_100000_payment_request::Migration),
+            Box::new(m20261019_110000_bill::Migration),
+            Box::new(m20261019_110100_bill_participant::Migration),
+            Box::new(m20261019_120000_wallet_hold::Migration),
+            Box::new(m20261019_120100_transfer_batch::Migration),
+            Box::new(m20261019_120200_transfer_batch_item::Migration),
+            Box::new(m20261019_130000_transaction_reversed_status::Migration),
+            Box::new(m20261019_130100_user_role::Migration),
+            Box::new(m20261019_130200_transaction_reversal::Migration),
+            Box::new(m20261019_140000_dispute::Migration),
+            Box::new(m20261019_150000_wallet_freeze::Migration),
+            Box::new(m20261019_160000_payment_method::Migration),
+            Box::new(m20261019_170000_virtual_account::Migration),
+            Box::new(m20261019_180000_outbox::Migration),
+            Box::new(m20261019_190000_user_locale::Migration),
+            Box::new(m20261019_200000_user_phone_number::Migration),
+            Box::new(m20261019_200100_device_token::Migration),
+            Box::new(m20261019_200200_notification_preference::Migration),
+            Box::new(m20261019_210000_webhook_subscription::Migration),
+            Box::new(m20261019_210100_webhook_delivery::Migration),
+            Box::new(m20261019_220000_api_key::Migration),
+            Box::new(m20261019_230000_merchant::Migration),
+            Box::new(m20261019_230100_payment_link::Migration),
+            Box::new(m20261019_230200_payment_link_payment::Migration),
+            Box::new(m20261019_235000_invoice::Migration),
+            Box::new(m20261019_235100_invoice_item::Migration),
+            Box::new(m20261019_235200_invoice_payment::Migration),
+            Box::new(m20261019_235300_qr_code::Migration),
+            Box::new(m20261019_235400_escrow_account::Migration),
+            Box::new(m20261019_235500_escrow::Migration),
+            Box::new(m20261019_235600_virtual_card::Migration),
+            Box::new(m20261019_235700_card_authorization::Migration),
+            Box::new(m20261020_090000_transaction_partially_reversed_status::Migration),
+            Box::new(m20261020_100000_drop_webhook_response_body::Migration),
+            Box::new(m20261020_110000_boolean_flags::Migration),
         ]
     }
 }
@@ -0,0 +1,94 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+use super::m20231004_112043_wallet::Wallets;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // MySQL already has this table from the original migration
+        if manager.get_database_backend() == DbBackend::MySql {
+            return Ok(());
+        }
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Wallets::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, Wallets::Id))
+                    .col(&mut uuid_column(manager, Wallets::Uuid))
+                    .col(
+                        ColumnDef::new(Wallets::Default)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(Wallets::CurrentBalance)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(Wallets::PreviousBalance)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(ColumnDef::new(Wallets::UserId).string().not_null())
+                    .col(
+                        ColumnDef::new(Wallets::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Wallets::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Wallets::DeletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("wallets_user_id_foreign")
+                            .from(Wallets::Table, Wallets::UserId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("wallets_user_id_index")
+                    .table(Wallets::Table)
+                    .col(Wallets::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() == DbBackend::MySql {
+            return Ok(());
+        }
+
+        manager
+            .drop_table(Table::drop().table(Wallets::Table).to_owned())
+            .await
+    }
+}
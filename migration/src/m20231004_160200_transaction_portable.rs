@@ -0,0 +1,255 @@
+use sea_orm::EnumIter;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+use super::columns::{create_enum_type, drop_enum_type, enum_column, id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+use super::m20231004_112043_wallet::Wallets;
+use super::m20231004_154313_transaction::Transactions;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // MySQL already has this table from the original migration
+        if manager.get_database_backend() == DbBackend::MySql {
+            return Ok(());
+        }
+
+        create_enum_type(
+            manager,
+            TransactionType::Table,
+            [TransactionType::Credit, TransactionType::Debit],
+        )
+        .await?;
+        create_enum_type(
+            manager,
+            TransactionStatus::Table,
+            [
+                TransactionStatus::Successful,
+                TransactionStatus::Pending,
+                TransactionStatus::Failed,
+            ],
+        )
+        .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Transactions::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, Transactions::Id))
+                    .col(&mut uuid_column(manager, Transactions::Uuid))
+                    .col(
+                        ColumnDef::new(Transactions::Amount)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(&mut enum_column(
+                        manager,
+                        Transactions::TrxType,
+                        TransactionType::Table,
+                        [TransactionType::Credit, TransactionType::Debit],
+                    ))
+                    .col(
+                        enum_column(
+                            manager,
+                            Transactions::Status,
+                            TransactionStatus::Table,
+                            [
+                                TransactionStatus::Successful,
+                                TransactionStatus::Pending,
+                                TransactionStatus::Failed,
+                            ],
+                        )
+                        .default("successful"),
+                    )
+                    .col(
+                        ColumnDef::new(Transactions::Description)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Transactions::ProviderReference)
+                            .string()
+                            .null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(Transactions::CurrentBalance)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(Transactions::PreviousBalance)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(ColumnDef::new(Transactions::UserId).string().not_null())
+                    .col(ColumnDef::new(Transactions::WalletId).string().not_null())
+                    .col(ColumnDef::new(Transactions::Provider).string().not_null())
+                    .col(
+                        ColumnDef::new(Transactions::Fees)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(Transactions::ProviderFees)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(ColumnDef::new(Transactions::Category).string().not_null())
+                    .col(ColumnDef::new(Transactions::Meta).text().null())
+                    .col(
+                        ColumnDef::new(Transactions::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Transactions::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Transactions::DeletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("transactions_user_id_foreign")
+                            .from(Transactions::Table, Transactions::UserId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("transactions_wallet_id_foreign")
+                            .from(Transactions::Table, Transactions::WalletId)
+                            .to(Wallets::Table, Wallets::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("transactions_amount_index")
+                    .table(Transactions::Table)
+                    .col(Transactions::Amount)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("transactions_trx_type_index")
+                    .table(Transactions::Table)
+                    .col(Transactions::TrxType)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("transactions_status_index")
+                    .table(Transactions::Table)
+                    .col(Transactions::Status)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("transactions_user_id_index")
+                    .table(Transactions::Table)
+                    .col(Transactions::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("transactions_wallet_id_index")
+                    .table(Transactions::Table)
+                    .col(Transactions::WalletId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("transactions_provider_index")
+                    .table(Transactions::Table)
+                    .col(Transactions::Provider)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("transactions_fees_index")
+                    .table(Transactions::Table)
+                    .col(Transactions::Fees)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("transactions_category_index")
+                    .table(Transactions::Table)
+                    .col(Transactions::Category)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() == DbBackend::MySql {
+            return Ok(());
+        }
+
+        manager
+            .drop_table(Table::drop().table(Transactions::Table).to_owned())
+            .await?;
+
+        drop_enum_type(manager, TransactionType::Table).await?;
+        drop_enum_type(manager, TransactionStatus::Table).await
+    }
+}
+
+#[derive(Iden, EnumIter)]
+pub enum TransactionStatus {
+    #[iden = "status"]
+    Table,
+    Successful,
+    Pending,
+    Failed,
+}
+
+#[derive(Iden, EnumIter)]
+pub enum TransactionType {
+    #[iden = "trx_type"]
+    Table,
+    Credit,
+    Debit,
+}
@@ -0,0 +1,170 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ScheduledTransfers::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, ScheduledTransfers::Id))
+                    .col(&mut uuid_column(manager, ScheduledTransfers::Uuid))
+                    .col(
+                        ColumnDef::new(ScheduledTransfers::UserId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledTransfers::ReceiverId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledTransfers::Amount)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledTransfers::Narration)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledTransfers::CronExpression)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledTransfers::NextRunAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledTransfers::LastRunAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledTransfers::EndAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledTransfers::Status)
+                            .string()
+                            .not_null()
+                            .default("active"),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledTransfers::RunCount)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledTransfers::RetryCount)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledTransfers::MaxRetries)
+                            .integer()
+                            .not_null()
+                            .default(3),
+                    )
+                    .col(ColumnDef::new(ScheduledTransfers::LastError).text().null())
+                    .col(
+                        ColumnDef::new(ScheduledTransfers::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledTransfers::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledTransfers::DeletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("scheduled_transfers_user_id_foreign")
+                            .from(ScheduledTransfers::Table, ScheduledTransfers::UserId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("scheduled_transfers_receiver_id_foreign")
+                            .from(ScheduledTransfers::Table, ScheduledTransfers::ReceiverId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("scheduled_transfers_user_id_index")
+                    .table(ScheduledTransfers::Table)
+                    .col(ScheduledTransfers::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("scheduled_transfers_status_next_run_at_index")
+                    .table(ScheduledTransfers::Table)
+                    .col(ScheduledTransfers::Status)
+                    .col(ScheduledTransfers::NextRunAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ScheduledTransfers::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum ScheduledTransfers {
+    Table,
+    Id,
+    Uuid,
+    UserId,
+    ReceiverId,
+    Amount,
+    Narration,
+    CronExpression,
+    NextRunAt,
+    LastRunAt,
+    EndAt,
+    Status,
+    RunCount,
+    RetryCount,
+    MaxRetries,
+    LastError,
+    CreatedAt,
+    UpdatedAt,
+    DeletedAt,
+}
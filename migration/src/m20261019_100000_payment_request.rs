@@ -0,0 +1,142 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PaymentRequests::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, PaymentRequests::Id))
+                    .col(&mut uuid_column(manager, PaymentRequests::Uuid))
+                    .col(
+                        ColumnDef::new(PaymentRequests::RequesterId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PaymentRequests::PayerId).string().not_null())
+                    .col(
+                        ColumnDef::new(PaymentRequests::Amount)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(ColumnDef::new(PaymentRequests::Narration).string().null())
+                    .col(
+                        ColumnDef::new(PaymentRequests::Status)
+                            .string()
+                            .not_null()
+                            .default("pending"),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentRequests::TransactionReference)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentRequests::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentRequests::RespondedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentRequests::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentRequests::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentRequests::DeletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("payment_requests_requester_id_foreign")
+                            .from(PaymentRequests::Table, PaymentRequests::RequesterId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("payment_requests_payer_id_foreign")
+                            .from(PaymentRequests::Table, PaymentRequests::PayerId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("payment_requests_requester_id_index")
+                    .table(PaymentRequests::Table)
+                    .col(PaymentRequests::RequesterId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("payment_requests_payer_id_index")
+                    .table(PaymentRequests::Table)
+                    .col(PaymentRequests::PayerId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("payment_requests_status_index")
+                    .table(PaymentRequests::Table)
+                    .col(PaymentRequests::Status)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PaymentRequests::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum PaymentRequests {
+    Table,
+    Id,
+    Uuid,
+    RequesterId,
+    PayerId,
+    Amount,
+    Narration,
+    Status,
+    TransactionReference,
+    ExpiresAt,
+    RespondedAt,
+    CreatedAt,
+    UpdatedAt,
+    DeletedAt,
+}
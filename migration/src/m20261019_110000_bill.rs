@@ -0,0 +1,101 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Bills::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, Bills::Id))
+                    .col(&mut uuid_column(manager, Bills::Uuid))
+                    .col(ColumnDef::new(Bills::CreatorId).string().not_null())
+                    .col(ColumnDef::new(Bills::Title).string().not_null())
+                    .col(
+                        ColumnDef::new(Bills::TotalAmount)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(Bills::AmountPaid)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(ColumnDef::new(Bills::SplitType).string().not_null())
+                    .col(
+                        ColumnDef::new(Bills::Status)
+                            .string()
+                            .not_null()
+                            .default("open"),
+                    )
+                    .col(
+                        ColumnDef::new(Bills::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Bills::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Bills::DeletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("bills_creator_id_foreign")
+                            .from(Bills::Table, Bills::CreatorId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("bills_creator_id_index")
+                    .table(Bills::Table)
+                    .col(Bills::CreatorId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Bills::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Bills {
+    Table,
+    Id,
+    Uuid,
+    CreatorId,
+    Title,
+    TotalAmount,
+    AmountPaid,
+    SplitType,
+    Status,
+    CreatedAt,
+    UpdatedAt,
+    DeletedAt,
+}
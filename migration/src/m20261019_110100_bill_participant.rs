@@ -0,0 +1,129 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+use super::m20261019_100000_payment_request::PaymentRequests;
+use super::m20261019_110000_bill::Bills;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BillParticipants::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, BillParticipants::Id))
+                    .col(&mut uuid_column(manager, BillParticipants::Uuid))
+                    .col(ColumnDef::new(BillParticipants::BillId).string().not_null())
+                    .col(ColumnDef::new(BillParticipants::UserId).string().not_null())
+                    .col(
+                        ColumnDef::new(BillParticipants::Amount)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(BillParticipants::Status)
+                            .string()
+                            .not_null()
+                            .default("pending"),
+                    )
+                    .col(
+                        ColumnDef::new(BillParticipants::PaymentRequestId)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(BillParticipants::PaidAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(BillParticipants::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BillParticipants::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BillParticipants::DeletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("bill_participants_bill_id_foreign")
+                            .from(BillParticipants::Table, BillParticipants::BillId)
+                            .to(Bills::Table, Bills::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("bill_participants_user_id_foreign")
+                            .from(BillParticipants::Table, BillParticipants::UserId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("bill_participants_payment_request_id_foreign")
+                            .from(BillParticipants::Table, BillParticipants::PaymentRequestId)
+                            .to(PaymentRequests::Table, PaymentRequests::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("bill_participants_bill_id_index")
+                    .table(BillParticipants::Table)
+                    .col(BillParticipants::BillId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("bill_participants_user_id_index")
+                    .table(BillParticipants::Table)
+                    .col(BillParticipants::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BillParticipants::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum BillParticipants {
+    Table,
+    Id,
+    Uuid,
+    BillId,
+    UserId,
+    Amount,
+    Status,
+    PaymentRequestId,
+    PaidAt,
+    CreatedAt,
+    UpdatedAt,
+    DeletedAt,
+}
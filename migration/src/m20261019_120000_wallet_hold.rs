@@ -0,0 +1,114 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+use super::m20231004_112043_wallet::Wallets;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WalletHolds::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, WalletHolds::Id))
+                    .col(&mut uuid_column(manager, WalletHolds::Uuid))
+                    .col(ColumnDef::new(WalletHolds::WalletId).string().not_null())
+                    .col(ColumnDef::new(WalletHolds::UserId).string().not_null())
+                    .col(
+                        ColumnDef::new(WalletHolds::Amount)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(ColumnDef::new(WalletHolds::Reason).string().not_null())
+                    .col(ColumnDef::new(WalletHolds::Reference).string().not_null())
+                    .col(
+                        ColumnDef::new(WalletHolds::Status)
+                            .string()
+                            .not_null()
+                            .default("active"),
+                    )
+                    .col(
+                        ColumnDef::new(WalletHolds::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WalletHolds::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WalletHolds::DeletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("wallet_holds_wallet_id_foreign")
+                            .from(WalletHolds::Table, WalletHolds::WalletId)
+                            .to(Wallets::Table, Wallets::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("wallet_holds_user_id_foreign")
+                            .from(WalletHolds::Table, WalletHolds::UserId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("wallet_holds_wallet_id_status_index")
+                    .table(WalletHolds::Table)
+                    .col(WalletHolds::WalletId)
+                    .col(WalletHolds::Status)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("wallet_holds_reference_index")
+                    .table(WalletHolds::Table)
+                    .col(WalletHolds::Reference)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WalletHolds::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum WalletHolds {
+    Table,
+    Id,
+    Uuid,
+    WalletId,
+    UserId,
+    Amount,
+    Reason,
+    Reference,
+    Status,
+    CreatedAt,
+    UpdatedAt,
+    DeletedAt,
+}
@@ -0,0 +1,129 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TransferBatches::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, TransferBatches::Id))
+                    .col(&mut uuid_column(manager, TransferBatches::Uuid))
+                    .col(ColumnDef::new(TransferBatches::UserId).string().not_null())
+                    .col(
+                        ColumnDef::new(TransferBatches::TotalAmount)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(TransferBatches::ItemCount)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(TransferBatches::SuccessfulCount)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(TransferBatches::FailedCount)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(TransferBatches::HoldId).string().null())
+                    .col(
+                        ColumnDef::new(TransferBatches::Status)
+                            .string()
+                            .not_null()
+                            .default("processing"),
+                    )
+                    .col(
+                        ColumnDef::new(TransferBatches::CompletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(TransferBatches::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TransferBatches::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TransferBatches::DeletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("transfer_batches_user_id_foreign")
+                            .from(TransferBatches::Table, TransferBatches::UserId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("transfer_batches_user_id_index")
+                    .table(TransferBatches::Table)
+                    .col(TransferBatches::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("transfer_batches_status_index")
+                    .table(TransferBatches::Table)
+                    .col(TransferBatches::Status)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TransferBatches::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum TransferBatches {
+    Table,
+    Id,
+    Uuid,
+    UserId,
+    TotalAmount,
+    ItemCount,
+    SuccessfulCount,
+    FailedCount,
+    HoldId,
+    Status,
+    CompletedAt,
+    CreatedAt,
+    UpdatedAt,
+    DeletedAt,
+}
@@ -0,0 +1,148 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+use super::m20261019_120100_transfer_batch::TransferBatches;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TransferBatchItems::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, TransferBatchItems::Id))
+                    .col(&mut uuid_column(manager, TransferBatchItems::Uuid))
+                    .col(
+                        ColumnDef::new(TransferBatchItems::BatchId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TransferBatchItems::Position)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TransferBatchItems::ReceiverType)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TransferBatchItems::ReceiverId)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(TransferBatchItems::AccountNumber)
+                            .string()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(TransferBatchItems::BankCode).string().null())
+                    .col(
+                        ColumnDef::new(TransferBatchItems::AccountName)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(TransferBatchItems::Amount)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(TransferBatchItems::Narration)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(TransferBatchItems::Status)
+                            .string()
+                            .not_null()
+                            .default("pending"),
+                    )
+                    .col(
+                        ColumnDef::new(TransferBatchItems::TransactionReference)
+                            .string()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(TransferBatchItems::Error).text().null())
+                    .col(
+                        ColumnDef::new(TransferBatchItems::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TransferBatchItems::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TransferBatchItems::DeletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("transfer_batch_items_batch_id_foreign")
+                            .from(TransferBatchItems::Table, TransferBatchItems::BatchId)
+                            .to(TransferBatches::Table, TransferBatches::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("transfer_batch_items_receiver_id_foreign")
+                            .from(TransferBatchItems::Table, TransferBatchItems::ReceiverId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("transfer_batch_items_batch_id_position_index")
+                    .table(TransferBatchItems::Table)
+                    .col(TransferBatchItems::BatchId)
+                    .col(TransferBatchItems::Position)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TransferBatchItems::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum TransferBatchItems {
+    Table,
+    Id,
+    Uuid,
+    BatchId,
+    Position,
+    ReceiverType,
+    ReceiverId,
+    AccountNumber,
+    BankCode,
+    AccountName,
+    Amount,
+    Narration,
+    Status,
+    TransactionReference,
+    Error,
+    CreatedAt,
+    UpdatedAt,
+    DeletedAt,
+}
@@ -0,0 +1,87 @@
+use sea_orm::{DbBackend, EnumIter};
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_query::extension::postgres::Type;
+
+use super::m20231004_154313_transaction::Transactions;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        match manager.get_database_backend() {
+            DbBackend::MySql => {}
+            DbBackend::Postgres => {
+                return manager
+                    .alter_type(
+                        Type::alter()
+                            .name(TransactionStatus::Table)
+                            .add_value(TransactionStatus::Reversed)
+                            .to_owned(),
+                    )
+                    .await
+            }
+            // SQLite stores the status as a plain string, any value already fits
+            DbBackend::Sqlite => return Ok(()),
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transactions::Table)
+                    .modify_column(
+                        ColumnDef::new(Transactions::Status)
+                            .enumeration(
+                                TransactionStatus::Table,
+                                [
+                                    TransactionStatus::Successful,
+                                    TransactionStatus::Pending,
+                                    TransactionStatus::Failed,
+                                    TransactionStatus::Reversed,
+                                ],
+                            )
+                            .default("successful"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres can't drop a value from an enum type, the unused variant is left in place
+        if manager.get_database_backend() != DbBackend::MySql {
+            return Ok(());
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transactions::Table)
+                    .modify_column(
+                        ColumnDef::new(Transactions::Status)
+                            .enumeration(
+                                TransactionStatus::Table,
+                                [
+                                    TransactionStatus::Successful,
+                                    TransactionStatus::Pending,
+                                    TransactionStatus::Failed,
+                                ],
+                            )
+                            .default("successful"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden, EnumIter)]
+pub enum TransactionStatus {
+    #[iden = "status"]
+    Table,
+    Successful,
+    Pending,
+    Failed,
+    Reversed,
+}
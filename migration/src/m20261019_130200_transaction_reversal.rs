@@ -0,0 +1,127 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+use super::m20231004_154313_transaction::Transactions;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TransactionReversals::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, TransactionReversals::Id))
+                    .col(&mut uuid_column(manager, TransactionReversals::Uuid))
+                    .col(
+                        ColumnDef::new(TransactionReversals::TransactionId)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(TransactionReversals::ReversedBy)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TransactionReversals::Reason)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TransactionReversals::Amount)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(TransactionReversals::RecoveredAmount)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(TransactionReversals::ShortfallAmount)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(TransactionReversals::ProviderRefundReference)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(TransactionReversals::Status)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TransactionReversals::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TransactionReversals::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TransactionReversals::DeletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("transaction_reversals_transaction_id_foreign")
+                            .from(
+                                TransactionReversals::Table,
+                                TransactionReversals::TransactionId,
+                            )
+                            .to(Transactions::Table, Transactions::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("transaction_reversals_reversed_by_foreign")
+                            .from(
+                                TransactionReversals::Table,
+                                TransactionReversals::ReversedBy,
+                            )
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TransactionReversals::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum TransactionReversals {
+    Table,
+    Id,
+    Uuid,
+    TransactionId,
+    ReversedBy,
+    Reason,
+    Amount,
+    RecoveredAmount,
+    ShortfallAmount,
+    ProviderRefundReference,
+    Status,
+    CreatedAt,
+    UpdatedAt,
+    DeletedAt,
+}
@@ -0,0 +1,176 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+use super::m20231004_154313_transaction::Transactions;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Disputes::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, Disputes::Id))
+                    .col(&mut uuid_column(manager, Disputes::Uuid))
+                    .col(ColumnDef::new(Disputes::TransactionId).string().not_null())
+                    .col(ColumnDef::new(Disputes::UserId).string().not_null())
+                    .col(ColumnDef::new(Disputes::CounterpartyId).string().null())
+                    .col(
+                        ColumnDef::new(Disputes::Amount)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(ColumnDef::new(Disputes::Reason).text().not_null())
+                    .col(
+                        ColumnDef::new(Disputes::AttachmentReference)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(Disputes::Status)
+                            .string()
+                            .not_null()
+                            .default("open"),
+                    )
+                    .col(ColumnDef::new(Disputes::ResolvedInFavourOf).string().null())
+                    .col(ColumnDef::new(Disputes::ResolutionNote).text().null())
+                    .col(ColumnDef::new(Disputes::AssignedTo).string().null())
+                    .col(ColumnDef::new(Disputes::HoldId).string().null())
+                    .col(ColumnDef::new(Disputes::ReversalId).string().null())
+                    .col(
+                        ColumnDef::new(Disputes::FirstResponseDueAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Disputes::ResolutionDueAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Disputes::FirstRespondedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(Disputes::ResolvedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(Disputes::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Disputes::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Disputes::DeletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("disputes_transaction_id_foreign")
+                            .from(Disputes::Table, Disputes::TransactionId)
+                            .to(Transactions::Table, Transactions::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("disputes_user_id_foreign")
+                            .from(Disputes::Table, Disputes::UserId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("disputes_counterparty_id_foreign")
+                            .from(Disputes::Table, Disputes::CounterpartyId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("disputes_assigned_to_foreign")
+                            .from(Disputes::Table, Disputes::AssignedTo)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("disputes_transaction_id_index")
+                    .table(Disputes::Table)
+                    .col(Disputes::TransactionId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("disputes_user_id_index")
+                    .table(Disputes::Table)
+                    .col(Disputes::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("disputes_status_resolution_due_at_index")
+                    .table(Disputes::Table)
+                    .col(Disputes::Status)
+                    .col(Disputes::ResolutionDueAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Disputes::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Disputes {
+    Table,
+    Id,
+    Uuid,
+    TransactionId,
+    UserId,
+    CounterpartyId,
+    Amount,
+    Reason,
+    AttachmentReference,
+    Status,
+    ResolvedInFavourOf,
+    ResolutionNote,
+    AssignedTo,
+    HoldId,
+    ReversalId,
+    FirstResponseDueAt,
+    ResolutionDueAt,
+    FirstRespondedAt,
+    ResolvedAt,
+    CreatedAt,
+    UpdatedAt,
+    DeletedAt,
+}
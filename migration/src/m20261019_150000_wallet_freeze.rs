@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20231004_112043_wallet::Wallets;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// One column per statement, SQLite can't alter more than one column at a time
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Wallets::Table)
+                    .add_column(
+                        ColumnDef::new(WalletFreeze::FrozenAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Wallets::Table)
+                    .add_column(ColumnDef::new(WalletFreeze::FrozenReason).string().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Wallets::Table)
+                    .drop_column(WalletFreeze::FrozenReason)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Wallets::Table)
+                    .drop_column(WalletFreeze::FrozenAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum WalletFreeze {
+    FrozenAt,
+    FrozenReason,
+}
@@ -0,0 +1,105 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PaymentMethods::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, PaymentMethods::Id))
+                    .col(&mut uuid_column(manager, PaymentMethods::Uuid))
+                    .col(ColumnDef::new(PaymentMethods::UserId).string().not_null())
+                    .col(ColumnDef::new(PaymentMethods::Provider).string().not_null())
+                    .col(
+                        ColumnDef::new(PaymentMethods::AuthorizationCode)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentMethods::Signature)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PaymentMethods::Email).string().not_null())
+                    .col(ColumnDef::new(PaymentMethods::Brand).string().null())
+                    .col(ColumnDef::new(PaymentMethods::Bank).string().null())
+                    .col(ColumnDef::new(PaymentMethods::Last4).string().not_null())
+                    .col(ColumnDef::new(PaymentMethods::ExpMonth).string().not_null())
+                    .col(ColumnDef::new(PaymentMethods::ExpYear).string().not_null())
+                    .col(
+                        ColumnDef::new(PaymentMethods::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentMethods::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentMethods::DeletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("payment_methods_user_id_foreign")
+                            .from(PaymentMethods::Table, PaymentMethods::UserId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The same card charged again comes back with the same signature
+        manager
+            .create_index(
+                Index::create()
+                    .name("payment_methods_user_id_signature_unique")
+                    .table(PaymentMethods::Table)
+                    .col(PaymentMethods::UserId)
+                    .col(PaymentMethods::Signature)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PaymentMethods::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum PaymentMethods {
+    Table,
+    Id,
+    Uuid,
+    UserId,
+    Provider,
+    AuthorizationCode,
+    Signature,
+    Email,
+    Brand,
+    Bank,
+    Last4,
+    ExpMonth,
+    ExpYear,
+    CreatedAt,
+    UpdatedAt,
+    DeletedAt,
+}
@@ -0,0 +1,140 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+use super::m20231004_112043_wallet::Wallets;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(VirtualAccounts::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, VirtualAccounts::Id))
+                    .col(&mut uuid_column(manager, VirtualAccounts::Uuid))
+                    .col(ColumnDef::new(VirtualAccounts::UserId).string().not_null())
+                    .col(
+                        ColumnDef::new(VirtualAccounts::WalletId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(VirtualAccounts::Provider)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(VirtualAccounts::CustomerCode)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(VirtualAccounts::ProviderAccountId)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(VirtualAccounts::AccountNumber)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(VirtualAccounts::AccountName)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(VirtualAccounts::BankName)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(VirtualAccounts::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(VirtualAccounts::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(VirtualAccounts::DeletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("virtual_accounts_user_id_foreign")
+                            .from(VirtualAccounts::Table, VirtualAccounts::UserId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("virtual_accounts_wallet_id_foreign")
+                            .from(VirtualAccounts::Table, VirtualAccounts::WalletId)
+                            .to(Wallets::Table, Wallets::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // One account per user with each provider
+        manager
+            .create_index(
+                Index::create()
+                    .name("virtual_accounts_user_id_provider_unique")
+                    .table(VirtualAccounts::Table)
+                    .col(VirtualAccounts::UserId)
+                    .col(VirtualAccounts::Provider)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Incoming transfers are matched to the account by the provider's customer
+        manager
+            .create_index(
+                Index::create()
+                    .name("virtual_accounts_customer_code_index")
+                    .table(VirtualAccounts::Table)
+                    .col(VirtualAccounts::CustomerCode)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(VirtualAccounts::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum VirtualAccounts {
+    Table,
+    Id,
+    Uuid,
+    UserId,
+    WalletId,
+    Provider,
+    CustomerCode,
+    ProviderAccountId,
+    AccountNumber,
+    AccountName,
+    BankName,
+    CreatedAt,
+    UpdatedAt,
+    DeletedAt,
+}
@@ -0,0 +1,95 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(OutboxMessages::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, OutboxMessages::Id))
+                    .col(&mut uuid_column(manager, OutboxMessages::Uuid))
+                    .col(ColumnDef::new(OutboxMessages::Kind).string().not_null())
+                    .col(ColumnDef::new(OutboxMessages::Payload).text().not_null())
+                    .col(
+                        ColumnDef::new(OutboxMessages::Status)
+                            .string()
+                            .not_null()
+                            .default("pending"),
+                    )
+                    .col(
+                        ColumnDef::new(OutboxMessages::Attempts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(OutboxMessages::NextAttemptAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OutboxMessages::LastError).text().null())
+                    .col(
+                        ColumnDef::new(OutboxMessages::DeliveredAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(OutboxMessages::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(OutboxMessages::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("outbox_messages_status_next_attempt_at_index")
+                    .table(OutboxMessages::Table)
+                    .col(OutboxMessages::Status)
+                    .col(OutboxMessages::NextAttemptAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OutboxMessages::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum OutboxMessages {
+    Table,
+    Id,
+    Uuid,
+    Kind,
+    Payload,
+    Status,
+    Attempts,
+    NextAttemptAt,
+    LastError,
+    DeliveredAt,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,79 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DeviceTokens::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, DeviceTokens::Id))
+                    .col(&mut uuid_column(manager, DeviceTokens::Uuid))
+                    .col(ColumnDef::new(DeviceTokens::UserId).string().not_null())
+                    .col(
+                        ColumnDef::new(DeviceTokens::Token)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(DeviceTokens::Platform).string().not_null())
+                    .col(
+                        ColumnDef::new(DeviceTokens::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DeviceTokens::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("device_tokens_user_id_foreign")
+                            .from(DeviceTokens::Table, DeviceTokens::UserId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("device_tokens_user_id_index")
+                    .table(DeviceTokens::Table)
+                    .col(DeviceTokens::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DeviceTokens::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum DeviceTokens {
+    Table,
+    Id,
+    Uuid,
+    UserId,
+    Token,
+    Platform,
+    CreatedAt,
+    UpdatedAt,
+}
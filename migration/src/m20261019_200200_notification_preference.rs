@@ -0,0 +1,103 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(NotificationPreferences::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, NotificationPreferences::Id))
+                    .col(&mut uuid_column(manager, NotificationPreferences::Uuid))
+                    .col(
+                        ColumnDef::new(NotificationPreferences::UserId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreferences::Event)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreferences::Channel)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreferences::Enabled)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreferences::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreferences::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("notification_preferences_user_id_foreign")
+                            .from(
+                                NotificationPreferences::Table,
+                                NotificationPreferences::UserId,
+                            )
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Only choices that differ from the defaults are stored, one per event and channel
+        manager
+            .create_index(
+                Index::create()
+                    .name("notification_preferences_user_id_event_channel_unique")
+                    .table(NotificationPreferences::Table)
+                    .col(NotificationPreferences::UserId)
+                    .col(NotificationPreferences::Event)
+                    .col(NotificationPreferences::Channel)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(NotificationPreferences::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum NotificationPreferences {
+    Table,
+    Id,
+    Uuid,
+    UserId,
+    Event,
+    Channel,
+    Enabled,
+    CreatedAt,
+    UpdatedAt,
+}
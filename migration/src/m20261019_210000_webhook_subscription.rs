@@ -0,0 +1,94 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebhookSubscriptions::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, WebhookSubscriptions::Id))
+                    .col(&mut uuid_column(manager, WebhookSubscriptions::Uuid))
+                    .col(
+                        ColumnDef::new(WebhookSubscriptions::UserId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(WebhookSubscriptions::Url).text().not_null())
+                    .col(
+                        ColumnDef::new(WebhookSubscriptions::Secret)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookSubscriptions::EventTypes)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookSubscriptions::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookSubscriptions::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookSubscriptions::DeletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("webhook_subscriptions_user_id_foreign")
+                            .from(WebhookSubscriptions::Table, WebhookSubscriptions::UserId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("webhook_subscriptions_user_id_index")
+                    .table(WebhookSubscriptions::Table)
+                    .col(WebhookSubscriptions::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WebhookSubscriptions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum WebhookSubscriptions {
+    Table,
+    Id,
+    Uuid,
+    UserId,
+    Url,
+    Secret,
+    EventTypes,
+    CreatedAt,
+    UpdatedAt,
+    DeletedAt,
+}
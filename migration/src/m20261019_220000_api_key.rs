@@ -0,0 +1,115 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ApiKeys::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, ApiKeys::Id))
+                    .col(&mut uuid_column(manager, ApiKeys::Uuid))
+                    .col(ColumnDef::new(ApiKeys::UserId).string().not_null())
+                    .col(ColumnDef::new(ApiKeys::Name).string().not_null())
+                    .col(
+                        ColumnDef::new(ApiKeys::PublishableKey)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ApiKeys::SecretHash)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(ApiKeys::SecretLast4).string().not_null())
+                    .col(ColumnDef::new(ApiKeys::SigningSecret).string().not_null())
+                    .col(ColumnDef::new(ApiKeys::Scopes).string().not_null())
+                    .col(ColumnDef::new(ApiKeys::AllowedIps).text().null())
+                    .col(
+                        ColumnDef::new(ApiKeys::RequireSignature)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(
+                        ColumnDef::new(ApiKeys::LastUsedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(ApiKeys::LastUsedIp).string().null())
+                    .col(
+                        ColumnDef::new(ApiKeys::RevokedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ApiKeys::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ApiKeys::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("api_keys_user_id_foreign")
+                            .from(ApiKeys::Table, ApiKeys::UserId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("api_keys_user_id_index")
+                    .table(ApiKeys::Table)
+                    .col(ApiKeys::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ApiKeys::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum ApiKeys {
+    Table,
+    Id,
+    Uuid,
+    UserId,
+    Name,
+    PublishableKey,
+    SecretHash,
+    SecretLast4,
+    SigningSecret,
+    Scopes,
+    AllowedIps,
+    RequireSignature,
+    LastUsedAt,
+    LastUsedIp,
+    RevokedAt,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,78 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+use super::m20231004_112043_wallet::Wallets;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Merchants::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, Merchants::Id))
+                    .col(&mut uuid_column(manager, Merchants::Uuid))
+                    .col(
+                        ColumnDef::new(Merchants::UserId)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(Merchants::BusinessName).string().not_null())
+                    .col(
+                        ColumnDef::new(Merchants::SettlementWalletId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Merchants::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Merchants::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("merchants_user_id_foreign")
+                            .from(Merchants::Table, Merchants::UserId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("merchants_settlement_wallet_id_foreign")
+                            .from(Merchants::Table, Merchants::SettlementWalletId)
+                            .to(Wallets::Table, Wallets::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Merchants::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Merchants {
+    Table,
+    Id,
+    Uuid,
+    UserId,
+    BusinessName,
+    SettlementWalletId,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,109 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20261019_230000_merchant::Merchants;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PaymentLinks::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, PaymentLinks::Id))
+                    .col(&mut uuid_column(manager, PaymentLinks::Uuid))
+                    .col(ColumnDef::new(PaymentLinks::MerchantId).string().not_null())
+                    .col(
+                        ColumnDef::new(PaymentLinks::Slug)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(PaymentLinks::Title).string().not_null())
+                    .col(ColumnDef::new(PaymentLinks::Description).text().null())
+                    .col(
+                        ColumnDef::new(PaymentLinks::Amount)
+                            .decimal_len(18, 2)
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentLinks::Status)
+                            .string()
+                            .not_null()
+                            .default("active"),
+                    )
+                    .col(ColumnDef::new(PaymentLinks::MaxUses).integer().null())
+                    .col(
+                        ColumnDef::new(PaymentLinks::UseCount)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentLinks::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentLinks::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentLinks::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("payment_links_merchant_id_foreign")
+                            .from(PaymentLinks::Table, PaymentLinks::MerchantId)
+                            .to(Merchants::Table, Merchants::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("payment_links_merchant_id_index")
+                    .table(PaymentLinks::Table)
+                    .col(PaymentLinks::MerchantId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PaymentLinks::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum PaymentLinks {
+    Table,
+    Id,
+    Uuid,
+    MerchantId,
+    Slug,
+    Title,
+    Description,
+    Amount,
+    Status,
+    MaxUses,
+    UseCount,
+    ExpiresAt,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,150 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+use super::m20261019_230100_payment_link::PaymentLinks;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PaymentLinkPayments::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, PaymentLinkPayments::Id))
+                    .col(&mut uuid_column(manager, PaymentLinkPayments::Uuid))
+                    .col(
+                        ColumnDef::new(PaymentLinkPayments::PaymentLinkId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentLinkPayments::MerchantId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PaymentLinkPayments::PayerId).string().null())
+                    .col(
+                        ColumnDef::new(PaymentLinkPayments::PayerEmail)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentLinkPayments::Method)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentLinkPayments::Amount)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentLinkPayments::Fee)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentLinkPayments::Reference)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentLinkPayments::Provider)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentLinkPayments::Status)
+                            .string()
+                            .not_null()
+                            .default("pending"),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentLinkPayments::TransactionId)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentLinkPayments::PaidAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentLinkPayments::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentLinkPayments::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("payment_link_payments_payment_link_id_foreign")
+                            .from(
+                                PaymentLinkPayments::Table,
+                                PaymentLinkPayments::PaymentLinkId,
+                            )
+                            .to(PaymentLinks::Table, PaymentLinks::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("payment_link_payments_payer_id_foreign")
+                            .from(PaymentLinkPayments::Table, PaymentLinkPayments::PayerId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("payment_link_payments_payment_link_id_index")
+                    .table(PaymentLinkPayments::Table)
+                    .col(PaymentLinkPayments::PaymentLinkId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PaymentLinkPayments::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum PaymentLinkPayments {
+    Table,
+    Id,
+    Uuid,
+    PaymentLinkId,
+    MerchantId,
+    PayerId,
+    PayerEmail,
+    Method,
+    Amount,
+    Fee,
+    Reference,
+    Provider,
+    Status,
+    TransactionId,
+    PaidAt,
+    CreatedAt,
+    UpdatedAt,
+}
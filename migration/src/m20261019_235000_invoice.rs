@@ -0,0 +1,176 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20261019_230000_merchant::Merchants;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Invoices::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, Invoices::Id))
+                    .col(&mut uuid_column(manager, Invoices::Uuid))
+                    .col(ColumnDef::new(Invoices::MerchantId).string().not_null())
+                    .col(ColumnDef::new(Invoices::Number).string().not_null())
+                    .col(
+                        ColumnDef::new(Invoices::Slug)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(Invoices::CustomerName).string().not_null())
+                    .col(ColumnDef::new(Invoices::CustomerEmail).string().not_null())
+                    .col(
+                        ColumnDef::new(Invoices::Locale)
+                            .string()
+                            .not_null()
+                            .default("en"),
+                    )
+                    .col(
+                        ColumnDef::new(Invoices::Currency)
+                            .string()
+                            .not_null()
+                            .default("NGN"),
+                    )
+                    .col(
+                        ColumnDef::new(Invoices::Subtotal)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(Invoices::TaxRate)
+                            .decimal_len(5, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(Invoices::TaxAmount)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(Invoices::Total)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(Invoices::AmountPaid)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(Invoices::Status)
+                            .string()
+                            .not_null()
+                            .default("open"),
+                    )
+                    .col(ColumnDef::new(Invoices::DueDate).date().not_null())
+                    .col(ColumnDef::new(Invoices::Notes).text().null())
+                    .col(
+                        ColumnDef::new(Invoices::ReminderCount)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(Invoices::LastRemindedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(Invoices::PaidAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(Invoices::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Invoices::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("invoices_merchant_id_foreign")
+                            .from(Invoices::Table, Invoices::MerchantId)
+                            .to(Merchants::Table, Merchants::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Numbers run per merchant
+        manager
+            .create_index(
+                Index::create()
+                    .name("invoices_merchant_id_number_unique")
+                    .table(Invoices::Table)
+                    .col(Invoices::MerchantId)
+                    .col(Invoices::Number)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("invoices_status_due_date_index")
+                    .table(Invoices::Table)
+                    .col(Invoices::Status)
+                    .col(Invoices::DueDate)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Invoices::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Invoices {
+    Table,
+    Id,
+    Uuid,
+    MerchantId,
+    Number,
+    Slug,
+    CustomerName,
+    CustomerEmail,
+    Locale,
+    Currency,
+    Subtotal,
+    TaxRate,
+    TaxAmount,
+    Total,
+    AmountPaid,
+    Status,
+    DueDate,
+    Notes,
+    ReminderCount,
+    LastRemindedAt,
+    PaidAt,
+    CreatedAt,
+    UpdatedAt,
+}
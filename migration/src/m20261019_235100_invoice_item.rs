@@ -0,0 +1,92 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20261019_235000_invoice::Invoices;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(InvoiceItems::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, InvoiceItems::Id))
+                    .col(&mut uuid_column(manager, InvoiceItems::Uuid))
+                    .col(ColumnDef::new(InvoiceItems::InvoiceId).string().not_null())
+                    .col(
+                        ColumnDef::new(InvoiceItems::Description)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(InvoiceItems::Quantity).integer().not_null())
+                    .col(
+                        ColumnDef::new(InvoiceItems::UnitPrice)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(InvoiceItems::Amount)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(InvoiceItems::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(InvoiceItems::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("invoice_items_invoice_id_foreign")
+                            .from(InvoiceItems::Table, InvoiceItems::InvoiceId)
+                            .to(Invoices::Table, Invoices::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("invoice_items_invoice_id_index")
+                    .table(InvoiceItems::Table)
+                    .col(InvoiceItems::InvoiceId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(InvoiceItems::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum InvoiceItems {
+    Table,
+    Id,
+    Uuid,
+    InvoiceId,
+    Description,
+    Quantity,
+    UnitPrice,
+    Amount,
+    CreatedAt,
+    UpdatedAt,
+}
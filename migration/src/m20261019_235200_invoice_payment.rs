@@ -0,0 +1,143 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+use super::m20261019_235000_invoice::Invoices;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(InvoicePayments::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, InvoicePayments::Id))
+                    .col(&mut uuid_column(manager, InvoicePayments::Uuid))
+                    .col(
+                        ColumnDef::new(InvoicePayments::InvoiceId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(InvoicePayments::MerchantId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(InvoicePayments::PayerId).string().null())
+                    .col(
+                        ColumnDef::new(InvoicePayments::PayerEmail)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(InvoicePayments::Method).string().not_null())
+                    .col(
+                        ColumnDef::new(InvoicePayments::Amount)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(InvoicePayments::Fee)
+                            .decimal_len(18, 2)
+                            .not_null()
+                            .default(0.00),
+                    )
+                    .col(
+                        ColumnDef::new(InvoicePayments::Reference)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(InvoicePayments::Provider)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(InvoicePayments::Status)
+                            .string()
+                            .not_null()
+                            .default("pending"),
+                    )
+                    .col(
+                        ColumnDef::new(InvoicePayments::TransactionId)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(InvoicePayments::PaidAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(InvoicePayments::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(InvoicePayments::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("invoice_payments_invoice_id_foreign")
+                            .from(InvoicePayments::Table, InvoicePayments::InvoiceId)
+                            .to(Invoices::Table, Invoices::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("invoice_payments_payer_id_foreign")
+                            .from(InvoicePayments::Table, InvoicePayments::PayerId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("invoice_payments_invoice_id_index")
+                    .table(InvoicePayments::Table)
+                    .col(InvoicePayments::InvoiceId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(InvoicePayments::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum InvoicePayments {
+    Table,
+    Id,
+    Uuid,
+    InvoiceId,
+    MerchantId,
+    PayerId,
+    PayerEmail,
+    Method,
+    Amount,
+    Fee,
+    Reference,
+    Provider,
+    Status,
+    TransactionId,
+    PaidAt,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,116 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+use super::m20231004_112043_wallet::Wallets;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(QrCodes::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, QrCodes::Id))
+                    .col(&mut uuid_column(manager, QrCodes::Uuid))
+                    .col(ColumnDef::new(QrCodes::UserId).string().not_null())
+                    .col(ColumnDef::new(QrCodes::WalletId).string().not_null())
+                    .col(
+                        ColumnDef::new(QrCodes::Amount)
+                            .decimal_len(18, 2)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(QrCodes::Description).string().null())
+                    .col(
+                        ColumnDef::new(QrCodes::Status)
+                            .string()
+                            .not_null()
+                            .default("active"),
+                    )
+                    .col(
+                        ColumnDef::new(QrCodes::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(QrCodes::PaidBy).string().null())
+                    .col(ColumnDef::new(QrCodes::TransactionId).string().null())
+                    .col(
+                        ColumnDef::new(QrCodes::PaidAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(QrCodes::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(QrCodes::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("qr_codes_user_id_foreign")
+                            .from(QrCodes::Table, QrCodes::UserId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("qr_codes_wallet_id_foreign")
+                            .from(QrCodes::Table, QrCodes::WalletId)
+                            .to(Wallets::Table, Wallets::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("qr_codes_paid_by_foreign")
+                            .from(QrCodes::Table, QrCodes::PaidBy)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("qr_codes_user_id_index")
+                    .table(QrCodes::Table)
+                    .col(QrCodes::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(QrCodes::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum QrCodes {
+    Table,
+    Id,
+    Uuid,
+    UserId,
+    WalletId,
+    Amount,
+    Description,
+    Status,
+    ExpiresAt,
+    PaidBy,
+    TransactionId,
+    PaidAt,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,77 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20231003_223905_user::Users;
+use super::m20231004_112043_wallet::Wallets;
+use super::m20261019_130100_user_role::UserRole;
+
+// Must match ESCROW_USER_ID and ESCROW_WALLET_ID in the escrow service
+const ESCROW_USER_ID: &str = "00000000-0000-4000-8000-00000000e5c0";
+const ESCROW_WALLET_ID: &str = "00000000-0000-4000-8000-00000000e5c1";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// The system account escrowed funds are held in until they are released or refunded. It can't log
+// in, as the password is not a valid hash, and can't be sent funds, as it is not verified
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(Users::Table)
+                    .columns([
+                        Users::Uuid.into_iden(),
+                        Users::FirstName.into_iden(),
+                        Users::LastName.into_iden(),
+                        Users::Email.into_iden(),
+                        Users::Password.into_iden(),
+                        Users::IsVerified.into_iden(),
+                        UserRole::Role.into_iden(),
+                    ])
+                    .values_panic([
+                        ESCROW_USER_ID.into(),
+                        "Escrow".into(),
+                        "Money Transfer".into(),
+                        "escrow@system.moneytransfer.am".into(),
+                        "!".into(),
+                        false.into(),
+                        "system".into(),
+                    ])
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(Wallets::Table)
+                    .columns([Wallets::Uuid, Wallets::UserId])
+                    .values_panic([ESCROW_WALLET_ID.into(), ESCROW_USER_ID.into()])
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .exec_stmt(
+                Query::delete()
+                    .from_table(Wallets::Table)
+                    .and_where(Expr::col(Wallets::Uuid).eq(ESCROW_WALLET_ID))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .exec_stmt(
+                Query::delete()
+                    .from_table(Users::Table)
+                    .and_where(Expr::col(Users::Uuid).eq(ESCROW_USER_ID))
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,163 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Escrows::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, Escrows::Id))
+                    .col(&mut uuid_column(manager, Escrows::Uuid))
+                    .col(ColumnDef::new(Escrows::BuyerId).string().not_null())
+                    .col(ColumnDef::new(Escrows::SellerId).string().not_null())
+                    .col(
+                        ColumnDef::new(Escrows::Amount)
+                            .decimal_len(18, 2)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Escrows::Description).string().not_null())
+                    .col(
+                        ColumnDef::new(Escrows::Status)
+                            .string()
+                            .not_null()
+                            .default("funded"),
+                    )
+                    .col(
+                        ColumnDef::new(Escrows::FundingTransactionId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Escrows::SettlementTransactionId)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(Escrows::DeliveredAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(Escrows::AutoReleaseAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(Escrows::DisputeReason).text().null())
+                    .col(
+                        ColumnDef::new(Escrows::DisputedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(Escrows::ResolvedBy).string().null())
+                    .col(ColumnDef::new(Escrows::ResolutionNote).text().null())
+                    .col(
+                        ColumnDef::new(Escrows::SettledAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(Escrows::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Escrows::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("escrows_buyer_id_foreign")
+                            .from(Escrows::Table, Escrows::BuyerId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("escrows_seller_id_foreign")
+                            .from(Escrows::Table, Escrows::SellerId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("escrows_resolved_by_foreign")
+                            .from(Escrows::Table, Escrows::ResolvedBy)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("escrows_buyer_id_index")
+                    .table(Escrows::Table)
+                    .col(Escrows::BuyerId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("escrows_seller_id_index")
+                    .table(Escrows::Table)
+                    .col(Escrows::SellerId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // For the auto-release worker
+        manager
+            .create_index(
+                Index::create()
+                    .name("escrows_status_auto_release_at_index")
+                    .table(Escrows::Table)
+                    .col(Escrows::Status)
+                    .col(Escrows::AutoReleaseAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Escrows::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Escrows {
+    Table,
+    Id,
+    Uuid,
+    BuyerId,
+    SellerId,
+    Amount,
+    Description,
+    Status,
+    FundingTransactionId,
+    SettlementTransactionId,
+    DeliveredAt,
+    AutoReleaseAt,
+    DisputeReason,
+    DisputedAt,
+    ResolvedBy,
+    ResolutionNote,
+    SettledAt,
+    CreatedAt,
+    UpdatedAt,
+}
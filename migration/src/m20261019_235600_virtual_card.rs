@@ -0,0 +1,125 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231003_223905_user::Users;
+use super::m20231004_112043_wallet::Wallets;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(VirtualCards::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, VirtualCards::Id))
+                    .col(&mut uuid_column(manager, VirtualCards::Uuid))
+                    .col(ColumnDef::new(VirtualCards::UserId).string().not_null())
+                    .col(ColumnDef::new(VirtualCards::WalletId).string().not_null())
+                    .col(ColumnDef::new(VirtualCards::Issuer).string().not_null())
+                    .col(
+                        ColumnDef::new(VirtualCards::IssuerReference)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(VirtualCards::Label).string().null())
+                    .col(ColumnDef::new(VirtualCards::NameOnCard).string().not_null())
+                    .col(ColumnDef::new(VirtualCards::Brand).string().not_null())
+                    .col(ColumnDef::new(VirtualCards::Last4).string().not_null())
+                    .col(ColumnDef::new(VirtualCards::ExpMonth).string().not_null())
+                    .col(ColumnDef::new(VirtualCards::ExpYear).string().not_null())
+                    .col(
+                        ColumnDef::new(VirtualCards::Status)
+                            .string()
+                            .not_null()
+                            .default("active"),
+                    )
+                    .col(
+                        ColumnDef::new(VirtualCards::SpendLimit)
+                            .decimal_len(18, 2)
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(VirtualCards::SpendLimitInterval)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(VirtualCards::TerminatedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(VirtualCards::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(VirtualCards::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("virtual_cards_user_id_foreign")
+                            .from(VirtualCards::Table, VirtualCards::UserId)
+                            .to(Users::Table, Users::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("virtual_cards_wallet_id_foreign")
+                            .from(VirtualCards::Table, VirtualCards::WalletId)
+                            .to(Wallets::Table, Wallets::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("virtual_cards_user_id_index")
+                    .table(VirtualCards::Table)
+                    .col(VirtualCards::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(VirtualCards::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum VirtualCards {
+    Table,
+    Id,
+    Uuid,
+    UserId,
+    WalletId,
+    Issuer,
+    IssuerReference,
+    Label,
+    NameOnCard,
+    Brand,
+    Last4,
+    ExpMonth,
+    ExpYear,
+    Status,
+    SpendLimit,
+    SpendLimitInterval,
+    TerminatedAt,
+    CreatedAt,
+    UpdatedAt,
+}
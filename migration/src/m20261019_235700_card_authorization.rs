@@ -0,0 +1,134 @@
+use sea_orm_migration::prelude::*;
+
+use super::columns::{id_column, uuid_column};
+use super::m20231004_112043_wallet::Wallets;
+use super::m20261019_235600_virtual_card::VirtualCards;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CardAuthorizations::Table)
+                    .if_not_exists()
+                    .col(&mut id_column(manager, CardAuthorizations::Id))
+                    .col(&mut uuid_column(manager, CardAuthorizations::Uuid))
+                    .col(
+                        ColumnDef::new(CardAuthorizations::CardId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CardAuthorizations::WalletId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CardAuthorizations::Reference)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(CardAuthorizations::Amount)
+                            .decimal_len(18, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CardAuthorizations::MerchantName)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CardAuthorizations::Status)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CardAuthorizations::DeclineReason)
+                            .string()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(CardAuthorizations::HoldId).string().null())
+                    .col(
+                        ColumnDef::new(CardAuthorizations::ClearedAmount)
+                            .decimal_len(18, 2)
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(CardAuthorizations::TransactionId)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(CardAuthorizations::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CardAuthorizations::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("card_authorizations_card_id_foreign")
+                            .from(CardAuthorizations::Table, CardAuthorizations::CardId)
+                            .to(VirtualCards::Table, VirtualCards::Uuid),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("card_authorizations_wallet_id_foreign")
+                            .from(CardAuthorizations::Table, CardAuthorizations::WalletId)
+                            .to(Wallets::Table, Wallets::Uuid),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Spend limits sum a card's authorizations over a period
+        manager
+            .create_index(
+                Index::create()
+                    .name("card_authorizations_card_id_created_at_index")
+                    .table(CardAuthorizations::Table)
+                    .col(CardAuthorizations::CardId)
+                    .col(CardAuthorizations::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CardAuthorizations::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum CardAuthorizations {
+    Table,
+    Id,
+    Uuid,
+    CardId,
+    WalletId,
+    Reference,
+    Amount,
+    MerchantName,
+    Status,
+    DeclineReason,
+    HoldId,
+    ClearedAmount,
+    TransactionId,
+    CreatedAt,
+    UpdatedAt,
+}
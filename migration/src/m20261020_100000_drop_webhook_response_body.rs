@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20261019_210100_webhook_delivery::WebhookDeliveries;
+
+// Endpoints can answer with anything, including whatever an internal service returned, so their
+// response bodies are no longer kept
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(WebhookDeliveries::Table)
+                    .drop_column(WebhookDeliveries::ResponseBody)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(WebhookDeliveries::Table)
+                    .add_column(
+                        ColumnDef::new(WebhookDeliveries::ResponseBody)
+                            .text()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
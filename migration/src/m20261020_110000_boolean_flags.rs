@@ -0,0 +1,73 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+use super::m20231003_223905_user::Users;
+use super::m20231004_112043_wallet::Wallets;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// The entities read users.is_verified and wallets.default as bool. MySQL stores BOOLEAN as
+// TINYINT(1), so any other value left in the columns is settled to 1 and the columns are
+// declared boolean again. The portable migrations created real booleans on other backends
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DbBackend::MySql {
+            return Ok(());
+        }
+
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Users::Table)
+                    .value(Users::IsVerified, true)
+                    .and_where(Expr::col(Users::IsVerified).is_not_in([0, 1]))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Wallets::Table)
+                    .value(Wallets::Default, true)
+                    .and_where(Expr::col(Wallets::Default).is_not_in([0, 1]))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .modify_column(
+                        ColumnDef::new(Users::IsVerified)
+                            .boolean()
+                            .default(false)
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Wallets::Table)
+                    .modify_column(
+                        ColumnDef::new(Wallets::Default)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    // BOOLEAN is TINYINT(1) on MySQL, the columns are the type they were before
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+// The first migrations shipped when MySQL was the only backend and can't change now that
+// databases have run them. They only run on MySQL, other backends create the same tables in the
+// portable migrations that follow them
+pub struct MySqlOnly<M>(pub M);
+
+impl<M: MigrationName> MigrationName for MySqlOnly<M> {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+}
+
+#[async_trait::async_trait]
+impl<M: MigrationTrait> MigrationTrait for MySqlOnly<M> {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DbBackend::MySql {
+            return Ok(());
+        }
+
+        self.0.up(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DbBackend::MySql {
+            return Ok(());
+        }
+
+        self.0.down(manager).await
+    }
+}
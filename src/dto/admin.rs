@@ -0,0 +1,21 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct ReverseTransactionBody {
+    #[validate(length(min = 4, max = 255))]
+    pub reason: String,
+
+    // Recover the full amount even if the wallet ends up negative. Defaults to partial recovery
+    pub allow_negative_balance: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ReconcileSettlementsBody {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+
+    // Credit users for successful charges that were never credited. Defaults to report only
+    pub auto_credit: Option<bool>,
+}
@@ -0,0 +1,27 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct CreateApiKeyBody {
+    #[validate(length(min = 1, max = 100, message = "Name is required"))]
+    pub name: String,
+
+    #[validate(length(min = 1, message = "At least one scope is required"))]
+    pub scopes: Vec<String>,
+
+    // Any address may use the key when empty
+    #[serde(default)]
+    pub allowed_ips: Vec<String>,
+
+    #[serde(default)]
+    pub require_signature: bool,
+
+    #[validate(length(min = 3, message = "Password must be minimum of three(3) characters"))]
+    pub password: String,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct RotateApiKeyBody {
+    #[validate(length(min = 3, message = "Password must be minimum of three(3) characters"))]
+    pub password: String,
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Deserialize, Serialize, Validate, Debug)]
+pub struct BillParticipantBody {
+    #[validate(length(min = 4))]
+    pub user_id: Option<String>,
+
+    #[validate(email(message = "Email must be a valid email type"))]
+    pub email: Option<String>,
+
+    // Only used for custom splits
+    #[validate(range(min = 1, message = "Participant amount must be at least 1 Naira"))]
+    pub amount: Option<u64>,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct CreateBillBody {
+    #[validate(length(min = 2, max = 100))]
+    pub title: String,
+
+    #[validate(range(min = 100, message = "Minimum bill amount is 100 Naira"))]
+    pub total_amount: u64,
+
+    // "equal" or "custom"
+    pub split_type: String,
+
+    #[validate(length(
+        min = 1,
+        max = 50,
+        message = "A bill must have between 1 and 50 participants"
+    ))]
+    #[validate]
+    pub participants: Vec<BillParticipantBody>,
+}
@@ -0,0 +1,28 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct CreateCardBody {
+    // The default wallet when left out
+    #[validate(length(min = 4))]
+    pub wallet_id: Option<String>,
+
+    #[validate(length(min = 2, max = 50))]
+    pub label: Option<String>,
+
+    #[validate(range(min = 100, message = "Minimum spend limit is 100 Naira"))]
+    pub spend_limit: Option<u64>,
+
+    // "transaction" (default), "daily" or "monthly"
+    pub spend_limit_interval: Option<String>,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct SpendLimitBody {
+    // Left out to remove the limit
+    #[validate(range(min = 100, message = "Minimum spend limit is 100 Naira"))]
+    pub spend_limit: Option<u64>,
+
+    // "transaction" (default), "daily" or "monthly"
+    pub spend_limit_interval: Option<String>,
+}
@@ -0,0 +1,37 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct OpenDisputeBody {
+    #[validate(length(min = 4))]
+    pub transaction_id: String,
+
+    #[validate(length(min = 10, max = 2000))]
+    pub reason: String,
+
+    // Reference to evidence uploaded elsewhere e.g a receipt or screenshot url
+    #[validate(length(min = 4, max = 255))]
+    pub attachment_reference: Option<String>,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct ResolveDisputeBody {
+    // "customer" or "counterparty"
+    pub in_favour_of: String,
+
+    #[validate(length(min = 4, max = 2000))]
+    pub note: String,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct RejectDisputeBody {
+    #[validate(length(min = 4, max = 2000))]
+    pub note: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DisputeListParams {
+    pub status: Option<String>,
+    // Only disputes past their resolution SLA
+    pub overdue: Option<bool>,
+}
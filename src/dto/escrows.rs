@@ -0,0 +1,39 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct CreateEscrowBody {
+    #[validate(range(min = 100, message = "Minimum escrow amount is 100 Naira"))]
+    pub amount: u64,
+
+    #[validate(length(min = 6, max = 6, message = "PIN must be Six(6) characters long"))]
+    pub pin: String,
+
+    #[validate(length(min = 4))]
+    pub seller_id: String,
+
+    // What the buyer is paying for e.g "iPhone 13, 128GB"
+    #[validate(length(min = 3, max = 255))]
+    pub description: String,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct ReleaseEscrowBody {
+    #[validate(length(min = 6, max = 6, message = "PIN must be Six(6) characters long"))]
+    pub pin: String,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct DisputeEscrowBody {
+    #[validate(length(min = 10, max = 2000))]
+    pub reason: String,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct ResolveEscrowBody {
+    // "buyer" or "seller"
+    pub in_favour_of: String,
+
+    #[validate(length(min = 4, max = 2000))]
+    pub note: String,
+}
@@ -0,0 +1,72 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Deserialize, Serialize, Validate, Debug)]
+pub struct InvoiceItemBody {
+    #[validate(length(min = 1, max = 200, message = "Item description is required"))]
+    pub description: String,
+
+    #[validate(range(min = 1, message = "Quantity must be at least 1"))]
+    pub quantity: i32,
+
+    #[validate(range(min = 1, message = "Unit price must be at least 1 Naira"))]
+    pub unit_price: u64,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct CreateInvoiceBody {
+    #[validate(length(min = 2, max = 100, message = "Customer name is required"))]
+    pub customer_name: String,
+
+    #[validate(email(message = "Email must be a valid email type"))]
+    pub customer_email: String,
+
+    // Language for the customer's emails and the invoice, defaults to English
+    pub locale: Option<String>,
+
+    // Defaults to NGN
+    pub currency: Option<String>,
+
+    #[validate(range(
+        min = 0.0,
+        max = 100.0,
+        message = "Tax rate must be between 0 and 100 percent"
+    ))]
+    pub tax_rate: Option<f64>,
+
+    pub due_date: NaiveDate,
+
+    #[validate(length(max = 1000))]
+    pub notes: Option<String>,
+
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "An invoice must have between 1 and 100 items"
+    ))]
+    #[validate]
+    pub items: Vec<InvoiceItemBody>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct InvoiceListParams {
+    pub status: Option<String>,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct InvoiceWalletPaymentBody {
+    #[validate(length(min = 6, max = 6, message = "PIN must be Six(6) characters long"))]
+    pub pin: String,
+
+    // Whatever is left to pay when not set
+    #[validate(range(min = 100, message = "Minimum payment amount is 100 Naira"))]
+    pub amount: Option<u64>,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct InvoiceCardPaymentBody {
+    // Whatever is left to pay when not set
+    #[validate(range(min = 100, message = "Minimum payment amount is 100 Naira"))]
+    pub amount: Option<u64>,
+}
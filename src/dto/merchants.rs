@@ -0,0 +1,42 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct CreateMerchantBody {
+    #[validate(length(min = 2, max = 100, message = "Business name is required"))]
+    pub business_name: String,
+
+    // The default wallet is used when not set
+    pub settlement_wallet_id: Option<String>,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct UpdateMerchantBody {
+    #[validate(length(min = 2, max = 100, message = "Business name is required"))]
+    pub business_name: Option<String>,
+
+    pub settlement_wallet_id: Option<String>,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct CreatePaymentLinkBody {
+    #[validate(length(min = 2, max = 100, message = "Title is required"))]
+    pub title: String,
+
+    #[validate(length(max = 1000))]
+    pub description: Option<String>,
+
+    // Payers choose the amount when not set
+    #[validate(range(min = 100, message = "Minimum payment amount is 100 Naira"))]
+    pub amount: Option<u64>,
+
+    #[validate(range(min = 1, message = "A link must allow at least one payment"))]
+    pub max_uses: Option<i32>,
+
+    #[validate(range(
+        min = 1,
+        max = 8760,
+        message = "Link can only be valid for 1 to 8760 hours"
+    ))]
+    pub expires_in_hours: Option<i64>,
+}
@@ -0,0 +1,18 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Deserialize, Debug)]
+pub struct UpdateNotificationPreferenceBody {
+    pub event: String,
+    pub channel: String,
+    pub enabled: bool,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct RegisterDeviceBody {
+    #[validate(length(min = 1, max = 4096, message = "Device token is required"))]
+    pub token: String,
+
+    #[validate(length(min = 1, max = 20, message = "Platform is required"))]
+    pub platform: String,
+}
@@ -0,0 +1,22 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct WalletPaymentBody {
+    #[validate(length(min = 6, max = 6, message = "PIN must be Six(6) characters long"))]
+    pub pin: String,
+
+    // Only read for open amount links
+    #[validate(range(min = 100, message = "Minimum payment amount is 100 Naira"))]
+    pub amount: Option<u64>,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct CardPaymentBody {
+    #[validate(email(message = "Email must be a valid email type"))]
+    pub email: String,
+
+    // Only read for open amount links
+    #[validate(range(min = 100, message = "Minimum payment amount is 100 Naira"))]
+    pub amount: Option<u64>,
+}
@@ -0,0 +1,11 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct FundWithPaymentMethodBody {
+    #[validate(range(min = 100, message = "Minimum funding amount is 100 Naira"))]
+    pub amount: u64, // Amount in Naira
+
+    #[validate(length(min = 6, max = 6, message = "PIN must be Six(6) characters long"))]
+    pub pin: String,
+}
@@ -0,0 +1,27 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct CreatePaymentRequestBody {
+    #[validate(range(min = 100, message = "Minimum request amount is 100 Naira"))]
+    pub amount: u64,
+
+    #[validate(length(min = 4))]
+    pub payer_id: String,
+
+    #[validate(length(min = 4, max = 255))]
+    pub narration: Option<String>,
+
+    #[validate(range(
+        min = 1,
+        max = 720,
+        message = "Request can only be valid for 1 to 720 hours"
+    ))]
+    pub expires_in_hours: Option<i64>,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct AcceptPaymentRequestBody {
+    #[validate(length(min = 6, max = 6, message = "PIN must be Six(6) characters long"))]
+    pub pin: String,
+}
@@ -0,0 +1,56 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct StaticQrParams {
+    // Left out for codes where the payer enters the amount
+    #[validate(range(min = 100, message = "Minimum payment amount is 100 Naira"))]
+    pub amount: Option<u64>,
+
+    // png (default) or svg, only read for images
+    pub format: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct QrImageParams {
+    // png (default) or svg
+    pub format: Option<String>,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct CreateQrCodeBody {
+    #[validate(range(min = 100, message = "Minimum payment amount is 100 Naira"))]
+    pub amount: u64,
+
+    #[validate(length(min = 3, max = 255))]
+    pub description: Option<String>,
+
+    #[validate(range(
+        min = 1,
+        max = 1440,
+        message = "A QR code can be valid for between 1 minute and 24 hours"
+    ))]
+    pub expires_in_mins: Option<i64>,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct DecodeQrBody {
+    #[validate(length(min = 1, max = 1000, message = "QR payload is required"))]
+    pub payload: String,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct QrPaymentBody {
+    #[validate(length(min = 1, max = 1000, message = "QR payload is required"))]
+    pub payload: String,
+
+    #[validate(length(min = 6, max = 6, message = "PIN must be Six(6) characters long"))]
+    pub pin: String,
+
+    // Only read when the QR code has no amount
+    #[validate(range(min = 100, message = "Minimum payment amount is 100 Naira"))]
+    pub amount: Option<u64>,
+
+    #[validate(length(min = 4, max = 255))]
+    pub narration: Option<String>,
+}
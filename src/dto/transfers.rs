@@ -1,4 +1,5 @@
-use serde::Deserialize;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use validator::Validate;
 
 #[derive(Deserialize, Validate, Debug)]
@@ -24,3 +25,82 @@ pub struct P2PTransferBody {
     #[validate(length(min = 4, max = 255))]
     pub narration: Option<String>,
 }
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct ScheduledTransferBody {
+    #[validate(range(min = 100, message = "Minimum transfer amount is 100 Naira"))]
+    pub amount: u64,
+
+    #[validate(length(min = 6, max = 6, message = "PIN must be Six(6) characters long"))]
+    pub pin: String,
+
+    #[validate(length(min = 4))]
+    pub receiver_id: String,
+
+    #[validate(length(min = 4, max = 255))]
+    pub narration: Option<String>,
+
+    // One-off transfer at a future time
+    pub run_at: Option<DateTime<Utc>>,
+
+    // Recurring transfer e.g "0 9 1 * *" for 9am on the first day of every month
+    #[validate(length(min = 9, max = 100))]
+    pub cron_expression: Option<String>,
+
+    pub end_at: Option<DateTime<Utc>>,
+
+    #[validate(range(min = 0, max = 10, message = "Maximum of 10 retries allowed"))]
+    pub max_retries: Option<i32>,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct WithdrawalBody {
+    #[validate(range(min = 100, message = "Minimum withdrawal amount is 100 Naira"))]
+    pub amount: u64,
+
+    #[validate(length(min = 6, max = 6, message = "PIN must be Six(6) characters long"))]
+    pub pin: String,
+
+    #[validate(length(min = 10, max = 10, message = "Account number must be 10 digits"))]
+    pub account_number: String,
+
+    #[validate(length(min = 3, max = 10))]
+    pub bank_code: String,
+
+    #[validate(length(min = 4, max = 255))]
+    pub narration: Option<String>,
+}
+
+// A single batch row. Exactly one of user_id, email or account_number (with bank_code) identifies the receiver
+#[derive(Deserialize, Serialize, Validate, Debug)]
+pub struct BatchTransferItemBody {
+    #[validate(length(min = 4))]
+    pub user_id: Option<String>,
+
+    #[validate(email(message = "Email must be a valid email type"))]
+    pub email: Option<String>,
+
+    #[validate(length(min = 10, max = 10, message = "Account number must be 10 digits"))]
+    pub account_number: Option<String>,
+
+    #[validate(length(min = 3, max = 10))]
+    pub bank_code: Option<String>,
+
+    #[validate(range(min = 100, message = "Minimum transfer amount is 100 Naira"))]
+    pub amount: u64,
+
+    #[validate(length(min = 4, max = 255))]
+    pub narration: Option<String>,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct CreateTransferBatchBody {
+    #[validate(length(min = 6, max = 6, message = "PIN must be Six(6) characters long"))]
+    pub pin: String,
+
+    // Items are validated row by row so errors can point at the offending row
+    pub items: Option<Vec<BatchTransferItemBody>>,
+
+    // Alternative to items. Header row: user_id,email,account_number,bank_code,amount,narration
+    pub csv: Option<String>,
+}
@@ -14,6 +14,9 @@ pub struct SignupBody {
 
     #[validate(length(min = 3, message = "Password must be minimum of three(3) characters"))]
     pub password: String,
+
+    // Language for emails, defaults to English
+    pub locale: Option<String>,
 }
 
 #[derive(Deserialize, Validate, Debug)]
@@ -49,3 +52,28 @@ pub struct SetWithdrawalPinBody {
     #[validate(length(min = 3, message = "Password must be minimum of three(3) characters"))]
     pub password: String,
 }
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct ForgotPasswordBody {
+    #[validate(email(message = "Email must be a valid email type"))]
+    pub email: String,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct ResetPasswordBody {
+    pub token: String,
+
+    #[validate(length(min = 3, message = "Password must be minimum of three(3) characters"))]
+    pub password: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UpdateLocaleBody {
+    pub locale: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UpdatePhoneNumberBody {
+    // Null removes the number and with it SMS notifications
+    pub phone_number: Option<String>,
+}
@@ -0,0 +1,7 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+pub struct TransactionHistoryParams {
+    pub page: Option<u64>,
+    pub per_page: Option<u64>,
+}
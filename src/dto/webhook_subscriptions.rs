@@ -0,0 +1,11 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct CreateWebhookSubscriptionBody {
+    #[validate(url(message = "A valid URL is required"))]
+    pub url: String,
+
+    #[validate(length(min = 1, message = "At least one event type is required"))]
+    pub event_types: Vec<String>,
+}
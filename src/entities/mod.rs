@@ -2,7 +2,34 @@
 
 pub mod prelude;
 
+pub mod api_keys;
+pub mod bill_participants;
+pub mod bills;
+pub mod card_authorizations;
+pub mod device_tokens;
+pub mod disputes;
+pub mod escrows;
+pub mod invoice_items;
+pub mod invoice_payments;
+pub mod invoices;
+pub mod merchants;
+pub mod notification_preferences;
+pub mod outbox_messages;
+pub mod payment_link_payments;
+pub mod payment_links;
+pub mod payment_methods;
+pub mod payment_requests;
+pub mod qr_codes;
+pub mod scheduled_transfers;
 pub mod sea_orm_active_enums;
+pub mod transaction_reversals;
 pub mod transactions;
+pub mod transfer_batch_items;
+pub mod transfer_batches;
 pub mod users;
+pub mod virtual_accounts;
+pub mod virtual_cards;
+pub mod wallet_holds;
 pub mod wallets;
+pub mod webhook_deliveries;
+pub mod webhook_subscriptions;
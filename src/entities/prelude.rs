@@ -1,5 +1,32 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.3
 
+pub use super::api_keys::Entity as ApiKeys;
+pub use super::bill_participants::Entity as BillParticipants;
+pub use super::bills::Entity as Bills;
+pub use super::card_authorizations::Entity as CardAuthorizations;
+pub use super::device_tokens::Entity as DeviceTokens;
+pub use super::disputes::Entity as Disputes;
+pub use super::escrows::Entity as Escrows;
+pub use super::invoice_items::Entity as InvoiceItems;
+pub use super::invoice_payments::Entity as InvoicePayments;
+pub use super::invoices::Entity as Invoices;
+pub use super::merchants::Entity as Merchants;
+pub use super::notification_preferences::Entity as NotificationPreferences;
+pub use super::outbox_messages::Entity as OutboxMessages;
+pub use super::payment_link_payments::Entity as PaymentLinkPayments;
+pub use super::payment_links::Entity as PaymentLinks;
+pub use super::payment_methods::Entity as PaymentMethods;
+pub use super::payment_requests::Entity as PaymentRequests;
+pub use super::qr_codes::Entity as QrCodes;
+pub use super::scheduled_transfers::Entity as ScheduledTransfers;
+pub use super::transaction_reversals::Entity as TransactionReversals;
 pub use super::transactions::Entity as Transactions;
+pub use super::transfer_batch_items::Entity as TransferBatchItems;
+pub use super::transfer_batches::Entity as TransferBatches;
 pub use super::users::Entity as Users;
+pub use super::virtual_accounts::Entity as VirtualAccounts;
+pub use super::virtual_cards::Entity as VirtualCards;
+pub use super::wallet_holds::Entity as WalletHolds;
 pub use super::wallets::Entity as Wallets;
+pub use super::webhook_deliveries::Entity as WebhookDeliveries;
+pub use super::webhook_subscriptions::Entity as WebhookSubscriptions;
@@ -1,8 +1,10 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.3
 
 use sea_orm::entity::prelude::*;
+use serde::Serialize;
 
-#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "status")]
 pub enum Status {
     #[sea_orm(string_value = "successful")]
@@ -11,8 +13,14 @@ pub enum Status {
     Pending,
     #[sea_orm(string_value = "failed")]
     Failed,
+    #[sea_orm(string_value = "reversed")]
+    Reversed,
+    #[sea_orm(string_value = "partially_reversed")]
+    #[serde(rename = "partially_reversed")]
+    PartiallyReversed,
 }
-#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "trx_type")]
 pub enum TrxType {
     #[sea_orm(string_value = "credit")]
@@ -3,8 +3,9 @@
 use super::sea_orm_active_enums::Status;
 use super::sea_orm_active_enums::TrxType;
 use sea_orm::entity::prelude::*;
+use serde::Serialize;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize)]
 #[sea_orm(table_name = "transactions")]
 pub struct Model {
     #[sea_orm(unique)]
@@ -2,6 +2,9 @@
 
 use sea_orm::entity::prelude::*;
 use serde::Serialize;
+use std::fmt;
+
+use crate::utils::helpers::serialize_flag;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize)]
 #[sea_orm(table_name = "users")]
@@ -16,7 +19,11 @@ pub struct Model {
     pub email: String,
     pub password: String,
     pub withdrawal_pin: Option<String>,
-    pub is_verified: i8,
+    #[serde(serialize_with = "serialize_flag")]
+    pub is_verified: bool,
+    pub role: String,
+    pub locale: String,
+    pub phone_number: Option<String>,
     pub created_at: DateTimeUtc,
     pub updated_at: DateTimeUtc,
     pub deleted_at: Option<DateTimeUtc>,
@@ -42,21 +49,54 @@ pub struct UserResponse {
     pub first_name: String,
     pub last_name: String,
     pub email: String,
-    pub is_verified: i8,
+    #[serde(serialize_with = "serialize_flag")]
+    pub is_verified: bool,
+    pub role: String,
+    pub locale: String,
+    pub phone_number: Option<String>,
     pub created_at: DateTimeUtc,
     pub updated_at: DateTimeUtc,
     pub deleted_at: Option<DateTimeUtc>,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum UserRole {
+    User,
+    Support,
+    Admin,
+    // Internal accounts such as the escrow account, never signed in to
+    System,
+}
+
+impl fmt::Display for UserRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let role = match self {
+            UserRole::User => "user",
+            UserRole::Support => "support",
+            UserRole::Admin => "admin",
+            UserRole::System => "system",
+        };
+
+        write!(f, "{}", role)
+    }
+}
+
 impl Model {
+    pub fn has_role(&self, role: UserRole) -> bool {
+        self.role == role.to_string()
+    }
+
     pub fn filter_response(&self) -> UserResponse {
         UserResponse {
             id: self.id,
-            uuid: format!("{}", self.uuid),
-            first_name: format!("{}", self.first_name),
-            last_name: format!("{}", self.last_name),
-            email: format!("{}", self.email),
+            uuid: self.uuid.to_string(),
+            first_name: self.first_name.to_string(),
+            last_name: self.last_name.to_string(),
+            email: self.email.to_string(),
             is_verified: self.is_verified,
+            role: self.role.to_string(),
+            locale: self.locale.to_string(),
+            phone_number: self.phone_number.clone(),
             created_at: self.created_at,
             updated_at: self.updated_at,
             deleted_at: self.deleted_at,
@@ -3,6 +3,8 @@
 use sea_orm::entity::prelude::*;
 use serde::Serialize;
 
+use crate::utils::helpers::serialize_flag;
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize)]
 #[sea_orm(table_name = "wallets")]
 pub struct Model {
@@ -10,7 +12,8 @@ pub struct Model {
     pub id: i32,
     #[sea_orm(primary_key, auto_increment = false, unique)]
     pub uuid: String,
-    pub default: i8,
+    #[serde(serialize_with = "serialize_flag")]
+    pub default: bool,
     #[sea_orm(column_type = "Decimal(Some((18, 2)))")]
     pub current_balance: Decimal,
     #[sea_orm(column_type = "Decimal(Some((18, 2)))")]
@@ -19,6 +22,8 @@ pub struct Model {
     pub created_at: DateTimeUtc,
     pub updated_at: DateTimeUtc,
     pub deleted_at: Option<DateTimeUtc>,
+    pub frozen_at: Option<DateTimeUtc>,
+    pub frozen_reason: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -40,3 +45,10 @@ impl Related<super::users::Entity> for Entity {
 }
 
 impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    // Frozen wallets can still receive funds but nothing can be debited from them
+    pub fn is_frozen(&self) -> bool {
+        self.frozen_at.is_some()
+    }
+}
@@ -0,0 +1,283 @@
+use actix_web::{web, HttpResponse, Responder};
+use sea_orm::*;
+use serde_json::json;
+use tracing::{error, instrument};
+use validator::Validate;
+
+use crate::dto::admin::{ReconcileSettlementsBody, ReverseTransactionBody};
+use crate::entities::{
+    prelude::{TransactionReversals, Wallets},
+    transaction_reversals, users, wallets,
+};
+use crate::service::outbox::{self, OutboxError};
+use crate::service::reversal::{self, ReversalError, ReversalRequest};
+use crate::service::settlement_reconciliation::{
+    reconcile_paystack_settlements, SettlementError, SettlementReconciliation,
+};
+use crate::service::wallet_reconciliation;
+use crate::AppState;
+
+#[instrument(skip(body, req_user, app_state), fields(admin_id = %req_user.uuid))]
+pub async fn reverse_transaction(
+    path: web::Path<String>,
+    body: web::Json<ReverseTransactionBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    let reversal_request = ReversalRequest {
+        transaction_id: path.into_inner(),
+        reason: request_payload.reason,
+        allow_negative_balance: request_payload.allow_negative_balance.unwrap_or(false),
+    };
+
+    let txn = app_state
+        .db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadWrite),
+        )
+        .await
+        .expect("Failed to start a DB transaction");
+
+    match reversal::reverse_transaction(&txn, &app_state.env, &req_user, reversal_request).await {
+        Ok(reversal) => {
+            if let Err(err) = txn.commit().await {
+                error!(
+                    "DB error committing reversal {} ===> {}",
+                    reversal.uuid, err
+                );
+                return reversal_error_response(ReversalError::DatabaseError(err));
+            }
+
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Transaction reversed",
+                "data": { "reversal": reversal }
+            }))
+        }
+        Err(err) => {
+            let _ = txn.rollback().await;
+            reversal_error_response(err)
+        }
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(admin_id = %req_user.uuid))]
+pub async fn list_reversals(
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let reversals = TransactionReversals::find()
+        .filter(transaction_reversals::Column::DeletedAt.is_null())
+        .order_by_desc(transaction_reversals::Column::CreatedAt)
+        .all(&app_state.db)
+        .await;
+
+    match reversals {
+        Ok(reversals) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched reversals",
+            "data": { "reversals": reversals }
+        })),
+        Err(err) => {
+            error!("Error retrieving reversals: {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "Failed to fetch reversals" }))
+        }
+    }
+}
+
+// Checks a single wallet against its transactions without freezing it
+#[instrument(skip(req_user, app_state), fields(admin_id = %req_user.uuid))]
+pub async fn reconcile_wallet(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let wallet_id = path.into_inner();
+    let wallet = match find_wallet(&wallet_id, &app_state).await {
+        Ok(wallet) => wallet,
+        Err(response) => return response,
+    };
+
+    match wallet_reconciliation::reconcile_wallet(&app_state.db, &wallet.uuid).await {
+        Ok(mismatches) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Wallet reconciled",
+            "data": {
+                "wallet_id": wallet.uuid,
+                "balanced": mismatches.is_empty(),
+                "frozen_at": wallet.frozen_at,
+                "frozen_reason": wallet.frozen_reason,
+                "mismatches": mismatches
+            }
+        })),
+        Err(err) => {
+            error!("DB error reconciling wallet {} ===> {}", wallet_id, err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(admin_id = %req_user.uuid))]
+pub async fn unfreeze_wallet(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let wallet_id = path.into_inner();
+    let wallet = match find_wallet(&wallet_id, &app_state).await {
+        Ok(wallet) => wallet,
+        Err(response) => return response,
+    };
+
+    if !wallet.is_frozen() {
+        return HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": "Wallet is not frozen" }));
+    }
+
+    match wallet_reconciliation::unfreeze_wallet(&app_state.db, &wallet.uuid).await {
+        Ok(_) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Wallet unfrozen",
+            "data": { "wallet_id": wallet.uuid }
+        })),
+        Err(err) => {
+            error!("DB error unfreezing wallet {} ===> {}", wallet_id, err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
+
+// Matches our Paystack charges and transfers against Paystack's own records for the period
+#[instrument(skip(body, req_user, app_state), fields(admin_id = %req_user.uuid))]
+pub async fn reconcile_settlements(
+    body: web::Json<ReconcileSettlementsBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let body = body.into_inner();
+    let request = SettlementReconciliation {
+        from: body.from,
+        to: body.to,
+        auto_credit: body.auto_credit.unwrap_or(false),
+    };
+
+    match reconcile_paystack_settlements(&app_state, request).await {
+        Ok(report) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Settlements reconciled",
+            "data": { "report": report }
+        })),
+        Err(err) if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        Err(SettlementError::ProviderError(err)) => {
+            error!("Paystack error reconciling settlements ===> {}", err);
+            HttpResponse::BadGateway().json(json!({
+                "status": "error",
+                "message": "Could not fetch records from Paystack, please try again"
+            }))
+        }
+        Err(err) => {
+            error!("Error reconciling settlements ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
+
+// Messages the outbox worker gave up on
+#[instrument(skip(req_user, app_state), fields(admin_id = %req_user.uuid))]
+pub async fn dead_outbox_messages(
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match outbox::dead_messages(&app_state.db).await {
+        Ok(messages) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched dead outbox messages",
+            "data": { "messages": messages }
+        })),
+        Err(err) => {
+            error!("Error retrieving dead outbox messages: {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "Failed to fetch outbox messages" }))
+        }
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(admin_id = %req_user.uuid))]
+pub async fn retry_outbox_message(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let message_id = path.into_inner();
+
+    match outbox::retry_dead_message(&app_state.db, &message_id).await {
+        Ok(message) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Outbox message queued for delivery",
+            "data": { "message": message }
+        })),
+        Err(OutboxError::NotFound) => HttpResponse::NotFound()
+            .json(json!({ "status": "error", "message": "Outbox message not found" })),
+        Err(err) if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        Err(err) => {
+            error!(
+                "DB error retrying outbox message {} ===> {}",
+                message_id, err
+            );
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
+
+async fn find_wallet(
+    wallet_id: &String,
+    app_state: &web::Data<AppState>,
+) -> Result<wallets::Model, HttpResponse> {
+    let wallet = Wallets::find()
+        .filter(wallets::Column::Uuid.eq(wallet_id))
+        .filter(wallets::Column::DeletedAt.is_null())
+        .one(&app_state.db)
+        .await;
+
+    match wallet {
+        Ok(Some(wallet)) => Ok(wallet),
+        Ok(None) => Err(HttpResponse::NotFound()
+            .json(json!({ "status": "error", "message": "Wallet not found" }))),
+        Err(err) => {
+            error!("DB error fetching wallet {} ===> {}", wallet_id, err);
+            Err(HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" })))
+        }
+    }
+}
+
+fn reversal_error_response(err: ReversalError) -> HttpResponse {
+    match err {
+        ReversalError::NotFound => {
+            HttpResponse::NotFound().json(json!({ "status": "error", "message": err.to_string() }))
+        }
+        err if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        err => {
+            error!("Reversal error ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
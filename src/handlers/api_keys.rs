@@ -0,0 +1,139 @@
+use actix_web::{web, HttpResponse, Responder};
+use serde_json::json;
+use tracing::{error, instrument};
+use validator::Validate;
+
+use crate::dto::api_keys::{CreateApiKeyBody, RotateApiKeyBody};
+use crate::entities::users;
+use crate::service::api_key::{
+    create_api_key, revoke_api_key, rotate_api_key, user_api_keys, ApiKeyError,
+};
+use crate::utils::helpers::validate_password;
+use crate::AppState;
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid, name = %body.name))]
+pub async fn add_api_key(
+    body: web::Json<CreateApiKeyBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    if !validate_password(
+        &req_user.password,
+        &request_payload.password,
+        &app_state.env.hash_key,
+    ) {
+        return HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": "Wrong password provided" }));
+    }
+
+    let credentials = create_api_key(
+        &app_state.db,
+        &req_user.uuid,
+        &request_payload.name,
+        &request_payload.scopes,
+        &request_payload.allowed_ips,
+        request_payload.require_signature,
+    )
+    .await;
+
+    match credentials {
+        Ok(credentials) => HttpResponse::Created().json(json!({
+            "status": "success",
+            "message": "API key created successfully. Store the secret key and signing secret, they won't be shown again",
+            "data": credentials
+        })),
+        Err(err) => api_key_error_response(err),
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_api_keys(
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match user_api_keys(&app_state.db, &req_user.uuid).await {
+        Ok(api_keys) => {
+            let api_keys: Vec<_> = api_keys
+                .into_iter()
+                .map(|api_key| api_key.filter_response())
+                .collect();
+
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Fetched API keys",
+                "data": { "api_keys": api_keys }
+            }))
+        }
+        Err(err) => api_key_error_response(err.into()),
+    }
+}
+
+#[instrument(skip(path, body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn rotate_my_api_key(
+    path: web::Path<String>,
+    body: web::Json<RotateApiKeyBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    if !validate_password(
+        &req_user.password,
+        &request_payload.password,
+        &app_state.env.hash_key,
+    ) {
+        return HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": "Wrong password provided" }));
+    }
+
+    match rotate_api_key(&app_state.db, &req_user.uuid, &path.into_inner()).await {
+        Ok(credentials) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "API key rotated successfully. The previous secret key no longer works",
+            "data": credentials
+        })),
+        Err(err) => api_key_error_response(err),
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn revoke_my_api_key(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match revoke_api_key(&app_state.db, &req_user.uuid, &path.into_inner()).await {
+        Ok(_) => HttpResponse::Ok()
+            .json(json!({ "status": "success", "message": "API key revoked successfully" })),
+        Err(err) => api_key_error_response(err),
+    }
+}
+
+fn api_key_error_response(err: ApiKeyError) -> HttpResponse {
+    match err {
+        ApiKeyError::KeyNotFound => {
+            HttpResponse::NotFound().json(json!({ "status": "error", "message": err.to_string() }))
+        }
+        err if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        err => {
+            error!("Error managing API keys ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use actix_web::{web, HttpResponse, Responder};
+use sea_orm::*;
+use serde_json::json;
+use tracing::{error, instrument};
+use validator::Validate;
+
+use crate::dto::bills::CreateBillBody;
+use crate::entities::{
+    bill_participants, bills, payment_requests,
+    prelude::{BillParticipants, Bills, PaymentRequests, Users},
+    users,
+};
+use crate::service::bill::{self, BillError, BillParticipantInput, NewBill, SplitType};
+use crate::AppState;
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid, total_amount = %body.total_amount))]
+pub async fn create_bill(
+    body: web::Json<CreateBillBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    if !req_user.is_verified {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "Please verify your account before taking this action"
+        }));
+    }
+
+    let split_type = match request_payload.split_type.as_str() {
+        "equal" => SplitType::Equal,
+        "custom" => SplitType::Custom,
+        _ => {
+            return HttpResponse::BadRequest().json(
+                json!({ "status": "error", "message": "split_type must be either equal or custom" }),
+            );
+        }
+    };
+
+    let new_bill = NewBill {
+        title: request_payload.title,
+        total_amount: request_payload.total_amount.into(),
+        split_type,
+        participants: request_payload
+            .participants
+            .into_iter()
+            .map(|participant| BillParticipantInput {
+                user_id: participant.user_id,
+                email: participant.email,
+                amount: participant.amount.map(|amount| amount.into()),
+            })
+            .collect(),
+    };
+
+    let txn = app_state
+        .db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadWrite),
+        )
+        .await
+        .expect("Failed to start a DB transaction");
+
+    match bill::create_bill(&txn, &req_user, new_bill).await {
+        Ok((bill, participants)) => {
+            let _ = txn.commit().await;
+            HttpResponse::Created().json(json!({
+                "status": "success",
+                "message": "Bill created successfully",
+                "data": { "bill": bill, "participants": participants }
+            }))
+        }
+        Err(err) => {
+            let _ = txn.rollback().await;
+            bill_error_response(err)
+        }
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_bills(
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let bills = Bills::find()
+        .filter(bills::Column::CreatorId.eq(&req_user.uuid))
+        .filter(bills::Column::DeletedAt.is_null())
+        .order_by_desc(bills::Column::CreatedAt)
+        .all(&app_state.db)
+        .await;
+
+    match bills {
+        Ok(bills) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched bills",
+            "data": { "bills": bills }
+        })),
+        Err(err) => {
+            error!("Error retrieving bills: {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "Failed to fetch bills" }))
+        }
+    }
+}
+
+// Visible to the creator and to every participant of the bill
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn get_bill(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let bill_id = path.into_inner();
+    let bill = Bills::find()
+        .filter(bills::Column::Uuid.eq(&bill_id))
+        .filter(bills::Column::DeletedAt.is_null())
+        .one(&app_state.db)
+        .await;
+
+    let bill = match bill {
+        Ok(Some(bill)) => bill,
+        Ok(None) => return bill_error_response(BillError::NotFound),
+        Err(err) => return bill_error_response(BillError::DatabaseError(err)),
+    };
+
+    let participants = BillParticipants::find()
+        .filter(bill_participants::Column::BillId.eq(&bill.uuid))
+        .find_also_related(Users)
+        .all(&app_state.db)
+        .await;
+
+    let participants = match participants {
+        Ok(participants) => participants,
+        Err(err) => return bill_error_response(BillError::DatabaseError(err)),
+    };
+
+    let is_participant = participants
+        .iter()
+        .any(|(participant, _)| participant.user_id == req_user.uuid);
+    if bill.creator_id != req_user.uuid && !is_participant {
+        return bill_error_response(BillError::NotFound);
+    }
+
+    let request_ids: Vec<String> = participants
+        .iter()
+        .map(|(participant, _)| participant.payment_request_id.to_string())
+        .collect();
+    let requests = PaymentRequests::find()
+        .filter(payment_requests::Column::Uuid.is_in(request_ids))
+        .all(&app_state.db)
+        .await;
+
+    let requests = match requests {
+        Ok(requests) => requests,
+        Err(err) => return bill_error_response(BillError::DatabaseError(err)),
+    };
+
+    let participants: Vec<_> = participants
+        .into_iter()
+        .map(|(participant, user)| {
+            let request_status = requests
+                .iter()
+                .find(|request| request.uuid == participant.payment_request_id)
+                .map(|request| request.status.to_string());
+
+            json!({
+                "participant": participant,
+                "user": user.map(|user| json!({
+                    "uuid": user.uuid,
+                    "first_name": user.first_name,
+                    "last_name": user.last_name,
+                })),
+                "payment_request_status": request_status,
+            })
+        })
+        .collect();
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "message": "Fetched bill",
+        "data": { "bill": bill, "participants": participants }
+    }))
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn cancel_bill(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let txn = app_state
+        .db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadWrite),
+        )
+        .await
+        .expect("Failed to start a DB transaction");
+
+    match bill::cancel_bill(&txn, &req_user, &path.into_inner()).await {
+        Ok(bill) => {
+            let _ = txn.commit().await;
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Bill cancelled",
+                "data": { "bill": bill }
+            }))
+        }
+        Err(err) => {
+            let _ = txn.rollback().await;
+            bill_error_response(err)
+        }
+    }
+}
+
+fn bill_error_response(err: BillError) -> HttpResponse {
+    match err {
+        BillError::NotFound => {
+            HttpResponse::NotFound().json(json!({ "status": "error", "message": err.to_string() }))
+        }
+        err if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        err => {
+            error!("Bill error ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
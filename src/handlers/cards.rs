@@ -0,0 +1,301 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use sea_orm::*;
+use serde_json::json;
+use tracing::{error, instrument};
+use validator::Validate;
+
+use crate::dto::cards::{CreateCardBody, SpendLimitBody};
+use crate::entities::users;
+use crate::service::virtual_card::{
+    card_authorizations, create_card, find_user_card, freeze_card, handle_card_event,
+    set_spend_limit, terminate_card, unfreeze_card, user_cards, NewCard, SpendLimitInterval,
+    VirtualCardError,
+};
+use crate::utils::card_issuer::{card_issuer, card_issuer_by_name, CardIssuer, IssuerError};
+use crate::AppState;
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn add_card(
+    body: web::Json<CreateCardBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    let spend_limit_interval = match parse_interval(request_payload.spend_limit_interval) {
+        Ok(interval) => interval,
+        Err(err) => return card_error_response(err),
+    };
+
+    let issuer = match configured_issuer(&app_state) {
+        Ok(issuer) => issuer,
+        Err(err) => return card_error_response(err),
+    };
+
+    let new_card = NewCard {
+        wallet_id: request_payload.wallet_id,
+        label: request_payload.label,
+        spend_limit: request_payload.spend_limit.map(|limit| limit.into()),
+        spend_limit_interval,
+    };
+
+    match create_card(&app_state.db, issuer.as_ref(), &req_user, new_card).await {
+        Ok(card) => HttpResponse::Created().json(json!({
+            "status": "success",
+            "message": "Card created successfully",
+            "data": { "card": card }
+        })),
+        Err(err) => card_error_response(err),
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_cards(
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match user_cards(&app_state.db, &req_user).await {
+        Ok(cards) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched cards",
+            "data": { "cards": cards }
+        })),
+        Err(err) => card_error_response(err.into()),
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_card(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match find_user_card(&app_state.db, &req_user, &path.into_inner()).await {
+        Ok(card) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched card",
+            "data": { "card": card }
+        })),
+        Err(err) => card_error_response(err),
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_card_authorizations(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match card_authorizations(&app_state.db, &req_user, &path.into_inner()).await {
+        Ok(authorizations) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched card authorizations",
+            "data": { "authorizations": authorizations }
+        })),
+        Err(err) => card_error_response(err),
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn freeze_my_card(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let issuer = match configured_issuer(&app_state) {
+        Ok(issuer) => issuer,
+        Err(err) => return card_error_response(err),
+    };
+
+    let card_id = path.into_inner();
+    match freeze_card(&app_state.db, issuer.as_ref(), &req_user, &card_id).await {
+        Ok(card) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Card frozen",
+            "data": { "card": card }
+        })),
+        Err(err) => card_error_response(err),
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn unfreeze_my_card(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let issuer = match configured_issuer(&app_state) {
+        Ok(issuer) => issuer,
+        Err(err) => return card_error_response(err),
+    };
+
+    let card_id = path.into_inner();
+    match unfreeze_card(&app_state.db, issuer.as_ref(), &req_user, &card_id).await {
+        Ok(card) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Card unfrozen",
+            "data": { "card": card }
+        })),
+        Err(err) => card_error_response(err),
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn terminate_my_card(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let issuer = match configured_issuer(&app_state) {
+        Ok(issuer) => issuer,
+        Err(err) => return card_error_response(err),
+    };
+
+    let card_id = path.into_inner();
+    match terminate_card(&app_state.db, issuer.as_ref(), &req_user, &card_id).await {
+        Ok(card) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Card terminated",
+            "data": { "card": card }
+        })),
+        Err(err) => card_error_response(err),
+    }
+}
+
+#[instrument(skip(path, body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn update_card_spend_limit(
+    path: web::Path<String>,
+    body: web::Json<SpendLimitBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    let interval = match parse_interval(request_payload.spend_limit_interval) {
+        Ok(interval) => interval,
+        Err(err) => return card_error_response(err),
+    };
+
+    let spend_limit = request_payload.spend_limit.map(|limit| limit.into());
+    let card_id = path.into_inner();
+    match set_spend_limit(&app_state.db, &req_user, &card_id, spend_limit, interval).await {
+        Ok(card) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Spend limit updated",
+            "data": { "card": card }
+        })),
+        Err(err) => card_error_response(err),
+    }
+}
+
+// Authorization requests are answered in the response, anything other than an approval is
+// taken by the issuer as a decline
+#[instrument(skip(req, body, app_state))]
+pub async fn card_issuer_webhook(
+    path: web::Path<String>,
+    req: HttpRequest,
+    body: web::Bytes,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let issuer_name = path.into_inner();
+    let issuer = match card_issuer_by_name(&issuer_name, &app_state.env) {
+        Some(issuer) => issuer,
+        None => {
+            return HttpResponse::NotFound()
+                .json(json!({ "status": "error", "message": "Unknown card issuer" }));
+        }
+    };
+
+    let signature = req
+        .headers()
+        .get(issuer.signature_header())
+        .and_then(|signature| signature.to_str().ok())
+        .unwrap_or_default();
+
+    let event = match issuer.parse_webhook(signature, &body) {
+        Ok(event) => event,
+        Err(IssuerError::InvalidSignature) => {
+            return HttpResponse::Unauthorized()
+                .json(json!({ "status": "error", "message": "Invalid signature" }));
+        }
+        Err(err) => {
+            error!("Error parsing {} card webhook: {}", issuer_name, err);
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Invalid webhook payload" }));
+        }
+    };
+
+    let txn = app_state
+        .db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadWrite),
+        )
+        .await
+        .expect("Failed to start a DB transaction");
+
+    let handled = match handle_card_event(&txn, issuer.as_ref(), event).await {
+        Ok(decision) => txn.commit().await.map(|_| decision).map_err(Into::into),
+        Err(err) => {
+            let _ = txn.rollback().await;
+            Err(err)
+        }
+    };
+
+    match handled {
+        Ok(decision) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Card issuer webhook successful",
+            "data": decision
+        })),
+        Err(err) => {
+            error!(
+                "Error occured handling {} card webhook: {}",
+                issuer_name, err
+            );
+            card_error_response(err)
+        }
+    }
+}
+
+fn configured_issuer(app_state: &AppState) -> Result<Box<dyn CardIssuer>, VirtualCardError> {
+    card_issuer(&app_state.env).ok_or(VirtualCardError::IssuerNotConfigured)
+}
+
+fn parse_interval(
+    interval: Option<String>,
+) -> Result<Option<SpendLimitInterval>, VirtualCardError> {
+    match interval {
+        Some(interval) => SpendLimitInterval::parse(&interval)
+            .map(Some)
+            .ok_or(VirtualCardError::InvalidSpendLimitInterval),
+        None => Ok(None),
+    }
+}
+
+fn card_error_response(err: VirtualCardError) -> HttpResponse {
+    match err {
+        err if err.is_not_found() => {
+            HttpResponse::NotFound().json(json!({ "status": "error", "message": err.to_string() }))
+        }
+        err if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        err => {
+            error!("Error handling card request ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use actix_web::{web, HttpResponse, Responder};
+use chrono::Utc;
+use sea_orm::*;
+use serde_json::json;
+use tracing::{error, instrument};
+use validator::Validate;
+
+use crate::dto::disputes::{
+    DisputeListParams, OpenDisputeBody, RejectDisputeBody, ResolveDisputeBody,
+};
+use crate::entities::{disputes, prelude::Disputes, users};
+use crate::service::dispute::{
+    self, notify_dispute_parties, DisputeError, DisputeParty, DisputeStatus, NewDispute,
+};
+use crate::AppState;
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid, transaction_id = %body.transaction_id))]
+pub async fn open_dispute(
+    body: web::Json<OpenDisputeBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    let new_dispute = NewDispute {
+        transaction_id: request_payload.transaction_id,
+        reason: request_payload.reason,
+        attachment_reference: request_payload.attachment_reference,
+    };
+
+    match dispute::open_dispute(&app_state.db, &req_user, new_dispute).await {
+        Ok(dispute) => {
+            let message = String::from("A dispute has been opened on a transaction. Our support team will review it shortly.");
+            notify_dispute_parties(&app_state, &dispute, &message).await;
+
+            HttpResponse::Created().json(json!({
+                "status": "success",
+                "message": "Dispute opened successfully",
+                "data": { "dispute": dispute }
+            }))
+        }
+        Err(err) => dispute_error_response(err),
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_disputes(
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let disputes = Disputes::find()
+        .filter(disputes::Column::UserId.eq(&req_user.uuid))
+        .filter(disputes::Column::DeletedAt.is_null())
+        .order_by_desc(disputes::Column::CreatedAt)
+        .all(&app_state.db)
+        .await;
+
+    match disputes {
+        Ok(disputes) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched disputes",
+            "data": { "disputes": disputes }
+        })),
+        Err(err) => {
+            error!("Error retrieving disputes: {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "Failed to fetch disputes" }))
+        }
+    }
+}
+
+// Visible to the customer who opened the dispute and to the counterparty
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn get_dispute(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let dispute = Disputes::find()
+        .filter(disputes::Column::Uuid.eq(path.into_inner()))
+        .filter(
+            Condition::any()
+                .add(disputes::Column::UserId.eq(&req_user.uuid))
+                .add(disputes::Column::CounterpartyId.eq(&req_user.uuid)),
+        )
+        .filter(disputes::Column::DeletedAt.is_null())
+        .one(&app_state.db)
+        .await;
+
+    match dispute {
+        Ok(Some(dispute)) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched dispute",
+            "data": { "dispute": dispute }
+        })),
+        Ok(None) => dispute_error_response(DisputeError::NotFound),
+        Err(err) => dispute_error_response(DisputeError::DatabaseError(err)),
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(support_id = %req_user.uuid))]
+pub async fn support_disputes(
+    params: web::Query<DisputeListParams>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let mut query = Disputes::find().filter(disputes::Column::DeletedAt.is_null());
+
+    if let Some(status) = &params.status {
+        query = query.filter(disputes::Column::Status.eq(status));
+    }
+
+    if params.overdue.unwrap_or(false) {
+        query = query
+            .filter(disputes::Column::Status.is_in([
+                DisputeStatus::Open.to_string(),
+                DisputeStatus::Investigating.to_string(),
+            ]))
+            .filter(disputes::Column::ResolutionDueAt.lt(Utc::now()));
+    }
+
+    let disputes = query
+        .order_by_asc(disputes::Column::ResolutionDueAt)
+        .all(&app_state.db)
+        .await;
+
+    match disputes {
+        Ok(disputes) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched disputes",
+            "data": { "disputes": disputes }
+        })),
+        Err(err) => {
+            error!("Error retrieving disputes for support: {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "Failed to fetch disputes" }))
+        }
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(support_id = %req_user.uuid))]
+pub async fn investigate_dispute(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match dispute::start_investigation(&app_state.db, &req_user, &path.into_inner()).await {
+        Ok(dispute) => {
+            let message = String::from("Our support team has started investigating this dispute.");
+            notify_dispute_parties(&app_state, &dispute, &message).await;
+
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Dispute is now under investigation",
+                "data": { "dispute": dispute }
+            }))
+        }
+        Err(err) => dispute_error_response(err),
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(support_id = %req_user.uuid))]
+pub async fn hold_dispute_funds(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match dispute::hold_counterparty_funds(&app_state.db, &req_user, &path.into_inner()).await {
+        Ok(dispute) => {
+            let message = String::from(
+                "The disputed amount has been placed on hold until this dispute is resolved.",
+            );
+            notify_dispute_parties(&app_state, &dispute, &message).await;
+
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Counterparty funds placed on hold",
+                "data": { "dispute": dispute }
+            }))
+        }
+        Err(err) => dispute_error_response(err),
+    }
+}
+
+#[instrument(skip(body, req_user, app_state), fields(support_id = %req_user.uuid))]
+pub async fn resolve_dispute(
+    path: web::Path<String>,
+    body: web::Json<ResolveDisputeBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    let in_favour_of = match request_payload.in_favour_of.as_str() {
+        "customer" => DisputeParty::Customer,
+        "counterparty" => DisputeParty::Counterparty,
+        _ => {
+            return HttpResponse::BadRequest().json(json!({
+                "status": "error",
+                "message": "in_favour_of must be either customer or counterparty"
+            }));
+        }
+    };
+
+    let txn = app_state
+        .db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadWrite),
+        )
+        .await
+        .expect("Failed to start a DB transaction");
+
+    let resolved = dispute::resolve_dispute(
+        &txn,
+        &app_state.env,
+        &req_user,
+        &path.into_inner(),
+        in_favour_of,
+        request_payload.note,
+    )
+    .await;
+
+    match resolved {
+        Ok(dispute) => {
+            if let Err(err) = txn.commit().await {
+                return dispute_error_response(DisputeError::DatabaseError(err));
+            }
+
+            let message = format!(
+                "This dispute has been resolved in favour of the {}.",
+                dispute.resolved_in_favour_of.clone().unwrap_or_default()
+            );
+            notify_dispute_parties(&app_state, &dispute, &message).await;
+
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Dispute resolved",
+                "data": { "dispute": dispute }
+            }))
+        }
+        Err(err) => {
+            let _ = txn.rollback().await;
+            dispute_error_response(err)
+        }
+    }
+}
+
+#[instrument(skip(body, req_user, app_state), fields(support_id = %req_user.uuid))]
+pub async fn reject_dispute(
+    path: web::Path<String>,
+    body: web::Json<RejectDisputeBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    let rejected = dispute::reject_dispute(
+        &app_state.db,
+        &req_user,
+        &path.into_inner(),
+        request_payload.note,
+    )
+    .await;
+
+    match rejected {
+        Ok(dispute) => {
+            let message = String::from("This dispute has been reviewed and rejected.");
+            notify_dispute_parties(&app_state, &dispute, &message).await;
+
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Dispute rejected",
+                "data": { "dispute": dispute }
+            }))
+        }
+        Err(err) => dispute_error_response(err),
+    }
+}
+
+fn dispute_error_response(err: DisputeError) -> HttpResponse {
+    match err {
+        DisputeError::NotFound | DisputeError::TransactionNotFound => {
+            HttpResponse::NotFound().json(json!({ "status": "error", "message": err.to_string() }))
+        }
+        err if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        err => {
+            error!("Dispute error ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
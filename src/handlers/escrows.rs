@@ -0,0 +1,269 @@
+use actix_web::{web, HttpResponse, Responder};
+use sea_orm::*;
+use serde_json::json;
+use tracing::{error, instrument};
+use validator::Validate;
+
+use crate::dto::escrows::{
+    CreateEscrowBody, DisputeEscrowBody, ReleaseEscrowBody, ResolveEscrowBody,
+};
+use crate::entities::{escrows, users};
+use crate::service::escrow::{
+    create_escrow, dispute_escrow, find_user_escrow, mark_delivered, refund_escrow, release_escrow,
+    resolve_escrow_dispute, user_escrows, EscrowError, EscrowParty, NewEscrow,
+};
+use crate::utils::helpers::validate_user_pin;
+use crate::AppState;
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn add_escrow(
+    body: web::Json<CreateEscrowBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    if let Err(msg) = validate_user_pin(&req_user, &request_payload.pin, &app_state.env.hash_key) {
+        return HttpResponse::BadRequest().json(json!({ "status": "error",  "message": msg }));
+    }
+
+    let txn = begin_txn(&app_state).await;
+    let new_escrow = NewEscrow {
+        seller_id: request_payload.seller_id,
+        amount: request_payload.amount.into(),
+        description: request_payload.description,
+    };
+    let created = create_escrow(&txn, &req_user, new_escrow).await;
+
+    match finish(txn, created).await {
+        Ok(escrow) => HttpResponse::Created().json(json!({
+            "status": "success",
+            "message": "Funds are now held in escrow",
+            "data": { "escrow": escrow }
+        })),
+        Err(err) => escrow_error_response(err),
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_escrows(
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match user_escrows(&app_state.db, &req_user).await {
+        Ok(escrows) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched escrows",
+            "data": { "escrows": escrows }
+        })),
+        Err(err) => escrow_error_response(err.into()),
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_escrow(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match find_user_escrow(&app_state.db, &req_user, &path.into_inner()).await {
+        Ok(escrow) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched escrow",
+            "data": { "escrow": escrow }
+        })),
+        Err(err) => escrow_error_response(err),
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn deliver_escrow(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let txn = begin_txn(&app_state).await;
+    let auto_release_hours = app_state.env.escrow_auto_release_hours;
+    let delivered = mark_delivered(&txn, auto_release_hours, &req_user, &path.into_inner()).await;
+
+    match finish(txn, delivered).await {
+        Ok(escrow) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Escrow marked delivered",
+            "data": { "escrow": escrow }
+        })),
+        Err(err) => escrow_error_response(err),
+    }
+}
+
+#[instrument(skip(path, body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn release_my_escrow(
+    path: web::Path<String>,
+    body: web::Json<ReleaseEscrowBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(err) = body.validate() {
+        return HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+    }
+
+    if let Err(msg) = validate_user_pin(&req_user, &body.pin, &app_state.env.hash_key) {
+        return HttpResponse::BadRequest().json(json!({ "status": "error",  "message": msg }));
+    }
+
+    let txn = begin_txn(&app_state).await;
+    let released = release_escrow(&txn, &req_user, &path.into_inner()).await;
+
+    match finish(txn, released).await {
+        Ok(escrow) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Escrow released to the seller",
+            "data": { "escrow": escrow }
+        })),
+        Err(err) => escrow_error_response(err),
+    }
+}
+
+#[instrument(skip(path, body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn dispute_my_escrow(
+    path: web::Path<String>,
+    body: web::Json<DisputeEscrowBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    let txn = begin_txn(&app_state).await;
+    let disputed =
+        dispute_escrow(&txn, &req_user, &path.into_inner(), request_payload.reason).await;
+
+    match finish(txn, disputed).await {
+        Ok(escrow) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Dispute raised. Our support team will be in touch",
+            "data": { "escrow": escrow }
+        })),
+        Err(err) => escrow_error_response(err),
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn refund_my_escrow(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let txn = begin_txn(&app_state).await;
+    let refunded = refund_escrow(&txn, &req_user, &path.into_inner()).await;
+
+    match finish(txn, refunded).await {
+        Ok(escrow) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Escrow refunded to the buyer",
+            "data": { "escrow": escrow }
+        })),
+        Err(err) => escrow_error_response(err),
+    }
+}
+
+#[instrument(skip(path, body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn resolve_escrow(
+    path: web::Path<String>,
+    body: web::Json<ResolveEscrowBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    let in_favour_of = match EscrowParty::parse(&request_payload.in_favour_of) {
+        Some(party) => party,
+        None => {
+            return HttpResponse::BadRequest().json(json!({
+                "status": "error",
+                "message": "in_favour_of must be either buyer or seller"
+            }));
+        }
+    };
+
+    let txn = begin_txn(&app_state).await;
+    let resolved = resolve_escrow_dispute(
+        &txn,
+        &req_user,
+        &path.into_inner(),
+        in_favour_of,
+        request_payload.note,
+    )
+    .await;
+
+    match finish(txn, resolved).await {
+        Ok(escrow) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": format!("Escrow dispute resolved in favour of the {}", in_favour_of),
+            "data": { "escrow": escrow }
+        })),
+        Err(err) => escrow_error_response(err),
+    }
+}
+
+// Every change to an escrow moves money or races other changes, so each runs in its own
+// repeatable read transaction
+async fn begin_txn(app_state: &AppState) -> DatabaseTransaction {
+    app_state
+        .db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadWrite),
+        )
+        .await
+        .expect("Failed to start a DB transaction")
+}
+
+async fn finish(
+    txn: DatabaseTransaction,
+    result: Result<escrows::Model, EscrowError>,
+) -> Result<escrows::Model, EscrowError> {
+    match result {
+        Ok(escrow) => {
+            txn.commit().await?;
+            Ok(escrow)
+        }
+        Err(err) => {
+            let _ = txn.rollback().await;
+            Err(err)
+        }
+    }
+}
+
+fn escrow_error_response(err: EscrowError) -> HttpResponse {
+    match err {
+        err if err.is_not_found() => {
+            HttpResponse::NotFound().json(json!({ "status": "error", "message": err.to_string() }))
+        }
+        err if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        err => {
+            error!("Error handling escrow request ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use actix_web::{http::header, web, HttpResponse, Responder};
+use rust_decimal::{prelude::FromPrimitive, Decimal};
+use sea_orm::*;
+use serde_json::json;
+use tracing::{error, instrument};
+use validator::Validate;
+
+use crate::dto::invoices::{
+    CreateInvoiceBody, InvoiceCardPaymentBody, InvoiceListParams, InvoiceWalletPaymentBody,
+};
+use crate::entities::users;
+use crate::service::invoice::{
+    cancel_invoice, check_invoice_card_payment, create_invoice, hosted_invoice, invoice_details,
+    invoice_document, invoice_pdf, merchant_invoices, pay_invoice_with_wallet,
+    start_invoice_card_payment, InvoiceError, NewInvoice, NewInvoiceItem,
+};
+use crate::service::merchant::find_merchant;
+use crate::utils::helpers::validate_user_pin;
+use crate::AppState;
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn add_invoice(
+    body: web::Json<CreateInvoiceBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    let merchant = match find_merchant(&app_state.db, &req_user.uuid).await {
+        Ok(merchant) => merchant,
+        Err(err) => return invoice_error_response(err.into()),
+    };
+
+    let items = request_payload
+        .items
+        .into_iter()
+        .map(|item| NewInvoiceItem {
+            description: item.description,
+            quantity: item.quantity,
+            unit_price: item.unit_price.into(),
+        })
+        .collect();
+    let tax_rate = request_payload
+        .tax_rate
+        .and_then(Decimal::from_f64)
+        .unwrap_or_default()
+        .round_dp(2);
+
+    let new_invoice = NewInvoice {
+        customer_name: request_payload.customer_name,
+        customer_email: request_payload.customer_email,
+        locale: request_payload.locale,
+        currency: request_payload.currency,
+        tax_rate,
+        due_date: request_payload.due_date,
+        notes: request_payload.notes,
+        items,
+    };
+
+    match create_invoice(&app_state.db, &app_state.env, &merchant, new_invoice).await {
+        Ok(details) => HttpResponse::Created().json(json!({
+            "status": "success",
+            "message": "Invoice sent successfully",
+            "data": details
+        })),
+        Err(err) => invoice_error_response(err),
+    }
+}
+
+#[instrument(skip(params, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_invoices(
+    params: web::Query<InvoiceListParams>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let merchant = match find_merchant(&app_state.db, &req_user.uuid).await {
+        Ok(merchant) => merchant,
+        Err(err) => return invoice_error_response(err.into()),
+    };
+
+    match merchant_invoices(&app_state.db, &merchant, params.status.as_deref()).await {
+        Ok(invoices) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched invoices",
+            "data": { "invoices": invoices }
+        })),
+        Err(err) => invoice_error_response(err.into()),
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_invoice(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let merchant = match find_merchant(&app_state.db, &req_user.uuid).await {
+        Ok(merchant) => merchant,
+        Err(err) => return invoice_error_response(err.into()),
+    };
+
+    match invoice_details(&app_state.db, &merchant, &path.into_inner()).await {
+        Ok(details) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched invoice",
+            "data": details
+        })),
+        Err(err) => invoice_error_response(err),
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn cancel_my_invoice(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let merchant = match find_merchant(&app_state.db, &req_user.uuid).await {
+        Ok(merchant) => merchant,
+        Err(err) => return invoice_error_response(err.into()),
+    };
+
+    match cancel_invoice(&app_state.db, &merchant, &path.into_inner()).await {
+        Ok(invoice) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Invoice cancelled",
+            "data": { "invoice": invoice }
+        })),
+        Err(err) => invoice_error_response(err),
+    }
+}
+
+#[instrument(skip(app_state))]
+pub async fn view_invoice(
+    path: web::Path<String>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match hosted_invoice(&app_state.db, &path.into_inner()).await {
+        Ok(invoice) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched invoice",
+            "data": { "invoice": invoice }
+        })),
+        Err(err) => invoice_error_response(err),
+    }
+}
+
+#[instrument(skip(app_state))]
+pub async fn view_invoice_document(
+    path: web::Path<String>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match invoice_document(&app_state.db, &app_state.env, &path.into_inner()).await {
+        Ok(document) => HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(document),
+        Err(err) => invoice_error_response(err),
+    }
+}
+
+#[instrument(skip(app_state))]
+pub async fn download_invoice_pdf(
+    path: web::Path<String>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match invoice_pdf(&app_state.db, &app_state.env, &path.into_inner()).await {
+        Ok((number, pdf)) => HttpResponse::Ok()
+            .content_type("application/pdf")
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                format!("inline; filename=\"{}.pdf\"", number),
+            ))
+            .body(pdf),
+        Err(err) => invoice_error_response(err),
+    }
+}
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn pay_invoice_from_wallet(
+    path: web::Path<String>,
+    body: web::Json<InvoiceWalletPaymentBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    if let Err(msg) = validate_user_pin(&req_user, &request_payload.pin, &app_state.env.hash_key) {
+        return HttpResponse::BadRequest().json(json!({ "status": "error",  "message": msg }));
+    }
+
+    let txn = app_state
+        .db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadWrite),
+        )
+        .await
+        .expect("Failed to start a DB transaction");
+
+    let payment = pay_invoice_with_wallet(
+        &txn,
+        &app_state.env,
+        &req_user,
+        &path.into_inner(),
+        request_payload.amount.map(|amount| amount.into()),
+    )
+    .await;
+
+    match payment {
+        Ok((payment, invoice)) => {
+            if let Err(err) = txn.commit().await {
+                error!("DB error committing invoice payment ===> {}", err);
+                return HttpResponse::InternalServerError()
+                    .json(json!({ "status": "error", "message": "An unexpected error occured" }));
+            }
+
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Payment successful",
+                "data": {
+                    "payment": payment,
+                    "amount_paid": invoice.amount_paid,
+                    "balance_due": invoice.balance_due(),
+                    "invoice_status": invoice.status
+                }
+            }))
+        }
+        Err(err) => {
+            let _ = txn.rollback().await;
+            invoice_error_response(err)
+        }
+    }
+}
+
+#[instrument(skip(body, app_state))]
+pub async fn pay_invoice_by_card(
+    path: web::Path<String>,
+    body: web::Json<InvoiceCardPaymentBody>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    let payment = start_invoice_card_payment(
+        &app_state.db,
+        &app_state.env,
+        &path.into_inner(),
+        request_payload.amount.map(|amount| amount.into()),
+    )
+    .await;
+
+    match payment {
+        Ok(card_payment) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Payment initiated successfully",
+            "data": {
+                "payment": card_payment.payment,
+                "authorization": card_payment.authorization
+            }
+        })),
+        Err(err) if err.is_client_error() => invoice_error_response(err),
+        Err(err) => {
+            error!("Error initiating invoice card payment ===> {}", err);
+            HttpResponse::BadRequest().json(json!({
+                "status": "error",
+                "message": "Cannot initiate payment at this time, Please try again later"
+            }))
+        }
+    }
+}
+
+#[instrument(skip(app_state))]
+pub async fn invoice_payment_status(
+    path: web::Path<(String, String)>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let (slug, reference) = path.into_inner();
+
+    match check_invoice_card_payment(&app_state.db, &app_state.env, &slug, &reference).await {
+        Ok(payment) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched payment",
+            "data": { "payment": payment }
+        })),
+        Err(err) => invoice_error_response(err),
+    }
+}
+
+fn invoice_error_response(err: InvoiceError) -> HttpResponse {
+    match err {
+        err if err.is_not_found() => {
+            HttpResponse::NotFound().json(json!({ "status": "error", "message": err.to_string() }))
+        }
+        err if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        err => {
+            error!("Error handling invoice request ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{Duration, Utc};
+use serde_json::json;
+use tracing::{error, instrument};
+use validator::Validate;
+
+use crate::dto::merchants::{CreateMerchantBody, CreatePaymentLinkBody, UpdateMerchantBody};
+use crate::entities::users;
+use crate::service::merchant::{
+    create_merchant, create_payment_link, disable_payment_link, find_merchant,
+    merchant_payment_links, payment_link_report, update_merchant, MerchantError, NewPaymentLink,
+};
+use crate::AppState;
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn add_merchant_profile(
+    body: web::Json<CreateMerchantBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    if !req_user.is_verified {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "Please verify your account before taking this action"
+        }));
+    }
+
+    let merchant = create_merchant(
+        &app_state.db,
+        &req_user,
+        &request_payload.business_name,
+        request_payload.settlement_wallet_id.as_deref(),
+    )
+    .await;
+
+    match merchant {
+        Ok(merchant) => HttpResponse::Created().json(json!({
+            "status": "success",
+            "message": "Merchant profile created successfully",
+            "data": { "merchant": merchant }
+        })),
+        Err(err) => merchant_error_response(err),
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_merchant_profile(
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match find_merchant(&app_state.db, &req_user.uuid).await {
+        Ok(merchant) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched merchant profile",
+            "data": { "merchant": merchant }
+        })),
+        Err(err) => merchant_error_response(err),
+    }
+}
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn update_merchant_profile(
+    body: web::Json<UpdateMerchantBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    let merchant = update_merchant(
+        &app_state.db,
+        &req_user.uuid,
+        request_payload.business_name.as_deref(),
+        request_payload.settlement_wallet_id.as_deref(),
+    )
+    .await;
+
+    match merchant {
+        Ok(merchant) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Merchant profile updated successfully",
+            "data": { "merchant": merchant }
+        })),
+        Err(err) => merchant_error_response(err),
+    }
+}
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn add_payment_link(
+    body: web::Json<CreatePaymentLinkBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    let merchant = match find_merchant(&app_state.db, &req_user.uuid).await {
+        Ok(merchant) => merchant,
+        Err(err) => return merchant_error_response(err),
+    };
+
+    let link = NewPaymentLink {
+        title: request_payload.title,
+        description: request_payload.description,
+        amount: request_payload.amount.map(|amount| amount.into()),
+        max_uses: request_payload.max_uses,
+        expires_at: request_payload
+            .expires_in_hours
+            .map(|hours| Utc::now() + Duration::hours(hours)),
+    };
+
+    match create_payment_link(&app_state.db, &merchant, link).await {
+        Ok(payment_link) => HttpResponse::Created().json(json!({
+            "status": "success",
+            "message": "Payment link created successfully",
+            "data": { "payment_link": payment_link }
+        })),
+        Err(err) => merchant_error_response(err),
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_payment_links(
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let merchant = match find_merchant(&app_state.db, &req_user.uuid).await {
+        Ok(merchant) => merchant,
+        Err(err) => return merchant_error_response(err),
+    };
+
+    match merchant_payment_links(&app_state.db, &merchant).await {
+        Ok(payment_links) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched payment links",
+            "data": { "payment_links": payment_links }
+        })),
+        Err(err) => merchant_error_response(err.into()),
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn disable_my_payment_link(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let merchant = match find_merchant(&app_state.db, &req_user.uuid).await {
+        Ok(merchant) => merchant,
+        Err(err) => return merchant_error_response(err),
+    };
+
+    match disable_payment_link(&app_state.db, &merchant, &path.into_inner()).await {
+        Ok(payment_link) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Payment link disabled",
+            "data": { "payment_link": payment_link }
+        })),
+        Err(err) => merchant_error_response(err),
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_payment_link_report(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let merchant = match find_merchant(&app_state.db, &req_user.uuid).await {
+        Ok(merchant) => merchant,
+        Err(err) => return merchant_error_response(err),
+    };
+
+    match payment_link_report(&app_state.db, &merchant, &path.into_inner()).await {
+        Ok(report) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched payment link report",
+            "data": { "report": report }
+        })),
+        Err(err) => merchant_error_response(err),
+    }
+}
+
+pub fn merchant_error_response(err: MerchantError) -> HttpResponse {
+    match err {
+        err if err.is_not_found() => {
+            HttpResponse::NotFound().json(json!({ "status": "error", "message": err.to_string() }))
+        }
+        err if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        err => {
+            error!("Error handling merchant request ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use actix_web::{web, HttpResponse, Responder};
+use serde_json::json;
+use tracing::{error, instrument};
+use validator::Validate;
+
+use crate::dto::notifications::{RegisterDeviceBody, UpdateNotificationPreferenceBody};
+use crate::entities::users;
+use crate::service::notification::{
+    register_device, remove_device, set_preference, user_preferences, NotificationError,
+};
+use crate::AppState;
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_notification_preferences(
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match user_preferences(&app_state.db, &req_user.uuid).await {
+        Ok(preferences) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched notification preferences",
+            "data": { "preferences": preferences }
+        })),
+        Err(err) => {
+            error!("Error retrieving notification preferences ===> {}", err);
+            HttpResponse::InternalServerError().json(
+                json!({ "status": "error", "message": "Failed to fetch notification preferences" }),
+            )
+        }
+    }
+}
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid, event = %body.event, channel = %body.channel))]
+pub async fn update_notification_preference(
+    body: web::Json<UpdateNotificationPreferenceBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let body = body.into_inner();
+    let updated = set_preference(
+        &app_state.db,
+        &req_user.uuid,
+        &body.event,
+        &body.channel,
+        body.enabled,
+    )
+    .await;
+
+    if let Err(err) = updated {
+        return notification_error_response(err);
+    }
+
+    match user_preferences(&app_state.db, &req_user.uuid).await {
+        Ok(preferences) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Notification preference updated successfully",
+            "data": { "preferences": preferences }
+        })),
+        Err(err) => notification_error_response(err.into()),
+    }
+}
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid, platform = %body.platform))]
+pub async fn add_device(
+    body: web::Json<RegisterDeviceBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    let device = register_device(
+        &app_state.db,
+        &req_user.uuid,
+        &request_payload.token,
+        &request_payload.platform.to_lowercase(),
+    )
+    .await;
+
+    match device {
+        Ok(device) => HttpResponse::Created().json(json!({
+            "status": "success",
+            "message": "Device registered successfully",
+            "data": { "device": device }
+        })),
+        Err(err) => notification_error_response(err.into()),
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn delete_device(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match remove_device(&app_state.db, &req_user.uuid, &path.into_inner()).await {
+        Ok(_) => HttpResponse::Ok()
+            .json(json!({ "status": "success", "message": "Device removed successfully" })),
+        Err(err) => notification_error_response(err),
+    }
+}
+
+fn notification_error_response(err: NotificationError) -> HttpResponse {
+    match err {
+        NotificationError::DeviceNotFound => {
+            HttpResponse::NotFound().json(json!({ "status": "error", "message": err.to_string() }))
+        }
+        err if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        err => {
+            error!("Error updating notification settings ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use actix_web::{web, HttpResponse, Responder};
+use sea_orm::*;
+use serde_json::json;
+use tracing::{error, instrument};
+use validator::Validate;
+
+use crate::dto::payment_links::{CardPaymentBody, WalletPaymentBody};
+use crate::entities::users;
+use crate::service::merchant::{
+    check_card_payment, find_open_link, pay_with_wallet, start_card_payment,
+};
+use crate::utils::helpers::validate_user_pin;
+use crate::AppState;
+
+use super::merchants::merchant_error_response;
+
+#[instrument(skip(app_state))]
+pub async fn view_payment_link(
+    path: web::Path<String>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match find_open_link(&app_state.db, &path.into_inner()).await {
+        Ok((link, merchant)) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched payment link",
+            "data": { "payment_link": link.filter_response(&merchant.business_name) }
+        })),
+        Err(err) => merchant_error_response(err),
+    }
+}
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn pay_link_with_wallet(
+    path: web::Path<String>,
+    body: web::Json<WalletPaymentBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    if let Err(msg) = validate_user_pin(&req_user, &request_payload.pin, &app_state.env.hash_key) {
+        return HttpResponse::BadRequest().json(json!({ "status": "error",  "message": msg }));
+    }
+
+    let txn = app_state
+        .db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadWrite),
+        )
+        .await
+        .expect("Failed to start a DB transaction");
+
+    let payment = pay_with_wallet(
+        &txn,
+        &app_state.env,
+        &req_user,
+        &path.into_inner(),
+        request_payload.amount.map(|amount| amount.into()),
+    )
+    .await;
+
+    match payment {
+        Ok(payment) => {
+            if let Err(err) = txn.commit().await {
+                error!("DB error committing payment link payment ===> {}", err);
+                return HttpResponse::InternalServerError()
+                    .json(json!({ "status": "error", "message": "An unexpected error occured" }));
+            }
+
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Payment successful",
+                "data": { "payment": payment }
+            }))
+        }
+        Err(err) => {
+            let _ = txn.rollback().await;
+            merchant_error_response(err)
+        }
+    }
+}
+
+#[instrument(skip(body, app_state))]
+pub async fn pay_link_with_card(
+    path: web::Path<String>,
+    body: web::Json<CardPaymentBody>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    let payment = start_card_payment(
+        &app_state.db,
+        &app_state.env,
+        &path.into_inner(),
+        &request_payload.email,
+        request_payload.amount.map(|amount| amount.into()),
+    )
+    .await;
+
+    match payment {
+        Ok(card_payment) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Payment initiated successfully",
+            "data": {
+                "payment": card_payment.payment,
+                "authorization": card_payment.authorization
+            }
+        })),
+        Err(err) if err.is_client_error() => merchant_error_response(err),
+        Err(err) => {
+            error!("Error initiating payment link card payment ===> {}", err);
+            HttpResponse::BadRequest().json(json!({
+                "status": "error",
+                "message": "Cannot initiate payment at this time, Please try again later"
+            }))
+        }
+    }
+}
+
+#[instrument(skip(app_state))]
+pub async fn payment_link_payment_status(
+    path: web::Path<(String, String)>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let (slug, reference) = path.into_inner();
+
+    match check_card_payment(&app_state.db, &app_state.env, &slug, &reference).await {
+        Ok(payment) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched payment",
+            "data": { "payment": payment }
+        })),
+        Err(err) => merchant_error_response(err),
+    }
+}
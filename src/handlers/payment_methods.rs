@@ -0,0 +1,128 @@
+use actix_web::{web, HttpResponse, Responder};
+use serde_json::json;
+use tracing::{error, instrument};
+use validator::Validate;
+
+use crate::dto::payment_methods::FundWithPaymentMethodBody;
+use crate::entities::{sea_orm_active_enums::Status, users};
+use crate::service::payment_method::{
+    delete_payment_method, fund_with_payment_method, user_payment_methods, PaymentMethodError,
+};
+use crate::utils::helpers::validate_user_pin;
+use crate::AppState;
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_payment_methods(
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match user_payment_methods(&app_state.db, &req_user.uuid).await {
+        Ok(payment_methods) => {
+            let payment_methods: Vec<_> = payment_methods
+                .iter()
+                .map(|payment_method| payment_method.filter_response())
+                .collect();
+
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Fetched saved cards",
+                "data": { "payment_methods": payment_methods }
+            }))
+        }
+        Err(err) => {
+            error!("Error retrieving saved cards: {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "Failed to fetch saved cards" }))
+        }
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn remove_payment_method(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match delete_payment_method(&app_state.db, &req_user.uuid, &path.into_inner()).await {
+        Ok(_) => HttpResponse::Ok()
+            .json(json!({ "status": "success", "message": "Card removed successfully" })),
+        Err(err) => payment_method_error_response(err),
+    }
+}
+
+#[instrument(skip(path, body, req_user, app_state), fields(user_id = %req_user.uuid, amount = %body.amount))]
+pub async fn fund_with_saved_card(
+    path: web::Path<String>,
+    body: web::Json<FundWithPaymentMethodBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    if !req_user.is_verified {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "Please verify your account before taking this action"
+        }));
+    }
+
+    if let Err(msg) = validate_user_pin(&req_user, &request_payload.pin, &app_state.env.hash_key) {
+        return HttpResponse::BadRequest().json(json!({ "status": "error",  "message": msg }));
+    }
+
+    let funding = fund_with_payment_method(
+        &app_state,
+        &req_user,
+        &path.into_inner(),
+        request_payload.amount.into(),
+    )
+    .await;
+
+    match funding {
+        Ok(funding) => {
+            let message = match funding.status {
+                Some(Status::Successful) => "Wallet funded successfully",
+                _ => "Funding is being processed",
+            };
+
+            HttpResponse::Ok().json(json!({
+                "status": "success", "message": message, "data": { "transaction": funding }
+            }))
+        }
+        // Settled by the funding poller once the provider answers
+        Err(PaymentMethodError::ProviderUnavailable(err)) => {
+            error!("Error charging saved card ===> {}", err);
+            HttpResponse::Ok()
+                .json(json!({ "status": "success", "message": "Funding is being processed" }))
+        }
+        Err(err) => payment_method_error_response(err),
+    }
+}
+
+fn payment_method_error_response(err: PaymentMethodError) -> HttpResponse {
+    match err {
+        PaymentMethodError::NotFound => {
+            HttpResponse::NotFound().json(json!({ "status": "error", "message": err.to_string() }))
+        }
+        err if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        PaymentMethodError::ProviderError(err) => {
+            error!("Error charging saved card ===> {}", err);
+            HttpResponse::BadRequest().json(json!({
+                "status": "error",
+                "message": "Cannot charge card at this time, Please try again later"
+            }))
+        }
+        err => {
+            error!("Error processing saved card ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
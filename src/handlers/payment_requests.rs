@@ -0,0 +1,261 @@
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{Duration, Utc};
+use sea_orm::*;
+use serde_json::json;
+use tracing::{error, instrument};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::dto::payment_requests::{AcceptPaymentRequestBody, CreatePaymentRequestBody};
+use crate::entities::{
+    payment_requests,
+    prelude::{PaymentRequests, Users},
+    users,
+};
+use crate::service::payment_request::{self, PaymentRequestError, PaymentRequestStatus};
+use crate::utils::helpers::validate_user_pin;
+use crate::AppState;
+
+// Requests are valid for 7 days unless the requester says otherwise
+const DEFAULT_EXPIRY_HOURS: i64 = 168;
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid, amount = %body.amount, payer_id = %body.payer_id))]
+pub async fn create_payment_request(
+    body: web::Json<CreatePaymentRequestBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    if !req_user.is_verified {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "Please verify your account before taking this action"
+        }));
+    }
+
+    if req_user.uuid == request_payload.payer_id {
+        return HttpResponse::BadRequest()
+            .json(json!({ "status": "error",  "message": "Cannot request funds from yourself" }));
+    }
+
+    let payer = Users::find()
+        .filter(users::Column::Uuid.eq(&request_payload.payer_id))
+        .one(&app_state.db)
+        .await;
+
+    let payer = match payer {
+        Ok(Some(payer)) => payer,
+        Ok(None) => {
+            return HttpResponse::BadRequest().json(json!({
+                "status": "error",
+                "message": "User you are trying to request funds from not found"
+            }));
+        }
+        Err(err) => {
+            error!("DB error validating payer details ===> {}", err);
+            return HttpResponse::InternalServerError().json(
+                json!({ "status": "error", "message": "An error occured trying to validate payer" }),
+            );
+        }
+    };
+
+    if !payer.is_verified {
+        let msg = format!(
+            "Cannot request funds from {} {}, As they are not yet verified",
+            payer.last_name, payer.first_name
+        );
+        return HttpResponse::BadRequest().json(json!({ "status": "error",  "message": msg }));
+    }
+
+    let expires_in_hours = request_payload
+        .expires_in_hours
+        .unwrap_or(DEFAULT_EXPIRY_HOURS);
+
+    let new_request = payment_requests::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        requester_id: Set(req_user.uuid.to_string()),
+        payer_id: Set(payer.uuid),
+        amount: Set(request_payload.amount.into()),
+        narration: Set(request_payload.narration),
+        status: Set(PaymentRequestStatus::Pending.to_string()),
+        expires_at: Set(Utc::now() + Duration::hours(expires_in_hours)),
+        ..Default::default()
+    };
+
+    match new_request.insert(&app_state.db).await {
+        Ok(payment_request) => HttpResponse::Created().json(json!({
+            "status": "success",
+            "message": "Payment request sent successfully",
+            "data": { "payment_request": payment_request }
+        })),
+        Err(err) => {
+            error!("DB error saving payment request ===> {}", err);
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "An error occured trying to create payment request"
+            }))
+        }
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn incoming_payment_requests(
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    list_payment_requests(payment_requests::Column::PayerId, &req_user, &app_state).await
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn outgoing_payment_requests(
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    list_payment_requests(payment_requests::Column::RequesterId, &req_user, &app_state).await
+}
+
+async fn list_payment_requests(
+    user_column: payment_requests::Column,
+    req_user: &users::Model,
+    app_state: &AppState,
+) -> HttpResponse {
+    let requests = PaymentRequests::find()
+        .filter(user_column.eq(&req_user.uuid))
+        .filter(payment_requests::Column::DeletedAt.is_null())
+        .order_by_desc(payment_requests::Column::CreatedAt)
+        .all(&app_state.db)
+        .await;
+
+    match requests {
+        Ok(requests) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched payment requests",
+            "data": { "payment_requests": requests }
+        })),
+        Err(err) => {
+            error!("Error retrieving payment requests: {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "Failed to fetch payment requests" }))
+        }
+    }
+}
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn accept_payment_request(
+    path: web::Path<String>,
+    body: web::Json<AcceptPaymentRequestBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    if let Err(msg) = validate_user_pin(&req_user, &request_payload.pin, &app_state.env.hash_key) {
+        return HttpResponse::BadRequest().json(json!({ "status": "error",  "message": msg }));
+    }
+
+    let txn = app_state
+        .db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadWrite),
+        )
+        .await
+        .expect("Failed to start a DB transaction");
+
+    match payment_request::accept_payment_request(&txn, &req_user, &path.into_inner()).await {
+        Ok(payment_request) => {
+            let _ = txn.commit().await;
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Payment request accepted, Funds sent successfully",
+                "data": { "payment_request": payment_request }
+            }))
+        }
+        Err(err) => {
+            let _ = txn.rollback().await;
+            payment_request_error_response(err)
+        }
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn decline_payment_request(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let txn = match app_state.db.begin().await {
+        Ok(txn) => txn,
+        Err(err) => return payment_request_error_response(PaymentRequestError::DatabaseError(err)),
+    };
+
+    match payment_request::decline_payment_request(&txn, &req_user, &path.into_inner()).await {
+        Ok(payment_request) => match txn.commit().await {
+            Ok(_) => HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Payment request declined",
+                "data": { "payment_request": payment_request }
+            })),
+            Err(err) => payment_request_error_response(PaymentRequestError::DatabaseError(err)),
+        },
+        Err(err) => {
+            let _ = txn.rollback().await;
+            payment_request_error_response(err)
+        }
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn cancel_payment_request(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let txn = match app_state.db.begin().await {
+        Ok(txn) => txn,
+        Err(err) => return payment_request_error_response(PaymentRequestError::DatabaseError(err)),
+    };
+
+    match payment_request::cancel_payment_request(&txn, &req_user, &path.into_inner()).await {
+        Ok(payment_request) => match txn.commit().await {
+            Ok(_) => HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Payment request cancelled",
+                "data": { "payment_request": payment_request }
+            })),
+            Err(err) => payment_request_error_response(PaymentRequestError::DatabaseError(err)),
+        },
+        Err(err) => {
+            let _ = txn.rollback().await;
+            payment_request_error_response(err)
+        }
+    }
+}
+
+fn payment_request_error_response(err: PaymentRequestError) -> HttpResponse {
+    match err {
+        PaymentRequestError::NotFound => {
+            HttpResponse::NotFound().json(json!({ "status": "error", "message": err.to_string() }))
+        }
+        err if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        err => {
+            error!("Payment request error ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
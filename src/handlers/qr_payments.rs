@@ -0,0 +1,249 @@
+use actix_web::{web, HttpResponse, Responder};
+use sea_orm::*;
+use serde_json::json;
+use tracing::{error, instrument};
+use validator::Validate;
+
+use crate::dto::qr_payments::{
+    CreateQrCodeBody, DecodeQrBody, QrImageParams, QrPaymentBody, StaticQrParams,
+};
+use crate::entities::users;
+use crate::service::qr_payment::{
+    cancel_qr_code, create_dynamic_qr_code, decode_qr_code, find_user_qr_code, pay_with_qr_code,
+    qr_image, static_qr_code, user_qr_codes, NewQrCode, QrPayment, QrPaymentError,
+};
+use crate::utils::helpers::validate_user_pin;
+use crate::AppState;
+
+#[instrument(skip(params, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_static_qr(
+    params: web::Query<StaticQrParams>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(err) = params.validate() {
+        return HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+    }
+
+    let amount = params.amount.map(|amount| amount.into());
+    match static_qr_code(&app_state.db, &app_state.env, &req_user, amount).await {
+        Ok(qr_code) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched QR code",
+            "data": { "qr_code": qr_code }
+        })),
+        Err(err) => qr_error_response(err),
+    }
+}
+
+#[instrument(skip(params, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_static_qr_image(
+    params: web::Query<StaticQrParams>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(err) = params.validate() {
+        return HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+    }
+
+    let amount = params.amount.map(|amount| amount.into());
+    let qr_code = match static_qr_code(&app_state.db, &app_state.env, &req_user, amount).await {
+        Ok(qr_code) => qr_code,
+        Err(err) => return qr_error_response(err),
+    };
+
+    image_response(&qr_code.payload, params.format.as_deref())
+}
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn add_qr_code(
+    body: web::Json<CreateQrCodeBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    let new_qr_code = NewQrCode {
+        amount: request_payload.amount.into(),
+        description: request_payload.description,
+        expires_in_mins: request_payload.expires_in_mins,
+    };
+
+    match create_dynamic_qr_code(&app_state.db, &app_state.env, &req_user, new_qr_code).await {
+        Ok(qr_code) => HttpResponse::Created().json(json!({
+            "status": "success",
+            "message": "QR code created successfully",
+            "data": qr_code
+        })),
+        Err(err) => qr_error_response(err),
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_qr_codes(
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match user_qr_codes(&app_state.db, &app_state.env, &req_user).await {
+        Ok(qr_codes) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched QR codes",
+            "data": { "qr_codes": qr_codes }
+        })),
+        Err(err) => qr_error_response(err.into()),
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_qr_code(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match find_user_qr_code(&app_state.db, &app_state.env, &req_user, &path.into_inner()).await {
+        Ok(qr_code) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched QR code",
+            "data": qr_code
+        })),
+        Err(err) => qr_error_response(err),
+    }
+}
+
+#[instrument(skip(path, params, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_qr_code_image(
+    path: web::Path<String>,
+    params: web::Query<QrImageParams>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let qr_code_id = path.into_inner();
+    let found = find_user_qr_code(&app_state.db, &app_state.env, &req_user, &qr_code_id).await;
+    let qr_code = match found {
+        Ok(qr_code) => qr_code,
+        Err(err) => return qr_error_response(err),
+    };
+
+    image_response(&qr_code.payload, params.format.as_deref())
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn cancel_my_qr_code(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match cancel_qr_code(&app_state.db, &app_state.env, &req_user, &path.into_inner()).await {
+        Ok(qr_code) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "QR code cancelled",
+            "data": { "qr_code": qr_code }
+        })),
+        Err(err) => qr_error_response(err),
+    }
+}
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn decode_qr(
+    body: web::Json<DecodeQrBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(err) = body.validate() {
+        return HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+    }
+
+    match decode_qr_code(&app_state.db, &app_state.env, &body.payload).await {
+        Ok(qr_code) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Decoded QR code",
+            "data": { "qr_code": qr_code }
+        })),
+        Err(err) => qr_error_response(err),
+    }
+}
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn pay_qr(
+    body: web::Json<QrPaymentBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    if let Err(msg) = validate_user_pin(&req_user, &request_payload.pin, &app_state.env.hash_key) {
+        return HttpResponse::BadRequest().json(json!({ "status": "error",  "message": msg }));
+    }
+
+    let txn = app_state
+        .db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadWrite),
+        )
+        .await
+        .expect("Failed to start a DB transaction");
+
+    let payment = QrPayment {
+        payload: request_payload.payload,
+        amount: request_payload.amount.map(|amount| amount.into()),
+        narration: request_payload.narration,
+    };
+
+    match pay_with_qr_code(&txn, &app_state.env, &req_user, payment).await {
+        Ok(receipt) => {
+            if let Err(err) = txn.commit().await {
+                error!("DB error committing QR payment ===> {}", err);
+                return HttpResponse::InternalServerError()
+                    .json(json!({ "status": "error", "message": "An unexpected error occured" }));
+            }
+
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Funds sent successfully",
+                "data": { "receipt": receipt }
+            }))
+        }
+        Err(err) => {
+            let _ = txn.rollback().await;
+            qr_error_response(err)
+        }
+    }
+}
+
+fn image_response(payload: &str, format: Option<&str>) -> HttpResponse {
+    match qr_image(payload, format.unwrap_or("png")) {
+        Ok((content_type, image)) => HttpResponse::Ok().content_type(content_type).body(image),
+        Err(err) => qr_error_response(err),
+    }
+}
+
+fn qr_error_response(err: QrPaymentError) -> HttpResponse {
+    match err {
+        err if err.is_not_found() => {
+            HttpResponse::NotFound().json(json!({ "status": "error", "message": err.to_string() }))
+        }
+        err if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        err => {
+            error!("Error handling QR request ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use actix_web::{web, HttpResponse, Responder};
+use chrono::Utc;
+use sea_orm::*;
+use serde_json::json;
+use tracing::{error, instrument};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::dto::transfers::ScheduledTransferBody;
+use crate::entities::{
+    prelude::{ScheduledTransfers, Users},
+    scheduled_transfers, users,
+};
+use crate::service::scheduled_transfer::{next_occurrence, parse_cron_expression, ScheduleStatus};
+use crate::utils::helpers::validate_user_pin;
+use crate::AppState;
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid, amount = %body.amount, receiver_id = %body.receiver_id))]
+pub async fn create_scheduled_transfer(
+    body: web::Json<ScheduledTransferBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    if !req_user.is_verified {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "Please verify your account before taking this action"
+        }));
+    }
+
+    if req_user.uuid == request_payload.receiver_id {
+        return HttpResponse::BadRequest()
+            .json(json!({ "status": "error",  "message": "Cannot send funds to yourself" }));
+    }
+
+    if let Err(msg) = validate_user_pin(&req_user, &request_payload.pin, &app_state.env.hash_key) {
+        return HttpResponse::BadRequest().json(json!({ "status": "error",  "message": msg }));
+    }
+
+    let now = Utc::now();
+    let next_run_at = match (&request_payload.run_at, &request_payload.cron_expression) {
+        (Some(run_at), None) => {
+            if *run_at <= now {
+                return HttpResponse::BadRequest().json(
+                    json!({ "status": "error",  "message": "run_at must be a time in the future" }),
+                );
+            }
+            *run_at
+        }
+        (None, Some(cron_expression)) => {
+            if let Err(err) = parse_cron_expression(cron_expression) {
+                let msg = format!("Invalid cron expression: {}", err);
+                return HttpResponse::BadRequest()
+                    .json(json!({ "status": "error",  "message": msg }));
+            }
+
+            match next_occurrence(cron_expression, now, request_payload.end_at) {
+                Some(next_run_at) => next_run_at,
+                None => {
+                    return HttpResponse::BadRequest().json(json!({
+                        "status": "error",
+                        "message": "Schedule does not have any run before end_at"
+                    }));
+                }
+            }
+        }
+        _ => {
+            return HttpResponse::BadRequest().json(json!({
+                "status": "error",
+                "message": "Please pass either run_at for a one-off transfer or cron_expression for a recurring transfer"
+            }));
+        }
+    };
+
+    let receiver = Users::find()
+        .filter(users::Column::Uuid.eq(&request_payload.receiver_id))
+        .one(&app_state.db)
+        .await;
+
+    match receiver {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return HttpResponse::BadRequest().json(
+                json!({ "status": "error", "message": "User you are trying to send funds to not found" }),
+            );
+        }
+        Err(err) => {
+            error!("DB error validating receiver details ===> {}", err);
+            return HttpResponse::InternalServerError().json(
+                json!({ "status": "error", "message": "An error occured trying to validate receiver" }),
+            );
+        }
+    }
+
+    let new_schedule = scheduled_transfers::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        user_id: Set(req_user.uuid.to_string()),
+        receiver_id: Set(request_payload.receiver_id),
+        amount: Set(request_payload.amount.into()),
+        narration: Set(request_payload.narration),
+        cron_expression: Set(request_payload.cron_expression),
+        next_run_at: Set(Some(next_run_at)),
+        end_at: Set(request_payload.end_at),
+        status: Set(ScheduleStatus::Active.to_string()),
+        max_retries: Set(request_payload.max_retries.unwrap_or(3)),
+        ..Default::default()
+    };
+
+    match new_schedule.insert(&app_state.db).await {
+        Ok(schedule) => HttpResponse::Created().json(json!({
+            "status": "success",
+            "message": "Transfer scheduled successfully",
+            "data": { "scheduled_transfer": schedule }
+        })),
+        Err(err) => {
+            error!("DB error saving scheduled transfer ===> {}", err);
+            HttpResponse::InternalServerError().json(
+                json!({ "status": "error", "message": "An error occured trying to schedule transfer" }),
+            )
+        }
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_scheduled_transfers(
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let schedules = ScheduledTransfers::find()
+        .filter(scheduled_transfers::Column::UserId.eq(&req_user.uuid))
+        .filter(scheduled_transfers::Column::DeletedAt.is_null())
+        .order_by_desc(scheduled_transfers::Column::CreatedAt)
+        .all(&app_state.db)
+        .await;
+
+    match schedules {
+        Ok(schedules) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched scheduled transfers",
+            "data": { "scheduled_transfers": schedules }
+        })),
+        Err(err) => {
+            error!("Error retrieving scheduled transfers: {}", err);
+            HttpResponse::InternalServerError().json(
+                json!({ "status": "error", "message": "Failed to fetch scheduled transfers" }),
+            )
+        }
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn pause_scheduled_transfer(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    update_schedule_status(
+        &path.into_inner(),
+        &req_user,
+        &app_state,
+        &[ScheduleStatus::Active],
+        ScheduleStatus::Paused,
+    )
+    .await
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn resume_scheduled_transfer(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    update_schedule_status(
+        &path.into_inner(),
+        &req_user,
+        &app_state,
+        &[ScheduleStatus::Paused],
+        ScheduleStatus::Active,
+    )
+    .await
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn cancel_scheduled_transfer(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    update_schedule_status(
+        &path.into_inner(),
+        &req_user,
+        &app_state,
+        &[ScheduleStatus::Active, ScheduleStatus::Paused],
+        ScheduleStatus::Cancelled,
+    )
+    .await
+}
+
+async fn update_schedule_status(
+    schedule_id: &String,
+    req_user: &users::Model,
+    app_state: &AppState,
+    allowed_from: &[ScheduleStatus],
+    new_status: ScheduleStatus,
+) -> HttpResponse {
+    let schedule = ScheduledTransfers::find()
+        .filter(scheduled_transfers::Column::Uuid.eq(schedule_id))
+        .filter(scheduled_transfers::Column::UserId.eq(&req_user.uuid))
+        .filter(scheduled_transfers::Column::DeletedAt.is_null())
+        .one(&app_state.db)
+        .await;
+
+    let schedule = match schedule {
+        Ok(Some(schedule)) => schedule,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(json!({ "status": "error", "message": "Scheduled transfer not found" }));
+        }
+        Err(err) => {
+            error!("DB error fetching scheduled transfer ===> {}", err);
+            return HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }));
+        }
+    };
+
+    if !allowed_from
+        .iter()
+        .any(|status| status.to_string() == schedule.status)
+    {
+        let msg = format!("Scheduled transfer is already {}", schedule.status);
+        return HttpResponse::BadRequest().json(json!({ "status": "error", "message": msg }));
+    }
+
+    let now = Utc::now();
+    let mut updated_schedule: scheduled_transfers::ActiveModel = schedule.clone().into();
+
+    // Recurring schedules resumed after missing runs continue from the next occurrence instead of catching up
+    if new_status == ScheduleStatus::Active {
+        if let Some(cron_expression) = &schedule.cron_expression {
+            let next_run_at = match schedule.next_run_at {
+                Some(next_run_at) if next_run_at > now => Some(next_run_at),
+                _ => next_occurrence(cron_expression, now, schedule.end_at),
+            };
+
+            if next_run_at.is_none() {
+                return HttpResponse::BadRequest().json(json!({
+                    "status": "error",
+                    "message": "Schedule does not have any run left before end_at"
+                }));
+            }
+            updated_schedule.next_run_at = Set(next_run_at);
+        }
+        updated_schedule.retry_count = Set(0);
+    }
+
+    updated_schedule.status = Set(new_status.to_string());
+    updated_schedule.updated_at = Set(now);
+
+    match updated_schedule.update(&app_state.db).await {
+        Ok(schedule) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Scheduled transfer updated successfully",
+            "data": { "scheduled_transfer": schedule }
+        })),
+        Err(err) => {
+            error!("DB error updating scheduled transfer ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
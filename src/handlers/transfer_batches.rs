@@ -0,0 +1,189 @@
+use actix_web::{web, HttpResponse, Responder};
+use sea_orm::*;
+use serde_json::json;
+use tracing::{error, instrument};
+use validator::Validate;
+
+use crate::dto::transfers::CreateTransferBatchBody;
+use crate::entities::{
+    prelude::{Transactions, TransferBatchItems, TransferBatches},
+    transactions, transfer_batch_items, transfer_batches, users,
+};
+use crate::service::transfer_batch::{
+    self, parse_batch_items, process_transfer_batch, validate_batch_items, TransferBatchError,
+};
+use crate::utils::helpers::validate_user_pin;
+use crate::AppState;
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn create_transfer_batch(
+    body: web::Json<CreateTransferBatchBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    if !req_user.is_verified {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "Please verify your account before taking this action"
+        }));
+    }
+
+    if let Err(msg) = validate_user_pin(&req_user, &request_payload.pin, &app_state.env.hash_key) {
+        return HttpResponse::BadRequest().json(json!({ "status": "error",  "message": msg }));
+    }
+
+    let items = match parse_batch_items(request_payload.items, request_payload.csv) {
+        Ok(items) => items,
+        Err(err) => return transfer_batch_error_response(err),
+    };
+
+    let items = match validate_batch_items(&app_state, &req_user, items).await {
+        Ok(items) => items,
+        Err(err) => return transfer_batch_error_response(err),
+    };
+
+    let txn = app_state
+        .db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadWrite),
+        )
+        .await
+        .expect("Failed to start a DB transaction");
+
+    match transfer_batch::create_transfer_batch(&txn, &req_user, items).await {
+        Ok((batch, items)) => {
+            let _ = txn.commit().await;
+            actix_web::rt::spawn(process_transfer_batch(
+                app_state.get_ref().clone(),
+                batch.uuid.to_string(),
+            ));
+
+            HttpResponse::Accepted().json(json!({
+                "status": "success",
+                "message": "Transfer batch accepted for processing",
+                "data": { "batch": batch, "items": items }
+            }))
+        }
+        Err(err) => {
+            let _ = txn.rollback().await;
+            transfer_batch_error_response(err)
+        }
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_transfer_batches(
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let batches = TransferBatches::find()
+        .filter(transfer_batches::Column::UserId.eq(&req_user.uuid))
+        .filter(transfer_batches::Column::DeletedAt.is_null())
+        .order_by_desc(transfer_batches::Column::CreatedAt)
+        .all(&app_state.db)
+        .await;
+
+    match batches {
+        Ok(batches) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched transfer batches",
+            "data": { "batches": batches }
+        })),
+        Err(err) => {
+            error!("Error retrieving transfer batches: {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "Failed to fetch transfer batches" }))
+        }
+    }
+}
+
+// Batch report with every item and the current status of the transaction it produced
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn get_transfer_batch(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let batch = TransferBatches::find()
+        .filter(transfer_batches::Column::Uuid.eq(path.into_inner()))
+        .filter(transfer_batches::Column::UserId.eq(&req_user.uuid))
+        .filter(transfer_batches::Column::DeletedAt.is_null())
+        .one(&app_state.db)
+        .await;
+
+    let batch = match batch {
+        Ok(Some(batch)) => batch,
+        Ok(None) => return transfer_batch_error_response(TransferBatchError::NotFound),
+        Err(err) => return transfer_batch_error_response(TransferBatchError::DatabaseError(err)),
+    };
+
+    let items = TransferBatchItems::find()
+        .filter(transfer_batch_items::Column::BatchId.eq(&batch.uuid))
+        .order_by_asc(transfer_batch_items::Column::Position)
+        .all(&app_state.db)
+        .await;
+
+    let items = match items {
+        Ok(items) => items,
+        Err(err) => return transfer_batch_error_response(TransferBatchError::DatabaseError(err)),
+    };
+
+    let references: Vec<String> = items
+        .iter()
+        .filter_map(|item| item.transaction_reference.clone())
+        .collect();
+    let transactions = Transactions::find()
+        .filter(transactions::Column::Uuid.is_in(references))
+        .all(&app_state.db)
+        .await;
+
+    let transactions = match transactions {
+        Ok(transactions) => transactions,
+        Err(err) => return transfer_batch_error_response(TransferBatchError::DatabaseError(err)),
+    };
+
+    let items: Vec<_> = items
+        .into_iter()
+        .map(|item| {
+            let transaction_status = transactions
+                .iter()
+                .find(|transaction| Some(&transaction.uuid) == item.transaction_reference.as_ref())
+                .and_then(|transaction| transaction.status.clone())
+                .map(|status| status.to_value());
+
+            json!({ "item": item, "transaction_status": transaction_status })
+        })
+        .collect();
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "message": "Fetched transfer batch",
+        "data": { "batch": batch, "items": items }
+    }))
+}
+
+fn transfer_batch_error_response(err: TransferBatchError) -> HttpResponse {
+    match err {
+        TransferBatchError::NotFound => {
+            HttpResponse::NotFound().json(json!({ "status": "error", "message": err.to_string() }))
+        }
+        TransferBatchError::InvalidItems(ref errors) => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string(), "data": errors })),
+        err if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        err => {
+            error!("Transfer batch error ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
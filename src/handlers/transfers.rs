@@ -1,16 +1,20 @@
 use actix_web::{web, HttpResponse, Responder};
-use sea_orm::*;
 use serde_json::json;
 use tracing::{error, instrument};
-use validator::Validate;
-use rust_decimal::Decimal;
 use uuid::Uuid;
+use validator::Validate;
 
-use crate::dto::transfers::{ InitiateFundingBody, P2PTransferBody };
-use crate::entities::{ prelude::{ Users, Wallets }, users, wallets, sea_orm_active_enums::{ TrxType, Status } };
-use crate::utils::helpers::validate_password;
-use crate::utils::paystack::initiate_user_funding;
-use crate::service::transaction_balance::{ TransactionBalance, TrxCategory, TransactionBalanceTrait };
+use crate::dto::transfers::{ InitiateFundingBody, P2PTransferBody, WithdrawalBody };
+use crate::entities::{ sea_orm_active_enums::Status, users };
+use crate::utils::helpers::{ validate_password, validate_user_pin };
+use crate::utils::payment_provider::{ initialize_charge, ChargeRequest };
+use crate::service::outward_transfer::{
+    resolve_bank_account, OutwardTransfer, OutwardTransferError, OutwardTransferTrait
+};
+use crate::service::funding::{ find_funding, record_pending_funding, settle_funding };
+use crate::service::p2p_transfer::{ P2PTransfer, P2PTransferTrait };
+use crate::service::transaction_balance::TrxCategory;
+use crate::utils::payment_provider::provider_by_name;
 use crate::AppState;
 
 #[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid, amount = %body.amount))]
@@ -28,7 +32,7 @@ pub async fn fund_account(
         }
     };
 
-    if req_user.is_verified != 1 {
+    if !req_user.is_verified {
         return HttpResponse::BadRequest()
             .json(json!({ "status": "error",  "message": "Please verify your account before taking this action" }));
     }
@@ -43,19 +47,26 @@ pub async fn fund_account(
             .json(json!({ "status": "error",  "message": "Wrong password provided" }));
     }
 
-    let response = initiate_user_funding(
-        &req_user.email, 
-        &req_user.uuid, 
-        request_payload.amount,
-        &app_state.env
-    ).await;
+    let charge = ChargeRequest {
+        reference: Uuid::new_v4().to_string(),
+        email: req_user.email.to_string(),
+        user_id: req_user.uuid.to_string(),
+        amount: request_payload.amount.into(),
+    };
 
-    match response {
+    match initialize_charge(&charge, &app_state.env).await {
         Ok(response) => {
+            // The webhook still credits the charge if this fails, it just can't be polled for
+            if let Err(err) = record_pending_funding(
+                &app_state.db, &req_user, &response.provider, &response.reference, charge.amount
+            ).await {
+                error!("Error recording pending funding ===> {}", err);
+            }
+
             return HttpResponse::Ok().json(json!({
                 "status": "success",
                 "message": "Funding initiated successfully",
-                "data": response.data
+                "data": response
             }));
         }
         Err(err) => {
@@ -67,205 +78,180 @@ pub async fn fund_account(
     }
 }
 
-#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid, amount = %body.amount, receiver_id = %body.receiver_id))]
-pub async fn p2p_transfer(
-    body: web::Json<P2PTransferBody>, 
-    req_user: web::ReqData<users::Model>, 
+// Lets the app confirm a funding straight after checkout instead of waiting on the webhook
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn verify_funding(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
     app_state: web::Data<AppState>
 ) -> impl Responder {
-    let request_payload = match body.validate() {
-        Ok(_) => body.into_inner(),
+    let reference = path.into_inner();
+
+    let funding = match find_funding(&app_state.db, &reference).await {
+        Ok(Some(funding)) if funding.user_id == req_user.uuid && funding.category == TrxCategory::Funding.to_string() => funding,
+        Ok(_) => {
+            return HttpResponse::NotFound()
+                .json(json!({ "status": "error",  "message": "Funding not found" }));
+        }
         Err(err) => {
-            return HttpResponse::BadRequest().json(json!({
-                "status": "error", "message": "Validation errors", "data": err
-            }));
+            error!("Error fetching funding ===> {}", err);
+            return HttpResponse::InternalServerError()
+                .json(json!({ "status": "error",  "message": "Failed to fetch funding" }));
         }
     };
 
-    if req_user.is_verified != 1 {
-        return HttpResponse::BadRequest()
-            .json(json!({ "status": "error",  "message": "Please verify your account before taking this action" }));
-    }
-
-    if req_user.uuid == request_payload.receiver_id {
-        return HttpResponse::BadRequest()
-            .json(json!({ "status": "error",  "message": "Cannot send funds to yourself" }));
+    if funding.status == Some(Status::Successful) {
+        return HttpResponse::Ok().json(json!({
+            "status": "success", "message": "Funding fetched successfully", "data": { "transaction": funding }
+        }));
     }
 
-    let hashed_pin = match &req_user.withdrawal_pin {
-        Some(pin) => pin,
+    let provider = match provider_by_name(&funding.provider, &app_state.env) {
+        Some(provider) => provider,
         None => {
+            error!("Provider {} is not configured", funding.provider);
             return HttpResponse::BadRequest()
-                .json(json!({ "status": "error",  "message": "Please set your pin before taking this action" }));
+                .json(json!({ "status": "error",  "message": "Cannot verify funding at this time, Please try again later" }));
         }
     };
 
-    let is_valid_pin = validate_password(hashed_pin, &request_payload.pin, &app_state.env.hash_key);
-    if !is_valid_pin {
+    if let Err(err) = settle_funding(provider.as_ref(), &reference, &app_state.db).await {
+        error!("Error verifying funding ===> {}", err);
         return HttpResponse::BadRequest()
-            .json(json!({ "status": "error",  "message": "Incorrect PIN" }));
+            .json(json!({ "status": "error",  "message": "Cannot verify funding at this time, Please try again later" }));
     }
 
-    let receiver = Users::find()
-        .filter(users::Column::Uuid.eq(&request_payload.receiver_id))
-        .one(&app_state.db)
-        .await;
-
-    let receiver = match receiver {
-        Ok(Some(receiver)) => receiver,
-        Ok(None) => {
-            return HttpResponse::BadRequest()
-                .json(json!({ "status": "error", "message": "User you are trying to send funds to not found" }));
+    match find_funding(&app_state.db, &reference).await {
+        Ok(Some(funding)) => HttpResponse::Ok().json(json!({
+            "status": "success", "message": "Funding fetched successfully", "data": { "transaction": funding }
+        })),
+        Ok(None) => HttpResponse::NotFound()
+            .json(json!({ "status": "error",  "message": "Funding not found" })),
+        Err(err) => {
+            error!("Error fetching funding ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error",  "message": "Failed to fetch funding" }))
         }
+    }
+}
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid, amount = %body.amount, receiver_id = %body.receiver_id))]
+pub async fn p2p_transfer(
+    body: web::Json<P2PTransferBody>, 
+    req_user: web::ReqData<users::Model>, 
+    app_state: web::Data<AppState>
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
         Err(err) => {
-            error!("DB error validating receiver details ===> {}", err);
-            return HttpResponse::InternalServerError().json(
-                json!({ "status": "error", "message": "An error occured trying to validate receiver" }),
-            );
+            return HttpResponse::BadRequest().json(json!({
+                "status": "error", "message": "Validation errors", "data": err
+            }));
         }
     };
 
-    let sender_name = format!("{} {}", req_user.last_name, req_user.first_name);
-    let receiver_name = format!("{} {}", receiver.last_name, receiver.first_name);
+    if !req_user.is_verified {
+        return HttpResponse::BadRequest()
+            .json(json!({ "status": "error",  "message": "Please verify your account before taking this action" }));
+    }
 
-    if receiver.is_verified != 1 {
-        let msg = format!("Cannot send funds to {}, As they are not yet verified", &receiver_name);
+    if let Err(msg) = validate_user_pin(&req_user, &request_payload.pin, &app_state.env.hash_key) {
         return HttpResponse::BadRequest()
             .json(json!({ "status": "error",  "message": msg }));
     }
 
-    let amount: Decimal = request_payload.amount.into();
-    let txn = app_state.db
-        .begin_with_config(Some(IsolationLevel::RepeatableRead), Some(AccessMode::ReadWrite))
-        .await
-        .expect("Failed to start a DB transaction");
-
-    let user_wallets = Wallets::find()
-        .filter(
-            Condition::any()
-            .add(wallets::Column::UserId.eq(&req_user.uuid).and(wallets::Column::Default.eq(1)))
-            .add(wallets::Column::UserId.eq(&receiver.uuid).and(wallets::Column::Default.eq(1)))
-        )
-        .all(&txn)
-        .await;
-        
-    let user_wallets = match user_wallets {
-        Ok(user_wallets) => user_wallets,
-        Err(err) => {
-            error!("DB error fetching wallets ===> {}", err);
-            let _ = txn.rollback().await;
-            return HttpResponse::InternalServerError().json(
-                json!({ "status": "error", "message": "An unexpected error occured" }),
-            );
-        }
+    let p2p_transfer = P2PTransfer {
+        sender: req_user.into_inner(),
+        receiver_id: request_payload.receiver_id,
+        amount: request_payload.amount.into(),
+        narration: request_payload.narration,
     };
 
-    let sender_wallet = match user_wallets.iter().find(|wallet| wallet.user_id == req_user.uuid) {
-        Some(sender_wallet) => sender_wallet.to_owned(),
-        None => {
-            let _ = txn.rollback().await;
-            return HttpResponse::BadRequest().json(
-                json!({ "status": "error", "message": "You do not seem to have a valid wallet yet. Please contact support" }),
-            );
+    match p2p_transfer.transfer(&app_state.db).await {
+        Ok(_) => HttpResponse::Ok()
+            .json(json!({ "status": "success", "message": "Funds sent successfully" })),
+        Err(err) if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        Err(err) => {
+            error!("Error sending funds ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "Transfer Error" }))
         }
-    };
-
-    if amount > sender_wallet.current_balance {
-        let _ = txn.rollback().await;
-        return HttpResponse::BadRequest().json(
-            json!({ "status": "error", "message": "Insufficient Funds" }),
-        );
     }
+}
 
-    let receiver_wallet = match user_wallets.iter().find(|wallet| wallet.user_id == receiver.uuid) {
-        Some(receiver_wallet) => receiver_wallet.to_owned(),
-        None => {
-            let msg = format!("{} Does not have a valid wallet yet", &receiver_name);
-            let _ = txn.rollback().await;
-            return HttpResponse::BadRequest().json(
-                json!({ "status": "error", "message": msg }),
-            );
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid, amount = %body.amount))]
+pub async fn withdraw(
+    body: web::Json<WithdrawalBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest().json(json!({
+                "status": "error", "message": "Validation errors", "data": err
+            }));
         }
     };
 
-    if sender_wallet.uuid == receiver_wallet.uuid {
-        let _ = txn.rollback().await;
+    if !req_user.is_verified {
         return HttpResponse::BadRequest()
-            .json(json!({ "status": "error",  "message": "Cannot send funds to the same wallet" }));
+            .json(json!({ "status": "error",  "message": "Please verify your account before taking this action" }));
     }
 
-    let sender_ref = Uuid::new_v4();
-    let receiver_ref = Uuid::new_v4();
-    let narration = request_payload.narration.unwrap_or(String::from("Wallet Transfer"));
-
-    let meta = json!({
-        "sender_name": &sender_name,
-        "receiver_name": &receiver_name,
-        "sender_wallet_id": &sender_wallet.uuid,
-        "receiver_wallet_id": &receiver_wallet.uuid
-    }).to_string();
-
-
-    let debit_sender = TransactionBalance {
-        uuid: format!("{}", &sender_ref),
-        amount,
-        trx_type: TrxType::Debit,
-        status: Status::Successful,
-        description: format!("{} - TO {}", &narration, &receiver_name),
-        provider_reference: Some(format!("{}", &receiver_ref)),
-        current_balance: sender_wallet.current_balance - amount,
-        previous_balance: sender_wallet.current_balance,
-        user_id: format!("{}", &sender_wallet.user_id),
-        wallet_id: format!("{}", &sender_wallet.uuid),
-        provider: format!("money-transfer"),
-        fees: None,
-        provider_fees: None,
-        category: TrxCategory::P2P,
-        meta: Some(format!("{}", &meta)),
-    };
+    if let Err(msg) = validate_user_pin(&req_user, &request_payload.pin, &app_state.env.hash_key) {
+        return HttpResponse::BadRequest()
+            .json(json!({ "status": "error",  "message": msg }));
+    }
 
-    let _ = match debit_sender.save_transaction_update_balance(&txn).await {
-        Ok(resp) => resp,
+    let bank_account = match resolve_bank_account(
+        &request_payload.account_number,
+        &request_payload.bank_code,
+        &app_state.env
+    ).await {
+        Ok(bank_account) => bank_account,
+        Err(err) if err.is_client_error() => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": err.to_string() }));
+        }
         Err(err) => {
-            error!("DB error debiting sender: {}", err);
-            let _ = txn.rollback().await;
-            return HttpResponse::BadRequest().json(
-                json!({ "status": "error", "message": "Transfer Error" }),
-            );
+            error!("Error resolving bank account ===> {}", err);
+            return HttpResponse::BadRequest().json(json!({
+                "status": "error",  "message": "Cannot resolve bank account at this time, Please try again later"
+            }));
         }
     };
 
-    let credit_receiver = TransactionBalance {
-        uuid: format!("{}", &receiver_ref),
-        amount,
-        trx_type: TrxType::Credit,
-        status: Status::Successful,
-        description: format!("{} - FROM {}", &narration, &sender_name),
-        provider_reference: Some(format!("{}", &sender_ref)),
-        current_balance: receiver_wallet.current_balance + amount,
-        previous_balance: receiver_wallet.current_balance,
-        user_id: format!("{}", &receiver_wallet.user_id),
-        wallet_id: format!("{}", &receiver_wallet.uuid),
-        provider: format!("money-transfer"),
-        fees: None,
-        provider_fees: None,
-        category: TrxCategory::P2P,
-        meta: Some(format!("{}", &meta)),
+    let outward_transfer = OutwardTransfer {
+        sender: req_user.into_inner(),
+        bank_account,
+        amount: request_payload.amount.into(),
+        narration: request_payload.narration,
     };
 
-    let _ = match credit_receiver.save_transaction_update_balance(&txn).await {
-        Ok(resp) => resp,
+    match outward_transfer.transfer(&app_state).await {
+        Ok(reference) => HttpResponse::Ok().json(json!({
+            "status": "success", "message": "Withdrawal initiated successfully", "data": { "reference": reference }
+        })),
+        // The debit stays pending and is settled by the transfer webhook
+        Err(OutwardTransferError::ProviderUnavailable(err)) => {
+            error!("Error sending withdrawal to transfer provider ===> {}", err);
+            HttpResponse::Ok()
+                .json(json!({ "status": "success", "message": "Withdrawal is being processed" }))
+        }
+        Err(err) if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        Err(OutwardTransferError::ProviderError(msg)) => {
+            error!("Withdrawal rejected by transfer provider ===> {}", msg);
+            HttpResponse::BadRequest().json(json!({
+                "status": "error", "message": "Withdrawal failed and your wallet has been refunded"
+            }))
+        }
         Err(err) => {
-            error!("DB error crediting receiver: {}", err);
-            let _ = txn.rollback().await;
-            return HttpResponse::BadRequest().json(
-                json!({ "status": "error", "message": "Error trying to send funds to receiver" }),
-            );
+            error!("Error processing withdrawal ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "Withdrawal Error" }))
         }
-    };
-
-    let _ = txn.commit().await;
-
-    HttpResponse::Ok()
-        .json(json!({ "status": "success", "message": "Funds sent successfully" }))
+    }
 }
@@ -1,4 +1,4 @@
-use actix_web::{http, web, HttpResponse, Responder};
+use actix_web::{http, web, HttpRequest, HttpResponse, Responder};
 use argonautica::Hasher;
 use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
@@ -9,13 +9,19 @@ use uuid::Uuid;
 use validator::Validate;
 
 use crate::dto::users::{
-    LoginBody, SetWithdrawalPinBody, SignupBody, TokenClaims, VerifyAccountParams,
+    ForgotPasswordBody, LoginBody, ResetPasswordBody, SetWithdrawalPinBody, SignupBody,
+    TokenClaims, UpdateLocaleBody, UpdatePhoneNumberBody, VerifyAccountParams,
 };
-use crate::entities::{prelude::Users, users, wallets};
+use crate::entities::{
+    prelude::Users,
+    users::{self, UserRole},
+    wallets,
+};
+use crate::service::notification::{notify, NotificationEvent};
+use crate::service::virtual_account::ensure_virtual_account;
 use crate::utils::{
-    email_template::verify_account_template,
-    helpers::validate_password,
-    send_email::{SendEmail, SendEmailTrait},
+    email_template::{is_supported_locale, DEFAULT_LOCALE},
+    helpers::{client_ip, normalize_phone_number, validate_password},
 };
 use crate::AppState;
 
@@ -29,6 +35,15 @@ pub async fn signup(body: web::Json<SignupBody>, app_state: web::Data<AppState>)
         }
     };
 
+    let locale = user_payload
+        .locale
+        .unwrap_or(String::from(DEFAULT_LOCALE))
+        .to_lowercase();
+    if !is_supported_locale(&locale) {
+        return HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": "Unsupported locale" }));
+    }
+
     let lowercase_email = user_payload.email.to_lowercase();
     let check_user = Users::find()
         .filter(users::Column::Email.eq(&lowercase_email))
@@ -66,27 +81,22 @@ pub async fn signup(body: web::Json<SignupBody>, app_state: web::Data<AppState>)
         }
     };
 
+    let user_id = Uuid::new_v4().to_string();
     let new_user = users::ActiveModel {
-        uuid: Set(Uuid::new_v4().to_string()),
-        first_name: Set(format!("{}", &user_payload.first_name)),
-        last_name: Set(format!("{}", &user_payload.last_name)),
-        email: Set(format!("{}", &lowercase_email)),
+        uuid: Set(user_id.to_string()),
+        first_name: Set(user_payload.first_name.to_string()),
+        last_name: Set(user_payload.last_name.to_string()),
+        email: Set(lowercase_email.to_string()),
         password: Set(hashed_password),
+        role: Set(UserRole::User.to_string()),
+        locale: Set(locale),
         ..Default::default()
     };
 
-    let saved_user = new_user.insert(&app_state.db).await;
-    if let Err(err) = saved_user {
-        error!("Database error when trying to save user ===> {}", err);
-        return HttpResponse::InternalServerError().json(
-            json!({ "status": "error", "message": "An error occured trying to create user" }),
-        );
-    }
-
     // SIGN TOKEN FOR EMAIL VERIFICATION
     let now = Utc::now();
     let claims = TokenClaims {
-        sub: format!("{}", &lowercase_email),
+        sub: lowercase_email.to_string(),
         auth_type: String::from("ACCOUNT_VERIFICATION"),
         exp: (now + Duration::days(3)).timestamp() as usize,
         iat: now.timestamp() as usize,
@@ -102,22 +112,57 @@ pub async fn signup(body: web::Json<SignupBody>, app_state: web::Data<AppState>)
         String::new()
     });
 
-    let template = verify_account_template(&user_payload.first_name, &token, &app_state.env);
-    let email = SendEmail {
-        to: claims.sub,
-        from: format!("{}", &app_state.env.from_email),
-        subject: String::from("WELCOME, VERIFY YOUR ACCOUNT"),
-        template,
+    // The user and their verification email are saved together, the outbox worker sends it
+    let txn = match app_state.db.begin().await {
+        Ok(txn) => txn,
+        Err(err) => {
+            error!("Failed to start a DB transaction ===> {}", err);
+            return HttpResponse::InternalServerError().json(
+                json!({ "status": "error", "message": "An error occured trying to create user" }),
+            );
+        }
     };
 
-    let _ = email.send_email(&app_state.env).await;
+    if let Err(err) = new_user.insert(&txn).await {
+        error!("Database error when trying to save user ===> {}", err);
+        let _ = txn.rollback().await;
+        return HttpResponse::InternalServerError().json(
+            json!({ "status": "error", "message": "An error occured trying to create user" }),
+        );
+    }
+
+    let notification = notify(
+        &txn,
+        &user_id,
+        NotificationEvent::VerifyAccount,
+        json!({ "token": token }),
+    )
+    .await;
+    if let Err(err) = notification {
+        error!("Database error queueing verification email ===> {}", err);
+        let _ = txn.rollback().await;
+        return HttpResponse::InternalServerError().json(
+            json!({ "status": "error", "message": "An error occured trying to create user" }),
+        );
+    }
+
+    if let Err(err) = txn.commit().await {
+        error!("Database error when trying to save user ===> {}", err);
+        return HttpResponse::InternalServerError().json(
+            json!({ "status": "error", "message": "An error occured trying to create user" }),
+        );
+    }
 
     HttpResponse::Created()
         .json(json!({ "status": "success", "message": "User created successfully" }))
 }
 
-#[instrument(skip(body, app_state), fields(user_email = %body.email))]
-pub async fn login(body: web::Json<LoginBody>, app_state: web::Data<AppState>) -> impl Responder {
+#[instrument(skip(req, body, app_state), fields(user_email = %body.email))]
+pub async fn login(
+    req: HttpRequest,
+    body: web::Json<LoginBody>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
     let user_payload = match body.validate() {
         Ok(_) => body.into_inner(),
         Err(err) => {
@@ -177,6 +222,30 @@ pub async fn login(body: web::Json<LoginBody>, app_state: web::Data<AppState>) -
         }
     };
 
+    let user_agent = req
+        .headers()
+        .get(http::header::USER_AGENT)
+        .and_then(|user_agent| user_agent.to_str().ok())
+        .unwrap_or("Unknown device");
+    let ip_address = client_ip(&req, &app_state.env.trusted_proxies)
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "Unknown".to_string());
+    let login_details = json!({
+        "ip_address": ip_address,
+        "user_agent": user_agent,
+        "logged_in_at": now.format("%d %b %Y, %H:%M UTC").to_string(),
+    });
+    let notification = notify(
+        &app_state.db,
+        &check_user.uuid,
+        NotificationEvent::NewLogin,
+        login_details,
+    )
+    .await;
+    if let Err(err) = notification {
+        error!("Error queueing login notification ===> {}", err);
+    }
+
     HttpResponse::Ok().json(json!({
         "status": "success",
         "message": "Login successful",
@@ -244,13 +313,13 @@ pub async fn verify_account(
         }
     };
 
-    if check_user.is_verified == 1 {
+    if check_user.is_verified {
         return HttpResponse::Found()
             .insert_header((http::header::LOCATION, "https://github.com/Greatchinex"))
             .finish();
     }
 
-    let user_id = format!("{}", &check_user.uuid);
+    let user_id = check_user.uuid.to_string();
     let mut user: users::ActiveModel = check_user.into();
 
     let txn = app_state
@@ -262,9 +331,9 @@ pub async fn verify_account(
         .await
         .expect("Failed to start a DB transaction");
 
-    user.is_verified = Set(1);
+    user.is_verified = Set(true);
     user.updated_at = Set(Utc::now());
-    let _ = match user.update(&txn).await {
+    let verified_user = match user.update(&txn).await {
         Ok(user) => user,
         Err(err) => {
             error!("Failed to update user status for {}: {}", &claims.sub, err);
@@ -292,6 +361,14 @@ pub async fn verify_account(
 
     let _ = txn.commit().await;
 
+    // Verification doesn't hinge on the bank, the user can ask for the account again later
+    if let Err(err) = ensure_virtual_account(&app_state, &verified_user).await {
+        error!(
+            "Failed to create virtual account for {}: {}",
+            &claims.sub, err
+        );
+    }
+
     HttpResponse::Found()
         .insert_header((
             http::header::LOCATION,
@@ -325,19 +402,16 @@ pub async fn set_pin(
     }
 
     // If user is setting PIN for the first time or is changing an existing PIN
-    let is_pin_reset = match &req_user.withdrawal_pin {
-        Some(_) => true,
-        None => false,
-    };
+    let is_pin_reset = req_user.withdrawal_pin.is_some();
 
-    if is_pin_reset == true && request_payload.current_pin.is_none() {
+    if is_pin_reset && request_payload.current_pin.is_none() {
         return HttpResponse::BadRequest()
             .json(json!({ "status": "error",  "message": "Please pass your current PIN" }));
     }
 
-    if is_pin_reset == true {
+    if is_pin_reset {
         let hashed_pin = match &req_user.withdrawal_pin {
-            Some(hashed_pin) => format!("{}", hashed_pin),
+            Some(hashed_pin) => hashed_pin.to_string(),
             None => String::new(),
         };
 
@@ -358,7 +432,7 @@ pub async fn set_pin(
             &request_payload.new_pin,
             &app_state.env.hash_key,
         );
-        if check_new_pin == true {
+        if check_new_pin {
             return HttpResponse::BadRequest().json(
                 json!({ "status": "error",  "message": "New PIN cannot be the same as old PIN" }),
             );
@@ -380,22 +454,25 @@ pub async fn set_pin(
         }
     };
 
-    let user_id = format!("{}", &req_user.uuid);
-    let exec = app_state
-        .db
-        .execute(Statement::from_sql_and_values(
-            DbBackend::MySql,
-            r#"
-                UPDATE users
-                SET withdrawal_pin = ?, updated_at = ?
-                WHERE uuid = ?
-            ;"#,
-            [hashed_password.into(), Utc::now().into(), user_id.into()],
-        ))
-        .await;
+    let mut user: users::ActiveModel = req_user.into_inner().into();
+    user.withdrawal_pin = Set(Some(hashed_password));
+    user.updated_at = Set(Utc::now());
+    let exec = user.update(&app_state.db).await;
 
     match exec {
-        Ok(_) => {
+        Ok(user) => {
+            let changed_at = user.updated_at.format("%d %b %Y, %H:%M UTC").to_string();
+            let notification = notify(
+                &app_state.db,
+                &user.uuid,
+                NotificationEvent::PinChanged,
+                json!({ "changed_at": changed_at }),
+            )
+            .await;
+            if let Err(err) = notification {
+                error!("Error queueing PIN change notification ===> {}", err);
+            }
+
             return HttpResponse::Ok().json(json!({
                 "status": "success",
                 "message": "PIN set successfully"
@@ -409,3 +486,234 @@ pub async fn set_pin(
         }
     }
 }
+
+#[instrument(skip(body, app_state), fields(user_email = %body.email))]
+pub async fn forgot_password(
+    body: web::Json<ForgotPasswordBody>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    // Same answer whether or not the email has an account, so it can't be used to look up users
+    let reset_link_sent = || {
+        HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "If an account exists for this email, a password reset link has been sent to it"
+        }))
+    };
+
+    let check_user = Users::find()
+        .filter(users::Column::Email.eq(request_payload.email.to_lowercase()))
+        .one(&app_state.db)
+        .await;
+
+    let user = match check_user {
+        Ok(Some(user)) => user,
+        Ok(None) => return reset_link_sent(),
+        Err(err) => {
+            error!("Database error while trying to fetch a user ===> {}", err);
+            return HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }));
+        }
+    };
+
+    let now = Utc::now();
+    let claims = TokenClaims {
+        sub: user.uuid.to_string(),
+        auth_type: String::from("PASSWORD_RESET"),
+        exp: (now + Duration::hours(1)).timestamp() as usize,
+        iat: now.timestamp() as usize,
+    };
+
+    let token = match encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(password_reset_key(&app_state, &user).as_ref()),
+    ) {
+        Ok(token) => token,
+        Err(err) => {
+            error!("Error signing password reset token ===> {}", err);
+            return HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }));
+        }
+    };
+
+    let notification = notify(
+        &app_state.db,
+        &user.uuid,
+        NotificationEvent::PasswordReset,
+        json!({ "token": token }),
+    )
+    .await;
+    if let Err(err) = notification {
+        error!("Error queueing password reset email ===> {}", err);
+        return HttpResponse::InternalServerError()
+            .json(json!({ "status": "error", "message": "An unexpected error occured" }));
+    }
+
+    reset_link_sent()
+}
+
+pub async fn reset_password(
+    body: web::Json<ResetPasswordBody>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    let invalid_token = || {
+        HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": "Invalid or expired reset token" }))
+    };
+
+    // The token is signed with the password it resets, so see who it is for before checking it
+    let mut unverified = Validation::default();
+    unverified.insecure_disable_signature_validation();
+    let claims = match decode::<TokenClaims>(
+        &request_payload.token,
+        &DecodingKey::from_secret(&[]),
+        &unverified,
+    ) {
+        Ok(token) => token.claims,
+        Err(_) => return invalid_token(),
+    };
+
+    if claims.auth_type != "PASSWORD_RESET" {
+        return invalid_token();
+    }
+
+    let user = match Users::find()
+        .filter(users::Column::Uuid.eq(&claims.sub))
+        .one(&app_state.db)
+        .await
+    {
+        Ok(Some(user)) => user,
+        Ok(None) => return invalid_token(),
+        Err(err) => {
+            error!("Database error while trying to fetch a user ===> {}", err);
+            return HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }));
+        }
+    };
+
+    let verified = decode::<TokenClaims>(
+        &request_payload.token,
+        &DecodingKey::from_secret(password_reset_key(&app_state, &user).as_ref()),
+        &Validation::default(),
+    );
+    if verified.is_err() {
+        return invalid_token();
+    }
+
+    let mut hasher = Hasher::default();
+    let hashed_password = hasher
+        .with_password(request_payload.password)
+        .with_secret_key(&app_state.env.hash_key)
+        .hash();
+
+    let hashed_password = match hashed_password {
+        Ok(hashed_password) => hashed_password,
+        Err(err) => {
+            error!("Failed to hash password ===> {}", err);
+            return HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }));
+        }
+    };
+
+    let mut updated_user: users::ActiveModel = user.into();
+    updated_user.password = Set(hashed_password);
+    updated_user.updated_at = Set(Utc::now());
+
+    match updated_user.update(&app_state.db).await {
+        Ok(_) => HttpResponse::Ok()
+            .json(json!({ "status": "success", "message": "Password reset successfully" })),
+        Err(err) => {
+            error!("DB error resetting user password ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn update_locale(
+    body: web::Json<UpdateLocaleBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let locale = body.into_inner().locale.to_lowercase();
+    if !is_supported_locale(&locale) {
+        return HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": "Unsupported locale" }));
+    }
+
+    let mut user: users::ActiveModel = req_user.into_inner().into();
+    user.locale = Set(locale);
+    user.updated_at = Set(Utc::now());
+
+    match user.update(&app_state.db).await {
+        Ok(user) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Locale updated successfully",
+            "data": { "user": user.filter_response() }
+        })),
+        Err(err) => {
+            error!("DB error updating user locale ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn update_phone_number(
+    body: web::Json<UpdatePhoneNumberBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let phone_number = match body.into_inner().phone_number {
+        Some(phone_number) => match normalize_phone_number(&phone_number) {
+            Some(phone_number) => Some(phone_number),
+            None => {
+                return HttpResponse::BadRequest().json(json!({
+                    "status": "error",
+                    "message": "Phone number must be in international format, e.g. +2348012345678"
+                }));
+            }
+        },
+        None => None,
+    };
+
+    let mut user: users::ActiveModel = req_user.into_inner().into();
+    user.phone_number = Set(phone_number);
+    user.updated_at = Set(Utc::now());
+
+    match user.update(&app_state.db).await {
+        Ok(user) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Phone number updated successfully",
+            "data": { "user": user.filter_response() }
+        })),
+        Err(err) => {
+            error!("DB error updating user phone number ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
+
+// Changing the password changes the key, so a reset link only works once
+fn password_reset_key(app_state: &AppState, user: &users::Model) -> String {
+    format!("{}{}", app_state.env.app_key, user.password)
+}
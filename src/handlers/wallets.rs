@@ -1,9 +1,17 @@
 use actix_web::{web, HttpResponse, Responder};
 use sea_orm::*;
+use serde::Serialize;
 use serde_json::json;
 use tracing::{error, instrument};
 
-use crate::entities::{prelude::Wallets, users, wallets};
+use crate::dto::wallets::TransactionHistoryParams;
+use crate::entities::{
+    prelude::{Transactions, Wallets},
+    transactions, users,
+    virtual_accounts::VirtualAccountResponse,
+    wallets,
+};
+use crate::service::virtual_account::{ensure_virtual_account, user_virtual_accounts};
 use crate::AppState;
 
 #[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
@@ -25,9 +33,109 @@ pub async fn my_wallets(
         }
     };
 
+    let virtual_accounts = match user_virtual_accounts(&app_state.db, &req_user.uuid).await {
+        Ok(virtual_accounts) => virtual_accounts,
+        Err(err) => {
+            error!("Error retrieving user virtual accounts: {}", err);
+            return HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "Failed to fetch user wallets" }));
+        }
+    };
+
+    // Each wallet carries the bank accounts that fund it
+    let wallets: Vec<WalletResponse> = wallets
+        .into_iter()
+        .map(|wallet| WalletResponse {
+            virtual_accounts: virtual_accounts
+                .iter()
+                .filter(|account| account.wallet_id == wallet.uuid)
+                .map(|account| account.filter_response())
+                .collect(),
+            wallet,
+        })
+        .collect();
+
     HttpResponse::Ok().json(json!({
         "status": "success",
         "message": "Fetched user wallets",
         "data": { "wallets": wallets }
     }))
 }
+
+#[derive(Serialize)]
+struct WalletResponse {
+    #[serde(flatten)]
+    wallet: wallets::Model,
+    virtual_accounts: Vec<VirtualAccountResponse>,
+}
+
+// For users whose account couldn't be created when they verified
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn create_virtual_account(
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    if !req_user.is_verified {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "Please verify your account before taking this action"
+        }));
+    }
+
+    match ensure_virtual_account(&app_state, &req_user).await {
+        Ok(virtual_account) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Virtual account fetched successfully",
+            "data": { "virtual_account": virtual_account.filter_response() }
+        })),
+        Err(err) if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        Err(err) => {
+            error!("Error creating virtual account ===> {}", err);
+            HttpResponse::BadRequest().json(json!({
+                "status": "error",
+                "message": "Cannot create a virtual account at this time, Please try again later"
+            }))
+        }
+    }
+}
+
+const MAX_PER_PAGE: u64 = 100;
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_transactions(
+    params: web::Query<TransactionHistoryParams>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params.per_page.unwrap_or(20).clamp(1, MAX_PER_PAGE);
+
+    let paginator = Transactions::find()
+        .filter(transactions::Column::UserId.eq(&req_user.uuid))
+        .filter(transactions::Column::DeletedAt.is_null())
+        .order_by_desc(transactions::Column::CreatedAt)
+        .paginate(&app_state.db, per_page);
+
+    let transactions = match paginator.fetch_page(page - 1).await {
+        Ok(transactions) => transactions,
+        Err(err) => {
+            error!("Error retrieving user transactions: {}", err);
+            return HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "Failed to fetch transactions" }));
+        }
+    };
+
+    let total = paginator.num_items().await.unwrap_or_default();
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "message": "Fetched transactions",
+        "data": {
+            "transactions": transactions,
+            "page": page,
+            "per_page": per_page,
+            "total": total
+        }
+    }))
+}
@@ -0,0 +1,132 @@
+use actix_web::{web, HttpResponse, Responder};
+use serde_json::json;
+use tracing::{error, instrument};
+use validator::Validate;
+
+use crate::dto::webhook_subscriptions::CreateWebhookSubscriptionBody;
+use crate::entities::users;
+use crate::service::outbound_webhook::{
+    create_subscription, delete_subscription, redeliver, subscription_deliveries,
+    user_subscriptions, WebhookError,
+};
+use crate::AppState;
+
+#[instrument(skip(body, req_user, app_state), fields(user_id = %req_user.uuid, url = %body.url))]
+pub async fn add_webhook_subscription(
+    body: web::Json<CreateWebhookSubscriptionBody>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let request_payload = match body.validate() {
+        Ok(_) => body.into_inner(),
+        Err(err) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Validation errors", "data": err }));
+        }
+    };
+
+    let subscription = create_subscription(
+        &app_state.db,
+        &app_state.env,
+        &req_user.uuid,
+        &request_payload.url,
+        &request_payload.event_types,
+    )
+    .await;
+
+    match subscription {
+        // The secret is only ever shown here, it is what the subscriber verifies signatures with
+        Ok(subscription) => HttpResponse::Created().json(json!({
+            "status": "success",
+            "message": "Webhook subscription created successfully",
+            "data": {
+                "subscription": subscription.filter_response(),
+                "secret": subscription.secret
+            }
+        })),
+        Err(err) => webhook_error_response(err),
+    }
+}
+
+#[instrument(skip(req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn my_webhook_subscriptions(
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match user_subscriptions(&app_state.db, &req_user.uuid).await {
+        Ok(subscriptions) => {
+            let subscriptions: Vec<_> = subscriptions
+                .into_iter()
+                .map(|subscription| subscription.filter_response())
+                .collect();
+
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Fetched webhook subscriptions",
+                "data": { "subscriptions": subscriptions }
+            }))
+        }
+        Err(err) => webhook_error_response(err.into()),
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn remove_webhook_subscription(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match delete_subscription(&app_state.db, &req_user.uuid, &path.into_inner()).await {
+        Ok(_) => HttpResponse::Ok().json(
+            json!({ "status": "success", "message": "Webhook subscription deleted successfully" }),
+        ),
+        Err(err) => webhook_error_response(err),
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn webhook_deliveries(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match subscription_deliveries(&app_state.db, &req_user.uuid, &path.into_inner()).await {
+        Ok(deliveries) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Fetched webhook deliveries",
+            "data": { "deliveries": deliveries }
+        })),
+        Err(err) => webhook_error_response(err),
+    }
+}
+
+#[instrument(skip(path, req_user, app_state), fields(user_id = %req_user.uuid))]
+pub async fn redeliver_webhook(
+    path: web::Path<String>,
+    req_user: web::ReqData<users::Model>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    match redeliver(&app_state.db, &req_user.uuid, &path.into_inner()).await {
+        Ok(delivery) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Webhook delivery queued for redelivery",
+            "data": { "delivery": delivery }
+        })),
+        Err(err) => webhook_error_response(err),
+    }
+}
+
+fn webhook_error_response(err: WebhookError) -> HttpResponse {
+    match err {
+        WebhookError::SubscriptionNotFound | WebhookError::DeliveryNotFound => {
+            HttpResponse::NotFound().json(json!({ "status": "error", "message": err.to_string() }))
+        }
+        err if err.is_client_error() => HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": err.to_string() })),
+        err => {
+            error!("Error managing webhook subscriptions ===> {}", err);
+            HttpResponse::InternalServerError()
+                .json(json!({ "status": "error", "message": "An unexpected error occured" }))
+        }
+    }
+}
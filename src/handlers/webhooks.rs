@@ -1,49 +1,53 @@
 use actix_web::{web, HttpRequest, HttpResponse, Responder};
-use serde_json::{json, Value};
+use serde_json::json;
 use tracing::{error, instrument};
 
-use crate::service::paystack_webhook::handle_inflow_webhook;
-use crate::utils::helpers::validate_signature;
+use crate::service::provider_webhook::handle_webhook_event;
+use crate::utils::payment_provider::{provider_by_name, ProviderError};
 use crate::AppState;
 
-#[instrument(skip(req, app_state))]
-pub async fn paystack_webhook(
+// The signature is computed over the raw body, so it is taken as bytes rather than parsed JSON
+#[instrument(skip(req, body, app_state))]
+pub async fn provider_webhook(
+    path: web::Path<String>,
     req: HttpRequest,
-    body: web::Json<Value>,
+    body: web::Bytes,
     app_state: web::Data<AppState>,
 ) -> impl Responder {
+    let provider_name = path.into_inner();
+    let provider = match provider_by_name(&provider_name, &app_state.env) {
+        Some(provider) => provider,
+        None => {
+            return HttpResponse::NotFound()
+                .json(json!({ "status": "error", "message": "Unknown payment provider" }));
+        }
+    };
+
     let signature = req
         .headers()
-        .get("x-paystack-signature")
-        .expect("x-paystack-signature not sent in header payload")
-        .to_str()
-        .expect("Failed to parse signaure header to string");
-
-    // TODO: Properly look into below method
-    let is_valid_signature = validate_signature(
-        &body.to_string(),
-        &signature.to_string(),
-        &app_state.env.paystack_secret,
-    );
-    if !is_valid_signature {
-        return HttpResponse::Ok()
-            .json(json!({ "status": "success", "message": "Invlaid signature" }));
-    }
+        .get(provider.signature_header())
+        .and_then(|signature| signature.to_str().ok())
+        .unwrap_or_default();
 
-    let event_type = body["event"].as_str().unwrap_or_default().to_string();
+    let event = match provider.parse_webhook(signature, &body) {
+        Ok(event) => event,
+        Err(ProviderError::InvalidSignature) => {
+            return HttpResponse::Ok()
+                .json(json!({ "status": "success", "message": "Invlaid signature" }));
+        }
+        Err(err) => {
+            error!("Error parsing {} webhook: {}", provider_name, err);
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": "error", "message": "Invalid webhook payload" }));
+        }
+    };
 
-    if event_type == "charge.success" {
-        let _ = match handle_inflow_webhook(&body, &app_state).await {
-            Ok(bool) => bool,
-            Err(err) => {
-                error!("Error occured trying to fund user account: {}", err);
-                return HttpResponse::BadRequest().json(json!({
-                    "status": "error", "message": "Paystack webhook error"
-                }));
-            }
-        };
+    if let Err(err) = handle_webhook_event(provider.as_ref(), event, &app_state).await {
+        error!("Error occured handling {} webhook: {}", provider_name, err);
+        return HttpResponse::BadRequest()
+            .json(json!({ "status": "error", "message": "Payment provider webhook error" }));
     }
 
     HttpResponse::Ok()
-        .json(json!({ "status": "success", "message": "Paystack webhook successful" }))
+        .json(json!({ "status": "success", "message": "Payment provider webhook successful" }))
 }
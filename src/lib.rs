@@ -0,0 +1,74 @@
+use actix_web::{web, HttpResponse, Responder};
+use sea_orm::DatabaseConnection;
+use serde_json::json;
+
+use routes::admin::admin_route_group;
+use routes::api_keys::api_key_route_group;
+use routes::bills::bill_route_group;
+use routes::cards::card_route_group;
+use routes::disputes::dispute_route_group;
+use routes::escrows::escrow_route_group;
+use routes::invoices::invoice_route_group;
+use routes::merchants::merchant_route_group;
+use routes::notifications::notification_route_group;
+use routes::payment_links::payment_link_route_group;
+use routes::payment_methods::payment_method_route_group;
+use routes::payment_requests::payment_request_route_group;
+use routes::qr_payments::qr_payment_route_group;
+use routes::support::support_route_group;
+use routes::transfers::transfer_route_group;
+use routes::users::user_route_group;
+use routes::wallets::wallet_route_group;
+use routes::webhook_subscriptions::webhook_subscription_route_group;
+use routes::webhooks::webhook_route_group;
+use utils::config::EnvConfig;
+
+pub mod dto;
+pub mod entities;
+pub mod handlers;
+pub mod middlewares;
+pub mod routes;
+pub mod service;
+pub mod utils;
+
+#[derive(Debug, Clone)]
+pub struct AppState {
+    pub db: DatabaseConnection,
+    pub env: EnvConfig,
+}
+
+async fn health_checker() -> impl Responder {
+    HttpResponse::Ok()
+        .json(json!({ "status": "success", "message": "Welcome to MONEY TRANSFER APP" }))
+}
+
+async fn not_found() -> impl Responder {
+    HttpResponse::NotFound().json(
+        json!({ "status": "error", "message": "Oops! We can't find the url you are looking for" }),
+    )
+}
+
+// Registers every route group, shared by the server and the integration tests
+pub fn configure_app(conf: &mut web::ServiceConfig) {
+    conf.route("/", web::get().to(health_checker))
+        .configure(user_route_group)
+        .configure(wallet_route_group)
+        .configure(transfer_route_group)
+        .configure(payment_request_route_group)
+        .configure(payment_method_route_group)
+        .configure(bill_route_group)
+        .configure(dispute_route_group)
+        .configure(merchant_route_group)
+        .configure(payment_link_route_group)
+        .configure(invoice_route_group)
+        .configure(qr_payment_route_group)
+        .configure(escrow_route_group)
+        .configure(card_route_group)
+        .configure(notification_route_group)
+        .configure(webhook_route_group)
+        .configure(webhook_subscription_route_group)
+        .configure(api_key_route_group)
+        .configure(admin_route_group)
+        .configure(support_route_group)
+        .default_service(web::route().to(not_found));
+}
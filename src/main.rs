@@ -1,8 +1,7 @@
 use actix_cors::Cors;
-use actix_web::{web, App, HttpResponse, HttpServer, Responder};
+use actix_web::{web, App, HttpServer};
 use dotenv::dotenv;
-use sea_orm::{ConnectOptions, Database, DatabaseConnection};
-use serde_json::json;
+use sea_orm::{ConnectOptions, Database};
 use std::{io, process};
 use tracing::{error, info, log::LevelFilter};
 use tracing_actix_web::TracingLogger;
@@ -10,36 +9,19 @@ use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
 use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Registry};
 
-use routes::transfers::transfer_route_group;
-use routes::users::user_route_group;
-use routes::wallets::wallet_route_group;
-use routes::webhooks::webhook_route_group;
-use utils::config::EnvConfig;
-
-pub mod dto;
-pub mod entities;
-pub mod handlers;
-pub mod middlewares;
-pub mod routes;
-pub mod service;
-pub mod utils;
-
-#[derive(Debug, Clone)]
-pub struct AppState {
-    pub db: DatabaseConnection,
-    pub env: EnvConfig,
-}
-
-async fn health_checker() -> impl Responder {
-    HttpResponse::Ok()
-        .json(json!({ "status": "success", "message": "Welcome to MONEY TRANSFER APP" }))
-}
-
-async fn not_found() -> impl Responder {
-    HttpResponse::NotFound().json(
-        json!({ "status": "error", "message": "Oops! We can't find the url you are looking for" }),
-    )
-}
+use money_transfer::service::escrow::run_escrow_release_worker;
+use money_transfer::service::funding::run_pending_funding_worker;
+use money_transfer::service::outward_transfer::run_pending_transfer_worker;
+use money_transfer::service::invoice::run_invoice_reminder_worker;
+use money_transfer::service::outbox::run_outbox_worker;
+use money_transfer::service::payment_request::run_payment_request_expiry_worker;
+use money_transfer::service::scheduled_transfer::run_scheduled_transfer_worker;
+use money_transfer::service::transfer_batch::resume_transfer_batches;
+use money_transfer::service::wallet_reconciliation::{
+    reconcile_wallets, run_wallet_reconciliation_worker,
+};
+use money_transfer::utils::config::EnvConfig;
+use money_transfer::{configure_app, AppState};
 
 #[actix_web::main]
 async fn main() -> Result<(), anyhow::Error> {
@@ -49,7 +31,7 @@ async fn main() -> Result<(), anyhow::Error> {
 
     let (non_blocking_writer, _guard) = tracing_appender::non_blocking(io::stdout());
     let bunyan_formatting_layer =
-        BunyanFormattingLayer::new(format!("{}", &env.app_name), non_blocking_writer);
+        BunyanFormattingLayer::new(env.app_name.to_string(), non_blocking_writer);
 
     let subscriber = Registry::default()
         .with(EnvFilter::new("INFO"))
@@ -75,9 +57,32 @@ async fn main() -> Result<(), anyhow::Error> {
         }
     };
 
+    // `money-transfer reconcile-wallets [--freeze]` runs a single reconciliation and exits
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("reconcile-wallets") {
+        let freeze = args.iter().any(|arg| arg == "--freeze");
+        let report = reconcile_wallets(&pool, freeze).await?;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+
+        if report.mismatches.is_empty() {
+            return Ok(());
+        }
+        process::exit(2)
+    }
+
     info!("Starting server on port {}", &env.port);
 
     let app_state = AppState { db: pool, env };
+    actix_web::rt::spawn(run_scheduled_transfer_worker(app_state.clone()));
+    actix_web::rt::spawn(resume_transfer_batches(app_state.clone()));
+    actix_web::rt::spawn(run_wallet_reconciliation_worker(app_state.clone()));
+    actix_web::rt::spawn(run_pending_funding_worker(app_state.clone()));
+    actix_web::rt::spawn(run_pending_transfer_worker(app_state.clone()));
+    actix_web::rt::spawn(run_outbox_worker(app_state.clone()));
+    actix_web::rt::spawn(run_invoice_reminder_worker(app_state.clone()));
+    actix_web::rt::spawn(run_escrow_release_worker(app_state.clone()));
+    actix_web::rt::spawn(run_payment_request_expiry_worker(app_state.clone()));
+
     HttpServer::new(move || {
         let cors = Cors::default()
             .allowed_methods(vec!["GET", "POST", "PATCH", "PUT", "DELETE"])
@@ -85,12 +90,7 @@ async fn main() -> Result<(), anyhow::Error> {
 
         App::new()
             .app_data(web::Data::new(app_state.clone()))
-            .route("/", web::get().to(health_checker))
-            .configure(user_route_group)
-            .configure(wallet_route_group)
-            .configure(transfer_route_group)
-            .configure(webhook_route_group)
-            .default_service(web::route().to(not_found))
+            .configure(configure_app)
             .wrap(cors)
             .wrap(TracingLogger::default())
     })
@@ -0,0 +1,107 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    error::{ErrorForbidden, ErrorUnauthorized, PayloadError},
+    http, web, Error as ActixWebError, HttpMessage,
+};
+use actix_web_lab::middleware::Next;
+use futures::{future::ready, stream, Stream};
+use serde_json::json;
+use std::pin::Pin;
+use tracing::error;
+
+use super::auth::user_from_token;
+use crate::entities::users;
+use crate::service::api_key::{
+    authenticate_api_key, ApiCredentials, ApiKeyError, ApiKeyRequest, API_KEY_HEADER,
+    SECRET_KEY_PREFIX, SIGNATURE_HEADER, TIMESTAMP_HEADER,
+};
+use crate::utils::helpers::client_ip;
+use crate::AppState;
+
+// For the routes server-to-server clients call. Takes an API key, either the secret key as the
+// bearer token or a signed request, and a user's JWT otherwise
+pub async fn api_auth_middleware(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, ActixWebError> {
+    let user = match api_credentials(&req) {
+        Some(credentials) => user_from_api_key(&mut req, credentials).await?,
+        None => user_from_token(&req).await?,
+    };
+
+    req.extensions_mut().insert(user);
+
+    next.call(req).await
+}
+
+fn api_credentials(req: &ServiceRequest) -> Option<ApiCredentials> {
+    let header = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_string())
+    };
+
+    if let Some(publishable_key) = header(API_KEY_HEADER) {
+        return Some(ApiCredentials::Signed {
+            publishable_key,
+            timestamp: header(TIMESTAMP_HEADER).unwrap_or_default(),
+            signature: header(SIGNATURE_HEADER).unwrap_or_default(),
+        });
+    }
+
+    let authorization = header(http::header::AUTHORIZATION.as_str())?;
+    let token = authorization.strip_prefix("Bearer ")?.trim();
+    if token.starts_with(SECRET_KEY_PREFIX) {
+        Some(ApiCredentials::Secret(token.to_string()))
+    } else {
+        None
+    }
+}
+
+async fn user_from_api_key(
+    req: &mut ServiceRequest,
+    credentials: ApiCredentials,
+) -> Result<users::Model, ActixWebError> {
+    // Signatures cover the body, so it is read here and handed back for the handler to extract
+    let body = req.extract::<web::Bytes>().await?;
+    let replay: Pin<Box<dyn Stream<Item = Result<web::Bytes, PayloadError>>>> =
+        Box::pin(stream::once(ready(Ok(body.clone()))));
+    req.set_payload(Payload::from(replay));
+
+    let app_state = req.app_data::<web::Data<AppState>>().unwrap().clone();
+    let method = req.method().to_string();
+    let path = req
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| req.path().to_string());
+
+    let request = ApiKeyRequest {
+        credentials,
+        method: &method,
+        path: &path,
+        body: &body,
+        ip: client_ip(req.request(), &app_state.env.trusted_proxies),
+    };
+
+    match authenticate_api_key(&app_state.db, &request).await {
+        Ok(user) => Ok(user),
+        Err(ApiKeyError::DatabaseError(err)) => {
+            error!(
+                "ApiAuthMiddleware: DB error validating API key ===> {}",
+                err
+            );
+            Err(ErrorUnauthorized(
+                json!({ "status": "error", "message": "An unexpected error occured" }),
+            ))
+        }
+        Err(err) if err.is_forbidden() => Err(ErrorForbidden(
+            json!({ "status": "error", "message": err.to_string() }),
+        )),
+        Err(err) => Err(ErrorUnauthorized(
+            json!({ "status": "error", "message": err.to_string() }),
+        )),
+    }
+}
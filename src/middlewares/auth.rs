@@ -13,13 +13,24 @@ use tracing::error;
 use uuid::Uuid;
 
 use crate::dto::users::TokenClaims;
-use crate::entities::{prelude::Users, users::Column};
+use crate::entities::{
+    prelude::Users,
+    users::{self, Column},
+};
 use crate::AppState;
 
 pub async fn auth_middleware(
     req: ServiceRequest,
     next: Next<impl MessageBody>,
 ) -> Result<ServiceResponse<impl MessageBody>, ActixWebError> {
+    let user = user_from_token(&req).await?;
+    req.extensions_mut().insert(user);
+
+    next.call(req).await
+}
+
+// The user a "Bearer <JWT>" authorization header belongs to
+pub async fn user_from_token(req: &ServiceRequest) -> Result<users::Model, ActixWebError> {
     let authorization = req.headers().get(http::header::AUTHORIZATION);
     let authorization = match authorization {
         Some(auth) => auth,
@@ -65,24 +76,18 @@ pub async fn auth_middleware(
         .one(&app_state.db)
         .await;
 
-    let user = match user {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            return Err(ErrorUnauthorized(
-                json!({ "status": "error", "message": "Invalid user" }),
-            ));
-        }
+    match user {
+        Ok(Some(user)) => Ok(user),
+        Ok(None) => Err(ErrorUnauthorized(
+            json!({ "status": "error", "message": "Invalid user" }),
+        )),
         Err(err) => {
             error!("AuthMiddleware: DB error validating user ===> {}", err);
-            return Err(ErrorUnauthorized(
+            Err(ErrorUnauthorized(
                 json!({ "status": "error", "message": "An unexpected error occured" }),
-            ));
+            ))
         }
-    };
-
-    req.extensions_mut().insert(user);
-
-    next.call(req).await
+    }
 }
 
 pub struct AuthMiddleware {
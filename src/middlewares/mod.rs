@@ -1 +1,4 @@
+pub mod admin;
+pub mod api_key;
 pub mod auth;
+pub mod support;
@@ -0,0 +1,30 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    error::ErrorForbidden,
+    Error as ActixWebError, HttpMessage,
+};
+use actix_web_lab::middleware::Next;
+use serde_json::json;
+
+use crate::entities::users::{self, UserRole};
+
+// Support staff and admins. Must run after auth_middleware
+pub async fn support_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, ActixWebError> {
+    let is_support = req
+        .extensions()
+        .get::<users::Model>()
+        .map(|user| user.has_role(UserRole::Support) || user.has_role(UserRole::Admin))
+        .unwrap_or(false);
+
+    if !is_support {
+        return Err(ErrorForbidden(
+            json!({ "status": "error", "message": "You are not allowed to take this action" }),
+        ));
+    }
+
+    next.call(req).await
+}
@@ -0,0 +1,64 @@
+use actix_web::web::{get, post, scope, ServiceConfig};
+use actix_web_lab::middleware::from_fn;
+
+use crate::handlers::admin::{
+    dead_outbox_messages, list_reversals, reconcile_settlements, reconcile_wallet,
+    retry_outbox_message, reverse_transaction, unfreeze_wallet,
+};
+use crate::middlewares::{admin::admin_middleware, auth::auth_middleware};
+
+// Admin routes run auth_middleware first, then admin_middleware (last wrap runs first)
+pub fn admin_route_group(conf: &mut ServiceConfig) {
+    let scope = scope("/api/admin")
+        .route(
+            "/transactions/{id}/reverse",
+            post()
+                .to(reverse_transaction)
+                .wrap(from_fn(admin_middleware))
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/reversals",
+            get()
+                .to(list_reversals)
+                .wrap(from_fn(admin_middleware))
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/wallets/{id}/reconciliation",
+            get()
+                .to(reconcile_wallet)
+                .wrap(from_fn(admin_middleware))
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/settlements/paystack/reconcile",
+            post()
+                .to(reconcile_settlements)
+                .wrap(from_fn(admin_middleware))
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/wallets/{id}/unfreeze",
+            post()
+                .to(unfreeze_wallet)
+                .wrap(from_fn(admin_middleware))
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/outbox/dead",
+            get()
+                .to(dead_outbox_messages)
+                .wrap(from_fn(admin_middleware))
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/outbox/{id}/retry",
+            post()
+                .to(retry_outbox_message)
+                .wrap(from_fn(admin_middleware))
+                .wrap(from_fn(auth_middleware)),
+        );
+
+    conf.service(scope);
+}
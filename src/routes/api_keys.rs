@@ -0,0 +1,24 @@
+use actix_web::web::{delete, get, post, scope, ServiceConfig};
+use actix_web_lab::middleware::from_fn;
+
+use crate::handlers::api_keys::{add_api_key, my_api_keys, revoke_my_api_key, rotate_my_api_key};
+use crate::middlewares::auth::auth_middleware;
+
+// Managed with a user's JWT only, an API key can't mint or rotate keys
+pub fn api_key_route_group(conf: &mut ServiceConfig) {
+    let scope = scope("/api/api-keys")
+        .route("", post().to(add_api_key).wrap(from_fn(auth_middleware)))
+        .route("", get().to(my_api_keys).wrap(from_fn(auth_middleware)))
+        .route(
+            "/{id}/rotate",
+            post().to(rotate_my_api_key).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/{id}",
+            delete()
+                .to(revoke_my_api_key)
+                .wrap(from_fn(auth_middleware)),
+        );
+
+    conf.service(scope);
+}
@@ -0,0 +1,18 @@
+use actix_web::web::{get, patch, post, scope, ServiceConfig};
+use actix_web_lab::middleware::from_fn;
+
+use crate::handlers::bills::{cancel_bill, create_bill, get_bill, my_bills};
+use crate::middlewares::auth::auth_middleware;
+
+pub fn bill_route_group(conf: &mut ServiceConfig) {
+    let scope = scope("/api/bill")
+        .route("", post().to(create_bill).wrap(from_fn(auth_middleware)))
+        .route("", get().to(my_bills).wrap(from_fn(auth_middleware)))
+        .route("/{id}", get().to(get_bill).wrap(from_fn(auth_middleware)))
+        .route(
+            "/{id}/cancel",
+            patch().to(cancel_bill).wrap(from_fn(auth_middleware)),
+        );
+
+    conf.service(scope);
+}
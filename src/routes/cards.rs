@@ -0,0 +1,41 @@
+use actix_web::web::{get, patch, post, scope, ServiceConfig};
+use actix_web_lab::middleware::from_fn;
+
+use crate::handlers::cards::{
+    add_card, freeze_my_card, my_card, my_card_authorizations, my_cards, terminate_my_card,
+    unfreeze_my_card, update_card_spend_limit,
+};
+use crate::middlewares::auth::auth_middleware;
+
+pub fn card_route_group(conf: &mut ServiceConfig) {
+    let scope = scope("/api/cards")
+        .route("", post().to(add_card).wrap(from_fn(auth_middleware)))
+        .route("", get().to(my_cards).wrap(from_fn(auth_middleware)))
+        .route("/{id}", get().to(my_card).wrap(from_fn(auth_middleware)))
+        .route(
+            "/{id}/authorizations",
+            get()
+                .to(my_card_authorizations)
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/{id}/freeze",
+            patch().to(freeze_my_card).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/{id}/unfreeze",
+            patch().to(unfreeze_my_card).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/{id}/terminate",
+            patch().to(terminate_my_card).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/{id}/spend-limit",
+            patch()
+                .to(update_card_spend_limit)
+                .wrap(from_fn(auth_middleware)),
+        );
+
+    conf.service(scope);
+}
@@ -0,0 +1,17 @@
+use actix_web::web::{get, post, scope, ServiceConfig};
+use actix_web_lab::middleware::from_fn;
+
+use crate::handlers::disputes::{get_dispute, my_disputes, open_dispute};
+use crate::middlewares::auth::auth_middleware;
+
+pub fn dispute_route_group(conf: &mut ServiceConfig) {
+    let scope = scope("/api/dispute")
+        .route("", post().to(open_dispute).wrap(from_fn(auth_middleware)))
+        .route("", get().to(my_disputes).wrap(from_fn(auth_middleware)))
+        .route(
+            "/{id}",
+            get().to(get_dispute).wrap(from_fn(auth_middleware)),
+        );
+
+    conf.service(scope);
+}
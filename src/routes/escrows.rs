@@ -0,0 +1,33 @@
+use actix_web::web::{get, patch, post, scope, ServiceConfig};
+use actix_web_lab::middleware::from_fn;
+
+use crate::handlers::escrows::{
+    add_escrow, deliver_escrow, dispute_my_escrow, my_escrow, my_escrows, refund_my_escrow,
+    release_my_escrow,
+};
+use crate::middlewares::auth::auth_middleware;
+
+pub fn escrow_route_group(conf: &mut ServiceConfig) {
+    let scope = scope("/api/escrows")
+        .route("", post().to(add_escrow).wrap(from_fn(auth_middleware)))
+        .route("", get().to(my_escrows).wrap(from_fn(auth_middleware)))
+        .route("/{id}", get().to(my_escrow).wrap(from_fn(auth_middleware)))
+        .route(
+            "/{id}/deliver",
+            patch().to(deliver_escrow).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/{id}/release",
+            patch().to(release_my_escrow).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/{id}/dispute",
+            patch().to(dispute_my_escrow).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/{id}/refund",
+            patch().to(refund_my_escrow).wrap(from_fn(auth_middleware)),
+        );
+
+    conf.service(scope);
+}
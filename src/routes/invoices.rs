@@ -0,0 +1,29 @@
+use actix_web::web::{get, post, scope, ServiceConfig};
+use actix_web_lab::middleware::from_fn;
+
+use crate::handlers::invoices::{
+    download_invoice_pdf, invoice_payment_status, pay_invoice_by_card, pay_invoice_from_wallet,
+    view_invoice, view_invoice_document,
+};
+use crate::middlewares::auth::auth_middleware;
+
+// The hosted invoice the customer is emailed. Only paying from a wallet needs an account
+pub fn invoice_route_group(conf: &mut ServiceConfig) {
+    let scope = scope("/api/invoices")
+        .route("/{slug}", get().to(view_invoice))
+        .route("/{slug}/document", get().to(view_invoice_document))
+        .route("/{slug}/pdf", get().to(download_invoice_pdf))
+        .route(
+            "/{slug}/wallet",
+            post()
+                .to(pay_invoice_from_wallet)
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route("/{slug}/card", post().to(pay_invoice_by_card))
+        .route(
+            "/{slug}/payments/{reference}",
+            get().to(invoice_payment_status),
+        );
+
+    conf.service(scope);
+}
@@ -0,0 +1,67 @@
+use actix_web::web::{get, patch, post, scope, ServiceConfig};
+use actix_web_lab::middleware::from_fn;
+
+use crate::handlers::invoices::{add_invoice, cancel_my_invoice, my_invoice, my_invoices};
+use crate::handlers::merchants::{
+    add_merchant_profile, add_payment_link, disable_my_payment_link, my_merchant_profile,
+    my_payment_link_report, my_payment_links, update_merchant_profile,
+};
+use crate::middlewares::auth::auth_middleware;
+
+pub fn merchant_route_group(conf: &mut ServiceConfig) {
+    let scope = scope("/api/merchant")
+        .route(
+            "",
+            post()
+                .to(add_merchant_profile)
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "",
+            get().to(my_merchant_profile).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "",
+            patch()
+                .to(update_merchant_profile)
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/payment-links",
+            post().to(add_payment_link).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/payment-links",
+            get().to(my_payment_links).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/payment-links/{id}/disable",
+            patch()
+                .to(disable_my_payment_link)
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/payment-links/{id}/report",
+            get()
+                .to(my_payment_link_report)
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/invoices",
+            post().to(add_invoice).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/invoices",
+            get().to(my_invoices).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/invoices/{id}",
+            get().to(my_invoice).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/invoices/{id}/cancel",
+            patch().to(cancel_my_invoice).wrap(from_fn(auth_middleware)),
+        );
+
+    conf.service(scope);
+}
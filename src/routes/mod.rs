@@ -1,4 +1,19 @@
+pub mod admin;
+pub mod api_keys;
+pub mod bills;
+pub mod cards;
+pub mod disputes;
+pub mod escrows;
+pub mod invoices;
+pub mod merchants;
+pub mod notifications;
+pub mod payment_links;
+pub mod payment_methods;
+pub mod payment_requests;
+pub mod qr_payments;
+pub mod support;
 pub mod transfers;
 pub mod users;
 pub mod wallets;
+pub mod webhook_subscriptions;
 pub mod webhooks;
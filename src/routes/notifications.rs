@@ -0,0 +1,33 @@
+use actix_web::web::{delete, get, post, put, scope, ServiceConfig};
+use actix_web_lab::middleware::from_fn;
+
+use crate::handlers::notifications::{
+    add_device, delete_device, my_notification_preferences, update_notification_preference,
+};
+use crate::middlewares::auth::auth_middleware;
+
+pub fn notification_route_group(conf: &mut ServiceConfig) {
+    let scope = scope("/api/notifications")
+        .route(
+            "/preferences",
+            get()
+                .to(my_notification_preferences)
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/preferences",
+            put()
+                .to(update_notification_preference)
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/devices",
+            post().to(add_device).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/devices/{id}",
+            delete().to(delete_device).wrap(from_fn(auth_middleware)),
+        );
+
+    conf.service(scope);
+}
@@ -0,0 +1,26 @@
+use actix_web::web::{get, post, scope, ServiceConfig};
+use actix_web_lab::middleware::from_fn;
+
+use crate::handlers::payment_links::{
+    pay_link_with_card, pay_link_with_wallet, payment_link_payment_status, view_payment_link,
+};
+use crate::middlewares::auth::auth_middleware;
+
+// The hosted payment page. Only paying from a wallet needs an account
+pub fn payment_link_route_group(conf: &mut ServiceConfig) {
+    let scope = scope("/api/pay")
+        .route("/{slug}", get().to(view_payment_link))
+        .route(
+            "/{slug}/wallet",
+            post()
+                .to(pay_link_with_wallet)
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route("/{slug}/card", post().to(pay_link_with_card))
+        .route(
+            "/{slug}/payments/{reference}",
+            get().to(payment_link_payment_status),
+        );
+
+    conf.service(scope);
+}
@@ -0,0 +1,29 @@
+use actix_web::web::{delete, get, post, scope, ServiceConfig};
+use actix_web_lab::middleware::from_fn;
+
+use crate::handlers::payment_methods::{
+    fund_with_saved_card, my_payment_methods, remove_payment_method,
+};
+use crate::middlewares::auth::auth_middleware;
+
+pub fn payment_method_route_group(conf: &mut ServiceConfig) {
+    let scope = scope("/api/payment-methods")
+        .route(
+            "",
+            get().to(my_payment_methods).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/{id}",
+            delete()
+                .to(remove_payment_method)
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/{id}/fund",
+            post()
+                .to(fund_with_saved_card)
+                .wrap(from_fn(auth_middleware)),
+        );
+
+    conf.service(scope);
+}
@@ -0,0 +1,50 @@
+use actix_web::web::{get, patch, post, scope, ServiceConfig};
+use actix_web_lab::middleware::from_fn;
+
+use crate::handlers::payment_requests::{
+    accept_payment_request, cancel_payment_request, create_payment_request,
+    decline_payment_request, incoming_payment_requests, outgoing_payment_requests,
+};
+use crate::middlewares::auth::auth_middleware;
+
+pub fn payment_request_route_group(conf: &mut ServiceConfig) {
+    let scope = scope("/api/payment-request")
+        .route(
+            "",
+            post()
+                .to(create_payment_request)
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/incoming",
+            get()
+                .to(incoming_payment_requests)
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/outgoing",
+            get()
+                .to(outgoing_payment_requests)
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/{id}/accept",
+            post()
+                .to(accept_payment_request)
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/{id}/decline",
+            patch()
+                .to(decline_payment_request)
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/{id}/cancel",
+            patch()
+                .to(cancel_payment_request)
+                .wrap(from_fn(auth_middleware)),
+        );
+
+    conf.service(scope);
+}
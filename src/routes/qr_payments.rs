@@ -0,0 +1,47 @@
+use actix_web::web::{get, patch, post, scope, ServiceConfig};
+use actix_web_lab::middleware::from_fn;
+
+use crate::handlers::qr_payments::{
+    add_qr_code, cancel_my_qr_code, decode_qr, my_qr_code, my_qr_code_image, my_qr_codes,
+    my_static_qr, my_static_qr_image, pay_qr,
+};
+use crate::middlewares::auth::auth_middleware;
+
+pub fn qr_payment_route_group(conf: &mut ServiceConfig) {
+    let scope = scope("/api/qr")
+        .route(
+            "/static",
+            get().to(my_static_qr).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/static/image",
+            get().to(my_static_qr_image).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/dynamic",
+            post().to(add_qr_code).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/dynamic",
+            get().to(my_qr_codes).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/dynamic/{id}",
+            get().to(my_qr_code).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/dynamic/{id}/image",
+            get().to(my_qr_code_image).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/dynamic/{id}/cancel",
+            patch().to(cancel_my_qr_code).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/decode",
+            post().to(decode_qr).wrap(from_fn(auth_middleware)),
+        )
+        .route("/pay", post().to(pay_qr).wrap(from_fn(auth_middleware)));
+
+    conf.service(scope);
+}
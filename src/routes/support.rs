@@ -0,0 +1,57 @@
+use actix_web::web::{get, patch, post, scope, ServiceConfig};
+use actix_web_lab::middleware::from_fn;
+
+use crate::handlers::disputes::{
+    hold_dispute_funds, investigate_dispute, reject_dispute, resolve_dispute, support_disputes,
+};
+use crate::handlers::escrows::resolve_escrow;
+use crate::middlewares::{auth::auth_middleware, support::support_middleware};
+
+// Support routes run auth_middleware first, then support_middleware (last wrap runs first)
+pub fn support_route_group(conf: &mut ServiceConfig) {
+    let scope = scope("/api/support")
+        .route(
+            "/disputes",
+            get()
+                .to(support_disputes)
+                .wrap(from_fn(support_middleware))
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/disputes/{id}/investigate",
+            patch()
+                .to(investigate_dispute)
+                .wrap(from_fn(support_middleware))
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/disputes/{id}/hold",
+            post()
+                .to(hold_dispute_funds)
+                .wrap(from_fn(support_middleware))
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/disputes/{id}/resolve",
+            post()
+                .to(resolve_dispute)
+                .wrap(from_fn(support_middleware))
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/disputes/{id}/reject",
+            post()
+                .to(reject_dispute)
+                .wrap(from_fn(support_middleware))
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/escrows/{id}/resolve",
+            post()
+                .to(resolve_escrow)
+                .wrap(from_fn(support_middleware))
+                .wrap(from_fn(auth_middleware)),
+        );
+
+    conf.service(scope);
+}
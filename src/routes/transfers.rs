@@ -1,18 +1,81 @@
-use actix_web::web::{post, scope, ServiceConfig};
+use actix_web::web::{get, patch, post, scope, ServiceConfig};
 use actix_web_lab::middleware::from_fn;
 
-use crate::handlers::transfers::{fund_account, p2p_transfer};
-use crate::middlewares::auth::auth_middleware;
+use crate::handlers::scheduled_transfers::{
+    cancel_scheduled_transfer, create_scheduled_transfer, my_scheduled_transfers,
+    pause_scheduled_transfer, resume_scheduled_transfer,
+};
+use crate::handlers::transfer_batches::{
+    create_transfer_batch, get_transfer_batch, my_transfer_batches,
+};
+use crate::handlers::transfers::{fund_account, p2p_transfer, verify_funding, withdraw};
+use crate::middlewares::api_key::api_auth_middleware;
 
 pub fn transfer_route_group(conf: &mut ServiceConfig) {
     let scope = scope("/api/transfer")
         .route(
             "/fund-account",
-            post().to(fund_account).wrap(from_fn(auth_middleware)),
+            post().to(fund_account).wrap(from_fn(api_auth_middleware)),
+        )
+        .route(
+            "/fund-account/{reference}",
+            get().to(verify_funding).wrap(from_fn(api_auth_middleware)),
         )
         .route(
             "/p2p",
-            post().to(p2p_transfer).wrap(from_fn(auth_middleware)),
+            post().to(p2p_transfer).wrap(from_fn(api_auth_middleware)),
+        )
+        .route(
+            "/withdraw",
+            post().to(withdraw).wrap(from_fn(api_auth_middleware)),
+        )
+        .route(
+            "/batch",
+            post()
+                .to(create_transfer_batch)
+                .wrap(from_fn(api_auth_middleware)),
+        )
+        .route(
+            "/batch",
+            get()
+                .to(my_transfer_batches)
+                .wrap(from_fn(api_auth_middleware)),
+        )
+        .route(
+            "/batch/{id}",
+            get()
+                .to(get_transfer_batch)
+                .wrap(from_fn(api_auth_middleware)),
+        )
+        .route(
+            "/scheduled",
+            post()
+                .to(create_scheduled_transfer)
+                .wrap(from_fn(api_auth_middleware)),
+        )
+        .route(
+            "/scheduled",
+            get()
+                .to(my_scheduled_transfers)
+                .wrap(from_fn(api_auth_middleware)),
+        )
+        .route(
+            "/scheduled/{id}/pause",
+            patch()
+                .to(pause_scheduled_transfer)
+                .wrap(from_fn(api_auth_middleware)),
+        )
+        .route(
+            "/scheduled/{id}/resume",
+            patch()
+                .to(resume_scheduled_transfer)
+                .wrap(from_fn(api_auth_middleware)),
+        )
+        .route(
+            "/scheduled/{id}/cancel",
+            patch()
+                .to(cancel_scheduled_transfer)
+                .wrap(from_fn(api_auth_middleware)),
         );
 
     conf.service(scope);
@@ -1,7 +1,10 @@
-use actix_web::web::{get, post, scope, ServiceConfig};
+use actix_web::web::{get, post, put, scope, ServiceConfig};
 use actix_web_lab::middleware::from_fn;
 
-use crate::handlers::users::{login, me, set_pin, signup, verify_account};
+use crate::handlers::users::{
+    forgot_password, login, me, reset_password, set_pin, signup, update_locale,
+    update_phone_number, verify_account,
+};
 use crate::middlewares::auth::auth_middleware;
 
 pub fn user_route_group(conf: &mut ServiceConfig) {
@@ -10,6 +13,16 @@ pub fn user_route_group(conf: &mut ServiceConfig) {
         .route("/login", post().to(login))
         .route("/me", get().to(me).wrap(from_fn(auth_middleware)))
         .route("/verify-account", get().to(verify_account))
+        .route("/forgot-password", post().to(forgot_password))
+        .route("/reset-password", post().to(reset_password))
+        .route(
+            "/locale",
+            put().to(update_locale).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/phone",
+            put().to(update_phone_number).wrap(from_fn(auth_middleware)),
+        )
         .route(
             "/set-pin",
             post().to(set_pin).wrap(from_fn(auth_middleware)),
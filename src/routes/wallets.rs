@@ -1,14 +1,25 @@
-use actix_web::web::{get, scope, ServiceConfig};
+use actix_web::web::{get, post, scope, ServiceConfig};
 use actix_web_lab::middleware::from_fn;
 
-use crate::handlers::wallets::my_wallets;
-use crate::middlewares::auth::auth_middleware;
+use crate::handlers::wallets::{create_virtual_account, my_transactions, my_wallets};
+use crate::middlewares::api_key::api_auth_middleware;
 
 pub fn wallet_route_group(conf: &mut ServiceConfig) {
-    let scope = scope("/api/wallet").route(
-        "/my-wallets",
-        get().to(my_wallets).wrap(from_fn(auth_middleware)),
-    );
+    let scope = scope("/api/wallet")
+        .route(
+            "/my-wallets",
+            get().to(my_wallets).wrap(from_fn(api_auth_middleware)),
+        )
+        .route(
+            "/transactions",
+            get().to(my_transactions).wrap(from_fn(api_auth_middleware)),
+        )
+        .route(
+            "/virtual-account",
+            post()
+                .to(create_virtual_account)
+                .wrap(from_fn(api_auth_middleware)),
+        );
 
     conf.service(scope);
 }
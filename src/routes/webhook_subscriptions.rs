@@ -0,0 +1,40 @@
+use actix_web::web::{delete, get, post, scope, ServiceConfig};
+use actix_web_lab::middleware::from_fn;
+
+use crate::handlers::webhook_subscriptions::{
+    add_webhook_subscription, my_webhook_subscriptions, redeliver_webhook,
+    remove_webhook_subscription, webhook_deliveries,
+};
+use crate::middlewares::auth::auth_middleware;
+
+pub fn webhook_subscription_route_group(conf: &mut ServiceConfig) {
+    let scope = scope("/api/webhook-subscriptions")
+        .route(
+            "",
+            post()
+                .to(add_webhook_subscription)
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "",
+            get()
+                .to(my_webhook_subscriptions)
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/{id}",
+            delete()
+                .to(remove_webhook_subscription)
+                .wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/{id}/deliveries",
+            get().to(webhook_deliveries).wrap(from_fn(auth_middleware)),
+        )
+        .route(
+            "/deliveries/{id}/redeliver",
+            post().to(redeliver_webhook).wrap(from_fn(auth_middleware)),
+        );
+
+    conf.service(scope);
+}
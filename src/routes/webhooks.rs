@@ -1,9 +1,12 @@
 use actix_web::web::{post, scope, ServiceConfig};
 
-use crate::handlers::webhooks::paystack_webhook;
+use crate::handlers::cards::card_issuer_webhook;
+use crate::handlers::webhooks::provider_webhook;
 
 pub fn webhook_route_group(conf: &mut ServiceConfig) {
-    let scope = scope("/api/webhook").route("/paystack", post().to(paystack_webhook));
+    let scope = scope("/api/webhook")
+        .route("/cards/{issuer}", post().to(card_issuer_webhook))
+        .route("/{provider}", post().to(provider_webhook));
 
     conf.service(scope);
 }
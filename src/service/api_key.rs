@@ -0,0 +1,436 @@
+use chrono::Utc;
+use ring::hmac;
+use sea_orm::*;
+use serde::Serialize;
+use std::fmt;
+use std::net::IpAddr;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::entities::{
+    api_keys::{self, ApiKeyResponse},
+    prelude::{ApiKeys, Users},
+    users,
+};
+use crate::utils::helpers::{generate_token, hash_token};
+
+pub const SECRET_KEY_PREFIX: &str = "sk_";
+pub const PUBLISHABLE_KEY_PREFIX: &str = "pk_";
+pub const SIGNING_SECRET_PREFIX: &str = "sig_";
+
+// Signed requests send the publishable key, a unix timestamp and a hex encoded HMAC-SHA512 of
+// "{timestamp}.{METHOD}.{path and query}.{body}" with the key's signing secret
+pub const API_KEY_HEADER: &str = "x-api-key";
+pub const TIMESTAMP_HEADER: &str = "x-api-timestamp";
+pub const SIGNATURE_HEADER: &str = "x-api-signature";
+
+// How far a signed request's timestamp may be from ours, limiting how long it can be replayed
+const SIGNATURE_TOLERANCE_SECS: i64 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ApiScope {
+    WalletsRead,
+    WalletsWrite,
+    TransfersRead,
+    TransfersWrite,
+}
+
+impl ApiScope {
+    pub const ALL: [ApiScope; 4] = [
+        ApiScope::WalletsRead,
+        ApiScope::WalletsWrite,
+        ApiScope::TransfersRead,
+        ApiScope::TransfersWrite,
+    ];
+
+    pub fn parse(scope: &str) -> Option<ApiScope> {
+        ApiScope::ALL
+            .into_iter()
+            .find(|known| known.to_string() == scope)
+    }
+
+    // API keys only reach the wallet and transfer routes. Reads need the read scope, anything
+    // else the write scope
+    pub fn for_request(method: &str, path: &str) -> Option<ApiScope> {
+        let path = path.split('?').next().unwrap_or_default();
+        let read = method == "GET" || method == "HEAD";
+
+        if path == "/api/wallet" || path.starts_with("/api/wallet/") {
+            Some(if read {
+                ApiScope::WalletsRead
+            } else {
+                ApiScope::WalletsWrite
+            })
+        } else if path == "/api/transfer" || path.starts_with("/api/transfer/") {
+            Some(if read {
+                ApiScope::TransfersRead
+            } else {
+                ApiScope::TransfersWrite
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for ApiScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scope = match self {
+            ApiScope::WalletsRead => "wallets:read",
+            ApiScope::WalletsWrite => "wallets:write",
+            ApiScope::TransfersRead => "transfers:read",
+            ApiScope::TransfersWrite => "transfers:write",
+        };
+
+        write!(f, "{}", scope)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ApiKeyError {
+    #[error("At least one scope is required")]
+    NoScopes,
+
+    #[error("Unknown scope {0}")]
+    UnknownScope(String),
+
+    #[error("Invalid IP address {0}")]
+    InvalidIpAddress(String),
+
+    #[error("API key not found")]
+    KeyNotFound,
+
+    #[error("Failed to generate API key")]
+    SecretError,
+
+    #[error("Invalid API key")]
+    InvalidKey,
+
+    #[error("This API key only accepts signed requests")]
+    SignatureRequired,
+
+    #[error("Invalid or expired request signature")]
+    InvalidSignature,
+
+    #[error("API keys can't be used for this route")]
+    RouteNotAllowed,
+
+    #[error("This API key doesn't have the {0} scope")]
+    ScopeNotAllowed(ApiScope),
+
+    #[error("Requests from {0} are not allowed with this API key")]
+    IpNotAllowed(String),
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl ApiKeyError {
+    pub fn is_client_error(&self) -> bool {
+        matches!(
+            self,
+            ApiKeyError::NoScopes | ApiKeyError::UnknownScope(_) | ApiKeyError::InvalidIpAddress(_)
+        )
+    }
+
+    // Authenticated, but the key isn't allowed to make this request
+    pub fn is_forbidden(&self) -> bool {
+        matches!(
+            self,
+            ApiKeyError::RouteNotAllowed
+                | ApiKeyError::ScopeNotAllowed(_)
+                | ApiKeyError::IpNotAllowed(_)
+        )
+    }
+}
+
+// Only ever returned when a key is created or rotated, we keep a hash of the secret key
+#[derive(Serialize, Debug)]
+pub struct ApiKeyCredentials {
+    pub api_key: ApiKeyResponse,
+    pub secret_key: String,
+    pub signing_secret: String,
+}
+
+pub enum ApiCredentials {
+    // Sent as a bearer token
+    Secret(String),
+    Signed {
+        publishable_key: String,
+        timestamp: String,
+        signature: String,
+    },
+}
+
+pub struct ApiKeyRequest<'a> {
+    pub credentials: ApiCredentials,
+    pub method: &'a str,
+    // With the query string, it is part of what gets signed
+    pub path: &'a str,
+    pub body: &'a [u8],
+    pub ip: Option<IpAddr>,
+}
+
+fn new_secrets() -> Result<(String, String), ApiKeyError> {
+    let secret_key = generate_token(SECRET_KEY_PREFIX).ok_or(ApiKeyError::SecretError)?;
+    let signing_secret = generate_token(SIGNING_SECRET_PREFIX).ok_or(ApiKeyError::SecretError)?;
+
+    Ok((secret_key, signing_secret))
+}
+
+fn last4(secret_key: &str) -> String {
+    secret_key[secret_key.len() - 4..].to_string()
+}
+
+fn parse_scopes(scopes: &[String]) -> Result<String, ApiKeyError> {
+    let mut granted: Vec<String> = Vec::new();
+    for scope in scopes {
+        let scope = ApiScope::parse(scope)
+            .ok_or_else(|| ApiKeyError::UnknownScope(scope.to_string()))?
+            .to_string();
+        if !granted.contains(&scope) {
+            granted.push(scope);
+        }
+    }
+
+    if granted.is_empty() {
+        return Err(ApiKeyError::NoScopes);
+    }
+
+    Ok(granted.join(","))
+}
+
+fn parse_allowed_ips(allowed_ips: &[String]) -> Result<Option<String>, ApiKeyError> {
+    let mut allowed: Vec<String> = Vec::new();
+    for ip in allowed_ips {
+        let ip = ip
+            .trim()
+            .parse::<IpAddr>()
+            .map_err(|_| ApiKeyError::InvalidIpAddress(ip.to_string()))?
+            .to_string();
+        if !allowed.contains(&ip) {
+            allowed.push(ip);
+        }
+    }
+
+    if allowed.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(allowed.join(",")))
+    }
+}
+
+pub fn sign_request(
+    signing_secret: &str,
+    timestamp: &str,
+    method: &str,
+    path: &str,
+    body: &[u8],
+) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA512, signing_secret.as_bytes());
+    hex::encode(hmac::sign(&key, &signing_payload(timestamp, method, path, body)).as_ref())
+}
+
+fn signing_payload(timestamp: &str, method: &str, path: &str, body: &[u8]) -> Vec<u8> {
+    let mut payload = format!("{}.{}.{}.", timestamp, method, path).into_bytes();
+    payload.extend_from_slice(body);
+    payload
+}
+
+fn verify_signature(
+    key: &api_keys::Model,
+    request: &ApiKeyRequest,
+    timestamp: &str,
+    signature: &str,
+) -> bool {
+    let fresh = timestamp
+        .parse::<i64>()
+        .map(|timestamp| (Utc::now().timestamp() - timestamp).abs() <= SIGNATURE_TOLERANCE_SECS)
+        .unwrap_or(false);
+    let signature = match hex::decode(signature) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+
+    let hmac_key = hmac::Key::new(hmac::HMAC_SHA512, key.signing_secret.as_bytes());
+    let payload = signing_payload(timestamp, request.method, request.path, request.body);
+
+    fresh && hmac::verify(&hmac_key, &payload, &signature).is_ok()
+}
+
+pub async fn create_api_key<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+    name: &str,
+    scopes: &[String],
+    allowed_ips: &[String],
+    require_signature: bool,
+) -> Result<ApiKeyCredentials, ApiKeyError> {
+    let scopes = parse_scopes(scopes)?;
+    let allowed_ips = parse_allowed_ips(allowed_ips)?;
+    let publishable_key = generate_token(PUBLISHABLE_KEY_PREFIX).ok_or(ApiKeyError::SecretError)?;
+    let (secret_key, signing_secret) = new_secrets()?;
+
+    let api_key = api_keys::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        user_id: Set(user_id.to_string()),
+        name: Set(name.to_string()),
+        publishable_key: Set(publishable_key),
+        secret_hash: Set(hash_token(&secret_key)),
+        secret_last4: Set(last4(&secret_key)),
+        signing_secret: Set(signing_secret.to_string()),
+        scopes: Set(scopes),
+        allowed_ips: Set(allowed_ips),
+        require_signature: Set(require_signature),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    Ok(ApiKeyCredentials {
+        api_key: api_key.filter_response(),
+        secret_key,
+        signing_secret,
+    })
+}
+
+// Revoked keys are left out
+pub async fn user_api_keys<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+) -> Result<Vec<api_keys::Model>, DbErr> {
+    ApiKeys::find()
+        .filter(api_keys::Column::UserId.eq(user_id))
+        .filter(api_keys::Column::RevokedAt.is_null())
+        .order_by_asc(api_keys::Column::Id)
+        .all(db)
+        .await
+}
+
+async fn find_api_key<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+    key_id: &str,
+) -> Result<api_keys::Model, ApiKeyError> {
+    ApiKeys::find()
+        .filter(api_keys::Column::Uuid.eq(key_id))
+        .filter(api_keys::Column::UserId.eq(user_id))
+        .filter(api_keys::Column::RevokedAt.is_null())
+        .one(db)
+        .await?
+        .ok_or(ApiKeyError::KeyNotFound)
+}
+
+// New secret key and signing secret, the old ones stop working straight away. The publishable key,
+// scopes and allowlist stay as they are
+pub async fn rotate_api_key<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+    key_id: &str,
+) -> Result<ApiKeyCredentials, ApiKeyError> {
+    let api_key = find_api_key(db, user_id, key_id).await?;
+    let (secret_key, signing_secret) = new_secrets()?;
+
+    let mut rotated: api_keys::ActiveModel = api_key.into();
+    rotated.secret_hash = Set(hash_token(&secret_key));
+    rotated.secret_last4 = Set(last4(&secret_key));
+    rotated.signing_secret = Set(signing_secret.to_string());
+    rotated.updated_at = Set(Utc::now());
+    let rotated = rotated.update(db).await?;
+
+    Ok(ApiKeyCredentials {
+        api_key: rotated.filter_response(),
+        secret_key,
+        signing_secret,
+    })
+}
+
+pub async fn revoke_api_key<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+    key_id: &str,
+) -> Result<(), ApiKeyError> {
+    let api_key = find_api_key(db, user_id, key_id).await?;
+
+    let mut revoked: api_keys::ActiveModel = api_key.into();
+    revoked.revoked_at = Set(Some(Utc::now()));
+    revoked.updated_at = Set(Utc::now());
+    revoked.update(db).await?;
+
+    Ok(())
+}
+
+// Resolves the user an API key acts for, checking the key is live, signed if it has to be,
+// allowed this route and called from an allowed address
+pub async fn authenticate_api_key<C: ConnectionTrait>(
+    db: &C,
+    request: &ApiKeyRequest<'_>,
+) -> Result<users::Model, ApiKeyError> {
+    let api_key = match &request.credentials {
+        ApiCredentials::Secret(secret_key) => {
+            ApiKeys::find()
+                .filter(api_keys::Column::SecretHash.eq(hash_token(secret_key)))
+                .one(db)
+                .await?
+        }
+        ApiCredentials::Signed {
+            publishable_key, ..
+        } => {
+            ApiKeys::find()
+                .filter(api_keys::Column::PublishableKey.eq(publishable_key))
+                .one(db)
+                .await?
+        }
+    };
+
+    let api_key = match api_key {
+        Some(api_key) if api_key.revoked_at.is_none() => api_key,
+        _ => return Err(ApiKeyError::InvalidKey),
+    };
+
+    match &request.credentials {
+        ApiCredentials::Secret(_) if api_key.require_signature => {
+            return Err(ApiKeyError::SignatureRequired)
+        }
+        ApiCredentials::Signed {
+            timestamp,
+            signature,
+            ..
+        } if !verify_signature(&api_key, request, timestamp, signature) => {
+            return Err(ApiKeyError::InvalidSignature)
+        }
+        _ => {}
+    }
+
+    let scope =
+        ApiScope::for_request(request.method, request.path).ok_or(ApiKeyError::RouteNotAllowed)?;
+    if !api_key.has_scope(&scope.to_string()) {
+        return Err(ApiKeyError::ScopeNotAllowed(scope));
+    }
+
+    let ip = request.ip.map(|ip| ip.to_string());
+    let allowed_ips = api_key.allowed_ips();
+    if !allowed_ips.is_empty() {
+        match &ip {
+            Some(ip) if allowed_ips.contains(ip) => {}
+            _ => {
+                return Err(ApiKeyError::IpNotAllowed(
+                    ip.unwrap_or_else(|| String::from("an unknown address")),
+                ))
+            }
+        }
+    }
+
+    let user = Users::find()
+        .filter(users::Column::Uuid.eq(&api_key.user_id))
+        .one(db)
+        .await?
+        .ok_or(ApiKeyError::InvalidKey)?;
+
+    let mut used: api_keys::ActiveModel = api_key.into();
+    used.last_used_at = Set(Some(Utc::now()));
+    used.last_used_ip = Set(ip);
+    used.update(db).await?;
+
+    Ok(user)
+}
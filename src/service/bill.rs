@@ -0,0 +1,447 @@
+use chrono::{Duration, Utc};
+use rust_decimal::{Decimal, RoundingStrategy};
+use sea_orm::*;
+use std::fmt;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::entities::{
+    bill_participants, bills, payment_requests,
+    prelude::{BillParticipants, Bills, PaymentRequests, Users},
+    users,
+};
+
+use super::payment_request::PaymentRequestStatus;
+
+// Payment requests raised for a bill are valid for 7 days
+const BILL_REQUEST_EXPIRY_HOURS: i64 = 168;
+
+#[derive(Debug, PartialEq)]
+pub enum BillStatus {
+    Open,
+    Settled,
+    // Every participant has responded but only some of them paid
+    PartiallySettled,
+    // Every participant has responded and none of them paid
+    Closed,
+    Cancelled,
+}
+
+impl fmt::Display for BillStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            BillStatus::Open => "open",
+            BillStatus::Settled => "settled",
+            BillStatus::PartiallySettled => "partially_settled",
+            BillStatus::Closed => "closed",
+            BillStatus::Cancelled => "cancelled",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BillParticipantStatus {
+    Pending,
+    Paid,
+    Declined,
+    Cancelled,
+    Expired,
+}
+
+impl fmt::Display for BillParticipantStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            BillParticipantStatus::Pending => "pending",
+            BillParticipantStatus::Paid => "paid",
+            BillParticipantStatus::Declined => "declined",
+            BillParticipantStatus::Cancelled => "cancelled",
+            BillParticipantStatus::Expired => "expired",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SplitType {
+    Equal,
+    Custom,
+}
+
+impl fmt::Display for SplitType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let split_type = match self {
+            SplitType::Equal => "equal",
+            SplitType::Custom => "custom",
+        };
+
+        write!(f, "{}", split_type)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum BillError {
+    #[error("Bill not found")]
+    NotFound,
+
+    #[error("Bill is already {0}")]
+    NotOpen(String),
+
+    #[error("Participant {0} not found")]
+    ParticipantNotFound(String),
+
+    #[error("Cannot split a bill with {0}, As they are not yet verified")]
+    ParticipantNotVerified(String),
+
+    #[error("You cannot add yourself as a participant")]
+    CreatorAsParticipant,
+
+    #[error("Participant {0} was added more than once")]
+    DuplicateParticipant(String),
+
+    #[error("{0}")]
+    InvalidShares(String),
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl BillError {
+    pub fn is_client_error(&self) -> bool {
+        !matches!(self, BillError::DatabaseError(_))
+    }
+}
+
+// A participant as sent by the client, identified either by uuid or email
+pub struct BillParticipantInput {
+    pub user_id: Option<String>,
+    pub email: Option<String>,
+    pub amount: Option<Decimal>,
+}
+
+pub struct NewBill {
+    pub title: String,
+    pub total_amount: Decimal,
+    pub split_type: SplitType,
+    pub participants: Vec<BillParticipantInput>,
+}
+
+// Splits the total into `count` shares rounded to kobo, any remainder goes to the first share
+pub fn split_equally(total: Decimal, count: usize) -> Vec<Decimal> {
+    if count == 0 {
+        return vec![];
+    }
+
+    let share = (total / Decimal::from(count)).round_dp_with_strategy(2, RoundingStrategy::ToZero);
+    let mut shares = vec![share; count];
+    shares[0] += total - share * Decimal::from(count);
+
+    shares
+}
+
+async fn resolve_participant<C: ConnectionTrait>(
+    db: &C,
+    participant: &BillParticipantInput,
+) -> Result<users::Model, BillError> {
+    let (column, identifier) = match (&participant.user_id, &participant.email) {
+        (Some(user_id), _) => (users::Column::Uuid, user_id.to_string()),
+        (None, Some(email)) => (users::Column::Email, email.to_lowercase()),
+        (None, None) => {
+            return Err(BillError::InvalidShares(String::from(
+                "Each participant must have either a user_id or an email",
+            )))
+        }
+    };
+
+    let user = Users::find().filter(column.eq(&identifier)).one(db).await?;
+
+    match user {
+        Some(user) if !user.is_verified => Err(BillError::ParticipantNotVerified(format!(
+            "{} {}",
+            user.last_name, user.first_name
+        ))),
+        Some(user) => Ok(user),
+        None => Err(BillError::ParticipantNotFound(identifier)),
+    }
+}
+
+// Creates the bill and raises a payment request against every participant for their share
+pub async fn create_bill(
+    txn: &DatabaseTransaction,
+    creator: &users::Model,
+    new_bill: NewBill,
+) -> Result<(bills::Model, Vec<bill_participants::Model>), BillError> {
+    let mut participants: Vec<users::Model> = vec![];
+    for participant in new_bill.participants.iter() {
+        let user = resolve_participant(txn, participant).await?;
+
+        if user.uuid == creator.uuid {
+            return Err(BillError::CreatorAsParticipant);
+        }
+        if participants.iter().any(|p| p.uuid == user.uuid) {
+            return Err(BillError::DuplicateParticipant(user.email));
+        }
+        participants.push(user);
+    }
+
+    let shares = match new_bill.split_type {
+        SplitType::Equal => split_equally(new_bill.total_amount, participants.len()),
+        SplitType::Custom => {
+            let shares: Vec<Decimal> = new_bill
+                .participants
+                .iter()
+                .map(|participant| participant.amount.unwrap_or_default())
+                .collect();
+
+            if shares.iter().any(|share| *share <= Decimal::ZERO) {
+                return Err(BillError::InvalidShares(String::from(
+                    "Every participant must have an amount greater than zero for a custom split",
+                )));
+            }
+
+            let sum: Decimal = shares.iter().sum();
+            if sum != new_bill.total_amount {
+                return Err(BillError::InvalidShares(format!(
+                    "Participant amounts add up to {} instead of the bill total of {}",
+                    sum, new_bill.total_amount
+                )));
+            }
+            shares
+        }
+    };
+
+    if shares.iter().any(|share| *share < Decimal::new(1, 2)) {
+        return Err(BillError::InvalidShares(String::from(
+            "Bill total is too small to be split between all participants",
+        )));
+    }
+
+    let bill = bills::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        creator_id: Set(creator.uuid.to_string()),
+        title: Set(new_bill.title.to_string()),
+        total_amount: Set(new_bill.total_amount),
+        split_type: Set(new_bill.split_type.to_string()),
+        status: Set(BillStatus::Open.to_string()),
+        ..Default::default()
+    }
+    .insert(txn)
+    .await?;
+
+    let expires_at = Utc::now() + Duration::hours(BILL_REQUEST_EXPIRY_HOURS);
+    let mut bill_participants: Vec<bill_participants::Model> = vec![];
+    for (participant, share) in participants.iter().zip(shares) {
+        let payment_request = payment_requests::ActiveModel {
+            uuid: Set(Uuid::new_v4().to_string()),
+            requester_id: Set(creator.uuid.to_string()),
+            payer_id: Set(participant.uuid.to_string()),
+            amount: Set(share),
+            narration: Set(Some(format!("Bill: {}", &new_bill.title))),
+            status: Set(PaymentRequestStatus::Pending.to_string()),
+            expires_at: Set(expires_at),
+            ..Default::default()
+        }
+        .insert(txn)
+        .await?;
+
+        let bill_participant = bill_participants::ActiveModel {
+            uuid: Set(Uuid::new_v4().to_string()),
+            bill_id: Set(bill.uuid.to_string()),
+            user_id: Set(participant.uuid.to_string()),
+            amount: Set(share),
+            status: Set(BillParticipantStatus::Pending.to_string()),
+            payment_request_id: Set(payment_request.uuid),
+            ..Default::default()
+        }
+        .insert(txn)
+        .await?;
+
+        bill_participants.push(bill_participant);
+    }
+
+    Ok((bill, bill_participants))
+}
+
+// Called once a payment request has been settled. No-op for requests that are not part of a bill
+pub async fn record_request_paid(
+    txn: &DatabaseTransaction,
+    request: &payment_requests::Model,
+) -> Result<(), DbErr> {
+    let participant = BillParticipants::find()
+        .filter(bill_participants::Column::PaymentRequestId.eq(&request.uuid))
+        .one(txn)
+        .await?;
+
+    let participant = match participant {
+        Some(participant) => participant,
+        None => return Ok(()),
+    };
+
+    let now = Utc::now();
+    let bill_id = participant.bill_id.to_string();
+    let mut paid_participant: bill_participants::ActiveModel = participant.into();
+    paid_participant.status = Set(BillParticipantStatus::Paid.to_string());
+    paid_participant.paid_at = Set(Some(now));
+    paid_participant.updated_at = Set(now);
+    paid_participant.update(txn).await?;
+
+    Bills::update_many()
+        .col_expr(
+            bills::Column::AmountPaid,
+            sea_query::Expr::col(bills::Column::AmountPaid).add(request.amount),
+        )
+        .col_expr(bills::Column::UpdatedAt, sea_query::Expr::value(now))
+        .filter(bills::Column::Uuid.eq(&bill_id))
+        .exec(txn)
+        .await?;
+
+    finalize_bill(txn, &bill_id).await?;
+
+    Ok(())
+}
+
+// Called when a payment request is declined, cancelled or expires without being paid
+pub async fn record_request_closed<C: ConnectionTrait>(
+    db: &C,
+    request: &payment_requests::Model,
+    status: BillParticipantStatus,
+) -> Result<(), DbErr> {
+    let participant = BillParticipants::find()
+        .filter(bill_participants::Column::PaymentRequestId.eq(&request.uuid))
+        .one(db)
+        .await?;
+
+    let participant = match participant {
+        Some(participant) => participant,
+        None => return Ok(()),
+    };
+
+    BillParticipants::update_many()
+        .col_expr(
+            bill_participants::Column::Status,
+            sea_query::Expr::value(status.to_string()),
+        )
+        .col_expr(
+            bill_participants::Column::UpdatedAt,
+            sea_query::Expr::value(Utc::now()),
+        )
+        .filter(bill_participants::Column::Uuid.eq(&participant.uuid))
+        .filter(bill_participants::Column::Status.eq(BillParticipantStatus::Pending.to_string()))
+        .exec(db)
+        .await?;
+
+    finalize_bill(db, &participant.bill_id).await
+}
+
+// Once no participant is left pending the bill can't collect anything more, so it is moved to
+// settled, partially_settled or closed depending on how many of them paid
+async fn finalize_bill<C: ConnectionTrait>(db: &C, bill_id: &String) -> Result<(), DbErr> {
+    let participants = BillParticipants::find()
+        .filter(bill_participants::Column::BillId.eq(bill_id))
+        .all(db)
+        .await?;
+
+    let pending = BillParticipantStatus::Pending.to_string();
+    if participants
+        .iter()
+        .any(|participant| participant.status == pending)
+    {
+        return Ok(());
+    }
+
+    let paid = BillParticipantStatus::Paid.to_string();
+    let paid_count = participants
+        .iter()
+        .filter(|participant| participant.status == paid)
+        .count();
+    let status = match paid_count {
+        0 => BillStatus::Closed,
+        count if count == participants.len() => BillStatus::Settled,
+        _ => BillStatus::PartiallySettled,
+    };
+
+    Bills::update_many()
+        .col_expr(
+            bills::Column::Status,
+            sea_query::Expr::value(status.to_string()),
+        )
+        .col_expr(bills::Column::UpdatedAt, sea_query::Expr::value(Utc::now()))
+        .filter(bills::Column::Uuid.eq(bill_id))
+        .filter(bills::Column::Status.eq(BillStatus::Open.to_string()))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+// Cancels an open bill along with every payment request that has not been paid yet
+pub async fn cancel_bill(
+    txn: &DatabaseTransaction,
+    creator: &users::Model,
+    bill_id: &String,
+) -> Result<bills::Model, BillError> {
+    let bill = Bills::find()
+        .filter(bills::Column::Uuid.eq(bill_id))
+        .filter(bills::Column::CreatorId.eq(&creator.uuid))
+        .filter(bills::Column::DeletedAt.is_null())
+        .one(txn)
+        .await?;
+
+    let bill = match bill {
+        Some(bill) => bill,
+        None => return Err(BillError::NotFound),
+    };
+
+    if bill.status != BillStatus::Open.to_string() {
+        return Err(BillError::NotOpen(bill.status));
+    }
+
+    let now = Utc::now();
+    let pending_participants = BillParticipants::find()
+        .filter(bill_participants::Column::BillId.eq(&bill.uuid))
+        .filter(bill_participants::Column::Status.eq(BillParticipantStatus::Pending.to_string()))
+        .all(txn)
+        .await?;
+
+    let request_ids: Vec<String> = pending_participants
+        .iter()
+        .map(|participant| participant.payment_request_id.to_string())
+        .collect();
+
+    PaymentRequests::update_many()
+        .col_expr(
+            payment_requests::Column::Status,
+            sea_query::Expr::value(PaymentRequestStatus::Cancelled.to_string()),
+        )
+        .col_expr(
+            payment_requests::Column::UpdatedAt,
+            sea_query::Expr::value(now),
+        )
+        .filter(payment_requests::Column::Uuid.is_in(request_ids))
+        .filter(payment_requests::Column::Status.eq(PaymentRequestStatus::Pending.to_string()))
+        .exec(txn)
+        .await?;
+
+    BillParticipants::update_many()
+        .col_expr(
+            bill_participants::Column::Status,
+            sea_query::Expr::value(BillParticipantStatus::Cancelled.to_string()),
+        )
+        .col_expr(
+            bill_participants::Column::UpdatedAt,
+            sea_query::Expr::value(now),
+        )
+        .filter(bill_participants::Column::BillId.eq(&bill.uuid))
+        .filter(bill_participants::Column::Status.eq(BillParticipantStatus::Pending.to_string()))
+        .exec(txn)
+        .await?;
+
+    let mut cancelled_bill: bills::ActiveModel = bill.into();
+    cancelled_bill.status = Set(BillStatus::Cancelled.to_string());
+    cancelled_bill.updated_at = Set(now);
+    let cancelled_bill = cancelled_bill.update(txn).await?;
+
+    Ok(cancelled_bill)
+}
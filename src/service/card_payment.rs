@@ -0,0 +1,266 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use std::fmt;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::entities::{invoice_payments, payment_link_payments};
+use crate::utils::config::EnvConfig;
+use crate::utils::payment_provider::{
+    provider_by_name, ChargeStatus, ChargeVerification, PaymentProvider, ProviderError,
+};
+use crate::AppState;
+
+use super::funding::{FundingOutcome, StaleFundingSummary};
+use super::merchant::LinkPaymentStatus;
+
+// The columns card payment tables share, so claiming and failing a payment is written once
+pub trait CardPaymentTable: EntityTrait {
+    const ID: Self::Column;
+    const REFERENCE: Self::Column;
+    const STATUS: Self::Column;
+    const AMOUNT: Self::Column;
+    const FEE: Self::Column;
+    const TRANSACTION_ID: Self::Column;
+    const PAID_AT: Self::Column;
+    const CREATED_AT: Self::Column;
+    const UPDATED_AT: Self::Column;
+}
+
+// A payment a customer makes to a merchant by card, e.g on a payment link or an invoice. What
+// differs between them is what the merchant's credit counts against
+#[async_trait]
+pub trait MerchantCardPayment: Sized + Send + Sync + 'static {
+    type Table: CardPaymentTable<Model = Self>;
+    type Error: From<DbErr> + From<ProviderError> + fmt::Display + Send;
+
+    // For logs, e.g "invoice payment"
+    const KIND: &'static str;
+
+    fn id(&self) -> i32;
+    fn reference(&self) -> &str;
+    fn status(&self) -> &str;
+    fn provider(&self) -> &str;
+    fn amount(&self) -> Decimal;
+    fn fee(&self) -> Decimal;
+    fn created_at(&self) -> DateTime<Utc>;
+
+    fn not_found() -> Self::Error;
+
+    // The payment as claimed, with what was actually charged
+    fn settled(self, amount: Decimal, fee: Decimal, transaction_id: String) -> Self;
+
+    // Credits the merchant for a payment that was just claimed, in the same transaction
+    async fn credit(
+        txn: &DatabaseTransaction,
+        env: &EnvConfig,
+        payment: &Self,
+        verification: &ChargeVerification,
+    ) -> Result<(), Self::Error>;
+}
+
+pub async fn find_card_payment<P: MerchantCardPayment, C: ConnectionTrait>(
+    db: &C,
+    reference: &str,
+) -> Result<Option<P>, DbErr> {
+    P::Table::find()
+        .filter(P::Table::REFERENCE.eq(reference))
+        .one(db)
+        .await
+}
+
+pub async fn mark_card_payment_failed<P: MerchantCardPayment, C: ConnectionTrait>(
+    db: &C,
+    payment: &P,
+) -> Result<bool, DbErr> {
+    let updated = P::Table::update_many()
+        .col_expr(
+            P::Table::STATUS,
+            sea_query::Expr::value(LinkPaymentStatus::Failed.to_string()),
+        )
+        .col_expr(P::Table::UPDATED_AT, sea_query::Expr::value(Utc::now()))
+        .filter(P::Table::ID.eq(payment.id()))
+        .filter(P::Table::STATUS.eq(LinkPaymentStatus::Pending.to_string()))
+        .exec(db)
+        .await?;
+
+    Ok(updated.rows_affected > 0)
+}
+
+// Like fundings, card payments are verified with the provider and only ever credited here, so
+// the webhook, the payer checking on their payment and the polling job can't credit twice
+pub async fn settle_card_payment<P: MerchantCardPayment>(
+    provider: &dyn PaymentProvider,
+    reference: &str,
+    db: &DatabaseConnection,
+    env: &EnvConfig,
+) -> Result<FundingOutcome, P::Error> {
+    let payment = find_card_payment::<P, _>(db, reference)
+        .await?
+        .ok_or_else(P::not_found)?;
+    if payment.status() != LinkPaymentStatus::Pending.to_string() {
+        return Ok(FundingOutcome::AlreadySettled);
+    }
+
+    let verification = provider.verify_charge(reference).await?;
+    match verification.status {
+        ChargeStatus::Successful if !verification.amount.is_zero() => {}
+        ChargeStatus::Failed => {
+            mark_card_payment_failed(db, &payment).await?;
+            return Ok(FundingOutcome::Failed);
+        }
+        _ => return Ok(FundingOutcome::Pending),
+    }
+
+    let txn = db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadWrite),
+        )
+        .await?;
+
+    match credit_card_payment(&txn, env, payment, &verification).await {
+        Ok(FundingOutcome::Credited) => {
+            txn.commit().await?;
+            Ok(FundingOutcome::Credited)
+        }
+        Ok(outcome) => {
+            let _ = txn.rollback().await;
+            Ok(outcome)
+        }
+        Err(err) => {
+            error!("Error crediting {} {} ===> {}", P::KIND, reference, err);
+            let _ = txn.rollback().await;
+            Err(err)
+        }
+    }
+}
+
+// The pending payment is claimed first so a concurrent settlement finds nothing left to credit
+async fn credit_card_payment<P: MerchantCardPayment>(
+    txn: &DatabaseTransaction,
+    env: &EnvConfig,
+    payment: P,
+    verification: &ChargeVerification,
+) -> Result<FundingOutcome, P::Error> {
+    let mut amount = payment.amount();
+    if verification.amount != amount {
+        warn!(
+            "Charge for {} {} was for {} instead of {}",
+            P::KIND,
+            payment.reference(),
+            verification.amount,
+            amount
+        );
+        amount = verification.amount;
+    }
+    let fee = payment.fee().min(amount);
+
+    let now = Utc::now();
+    let transaction_id = Uuid::new_v4().to_string();
+    let claimed = P::Table::update_many()
+        .col_expr(
+            P::Table::STATUS,
+            sea_query::Expr::value(LinkPaymentStatus::Successful.to_string()),
+        )
+        .col_expr(P::Table::AMOUNT, sea_query::Expr::value(amount))
+        .col_expr(P::Table::FEE, sea_query::Expr::value(fee))
+        .col_expr(
+            P::Table::TRANSACTION_ID,
+            sea_query::Expr::value(transaction_id.to_string()),
+        )
+        .col_expr(P::Table::PAID_AT, sea_query::Expr::value(now))
+        .col_expr(P::Table::UPDATED_AT, sea_query::Expr::value(now))
+        .filter(P::Table::ID.eq(payment.id()))
+        .filter(P::Table::STATUS.eq(LinkPaymentStatus::Pending.to_string()))
+        .exec(txn)
+        .await?;
+    if claimed.rows_affected == 0 {
+        return Ok(FundingOutcome::AlreadySettled);
+    }
+
+    let payment = payment.settled(amount, fee, transaction_id);
+    P::credit(txn, env, &payment, verification).await?;
+
+    Ok(FundingOutcome::Credited)
+}
+
+// Payment link and invoice payments whose webhook never arrived, polled with the same windows
+// as fundings
+pub async fn process_stale_card_payments(
+    app_state: &AppState,
+) -> Result<StaleFundingSummary, DbErr> {
+    let now = Utc::now();
+    let stale_before = now - Duration::minutes(app_state.env.funding_stale_after_mins);
+    let abandon_before = now - Duration::minutes(app_state.env.funding_abandon_after_mins);
+
+    let mut summary = StaleFundingSummary::default();
+    process_stale::<payment_link_payments::Model>(
+        app_state,
+        stale_before,
+        abandon_before,
+        &mut summary,
+    )
+    .await?;
+    process_stale::<invoice_payments::Model>(app_state, stale_before, abandon_before, &mut summary)
+        .await?;
+
+    Ok(summary)
+}
+
+// Verified again like stale fundings, those still unpaid past the abandon window are marked failed
+async fn process_stale<P: MerchantCardPayment>(
+    app_state: &AppState,
+    stale_before: DateTime<Utc>,
+    abandon_before: DateTime<Utc>,
+    summary: &mut StaleFundingSummary,
+) -> Result<(), DbErr> {
+    let (db, env) = (&app_state.db, &app_state.env);
+
+    let stale_payments = P::Table::find()
+        .filter(P::Table::STATUS.eq(LinkPaymentStatus::Pending.to_string()))
+        .filter(P::Table::CREATED_AT.lte(stale_before))
+        .order_by_asc(P::Table::ID)
+        .all(db)
+        .await?;
+
+    for payment in stale_payments {
+        summary.checked += 1;
+
+        let outcome = match provider_by_name(payment.provider(), env) {
+            Some(provider) => {
+                settle_card_payment::<P>(provider.as_ref(), payment.reference(), db, env).await
+            }
+            None => {
+                warn!(
+                    "Provider {} is not configured, cannot verify {} {}",
+                    payment.provider(),
+                    P::KIND,
+                    payment.reference()
+                );
+                Ok(FundingOutcome::Pending)
+            }
+        };
+
+        match outcome {
+            Ok(FundingOutcome::Credited) => summary.credited += 1,
+            Ok(FundingOutcome::Failed) => summary.failed += 1,
+            Ok(FundingOutcome::Pending) if payment.created_at() <= abandon_before => {
+                if mark_card_payment_failed(db, &payment).await? {
+                    summary.abandoned += 1;
+                }
+            }
+            Ok(_) => {}
+            Err(err) => error!(
+                "Error verifying {} {} ===> {}",
+                P::KIND,
+                payment.reference(),
+                err
+            ),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,377 @@
+use chrono::{Duration, Utc};
+use sea_orm::*;
+use serde_json::json;
+use std::fmt;
+use thiserror::Error;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::entities::{
+    disputes,
+    prelude::{Disputes, Transactions, Wallets},
+    sea_orm_active_enums::{Status, TrxType},
+    transactions, users, wallets,
+};
+use crate::utils::config::EnvConfig;
+use crate::AppState;
+
+use super::notification::{notify, NotificationEvent};
+use super::reversal::{reverse_transaction, ReversalError, ReversalRequest};
+use super::transaction_balance::TrxCategory;
+use super::wallet_hold::{place_hold, release_hold};
+
+// Support must pick up a dispute within a day and close it within three
+const FIRST_RESPONSE_SLA_HOURS: i64 = 24;
+const RESOLUTION_SLA_HOURS: i64 = 72;
+
+#[derive(Debug, PartialEq)]
+pub enum DisputeStatus {
+    Open,
+    Investigating,
+    Resolved,
+    Rejected,
+}
+
+impl fmt::Display for DisputeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            DisputeStatus::Open => "open",
+            DisputeStatus::Investigating => "investigating",
+            DisputeStatus::Resolved => "resolved",
+            DisputeStatus::Rejected => "rejected",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DisputeParty {
+    Customer,
+    Counterparty,
+}
+
+impl fmt::Display for DisputeParty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let party = match self {
+            DisputeParty::Customer => "customer",
+            DisputeParty::Counterparty => "counterparty",
+        };
+
+        write!(f, "{}", party)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DisputeError {
+    #[error("Dispute not found")]
+    NotFound,
+
+    #[error("Transaction not found")]
+    TransactionNotFound,
+
+    #[error("{0}")]
+    NotDisputable(String),
+
+    #[error("There is already an open dispute on this transaction")]
+    AlreadyDisputed,
+
+    #[error("Dispute is already {0}")]
+    InvalidState(String),
+
+    #[error("Dispute has no counterparty wallet to hold")]
+    NoCounterparty,
+
+    #[error("Funds have already been held for this dispute")]
+    HoldAlreadyPlaced,
+
+    #[error(transparent)]
+    ReversalError(#[from] ReversalError),
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl DisputeError {
+    pub fn is_client_error(&self) -> bool {
+        match self {
+            DisputeError::ReversalError(err) => err.is_client_error(),
+            DisputeError::DatabaseError(_) => false,
+            _ => true,
+        }
+    }
+}
+
+pub struct NewDispute {
+    pub transaction_id: String,
+    pub reason: String,
+    pub attachment_reference: Option<String>,
+}
+
+fn active_statuses() -> Vec<String> {
+    vec![
+        DisputeStatus::Open.to_string(),
+        DisputeStatus::Investigating.to_string(),
+    ]
+}
+
+// Customers can dispute transfers they sent and wallet fundings
+pub async fn open_dispute<C: ConnectionTrait>(
+    db: &C,
+    user: &users::Model,
+    new_dispute: NewDispute,
+) -> Result<disputes::Model, DisputeError> {
+    let transaction = Transactions::find()
+        .filter(transactions::Column::Uuid.eq(&new_dispute.transaction_id))
+        .filter(transactions::Column::UserId.eq(&user.uuid))
+        .filter(transactions::Column::DeletedAt.is_null())
+        .one(db)
+        .await?;
+
+    let transaction = match transaction {
+        Some(transaction) => transaction,
+        None => return Err(DisputeError::TransactionNotFound),
+    };
+
+    if transaction.status != Some(Status::Successful) {
+        return Err(DisputeError::NotDisputable(String::from(
+            "Only successful transactions can be disputed",
+        )));
+    }
+
+    let is_p2p_debit = transaction.category == TrxCategory::P2P.to_string()
+        && transaction.trx_type == Some(TrxType::Debit);
+    let is_funding = transaction.category == TrxCategory::Funding.to_string();
+
+    let counterparty_id = if is_p2p_debit {
+        let counterpart = Transactions::find()
+            .filter(transactions::Column::Uuid.eq(transaction.provider_reference.clone()))
+            .one(db)
+            .await?;
+        counterpart.map(|counterpart| counterpart.user_id)
+    } else if is_funding {
+        None
+    } else {
+        return Err(DisputeError::NotDisputable(String::from(
+            "Only transfers you sent and wallet fundings can be disputed",
+        )));
+    };
+
+    let existing_dispute = Disputes::find()
+        .filter(disputes::Column::TransactionId.eq(&transaction.uuid))
+        .filter(disputes::Column::Status.is_in(active_statuses()))
+        .one(db)
+        .await?;
+    if existing_dispute.is_some() {
+        return Err(DisputeError::AlreadyDisputed);
+    }
+
+    let now = Utc::now();
+    let dispute = disputes::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        transaction_id: Set(transaction.uuid),
+        user_id: Set(user.uuid.to_string()),
+        counterparty_id: Set(counterparty_id),
+        amount: Set(transaction.amount),
+        reason: Set(new_dispute.reason),
+        attachment_reference: Set(new_dispute.attachment_reference),
+        status: Set(DisputeStatus::Open.to_string()),
+        first_response_due_at: Set(now + Duration::hours(FIRST_RESPONSE_SLA_HOURS)),
+        resolution_due_at: Set(now + Duration::hours(RESOLUTION_SLA_HOURS)),
+        ..Default::default()
+    };
+
+    Ok(dispute.insert(db).await?)
+}
+
+async fn find_active_dispute<C: ConnectionTrait>(
+    db: &C,
+    dispute_id: &String,
+) -> Result<disputes::Model, DisputeError> {
+    let dispute = Disputes::find()
+        .filter(disputes::Column::Uuid.eq(dispute_id))
+        .filter(disputes::Column::DeletedAt.is_null())
+        .one(db)
+        .await?;
+
+    let dispute = match dispute {
+        Some(dispute) => dispute,
+        None => return Err(DisputeError::NotFound),
+    };
+
+    if !active_statuses().contains(&dispute.status) {
+        return Err(DisputeError::InvalidState(dispute.status));
+    }
+
+    Ok(dispute)
+}
+
+// Any support action counts as the first response and claims the dispute if unassigned
+fn record_support_action(
+    dispute: disputes::Model,
+    support: &users::Model,
+) -> disputes::ActiveModel {
+    let now = Utc::now();
+    let first_responded_at = dispute.first_responded_at.unwrap_or(now);
+    let assigned_to = dispute
+        .assigned_to
+        .clone()
+        .unwrap_or(support.uuid.to_string());
+
+    let mut updated_dispute: disputes::ActiveModel = dispute.into();
+    updated_dispute.first_responded_at = Set(Some(first_responded_at));
+    updated_dispute.assigned_to = Set(Some(assigned_to));
+    updated_dispute.updated_at = Set(now);
+    updated_dispute
+}
+
+pub async fn start_investigation<C: ConnectionTrait>(
+    db: &C,
+    support: &users::Model,
+    dispute_id: &String,
+) -> Result<disputes::Model, DisputeError> {
+    let dispute = find_active_dispute(db, dispute_id).await?;
+    if dispute.status != DisputeStatus::Open.to_string() {
+        return Err(DisputeError::InvalidState(dispute.status));
+    }
+
+    let mut updated_dispute = record_support_action(dispute, support);
+    updated_dispute.status = Set(DisputeStatus::Investigating.to_string());
+    updated_dispute.assigned_to = Set(Some(support.uuid.to_string()));
+
+    Ok(updated_dispute.update(db).await?)
+}
+
+// Stops the counterparty from spending the disputed amount while the dispute is looked into
+pub async fn hold_counterparty_funds<C: ConnectionTrait>(
+    db: &C,
+    support: &users::Model,
+    dispute_id: &String,
+) -> Result<disputes::Model, DisputeError> {
+    let dispute = find_active_dispute(db, dispute_id).await?;
+    if dispute.hold_id.is_some() {
+        return Err(DisputeError::HoldAlreadyPlaced);
+    }
+
+    let counterparty_id = match &dispute.counterparty_id {
+        Some(counterparty_id) => counterparty_id,
+        None => return Err(DisputeError::NoCounterparty),
+    };
+
+    let counterparty_wallet = Wallets::find()
+        .filter(wallets::Column::UserId.eq(counterparty_id))
+        .filter(wallets::Column::Default.eq(true))
+        .one(db)
+        .await?;
+
+    let counterparty_wallet = match counterparty_wallet {
+        Some(counterparty_wallet) => counterparty_wallet,
+        None => return Err(DisputeError::NoCounterparty),
+    };
+
+    let hold = place_hold(
+        db,
+        &counterparty_wallet,
+        dispute.amount,
+        "dispute",
+        &dispute.uuid,
+    )
+    .await?;
+
+    let mut updated_dispute = record_support_action(dispute, support);
+    updated_dispute.hold_id = Set(Some(hold.uuid));
+
+    Ok(updated_dispute.update(db).await?)
+}
+
+// Resolving for the customer reverses the transaction. The hold is released first so the
+// held funds can be recovered by the reversal
+pub async fn resolve_dispute(
+    txn: &DatabaseTransaction,
+    env: &EnvConfig,
+    support: &users::Model,
+    dispute_id: &String,
+    in_favour_of: DisputeParty,
+    note: String,
+) -> Result<disputes::Model, DisputeError> {
+    let dispute = find_active_dispute(txn, dispute_id).await?;
+
+    if let Some(hold_id) = &dispute.hold_id {
+        release_hold(txn, hold_id).await?;
+    }
+
+    let reversal_id = match in_favour_of {
+        DisputeParty::Customer => {
+            let reversal_request = ReversalRequest {
+                transaction_id: dispute.transaction_id.to_string(),
+                reason: format!("Dispute {}: {}", dispute.uuid, note),
+                allow_negative_balance: false,
+            };
+            let reversal = reverse_transaction(txn, env, support, reversal_request).await?;
+            Some(reversal.uuid)
+        }
+        DisputeParty::Counterparty => None,
+    };
+
+    let mut updated_dispute = record_support_action(dispute, support);
+    updated_dispute.status = Set(DisputeStatus::Resolved.to_string());
+    updated_dispute.resolved_in_favour_of = Set(Some(in_favour_of.to_string()));
+    updated_dispute.resolution_note = Set(Some(note));
+    updated_dispute.reversal_id = Set(reversal_id);
+    updated_dispute.resolved_at = Set(Some(Utc::now()));
+
+    Ok(updated_dispute.update(txn).await?)
+}
+
+pub async fn reject_dispute<C: ConnectionTrait>(
+    db: &C,
+    support: &users::Model,
+    dispute_id: &String,
+    note: String,
+) -> Result<disputes::Model, DisputeError> {
+    let dispute = find_active_dispute(db, dispute_id).await?;
+
+    if let Some(hold_id) = &dispute.hold_id {
+        release_hold(db, hold_id).await?;
+    }
+
+    let mut updated_dispute = record_support_action(dispute, support);
+    updated_dispute.status = Set(DisputeStatus::Rejected.to_string());
+    updated_dispute.resolution_note = Set(Some(note));
+    updated_dispute.resolved_at = Set(Some(Utc::now()));
+
+    Ok(updated_dispute.update(db).await?)
+}
+
+// Emails the customer who opened the dispute and, where there is one, the counterparty
+pub async fn notify_dispute_parties(
+    app_state: &AppState,
+    dispute: &disputes::Model,
+    message: &String,
+) {
+    let mut user_ids = vec![dispute.user_id.to_string()];
+    if let Some(counterparty_id) = &dispute.counterparty_id {
+        user_ids.push(counterparty_id.to_string());
+    }
+
+    let details = json!({
+        "message": message,
+        "dispute_id": dispute.uuid,
+        "transaction_id": dispute.transaction_id,
+        "amount": dispute.amount,
+        "status": dispute.status,
+    });
+
+    for user_id in user_ids {
+        let notification = notify(
+            &app_state.db,
+            &user_id,
+            NotificationEvent::DisputeUpdate,
+            details.clone(),
+        )
+        .await;
+        if let Err(err) = notification {
+            error!("Error queueing dispute email for {} ===> {}", user_id, err);
+        }
+    }
+}
@@ -0,0 +1,710 @@
+use chrono::{Duration, Utc};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fmt;
+use thiserror::Error;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::entities::{
+    escrows,
+    prelude::{Escrows, Users, Wallets},
+    sea_orm_active_enums::{Status, TrxType},
+    users, wallets,
+};
+use crate::AppState;
+
+use super::notification::{notify, NotificationEvent};
+use super::transaction_balance::{
+    lock_wallets, TransactionBalance, TransactionBalanceTrait, TrxCategory,
+};
+use super::wallet_hold::available_balance;
+
+// Seeded by the escrow account migration. Funds in escrow sit in this wallet, so they have left
+// the buyer's balance without reaching the seller's
+pub const ESCROW_USER_ID: &str = "00000000-0000-4000-8000-00000000e5c0";
+pub const ESCROW_WALLET_ID: &str = "00000000-0000-4000-8000-00000000e5c1";
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EscrowStatus {
+    Funded,
+    Delivered,
+    Disputed,
+    Released,
+    Refunded,
+}
+
+impl fmt::Display for EscrowStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            EscrowStatus::Funded => "funded",
+            EscrowStatus::Delivered => "delivered",
+            EscrowStatus::Disputed => "disputed",
+            EscrowStatus::Released => "released",
+            EscrowStatus::Refunded => "refunded",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EscrowParty {
+    Buyer,
+    Seller,
+}
+
+impl fmt::Display for EscrowParty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let party = match self {
+            EscrowParty::Buyer => "buyer",
+            EscrowParty::Seller => "seller",
+        };
+
+        write!(f, "{}", party)
+    }
+}
+
+impl EscrowParty {
+    pub fn parse(party: &str) -> Option<EscrowParty> {
+        match party {
+            "buyer" => Some(EscrowParty::Buyer),
+            "seller" => Some(EscrowParty::Seller),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum EscrowError {
+    #[error("Please verify your account before taking this action")]
+    BuyerNotVerified,
+
+    #[error("You cannot open an escrow with yourself")]
+    SelfEscrow,
+
+    #[error("Seller not found")]
+    SellerNotFound,
+
+    #[error("{0} is not verified yet and cannot receive escrow payments")]
+    SellerNotVerified(String),
+
+    #[error("You do not seem to have a valid wallet yet. Please contact support")]
+    BuyerWalletNotFound,
+
+    #[error("The seller does not have a wallet to be paid into yet")]
+    SellerWalletNotFound,
+
+    #[error("This wallet is frozen and cannot be debited")]
+    WalletFrozen,
+
+    #[error("Insufficient funds")]
+    InsufficientFunds,
+
+    #[error("Escrow not found")]
+    EscrowNotFound,
+
+    #[error("This escrow cannot be {0} while it is {1}")]
+    InvalidState(String, String),
+
+    #[error("Escrow account not found")]
+    EscrowAccountNotFound,
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl EscrowError {
+    pub fn is_client_error(&self) -> bool {
+        !matches!(
+            self,
+            EscrowError::EscrowAccountNotFound | EscrowError::DatabaseError(_)
+        )
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(
+            self,
+            EscrowError::SellerNotFound | EscrowError::EscrowNotFound
+        )
+    }
+}
+
+pub struct NewEscrow {
+    pub seller_id: String,
+    pub amount: Decimal,
+    pub description: String,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct EscrowReleaseSummary {
+    pub released: usize,
+    pub failed: usize,
+}
+
+fn full_name(user: &users::Model) -> String {
+    format!("{} {}", user.last_name, user.first_name)
+}
+
+async fn default_wallet<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+) -> Result<Option<wallets::Model>, DbErr> {
+    Wallets::find()
+        .filter(wallets::Column::UserId.eq(user_id))
+        .filter(wallets::Column::Default.eq(true))
+        .one(db)
+        .await
+}
+
+async fn find_user<C: ConnectionTrait>(db: &C, user_id: &str) -> Result<users::Model, EscrowError> {
+    Users::find_by_id(user_id)
+        .one(db)
+        .await?
+        .ok_or(EscrowError::EscrowNotFound)
+}
+
+// One leg of an escrow movement, both sides read fresh inside the transaction. The debit and
+// credit reference each other like a P2P transfer, and the credit id is chosen by the caller so
+// the escrow can point at it
+struct EscrowMovement<'a> {
+    from_wallet_id: &'a str,
+    to_wallet_id: &'a str,
+    amount: Decimal,
+    debit_description: String,
+    credit_description: String,
+    credit_id: String,
+    meta: Value,
+}
+
+// Locks both wallets in the id order every balance writer uses
+async fn lock_pair(
+    txn: &DatabaseTransaction,
+    first_id: &str,
+    second_id: &str,
+) -> Result<(wallets::Model, wallets::Model), EscrowError> {
+    let locked = lock_wallets(
+        txn,
+        Condition::all().add(wallets::Column::Uuid.is_in([first_id, second_id])),
+    )
+    .await?;
+    let find = |wallet_id: &str| {
+        locked
+            .iter()
+            .find(|wallet| wallet.uuid == wallet_id)
+            .cloned()
+            .ok_or(EscrowError::EscrowAccountNotFound)
+    };
+
+    Ok((find(first_id)?, find(second_id)?))
+}
+
+async fn post_movement(
+    txn: &DatabaseTransaction,
+    movement: EscrowMovement<'_>,
+) -> Result<String, EscrowError> {
+    // Every escrow movement goes through the escrow wallet, so both rows are locked before their
+    // balances are read
+    let (from_wallet, to_wallet) =
+        lock_pair(txn, movement.from_wallet_id, movement.to_wallet_id).await?;
+    let debit_id = Uuid::new_v4().to_string();
+
+    TransactionBalance {
+        uuid: debit_id.to_string(),
+        amount: movement.amount,
+        trx_type: TrxType::Debit,
+        status: Status::Successful,
+        description: movement.debit_description,
+        provider_reference: Some(movement.credit_id.to_string()),
+        current_balance: from_wallet.current_balance - movement.amount,
+        previous_balance: from_wallet.current_balance,
+        user_id: from_wallet.user_id.to_string(),
+        wallet_id: from_wallet.uuid.to_string(),
+        provider: String::from("money-transfer"),
+        fees: None,
+        provider_fees: None,
+        category: TrxCategory::Escrow,
+        meta: Some(movement.meta.to_string()),
+    }
+    .save_transaction_update_balance(txn)
+    .await?;
+
+    TransactionBalance {
+        uuid: movement.credit_id,
+        amount: movement.amount,
+        trx_type: TrxType::Credit,
+        status: Status::Successful,
+        description: movement.credit_description,
+        provider_reference: Some(debit_id.to_string()),
+        current_balance: to_wallet.current_balance + movement.amount,
+        previous_balance: to_wallet.current_balance,
+        user_id: to_wallet.user_id.to_string(),
+        wallet_id: to_wallet.uuid.to_string(),
+        provider: String::from("money-transfer"),
+        fees: None,
+        provider_fees: None,
+        category: TrxCategory::Escrow,
+        meta: Some(movement.meta.to_string()),
+    }
+    .save_transaction_update_balance(txn)
+    .await?;
+
+    Ok(debit_id)
+}
+
+// Tells one side of the escrow where it stands, named against the other side
+async fn notify_party<C: ConnectionTrait>(
+    db: &C,
+    escrow: &escrows::Model,
+    party: EscrowParty,
+) -> Result<(), EscrowError> {
+    let (user_id, counterparty_id) = match party {
+        EscrowParty::Buyer => (&escrow.buyer_id, &escrow.seller_id),
+        EscrowParty::Seller => (&escrow.seller_id, &escrow.buyer_id),
+    };
+    let counterparty = find_user(db, counterparty_id).await?;
+
+    let data = json!({
+        "escrow_id": &escrow.uuid,
+        "description": &escrow.description,
+        "amount": escrow.amount,
+        "status": &escrow.status,
+        "role": party.to_string(),
+        "counterparty_name": full_name(&counterparty),
+        "auto_release_at": escrow
+            .auto_release_at
+            .map(|at| at.format("%d %b %Y, %H:%M UTC").to_string()),
+    });
+
+    notify(db, user_id, NotificationEvent::EscrowUpdate, data).await?;
+
+    Ok(())
+}
+
+// The status change is made conditional on the escrow still being in one of `from`, so two
+// requests racing on the same escrow can't both act on it
+fn transition(
+    escrow: &escrows::Model,
+    from: &[EscrowStatus],
+    to: EscrowStatus,
+) -> UpdateMany<escrows::Entity> {
+    Escrows::update_many()
+        .col_expr(
+            escrows::Column::Status,
+            sea_query::Expr::value(to.to_string()),
+        )
+        .col_expr(
+            escrows::Column::UpdatedAt,
+            sea_query::Expr::value(Utc::now()),
+        )
+        .filter(escrows::Column::Id.eq(escrow.id))
+        .filter(escrows::Column::Status.is_in(from.iter().map(|status| status.to_string())))
+}
+
+async fn apply_transition<C: ConnectionTrait>(
+    db: &C,
+    escrow: &escrows::Model,
+    update: UpdateMany<escrows::Entity>,
+    action: &str,
+) -> Result<escrows::Model, EscrowError> {
+    if update.exec(db).await?.rows_affected == 0 {
+        return Err(EscrowError::InvalidState(
+            action.to_string(),
+            escrow.status.to_string(),
+        ));
+    }
+
+    Escrows::find_by_id(&escrow.uuid)
+        .one(db)
+        .await?
+        .ok_or(EscrowError::EscrowNotFound)
+}
+
+// Pays out of the escrow account, to the seller on release or back to the buyer on refund
+async fn settle(
+    txn: &DatabaseTransaction,
+    escrow: &escrows::Model,
+    from: &[EscrowStatus],
+    outcome: EscrowStatus,
+    resolution: Option<(&users::Model, String)>,
+) -> Result<escrows::Model, EscrowError> {
+    let (recipient_id, action, label) = match outcome {
+        EscrowStatus::Released => (&escrow.seller_id, "released", "Escrow release"),
+        _ => (&escrow.buyer_id, "refunded", "Escrow refund"),
+    };
+
+    let recipient_wallet = default_wallet(txn, recipient_id)
+        .await?
+        .ok_or(match outcome {
+            EscrowStatus::Released => EscrowError::SellerWalletNotFound,
+            _ => EscrowError::BuyerWalletNotFound,
+        })?;
+
+    let credit_id = Uuid::new_v4().to_string();
+    let mut update = transition(escrow, from, outcome)
+        .col_expr(
+            escrows::Column::SettlementTransactionId,
+            sea_query::Expr::value(credit_id.to_string()),
+        )
+        .col_expr(
+            escrows::Column::SettledAt,
+            sea_query::Expr::value(Utc::now()),
+        );
+
+    if let Some((support, note)) = resolution {
+        update = update
+            .col_expr(
+                escrows::Column::ResolvedBy,
+                sea_query::Expr::value(support.uuid.to_string()),
+            )
+            .col_expr(
+                escrows::Column::ResolutionNote,
+                sea_query::Expr::value(note),
+            );
+    }
+
+    let settled = apply_transition(txn, escrow, update, action).await?;
+
+    post_movement(
+        txn,
+        EscrowMovement {
+            from_wallet_id: ESCROW_WALLET_ID,
+            to_wallet_id: &recipient_wallet.uuid,
+            amount: escrow.amount,
+            debit_description: format!("{} - {}", label, &escrow.description),
+            credit_description: format!("{} - {}", label, &escrow.description),
+            credit_id,
+            meta: json!({ "escrow_id": &escrow.uuid }),
+        },
+    )
+    .await?;
+
+    Ok(settled)
+}
+
+// Moves the amount from the buyer's default wallet into the escrow account
+pub async fn create_escrow(
+    txn: &DatabaseTransaction,
+    buyer: &users::Model,
+    new_escrow: NewEscrow,
+) -> Result<escrows::Model, EscrowError> {
+    if !buyer.is_verified {
+        return Err(EscrowError::BuyerNotVerified);
+    }
+
+    if buyer.uuid == new_escrow.seller_id {
+        return Err(EscrowError::SelfEscrow);
+    }
+
+    let seller = Users::find_by_id(&new_escrow.seller_id)
+        .one(txn)
+        .await?
+        .ok_or(EscrowError::SellerNotFound)?;
+
+    // The escrow account itself is never verified, so it can't be picked as a seller
+    if !seller.is_verified {
+        return Err(EscrowError::SellerNotVerified(full_name(&seller)));
+    }
+
+    if default_wallet(txn, &seller.uuid).await?.is_none() {
+        return Err(EscrowError::SellerWalletNotFound);
+    }
+
+    let buyer_wallet = default_wallet(txn, &buyer.uuid)
+        .await?
+        .ok_or(EscrowError::BuyerWalletNotFound)?;
+    // The balance is checked against the locked row. The escrow wallet is locked with it, in the
+    // same order post_movement takes them
+    let (buyer_wallet, _) = lock_pair(txn, &buyer_wallet.uuid, ESCROW_WALLET_ID).await?;
+
+    if buyer_wallet.is_frozen() {
+        return Err(EscrowError::WalletFrozen);
+    }
+
+    if new_escrow.amount > available_balance(txn, &buyer_wallet).await? {
+        return Err(EscrowError::InsufficientFunds);
+    }
+
+    let escrow_id = Uuid::new_v4().to_string();
+    let funding_transaction_id = post_movement(
+        txn,
+        EscrowMovement {
+            from_wallet_id: &buyer_wallet.uuid,
+            to_wallet_id: ESCROW_WALLET_ID,
+            amount: new_escrow.amount,
+            debit_description: format!(
+                "Escrow - {} - TO {}",
+                &new_escrow.description,
+                full_name(&seller)
+            ),
+            credit_description: format!(
+                "Escrow - {} - FROM {}",
+                &new_escrow.description,
+                full_name(buyer)
+            ),
+            credit_id: Uuid::new_v4().to_string(),
+            meta: json!({ "escrow_id": &escrow_id, "seller_id": &seller.uuid }),
+        },
+    )
+    .await?;
+
+    let escrow = escrows::ActiveModel {
+        uuid: Set(escrow_id),
+        buyer_id: Set(buyer.uuid.to_string()),
+        seller_id: Set(seller.uuid.to_string()),
+        amount: Set(new_escrow.amount),
+        description: Set(new_escrow.description),
+        status: Set(EscrowStatus::Funded.to_string()),
+        funding_transaction_id: Set(funding_transaction_id),
+        ..Default::default()
+    }
+    .insert(txn)
+    .await?;
+
+    notify_party(txn, &escrow, EscrowParty::Seller).await?;
+
+    Ok(escrow)
+}
+
+pub async fn user_escrows<C: ConnectionTrait>(
+    db: &C,
+    user: &users::Model,
+) -> Result<Vec<escrows::Model>, DbErr> {
+    Escrows::find()
+        .filter(
+            Condition::any()
+                .add(escrows::Column::BuyerId.eq(&user.uuid))
+                .add(escrows::Column::SellerId.eq(&user.uuid)),
+        )
+        .order_by_desc(escrows::Column::CreatedAt)
+        .all(db)
+        .await
+}
+
+// Either side of the escrow can see it
+pub async fn find_user_escrow<C: ConnectionTrait>(
+    db: &C,
+    user: &users::Model,
+    escrow_id: &str,
+) -> Result<escrows::Model, EscrowError> {
+    Escrows::find()
+        .filter(escrows::Column::Uuid.eq(escrow_id))
+        .filter(
+            Condition::any()
+                .add(escrows::Column::BuyerId.eq(&user.uuid))
+                .add(escrows::Column::SellerId.eq(&user.uuid)),
+        )
+        .one(db)
+        .await?
+        .ok_or(EscrowError::EscrowNotFound)
+}
+
+// Only the side allowed to take an action finds the escrow for it
+async fn find_escrow_as<C: ConnectionTrait>(
+    db: &C,
+    user: &users::Model,
+    escrow_id: &str,
+    party: EscrowParty,
+) -> Result<escrows::Model, EscrowError> {
+    let party_column = match party {
+        EscrowParty::Buyer => escrows::Column::BuyerId,
+        EscrowParty::Seller => escrows::Column::SellerId,
+    };
+
+    Escrows::find()
+        .filter(escrows::Column::Uuid.eq(escrow_id))
+        .filter(party_column.eq(&user.uuid))
+        .one(db)
+        .await?
+        .ok_or(EscrowError::EscrowNotFound)
+}
+
+// Starts the auto-release clock. The buyer has until then to release or dispute
+pub async fn mark_delivered(
+    txn: &DatabaseTransaction,
+    auto_release_hours: i64,
+    seller: &users::Model,
+    escrow_id: &str,
+) -> Result<escrows::Model, EscrowError> {
+    let escrow = find_escrow_as(txn, seller, escrow_id, EscrowParty::Seller).await?;
+
+    let now = Utc::now();
+    let update = transition(&escrow, &[EscrowStatus::Funded], EscrowStatus::Delivered)
+        .col_expr(escrows::Column::DeliveredAt, sea_query::Expr::value(now))
+        .col_expr(
+            escrows::Column::AutoReleaseAt,
+            sea_query::Expr::value(now + Duration::hours(auto_release_hours)),
+        );
+    let escrow = apply_transition(txn, &escrow, update, "marked delivered").await?;
+
+    notify_party(txn, &escrow, EscrowParty::Buyer).await?;
+
+    Ok(escrow)
+}
+
+pub async fn release_escrow(
+    txn: &DatabaseTransaction,
+    buyer: &users::Model,
+    escrow_id: &str,
+) -> Result<escrows::Model, EscrowError> {
+    let escrow = find_escrow_as(txn, buyer, escrow_id, EscrowParty::Buyer).await?;
+    let from = [EscrowStatus::Funded, EscrowStatus::Delivered];
+    let escrow = settle(txn, &escrow, &from, EscrowStatus::Released, None).await?;
+
+    notify_party(txn, &escrow, EscrowParty::Seller).await?;
+
+    Ok(escrow)
+}
+
+// Stops the auto-release and leaves the funds in escrow until support resolves it
+pub async fn dispute_escrow(
+    txn: &DatabaseTransaction,
+    buyer: &users::Model,
+    escrow_id: &str,
+    reason: String,
+) -> Result<escrows::Model, EscrowError> {
+    let escrow = find_escrow_as(txn, buyer, escrow_id, EscrowParty::Buyer).await?;
+
+    let from = [EscrowStatus::Funded, EscrowStatus::Delivered];
+    let update = transition(&escrow, &from, EscrowStatus::Disputed)
+        .col_expr(
+            escrows::Column::DisputeReason,
+            sea_query::Expr::value(reason),
+        )
+        .col_expr(
+            escrows::Column::DisputedAt,
+            sea_query::Expr::value(Utc::now()),
+        )
+        .col_expr(
+            escrows::Column::AutoReleaseAt,
+            sea_query::Expr::value(Option::<chrono::DateTime<Utc>>::None),
+        );
+    let escrow = apply_transition(txn, &escrow, update, "disputed").await?;
+
+    notify_party(txn, &escrow, EscrowParty::Seller).await?;
+
+    Ok(escrow)
+}
+
+// The seller can give the money back at any point before it is released, including to settle a
+// dispute without support
+pub async fn refund_escrow(
+    txn: &DatabaseTransaction,
+    seller: &users::Model,
+    escrow_id: &str,
+) -> Result<escrows::Model, EscrowError> {
+    let escrow = find_escrow_as(txn, seller, escrow_id, EscrowParty::Seller).await?;
+    let from = [
+        EscrowStatus::Funded,
+        EscrowStatus::Delivered,
+        EscrowStatus::Disputed,
+    ];
+    let escrow = settle(txn, &escrow, &from, EscrowStatus::Refunded, None).await?;
+
+    notify_party(txn, &escrow, EscrowParty::Buyer).await?;
+
+    Ok(escrow)
+}
+
+// Support settles a disputed escrow by paying the seller or refunding the buyer
+pub async fn resolve_escrow_dispute(
+    txn: &DatabaseTransaction,
+    support: &users::Model,
+    escrow_id: &str,
+    in_favour_of: EscrowParty,
+    note: String,
+) -> Result<escrows::Model, EscrowError> {
+    let escrow = Escrows::find_by_id(escrow_id)
+        .one(txn)
+        .await?
+        .ok_or(EscrowError::EscrowNotFound)?;
+
+    let outcome = match in_favour_of {
+        EscrowParty::Seller => EscrowStatus::Released,
+        EscrowParty::Buyer => EscrowStatus::Refunded,
+    };
+    let from = [EscrowStatus::Disputed];
+    let escrow = settle(txn, &escrow, &from, outcome, Some((support, note))).await?;
+
+    notify_party(txn, &escrow, EscrowParty::Buyer).await?;
+    notify_party(txn, &escrow, EscrowParty::Seller).await?;
+
+    Ok(escrow)
+}
+
+pub async fn run_escrow_release_worker(app_state: AppState) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        app_state.env.escrow_release_interval_secs,
+    ));
+
+    loop {
+        interval.tick().await;
+
+        match process_due_escrows(&app_state).await {
+            Ok(summary) if summary.released > 0 || summary.failed > 0 => info!(
+                "Auto-released {} escrow(s), {} failed",
+                summary.released, summary.failed
+            ),
+            Ok(_) => {}
+            Err(err) => error!("Error checking escrows due for release: {}", err),
+        }
+    }
+}
+
+// Delivered escrows the buyer hasn't acted on by their auto-release time are paid to the seller
+pub async fn process_due_escrows(app_state: &AppState) -> Result<EscrowReleaseSummary, DbErr> {
+    let due = Escrows::find()
+        .filter(escrows::Column::Status.eq(EscrowStatus::Delivered.to_string()))
+        .filter(escrows::Column::AutoReleaseAt.lte(Utc::now()))
+        .order_by_asc(escrows::Column::Id)
+        .all(&app_state.db)
+        .await?;
+
+    let mut summary = EscrowReleaseSummary::default();
+    for escrow in due {
+        match auto_release(&app_state.db, &escrow).await {
+            Ok(()) => summary.released += 1,
+            Err(err) => {
+                error!("Error auto-releasing escrow {} ===> {}", &escrow.uuid, err);
+                summary.failed += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+// Each escrow in its own transaction, so one failure doesn't hold up the rest
+async fn auto_release(db: &DatabaseConnection, escrow: &escrows::Model) -> Result<(), EscrowError> {
+    let txn = db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadWrite),
+        )
+        .await?;
+
+    let released = settle(
+        &txn,
+        escrow,
+        &[EscrowStatus::Delivered],
+        EscrowStatus::Released,
+        None,
+    )
+    .await;
+
+    let released = match released {
+        Ok(released) => released,
+        Err(err) => {
+            let _ = txn.rollback().await;
+            return Err(err);
+        }
+    };
+
+    notify_party(&txn, &released, EscrowParty::Buyer).await?;
+    notify_party(&txn, &released, EscrowParty::Seller).await?;
+    txn.commit().await?;
+
+    Ok(())
+}
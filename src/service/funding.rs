@@ -0,0 +1,414 @@
+use chrono::{Duration, Utc};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::json;
+use thiserror::Error;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::entities::{
+    prelude::{Transactions, Users, Wallets},
+    sea_orm_active_enums::{Status, TrxType},
+    transactions, users, wallets,
+};
+use crate::utils::payment_provider::{
+    provider_by_name, ChargeStatus, ChargeVerification, PaymentProvider, ProviderError,
+};
+use crate::AppState;
+
+use super::card_payment::process_stale_card_payments;
+use super::notification::{notify, NotificationEvent};
+use super::outbound_webhook::{emit_webhook_event, WebhookEventType};
+use super::payment_method::save_card;
+use super::transaction_balance::{
+    lock_wallets, TransactionBalance, TransactionBalanceTrait, TrxCategory,
+};
+use super::virtual_account::find_virtual_account;
+
+#[derive(Error, Debug)]
+pub enum FundingError {
+    #[error("No wallet found for user")]
+    WalletNotFound,
+
+    #[error(transparent)]
+    ProviderError(#[from] ProviderError),
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FundingOutcome {
+    Credited,
+    // Credited before, or the reference belongs to something other than a funding
+    AlreadySettled,
+    Pending,
+    // The provider reports the charge as failed or abandoned
+    Failed,
+    OwnerNotFound,
+}
+
+// The wallet is the user's default one unless the charge was made for a specific wallet
+struct ChargeOwner {
+    user_id: String,
+    wallet_id: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct StaleFundingSummary {
+    pub checked: usize,
+    pub credited: usize,
+    pub failed: usize,
+    pub abandoned: usize,
+}
+
+// Every initialized funding is kept as a pending credit until the charge settles, the balances
+// stay as they are so nothing moves before the provider confirms
+pub async fn record_pending_funding<C: ConnectionTrait>(
+    db: &C,
+    user: &users::Model,
+    provider: &str,
+    reference: &str,
+    amount: Decimal,
+) -> Result<transactions::Model, FundingError> {
+    let wallet = Wallets::find()
+        .filter(wallets::Column::UserId.eq(&user.uuid))
+        .filter(wallets::Column::Default.eq(true))
+        .one(db)
+        .await?
+        .ok_or(FundingError::WalletNotFound)?;
+
+    let uuid = Uuid::new_v4();
+    let funding = transactions::ActiveModel {
+        uuid: Set(uuid.to_string()),
+        amount: Set(amount),
+        trx_type: Set(Some(TrxType::Credit)),
+        status: Set(Some(Status::Pending)),
+        description: Set(format!("Funding of account. ID: {}", &uuid)),
+        provider_reference: Set(Some(reference.to_string())),
+        current_balance: Set(wallet.current_balance),
+        previous_balance: Set(wallet.current_balance),
+        user_id: Set(user.uuid.to_string()),
+        wallet_id: Set(wallet.uuid.to_string()),
+        provider: Set(provider.to_string()),
+        category: Set(TrxCategory::Funding.to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    Ok(funding)
+}
+
+// The charge is verified with the provider rather than trusting whoever asked. Fundings are only
+// ever credited here, so webhooks, on-demand checks and the polling job can't credit twice
+pub async fn settle_funding(
+    provider: &dyn PaymentProvider,
+    reference: &str,
+    db: &DatabaseConnection,
+) -> Result<FundingOutcome, FundingError> {
+    let existing = find_funding(db, reference).await?;
+    if let Some(existing) = &existing {
+        if existing.status == Some(Status::Successful)
+            || existing.category != TrxCategory::Funding.to_string()
+        {
+            return Ok(FundingOutcome::AlreadySettled);
+        }
+    }
+
+    let verification = provider.verify_charge(reference).await?;
+    match verification.status {
+        ChargeStatus::Successful if !verification.amount.is_zero() => {}
+        ChargeStatus::Failed => {
+            // The pending placeholder is closed straight away rather than left for the abandon window
+            if let Some(placeholder) = &existing {
+                mark_funding_failed(db, placeholder).await?;
+            }
+            return Ok(FundingOutcome::Failed);
+        }
+        _ => return Ok(FundingOutcome::Pending),
+    }
+
+    let owner = match &existing {
+        Some(placeholder) => ChargeOwner {
+            user_id: placeholder.user_id.to_string(),
+            wallet_id: Some(placeholder.wallet_id.to_string()),
+        },
+        None => match find_charge_owner(db, provider, &verification).await? {
+            Some(owner) => owner,
+            None => {
+                info!("No user found for {} charge {}", provider.name(), reference);
+                return Ok(FundingOutcome::OwnerNotFound);
+            }
+        },
+    };
+
+    let txn = db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadWrite),
+        )
+        .await?;
+
+    match credit_funding(&txn, provider, &verification, &owner, existing).await {
+        Ok(FundingOutcome::Credited) => {
+            txn.commit().await?;
+            remember_card(db, provider, &verification, &owner.user_id).await;
+            Ok(FundingOutcome::Credited)
+        }
+        Ok(outcome) => {
+            let _ = txn.rollback().await;
+            Ok(outcome)
+        }
+        Err(err) => {
+            error!("DB error updating wallet and creating transaction: {}", err);
+            let _ = txn.rollback().await;
+            Err(FundingError::DatabaseError(err))
+        }
+    }
+}
+
+// The pending placeholder is swapped for the credit so the credit sits in the ledger where the
+// balance actually changed. The unique reference stops a concurrent settlement from crediting too
+async fn credit_funding(
+    txn: &DatabaseTransaction,
+    provider: &dyn PaymentProvider,
+    verification: &ChargeVerification,
+    owner: &ChargeOwner,
+    placeholder: Option<transactions::Model>,
+) -> Result<FundingOutcome, DbErr> {
+    if let Some(placeholder) = &placeholder {
+        let removed = Transactions::delete_many()
+            .filter(transactions::Column::Id.eq(placeholder.id))
+            .filter(transactions::Column::Status.ne(Status::Successful))
+            .exec(txn)
+            .await?;
+        if removed.rows_affected == 0 {
+            return Ok(FundingOutcome::AlreadySettled);
+        }
+    }
+
+    let user_id = &owner.user_id;
+    let wallet_condition = Condition::all().add(wallets::Column::UserId.eq(user_id));
+    let wallet_condition = match &owner.wallet_id {
+        Some(wallet_id) => wallet_condition.add(wallets::Column::Uuid.eq(wallet_id)),
+        None => wallet_condition.add(wallets::Column::Default.eq(true)),
+    };
+
+    let my_wallet = match lock_wallets(txn, wallet_condition).await?.pop() {
+        Some(my_wallet) => my_wallet,
+        None => {
+            info!("No wallet found for user {}", user_id);
+            return Ok(FundingOutcome::OwnerNotFound);
+        }
+    };
+
+    let uuid = placeholder
+        .as_ref()
+        .map(|placeholder| placeholder.uuid.to_string())
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let balance = my_wallet.current_balance + verification.amount;
+    TransactionBalance {
+        description: format!("Funding of account. ID: {}", &uuid),
+        uuid: uuid.to_string(),
+        amount: verification.amount,
+        trx_type: TrxType::Credit,
+        status: Status::Successful,
+        provider_reference: Some(verification.reference.to_string()),
+        current_balance: balance,
+        previous_balance: my_wallet.current_balance,
+        user_id: user_id.to_string(),
+        wallet_id: my_wallet.uuid.to_string(),
+        provider: provider.name().to_string(),
+        fees: None,
+        provider_fees: Some(verification.fees),
+        category: TrxCategory::Funding,
+        meta: Some(verification.raw.to_string()),
+    }
+    .save_transaction_update_balance(txn)
+    .await?;
+
+    let funded = json!({
+        "amount": verification.amount,
+        "reference": &verification.reference,
+        "balance": balance,
+    });
+    notify(txn, user_id, NotificationEvent::WalletFunded, funded).await?;
+
+    let funded = json!({
+        "reference": &verification.reference,
+        "transaction_id": &uuid,
+        "amount": verification.amount,
+        "wallet_id": &my_wallet.uuid,
+        "balance": balance,
+        "provider": provider.name(),
+    });
+    emit_webhook_event(txn, user_id, WebhookEventType::WalletFunded, funded).await?;
+
+    Ok(FundingOutcome::Credited)
+}
+
+// Saving the card is a convenience, a failure here must not undo the credit
+async fn remember_card(
+    db: &DatabaseConnection,
+    provider: &dyn PaymentProvider,
+    verification: &ChargeVerification,
+    user_id: &String,
+) {
+    let (card, email) = match (&verification.card, &verification.customer_email) {
+        (Some(card), Some(email)) => (card, email),
+        _ => return,
+    };
+
+    if let Err(err) = save_card(db, user_id, email, provider.name(), card).await {
+        error!("Error saving card for user {} ===> {}", user_id, err);
+    }
+}
+
+// Background worker for fundings and merchant card payments whose webhook never arrived.
+// Spawned once on startup
+pub async fn run_pending_funding_worker(app_state: AppState) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        app_state.env.funding_poll_interval_secs,
+    ));
+
+    loop {
+        interval.tick().await;
+
+        match process_stale_fundings(&app_state).await {
+            Ok(summary) if summary.checked > 0 => info!(
+                "Checked {} stale funding(s), {} credited, {} failed and {} abandoned",
+                summary.checked, summary.credited, summary.failed, summary.abandoned
+            ),
+            Ok(_) => {}
+            Err(err) => error!("Error checking stale fundings: {}", err),
+        }
+
+        match process_stale_card_payments(&app_state).await {
+            Ok(summary) if summary.checked > 0 => info!(
+                "Checked {} stale card payment(s), {} credited, {} failed and {} abandoned",
+                summary.checked, summary.credited, summary.failed, summary.abandoned
+            ),
+            Ok(_) => {}
+            Err(err) => error!("Error checking stale card payments: {}", err),
+        }
+    }
+}
+
+// Pending fundings older than the stale window are verified again, those still unpaid once the
+// abandon window has passed are marked failed
+pub async fn process_stale_fundings(app_state: &AppState) -> Result<StaleFundingSummary, DbErr> {
+    let now = Utc::now();
+    let stale_before = now - Duration::minutes(app_state.env.funding_stale_after_mins);
+    let abandon_before = now - Duration::minutes(app_state.env.funding_abandon_after_mins);
+
+    let stale_fundings = Transactions::find()
+        .filter(transactions::Column::Category.eq(TrxCategory::Funding.to_string()))
+        .filter(transactions::Column::Status.eq(Status::Pending))
+        .filter(transactions::Column::CreatedAt.lte(stale_before))
+        .order_by_asc(transactions::Column::Id)
+        .all(&app_state.db)
+        .await?;
+
+    let mut summary = StaleFundingSummary::default();
+    for funding in stale_fundings {
+        summary.checked += 1;
+        let reference = funding.provider_reference.clone().unwrap_or_default();
+
+        let outcome = match provider_by_name(&funding.provider, &app_state.env) {
+            Some(provider) => settle_funding(provider.as_ref(), &reference, &app_state.db).await,
+            None => {
+                warn!(
+                    "Provider {} is not configured, cannot verify funding {}",
+                    funding.provider, reference
+                );
+                Ok(FundingOutcome::Pending)
+            }
+        };
+
+        match outcome {
+            Ok(FundingOutcome::Credited) => summary.credited += 1,
+            Ok(FundingOutcome::Failed) => summary.failed += 1,
+            Ok(FundingOutcome::Pending) if funding.created_at <= abandon_before => {
+                if mark_funding_failed(&app_state.db, &funding).await? {
+                    summary.abandoned += 1;
+                }
+            }
+            Ok(_) => {}
+            Err(err) => error!("Error verifying funding {} ===> {}", reference, err),
+        }
+    }
+
+    Ok(summary)
+}
+
+pub async fn mark_funding_failed<C: ConnectionTrait>(
+    db: &C,
+    funding: &transactions::Model,
+) -> Result<bool, DbErr> {
+    let updated = Transactions::update_many()
+        .col_expr(
+            transactions::Column::Status,
+            sea_query::Expr::value(Status::Failed),
+        )
+        .col_expr(
+            transactions::Column::UpdatedAt,
+            sea_query::Expr::value(Utc::now()),
+        )
+        .filter(transactions::Column::Id.eq(funding.id))
+        .filter(transactions::Column::Status.eq(Status::Pending))
+        .exec(db)
+        .await?;
+
+    Ok(updated.rows_affected > 0)
+}
+
+pub async fn find_funding<C: ConnectionTrait>(
+    db: &C,
+    reference: &str,
+) -> Result<Option<transactions::Model>, DbErr> {
+    Transactions::find()
+        .filter(transactions::Column::ProviderReference.eq(reference))
+        .one(db)
+        .await
+}
+
+// Charges carry our user id in their metadata. Transfers into a virtual account are matched by
+// the provider's customer instead, and the customer email is the last resort
+async fn find_charge_owner(
+    db: &DatabaseConnection,
+    provider: &dyn PaymentProvider,
+    verification: &ChargeVerification,
+) -> Result<Option<ChargeOwner>, DbErr> {
+    if let Some(user_id) = &verification.user_id {
+        return Ok(Some(ChargeOwner {
+            user_id: user_id.to_string(),
+            wallet_id: None,
+        }));
+    }
+
+    if let Some(customer_code) = &verification.virtual_account_customer {
+        if let Some(account) = find_virtual_account(db, provider.name(), customer_code).await? {
+            return Ok(Some(ChargeOwner {
+                user_id: account.user_id,
+                wallet_id: Some(account.wallet_id),
+            }));
+        }
+    }
+
+    let email = match &verification.customer_email {
+        Some(email) => email,
+        None => return Ok(None),
+    };
+
+    let user = Users::find()
+        .filter(users::Column::Email.eq(email))
+        .one(db)
+        .await?;
+
+    Ok(user.map(|user| ChargeOwner {
+        user_id: user.uuid,
+        wallet_id: None,
+    }))
+}
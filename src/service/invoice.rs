@@ -0,0 +1,1035 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde::Serialize;
+use serde_json::json;
+use std::fmt;
+use tera::Context;
+use thiserror::Error;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::entities::{
+    invoice_items, invoice_payments, invoices, merchants,
+    prelude::{InvoiceItems, InvoicePayments, Invoices, Merchants},
+    users,
+};
+use crate::utils::config::EnvConfig;
+use crate::utils::email_template::{
+    is_supported_locale, render_invoice_document, render_invoice_email, DEFAULT_LOCALE,
+};
+use crate::utils::payment_provider::{
+    initialize_charge, provider_by_name, ChargeInitialization, ChargeRequest, ChargeVerification,
+    ProviderError,
+};
+use crate::utils::pdf::text_pdf;
+use crate::utils::send_email::SendEmail;
+use crate::AppState;
+
+use super::card_payment::{
+    mark_card_payment_failed, settle_card_payment, CardPaymentTable, MerchantCardPayment,
+};
+use super::merchant::{
+    credit_merchant, debit_payer, merchant_fee, LinkPaymentMethod, LinkPaymentStatus,
+    MerchantCredit, MerchantError,
+};
+use super::outbound_webhook::{emit_webhook_event, WebhookEventType};
+use super::outbox::enqueue_email;
+
+// Wallets and card charges are all in Naira, so that's all an invoice can be paid in for now
+pub const SUPPORTED_CURRENCIES: [&str; 1] = ["NGN"];
+
+#[derive(Debug, PartialEq)]
+pub enum InvoiceStatus {
+    Open,
+    PartiallyPaid,
+    Paid,
+    Cancelled,
+}
+
+impl fmt::Display for InvoiceStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            InvoiceStatus::Open => "open",
+            InvoiceStatus::PartiallyPaid => "partially_paid",
+            InvoiceStatus::Paid => "paid",
+            InvoiceStatus::Cancelled => "cancelled",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum InvoiceError {
+    #[error("Invoice not found")]
+    InvoiceNotFound,
+
+    #[error("Invoices can only be issued in {}", SUPPORTED_CURRENCIES.join(", "))]
+    UnsupportedCurrency,
+
+    #[error("Due date can't be in the past")]
+    DueDateInPast,
+
+    #[error("Invoice has been cancelled")]
+    InvoiceCancelled,
+
+    #[error("Invoice has already been paid")]
+    InvoicePaid,
+
+    #[error("Only invoices without payments can be cancelled")]
+    CannotCancel,
+
+    #[error("Cannot pay your own invoice")]
+    SelfPayment,
+
+    #[error("Amount is more than the {0} left to pay")]
+    AmountTooHigh(Decimal),
+
+    #[error("Payment not found")]
+    PaymentNotFound,
+
+    #[error(transparent)]
+    MerchantError(#[from] MerchantError),
+
+    #[error(transparent)]
+    ProviderError(#[from] ProviderError),
+
+    #[error("Failed to render invoice: {0}")]
+    TemplateError(#[from] tera::Error),
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl InvoiceError {
+    pub fn is_client_error(&self) -> bool {
+        match self {
+            InvoiceError::MerchantError(err) => err.is_client_error(),
+            InvoiceError::ProviderError(_)
+            | InvoiceError::TemplateError(_)
+            | InvoiceError::DatabaseError(_) => false,
+            _ => true,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        match self {
+            InvoiceError::MerchantError(err) => err.is_not_found(),
+            InvoiceError::InvoiceNotFound | InvoiceError::PaymentNotFound => true,
+            _ => false,
+        }
+    }
+}
+
+pub struct NewInvoiceItem {
+    pub description: String,
+    pub quantity: i32,
+    pub unit_price: Decimal,
+}
+
+pub struct NewInvoice {
+    pub customer_name: String,
+    pub customer_email: String,
+    pub locale: Option<String>,
+    pub currency: Option<String>,
+    // Percentage of the subtotal
+    pub tax_rate: Decimal,
+    pub due_date: NaiveDate,
+    pub notes: Option<String>,
+    pub items: Vec<NewInvoiceItem>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct InvoiceDetails {
+    pub invoice: invoices::Model,
+    pub items: Vec<invoice_items::Model>,
+    pub payments: Vec<invoice_payments::Model>,
+}
+
+// What the customer sees on the hosted invoice
+#[derive(Serialize, Debug)]
+pub struct HostedInvoice {
+    pub slug: String,
+    pub number: String,
+    pub business_name: String,
+    pub customer_name: String,
+    pub currency: String,
+    pub items: Vec<invoice_items::Model>,
+    pub subtotal: Decimal,
+    pub tax_rate: Decimal,
+    pub tax_amount: Decimal,
+    pub total: Decimal,
+    pub amount_paid: Decimal,
+    pub balance_due: Decimal,
+    pub status: String,
+    pub overdue: bool,
+    pub due_date: NaiveDate,
+    pub notes: Option<String>,
+}
+
+pub struct CardInvoicePayment {
+    pub payment: invoice_payments::Model,
+    pub authorization: ChargeInitialization,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct InvoiceReminderSummary {
+    pub reminded: usize,
+    pub failed: usize,
+}
+
+fn is_payable(invoice: &invoices::Model) -> bool {
+    invoice.status == InvoiceStatus::Open.to_string()
+        || invoice.status == InvoiceStatus::PartiallyPaid.to_string()
+}
+
+// Still owed something after the due date
+pub fn is_overdue(invoice: &invoices::Model) -> bool {
+    is_payable(invoice) && invoice.due_date < Utc::now().date_naive()
+}
+
+fn money(amount: Decimal) -> String {
+    format!("{:.2}", amount)
+}
+
+// Everything the invoice's emails and document show
+fn invoice_context(
+    env: &EnvConfig,
+    invoice: &invoices::Model,
+    merchant: &merchants::Model,
+    items: &[invoice_items::Model],
+) -> Context {
+    let invoice_url = format!("{}/api/invoices/{}", env.app_base_url, &invoice.slug);
+    let items: Vec<_> = items
+        .iter()
+        .map(|item| {
+            json!({
+                "description": &item.description,
+                "quantity": item.quantity,
+                "unit_price": money(item.unit_price),
+                "amount": money(item.amount),
+            })
+        })
+        .collect();
+
+    let mut context = Context::new();
+    context.insert("business_name", &merchant.business_name);
+    context.insert("number", &invoice.number);
+    context.insert("customer_name", &invoice.customer_name);
+    context.insert("customer_email", &invoice.customer_email);
+    context.insert("currency", &invoice.currency);
+    context.insert("items", &items);
+    context.insert("subtotal", &money(invoice.subtotal));
+    context.insert("tax_rate", &invoice.tax_rate.normalize().to_string());
+    context.insert("tax_amount", &money(invoice.tax_amount));
+    context.insert("total", &money(invoice.total));
+    context.insert("amount_paid", &money(invoice.amount_paid));
+    context.insert("balance_due", &money(invoice.balance_due()));
+    context.insert("status", &invoice.status);
+    context.insert("overdue", &is_overdue(invoice));
+    context.insert("due_date", &invoice.due_date.to_string());
+    context.insert("issued_on", &invoice.created_at.date_naive().to_string());
+    context.insert("notes", &invoice.notes);
+    context.insert("invoice_url", &format!("{}/document", invoice_url));
+    context.insert("pdf_url", &format!("{}/pdf", invoice_url));
+    context
+}
+
+// Customers don't need an account, so invoice emails go straight to the outbox rather than
+// through a user's notification preferences
+async fn send_invoice_email<C: ConnectionTrait>(
+    db: &C,
+    env: &EnvConfig,
+    invoice: &invoices::Model,
+    name: &str,
+    context: &Context,
+) -> Result<(), InvoiceError> {
+    let email = render_invoice_email(name, &invoice.locale, context)?;
+
+    enqueue_email(
+        db,
+        &SendEmail {
+            to: invoice.customer_email.to_string(),
+            from: env.from_email.to_string(),
+            subject: email.subject,
+            template: email.html,
+            text: Some(email.text),
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn invoice_items<C: ConnectionTrait>(
+    db: &C,
+    invoice: &invoices::Model,
+) -> Result<Vec<invoice_items::Model>, DbErr> {
+    InvoiceItems::find()
+        .filter(invoice_items::Column::InvoiceId.eq(&invoice.uuid))
+        .order_by_asc(invoice_items::Column::Id)
+        .all(db)
+        .await
+}
+
+async fn invoice_merchant<C: ConnectionTrait>(
+    db: &C,
+    invoice: &invoices::Model,
+) -> Result<merchants::Model, InvoiceError> {
+    Ok(Merchants::find()
+        .filter(merchants::Column::Uuid.eq(&invoice.merchant_id))
+        .one(db)
+        .await?
+        .ok_or(MerchantError::MerchantNotFound)?)
+}
+
+// Amounts are worked out here rather than taken from the merchant, and the customer is emailed
+// the invoice once it is saved
+pub async fn create_invoice(
+    db: &DatabaseConnection,
+    env: &EnvConfig,
+    merchant: &merchants::Model,
+    new_invoice: NewInvoice,
+) -> Result<InvoiceDetails, InvoiceError> {
+    let currency = new_invoice
+        .currency
+        .as_deref()
+        .unwrap_or(SUPPORTED_CURRENCIES[0])
+        .trim()
+        .to_uppercase();
+    if !SUPPORTED_CURRENCIES.contains(&currency.as_str()) {
+        return Err(InvoiceError::UnsupportedCurrency);
+    }
+
+    if new_invoice.due_date < Utc::now().date_naive() {
+        return Err(InvoiceError::DueDateInPast);
+    }
+
+    let locale = new_invoice
+        .locale
+        .filter(|locale| is_supported_locale(locale))
+        .unwrap_or(String::from(DEFAULT_LOCALE));
+
+    let subtotal: Decimal = new_invoice
+        .items
+        .iter()
+        .map(|item| item.unit_price * Decimal::from(item.quantity))
+        .sum();
+    let tax_amount = (subtotal * new_invoice.tax_rate / Decimal::from(100)).round_dp(2);
+
+    let txn = db.begin().await?;
+
+    let issued = Invoices::find()
+        .filter(invoices::Column::MerchantId.eq(&merchant.uuid))
+        .count(&txn)
+        .await?;
+
+    let invoice = invoices::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        merchant_id: Set(merchant.uuid.to_string()),
+        number: Set(format!("INV-{:06}", issued + 1)),
+        slug: Set(Uuid::new_v4().simple().to_string()),
+        customer_name: Set(new_invoice.customer_name.trim().to_string()),
+        customer_email: Set(new_invoice.customer_email.trim().to_lowercase()),
+        locale: Set(locale),
+        currency: Set(currency),
+        subtotal: Set(subtotal),
+        tax_rate: Set(new_invoice.tax_rate),
+        tax_amount: Set(tax_amount),
+        total: Set(subtotal + tax_amount),
+        amount_paid: Set(Decimal::ZERO),
+        status: Set(InvoiceStatus::Open.to_string()),
+        due_date: Set(new_invoice.due_date),
+        notes: Set(new_invoice.notes),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+
+    let mut items = Vec::new();
+    for item in new_invoice.items {
+        let item = invoice_items::ActiveModel {
+            uuid: Set(Uuid::new_v4().to_string()),
+            invoice_id: Set(invoice.uuid.to_string()),
+            description: Set(item.description.trim().to_string()),
+            quantity: Set(item.quantity),
+            unit_price: Set(item.unit_price),
+            amount: Set(item.unit_price * Decimal::from(item.quantity)),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        items.push(item);
+    }
+
+    let context = invoice_context(env, &invoice, merchant, &items);
+    send_invoice_email(&txn, env, &invoice, "issued", &context).await?;
+
+    txn.commit().await?;
+
+    Ok(InvoiceDetails {
+        invoice,
+        items,
+        payments: Vec::new(),
+    })
+}
+
+pub async fn merchant_invoices<C: ConnectionTrait>(
+    db: &C,
+    merchant: &merchants::Model,
+    status: Option<&str>,
+) -> Result<Vec<invoices::Model>, DbErr> {
+    let mut invoice_query =
+        Invoices::find().filter(invoices::Column::MerchantId.eq(&merchant.uuid));
+    if let Some(status) = status {
+        invoice_query = invoice_query.filter(invoices::Column::Status.eq(status));
+    }
+
+    invoice_query
+        .order_by_desc(invoices::Column::Id)
+        .all(db)
+        .await
+}
+
+async fn find_merchant_invoice<C: ConnectionTrait>(
+    db: &C,
+    merchant: &merchants::Model,
+    invoice_id: &str,
+) -> Result<invoices::Model, InvoiceError> {
+    Invoices::find()
+        .filter(invoices::Column::Uuid.eq(invoice_id))
+        .filter(invoices::Column::MerchantId.eq(&merchant.uuid))
+        .one(db)
+        .await?
+        .ok_or(InvoiceError::InvoiceNotFound)
+}
+
+pub async fn invoice_details<C: ConnectionTrait>(
+    db: &C,
+    merchant: &merchants::Model,
+    invoice_id: &str,
+) -> Result<InvoiceDetails, InvoiceError> {
+    let invoice = find_merchant_invoice(db, merchant, invoice_id).await?;
+
+    let items = invoice_items(db, &invoice).await?;
+    let payments = InvoicePayments::find()
+        .filter(invoice_payments::Column::InvoiceId.eq(&invoice.uuid))
+        .order_by_desc(invoice_payments::Column::Id)
+        .all(db)
+        .await?;
+
+    Ok(InvoiceDetails {
+        invoice,
+        items,
+        payments,
+    })
+}
+
+// Only while nothing has been paid, so a payment can't land on an invoice as it's cancelled
+pub async fn cancel_invoice<C: ConnectionTrait>(
+    db: &C,
+    merchant: &merchants::Model,
+    invoice_id: &str,
+) -> Result<invoices::Model, InvoiceError> {
+    let invoice = find_merchant_invoice(db, merchant, invoice_id).await?;
+    if invoice.status == InvoiceStatus::Cancelled.to_string() {
+        return Err(InvoiceError::InvoiceCancelled);
+    }
+
+    let cancelled = Invoices::update_many()
+        .col_expr(
+            invoices::Column::Status,
+            sea_query::Expr::value(InvoiceStatus::Cancelled.to_string()),
+        )
+        .col_expr(
+            invoices::Column::UpdatedAt,
+            sea_query::Expr::value(Utc::now()),
+        )
+        .filter(invoices::Column::Id.eq(invoice.id))
+        .filter(invoices::Column::Status.eq(InvoiceStatus::Open.to_string()))
+        .exec(db)
+        .await?;
+    if cancelled.rows_affected == 0 {
+        return Err(InvoiceError::CannotCancel);
+    }
+
+    find_merchant_invoice(db, merchant, invoice_id).await
+}
+
+async fn find_invoice_by_slug<C: ConnectionTrait>(
+    db: &C,
+    slug: &str,
+) -> Result<(invoices::Model, merchants::Model), InvoiceError> {
+    let found = Invoices::find()
+        .filter(invoices::Column::Slug.eq(slug))
+        .find_also_related(Merchants)
+        .one(db)
+        .await?;
+
+    match found {
+        Some((invoice, Some(merchant))) => Ok((invoice, merchant)),
+        _ => Err(InvoiceError::InvoiceNotFound),
+    }
+}
+
+pub async fn hosted_invoice<C: ConnectionTrait>(
+    db: &C,
+    slug: &str,
+) -> Result<HostedInvoice, InvoiceError> {
+    let (invoice, merchant) = find_invoice_by_slug(db, slug).await?;
+    let items = invoice_items(db, &invoice).await?;
+
+    Ok(HostedInvoice {
+        overdue: is_overdue(&invoice),
+        balance_due: invoice.balance_due(),
+        slug: invoice.slug,
+        number: invoice.number,
+        business_name: merchant.business_name,
+        customer_name: invoice.customer_name,
+        currency: invoice.currency,
+        items,
+        subtotal: invoice.subtotal,
+        tax_rate: invoice.tax_rate,
+        tax_amount: invoice.tax_amount,
+        total: invoice.total,
+        amount_paid: invoice.amount_paid,
+        status: invoice.status,
+        due_date: invoice.due_date,
+        notes: invoice.notes,
+    })
+}
+
+// The invoice rendered in the customer's locale, as a page or a PDF
+pub async fn invoice_document<C: ConnectionTrait>(
+    db: &C,
+    env: &EnvConfig,
+    slug: &str,
+) -> Result<String, InvoiceError> {
+    let (invoice, merchant) = find_invoice_by_slug(db, slug).await?;
+    let items = invoice_items(db, &invoice).await?;
+
+    let context = invoice_context(env, &invoice, &merchant, &items);
+    Ok(render_invoice_document(&invoice.locale, "html", &context)?)
+}
+
+pub async fn invoice_pdf<C: ConnectionTrait>(
+    db: &C,
+    env: &EnvConfig,
+    slug: &str,
+) -> Result<(String, Vec<u8>), InvoiceError> {
+    let (invoice, merchant) = find_invoice_by_slug(db, slug).await?;
+    let items = invoice_items(db, &invoice).await?;
+
+    let context = invoice_context(env, &invoice, &merchant, &items);
+    let text = render_invoice_document(&invoice.locale, "txt", &context)?;
+    let title = format!("{} {}", &merchant.business_name, &invoice.number);
+
+    Ok((invoice.number, text_pdf(&title, &text)))
+}
+
+async fn find_payable_invoice<C: ConnectionTrait>(
+    db: &C,
+    slug: &str,
+) -> Result<(invoices::Model, merchants::Model), InvoiceError> {
+    let (invoice, merchant) = find_invoice_by_slug(db, slug).await?;
+
+    if invoice.status == InvoiceStatus::Cancelled.to_string() {
+        return Err(InvoiceError::InvoiceCancelled);
+    }
+    if !is_payable(&invoice) {
+        return Err(InvoiceError::InvoicePaid);
+    }
+
+    Ok((invoice, merchant))
+}
+
+// Customers pay whatever is left unless they choose to pay part of it
+fn payment_amount(
+    invoice: &invoices::Model,
+    amount: Option<Decimal>,
+) -> Result<Decimal, InvoiceError> {
+    let balance_due = invoice.balance_due();
+    let amount = amount.unwrap_or(balance_due);
+
+    if amount > balance_due {
+        return Err(InvoiceError::AmountTooHigh(balance_due));
+    }
+
+    Ok(amount)
+}
+
+// Adds a payment to what has been paid on the invoice and moves its status along. Wallet
+// payments only go through while they fit in the balance, card payments are counted whatever
+// they come to since the customer has already been charged by then
+async fn apply_payment<C: ConnectionTrait>(
+    db: &C,
+    invoice: &invoices::Model,
+    amount: Decimal,
+    within_balance: bool,
+) -> Result<Option<invoices::Model>, DbErr> {
+    let now = Utc::now();
+    let mut apply = Invoices::update_many()
+        .col_expr(
+            invoices::Column::AmountPaid,
+            sea_query::Expr::col(invoices::Column::AmountPaid).add(amount),
+        )
+        .col_expr(invoices::Column::UpdatedAt, sea_query::Expr::value(now))
+        .filter(invoices::Column::Id.eq(invoice.id));
+
+    if within_balance {
+        apply = apply
+            .filter(invoices::Column::Status.is_in([
+                InvoiceStatus::Open.to_string(),
+                InvoiceStatus::PartiallyPaid.to_string(),
+            ]))
+            .filter(
+                sea_query::Expr::expr(
+                    sea_query::Expr::col(invoices::Column::AmountPaid).add(amount),
+                )
+                .lte(sea_query::Expr::col(invoices::Column::Total)),
+            );
+    }
+
+    if apply.exec(db).await?.rows_affected == 0 {
+        return Ok(None);
+    }
+
+    let invoice = Invoices::find_by_id(&invoice.uuid)
+        .one(db)
+        .await?
+        .ok_or(DbErr::RecordNotFound(invoice.uuid.to_string()))?;
+
+    let mut updated: invoices::ActiveModel = invoice.clone().into();
+    if invoice.amount_paid >= invoice.total {
+        updated.status = Set(InvoiceStatus::Paid.to_string());
+        updated.paid_at = Set(Some(now));
+    } else if invoice.status == InvoiceStatus::Open.to_string() {
+        updated.status = Set(InvoiceStatus::PartiallyPaid.to_string());
+    }
+
+    Ok(Some(updated.update(db).await?))
+}
+
+// Credits the merchant, lets them know and sends the customer a receipt
+async fn credit_invoice_payment(
+    txn: &DatabaseTransaction,
+    env: &EnvConfig,
+    merchant: &merchants::Model,
+    invoice: &invoices::Model,
+    payment: &invoice_payments::Model,
+    provider_reference: &str,
+    provider_fees: Option<Decimal>,
+) -> Result<(), InvoiceError> {
+    let credit = MerchantCredit {
+        transaction_id: payment.transaction_id.clone().unwrap_or_default(),
+        amount: payment.amount,
+        fee: payment.fee,
+        description: format!(
+            "Invoice {} - FROM {}",
+            &invoice.number, &payment.payer_email
+        ),
+        provider: payment.provider.to_string(),
+        provider_reference: provider_reference.to_string(),
+        provider_fees,
+        meta: json!({
+            "invoice_id": &invoice.uuid,
+            "payment_id": &payment.uuid,
+            "payer_email": &payment.payer_email,
+            "method": &payment.method,
+        }),
+    };
+    let balance = credit_merchant(txn, merchant, credit).await?;
+
+    let received = json!({
+        "reference": &payment.reference,
+        "invoice_id": &invoice.uuid,
+        "number": &invoice.number,
+        "method": &payment.method,
+        "amount": payment.amount,
+        "fee": payment.fee,
+        "net_amount": payment.amount - payment.fee,
+        "amount_paid": invoice.amount_paid,
+        "balance_due": invoice.balance_due(),
+        "status": &invoice.status,
+        "wallet_id": &merchant.settlement_wallet_id,
+        "balance": balance,
+        "payer_email": &payment.payer_email,
+    });
+    emit_webhook_event(
+        txn,
+        &merchant.user_id,
+        WebhookEventType::InvoicePaymentReceived,
+        received,
+    )
+    .await?;
+
+    let items = invoice_items(txn, invoice).await?;
+    let mut context = invoice_context(env, invoice, merchant, &items);
+    context.insert("amount", &money(payment.amount));
+    context.insert("reference", &payment.reference);
+    send_invoice_email(txn, env, invoice, "payment_received", &context).await?;
+
+    Ok(())
+}
+
+// Debits the payer and credits the merchant inside the caller's transaction
+pub async fn pay_invoice_with_wallet(
+    txn: &DatabaseTransaction,
+    env: &EnvConfig,
+    payer: &users::Model,
+    slug: &str,
+    amount: Option<Decimal>,
+) -> Result<(invoice_payments::Model, invoices::Model), InvoiceError> {
+    if !payer.is_verified {
+        return Err(MerchantError::PayerNotVerified.into());
+    }
+
+    let (invoice, merchant) = find_payable_invoice(txn, slug).await?;
+    if merchant.user_id == payer.uuid {
+        return Err(InvoiceError::SelfPayment);
+    }
+
+    let amount = payment_amount(&invoice, amount)?;
+    let invoice = apply_payment(txn, &invoice, amount, true)
+        .await?
+        .ok_or(InvoiceError::AmountTooHigh(invoice.balance_due()))?;
+
+    let transaction_id = Uuid::new_v4().to_string();
+    let meta = json!({
+        "invoice_id": &invoice.uuid,
+        "merchant_id": &merchant.uuid,
+        "business_name": &merchant.business_name,
+    });
+    let debit_id = debit_payer(
+        txn,
+        payer,
+        &merchant,
+        amount,
+        format!(
+            "Invoice {} - TO {}",
+            &invoice.number, &merchant.business_name
+        ),
+        &transaction_id,
+        meta,
+    )
+    .await?;
+
+    let payment = invoice_payments::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        invoice_id: Set(invoice.uuid.to_string()),
+        merchant_id: Set(merchant.uuid.to_string()),
+        payer_id: Set(Some(payer.uuid.to_string())),
+        payer_email: Set(payer.email.to_string()),
+        method: Set(LinkPaymentMethod::Wallet.to_string()),
+        amount: Set(amount),
+        fee: Set(merchant_fee(env, amount)),
+        reference: Set(Uuid::new_v4().to_string()),
+        provider: Set(String::from("money-transfer")),
+        status: Set(LinkPaymentStatus::Successful.to_string()),
+        transaction_id: Set(Some(transaction_id)),
+        paid_at: Set(Some(Utc::now())),
+        ..Default::default()
+    }
+    .insert(txn)
+    .await?;
+
+    credit_invoice_payment(txn, env, &merchant, &invoice, &payment, &debit_id, None).await?;
+
+    Ok((payment, invoice))
+}
+
+// As with payment links, the payment is recorded before the checkout is created and stays
+// pending until the charge is verified
+pub async fn start_invoice_card_payment(
+    db: &DatabaseConnection,
+    env: &EnvConfig,
+    slug: &str,
+    amount: Option<Decimal>,
+) -> Result<CardInvoicePayment, InvoiceError> {
+    let (invoice, merchant) = find_payable_invoice(db, slug).await?;
+    let amount = payment_amount(&invoice, amount)?;
+    let reference = Uuid::new_v4().to_string();
+
+    let payment = invoice_payments::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        invoice_id: Set(invoice.uuid.to_string()),
+        merchant_id: Set(merchant.uuid.to_string()),
+        payer_email: Set(invoice.customer_email.to_string()),
+        method: Set(LinkPaymentMethod::Card.to_string()),
+        amount: Set(amount),
+        fee: Set(merchant_fee(env, amount)),
+        reference: Set(reference.to_string()),
+        provider: Set(env.charge_providers.first().cloned().unwrap_or_default()),
+        status: Set(LinkPaymentStatus::Pending.to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    let charge = ChargeRequest {
+        reference,
+        email: invoice.customer_email.to_string(),
+        user_id: merchant.user_id.to_string(),
+        amount,
+    };
+
+    let authorization = match initialize_charge(&charge, env).await {
+        Ok(authorization) => authorization,
+        Err(err) => {
+            mark_card_payment_failed(db, &payment).await?;
+            return Err(InvoiceError::ProviderError(err));
+        }
+    };
+
+    let mut started: invoice_payments::ActiveModel = payment.into();
+    started.provider = Set(authorization.provider.to_string());
+    started.updated_at = Set(Utc::now());
+    let payment = started.update(db).await?;
+
+    Ok(CardInvoicePayment {
+        payment,
+        authorization,
+    })
+}
+
+pub async fn find_invoice_payment<C: ConnectionTrait>(
+    db: &C,
+    reference: &str,
+) -> Result<Option<invoice_payments::Model>, DbErr> {
+    InvoicePayments::find()
+        .filter(invoice_payments::Column::Reference.eq(reference))
+        .one(db)
+        .await
+}
+
+impl CardPaymentTable for InvoicePayments {
+    const ID: invoice_payments::Column = invoice_payments::Column::Id;
+    const REFERENCE: invoice_payments::Column = invoice_payments::Column::Reference;
+    const STATUS: invoice_payments::Column = invoice_payments::Column::Status;
+    const AMOUNT: invoice_payments::Column = invoice_payments::Column::Amount;
+    const FEE: invoice_payments::Column = invoice_payments::Column::Fee;
+    const TRANSACTION_ID: invoice_payments::Column = invoice_payments::Column::TransactionId;
+    const PAID_AT: invoice_payments::Column = invoice_payments::Column::PaidAt;
+    const CREATED_AT: invoice_payments::Column = invoice_payments::Column::CreatedAt;
+    const UPDATED_AT: invoice_payments::Column = invoice_payments::Column::UpdatedAt;
+}
+
+// It counts against the invoice even if that has been paid off meanwhile
+#[async_trait]
+impl MerchantCardPayment for invoice_payments::Model {
+    type Table = InvoicePayments;
+    type Error = InvoiceError;
+
+    const KIND: &'static str = "invoice payment";
+
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn reference(&self) -> &str {
+        &self.reference
+    }
+
+    fn status(&self) -> &str {
+        &self.status
+    }
+
+    fn provider(&self) -> &str {
+        &self.provider
+    }
+
+    fn amount(&self) -> Decimal {
+        self.amount
+    }
+
+    fn fee(&self) -> Decimal {
+        self.fee
+    }
+
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    fn not_found() -> InvoiceError {
+        InvoiceError::PaymentNotFound
+    }
+
+    fn settled(self, amount: Decimal, fee: Decimal, transaction_id: String) -> Self {
+        invoice_payments::Model {
+            amount,
+            fee,
+            transaction_id: Some(transaction_id),
+            ..self
+        }
+    }
+
+    async fn credit(
+        txn: &DatabaseTransaction,
+        env: &EnvConfig,
+        payment: &Self,
+        verification: &ChargeVerification,
+    ) -> Result<(), InvoiceError> {
+        let invoice = Invoices::find()
+            .filter(invoices::Column::Uuid.eq(&payment.invoice_id))
+            .one(txn)
+            .await?
+            .ok_or(InvoiceError::InvoiceNotFound)?;
+        let merchant = invoice_merchant(txn, &invoice).await?;
+
+        let invoice = apply_payment(txn, &invoice, payment.amount, false)
+            .await?
+            .ok_or(InvoiceError::InvoiceNotFound)?;
+        credit_invoice_payment(
+            txn,
+            env,
+            &merchant,
+            &invoice,
+            payment,
+            &verification.reference,
+            Some(verification.fees),
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+// For the hosted invoice to check on a card payment, settling it if the charge went through
+pub async fn check_invoice_card_payment(
+    db: &DatabaseConnection,
+    env: &EnvConfig,
+    slug: &str,
+    reference: &str,
+) -> Result<invoice_payments::Model, InvoiceError> {
+    let found = InvoicePayments::find()
+        .filter(invoice_payments::Column::Reference.eq(reference))
+        .find_also_related(Invoices)
+        .one(db)
+        .await?;
+
+    let payment = match found {
+        Some((payment, Some(invoice))) if invoice.slug == slug => payment,
+        _ => return Err(InvoiceError::PaymentNotFound),
+    };
+
+    if payment.status != LinkPaymentStatus::Pending.to_string() {
+        return Ok(payment);
+    }
+
+    match provider_by_name(&payment.provider, env) {
+        Some(provider) => {
+            settle_card_payment::<invoice_payments::Model>(provider.as_ref(), reference, db, env)
+                .await?;
+        }
+        None => warn!(
+            "Provider {} is not configured, cannot verify payment {}",
+            payment.provider, reference
+        ),
+    }
+
+    find_invoice_payment(db, reference)
+        .await?
+        .ok_or(InvoiceError::PaymentNotFound)
+}
+
+// Background worker reminding customers about overdue invoices. Spawned once on startup
+pub async fn run_invoice_reminder_worker(app_state: AppState) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        app_state.env.invoice_reminder_interval_secs,
+    ));
+
+    loop {
+        interval.tick().await;
+
+        match process_overdue_invoices(&app_state).await {
+            Ok(summary) if summary.reminded > 0 || summary.failed > 0 => info!(
+                "Reminded customers about {} overdue invoice(s), {} failed",
+                summary.reminded, summary.failed
+            ),
+            Ok(_) => {}
+            Err(err) => error!("Error checking overdue invoices: {}", err),
+        }
+    }
+}
+
+// Unpaid invoices past their due date get a reminder every so often, up to the max
+pub async fn process_overdue_invoices(
+    app_state: &AppState,
+) -> Result<InvoiceReminderSummary, DbErr> {
+    let env = &app_state.env;
+    let now = Utc::now();
+    let remind_before = now - Duration::hours(env.invoice_reminder_every_hours);
+
+    let overdue = Invoices::find()
+        .filter(invoices::Column::Status.is_in([
+            InvoiceStatus::Open.to_string(),
+            InvoiceStatus::PartiallyPaid.to_string(),
+        ]))
+        .filter(invoices::Column::DueDate.lt(now.date_naive()))
+        .filter(invoices::Column::ReminderCount.lt(env.invoice_max_reminders))
+        .filter(
+            Condition::any()
+                .add(invoices::Column::LastRemindedAt.is_null())
+                .add(invoices::Column::LastRemindedAt.lte(remind_before)),
+        )
+        .order_by_asc(invoices::Column::Id)
+        .all(&app_state.db)
+        .await?;
+
+    let mut summary = InvoiceReminderSummary::default();
+    for invoice in overdue {
+        match remind_customer(&app_state.db, env, &invoice).await {
+            Ok(true) => summary.reminded += 1,
+            Ok(false) => {}
+            Err(err) => {
+                error!(
+                    "Error reminding customer about invoice {} ===> {}",
+                    &invoice.uuid, err
+                );
+                summary.failed += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+// The reminder is counted before it is queued, so two workers can't both send it
+async fn remind_customer(
+    db: &DatabaseConnection,
+    env: &EnvConfig,
+    invoice: &invoices::Model,
+) -> Result<bool, InvoiceError> {
+    let txn = db.begin().await?;
+
+    let now = Utc::now();
+    let claimed = Invoices::update_many()
+        .col_expr(
+            invoices::Column::ReminderCount,
+            sea_query::Expr::value(invoice.reminder_count + 1),
+        )
+        .col_expr(
+            invoices::Column::LastRemindedAt,
+            sea_query::Expr::value(now),
+        )
+        .col_expr(invoices::Column::UpdatedAt, sea_query::Expr::value(now))
+        .filter(invoices::Column::Id.eq(invoice.id))
+        .filter(invoices::Column::ReminderCount.eq(invoice.reminder_count))
+        .exec(&txn)
+        .await?;
+    if claimed.rows_affected == 0 {
+        let _ = txn.rollback().await;
+        return Ok(false);
+    }
+
+    let merchant = invoice_merchant(&txn, invoice).await?;
+    let items = invoice_items(&txn, invoice).await?;
+    let context = invoice_context(env, invoice, &merchant, &items);
+    send_invoice_email(&txn, env, invoice, "reminder", &context).await?;
+
+    txn.commit().await?;
+
+    Ok(true)
+}
@@ -0,0 +1,865 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fmt;
+use thiserror::Error;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::entities::{
+    merchants, payment_link_payments, payment_links,
+    prelude::{Merchants, PaymentLinkPayments, PaymentLinks, Wallets},
+    sea_orm_active_enums::{Status, TrxType},
+    users, wallets,
+};
+use crate::utils::config::EnvConfig;
+use crate::utils::payment_provider::{
+    initialize_charge, provider_by_name, ChargeInitialization, ChargeRequest, ChargeVerification,
+    ProviderError,
+};
+
+use super::card_payment::{
+    mark_card_payment_failed, settle_card_payment, CardPaymentTable, MerchantCardPayment,
+};
+use super::outbound_webhook::{emit_webhook_event, WebhookEventType};
+use super::transaction_balance::{
+    lock_wallets, TransactionBalance, TransactionBalanceTrait, TrxCategory,
+};
+use super::wallet_hold::available_balance;
+
+#[derive(Debug, PartialEq)]
+pub enum PaymentLinkStatus {
+    Active,
+    Disabled,
+}
+
+impl fmt::Display for PaymentLinkStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            PaymentLinkStatus::Active => "active",
+            PaymentLinkStatus::Disabled => "disabled",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LinkPaymentMethod {
+    Wallet,
+    Card,
+}
+
+impl fmt::Display for LinkPaymentMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let method = match self {
+            LinkPaymentMethod::Wallet => "wallet",
+            LinkPaymentMethod::Card => "card",
+        };
+
+        write!(f, "{}", method)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LinkPaymentStatus {
+    Pending,
+    Successful,
+    Failed,
+}
+
+impl fmt::Display for LinkPaymentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            LinkPaymentStatus::Pending => "pending",
+            LinkPaymentStatus::Successful => "successful",
+            LinkPaymentStatus::Failed => "failed",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum MerchantError {
+    #[error("You already have a merchant profile")]
+    AlreadyMerchant,
+
+    #[error("Merchant profile not found")]
+    MerchantNotFound,
+
+    #[error("Settlement wallet not found")]
+    SettlementWalletNotFound,
+
+    #[error("Payment link not found")]
+    LinkNotFound,
+
+    #[error("Payment link has been disabled")]
+    LinkDisabled,
+
+    #[error("Payment link has expired")]
+    LinkExpired,
+
+    #[error("Payment link has reached its usage limit")]
+    LinkExhausted,
+
+    #[error("Please enter the amount you want to pay")]
+    AmountRequired,
+
+    #[error("Cannot pay your own payment link")]
+    SelfPayment,
+
+    #[error("Please verify your account before taking this action")]
+    PayerNotVerified,
+
+    #[error("You do not seem to have a valid wallet yet. Please contact support")]
+    PayerWalletNotFound,
+
+    #[error("Your wallet is frozen. Please contact support")]
+    WalletFrozen,
+
+    #[error("Insufficient Funds")]
+    InsufficientFunds,
+
+    #[error("Payment not found")]
+    PaymentNotFound,
+
+    #[error(transparent)]
+    ProviderError(#[from] ProviderError),
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl MerchantError {
+    pub fn is_client_error(&self) -> bool {
+        !matches!(
+            self,
+            MerchantError::ProviderError(_) | MerchantError::DatabaseError(_)
+        )
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(
+            self,
+            MerchantError::MerchantNotFound
+                | MerchantError::LinkNotFound
+                | MerchantError::PaymentNotFound
+        )
+    }
+}
+
+pub struct NewPaymentLink {
+    pub title: String,
+    pub description: Option<String>,
+    // None for an open amount the payer chooses
+    pub amount: Option<Decimal>,
+    pub max_uses: Option<i32>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+pub struct CardPayment {
+    pub payment: payment_link_payments::Model,
+    pub authorization: ChargeInitialization,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PaymentLinkReport {
+    pub payment_link: payment_links::Model,
+    pub successful_payments: usize,
+    pub pending_payments: usize,
+    pub failed_payments: usize,
+    pub gross_amount: Decimal,
+    pub fees: Decimal,
+    pub net_amount: Decimal,
+    pub payments: Vec<payment_link_payments::Model>,
+}
+
+// A percentage of the amount, capped
+pub fn merchant_fee(env: &EnvConfig, amount: Decimal) -> Decimal {
+    let fee = (amount * env.merchant_fee_percent / Decimal::from(100)).round_dp(2);
+
+    fee.min(env.merchant_fee_cap)
+}
+
+// The settlement wallet has to be one of the merchant's own, their default wallet unless chosen
+async fn settlement_wallet<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+    wallet_id: Option<&str>,
+) -> Result<wallets::Model, MerchantError> {
+    let mut wallet_query = Wallets::find().filter(wallets::Column::UserId.eq(user_id));
+    wallet_query = match wallet_id {
+        Some(wallet_id) => wallet_query.filter(wallets::Column::Uuid.eq(wallet_id)),
+        None => wallet_query.filter(wallets::Column::Default.eq(true)),
+    };
+
+    wallet_query
+        .one(db)
+        .await?
+        .ok_or(MerchantError::SettlementWalletNotFound)
+}
+
+pub async fn create_merchant<C: ConnectionTrait>(
+    db: &C,
+    user: &users::Model,
+    business_name: &str,
+    settlement_wallet_id: Option<&str>,
+) -> Result<merchants::Model, MerchantError> {
+    let existing = Merchants::find()
+        .filter(merchants::Column::UserId.eq(&user.uuid))
+        .one(db)
+        .await?;
+    if existing.is_some() {
+        return Err(MerchantError::AlreadyMerchant);
+    }
+
+    let wallet = settlement_wallet(db, &user.uuid, settlement_wallet_id).await?;
+
+    let merchant = merchants::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        user_id: Set(user.uuid.to_string()),
+        business_name: Set(business_name.trim().to_string()),
+        settlement_wallet_id: Set(wallet.uuid),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    Ok(merchant)
+}
+
+pub async fn find_merchant<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+) -> Result<merchants::Model, MerchantError> {
+    Merchants::find()
+        .filter(merchants::Column::UserId.eq(user_id))
+        .one(db)
+        .await?
+        .ok_or(MerchantError::MerchantNotFound)
+}
+
+pub async fn update_merchant<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+    business_name: Option<&str>,
+    settlement_wallet_id: Option<&str>,
+) -> Result<merchants::Model, MerchantError> {
+    let merchant = find_merchant(db, user_id).await?;
+
+    let mut updated: merchants::ActiveModel = merchant.into();
+    if let Some(business_name) = business_name {
+        updated.business_name = Set(business_name.trim().to_string());
+    }
+    if let Some(wallet_id) = settlement_wallet_id {
+        let wallet = settlement_wallet(db, user_id, Some(wallet_id)).await?;
+        updated.settlement_wallet_id = Set(wallet.uuid);
+    }
+    updated.updated_at = Set(Utc::now());
+
+    Ok(updated.update(db).await?)
+}
+
+pub async fn create_payment_link<C: ConnectionTrait>(
+    db: &C,
+    merchant: &merchants::Model,
+    link: NewPaymentLink,
+) -> Result<payment_links::Model, MerchantError> {
+    let slug = Uuid::new_v4().simple().to_string()[..12].to_string();
+
+    let payment_link = payment_links::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        merchant_id: Set(merchant.uuid.to_string()),
+        slug: Set(slug),
+        title: Set(link.title.trim().to_string()),
+        description: Set(link.description),
+        amount: Set(link.amount),
+        status: Set(PaymentLinkStatus::Active.to_string()),
+        max_uses: Set(link.max_uses),
+        expires_at: Set(link.expires_at),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    Ok(payment_link)
+}
+
+pub async fn merchant_payment_links<C: ConnectionTrait>(
+    db: &C,
+    merchant: &merchants::Model,
+) -> Result<Vec<payment_links::Model>, DbErr> {
+    PaymentLinks::find()
+        .filter(payment_links::Column::MerchantId.eq(&merchant.uuid))
+        .order_by_desc(payment_links::Column::Id)
+        .all(db)
+        .await
+}
+
+async fn find_merchant_link<C: ConnectionTrait>(
+    db: &C,
+    merchant: &merchants::Model,
+    link_id: &str,
+) -> Result<payment_links::Model, MerchantError> {
+    PaymentLinks::find()
+        .filter(payment_links::Column::Uuid.eq(link_id))
+        .filter(payment_links::Column::MerchantId.eq(&merchant.uuid))
+        .one(db)
+        .await?
+        .ok_or(MerchantError::LinkNotFound)
+}
+
+pub async fn disable_payment_link<C: ConnectionTrait>(
+    db: &C,
+    merchant: &merchants::Model,
+    link_id: &str,
+) -> Result<payment_links::Model, MerchantError> {
+    let link = find_merchant_link(db, merchant, link_id).await?;
+
+    let mut disabled: payment_links::ActiveModel = link.into();
+    disabled.status = Set(PaymentLinkStatus::Disabled.to_string());
+    disabled.updated_at = Set(Utc::now());
+
+    Ok(disabled.update(db).await?)
+}
+
+// A link and its merchant, as long as the link can still take payments
+pub async fn find_open_link<C: ConnectionTrait>(
+    db: &C,
+    slug: &str,
+) -> Result<(payment_links::Model, merchants::Model), MerchantError> {
+    let found = PaymentLinks::find()
+        .filter(payment_links::Column::Slug.eq(slug))
+        .find_also_related(Merchants)
+        .one(db)
+        .await?;
+
+    let (link, merchant) = match found {
+        Some((link, Some(merchant))) => (link, merchant),
+        _ => return Err(MerchantError::LinkNotFound),
+    };
+
+    if link.status != PaymentLinkStatus::Active.to_string() {
+        return Err(MerchantError::LinkDisabled);
+    }
+
+    if link
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now())
+    {
+        return Err(MerchantError::LinkExpired);
+    }
+
+    if link
+        .max_uses
+        .is_some_and(|max_uses| link.use_count >= max_uses)
+    {
+        return Err(MerchantError::LinkExhausted);
+    }
+
+    Ok((link, merchant))
+}
+
+// Fixed amount links ignore whatever amount the payer sends
+fn payment_amount(
+    link: &payment_links::Model,
+    amount: Option<Decimal>,
+) -> Result<Decimal, MerchantError> {
+    link.amount.or(amount).ok_or(MerchantError::AmountRequired)
+}
+
+// Counts a payment against the link. With a limit, the count only goes up while it is under it
+async fn claim_use<C: ConnectionTrait>(
+    db: &C,
+    link: &payment_links::Model,
+    max_uses: Option<i32>,
+) -> Result<bool, DbErr> {
+    let mut claim = PaymentLinks::update_many()
+        .col_expr(
+            payment_links::Column::UseCount,
+            sea_query::Expr::col(payment_links::Column::UseCount).add(1),
+        )
+        .col_expr(
+            payment_links::Column::UpdatedAt,
+            sea_query::Expr::value(Utc::now()),
+        )
+        .filter(payment_links::Column::Id.eq(link.id));
+
+    if let Some(max_uses) = max_uses {
+        claim = claim.filter(payment_links::Column::UseCount.lt(max_uses));
+    }
+
+    Ok(claim.exec(db).await?.rows_affected > 0)
+}
+
+// A payment to credit to a merchant's settlement wallet
+pub struct MerchantCredit {
+    pub transaction_id: String,
+    pub amount: Decimal,
+    pub fee: Decimal,
+    pub description: String,
+    pub provider: String,
+    pub provider_reference: String,
+    pub provider_fees: Option<Decimal>,
+    pub meta: Value,
+}
+
+// Credits the merchant the amount less our fee and returns their new balance. The credit carries
+// the gross amount with the fee in `fees`, so it matches the provider's charge when settlements
+// are reconciled
+pub async fn credit_merchant(
+    txn: &DatabaseTransaction,
+    merchant: &merchants::Model,
+    credit: MerchantCredit,
+) -> Result<Decimal, MerchantError> {
+    let wallet = lock_wallets(
+        txn,
+        Condition::all().add(wallets::Column::Uuid.eq(&merchant.settlement_wallet_id)),
+    )
+    .await?
+    .pop()
+    .ok_or(MerchantError::SettlementWalletNotFound)?;
+
+    let balance = wallet.current_balance + credit.amount - credit.fee;
+
+    TransactionBalance {
+        uuid: credit.transaction_id,
+        amount: credit.amount,
+        trx_type: TrxType::Credit,
+        status: Status::Successful,
+        description: credit.description,
+        provider_reference: Some(credit.provider_reference),
+        current_balance: balance,
+        previous_balance: wallet.current_balance,
+        user_id: wallet.user_id.to_string(),
+        wallet_id: wallet.uuid.to_string(),
+        provider: credit.provider,
+        fees: Some(credit.fee),
+        provider_fees: credit.provider_fees,
+        category: TrxCategory::MerchantPayment,
+        meta: Some(credit.meta.to_string()),
+    }
+    .save_transaction_update_balance(txn)
+    .await?;
+
+    Ok(balance)
+}
+
+// Debits the payer's default wallet for a payment to a merchant. The debit and the merchant's
+// credit reference each other. The merchant's settlement wallet is locked along with the payer's,
+// so the credit that follows doesn't take its lock out of order
+pub async fn debit_payer(
+    txn: &DatabaseTransaction,
+    payer: &users::Model,
+    merchant: &merchants::Model,
+    amount: Decimal,
+    description: String,
+    credit_id: &str,
+    meta: Value,
+) -> Result<String, MerchantError> {
+    let payer_wallet = lock_wallets(
+        txn,
+        Condition::any()
+            .add(
+                wallets::Column::UserId
+                    .eq(&payer.uuid)
+                    .and(wallets::Column::Default.eq(true)),
+            )
+            .add(wallets::Column::Uuid.eq(&merchant.settlement_wallet_id)),
+    )
+    .await?
+    .into_iter()
+    .find(|wallet| wallet.user_id == payer.uuid && wallet.default)
+    .ok_or(MerchantError::PayerWalletNotFound)?;
+
+    if payer_wallet.is_frozen() {
+        return Err(MerchantError::WalletFrozen);
+    }
+
+    if amount > available_balance(txn, &payer_wallet).await? {
+        return Err(MerchantError::InsufficientFunds);
+    }
+
+    let debit_id = Uuid::new_v4().to_string();
+    TransactionBalance {
+        uuid: debit_id.to_string(),
+        amount,
+        trx_type: TrxType::Debit,
+        status: Status::Successful,
+        description,
+        provider_reference: Some(credit_id.to_string()),
+        current_balance: payer_wallet.current_balance - amount,
+        previous_balance: payer_wallet.current_balance,
+        user_id: payer_wallet.user_id.to_string(),
+        wallet_id: payer_wallet.uuid.to_string(),
+        provider: String::from("money-transfer"),
+        fees: None,
+        provider_fees: None,
+        category: TrxCategory::MerchantPayment,
+        meta: Some(meta.to_string()),
+    }
+    .save_transaction_update_balance(txn)
+    .await?;
+
+    Ok(debit_id)
+}
+
+// Credits the merchant for a payment on one of their links and lets them know
+async fn credit_link_payment(
+    txn: &DatabaseTransaction,
+    merchant: &merchants::Model,
+    link: &payment_links::Model,
+    payment: &payment_link_payments::Model,
+    provider_reference: &str,
+    provider_fees: Option<Decimal>,
+) -> Result<(), MerchantError> {
+    let transaction_id = payment.transaction_id.clone().unwrap_or_default();
+    let credit = MerchantCredit {
+        transaction_id,
+        amount: payment.amount,
+        fee: payment.fee,
+        description: format!("{} - FROM {}", &link.title, &payment.payer_email),
+        provider: payment.provider.to_string(),
+        provider_reference: provider_reference.to_string(),
+        provider_fees,
+        meta: json!({
+            "payment_link_id": &link.uuid,
+            "payment_id": &payment.uuid,
+            "payer_email": &payment.payer_email,
+            "method": &payment.method,
+        }),
+    };
+    let balance = credit_merchant(txn, merchant, credit).await?;
+
+    let paid = json!({
+        "reference": &payment.reference,
+        "payment_link_id": &link.uuid,
+        "slug": &link.slug,
+        "method": &payment.method,
+        "amount": payment.amount,
+        "fee": payment.fee,
+        "net_amount": payment.amount - payment.fee,
+        "wallet_id": &merchant.settlement_wallet_id,
+        "balance": balance,
+        "payer_email": &payment.payer_email,
+    });
+    emit_webhook_event(
+        txn,
+        &merchant.user_id,
+        WebhookEventType::PaymentLinkPaid,
+        paid,
+    )
+    .await?;
+
+    Ok(())
+}
+
+// Debits the payer and credits the merchant inside the caller's transaction
+pub async fn pay_with_wallet(
+    txn: &DatabaseTransaction,
+    env: &EnvConfig,
+    payer: &users::Model,
+    slug: &str,
+    amount: Option<Decimal>,
+) -> Result<payment_link_payments::Model, MerchantError> {
+    if !payer.is_verified {
+        return Err(MerchantError::PayerNotVerified);
+    }
+
+    let (link, merchant) = find_open_link(txn, slug).await?;
+    if merchant.user_id == payer.uuid {
+        return Err(MerchantError::SelfPayment);
+    }
+
+    let amount = payment_amount(&link, amount)?;
+
+    if !claim_use(txn, &link, link.max_uses).await? {
+        return Err(MerchantError::LinkExhausted);
+    }
+
+    let transaction_id = Uuid::new_v4().to_string();
+    let meta = json!({
+        "payment_link_id": &link.uuid,
+        "merchant_id": &merchant.uuid,
+        "business_name": &merchant.business_name,
+    });
+    let debit_id = debit_payer(
+        txn,
+        payer,
+        &merchant,
+        amount,
+        format!("{} - TO {}", &link.title, &merchant.business_name),
+        &transaction_id,
+        meta,
+    )
+    .await?;
+
+    let payment = payment_link_payments::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        payment_link_id: Set(link.uuid.to_string()),
+        merchant_id: Set(merchant.uuid.to_string()),
+        payer_id: Set(Some(payer.uuid.to_string())),
+        payer_email: Set(payer.email.to_string()),
+        method: Set(LinkPaymentMethod::Wallet.to_string()),
+        amount: Set(amount),
+        fee: Set(merchant_fee(env, amount)),
+        reference: Set(Uuid::new_v4().to_string()),
+        provider: Set(String::from("money-transfer")),
+        status: Set(LinkPaymentStatus::Successful.to_string()),
+        transaction_id: Set(Some(transaction_id)),
+        paid_at: Set(Some(Utc::now())),
+        ..Default::default()
+    }
+    .insert(txn)
+    .await?;
+
+    credit_link_payment(txn, &merchant, &link, &payment, &debit_id, None).await?;
+
+    Ok(payment)
+}
+
+// The payment is recorded before the checkout is created so a webhook can never arrive for a
+// reference we don't know. It stays pending until the charge is verified
+pub async fn start_card_payment(
+    db: &DatabaseConnection,
+    env: &EnvConfig,
+    slug: &str,
+    email: &str,
+    amount: Option<Decimal>,
+) -> Result<CardPayment, MerchantError> {
+    let (link, merchant) = find_open_link(db, slug).await?;
+    let amount = payment_amount(&link, amount)?;
+    let reference = Uuid::new_v4().to_string();
+
+    let payment = payment_link_payments::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        payment_link_id: Set(link.uuid.to_string()),
+        merchant_id: Set(merchant.uuid.to_string()),
+        payer_email: Set(email.to_string()),
+        method: Set(LinkPaymentMethod::Card.to_string()),
+        amount: Set(amount),
+        fee: Set(merchant_fee(env, amount)),
+        reference: Set(reference.to_string()),
+        provider: Set(env.charge_providers.first().cloned().unwrap_or_default()),
+        status: Set(LinkPaymentStatus::Pending.to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    let charge = ChargeRequest {
+        reference,
+        email: email.to_string(),
+        user_id: merchant.user_id.to_string(),
+        amount,
+    };
+
+    let authorization = match initialize_charge(&charge, env).await {
+        Ok(authorization) => authorization,
+        Err(err) => {
+            mark_card_payment_failed(db, &payment).await?;
+            return Err(MerchantError::ProviderError(err));
+        }
+    };
+
+    // Another provider may have taken the charge when the preferred one was down
+    let mut started: payment_link_payments::ActiveModel = payment.into();
+    started.provider = Set(authorization.provider.to_string());
+    started.updated_at = Set(Utc::now());
+    let payment = started.update(db).await?;
+
+    Ok(CardPayment {
+        payment,
+        authorization,
+    })
+}
+
+pub async fn find_link_payment<C: ConnectionTrait>(
+    db: &C,
+    reference: &str,
+) -> Result<Option<payment_link_payments::Model>, DbErr> {
+    PaymentLinkPayments::find()
+        .filter(payment_link_payments::Column::Reference.eq(reference))
+        .one(db)
+        .await
+}
+
+impl CardPaymentTable for PaymentLinkPayments {
+    const ID: payment_link_payments::Column = payment_link_payments::Column::Id;
+    const REFERENCE: payment_link_payments::Column = payment_link_payments::Column::Reference;
+    const STATUS: payment_link_payments::Column = payment_link_payments::Column::Status;
+    const AMOUNT: payment_link_payments::Column = payment_link_payments::Column::Amount;
+    const FEE: payment_link_payments::Column = payment_link_payments::Column::Fee;
+    const TRANSACTION_ID: payment_link_payments::Column =
+        payment_link_payments::Column::TransactionId;
+    const PAID_AT: payment_link_payments::Column = payment_link_payments::Column::PaidAt;
+    const CREATED_AT: payment_link_payments::Column = payment_link_payments::Column::CreatedAt;
+    const UPDATED_AT: payment_link_payments::Column = payment_link_payments::Column::UpdatedAt;
+}
+
+// The use is counted even past the link's limit, the payer has already been charged by then
+#[async_trait]
+impl MerchantCardPayment for payment_link_payments::Model {
+    type Table = PaymentLinkPayments;
+    type Error = MerchantError;
+
+    const KIND: &'static str = "payment link payment";
+
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn reference(&self) -> &str {
+        &self.reference
+    }
+
+    fn status(&self) -> &str {
+        &self.status
+    }
+
+    fn provider(&self) -> &str {
+        &self.provider
+    }
+
+    fn amount(&self) -> Decimal {
+        self.amount
+    }
+
+    fn fee(&self) -> Decimal {
+        self.fee
+    }
+
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    fn not_found() -> MerchantError {
+        MerchantError::PaymentNotFound
+    }
+
+    fn settled(self, amount: Decimal, fee: Decimal, transaction_id: String) -> Self {
+        payment_link_payments::Model {
+            amount,
+            fee,
+            transaction_id: Some(transaction_id),
+            ..self
+        }
+    }
+
+    async fn credit(
+        txn: &DatabaseTransaction,
+        _env: &EnvConfig,
+        payment: &Self,
+        verification: &ChargeVerification,
+    ) -> Result<(), MerchantError> {
+        let link = PaymentLinks::find()
+            .filter(payment_links::Column::Uuid.eq(&payment.payment_link_id))
+            .one(txn)
+            .await?
+            .ok_or(MerchantError::LinkNotFound)?;
+        let merchant = Merchants::find()
+            .filter(merchants::Column::Uuid.eq(&payment.merchant_id))
+            .one(txn)
+            .await?
+            .ok_or(MerchantError::MerchantNotFound)?;
+
+        claim_use(txn, &link, None).await?;
+        credit_link_payment(
+            txn,
+            &merchant,
+            &link,
+            payment,
+            &verification.reference,
+            Some(verification.fees),
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+// For the hosted page to check on a card payment, settling it if the charge went through
+pub async fn check_card_payment(
+    db: &DatabaseConnection,
+    env: &EnvConfig,
+    slug: &str,
+    reference: &str,
+) -> Result<payment_link_payments::Model, MerchantError> {
+    let found = PaymentLinkPayments::find()
+        .filter(payment_link_payments::Column::Reference.eq(reference))
+        .find_also_related(PaymentLinks)
+        .one(db)
+        .await?;
+
+    let payment = match found {
+        Some((payment, Some(link))) if link.slug == slug => payment,
+        _ => return Err(MerchantError::PaymentNotFound),
+    };
+
+    if payment.status != LinkPaymentStatus::Pending.to_string() {
+        return Ok(payment);
+    }
+
+    match provider_by_name(&payment.provider, env) {
+        Some(provider) => {
+            settle_card_payment::<payment_link_payments::Model>(
+                provider.as_ref(),
+                reference,
+                db,
+                env,
+            )
+            .await?;
+        }
+        None => warn!(
+            "Provider {} is not configured, cannot verify payment {}",
+            payment.provider, reference
+        ),
+    }
+
+    find_link_payment(db, reference)
+        .await?
+        .ok_or(MerchantError::PaymentNotFound)
+}
+
+pub async fn payment_link_report<C: ConnectionTrait>(
+    db: &C,
+    merchant: &merchants::Model,
+    link_id: &str,
+) -> Result<PaymentLinkReport, MerchantError> {
+    let link = find_merchant_link(db, merchant, link_id).await?;
+
+    let payments = PaymentLinkPayments::find()
+        .filter(payment_link_payments::Column::PaymentLinkId.eq(&link.uuid))
+        .order_by_desc(payment_link_payments::Column::Id)
+        .all(db)
+        .await?;
+
+    let count = |status: LinkPaymentStatus| {
+        payments
+            .iter()
+            .filter(|payment| payment.status == status.to_string())
+            .count()
+    };
+
+    let successful: Vec<_> = payments
+        .iter()
+        .filter(|payment| payment.status == LinkPaymentStatus::Successful.to_string())
+        .collect();
+    let gross_amount: Decimal = successful.iter().map(|payment| payment.amount).sum();
+    let fees: Decimal = successful.iter().map(|payment| payment.fee).sum();
+
+    Ok(PaymentLinkReport {
+        successful_payments: successful.len(),
+        pending_payments: count(LinkPaymentStatus::Pending),
+        failed_payments: count(LinkPaymentStatus::Failed),
+        gross_amount,
+        fees,
+        net_amount: gross_amount - fees,
+        payment_link: link,
+        payments,
+    })
+}
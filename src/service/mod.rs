@@ -1,2 +1,26 @@
-pub mod paystack_webhook;
+pub mod api_key;
+pub mod bill;
+pub mod card_payment;
+pub mod dispute;
+pub mod escrow;
+pub mod funding;
+pub mod invoice;
+pub mod merchant;
+pub mod notification;
+pub mod outbound_webhook;
+pub mod outbox;
+pub mod outward_transfer;
+pub mod p2p_transfer;
+pub mod payment_method;
+pub mod payment_request;
+pub mod provider_webhook;
+pub mod qr_payment;
+pub mod reversal;
+pub mod scheduled_transfer;
+pub mod settlement_reconciliation;
 pub mod transaction_balance;
+pub mod transfer_batch;
+pub mod virtual_account;
+pub mod virtual_card;
+pub mod wallet_hold;
+pub mod wallet_reconciliation;
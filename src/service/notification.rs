@@ -0,0 +1,515 @@
+use chrono::Utc;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
+use tera::Context;
+use thiserror::Error;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::entities::{
+    device_tokens, notification_preferences,
+    prelude::{DeviceTokens, NotificationPreferences, Users},
+    users,
+};
+use crate::utils::{
+    config::EnvConfig,
+    email_template::{render_email, render_message},
+    fcm::FcmPush,
+    notification_channel::{ChannelError, NotificationChannel, NotificationSink},
+    send_email::{Mailer, SendEmail, SmtpMailer},
+    termii::TermiiSms,
+};
+use crate::AppState;
+
+use super::outbox::{enqueue, DeliveryError, OutboxKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    VerifyAccount,
+    PasswordReset,
+    TransferSent,
+    TransferReceived,
+    WalletFunded,
+    PinChanged,
+    NewLogin,
+    ScheduledTransferFailed,
+    DisputeUpdate,
+    EscrowUpdate,
+}
+
+impl NotificationEvent {
+    pub const ALL: [NotificationEvent; 10] = [
+        NotificationEvent::VerifyAccount,
+        NotificationEvent::PasswordReset,
+        NotificationEvent::TransferSent,
+        NotificationEvent::TransferReceived,
+        NotificationEvent::WalletFunded,
+        NotificationEvent::PinChanged,
+        NotificationEvent::NewLogin,
+        NotificationEvent::ScheduledTransferFailed,
+        NotificationEvent::DisputeUpdate,
+        NotificationEvent::EscrowUpdate,
+    ];
+
+    pub fn parse(event: &str) -> Option<NotificationEvent> {
+        NotificationEvent::ALL
+            .into_iter()
+            .find(|known| known.to_string() == event)
+    }
+
+    // Account security emails can't be turned off
+    pub fn requires_email(&self) -> bool {
+        matches!(
+            self,
+            NotificationEvent::VerifyAccount | NotificationEvent::PasswordReset
+        )
+    }
+
+    // Used until the user says otherwise. SMS costs money so it is kept to money movements and PIN
+    // changes, the links in account emails are no use on a phone's lock screen
+    pub fn enabled_by_default(&self, channel: Channel) -> bool {
+        match channel {
+            Channel::Email => true,
+            Channel::Sms => matches!(
+                self,
+                NotificationEvent::TransferSent
+                    | NotificationEvent::TransferReceived
+                    | NotificationEvent::WalletFunded
+                    | NotificationEvent::PinChanged
+            ),
+            Channel::Push => !self.requires_email(),
+        }
+    }
+}
+
+impl fmt::Display for NotificationEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let event = match self {
+            NotificationEvent::VerifyAccount => "verify_account",
+            NotificationEvent::PasswordReset => "password_reset",
+            NotificationEvent::TransferSent => "transfer_sent",
+            NotificationEvent::TransferReceived => "transfer_received",
+            NotificationEvent::WalletFunded => "wallet_funded",
+            NotificationEvent::PinChanged => "pin_changed",
+            NotificationEvent::NewLogin => "new_login",
+            NotificationEvent::ScheduledTransferFailed => "scheduled_transfer_failed",
+            NotificationEvent::DisputeUpdate => "dispute_update",
+            NotificationEvent::EscrowUpdate => "escrow_update",
+        };
+
+        write!(f, "{}", event)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+    #[default]
+    Email,
+    Sms,
+    Push,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 3] = [Channel::Email, Channel::Sms, Channel::Push];
+
+    pub fn parse(channel: &str) -> Option<Channel> {
+        Channel::ALL
+            .into_iter()
+            .find(|known| known.to_string() == channel)
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let channel = match self {
+            Channel::Email => "email",
+            Channel::Sms => "sms",
+            Channel::Push => "push",
+        };
+
+        write!(f, "{}", channel)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub event: NotificationEvent,
+    pub user_id: String,
+    // Messages queued before channels existed were all emails
+    #[serde(default)]
+    pub channel: Channel,
+    // Template variables, the user's name and the app URL are added when rendering
+    pub data: Value,
+}
+
+#[derive(Error, Debug)]
+pub enum NotificationError {
+    #[error("Device not found")]
+    DeviceNotFound,
+
+    #[error("Unknown notification event {0}")]
+    UnknownEvent(String),
+
+    #[error("Unknown notification channel {0}")]
+    UnknownChannel(String),
+
+    #[error("Emails for {0} can't be turned off")]
+    EmailRequired(NotificationEvent),
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl NotificationError {
+    pub fn is_client_error(&self) -> bool {
+        matches!(
+            self,
+            NotificationError::UnknownEvent(_)
+                | NotificationError::UnknownChannel(_)
+                | NotificationError::EmailRequired(_)
+        )
+    }
+}
+
+// Where each channel's messages go. Messages for a channel that isn't configured are
+// dead-lettered so they can be retried once it is
+#[derive(Clone, Default)]
+pub struct Notifier {
+    pub mailer: Option<Arc<dyn Mailer>>,
+    pub sms: Option<Arc<dyn NotificationChannel>>,
+    pub push: Option<Arc<dyn NotificationChannel>>,
+}
+
+impl Notifier {
+    pub fn new(mailer: Arc<dyn Mailer>) -> Notifier {
+        Notifier {
+            mailer: Some(mailer),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_sms(mut self, sms: Arc<dyn NotificationChannel>) -> Notifier {
+        self.sms = Some(sms);
+        self
+    }
+
+    pub fn with_push(mut self, push: Arc<dyn NotificationChannel>) -> Notifier {
+        self.push = Some(push);
+        self
+    }
+
+    // With a sink configured nothing leaves the machine, every channel writes to it
+    pub fn from_env(env: &EnvConfig) -> Notifier {
+        if !env.notification_sink.is_empty() {
+            let sink = &env.notification_sink;
+            return Notifier::new(Arc::new(NotificationSink::new("email", sink)))
+                .with_sms(Arc::new(NotificationSink::new("sms", sink)))
+                .with_push(Arc::new(NotificationSink::new("push", sink)));
+        }
+
+        let mut notifier = match SmtpMailer::new(env) {
+            Ok(mailer) => Notifier::new(Arc::new(mailer)),
+            Err(err) => {
+                warn!("Emails can't be sent: {}", err);
+                Notifier::default()
+            }
+        };
+        if !env.termii_api_key.is_empty() {
+            notifier = notifier.with_sms(Arc::new(TermiiSms::new(env)));
+        }
+        if !env.fcm_server_key.is_empty() {
+            notifier = notifier.with_push(Arc::new(FcmPush::new(env)));
+        }
+
+        notifier
+    }
+
+    pub fn mailer(&self) -> Result<&dyn Mailer, DeliveryError> {
+        self.mailer
+            .as_deref()
+            .ok_or(DeliveryError::ChannelNotConfigured(Channel::Email))
+    }
+}
+
+// Queued on the connection or transaction making the change, so a rolled back change notifies
+// nobody. One message goes out per channel the user wants this event on and can be reached by.
+// The user is looked up and the message rendered in their locale when it is delivered
+pub async fn notify<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+    event: NotificationEvent,
+    data: Value,
+) -> Result<(), DbErr> {
+    for channel in channels_for(db, user_id, event).await? {
+        let notification = Notification {
+            event,
+            user_id: user_id.to_string(),
+            channel,
+            data: data.clone(),
+        };
+
+        enqueue(db, OutboxKind::Notification, &notification).await?;
+    }
+
+    Ok(())
+}
+
+async fn channels_for<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+    event: NotificationEvent,
+) -> Result<Vec<Channel>, DbErr> {
+    let preferences = NotificationPreferences::find()
+        .filter(notification_preferences::Column::UserId.eq(user_id))
+        .filter(notification_preferences::Column::Event.eq(event.to_string()))
+        .all(db)
+        .await?;
+
+    let mut channels = Vec::new();
+    for channel in Channel::ALL {
+        let enabled = preferences
+            .iter()
+            .find(|preference| preference.channel == channel.to_string())
+            .map(|preference| preference.enabled)
+            .unwrap_or(event.enabled_by_default(channel));
+
+        let reachable = match channel {
+            Channel::Email => true,
+            Channel::Sms => {
+                Users::find()
+                    .filter(users::Column::Uuid.eq(user_id))
+                    .filter(users::Column::PhoneNumber.is_not_null())
+                    .count(db)
+                    .await?
+                    > 0
+            }
+            Channel::Push => {
+                DeviceTokens::find()
+                    .filter(device_tokens::Column::UserId.eq(user_id))
+                    .count(db)
+                    .await?
+                    > 0
+            }
+        };
+
+        if (enabled || (channel == Channel::Email && event.requires_email())) && reachable {
+            channels.push(channel);
+        }
+    }
+
+    Ok(channels)
+}
+
+pub async fn send_notification(
+    app_state: &AppState,
+    notifier: &Notifier,
+    notification: &Notification,
+) -> Result<(), DeliveryError> {
+    let user = Users::find()
+        .filter(users::Column::Uuid.eq(&notification.user_id))
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(|| DeliveryError::UserNotFound(notification.user_id.to_string()))?;
+
+    let mut context = Context::from_value(notification.data.clone())?;
+    context.insert("first_name", &user.first_name);
+    context.insert("app_base_url", &app_state.env.app_base_url);
+    let name = notification.event.to_string();
+
+    match notification.channel {
+        Channel::Email => {
+            let email = render_email(&name, &user.locale, &context)?;
+            notifier
+                .mailer()?
+                .send(&SendEmail {
+                    to: user.email,
+                    from: app_state.env.from_email.to_string(),
+                    subject: email.subject,
+                    template: email.html,
+                    text: Some(email.text),
+                })
+                .await?;
+        }
+        Channel::Sms => {
+            let sms = notifier
+                .sms
+                .as_ref()
+                .ok_or(DeliveryError::ChannelNotConfigured(Channel::Sms))?;
+            // Removed since the message was queued
+            let phone_number = user
+                .phone_number
+                .as_ref()
+                .ok_or_else(|| ChannelError::InvalidRecipient(user.uuid.to_string()))?;
+
+            let message = render_message(&name, &user.locale, &context)?;
+            sms.send(phone_number, &message).await?;
+        }
+        Channel::Push => {
+            let push = notifier
+                .push
+                .as_ref()
+                .ok_or(DeliveryError::ChannelNotConfigured(Channel::Push))?;
+            let devices = DeviceTokens::find()
+                .filter(device_tokens::Column::UserId.eq(&user.uuid))
+                .order_by_asc(device_tokens::Column::Id)
+                .all(&app_state.db)
+                .await?;
+
+            let message = render_message(&name, &user.locale, &context)?;
+            for device in devices {
+                match push.send(&device.token, &message).await {
+                    Ok(_) => {}
+                    // The app was uninstalled or the token rotated, stop sending to it
+                    Err(ChannelError::InvalidRecipient(_)) => {
+                        warn!("Removing unregistered device {}", device.uuid);
+                        device.delete(&app_state.db).await?;
+                    }
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct EventPreferences {
+    pub event: NotificationEvent,
+    pub email: bool,
+    pub sms: bool,
+    pub push: bool,
+}
+
+// Every event with the channels it goes out on, stored choices over the defaults
+pub async fn user_preferences<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+) -> Result<Vec<EventPreferences>, DbErr> {
+    let stored = NotificationPreferences::find()
+        .filter(notification_preferences::Column::UserId.eq(user_id))
+        .all(db)
+        .await?;
+
+    let enabled = |event: NotificationEvent, channel: Channel| {
+        stored
+            .iter()
+            .find(|preference| {
+                preference.event == event.to_string() && preference.channel == channel.to_string()
+            })
+            .map(|preference| preference.enabled)
+            .unwrap_or(event.enabled_by_default(channel))
+    };
+
+    Ok(NotificationEvent::ALL
+        .into_iter()
+        .map(|event| EventPreferences {
+            event,
+            email: enabled(event, Channel::Email) || event.requires_email(),
+            sms: enabled(event, Channel::Sms),
+            push: enabled(event, Channel::Push),
+        })
+        .collect())
+}
+
+pub async fn set_preference<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+    event: &str,
+    channel: &str,
+    enabled: bool,
+) -> Result<(), NotificationError> {
+    let event = NotificationEvent::parse(event)
+        .ok_or_else(|| NotificationError::UnknownEvent(event.to_string()))?;
+    let channel = Channel::parse(channel)
+        .ok_or_else(|| NotificationError::UnknownChannel(channel.to_string()))?;
+
+    if channel == Channel::Email && event.requires_email() && !enabled {
+        return Err(NotificationError::EmailRequired(event));
+    }
+
+    let existing = NotificationPreferences::find()
+        .filter(notification_preferences::Column::UserId.eq(user_id))
+        .filter(notification_preferences::Column::Event.eq(event.to_string()))
+        .filter(notification_preferences::Column::Channel.eq(channel.to_string()))
+        .one(db)
+        .await?;
+
+    match existing {
+        Some(preference) => {
+            let mut preference: notification_preferences::ActiveModel = preference.into();
+            preference.enabled = Set(enabled);
+            preference.updated_at = Set(Utc::now());
+            preference.update(db).await?;
+        }
+        None => {
+            notification_preferences::ActiveModel {
+                uuid: Set(Uuid::new_v4().to_string()),
+                user_id: Set(user_id.to_string()),
+                event: Set(event.to_string()),
+                channel: Set(channel.to_string()),
+                enabled: Set(enabled),
+                ..Default::default()
+            }
+            .insert(db)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+// A token belongs to one app install, so registering it again moves it to whoever is signed in
+pub async fn register_device<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+    token: &str,
+    platform: &str,
+) -> Result<device_tokens::Model, DbErr> {
+    let existing = DeviceTokens::find()
+        .filter(device_tokens::Column::Token.eq(token))
+        .one(db)
+        .await?;
+
+    match existing {
+        Some(device) => {
+            let mut device: device_tokens::ActiveModel = device.into();
+            device.user_id = Set(user_id.to_string());
+            device.platform = Set(platform.to_string());
+            device.updated_at = Set(Utc::now());
+            device.update(db).await
+        }
+        None => {
+            device_tokens::ActiveModel {
+                uuid: Set(Uuid::new_v4().to_string()),
+                user_id: Set(user_id.to_string()),
+                token: Set(token.to_string()),
+                platform: Set(platform.to_string()),
+                ..Default::default()
+            }
+            .insert(db)
+            .await
+        }
+    }
+}
+
+pub async fn remove_device<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+    device_id: &str,
+) -> Result<(), NotificationError> {
+    let device = DeviceTokens::find()
+        .filter(device_tokens::Column::Uuid.eq(device_id))
+        .filter(device_tokens::Column::UserId.eq(user_id))
+        .one(db)
+        .await?
+        .ok_or(NotificationError::DeviceNotFound)?;
+
+    device.delete(db).await?;
+    Ok(())
+}
@@ -0,0 +1,487 @@
+use chrono::Utc;
+use reqwest::{header, redirect, Client, Url};
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::entities::{
+    prelude::{WebhookDeliveries, WebhookSubscriptions},
+    webhook_deliveries, webhook_subscriptions,
+};
+use crate::utils::config::EnvConfig;
+use crate::utils::helpers::{generate_token, sign_payload};
+use crate::AppState;
+
+use super::outbox::{enqueue, DeliveryError, OutboxKind};
+
+// Hex encoded HMAC-SHA512 of the raw body with the subscription's secret
+pub const SIGNATURE_HEADER: &str = "x-moneytransfer-signature";
+pub const EVENT_HEADER: &str = "x-moneytransfer-event";
+pub const DELIVERY_HEADER: &str = "x-moneytransfer-delivery";
+
+// Most recent first
+const MAX_DELIVERIES_LISTED: u64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WebhookEventType {
+    TransferCompleted,
+    WalletFunded,
+    WithdrawalFailed,
+    PaymentLinkPaid,
+    InvoicePaymentReceived,
+}
+
+impl WebhookEventType {
+    pub const ALL: [WebhookEventType; 5] = [
+        WebhookEventType::TransferCompleted,
+        WebhookEventType::WalletFunded,
+        WebhookEventType::WithdrawalFailed,
+        WebhookEventType::PaymentLinkPaid,
+        WebhookEventType::InvoicePaymentReceived,
+    ];
+
+    pub fn parse(event_type: &str) -> Option<WebhookEventType> {
+        WebhookEventType::ALL
+            .into_iter()
+            .find(|known| known.to_string() == event_type)
+    }
+}
+
+impl fmt::Display for WebhookEventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let event_type = match self {
+            WebhookEventType::TransferCompleted => "transfer.completed",
+            WebhookEventType::WalletFunded => "wallet.funded",
+            WebhookEventType::WithdrawalFailed => "withdrawal.failed",
+            WebhookEventType::PaymentLinkPaid => "payment_link.paid",
+            WebhookEventType::InvoicePaymentReceived => "invoice.payment_received",
+        };
+
+        write!(f, "{}", event_type)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Delivered,
+    // The last attempt failed and another one is scheduled
+    Retrying,
+    // Out of attempts, only a manual redelivery sends it again
+    Failed,
+    // The subscription was deleted before the event could be delivered
+    Cancelled,
+}
+
+impl fmt::Display for WebhookDeliveryStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            WebhookDeliveryStatus::Pending => "pending",
+            WebhookDeliveryStatus::Delivered => "delivered",
+            WebhookDeliveryStatus::Retrying => "retrying",
+            WebhookDeliveryStatus::Failed => "failed",
+            WebhookDeliveryStatus::Cancelled => "cancelled",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum WebhookError {
+    #[error("Webhook URL must be an https URL")]
+    InvalidUrl,
+
+    #[error("Webhook URL host could not be resolved")]
+    UnresolvableHost,
+
+    #[error("Webhook URL must not point at a private or local address")]
+    PrivateAddress,
+
+    #[error("Subscribe to at least one event type")]
+    NoEventTypes,
+
+    #[error("Unknown event type {0}")]
+    UnknownEventType(String),
+
+    #[error("Webhook subscription not found")]
+    SubscriptionNotFound,
+
+    #[error("Webhook delivery not found")]
+    DeliveryNotFound,
+
+    #[error("Failed to generate a signing secret")]
+    SecretError,
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl WebhookError {
+    pub fn is_client_error(&self) -> bool {
+        matches!(
+            self,
+            WebhookError::InvalidUrl
+                | WebhookError::UnresolvableHost
+                | WebhookError::PrivateAddress
+                | WebhookError::NoEventTypes
+                | WebhookError::UnknownEventType(_)
+        )
+    }
+}
+
+// What the outbox carries, the event itself is stored on the delivery
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookJob {
+    pub delivery_id: String,
+}
+
+// Where a delivery is sent. The request is pinned to the address that was checked, so the host
+// can't resolve somewhere else between the check and the connection
+struct WebhookTarget {
+    host: String,
+    address: SocketAddr,
+}
+
+// Loopback, private, link-local and other addresses that aren't on the internet
+fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let octets = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || octets[0] == 0
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (octets[0] == 100 && octets[1] & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_address(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // Unique local fc00::/7 and link-local fe80::/10
+                    || first & 0xfe00 == 0xfc00
+                    || first & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
+
+// Checked when subscribing and again before every delivery, as DNS can change in between
+async fn resolve_webhook_url(url: &str, env: &EnvConfig) -> Result<WebhookTarget, WebhookError> {
+    let url = Url::parse(url).map_err(|_| WebhookError::InvalidUrl)?;
+    let insecure = env.webhook_allow_insecure_urls;
+    if !(url.scheme() == "https" || (insecure && url.scheme() == "http")) {
+        return Err(WebhookError::InvalidUrl);
+    }
+
+    let host = url.host_str().ok_or(WebhookError::InvalidUrl)?;
+    let port = url
+        .port_or_known_default()
+        .ok_or(WebhookError::InvalidUrl)?;
+    let addresses: Vec<SocketAddr> =
+        tokio::net::lookup_host((host.trim_matches(|c| c == '[' || c == ']'), port))
+            .await
+            .map_err(|_| WebhookError::UnresolvableHost)?
+            .collect();
+
+    if !insecure
+        && addresses
+            .iter()
+            .any(|address| !is_public_address(address.ip()))
+    {
+        return Err(WebhookError::PrivateAddress);
+    }
+
+    let address = *addresses.first().ok_or(WebhookError::UnresolvableHost)?;
+    Ok(WebhookTarget {
+        host: host.to_string(),
+        address,
+    })
+}
+
+pub async fn create_subscription<C: ConnectionTrait>(
+    db: &C,
+    env: &EnvConfig,
+    user_id: &str,
+    url: &str,
+    event_types: &[String],
+) -> Result<webhook_subscriptions::Model, WebhookError> {
+    resolve_webhook_url(url, env).await?;
+
+    let mut subscribed: Vec<String> = Vec::new();
+    for event_type in event_types {
+        let event_type = WebhookEventType::parse(event_type)
+            .ok_or_else(|| WebhookError::UnknownEventType(event_type.to_string()))?
+            .to_string();
+        if !subscribed.contains(&event_type) {
+            subscribed.push(event_type);
+        }
+    }
+
+    if subscribed.is_empty() {
+        return Err(WebhookError::NoEventTypes);
+    }
+
+    let subscription = webhook_subscriptions::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        user_id: Set(user_id.to_string()),
+        url: Set(url.to_string()),
+        secret: Set(generate_token("whsec_").ok_or(WebhookError::SecretError)?),
+        event_types: Set(subscribed.join(",")),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    Ok(subscription)
+}
+
+pub async fn user_subscriptions<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+) -> Result<Vec<webhook_subscriptions::Model>, DbErr> {
+    WebhookSubscriptions::find()
+        .filter(webhook_subscriptions::Column::UserId.eq(user_id))
+        .filter(webhook_subscriptions::Column::DeletedAt.is_null())
+        .order_by_asc(webhook_subscriptions::Column::Id)
+        .all(db)
+        .await
+}
+
+async fn find_subscription<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+    subscription_id: &str,
+) -> Result<webhook_subscriptions::Model, WebhookError> {
+    WebhookSubscriptions::find()
+        .filter(webhook_subscriptions::Column::Uuid.eq(subscription_id))
+        .filter(webhook_subscriptions::Column::UserId.eq(user_id))
+        .filter(webhook_subscriptions::Column::DeletedAt.is_null())
+        .one(db)
+        .await?
+        .ok_or(WebhookError::SubscriptionNotFound)
+}
+
+// Soft deleted so its delivery log stays readable. Deliveries still queued are cancelled
+pub async fn delete_subscription<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+    subscription_id: &str,
+) -> Result<(), WebhookError> {
+    let subscription = find_subscription(db, user_id, subscription_id).await?;
+
+    let mut deleted: webhook_subscriptions::ActiveModel = subscription.into();
+    deleted.deleted_at = Set(Some(Utc::now()));
+    deleted.updated_at = Set(Utc::now());
+    deleted.update(db).await?;
+
+    Ok(())
+}
+
+pub async fn subscription_deliveries<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+    subscription_id: &str,
+) -> Result<Vec<webhook_deliveries::Model>, WebhookError> {
+    let subscription = find_subscription(db, user_id, subscription_id).await?;
+
+    Ok(WebhookDeliveries::find()
+        .filter(webhook_deliveries::Column::SubscriptionId.eq(&subscription.uuid))
+        .order_by_desc(webhook_deliveries::Column::Id)
+        .limit(MAX_DELIVERIES_LISTED)
+        .all(db)
+        .await?)
+}
+
+// Sends the same event again, with the same id so the integrator can tell it is a repeat
+pub async fn redeliver<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+    delivery_id: &str,
+) -> Result<webhook_deliveries::Model, WebhookError> {
+    let (delivery, subscription) = WebhookDeliveries::find()
+        .filter(webhook_deliveries::Column::Uuid.eq(delivery_id))
+        .find_also_related(WebhookSubscriptions)
+        .one(db)
+        .await?
+        .ok_or(WebhookError::DeliveryNotFound)?;
+
+    match subscription {
+        Some(subscription)
+            if subscription.user_id == user_id && subscription.deleted_at.is_none() => {}
+        _ => return Err(WebhookError::DeliveryNotFound),
+    }
+
+    let mut pending: webhook_deliveries::ActiveModel = delivery.into();
+    pending.status = Set(WebhookDeliveryStatus::Pending.to_string());
+    pending.updated_at = Set(Utc::now());
+    let pending = pending.update(db).await?;
+
+    let job = WebhookJob {
+        delivery_id: pending.uuid.to_string(),
+    };
+    enqueue(db, OutboxKind::Webhook, &job).await?;
+
+    Ok(pending)
+}
+
+// Queued on the transaction making the change, so subscribers only hear about committed changes.
+// One delivery per subscription of the user that wants this event type
+pub async fn emit_webhook_event<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+    event_type: WebhookEventType,
+    data: Value,
+) -> Result<(), DbErr> {
+    let subscriptions: Vec<_> = user_subscriptions(db, user_id)
+        .await?
+        .into_iter()
+        .filter(|subscription| subscription.is_subscribed_to(&event_type.to_string()))
+        .collect();
+
+    if subscriptions.is_empty() {
+        return Ok(());
+    }
+
+    let event_id = Uuid::new_v4().to_string();
+    let payload = json!({
+        "id": &event_id,
+        "event": event_type.to_string(),
+        "created_at": Utc::now(),
+        "data": data,
+    })
+    .to_string();
+
+    for subscription in subscriptions {
+        let delivery = webhook_deliveries::ActiveModel {
+            uuid: Set(Uuid::new_v4().to_string()),
+            subscription_id: Set(subscription.uuid),
+            event_id: Set(event_id.to_string()),
+            event_type: Set(event_type.to_string()),
+            payload: Set(payload.to_string()),
+            status: Set(WebhookDeliveryStatus::Pending.to_string()),
+            attempts: Set(0),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        let job = WebhookJob {
+            delivery_id: delivery.uuid,
+        };
+        enqueue(db, OutboxKind::Webhook, &job).await?;
+    }
+
+    Ok(())
+}
+
+// Posts the event to the subscriber and logs the outcome on the delivery. Any answer other than
+// a 2xx is a failure for the outbox to retry with backoff. `attempt` counts this outbox message's
+// attempts, so a redelivery gets the full set again
+pub async fn send_webhook(
+    app_state: &AppState,
+    job: &WebhookJob,
+    attempt: i32,
+) -> Result<(), DeliveryError> {
+    let (delivery, subscription) = WebhookDeliveries::find()
+        .filter(webhook_deliveries::Column::Uuid.eq(&job.delivery_id))
+        .find_also_related(WebhookSubscriptions)
+        .one(&app_state.db)
+        .await?
+        .ok_or_else(|| DeliveryError::WebhookNotFound(job.delivery_id.to_string()))?;
+
+    let mut update: webhook_deliveries::ActiveModel = delivery.clone().into();
+    update.updated_at = Set(Utc::now());
+
+    let subscription = match subscription {
+        Some(subscription) if subscription.deleted_at.is_none() => subscription,
+        _ => {
+            update.status = Set(WebhookDeliveryStatus::Cancelled.to_string());
+            update.update(&app_state.db).await?;
+            return Ok(());
+        }
+    };
+
+    let response = post_webhook(&app_state.env, &subscription, &delivery).await;
+
+    update.attempts = Set(delivery.attempts + 1);
+    update.last_attempt_at = Set(Some(Utc::now()));
+
+    // Only the status is kept, the endpoint's body is never stored or shown
+    let failure = match response {
+        Ok(status) => {
+            update.response_status = Set(Some(status.as_u16() as i32));
+
+            if status.is_success() {
+                update.status = Set(WebhookDeliveryStatus::Delivered.to_string());
+                update.delivered_at = Set(Some(Utc::now()));
+                update.last_error = Set(None);
+                update.update(&app_state.db).await?;
+                return Ok(());
+            }
+
+            format!("Endpoint responded with {}", status)
+        }
+        Err(err) => {
+            update.response_status = Set(None);
+            err
+        }
+    };
+
+    let status = if attempt >= app_state.env.outbox_max_attempts {
+        WebhookDeliveryStatus::Failed
+    } else {
+        WebhookDeliveryStatus::Retrying
+    };
+    update.status = Set(status.to_string());
+    update.last_error = Set(Some(failure.to_string()));
+    update.update(&app_state.db).await?;
+
+    Err(DeliveryError::WebhookFailed(failure))
+}
+
+// Redirects aren't followed, they could lead anywhere the address check would have refused
+async fn post_webhook(
+    env: &EnvConfig,
+    subscription: &webhook_subscriptions::Model,
+    delivery: &webhook_deliveries::Model,
+) -> Result<reqwest::StatusCode, String> {
+    let target = resolve_webhook_url(&subscription.url, env)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let client = Client::builder()
+        .timeout(std::time::Duration::from_secs(env.webhook_timeout_secs))
+        .redirect(redirect::Policy::none())
+        .resolve(&target.host, target.address)
+        .build()
+        .map_err(|err| err.to_string())?;
+
+    let response = client
+        .post(&subscription.url)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(
+            SIGNATURE_HEADER,
+            sign_payload(&delivery.payload, &subscription.secret),
+        )
+        .header(EVENT_HEADER, &delivery.event_type)
+        .header(DELIVERY_HEADER, &delivery.uuid)
+        .body(delivery.payload.to_string())
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    Ok(response.status())
+}
@@ -0,0 +1,334 @@
+use chrono::{Duration, Utc};
+use sea_orm::*;
+use serde::Serialize;
+use std::fmt;
+use thiserror::Error;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::entities::{outbox_messages, prelude::OutboxMessages};
+use crate::utils::notification_channel::ChannelError;
+use crate::utils::payment_provider::ProviderError;
+use crate::utils::send_email::{EmailError, SendEmail};
+use crate::AppState;
+
+use super::notification::{send_notification, Channel, Notification, Notifier};
+use super::outbound_webhook::{send_webhook, WebhookJob};
+use super::reversal::{refund_reversal, RefundJob};
+
+// A claimed message is left alone for this long, so one whose worker died is picked up again
+const CLAIM_LEASE_SECS: i64 = 300;
+// Backoff stops growing here
+const MAX_RETRY_DELAY_SECS: i64 = 3600;
+const BATCH_SIZE: u64 = 50;
+
+#[derive(Debug, PartialEq)]
+pub enum OutboxKind {
+    Email,
+    Notification,
+    Webhook,
+    Refund,
+}
+
+impl fmt::Display for OutboxKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            OutboxKind::Email => "email",
+            OutboxKind::Notification => "notification",
+            OutboxKind::Webhook => "webhook",
+            OutboxKind::Refund => "refund",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum OutboxStatus {
+    Pending,
+    Delivered,
+    // Out of attempts or undeliverable, kept for an admin to look at and retry
+    Dead,
+}
+
+impl fmt::Display for OutboxStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            OutboxStatus::Pending => "pending",
+            OutboxStatus::Delivered => "delivered",
+            OutboxStatus::Dead => "dead",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DeliveryError {
+    #[error(transparent)]
+    EmailError(#[from] EmailError),
+
+    #[error(transparent)]
+    ChannelError(#[from] ChannelError),
+
+    #[error("No {0} channel is configured")]
+    ChannelNotConfigured(Channel),
+
+    #[error("Invalid payload: {0}")]
+    InvalidPayload(#[from] serde_json::Error),
+
+    #[error("Unknown message kind {0}")]
+    UnknownKind(String),
+
+    #[error("User {0} not found")]
+    UserNotFound(String),
+
+    #[error("Webhook delivery {0} not found")]
+    WebhookNotFound(String),
+
+    #[error("Webhook delivery failed: {0}")]
+    WebhookFailed(String),
+
+    #[error("Reversal {0} not found")]
+    ReversalNotFound(String),
+
+    #[error("Refund failed: {0}")]
+    RefundFailed(String),
+
+    #[error("Refund provider error: {0}")]
+    ProviderError(#[from] ProviderError),
+
+    #[error("Failed to render template: {0}")]
+    TemplateError(#[from] tera::Error),
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl DeliveryError {
+    pub fn is_permanent(&self) -> bool {
+        match self {
+            DeliveryError::EmailError(err) => err.is_permanent(),
+            DeliveryError::ChannelError(err) => err.is_permanent(),
+            DeliveryError::WebhookFailed(_) | DeliveryError::DatabaseError(_) => false,
+            DeliveryError::ProviderError(err) => !matches!(err, ProviderError::HttpRequestError(_)),
+            _ => true,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum OutboxError {
+    #[error("Message not found")]
+    NotFound,
+
+    #[error("Only dead messages can be retried")]
+    NotDead,
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl OutboxError {
+    pub fn is_client_error(&self) -> bool {
+        matches!(self, OutboxError::NotDead)
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct OutboxSummary {
+    pub delivered: usize,
+    pub retried: usize,
+    pub dead: usize,
+}
+
+// Pass the transaction making the business change so the message is only sent if it commits
+pub async fn enqueue<C: ConnectionTrait, T: Serialize>(
+    db: &C,
+    kind: OutboxKind,
+    payload: &T,
+) -> Result<outbox_messages::Model, DbErr> {
+    let payload = serde_json::to_string(payload).map_err(|err| DbErr::Custom(err.to_string()))?;
+
+    outbox_messages::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        kind: Set(kind.to_string()),
+        payload: Set(payload),
+        status: Set(OutboxStatus::Pending.to_string()),
+        attempts: Set(0),
+        next_attempt_at: Set(Utc::now()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+}
+
+pub async fn enqueue_email<C: ConnectionTrait>(
+    db: &C,
+    email: &SendEmail,
+) -> Result<outbox_messages::Model, DbErr> {
+    enqueue(db, OutboxKind::Email, email).await
+}
+
+// Background worker delivering queued messages. Spawned once on startup
+pub async fn run_outbox_worker(app_state: AppState) {
+    let notifier = Notifier::from_env(&app_state.env);
+
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        app_state.env.outbox_poll_interval_secs,
+    ));
+
+    loop {
+        interval.tick().await;
+
+        match process_outbox(&app_state, &notifier).await {
+            Ok(summary) if summary.retried > 0 || summary.dead > 0 => info!(
+                "Outbox run: {} delivered, {} to retry and {} dead",
+                summary.delivered, summary.retried, summary.dead
+            ),
+            Ok(_) => {}
+            Err(err) => error!("Error processing outbox: {}", err),
+        }
+    }
+}
+
+// Delivers the messages that are due. A failed delivery is retried with exponential backoff until
+// it runs out of attempts, then it is dead-lettered
+pub async fn process_outbox(
+    app_state: &AppState,
+    notifier: &Notifier,
+) -> Result<OutboxSummary, DbErr> {
+    let now = Utc::now();
+    let due = OutboxMessages::find()
+        .filter(outbox_messages::Column::Status.eq(OutboxStatus::Pending.to_string()))
+        .filter(outbox_messages::Column::NextAttemptAt.lte(now))
+        .order_by_asc(outbox_messages::Column::Id)
+        .limit(BATCH_SIZE)
+        .all(&app_state.db)
+        .await?;
+
+    let mut summary = OutboxSummary::default();
+    for message in due {
+        if !claim_message(&app_state.db, &message).await? {
+            continue;
+        }
+
+        let attempts = message.attempts + 1;
+        let mut update: outbox_messages::ActiveModel = message.clone().into();
+        update.attempts = Set(attempts);
+        update.updated_at = Set(Utc::now());
+
+        match deliver(app_state, &message, notifier).await {
+            Ok(_) => {
+                summary.delivered += 1;
+                update.status = Set(OutboxStatus::Delivered.to_string());
+                update.delivered_at = Set(Some(Utc::now()));
+                update.last_error = Set(None);
+            }
+            Err(err) if err.is_permanent() || attempts >= app_state.env.outbox_max_attempts => {
+                error!("Outbox message {} is dead ===> {}", message.uuid, err);
+                summary.dead += 1;
+                update.status = Set(OutboxStatus::Dead.to_string());
+                update.last_error = Set(Some(err.to_string()));
+            }
+            Err(err) => {
+                warn!(
+                    "Outbox message {} failed, retrying ===> {}",
+                    message.uuid, err
+                );
+                summary.retried += 1;
+                let delay = retry_delay(app_state.env.outbox_retry_base_secs, attempts);
+                update.next_attempt_at = Set(Utc::now() + delay);
+                update.last_error = Set(Some(err.to_string()));
+            }
+        }
+
+        update.update(&app_state.db).await?;
+    }
+
+    Ok(summary)
+}
+
+// Takes the message by pushing its next attempt past the lease, so another worker polling at the
+// same time skips it. Fails if someone else got there first
+async fn claim_message(
+    db: &DatabaseConnection,
+    message: &outbox_messages::Model,
+) -> Result<bool, DbErr> {
+    let claimed = OutboxMessages::update_many()
+        .col_expr(
+            outbox_messages::Column::NextAttemptAt,
+            sea_query::Expr::value(Utc::now() + Duration::seconds(CLAIM_LEASE_SECS)),
+        )
+        .filter(outbox_messages::Column::Id.eq(message.id))
+        .filter(outbox_messages::Column::Status.eq(OutboxStatus::Pending.to_string()))
+        .filter(outbox_messages::Column::NextAttemptAt.eq(message.next_attempt_at))
+        .exec(db)
+        .await?;
+
+    Ok(claimed.rows_affected > 0)
+}
+
+async fn deliver(
+    app_state: &AppState,
+    message: &outbox_messages::Model,
+    notifier: &Notifier,
+) -> Result<(), DeliveryError> {
+    match message.kind.as_str() {
+        kind if kind == OutboxKind::Email.to_string() => {
+            let email: SendEmail = serde_json::from_str(&message.payload)?;
+            notifier.mailer()?.send(&email).await?;
+            Ok(())
+        }
+        kind if kind == OutboxKind::Notification.to_string() => {
+            let notification: Notification = serde_json::from_str(&message.payload)?;
+            send_notification(app_state, notifier, &notification).await
+        }
+        kind if kind == OutboxKind::Webhook.to_string() => {
+            let job: WebhookJob = serde_json::from_str(&message.payload)?;
+            send_webhook(app_state, &job, message.attempts + 1).await
+        }
+        kind if kind == OutboxKind::Refund.to_string() => {
+            let job: RefundJob = serde_json::from_str(&message.payload)?;
+            refund_reversal(app_state, &job).await
+        }
+        kind => Err(DeliveryError::UnknownKind(kind.to_string())),
+    }
+}
+
+fn retry_delay(base_secs: i64, attempts: i32) -> Duration {
+    let factor = 2_i64.saturating_pow(attempts.saturating_sub(1) as u32);
+    Duration::seconds(base_secs.saturating_mul(factor).min(MAX_RETRY_DELAY_SECS))
+}
+
+pub async fn dead_messages<C: ConnectionTrait>(
+    db: &C,
+) -> Result<Vec<outbox_messages::Model>, DbErr> {
+    OutboxMessages::find()
+        .filter(outbox_messages::Column::Status.eq(OutboxStatus::Dead.to_string()))
+        .order_by_desc(outbox_messages::Column::UpdatedAt)
+        .all(db)
+        .await
+}
+
+// Gives a dead message a fresh set of attempts, due straight away
+pub async fn retry_dead_message<C: ConnectionTrait>(
+    db: &C,
+    message_id: &String,
+) -> Result<outbox_messages::Model, OutboxError> {
+    let message = OutboxMessages::find()
+        .filter(outbox_messages::Column::Uuid.eq(message_id))
+        .one(db)
+        .await?
+        .ok_or(OutboxError::NotFound)?;
+
+    if message.status != OutboxStatus::Dead.to_string() {
+        return Err(OutboxError::NotDead);
+    }
+
+    let mut retried: outbox_messages::ActiveModel = message.into();
+    retried.status = Set(OutboxStatus::Pending.to_string());
+    retried.attempts = Set(0);
+    retried.next_attempt_at = Set(Utc::now());
+    retried.updated_at = Set(Utc::now());
+
+    Ok(retried.update(db).await?)
+}
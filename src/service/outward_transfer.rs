@@ -0,0 +1,534 @@
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use rust_decimal::Decimal;
+use sea_orm::{sea_query::Expr, *};
+use serde_json::json;
+use thiserror::Error;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::entities::{
+    prelude::{Transactions, Wallets},
+    sea_orm_active_enums::{Status, TrxType},
+    transactions, users, wallets,
+};
+use crate::utils::config::EnvConfig;
+use crate::utils::payment_provider::{
+    self, provider_by_name, ProviderError, TransferRequest, TransferStatus,
+};
+use crate::AppState;
+
+use super::outbound_webhook::{emit_webhook_event, WebhookEventType};
+use super::transaction_balance::{
+    lock_wallets, TransactionBalance, TransactionBalanceTrait, TrxCategory,
+};
+use super::wallet_hold::available_balance;
+
+#[derive(Error, Debug)]
+pub enum OutwardTransferError {
+    #[error("Please verify your account before taking this action")]
+    SenderNotVerified,
+
+    #[error("You do not seem to have a valid wallet yet. Please contact support")]
+    SenderWalletNotFound,
+
+    #[error("Your wallet is frozen. Please contact support")]
+    WalletFrozen,
+
+    #[error("Insufficient Funds")]
+    InsufficientFunds,
+
+    #[error("Could not resolve account {0}, Please confirm the account number and bank code")]
+    AccountResolutionFailed(String),
+
+    #[error("Transfer provider error: {0}")]
+    ProviderError(String),
+
+    #[error("Transfer provider request failed: {0}")]
+    ProviderUnavailable(#[from] ProviderError),
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl OutwardTransferError {
+    pub fn is_client_error(&self) -> bool {
+        matches!(
+            self,
+            OutwardTransferError::SenderNotVerified
+                | OutwardTransferError::SenderWalletNotFound
+                | OutwardTransferError::WalletFrozen
+                | OutwardTransferError::InsufficientFunds
+                | OutwardTransferError::AccountResolutionFailed(_)
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct StaleTransferSummary {
+    pub checked: usize,
+    pub confirmed: usize,
+    pub reversed: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct BankAccount {
+    pub account_number: String,
+    pub bank_code: String,
+    pub account_name: String,
+}
+
+pub async fn resolve_bank_account(
+    account_number: &String,
+    bank_code: &String,
+    env: &EnvConfig,
+) -> Result<BankAccount, OutwardTransferError> {
+    match payment_provider::resolve_account(account_number, bank_code, env).await {
+        Ok(account) => Ok(BankAccount {
+            account_number: account.account_number,
+            bank_code: bank_code.to_string(),
+            account_name: account.account_name,
+        }),
+        Err(ProviderError::Rejected(_)) => Err(OutwardTransferError::AccountResolutionFailed(
+            account_number.to_string(),
+        )),
+        Err(err) => Err(OutwardTransferError::ProviderUnavailable(err)),
+    }
+}
+
+pub struct OutwardTransfer {
+    pub sender: users::Model,
+    pub bank_account: BankAccount,
+    pub amount: Decimal,
+    pub narration: Option<String>,
+}
+
+#[async_trait]
+pub trait OutwardTransferTrait {
+    // Debits the wallet and hands the transfer to a provider. Final status arrives through the transfer webhooks
+    async fn transfer(self, app_state: &AppState) -> Result<String, OutwardTransferError>;
+
+    // Writes the pending debit inside a transaction owned by the caller and returns the transfer reference
+    async fn debit_with_txn(
+        &self,
+        txn: &DatabaseTransaction,
+        env: &EnvConfig,
+    ) -> Result<String, OutwardTransferError>;
+
+    // Sends an already debited transfer to the providers in order of preference. Must only be
+    // called after the debit is committed
+    async fn dispatch(
+        &self,
+        reference: &str,
+        app_state: &AppState,
+    ) -> Result<(), OutwardTransferError>;
+}
+
+#[async_trait]
+impl OutwardTransferTrait for OutwardTransfer {
+    async fn transfer(self, app_state: &AppState) -> Result<String, OutwardTransferError> {
+        let txn = app_state
+            .db
+            .begin_with_config(
+                Some(IsolationLevel::RepeatableRead),
+                Some(AccessMode::ReadWrite),
+            )
+            .await?;
+
+        let reference = match self.debit_with_txn(&txn, &app_state.env).await {
+            Ok(reference) => {
+                txn.commit().await?;
+                reference
+            }
+            Err(err) => {
+                let _ = txn.rollback().await;
+                return Err(err);
+            }
+        };
+
+        self.dispatch(&reference, app_state).await?;
+        Ok(reference)
+    }
+
+    async fn debit_with_txn(
+        &self,
+        txn: &DatabaseTransaction,
+        env: &EnvConfig,
+    ) -> Result<String, OutwardTransferError> {
+        if !self.sender.is_verified {
+            return Err(OutwardTransferError::SenderNotVerified);
+        }
+
+        let sender_wallet = lock_wallets(
+            txn,
+            Condition::all()
+                .add(wallets::Column::UserId.eq(&self.sender.uuid))
+                .add(wallets::Column::Default.eq(true)),
+        )
+        .await?
+        .pop();
+
+        let sender_wallet = match sender_wallet {
+            Some(sender_wallet) => sender_wallet,
+            None => return Err(OutwardTransferError::SenderWalletNotFound),
+        };
+
+        if sender_wallet.is_frozen() {
+            return Err(OutwardTransferError::WalletFrozen);
+        }
+
+        if self.amount > available_balance(txn, &sender_wallet).await? {
+            return Err(OutwardTransferError::InsufficientFunds);
+        }
+
+        let reference = Uuid::new_v4().to_string();
+        let narration = self
+            .narration
+            .clone()
+            .unwrap_or(String::from("Bank Transfer"));
+
+        let meta = json!({
+            "account_number": &self.bank_account.account_number,
+            "bank_code": &self.bank_account.bank_code,
+            "account_name": &self.bank_account.account_name,
+        })
+        .to_string();
+
+        let debit_sender = TransactionBalance {
+            uuid: reference.to_string(),
+            amount: self.amount,
+            trx_type: TrxType::Debit,
+            status: Status::Pending,
+            description: format!("{} - TO {}", &narration, &self.bank_account.account_name),
+            provider_reference: Some(reference.to_string()),
+            current_balance: sender_wallet.current_balance - self.amount,
+            previous_balance: sender_wallet.current_balance,
+            user_id: sender_wallet.user_id.to_string(),
+            wallet_id: sender_wallet.uuid.to_string(),
+            provider: env.transfer_providers.first().cloned().unwrap_or_default(),
+            fees: None,
+            provider_fees: None,
+            category: TrxCategory::Outward,
+            meta: Some(meta),
+        };
+
+        if let Err(err) = debit_sender.save_transaction_update_balance(txn).await {
+            error!("DB error debiting sender for outward transfer: {}", err);
+            return Err(OutwardTransferError::DatabaseError(err));
+        }
+
+        Ok(reference)
+    }
+
+    async fn dispatch(
+        &self,
+        reference: &str,
+        app_state: &AppState,
+    ) -> Result<(), OutwardTransferError> {
+        let transfer_request = TransferRequest {
+            reference: reference.to_string(),
+            amount: self.amount,
+            account_number: self.bank_account.account_number.to_string(),
+            bank_code: self.bank_account.bank_code.to_string(),
+            account_name: self.bank_account.account_name.to_string(),
+            narration: self
+                .narration
+                .clone()
+                .unwrap_or(String::from("Bank Transfer")),
+        };
+
+        // A request that may have reached a provider leaves the debit pending for the webhook
+        // to settle. Only a definite failure is reversed straight away
+        match payment_provider::transfer(&transfer_request, &app_state.env).await {
+            Ok(initiation) => {
+                record_transfer_provider(&app_state.db, reference, &initiation.provider).await?;
+                Ok(())
+            }
+            Err(err) if err.is_definite_failure() => {
+                error!("Outward transfer {} failed: {}", reference, err);
+                let message = err.to_string();
+                reverse_outward_transfer(&app_state.db, reference, &message).await?;
+                Err(OutwardTransferError::ProviderError(message))
+            }
+            Err(err) => Err(OutwardTransferError::ProviderUnavailable(err)),
+        }
+    }
+}
+
+// The debit is written against the preferred provider, failover may have used another one
+async fn record_transfer_provider(
+    db: &DatabaseConnection,
+    reference: &str,
+    provider: &str,
+) -> Result<(), DbErr> {
+    Transactions::update_many()
+        .col_expr(transactions::Column::Provider, Expr::value(provider))
+        .filter(transactions::Column::Uuid.eq(reference))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+async fn find_outward_transfer(
+    txn: &DatabaseTransaction,
+    reference: &str,
+    statuses: &[Status],
+) -> Result<Option<transactions::Model>, DbErr> {
+    Transactions::find()
+        .filter(transactions::Column::Uuid.eq(reference))
+        .filter(transactions::Column::Category.eq(TrxCategory::Outward.to_string()))
+        .filter(transactions::Column::TrxType.eq(TrxType::Debit))
+        .filter(transactions::Column::Status.is_in(statuses.to_vec()))
+        .one(txn)
+        .await
+}
+
+// Moves the debit on from the status it was read in. False when something else got there first
+async fn claim_outward_transfer(
+    txn: &DatabaseTransaction,
+    transaction: &transactions::Model,
+    status: Status,
+) -> Result<bool, DbErr> {
+    let updated = Transactions::update_many()
+        .col_expr(transactions::Column::Status, Expr::value(status))
+        .col_expr(transactions::Column::UpdatedAt, Expr::value(Utc::now()))
+        .filter(transactions::Column::Id.eq(transaction.id))
+        .filter(transactions::Column::Status.eq(transaction.status.clone()))
+        .exec(txn)
+        .await?;
+
+    Ok(updated.rows_affected > 0)
+}
+
+// Marks a pending outward transfer as successful. Returns false if there was nothing pending to settle
+pub async fn confirm_outward_transfer(
+    db: &DatabaseConnection,
+    reference: &str,
+) -> Result<bool, DbErr> {
+    let txn = db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadWrite),
+        )
+        .await?;
+
+    let transaction = match find_outward_transfer(&txn, reference, &[Status::Pending]).await? {
+        Some(transaction) => transaction,
+        None => {
+            let _ = txn.rollback().await;
+            return Ok(false);
+        }
+    };
+
+    if !claim_outward_transfer(&txn, &transaction, Status::Successful).await? {
+        let _ = txn.rollback().await;
+        return Ok(false);
+    }
+
+    txn.commit().await?;
+    Ok(true)
+}
+
+// Marks a pending outward transfer as failed and refunds the debited amount to the wallet.
+// Returns false if there was nothing pending to reverse, so repeated webhooks are harmless
+pub async fn reverse_outward_transfer(
+    db: &DatabaseConnection,
+    reference: &str,
+    reason: &str,
+) -> Result<bool, DbErr> {
+    refund_outward_transfer(db, reference, reason, &[Status::Pending], Status::Failed).await
+}
+
+// The receiving bank can send a transfer back after the provider reported it successful, so a
+// reversal refunds the wallet whether the debit is still pending or already confirmed
+pub async fn refund_reversed_outward_transfer(
+    db: &DatabaseConnection,
+    reference: &str,
+    reason: &str,
+) -> Result<bool, DbErr> {
+    refund_outward_transfer(
+        db,
+        reference,
+        reason,
+        &[Status::Pending, Status::Successful],
+        Status::Reversed,
+    )
+    .await
+}
+
+async fn refund_outward_transfer(
+    db: &DatabaseConnection,
+    reference: &str,
+    reason: &str,
+    from: &[Status],
+    to: Status,
+) -> Result<bool, DbErr> {
+    let txn = db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadWrite),
+        )
+        .await?;
+
+    let transaction = match find_outward_transfer(&txn, reference, from).await? {
+        Some(transaction) => transaction,
+        None => {
+            let _ = txn.rollback().await;
+            return Ok(false);
+        }
+    };
+
+    // Claimed before the refund so two webhooks for the same transfer can't both credit it
+    if !claim_outward_transfer(&txn, &transaction, to).await? {
+        let _ = txn.rollback().await;
+        return Ok(false);
+    }
+
+    let wallet = Wallets::find()
+        .filter(wallets::Column::Uuid.eq(&transaction.wallet_id))
+        .one(&txn)
+        .await?;
+
+    let wallet = match wallet {
+        Some(wallet) => wallet,
+        None => {
+            error!(
+                "Wallet {} not found reversing outward transfer",
+                transaction.wallet_id
+            );
+            let _ = txn.rollback().await;
+            return Ok(false);
+        }
+    };
+
+    let refund = TransactionBalance {
+        uuid: Uuid::new_v4().to_string(),
+        amount: transaction.amount,
+        trx_type: TrxType::Credit,
+        status: Status::Successful,
+        description: format!("Reversal - {}", &transaction.description),
+        provider_reference: Some(format!("{}-reversal", reference)),
+        current_balance: wallet.current_balance + transaction.amount,
+        previous_balance: wallet.current_balance,
+        user_id: wallet.user_id.to_string(),
+        wallet_id: wallet.uuid.to_string(),
+        provider: transaction.provider.to_string(),
+        fees: None,
+        provider_fees: None,
+        category: TrxCategory::Outward,
+        meta: Some(json!({ "reversed_reference": reference, "reason": reason }).to_string()),
+    };
+
+    refund.save_transaction_update_balance(&txn).await?;
+
+    let withdrawal = json!({
+        "reference": reference,
+        "amount": transaction.amount,
+        "wallet_id": &wallet.uuid,
+        "balance": wallet.current_balance + transaction.amount,
+        "reason": reason,
+    });
+    emit_webhook_event(
+        &txn,
+        &wallet.user_id,
+        WebhookEventType::WithdrawalFailed,
+        withdrawal,
+    )
+    .await?;
+
+    txn.commit().await?;
+    info!("Reversed outward transfer {}: {}", reference, reason);
+
+    Ok(true)
+}
+
+// Background worker for outward transfers whose webhook never arrived, usually because the
+// request timed out after reaching the provider. Spawned once on startup
+pub async fn run_pending_transfer_worker(app_state: AppState) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        app_state.env.transfer_poll_interval_secs,
+    ));
+
+    loop {
+        interval.tick().await;
+
+        match process_stale_transfers(&app_state).await {
+            Ok(summary) if summary.checked > 0 => info!(
+                "Checked {} stale transfer(s), {} confirmed and {} reversed",
+                summary.checked, summary.confirmed, summary.reversed
+            ),
+            Ok(_) => {}
+            Err(err) => error!("Error checking stale transfers: {}", err),
+        }
+    }
+}
+
+// Pending outward debits older than the stale window are checked with the provider they were
+// sent to. Ones the provider never received are refunded once the abandon window has passed
+pub async fn process_stale_transfers(app_state: &AppState) -> Result<StaleTransferSummary, DbErr> {
+    let now = Utc::now();
+    let stale_before = now - Duration::minutes(app_state.env.transfer_stale_after_mins);
+    let abandon_before = now - Duration::minutes(app_state.env.transfer_abandon_after_mins);
+
+    let stale_transfers = Transactions::find()
+        .filter(transactions::Column::Category.eq(TrxCategory::Outward.to_string()))
+        .filter(transactions::Column::TrxType.eq(TrxType::Debit))
+        .filter(transactions::Column::Status.eq(Status::Pending))
+        .filter(transactions::Column::CreatedAt.lte(stale_before))
+        .order_by_asc(transactions::Column::Id)
+        .all(&app_state.db)
+        .await?;
+
+    let mut summary = StaleTransferSummary::default();
+    for transfer in stale_transfers {
+        summary.checked += 1;
+        let reference = transfer.uuid.to_string();
+
+        let provider = match provider_by_name(&transfer.provider, &app_state.env) {
+            Some(provider) => provider,
+            None => {
+                warn!(
+                    "Provider {} is not configured, cannot verify transfer {}",
+                    transfer.provider, reference
+                );
+                continue;
+            }
+        };
+
+        let status = match provider.transfer_status(&reference).await {
+            Ok(status) => status,
+            Err(err) => {
+                error!("Error verifying transfer {} ===> {}", reference, err);
+                continue;
+            }
+        };
+
+        match status {
+            TransferStatus::Successful => {
+                if confirm_outward_transfer(&app_state.db, &reference).await? {
+                    summary.confirmed += 1;
+                }
+            }
+            TransferStatus::Failed(reason) => {
+                if reverse_outward_transfer(&app_state.db, &reference, &reason).await? {
+                    summary.reversed += 1;
+                }
+            }
+            TransferStatus::Reversed(reason) => {
+                if refund_reversed_outward_transfer(&app_state.db, &reference, &reason).await? {
+                    summary.reversed += 1;
+                }
+            }
+            TransferStatus::NotFound if transfer.created_at <= abandon_before => {
+                let reason = format!("Transfer was not received by {}", provider.name());
+                if reverse_outward_transfer(&app_state.db, &reference, &reason).await? {
+                    summary.reversed += 1;
+                }
+            }
+            TransferStatus::NotFound | TransferStatus::Pending => {}
+        }
+    }
+
+    Ok(summary)
+}
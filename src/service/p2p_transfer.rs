@@ -0,0 +1,321 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::json;
+use thiserror::Error;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::entities::{
+    prelude::Users,
+    sea_orm_active_enums::{Status, TrxType},
+    users, wallets,
+};
+
+use super::notification::{notify, NotificationEvent};
+use super::outbound_webhook::{emit_webhook_event, WebhookEventType};
+use super::transaction_balance::{
+    lock_wallets, TransactionBalance, TransactionBalanceTrait, TrxCategory,
+};
+use super::wallet_hold::available_balance;
+
+#[derive(Error, Debug)]
+pub enum P2PTransferError {
+    #[error("Please verify your account before taking this action")]
+    SenderNotVerified,
+
+    #[error("Cannot send funds to yourself")]
+    SelfTransfer,
+
+    #[error("User you are trying to send funds to not found")]
+    ReceiverNotFound,
+
+    #[error("Cannot send funds to {0}, As they are not yet verified")]
+    ReceiverNotVerified(String),
+
+    #[error("You do not seem to have a valid wallet yet. Please contact support")]
+    SenderWalletNotFound,
+
+    #[error("{0} Does not have a valid wallet yet")]
+    ReceiverWalletNotFound(String),
+
+    #[error("Cannot send funds to the same wallet")]
+    SameWallet,
+
+    #[error("Your wallet is frozen. Please contact support")]
+    WalletFrozen,
+
+    #[error("Insufficient Funds")]
+    InsufficientFunds,
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl P2PTransferError {
+    // Errors caused by the request itself rather than by the platform, safe to return to the client as is
+    pub fn is_client_error(&self) -> bool {
+        !matches!(self, P2PTransferError::DatabaseError(_))
+    }
+}
+
+pub struct P2PTransfer {
+    pub sender: users::Model,
+    pub receiver_id: String,
+    pub amount: Decimal,
+    pub narration: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct P2PTransferReceipt {
+    pub sender_ref: String,
+    pub receiver_ref: String,
+    pub sender_name: String,
+    pub receiver_name: String,
+    pub receiver: users::Model,
+}
+
+#[async_trait]
+pub trait P2PTransferTrait {
+    // Runs the transfer in its own DB transaction
+    async fn transfer(
+        self,
+        db: &DatabaseConnection,
+    ) -> Result<P2PTransferReceipt, P2PTransferError>;
+
+    // Runs the transfer inside a transaction owned by the caller. Caller is responsible for commit/rollback
+    async fn transfer_with_txn(
+        self,
+        txn: &DatabaseTransaction,
+    ) -> Result<P2PTransferReceipt, P2PTransferError>;
+}
+
+#[async_trait]
+impl P2PTransferTrait for P2PTransfer {
+    async fn transfer(
+        self,
+        db: &DatabaseConnection,
+    ) -> Result<P2PTransferReceipt, P2PTransferError> {
+        let txn = db
+            .begin_with_config(
+                Some(IsolationLevel::RepeatableRead),
+                Some(AccessMode::ReadWrite),
+            )
+            .await?;
+
+        match self.transfer_with_txn(&txn).await {
+            Ok(receipt) => {
+                txn.commit().await?;
+                Ok(receipt)
+            }
+            Err(err) => {
+                let _ = txn.rollback().await;
+                Err(err)
+            }
+        }
+    }
+
+    async fn transfer_with_txn(
+        self,
+        txn: &DatabaseTransaction,
+    ) -> Result<P2PTransferReceipt, P2PTransferError> {
+        if !self.sender.is_verified {
+            return Err(P2PTransferError::SenderNotVerified);
+        }
+
+        if self.sender.uuid == self.receiver_id {
+            return Err(P2PTransferError::SelfTransfer);
+        }
+
+        let receiver = Users::find()
+            .filter(users::Column::Uuid.eq(&self.receiver_id))
+            .one(txn)
+            .await?;
+
+        let receiver = match receiver {
+            Some(receiver) => receiver,
+            None => return Err(P2PTransferError::ReceiverNotFound),
+        };
+
+        let sender_name = format!("{} {}", self.sender.last_name, self.sender.first_name);
+        let receiver_name = format!("{} {}", receiver.last_name, receiver.first_name);
+
+        if !receiver.is_verified {
+            return Err(P2PTransferError::ReceiverNotVerified(receiver_name));
+        }
+
+        let user_wallets = lock_wallets(
+            txn,
+            Condition::any()
+                .add(
+                    wallets::Column::UserId
+                        .eq(&self.sender.uuid)
+                        .and(wallets::Column::Default.eq(true)),
+                )
+                .add(
+                    wallets::Column::UserId
+                        .eq(&receiver.uuid)
+                        .and(wallets::Column::Default.eq(true)),
+                ),
+        )
+        .await?;
+
+        let sender_wallet = match user_wallets
+            .iter()
+            .find(|wallet| wallet.user_id == self.sender.uuid)
+        {
+            Some(sender_wallet) => sender_wallet.to_owned(),
+            None => return Err(P2PTransferError::SenderWalletNotFound),
+        };
+
+        if sender_wallet.is_frozen() {
+            return Err(P2PTransferError::WalletFrozen);
+        }
+
+        if self.amount > available_balance(txn, &sender_wallet).await? {
+            return Err(P2PTransferError::InsufficientFunds);
+        }
+
+        let receiver_wallet = match user_wallets
+            .iter()
+            .find(|wallet| wallet.user_id == receiver.uuid)
+        {
+            Some(receiver_wallet) => receiver_wallet.to_owned(),
+            None => return Err(P2PTransferError::ReceiverWalletNotFound(receiver_name)),
+        };
+
+        if sender_wallet.uuid == receiver_wallet.uuid {
+            return Err(P2PTransferError::SameWallet);
+        }
+
+        let sender_ref = Uuid::new_v4().to_string();
+        let receiver_ref = Uuid::new_v4().to_string();
+        let narration = self.narration.unwrap_or(String::from("Wallet Transfer"));
+
+        let meta = json!({
+            "sender_name": &sender_name,
+            "receiver_name": &receiver_name,
+            "sender_wallet_id": &sender_wallet.uuid,
+            "receiver_wallet_id": &receiver_wallet.uuid
+        })
+        .to_string();
+
+        let debit_sender = TransactionBalance {
+            uuid: sender_ref.to_string(),
+            amount: self.amount,
+            trx_type: TrxType::Debit,
+            status: Status::Successful,
+            description: format!("{} - TO {}", &narration, &receiver_name),
+            provider_reference: Some(receiver_ref.to_string()),
+            current_balance: sender_wallet.current_balance - self.amount,
+            previous_balance: sender_wallet.current_balance,
+            user_id: sender_wallet.user_id.to_string(),
+            wallet_id: sender_wallet.uuid.to_string(),
+            provider: String::from("money-transfer"),
+            fees: None,
+            provider_fees: None,
+            category: TrxCategory::P2P,
+            meta: Some(meta.to_string()),
+        };
+
+        if let Err(err) = debit_sender.save_transaction_update_balance(txn).await {
+            error!("DB error debiting sender: {}", err);
+            return Err(P2PTransferError::DatabaseError(err));
+        }
+
+        let credit_receiver = TransactionBalance {
+            uuid: receiver_ref.to_string(),
+            amount: self.amount,
+            trx_type: TrxType::Credit,
+            status: Status::Successful,
+            description: format!("{} - FROM {}", &narration, &sender_name),
+            provider_reference: Some(sender_ref.to_string()),
+            current_balance: receiver_wallet.current_balance + self.amount,
+            previous_balance: receiver_wallet.current_balance,
+            user_id: receiver_wallet.user_id.to_string(),
+            wallet_id: receiver_wallet.uuid.to_string(),
+            provider: String::from("money-transfer"),
+            fees: None,
+            provider_fees: None,
+            category: TrxCategory::P2P,
+            meta: Some(meta),
+        };
+
+        if let Err(err) = credit_receiver.save_transaction_update_balance(txn).await {
+            error!("DB error crediting receiver: {}", err);
+            return Err(P2PTransferError::DatabaseError(err));
+        }
+
+        let sent = json!({
+            "amount": self.amount,
+            "receiver_name": &receiver_name,
+            "narration": &narration,
+            "reference": &sender_ref,
+            "balance": sender_wallet.current_balance - self.amount,
+        });
+        notify(
+            txn,
+            &sender_wallet.user_id,
+            NotificationEvent::TransferSent,
+            sent,
+        )
+        .await?;
+
+        let received = json!({
+            "amount": self.amount,
+            "sender_name": &sender_name,
+            "narration": &narration,
+            "reference": &receiver_ref,
+            "balance": receiver_wallet.current_balance + self.amount,
+        });
+        notify(
+            txn,
+            &receiver_wallet.user_id,
+            NotificationEvent::TransferReceived,
+            received,
+        )
+        .await?;
+
+        let debited = json!({
+            "reference": &sender_ref,
+            "direction": "debit",
+            "amount": self.amount,
+            "wallet_id": &sender_wallet.uuid,
+            "balance": sender_wallet.current_balance - self.amount,
+            "counterparty_name": &receiver_name,
+            "narration": &narration,
+        });
+        emit_webhook_event(
+            txn,
+            &sender_wallet.user_id,
+            WebhookEventType::TransferCompleted,
+            debited,
+        )
+        .await?;
+
+        let credited = json!({
+            "reference": &receiver_ref,
+            "direction": "credit",
+            "amount": self.amount,
+            "wallet_id": &receiver_wallet.uuid,
+            "balance": receiver_wallet.current_balance + self.amount,
+            "counterparty_name": &sender_name,
+            "narration": &narration,
+        });
+        emit_webhook_event(
+            txn,
+            &receiver_wallet.user_id,
+            WebhookEventType::TransferCompleted,
+            credited,
+        )
+        .await?;
+
+        Ok(P2PTransferReceipt {
+            sender_ref,
+            receiver_ref,
+            sender_name,
+            receiver_name,
+            receiver,
+        })
+    }
+}
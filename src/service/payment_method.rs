@@ -0,0 +1,209 @@
+use chrono::Utc;
+use rust_decimal::Decimal;
+use sea_orm::*;
+use thiserror::Error;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::entities::{payment_methods, prelude::PaymentMethods, transactions, users};
+use crate::utils::payment_provider::{
+    CardAuthorization, ChargeRequest, ChargeStatus, PaymentProvider, ProviderError,
+};
+use crate::utils::paystack::Paystack;
+use crate::AppState;
+
+use super::funding::{
+    find_funding, mark_funding_failed, record_pending_funding, settle_funding, FundingError,
+};
+
+#[derive(Error, Debug)]
+pub enum PaymentMethodError {
+    #[error("Payment method not found")]
+    NotFound,
+
+    #[error("Cards saved with {0} cannot be charged")]
+    UnsupportedProvider(String),
+
+    #[error("{0}")]
+    Declined(String),
+
+    #[error(transparent)]
+    ProviderError(#[from] ProviderError),
+
+    // The charge may still have gone through, the funding stays pending for the poller
+    #[error(transparent)]
+    ProviderUnavailable(ProviderError),
+
+    #[error(transparent)]
+    FundingError(#[from] FundingError),
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl PaymentMethodError {
+    pub fn is_client_error(&self) -> bool {
+        matches!(
+            self,
+            PaymentMethodError::NotFound
+                | PaymentMethodError::UnsupportedProvider(_)
+                | PaymentMethodError::Declined(_)
+        )
+    }
+}
+
+// Charging the same card again returns the same signature, so it refreshes the saved card instead
+// of adding another. A card the user deleted comes back once they pay with it again
+pub async fn save_card<C: ConnectionTrait>(
+    db: &C,
+    user_id: &String,
+    email: &str,
+    provider: &str,
+    card: &CardAuthorization,
+) -> Result<payment_methods::Model, DbErr> {
+    let existing = PaymentMethods::find()
+        .filter(payment_methods::Column::UserId.eq(user_id))
+        .filter(payment_methods::Column::Signature.eq(&card.signature))
+        .one(db)
+        .await?;
+
+    let mut payment_method: payment_methods::ActiveModel = match &existing {
+        Some(existing) => existing.clone().into(),
+        None => payment_methods::ActiveModel {
+            uuid: Set(Uuid::new_v4().to_string()),
+            user_id: Set(user_id.to_string()),
+            signature: Set(card.signature.to_string()),
+            ..Default::default()
+        },
+    };
+
+    payment_method.provider = Set(provider.to_string());
+    payment_method.authorization_code = Set(card.authorization_code.to_string());
+    payment_method.email = Set(email.to_string());
+    payment_method.brand = Set(card.brand.clone());
+    payment_method.bank = Set(card.bank.clone());
+    payment_method.last4 = Set(card.last4.to_string());
+    payment_method.exp_month = Set(card.exp_month.to_string());
+    payment_method.exp_year = Set(card.exp_year.to_string());
+    payment_method.deleted_at = Set(None);
+
+    match existing {
+        Some(_) => {
+            payment_method.updated_at = Set(Utc::now());
+            payment_method.update(db).await
+        }
+        None => payment_method.insert(db).await,
+    }
+}
+
+pub async fn user_payment_methods<C: ConnectionTrait>(
+    db: &C,
+    user_id: &String,
+) -> Result<Vec<payment_methods::Model>, DbErr> {
+    PaymentMethods::find()
+        .filter(payment_methods::Column::UserId.eq(user_id))
+        .filter(payment_methods::Column::DeletedAt.is_null())
+        .order_by_desc(payment_methods::Column::UpdatedAt)
+        .all(db)
+        .await
+}
+
+pub async fn find_payment_method<C: ConnectionTrait>(
+    db: &C,
+    user_id: &String,
+    payment_method_id: &String,
+) -> Result<payment_methods::Model, PaymentMethodError> {
+    let payment_method = PaymentMethods::find()
+        .filter(payment_methods::Column::Uuid.eq(payment_method_id))
+        .filter(payment_methods::Column::UserId.eq(user_id))
+        .filter(payment_methods::Column::DeletedAt.is_null())
+        .one(db)
+        .await?;
+
+    payment_method.ok_or(PaymentMethodError::NotFound)
+}
+
+pub async fn delete_payment_method<C: ConnectionTrait>(
+    db: &C,
+    user_id: &String,
+    payment_method_id: &String,
+) -> Result<(), PaymentMethodError> {
+    let payment_method = find_payment_method(db, user_id, payment_method_id).await?;
+
+    let mut deleted: payment_methods::ActiveModel = payment_method.into();
+    deleted.deleted_at = Set(Some(Utc::now()));
+    deleted.updated_at = Set(Utc::now());
+    deleted.update(db).await?;
+
+    Ok(())
+}
+
+// Recorded as a pending funding like a checkout, then settled the same way. A charge that times
+// out stays pending for the funding poller to settle
+pub async fn fund_with_payment_method(
+    app_state: &AppState,
+    user: &users::Model,
+    payment_method_id: &String,
+    amount: Decimal,
+) -> Result<transactions::Model, PaymentMethodError> {
+    let payment_method = find_payment_method(&app_state.db, &user.uuid, payment_method_id).await?;
+
+    let paystack = Paystack::new(&app_state.env);
+    if payment_method.provider != paystack.name() {
+        return Err(PaymentMethodError::UnsupportedProvider(
+            payment_method.provider,
+        ));
+    }
+
+    let charge = ChargeRequest {
+        reference: Uuid::new_v4().to_string(),
+        email: payment_method.email.to_string(),
+        user_id: user.uuid.to_string(),
+        amount,
+    };
+    let funding = record_pending_funding(
+        &app_state.db,
+        user,
+        paystack.name(),
+        &charge.reference,
+        amount,
+    )
+    .await?;
+
+    let verification = match paystack
+        .charge_authorization(&charge, &payment_method.authorization_code)
+        .await
+    {
+        Ok(verification) => verification,
+        Err(err) if err.is_definite_failure() => {
+            mark_funding_failed(&app_state.db, &funding).await?;
+            return Err(match err {
+                ProviderError::Rejected(msg) => PaymentMethodError::Declined(msg),
+                err => PaymentMethodError::ProviderError(err),
+            });
+        }
+        Err(err) => return Err(PaymentMethodError::ProviderUnavailable(err)),
+    };
+
+    if verification.status == ChargeStatus::Failed {
+        mark_funding_failed(&app_state.db, &funding).await?;
+        let reason = verification.raw["data"]["gateway_response"]
+            .as_str()
+            .unwrap_or("Card was declined")
+            .to_string();
+        return Err(PaymentMethodError::Declined(reason));
+    }
+
+    if let Err(err) = settle_funding(&paystack, &charge.reference, &app_state.db).await {
+        error!(
+            "Error settling card funding {} ===> {}",
+            charge.reference, err
+        );
+    }
+
+    let funding = find_funding(&app_state.db, &charge.reference)
+        .await?
+        .unwrap_or(funding);
+
+    Ok(funding)
+}
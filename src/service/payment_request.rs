@@ -0,0 +1,276 @@
+use chrono::Utc;
+use sea_orm::*;
+use std::fmt;
+use thiserror::Error;
+use tracing::{error, info};
+
+use crate::entities::{payment_requests, prelude::PaymentRequests, users};
+use crate::AppState;
+
+use super::bill::{record_request_closed, record_request_paid, BillParticipantStatus};
+use super::p2p_transfer::{P2PTransfer, P2PTransferError, P2PTransferTrait};
+
+#[derive(Debug, PartialEq)]
+pub enum PaymentRequestStatus {
+    Pending,
+    Accepted,
+    Declined,
+    Cancelled,
+    Expired,
+}
+
+impl fmt::Display for PaymentRequestStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            PaymentRequestStatus::Pending => "pending",
+            PaymentRequestStatus::Accepted => "accepted",
+            PaymentRequestStatus::Declined => "declined",
+            PaymentRequestStatus::Cancelled => "cancelled",
+            PaymentRequestStatus::Expired => "expired",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum PaymentRequestError {
+    #[error("Payment request not found")]
+    NotFound,
+
+    #[error("Payment request has already been {0}")]
+    NotPending(String),
+
+    #[error("Payment request has expired")]
+    Expired,
+
+    #[error(transparent)]
+    TransferError(#[from] P2PTransferError),
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl PaymentRequestError {
+    pub fn is_client_error(&self) -> bool {
+        match self {
+            PaymentRequestError::TransferError(err) => err.is_client_error(),
+            PaymentRequestError::DatabaseError(_) => false,
+            _ => true,
+        }
+    }
+}
+
+pub async fn run_payment_request_expiry_worker(app_state: AppState) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        app_state.env.payment_request_expiry_interval_secs,
+    ));
+
+    loop {
+        interval.tick().await;
+
+        match expire_stale_requests(&app_state.db).await {
+            Ok(expired) if expired > 0 => info!("Expired {} payment request(s)", expired),
+            Ok(_) => {}
+            Err(err) => error!("Error expiring stale payment requests: {}", err),
+        }
+    }
+}
+
+// Marks every pending request that is past its expiry as expired, closing out the bill
+// participant behind it along with it
+pub async fn expire_stale_requests<C>(db: &C) -> Result<u64, DbErr>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let stale_requests = PaymentRequests::find()
+        .filter(payment_requests::Column::Status.eq(PaymentRequestStatus::Pending.to_string()))
+        .filter(payment_requests::Column::ExpiresAt.lte(Utc::now()))
+        .all(db)
+        .await?;
+
+    let mut expired = 0;
+    for request in stale_requests {
+        let txn = db.begin().await?;
+        match expire_request(&txn, request).await {
+            Ok(true) => {
+                txn.commit().await?;
+                expired += 1;
+            }
+            Ok(false) => {
+                let _ = txn.rollback().await;
+            }
+            Err(err) => {
+                let _ = txn.rollback().await;
+                return Err(err);
+            }
+        }
+    }
+
+    Ok(expired)
+}
+
+async fn expire_request(
+    txn: &DatabaseTransaction,
+    request: payment_requests::Model,
+) -> Result<bool, DbErr> {
+    match close_payment_request(txn, request, PaymentRequestStatus::Expired).await {
+        Ok(expired_request) => {
+            record_request_closed(txn, &expired_request, BillParticipantStatus::Expired).await?;
+            Ok(true)
+        }
+        // Responded to since it was fetched
+        Err(PaymentRequestError::NotPending(_)) => Ok(false),
+        Err(PaymentRequestError::DatabaseError(err)) => Err(err),
+        Err(err) => Err(DbErr::Custom(err.to_string())),
+    }
+}
+
+async fn find_pending_request<C: ConnectionTrait>(
+    db: &C,
+    request_id: &String,
+    payer_id: &String,
+) -> Result<payment_requests::Model, PaymentRequestError> {
+    let request = PaymentRequests::find()
+        .filter(payment_requests::Column::Uuid.eq(request_id))
+        .filter(payment_requests::Column::PayerId.eq(payer_id))
+        .filter(payment_requests::Column::DeletedAt.is_null())
+        .one(db)
+        .await?;
+
+    let request = match request {
+        Some(request) => request,
+        None => return Err(PaymentRequestError::NotFound),
+    };
+
+    if request.status == PaymentRequestStatus::Expired.to_string() {
+        return Err(PaymentRequestError::Expired);
+    }
+
+    if request.status != PaymentRequestStatus::Pending.to_string() {
+        return Err(PaymentRequestError::NotPending(request.status));
+    }
+
+    // Left for expire_stale_requests to mark, a write here would be rolled back with the
+    // rest of a failed accept
+    if request.expires_at <= Utc::now() {
+        return Err(PaymentRequestError::Expired);
+    }
+
+    Ok(request)
+}
+
+// Moves a pending request to `status`. Only one response can win, a request that has been
+// responded to, cancelled or expired in the meantime is left alone
+pub async fn close_payment_request<C: ConnectionTrait>(
+    db: &C,
+    request: payment_requests::Model,
+    status: PaymentRequestStatus,
+) -> Result<payment_requests::Model, PaymentRequestError> {
+    let now = Utc::now();
+    let responded_at = match status {
+        PaymentRequestStatus::Accepted | PaymentRequestStatus::Declined => Some(now),
+        _ => request.responded_at,
+    };
+
+    let closed = PaymentRequests::update_many()
+        .col_expr(
+            payment_requests::Column::Status,
+            sea_query::Expr::value(status.to_string()),
+        )
+        .col_expr(
+            payment_requests::Column::RespondedAt,
+            sea_query::Expr::value(responded_at),
+        )
+        .col_expr(
+            payment_requests::Column::UpdatedAt,
+            sea_query::Expr::value(now),
+        )
+        .filter(payment_requests::Column::Uuid.eq(&request.uuid))
+        .filter(payment_requests::Column::Status.eq(PaymentRequestStatus::Pending.to_string()))
+        .exec(db)
+        .await?;
+
+    if closed.rows_affected == 0 {
+        return Err(PaymentRequestError::NotPending(String::from(
+            "responded to",
+        )));
+    }
+
+    Ok(payment_requests::Model {
+        status: status.to_string(),
+        responded_at,
+        updated_at: now,
+        ..request
+    })
+}
+
+// Pays a request by moving funds from the payer to the requester. Settlement and the
+// request update happen inside the caller's transaction so either both land or neither does
+pub async fn accept_payment_request(
+    txn: &DatabaseTransaction,
+    payer: &users::Model,
+    request_id: &String,
+) -> Result<payment_requests::Model, PaymentRequestError> {
+    let request = find_pending_request(txn, request_id, &payer.uuid).await?;
+    // Claimed before any money moves so a concurrent accept finds nothing left to pay
+    let request = close_payment_request(txn, request, PaymentRequestStatus::Accepted).await?;
+
+    let p2p_transfer = P2PTransfer {
+        sender: payer.clone(),
+        receiver_id: request.requester_id.to_string(),
+        amount: request.amount,
+        narration: request.narration.clone(),
+    };
+
+    let receipt = p2p_transfer.transfer_with_txn(txn).await?;
+
+    let mut accepted_request: payment_requests::ActiveModel = request.into();
+    accepted_request.transaction_reference = Set(Some(receipt.sender_ref));
+
+    let accepted_request = accepted_request.update(txn).await?;
+    record_request_paid(txn, &accepted_request).await?;
+
+    Ok(accepted_request)
+}
+
+// Withdrawn by the requester. Like a decline, the bill participant is closed in the same
+// transaction
+pub async fn cancel_payment_request(
+    txn: &DatabaseTransaction,
+    requester: &users::Model,
+    request_id: &String,
+) -> Result<payment_requests::Model, PaymentRequestError> {
+    let request = PaymentRequests::find()
+        .filter(payment_requests::Column::Uuid.eq(request_id))
+        .filter(payment_requests::Column::RequesterId.eq(&requester.uuid))
+        .filter(payment_requests::Column::DeletedAt.is_null())
+        .one(txn)
+        .await?
+        .ok_or(PaymentRequestError::NotFound)?;
+
+    if request.status != PaymentRequestStatus::Pending.to_string() {
+        return Err(PaymentRequestError::NotPending(request.status));
+    }
+
+    let cancelled_request =
+        close_payment_request(txn, request, PaymentRequestStatus::Cancelled).await?;
+    record_request_closed(txn, &cancelled_request, BillParticipantStatus::Cancelled).await?;
+
+    Ok(cancelled_request)
+}
+
+// The request and the bill participant behind it are closed in the caller's transaction, so a
+// declined request never leaves its bill waiting on it
+pub async fn decline_payment_request(
+    txn: &DatabaseTransaction,
+    payer: &users::Model,
+    request_id: &String,
+) -> Result<payment_requests::Model, PaymentRequestError> {
+    let request = find_pending_request(txn, request_id, &payer.uuid).await?;
+    let declined_request =
+        close_payment_request(txn, request, PaymentRequestStatus::Declined).await?;
+    record_request_closed(txn, &declined_request, BillParticipantStatus::Declined).await?;
+
+    Ok(declined_request)
+}
@@ -0,0 +1,79 @@
+use thiserror::Error;
+use tracing::info;
+
+use crate::entities::{invoice_payments, payment_link_payments};
+use crate::utils::payment_provider::{PaymentProvider, ProviderError, WebhookEvent};
+use crate::AppState;
+
+use super::card_payment::settle_card_payment;
+use super::funding::{settle_funding, FundingError, FundingOutcome};
+use super::invoice::{find_invoice_payment, InvoiceError};
+use super::merchant::{find_link_payment, MerchantError};
+use super::outward_transfer::{
+    confirm_outward_transfer, refund_reversed_outward_transfer, reverse_outward_transfer,
+};
+
+#[derive(Error, Debug)]
+pub enum WebhookHandlerError {
+    #[error(transparent)]
+    ProviderError(#[from] ProviderError),
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] sea_orm::error::DbErr),
+
+    #[error(transparent)]
+    FundingError(#[from] FundingError),
+
+    #[error(transparent)]
+    MerchantError(#[from] MerchantError),
+
+    #[error(transparent)]
+    InvoiceError(#[from] InvoiceError),
+}
+
+// Returns false when the event had nothing to settle
+pub async fn handle_webhook_event(
+    provider: &dyn PaymentProvider,
+    event: WebhookEvent,
+    app_state: &AppState,
+) -> Result<bool, WebhookHandlerError> {
+    match event {
+        WebhookEvent::ChargeSuccessful { reference } => {
+            handle_inflow_webhook(provider, &reference, app_state).await
+        }
+        WebhookEvent::TransferSuccessful { reference } => {
+            Ok(confirm_outward_transfer(&app_state.db, &reference).await?)
+        }
+        WebhookEvent::TransferFailed { reference, reason } => {
+            Ok(reverse_outward_transfer(&app_state.db, &reference, &reason).await?)
+        }
+        WebhookEvent::TransferReversed { reference, reason } => {
+            Ok(refund_reversed_outward_transfer(&app_state.db, &reference, &reason).await?)
+        }
+        WebhookEvent::Ignored(event_type) => {
+            info!("Ignoring {} webhook event {}", provider.name(), event_type);
+            Ok(false)
+        }
+    }
+}
+
+// The charge is verified with the provider rather than trusting the webhook payload. Charges
+// made on a payment link or an invoice pay the merchant, anything else funds a wallet
+pub async fn handle_inflow_webhook(
+    provider: &dyn PaymentProvider,
+    reference: &str,
+    app_state: &AppState,
+) -> Result<bool, WebhookHandlerError> {
+    let db = &app_state.db;
+    let env = &app_state.env;
+
+    let outcome = if find_link_payment(db, reference).await?.is_some() {
+        settle_card_payment::<payment_link_payments::Model>(provider, reference, db, env).await?
+    } else if find_invoice_payment(db, reference).await?.is_some() {
+        settle_card_payment::<invoice_payments::Model>(provider, reference, db, env).await?
+    } else {
+        settle_funding(provider, reference, db).await?
+    };
+
+    Ok(outcome == FundingOutcome::Credited)
+}
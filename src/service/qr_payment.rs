@@ -0,0 +1,617 @@
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde::Serialize;
+use std::fmt;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::entities::{
+    merchants,
+    prelude::{Merchants, QrCodes, Users, Wallets},
+    qr_codes, users, wallets,
+};
+use crate::utils::config::EnvConfig;
+use crate::utils::helpers::{sign_payload, validate_signature};
+use crate::utils::qr::{qr_png, qr_svg, QrImageError};
+
+use super::p2p_transfer::{P2PTransfer, P2PTransferError, P2PTransferTrait};
+
+// Versions the payload so the format can change without breaking printed codes
+const QR_PAYLOAD_PREFIX: &str = "MTQR1";
+const QR_SIGNING_KEY_LABEL: &str = "qr-payment";
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum QrKind {
+    Static,
+    Dynamic,
+}
+
+impl fmt::Display for QrKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            QrKind::Static => "static",
+            QrKind::Dynamic => "dynamic",
+        };
+
+        write!(f, "{}", kind)
+    }
+}
+
+impl QrKind {
+    pub fn parse(kind: &str) -> Option<QrKind> {
+        match kind {
+            "static" => Some(QrKind::Static),
+            "dynamic" => Some(QrKind::Dynamic),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum QrCodeStatus {
+    Active,
+    Used,
+    Cancelled,
+}
+
+impl fmt::Display for QrCodeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            QrCodeStatus::Active => "active",
+            QrCodeStatus::Used => "used",
+            QrCodeStatus::Cancelled => "cancelled",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum QrImageFormat {
+    Png,
+    Svg,
+}
+
+impl QrImageFormat {
+    pub fn parse(format: &str) -> Option<QrImageFormat> {
+        match format.to_lowercase().as_str() {
+            "png" => Some(QrImageFormat::Png),
+            "svg" => Some(QrImageFormat::Svg),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            QrImageFormat::Png => "image/png",
+            QrImageFormat::Svg => "image/svg+xml",
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum QrPaymentError {
+    #[error("Please verify your account before taking this action")]
+    NotVerified,
+
+    #[error("You do not seem to have a valid wallet yet. Please contact support")]
+    WalletNotFound,
+
+    #[error("This QR code is not valid")]
+    InvalidQrCode,
+
+    #[error("QR code not found")]
+    QrCodeNotFound,
+
+    #[error("This QR code has already been used")]
+    QrCodeUsed,
+
+    #[error("This QR code has been cancelled")]
+    QrCodeCancelled,
+
+    #[error("This QR code has expired")]
+    QrCodeExpired,
+
+    #[error("Only unused QR codes can be cancelled")]
+    CannotCancel,
+
+    #[error("Please enter the amount you want to pay")]
+    AmountRequired,
+
+    #[error("This QR code is for {0}")]
+    AmountMismatch(Decimal),
+
+    #[error("Image format must be png or svg")]
+    UnsupportedFormat,
+
+    #[error(transparent)]
+    TransferError(#[from] P2PTransferError),
+
+    #[error(transparent)]
+    ImageError(#[from] QrImageError),
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl QrPaymentError {
+    pub fn is_client_error(&self) -> bool {
+        match self {
+            QrPaymentError::TransferError(err) => err.is_client_error(),
+            QrPaymentError::ImageError(_) | QrPaymentError::DatabaseError(_) => false,
+            _ => true,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, QrPaymentError::QrCodeNotFound)
+    }
+}
+
+// What a QR code carries. Everything before the signature is signed with the app key, so the
+// wallet, amount or code can't be swapped out without the payment being refused
+#[derive(Debug, PartialEq)]
+pub struct QrPayload {
+    pub kind: QrKind,
+    pub wallet_id: String,
+    // Static codes may leave the amount to the payer, dynamic codes always have one
+    pub amount: Option<Decimal>,
+    pub code_id: Option<String>,
+}
+
+impl QrPayload {
+    fn content(&self) -> String {
+        format!(
+            "{}|{}|{}|{}|{}",
+            QR_PAYLOAD_PREFIX,
+            self.kind,
+            self.wallet_id,
+            self.amount
+                .map(|amount| amount.normalize().to_string())
+                .unwrap_or_default(),
+            self.code_id.as_deref().unwrap_or_default()
+        )
+    }
+
+    // e.g "MTQR1|static|<wallet id>|2500||<signature>"
+    pub fn sign(&self, secret: &str) -> String {
+        let content = self.content();
+        let signature = sign_payload(&content, secret);
+
+        format!("{}|{}", content, signature)
+    }
+
+    pub fn verify(payload: &str, secret: &str) -> Result<QrPayload, QrPaymentError> {
+        let (content, signature) = payload
+            .trim()
+            .rsplit_once('|')
+            .ok_or(QrPaymentError::InvalidQrCode)?;
+
+        if !validate_signature(content, signature, secret) {
+            return Err(QrPaymentError::InvalidQrCode);
+        }
+
+        let parts: Vec<&str> = content.split('|').collect();
+        let [prefix, kind, wallet_id, amount, code_id] = parts[..] else {
+            return Err(QrPaymentError::InvalidQrCode);
+        };
+
+        let kind = match QrKind::parse(kind) {
+            Some(kind) if prefix == QR_PAYLOAD_PREFIX => kind,
+            _ => return Err(QrPaymentError::InvalidQrCode),
+        };
+
+        let amount = match amount {
+            "" => None,
+            amount => Some(
+                amount
+                    .parse::<Decimal>()
+                    .map_err(|_| QrPaymentError::InvalidQrCode)?,
+            ),
+        };
+        let code_id = Some(code_id.to_string()).filter(|code_id| !code_id.is_empty());
+
+        if kind == QrKind::Dynamic && (amount.is_none() || code_id.is_none()) {
+            return Err(QrPaymentError::InvalidQrCode);
+        }
+
+        Ok(QrPayload {
+            kind,
+            wallet_id: wallet_id.to_string(),
+            amount,
+            code_id,
+        })
+    }
+}
+
+// What the payer is shown before confirming, and what the payee gets to print
+#[derive(Serialize, Debug)]
+pub struct QrCodeDetails {
+    pub kind: String,
+    pub payload: String,
+    pub payee_name: String,
+    pub amount: Option<Decimal>,
+    pub description: Option<String>,
+    pub qr_code_id: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DynamicQrCode {
+    pub qr_code: qr_codes::Model,
+    pub payload: String,
+}
+
+pub struct NewQrCode {
+    pub amount: Decimal,
+    pub description: Option<String>,
+    pub expires_in_mins: Option<i64>,
+}
+
+pub struct QrPayment {
+    pub payload: String,
+    pub amount: Option<Decimal>,
+    pub narration: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct QrPaymentReceipt {
+    pub reference: String,
+    pub amount: Decimal,
+    pub payee_name: String,
+    pub qr_code_id: Option<String>,
+}
+
+async fn default_wallet<C: ConnectionTrait>(
+    db: &C,
+    user_id: &str,
+) -> Result<wallets::Model, QrPaymentError> {
+    Wallets::find()
+        .filter(wallets::Column::UserId.eq(user_id))
+        .filter(wallets::Column::Default.eq(true))
+        .one(db)
+        .await?
+        .ok_or(QrPaymentError::WalletNotFound)
+}
+
+// Merchants are paid under their business name
+async fn payee_name<C: ConnectionTrait>(db: &C, user: &users::Model) -> Result<String, DbErr> {
+    let merchant = Merchants::find()
+        .filter(merchants::Column::UserId.eq(&user.uuid))
+        .one(db)
+        .await?;
+
+    Ok(match merchant {
+        Some(merchant) => merchant.business_name,
+        None => format!("{} {}", user.last_name, user.first_name),
+    })
+}
+
+// app_key also signs the JWTs, so QR codes are signed with a key derived from it for this use only
+fn qr_signing_key(env: &EnvConfig) -> String {
+    sign_payload(QR_SIGNING_KEY_LABEL, &env.app_key)
+}
+
+fn dynamic_payload(env: &EnvConfig, qr_code: &qr_codes::Model) -> String {
+    QrPayload {
+        kind: QrKind::Dynamic,
+        wallet_id: qr_code.wallet_id.to_string(),
+        amount: Some(qr_code.amount),
+        code_id: Some(qr_code.uuid.to_string()),
+    }
+    .sign(&qr_signing_key(env))
+}
+
+fn ensure_usable(qr_code: &qr_codes::Model) -> Result<(), QrPaymentError> {
+    if qr_code.status == QrCodeStatus::Used.to_string() {
+        return Err(QrPaymentError::QrCodeUsed);
+    }
+
+    if qr_code.status == QrCodeStatus::Cancelled.to_string() {
+        return Err(QrPaymentError::QrCodeCancelled);
+    }
+
+    if qr_code.expires_at <= Utc::now() {
+        return Err(QrPaymentError::QrCodeExpired);
+    }
+
+    Ok(())
+}
+
+pub fn qr_image(payload: &str, format: &str) -> Result<(&'static str, Vec<u8>), QrPaymentError> {
+    let format = QrImageFormat::parse(format).ok_or(QrPaymentError::UnsupportedFormat)?;
+
+    let image = match format {
+        QrImageFormat::Png => qr_png(payload)?,
+        QrImageFormat::Svg => qr_svg(payload)?.into_bytes(),
+    };
+
+    Ok((format.content_type(), image))
+}
+
+// Signed on the fly for the user's default wallet, so the same code keeps working for as long
+// as that wallet does
+pub async fn static_qr_code<C: ConnectionTrait>(
+    db: &C,
+    env: &EnvConfig,
+    user: &users::Model,
+    amount: Option<Decimal>,
+) -> Result<QrCodeDetails, QrPaymentError> {
+    if !user.is_verified {
+        return Err(QrPaymentError::NotVerified);
+    }
+
+    let wallet = default_wallet(db, &user.uuid).await?;
+    let payload = QrPayload {
+        kind: QrKind::Static,
+        wallet_id: wallet.uuid,
+        amount,
+        code_id: None,
+    };
+
+    Ok(QrCodeDetails {
+        kind: QrKind::Static.to_string(),
+        payload: payload.sign(&qr_signing_key(env)),
+        payee_name: payee_name(db, user).await?,
+        amount,
+        description: None,
+        qr_code_id: None,
+        expires_at: None,
+    })
+}
+
+pub async fn create_dynamic_qr_code<C: ConnectionTrait>(
+    db: &C,
+    env: &EnvConfig,
+    user: &users::Model,
+    new_qr_code: NewQrCode,
+) -> Result<DynamicQrCode, QrPaymentError> {
+    if !user.is_verified {
+        return Err(QrPaymentError::NotVerified);
+    }
+
+    let wallet = default_wallet(db, &user.uuid).await?;
+    let expires_in_mins = new_qr_code.expires_in_mins.unwrap_or(env.qr_code_ttl_mins);
+
+    let qr_code = qr_codes::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        user_id: Set(user.uuid.to_string()),
+        wallet_id: Set(wallet.uuid),
+        amount: Set(new_qr_code.amount),
+        description: Set(new_qr_code.description),
+        status: Set(QrCodeStatus::Active.to_string()),
+        expires_at: Set(Utc::now() + Duration::minutes(expires_in_mins)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    Ok(DynamicQrCode {
+        payload: dynamic_payload(env, &qr_code),
+        qr_code,
+    })
+}
+
+pub async fn user_qr_codes<C: ConnectionTrait>(
+    db: &C,
+    env: &EnvConfig,
+    user: &users::Model,
+) -> Result<Vec<DynamicQrCode>, DbErr> {
+    let qr_codes = QrCodes::find()
+        .filter(qr_codes::Column::UserId.eq(&user.uuid))
+        .order_by_desc(qr_codes::Column::CreatedAt)
+        .all(db)
+        .await?;
+
+    Ok(qr_codes
+        .into_iter()
+        .map(|qr_code| DynamicQrCode {
+            payload: dynamic_payload(env, &qr_code),
+            qr_code,
+        })
+        .collect())
+}
+
+pub async fn find_user_qr_code<C: ConnectionTrait>(
+    db: &C,
+    env: &EnvConfig,
+    user: &users::Model,
+    qr_code_id: &str,
+) -> Result<DynamicQrCode, QrPaymentError> {
+    let qr_code = QrCodes::find()
+        .filter(qr_codes::Column::Uuid.eq(qr_code_id))
+        .filter(qr_codes::Column::UserId.eq(&user.uuid))
+        .one(db)
+        .await?
+        .ok_or(QrPaymentError::QrCodeNotFound)?;
+
+    Ok(DynamicQrCode {
+        payload: dynamic_payload(env, &qr_code),
+        qr_code,
+    })
+}
+
+// Only while the code is still active, so a payment landing at the same time can't be undone
+pub async fn cancel_qr_code<C: ConnectionTrait>(
+    db: &C,
+    env: &EnvConfig,
+    user: &users::Model,
+    qr_code_id: &str,
+) -> Result<qr_codes::Model, QrPaymentError> {
+    let found = find_user_qr_code(db, env, user, qr_code_id).await?;
+
+    let cancelled = QrCodes::update_many()
+        .col_expr(
+            qr_codes::Column::Status,
+            sea_query::Expr::value(QrCodeStatus::Cancelled.to_string()),
+        )
+        .col_expr(
+            qr_codes::Column::UpdatedAt,
+            sea_query::Expr::value(Utc::now()),
+        )
+        .filter(qr_codes::Column::Id.eq(found.qr_code.id))
+        .filter(qr_codes::Column::Status.eq(QrCodeStatus::Active.to_string()))
+        .exec(db)
+        .await?;
+
+    if cancelled.rows_affected == 0 {
+        return Err(QrPaymentError::CannotCancel);
+    }
+
+    QrCodes::find_by_id(found.qr_code.uuid)
+        .one(db)
+        .await?
+        .ok_or(QrPaymentError::QrCodeNotFound)
+}
+
+// The payee behind a verified payload. The wallet has to still be its owner's default, which is
+// the one P2P transfers credit
+async fn find_payee<C: ConnectionTrait>(
+    db: &C,
+    payload: &QrPayload,
+) -> Result<users::Model, QrPaymentError> {
+    let found = Wallets::find()
+        .filter(wallets::Column::Uuid.eq(&payload.wallet_id))
+        .filter(wallets::Column::Default.eq(true))
+        .find_also_related(Users)
+        .one(db)
+        .await?;
+
+    match found {
+        Some((_, Some(payee))) => Ok(payee),
+        _ => Err(QrPaymentError::InvalidQrCode),
+    }
+}
+
+async fn find_payload_qr_code<C: ConnectionTrait>(
+    db: &C,
+    payload: &QrPayload,
+) -> Result<Option<qr_codes::Model>, QrPaymentError> {
+    let code_id = match &payload.code_id {
+        Some(code_id) => code_id,
+        None => return Ok(None),
+    };
+
+    let qr_code = QrCodes::find()
+        .filter(qr_codes::Column::Uuid.eq(code_id))
+        .filter(qr_codes::Column::WalletId.eq(&payload.wallet_id))
+        .one(db)
+        .await?
+        .ok_or(QrPaymentError::InvalidQrCode)?;
+
+    ensure_usable(&qr_code)?;
+
+    Ok(Some(qr_code))
+}
+
+pub async fn decode_qr_code<C: ConnectionTrait>(
+    db: &C,
+    env: &EnvConfig,
+    payload: &str,
+) -> Result<QrCodeDetails, QrPaymentError> {
+    let decoded = QrPayload::verify(payload, &qr_signing_key(env))?;
+    let payee = find_payee(db, &decoded).await?;
+    let qr_code = find_payload_qr_code(db, &decoded).await?;
+
+    Ok(QrCodeDetails {
+        kind: decoded.kind.to_string(),
+        payload: payload.trim().to_string(),
+        payee_name: payee_name(db, &payee).await?,
+        amount: decoded.amount,
+        description: qr_code
+            .as_ref()
+            .and_then(|qr_code| qr_code.description.clone()),
+        qr_code_id: decoded.code_id,
+        expires_at: qr_code.map(|qr_code| qr_code.expires_at),
+    })
+}
+
+// Marks a one-time code used by the payer. Only one payment can win it
+async fn claim_qr_code<C: ConnectionTrait>(
+    db: &C,
+    qr_code: &qr_codes::Model,
+    payer: &users::Model,
+) -> Result<bool, DbErr> {
+    let now = Utc::now();
+    let claimed = QrCodes::update_many()
+        .col_expr(
+            qr_codes::Column::Status,
+            sea_query::Expr::value(QrCodeStatus::Used.to_string()),
+        )
+        .col_expr(
+            qr_codes::Column::PaidBy,
+            sea_query::Expr::value(payer.uuid.to_string()),
+        )
+        .col_expr(qr_codes::Column::PaidAt, sea_query::Expr::value(now))
+        .col_expr(qr_codes::Column::UpdatedAt, sea_query::Expr::value(now))
+        .filter(qr_codes::Column::Id.eq(qr_code.id))
+        .filter(qr_codes::Column::Status.eq(QrCodeStatus::Active.to_string()))
+        .filter(qr_codes::Column::ExpiresAt.gt(now))
+        .exec(db)
+        .await?;
+
+    Ok(claimed.rows_affected > 0)
+}
+
+// Verifies the payload and pays the wallet behind it with a regular P2P transfer. Runs inside
+// the caller's transaction so a failed transfer also gives a one-time code back
+pub async fn pay_with_qr_code(
+    txn: &DatabaseTransaction,
+    env: &EnvConfig,
+    payer: &users::Model,
+    payment: QrPayment,
+) -> Result<QrPaymentReceipt, QrPaymentError> {
+    let decoded = QrPayload::verify(&payment.payload, &qr_signing_key(env))?;
+    let payee = find_payee(txn, &decoded).await?;
+    let qr_code = find_payload_qr_code(txn, &decoded).await?;
+
+    // The amount in the code wins, the payer only picks one when the code has none
+    let amount = match (decoded.amount, payment.amount) {
+        (Some(fixed), Some(amount)) if fixed != amount => {
+            return Err(QrPaymentError::AmountMismatch(fixed))
+        }
+        (Some(fixed), _) => fixed,
+        (None, Some(amount)) => amount,
+        (None, None) => return Err(QrPaymentError::AmountRequired),
+    };
+
+    if let Some(qr_code) = &qr_code {
+        if !claim_qr_code(txn, qr_code, payer).await? {
+            // Paid, cancelled or expired since it was read
+            if let Some(latest) = QrCodes::find_by_id(&qr_code.uuid).one(txn).await? {
+                ensure_usable(&latest)?;
+            }
+            return Err(QrPaymentError::QrCodeUsed);
+        }
+    }
+
+    let narration = payment.narration.or(qr_code
+        .as_ref()
+        .and_then(|qr_code| qr_code.description.clone()));
+    let transfer = P2PTransfer {
+        sender: payer.clone(),
+        receiver_id: payee.uuid.to_string(),
+        amount,
+        narration: Some(narration.unwrap_or(String::from("QR Payment"))),
+    };
+    let receipt = transfer.transfer_with_txn(txn).await?;
+
+    if let Some(qr_code) = &qr_code {
+        QrCodes::update_many()
+            .col_expr(
+                qr_codes::Column::TransactionId,
+                sea_query::Expr::value(receipt.sender_ref.to_string()),
+            )
+            .filter(qr_codes::Column::Id.eq(qr_code.id))
+            .exec(txn)
+            .await?;
+    }
+
+    Ok(QrPaymentReceipt {
+        reference: receipt.sender_ref,
+        amount,
+        payee_name: payee_name(txn, &payee).await?,
+        qr_code_id: decoded.code_id,
+    })
+}
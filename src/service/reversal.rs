@@ -0,0 +1,501 @@
+use chrono::Utc;
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt;
+use thiserror::Error;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::entities::{
+    prelude::{TransactionReversals, Transactions},
+    sea_orm_active_enums::{Status, TrxType},
+    transaction_reversals, transactions, users, wallets,
+};
+use crate::utils::{
+    config::EnvConfig,
+    payment_provider::{provider_by_name, ProviderError},
+};
+use crate::AppState;
+
+use super::outbox::{enqueue, DeliveryError, OutboxKind};
+use super::transaction_balance::{
+    lock_wallets, TransactionBalance, TransactionBalanceTrait, TrxCategory,
+};
+use super::wallet_hold::available_balance;
+
+#[derive(Debug, PartialEq)]
+pub enum ReversalStatus {
+    Completed,
+    Partial,
+    // The wallet side is done and the card refund is queued
+    RefundPending,
+    // The refund request is on its way to the provider. A reversal left here after a crash may
+    // or may not have been refunded, so it is never sent again without checking with the provider
+    RefundSubmitted,
+    // The provider turned the refund down, the outbox message is dead-lettered for an admin
+    RefundFailed,
+}
+
+impl fmt::Display for ReversalStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            ReversalStatus::Completed => "completed",
+            ReversalStatus::Partial => "partial",
+            ReversalStatus::RefundPending => "refund_pending",
+            ReversalStatus::RefundSubmitted => "refund_submitted",
+            ReversalStatus::RefundFailed => "refund_failed",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ReversalError {
+    #[error("Transaction not found")]
+    NotFound,
+
+    #[error("Transaction has already been reversed")]
+    AlreadyReversed,
+
+    #[error("{0}")]
+    NotReversible(String),
+
+    #[error("Wallet {0} not found")]
+    WalletNotFound(String),
+
+    #[error("There are no available funds to recover. Allow a negative balance to reverse anyway")]
+    NothingToRecover,
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl ReversalError {
+    pub fn is_client_error(&self) -> bool {
+        !matches!(self, ReversalError::DatabaseError(_))
+    }
+}
+
+pub struct ReversalRequest {
+    pub transaction_id: String,
+    pub reason: String,
+    // Take the full amount back even if it leaves the wallet with a negative balance
+    pub allow_negative_balance: bool,
+}
+
+// What the outbox carries to refund a reversed funding
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefundJob {
+    pub reversal_id: String,
+}
+
+// Reverses a P2P transfer or a wallet funding inside a transaction owned by the caller. Card
+// refunds are queued on the outbox with the reversal, so the provider is only asked once the
+// ledger rows are committed
+pub async fn reverse_transaction(
+    txn: &DatabaseTransaction,
+    env: &EnvConfig,
+    admin: &users::Model,
+    request: ReversalRequest,
+) -> Result<transaction_reversals::Model, ReversalError> {
+    let transaction = find_transaction(txn, &request.transaction_id).await?;
+    ensure_reversible(&transaction)?;
+
+    let category = transaction.category.to_string();
+    let (primary_id, amount, recovered_amount, refund_pending) =
+        if category == TrxCategory::P2P.to_string() {
+            let (debit_row, credit_row) = find_p2p_pair(txn, transaction).await?;
+            let recovered_amount = reverse_p2p(txn, &debit_row, &credit_row, &request).await?;
+            (debit_row.uuid, debit_row.amount, recovered_amount, false)
+        } else if category == TrxCategory::Funding.to_string() {
+            let amount = transaction.amount;
+            let primary_id = transaction.uuid.to_string();
+            let (recovered_amount, refund_pending) =
+                reverse_funding(txn, env, transaction, &request).await?;
+            (primary_id, amount, recovered_amount, refund_pending)
+        } else {
+            return Err(ReversalError::NotReversible(String::from(
+                "Only p2p transfers and wallet fundings can be reversed",
+            )));
+        };
+
+    let shortfall_amount = amount - recovered_amount;
+    let status = match refund_pending {
+        true => ReversalStatus::RefundPending,
+        false => settled_status(shortfall_amount),
+    };
+
+    let reversal = transaction_reversals::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        transaction_id: Set(primary_id),
+        reversed_by: Set(admin.uuid.to_string()),
+        reason: Set(request.reason),
+        amount: Set(amount),
+        recovered_amount: Set(recovered_amount),
+        shortfall_amount: Set(shortfall_amount),
+        status: Set(status.to_string()),
+        ..Default::default()
+    }
+    .insert(txn)
+    .await?;
+
+    if refund_pending {
+        let job = RefundJob {
+            reversal_id: reversal.uuid.to_string(),
+        };
+        enqueue(txn, OutboxKind::Refund, &job).await?;
+    }
+
+    Ok(reversal)
+}
+
+fn settled_status(shortfall_amount: Decimal) -> ReversalStatus {
+    match shortfall_amount > Decimal::ZERO {
+        true => ReversalStatus::Partial,
+        false => ReversalStatus::Completed,
+    }
+}
+
+async fn find_transaction(
+    txn: &DatabaseTransaction,
+    transaction_id: &String,
+) -> Result<transactions::Model, ReversalError> {
+    // Locked until the reversal commits, so a concurrent reversal waits and then finds the
+    // transaction already reversed
+    let transaction = Transactions::find()
+        .filter(transactions::Column::Uuid.eq(transaction_id))
+        .filter(transactions::Column::DeletedAt.is_null())
+        .lock_exclusive()
+        .one(txn)
+        .await?;
+
+    match transaction {
+        Some(transaction) => Ok(transaction),
+        None => Err(ReversalError::NotFound),
+    }
+}
+
+fn ensure_reversible(transaction: &transactions::Model) -> Result<(), ReversalError> {
+    match transaction.status {
+        Some(Status::Successful) => Ok(()),
+        Some(Status::Reversed | Status::PartiallyReversed) => Err(ReversalError::AlreadyReversed),
+        _ => Err(ReversalError::NotReversible(String::from(
+            "Only successful transactions can be reversed",
+        ))),
+    }
+}
+
+// Each side of a P2P transfer points at the other through provider_reference
+async fn find_p2p_pair(
+    txn: &DatabaseTransaction,
+    transaction: transactions::Model,
+) -> Result<(transactions::Model, transactions::Model), ReversalError> {
+    let counterpart_id = transaction.provider_reference.clone().unwrap_or_default();
+    let counterpart = find_transaction(txn, &counterpart_id).await?;
+    ensure_reversible(&counterpart)?;
+
+    match transaction.trx_type {
+        Some(TrxType::Debit) => Ok((transaction, counterpart)),
+        _ => Ok((counterpart, transaction)),
+    }
+}
+
+async fn find_wallet(
+    txn: &DatabaseTransaction,
+    wallet_id: &String,
+) -> Result<wallets::Model, ReversalError> {
+    let wallet = lock_wallets(
+        txn,
+        Condition::all().add(wallets::Column::Uuid.eq(wallet_id)),
+    )
+    .await?
+    .pop();
+
+    match wallet {
+        Some(wallet) => Ok(wallet),
+        None => Err(ReversalError::WalletNotFound(wallet_id.to_string())),
+    }
+}
+
+// How much can be taken back from a wallet. Funds already spent are left as a shortfall
+// unless a negative balance is allowed
+async fn recoverable_amount(
+    txn: &DatabaseTransaction,
+    wallet: &wallets::Model,
+    amount: Decimal,
+    allow_negative_balance: bool,
+) -> Result<Decimal, ReversalError> {
+    if allow_negative_balance {
+        return Ok(amount);
+    }
+
+    let available = available_balance(txn, wallet).await?.max(Decimal::ZERO);
+    let recovered_amount = amount.min(available);
+    if recovered_amount == Decimal::ZERO {
+        return Err(ReversalError::NothingToRecover);
+    }
+
+    Ok(recovered_amount)
+}
+
+// Compensating rows carry the reversed row's uuid in provider_reference
+fn compensating_row(
+    original: &transactions::Model,
+    wallet: &wallets::Model,
+    amount: Decimal,
+    trx_type: TrxType,
+    reason: &String,
+) -> TransactionBalance {
+    let current_balance = match trx_type {
+        TrxType::Credit => wallet.current_balance + amount,
+        TrxType::Debit => wallet.current_balance - amount,
+    };
+
+    TransactionBalance {
+        uuid: Uuid::new_v4().to_string(),
+        amount,
+        trx_type,
+        status: Status::Successful,
+        description: format!("Reversal - {}", &original.description),
+        provider_reference: Some(format!("{}-reversal", original.uuid)),
+        current_balance,
+        previous_balance: wallet.current_balance,
+        user_id: wallet.user_id.to_string(),
+        wallet_id: wallet.uuid.to_string(),
+        provider: original.provider.to_string(),
+        fees: None,
+        provider_fees: None,
+        category: match original.category == TrxCategory::P2P.to_string() {
+            true => TrxCategory::P2P,
+            false => TrxCategory::Funding,
+        },
+        meta: Some(
+            json!({ "reversed_transaction_id": original.uuid, "reason": reason }).to_string(),
+        ),
+    }
+}
+
+// Whatever could not be recovered stays outstanding on a partially reversed transaction, the
+// reversal records how much
+async fn mark_reversed(
+    txn: &DatabaseTransaction,
+    transaction: transactions::Model,
+    recovered_amount: Decimal,
+) -> Result<(), ReversalError> {
+    let status = match recovered_amount < transaction.amount {
+        true => Status::PartiallyReversed,
+        false => Status::Reversed,
+    };
+
+    // Only a transaction that is still successful can be claimed, a reversal that got there
+    // first leaves nothing to update
+    let reversed = Transactions::update_many()
+        .col_expr(transactions::Column::Status, sea_query::Expr::value(status))
+        .col_expr(
+            transactions::Column::UpdatedAt,
+            sea_query::Expr::value(Utc::now()),
+        )
+        .filter(transactions::Column::Id.eq(transaction.id))
+        .filter(transactions::Column::Status.eq(Status::Successful))
+        .exec(txn)
+        .await?;
+    if reversed.rows_affected == 0 {
+        return Err(ReversalError::AlreadyReversed);
+    }
+
+    Ok(())
+}
+
+async fn reverse_p2p(
+    txn: &DatabaseTransaction,
+    debit_row: &transactions::Model,
+    credit_row: &transactions::Model,
+    request: &ReversalRequest,
+) -> Result<Decimal, ReversalError> {
+    let receiver_wallet = find_wallet(txn, &credit_row.wallet_id).await?;
+    let recovered_amount = recoverable_amount(
+        txn,
+        &receiver_wallet,
+        credit_row.amount,
+        request.allow_negative_balance,
+    )
+    .await?;
+
+    compensating_row(
+        credit_row,
+        &receiver_wallet,
+        recovered_amount,
+        TrxType::Debit,
+        &request.reason,
+    )
+    .save_transaction_update_balance(txn)
+    .await?;
+
+    let sender_wallet = find_wallet(txn, &debit_row.wallet_id).await?;
+    compensating_row(
+        debit_row,
+        &sender_wallet,
+        recovered_amount,
+        TrxType::Credit,
+        &request.reason,
+    )
+    .save_transaction_update_balance(txn)
+    .await?;
+
+    mark_reversed(txn, debit_row.clone(), recovered_amount).await?;
+    mark_reversed(txn, credit_row.clone(), recovered_amount).await?;
+
+    Ok(recovered_amount)
+}
+
+// Returns the amount taken back from the wallet and whether it is owed back to a card
+async fn reverse_funding(
+    txn: &DatabaseTransaction,
+    env: &EnvConfig,
+    transaction: transactions::Model,
+    request: &ReversalRequest,
+) -> Result<(Decimal, bool), ReversalError> {
+    let wallet = find_wallet(txn, &transaction.wallet_id).await?;
+    let recovered_amount = recoverable_amount(
+        txn,
+        &wallet,
+        transaction.amount,
+        request.allow_negative_balance,
+    )
+    .await?;
+
+    compensating_row(
+        &transaction,
+        &wallet,
+        recovered_amount,
+        TrxType::Debit,
+        &request.reason,
+    )
+    .save_transaction_update_balance(txn)
+    .await?;
+
+    // Fundings through a payment provider go back to the customer's card
+    let refund_pending = provider_by_name(&transaction.provider, env).is_some();
+    mark_reversed(txn, transaction, recovered_amount).await?;
+
+    Ok((recovered_amount, refund_pending))
+}
+
+// Refunds a committed funding reversal. Only what was taken back from the wallet is refunded.
+// Runs from the outbox, so a provider that can't be reached is tried again later
+pub async fn refund_reversal(app_state: &AppState, job: &RefundJob) -> Result<(), DeliveryError> {
+    let db = &app_state.db;
+    let reversal = TransactionReversals::find()
+        .filter(transaction_reversals::Column::Uuid.eq(&job.reversal_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| DeliveryError::ReversalNotFound(job.reversal_id.to_string()))?;
+
+    let transaction = Transactions::find()
+        .filter(transactions::Column::Uuid.eq(&reversal.transaction_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| DeliveryError::ReversalNotFound(job.reversal_id.to_string()))?;
+
+    let provider = provider_by_name(&transaction.provider, &app_state.env)
+        .ok_or(ProviderError::NoProviderConfigured)?;
+    let charge_reference = transaction.provider_reference.clone().unwrap_or_default();
+
+    // Claimed before the provider is asked, so a redelivery after a crash can't refund twice
+    let awaiting_refund = [
+        ReversalStatus::RefundPending.to_string(),
+        ReversalStatus::RefundFailed.to_string(),
+    ];
+    if !move_refund(
+        db,
+        &reversal,
+        &awaiting_refund,
+        ReversalStatus::RefundSubmitted,
+    )
+    .await?
+    {
+        if reversal.status == ReversalStatus::RefundSubmitted.to_string() {
+            warn!(
+                "Refund for reversal {} was already submitted, check it with {}",
+                reversal.uuid,
+                provider.name()
+            );
+        }
+        return Ok(());
+    }
+
+    let submitted = [ReversalStatus::RefundSubmitted.to_string()];
+    match provider
+        .refund(&charge_reference, reversal.recovered_amount)
+        .await
+    {
+        Ok(refund) => {
+            TransactionReversals::update_many()
+                .col_expr(
+                    transaction_reversals::Column::ProviderRefundReference,
+                    sea_query::Expr::value(refund.reference),
+                )
+                .filter(transaction_reversals::Column::Id.eq(reversal.id))
+                .exec(db)
+                .await?;
+            let settled = settled_status(reversal.shortfall_amount);
+            move_refund(db, &reversal, &submitted, settled).await?;
+            info!(
+                "Refunded {} for reversal {}",
+                charge_reference, reversal.uuid
+            );
+            Ok(())
+        }
+        Err(ProviderError::Rejected(message)) => {
+            error!(
+                "{} refund for {} failed: {}",
+                provider.name(),
+                charge_reference,
+                message
+            );
+            move_refund(db, &reversal, &submitted, ReversalStatus::RefundFailed).await?;
+            Err(DeliveryError::RefundFailed(message))
+        }
+        // The request never reached the provider, so it is safe to send again
+        Err(err) if err.is_definite_failure() => {
+            move_refund(db, &reversal, &submitted, ReversalStatus::RefundPending).await?;
+            Err(DeliveryError::ProviderError(err))
+        }
+        Err(err) => {
+            error!(
+                "{} refund for {} may have gone through, check reversal {}: {}",
+                provider.name(),
+                charge_reference,
+                reversal.uuid,
+                err
+            );
+            Ok(())
+        }
+    }
+}
+
+async fn move_refund(
+    db: &DatabaseConnection,
+    reversal: &transaction_reversals::Model,
+    from: &[String],
+    to: ReversalStatus,
+) -> Result<bool, DbErr> {
+    let moved = TransactionReversals::update_many()
+        .col_expr(
+            transaction_reversals::Column::Status,
+            sea_query::Expr::value(to.to_string()),
+        )
+        .col_expr(
+            transaction_reversals::Column::UpdatedAt,
+            sea_query::Expr::value(Utc::now()),
+        )
+        .filter(transaction_reversals::Column::Id.eq(reversal.id))
+        .filter(transaction_reversals::Column::Status.is_in(from.iter().cloned()))
+        .exec(db)
+        .await?;
+
+    Ok(moved.rows_affected > 0)
+}
@@ -0,0 +1,242 @@
+use chrono::{DateTime, Duration, Utc};
+use cron::Schedule;
+use sea_orm::*;
+use serde_json::json;
+use std::{fmt, str::FromStr};
+use tracing::{error, info, instrument};
+
+use crate::entities::{prelude::ScheduledTransfers, prelude::Users, scheduled_transfers, users};
+use crate::AppState;
+
+use super::notification::{notify, NotificationEvent};
+use super::p2p_transfer::{P2PTransfer, P2PTransferError, P2PTransferTrait};
+
+// How long to wait before retrying a run that failed due to insufficient funds
+const RETRY_DELAY_MINUTES: i64 = 30;
+
+#[derive(Debug, PartialEq)]
+pub enum ScheduleStatus {
+    Active,
+    Paused,
+    Cancelled,
+    Completed,
+    Failed,
+}
+
+impl fmt::Display for ScheduleStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            ScheduleStatus::Active => "active",
+            ScheduleStatus::Paused => "paused",
+            ScheduleStatus::Cancelled => "cancelled",
+            ScheduleStatus::Completed => "completed",
+            ScheduleStatus::Failed => "failed",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+// Accepts the standard five field cron format (minute hour day month weekday) and
+// the six/seven field format (with seconds and year) understood by the cron crate
+pub fn parse_cron_expression(expression: &str) -> Result<Schedule, cron::error::Error> {
+    let expression = expression.trim();
+    if expression.split_whitespace().count() == 5 {
+        return Schedule::from_str(&format!("0 {}", expression));
+    }
+
+    Schedule::from_str(expression)
+}
+
+pub fn next_occurrence(
+    cron_expression: &str,
+    after: DateTime<Utc>,
+    end_at: Option<DateTime<Utc>>,
+) -> Option<DateTime<Utc>> {
+    let schedule = parse_cron_expression(cron_expression).ok()?;
+    let next_run = schedule.after(&after).next()?;
+
+    match end_at {
+        Some(end_at) if next_run > end_at => None,
+        _ => Some(next_run),
+    }
+}
+
+// Background worker that executes due scheduled transfers. Spawned once on startup
+pub async fn run_scheduled_transfer_worker(app_state: AppState) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        app_state.env.scheduler_interval_secs,
+    ));
+
+    loop {
+        interval.tick().await;
+
+        match process_due_transfers(&app_state).await {
+            Ok(0) => {}
+            Ok(count) => info!("Executed {} scheduled transfer(s)", count),
+            Err(err) => error!("Error fetching due scheduled transfers: {}", err),
+        }
+    }
+}
+
+pub async fn process_due_transfers(app_state: &AppState) -> Result<usize, DbErr> {
+    let due_transfers = ScheduledTransfers::find()
+        .filter(scheduled_transfers::Column::Status.eq(ScheduleStatus::Active.to_string()))
+        .filter(scheduled_transfers::Column::NextRunAt.lte(Utc::now()))
+        .filter(scheduled_transfers::Column::DeletedAt.is_null())
+        .order_by_asc(scheduled_transfers::Column::NextRunAt)
+        .all(&app_state.db)
+        .await?;
+
+    let mut executed = 0;
+    for scheduled_transfer in due_transfers {
+        match execute_scheduled_transfer(scheduled_transfer, app_state).await {
+            Ok(true) => executed += 1,
+            Ok(false) => {}
+            Err(err) => error!("DB error executing scheduled transfer: {}", err),
+        }
+    }
+
+    Ok(executed)
+}
+
+// The due row is claimed inside the transaction that moves the money, so a failed schedule
+// update rolls the transfer back with it and a second worker polling the same row finds
+// nothing left to run. True only when the money moved
+#[instrument(skip(scheduled_transfer, app_state), fields(scheduled_transfer_id = %scheduled_transfer.uuid))]
+async fn execute_scheduled_transfer(
+    scheduled_transfer: scheduled_transfers::Model,
+    app_state: &AppState,
+) -> Result<bool, DbErr> {
+    let txn = app_state
+        .db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadWrite),
+        )
+        .await?;
+
+    match run_claimed_transfer(&txn, scheduled_transfer).await {
+        Ok(executed) => {
+            txn.commit().await?;
+            Ok(executed)
+        }
+        Err(err) => {
+            let _ = txn.rollback().await;
+            Err(err)
+        }
+    }
+}
+
+async fn run_claimed_transfer(
+    txn: &DatabaseTransaction,
+    scheduled_transfer: scheduled_transfers::Model,
+) -> Result<bool, DbErr> {
+    let now = Utc::now();
+    let claimed = ScheduledTransfers::update_many()
+        .col_expr(
+            scheduled_transfers::Column::UpdatedAt,
+            sea_query::Expr::value(now),
+        )
+        .filter(scheduled_transfers::Column::Uuid.eq(&scheduled_transfer.uuid))
+        .filter(scheduled_transfers::Column::Status.eq(ScheduleStatus::Active.to_string()))
+        .filter(scheduled_transfers::Column::NextRunAt.eq(scheduled_transfer.next_run_at))
+        .filter(scheduled_transfers::Column::DeletedAt.is_null())
+        .exec(txn)
+        .await?;
+
+    if claimed.rows_affected == 0 {
+        info!("Scheduled transfer already run or changed, skipping");
+        return Ok(false);
+    }
+
+    let sender = Users::find()
+        .filter(users::Column::Uuid.eq(&scheduled_transfer.user_id))
+        .one(txn)
+        .await?;
+
+    let sender = match sender {
+        Some(sender) => sender,
+        None => {
+            let mut schedule: scheduled_transfers::ActiveModel = scheduled_transfer.into();
+            schedule.status = Set(ScheduleStatus::Failed.to_string());
+            schedule.last_error = Set(Some(String::from("Sender not found")));
+            schedule.updated_at = Set(now);
+            schedule.update(txn).await?;
+            return Ok(false);
+        }
+    };
+
+    let p2p_transfer = P2PTransfer {
+        sender: sender.clone(),
+        receiver_id: scheduled_transfer.receiver_id.to_string(),
+        amount: scheduled_transfer.amount,
+        narration: scheduled_transfer.narration.clone(),
+    };
+
+    let result = p2p_transfer.transfer_with_txn(txn).await;
+    let executed = result.is_ok();
+    let mut schedule: scheduled_transfers::ActiveModel = scheduled_transfer.clone().into();
+    schedule.updated_at = Set(now);
+
+    let next_run_at = scheduled_transfer
+        .cron_expression
+        .as_ref()
+        .and_then(|expression| next_occurrence(expression, now, scheduled_transfer.end_at));
+
+    match result {
+        Ok(_) => {
+            info!("Scheduled transfer executed successfully");
+            schedule.run_count = Set(scheduled_transfer.run_count + 1);
+            schedule.retry_count = Set(0);
+            schedule.last_run_at = Set(Some(now));
+            schedule.last_error = Set(None);
+            schedule.next_run_at = Set(next_run_at);
+            if next_run_at.is_none() {
+                schedule.status = Set(ScheduleStatus::Completed.to_string());
+            }
+        }
+        Err(P2PTransferError::DatabaseError(err)) => {
+            // Rolled back with the claim so it gets picked up again on the next tick
+            return Err(err);
+        }
+        Err(P2PTransferError::InsufficientFunds)
+            if scheduled_transfer.retry_count < scheduled_transfer.max_retries =>
+        {
+            info!("Insufficient funds for scheduled transfer, retrying later");
+            schedule.retry_count = Set(scheduled_transfer.retry_count + 1);
+            schedule.last_error = Set(Some(P2PTransferError::InsufficientFunds.to_string()));
+            schedule.next_run_at = Set(Some(now + Duration::minutes(RETRY_DELAY_MINUTES)));
+        }
+        Err(err) => {
+            info!("Scheduled transfer failed: {}", err);
+            schedule.retry_count = Set(0);
+            schedule.last_run_at = Set(Some(now));
+            schedule.last_error = Set(Some(err.to_string()));
+            schedule.next_run_at = Set(next_run_at);
+            if next_run_at.is_none() {
+                schedule.status = Set(ScheduleStatus::Failed.to_string());
+            }
+
+            let details = json!({
+                "amount": scheduled_transfer.amount,
+                "reason": err.to_string(),
+                "schedule_id": scheduled_transfer.uuid,
+            });
+            let notification = notify(
+                txn,
+                &sender.uuid,
+                NotificationEvent::ScheduledTransferFailed,
+                details,
+            )
+            .await;
+            if let Err(err) = notification {
+                error!("Error queueing scheduled transfer email ===> {}", err);
+            }
+        }
+    }
+
+    schedule.update(txn).await?;
+
+    Ok(executed)
+}
@@ -0,0 +1,494 @@
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use thiserror::Error;
+use tracing::error;
+
+use crate::entities::{
+    prelude::Transactions,
+    sea_orm_active_enums::{Status, TrxType},
+    transactions,
+};
+use crate::utils::payment_provider::{ChargeStatus, PaymentProvider, ProviderError};
+use crate::utils::paystack::{Paystack, SettlementRecord};
+use crate::AppState;
+
+use super::provider_webhook::handle_inflow_webhook;
+use super::transaction_balance::TrxCategory;
+
+// Paystack listings get slow over long periods, larger ranges should be split up
+pub const MAX_SETTLEMENT_RANGE_DAYS: i64 = 31;
+
+// Our records in the period that Paystack's listing didn't have are looked for in a listing this
+// much wider on each side, a charge or transfer may be dated just across the boundary over there
+const MATCH_MARGIN_DAYS: i64 = 2;
+
+#[derive(Error, Debug)]
+pub enum SettlementError {
+    #[error("The start of the period must be before its end")]
+    InvalidRange,
+
+    #[error("A period can be at most {0} days long")]
+    RangeTooLong(i64),
+
+    #[error("Paystack request failed: {0}")]
+    ProviderError(#[from] ProviderError),
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl SettlementError {
+    pub fn is_client_error(&self) -> bool {
+        matches!(
+            self,
+            SettlementError::InvalidRange | SettlementError::RangeTooLong(_)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscrepancyKind {
+    // Paystack has a successful charge we never credited, usually a missed webhook
+    MissedCharge,
+    // We credited a funding Paystack has no successful charge for
+    UnknownCredit,
+    ChargeAmountMismatch,
+    // Our provider_fees differ from the fees Paystack deducted
+    ChargeFeeMismatch,
+    // We debited for a bank transfer Paystack has no record of
+    MissingTransfer,
+    // Paystack sent out a transfer we have no debit for
+    UnknownTransfer,
+    TransferAmountMismatch,
+    // One side settled the transfer and the other failed or reversed it
+    TransferStatusMismatch,
+    // Paystack couldn't be asked about the reference, it is left for the next run
+    LookupFailed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SettlementDiscrepancy {
+    pub kind: DiscrepancyKind,
+    pub reference: String,
+    pub transaction_id: Option<String>,
+    // Amount, fee or status depending on the kind
+    pub recorded: Option<String>,
+    pub provider: Option<String>,
+    pub credited: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SettlementReport {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub charges_checked: usize,
+    pub transfers_checked: usize,
+    pub credited: usize,
+    pub discrepancies: Vec<SettlementDiscrepancy>,
+}
+
+pub struct SettlementReconciliation {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    // Credit users for successful charges we have no record of
+    pub auto_credit: bool,
+}
+
+impl SettlementDiscrepancy {
+    fn new(kind: DiscrepancyKind, reference: &str) -> SettlementDiscrepancy {
+        SettlementDiscrepancy {
+            kind,
+            reference: reference.to_string(),
+            transaction_id: None,
+            recorded: None,
+            provider: None,
+            credited: false,
+        }
+    }
+
+    fn with_transaction(mut self, transaction: &transactions::Model) -> SettlementDiscrepancy {
+        self.transaction_id = Some(transaction.uuid.to_string());
+        self
+    }
+
+    fn recorded(mut self, value: impl ToString) -> SettlementDiscrepancy {
+        self.recorded = Some(value.to_string());
+        self
+    }
+
+    fn provider(mut self, value: impl ToString) -> SettlementDiscrepancy {
+        self.provider = Some(value.to_string());
+        self
+    }
+}
+
+pub async fn reconcile_paystack_settlements(
+    app_state: &AppState,
+    request: SettlementReconciliation,
+) -> Result<SettlementReport, SettlementError> {
+    if request.from >= request.to {
+        return Err(SettlementError::InvalidRange);
+    }
+    if request.to - request.from > Duration::days(MAX_SETTLEMENT_RANGE_DAYS) {
+        return Err(SettlementError::RangeTooLong(MAX_SETTLEMENT_RANGE_DAYS));
+    }
+
+    let paystack = Paystack::new(&app_state.env);
+    let mut report = SettlementReport {
+        from: request.from,
+        to: request.to,
+        charges_checked: 0,
+        transfers_checked: 0,
+        credited: 0,
+        discrepancies: vec![],
+    };
+
+    reconcile_charges(&paystack, app_state, &request, &mut report).await?;
+    reconcile_transfers(&paystack, app_state, &request, &mut report).await?;
+
+    Ok(report)
+}
+
+async fn reconcile_charges(
+    paystack: &Paystack,
+    app_state: &AppState,
+    request: &SettlementReconciliation,
+    report: &mut SettlementReport,
+) -> Result<(), SettlementError> {
+    let charges: Vec<SettlementRecord> = paystack
+        .list_charges(request.from, request.to)
+        .await?
+        .into_iter()
+        .filter(|charge| charge.status == "success")
+        .collect();
+    report.charges_checked = charges.len();
+
+    // Matched by reference whenever they were recorded, a charge paid just before the end of
+    // the period may have been credited after it
+    let credits = transactions_by_reference(
+        &app_state.db,
+        charges
+            .iter()
+            .map(|charge| charge.reference.to_string())
+            .collect(),
+    )
+    .await?;
+
+    for charge in &charges {
+        match credits.get(&charge.reference) {
+            Some(credit) if credit.status == Some(Status::Successful) => {
+                compare_charge(credit, charge, report)
+            }
+            // Never recorded, or still pending because the webhook didn't arrive
+            credit => {
+                let mut discrepancy =
+                    SettlementDiscrepancy::new(DiscrepancyKind::MissedCharge, &charge.reference)
+                        .provider(charge.amount);
+                if let Some(credit) = credit {
+                    discrepancy = discrepancy.with_transaction(credit);
+                }
+
+                if request.auto_credit {
+                    discrepancy.credited =
+                        match handle_inflow_webhook(paystack, &charge.reference, app_state).await {
+                            Ok(credited) => credited,
+                            Err(err) => {
+                                error!(
+                                    "Error crediting missed charge {} ===> {}",
+                                    charge.reference, err
+                                );
+                                false
+                            }
+                        };
+                    if discrepancy.credited {
+                        report.credited += 1;
+                    }
+                }
+
+                report.discrepancies.push(discrepancy);
+            }
+        }
+    }
+
+    // Our credits in the period that Paystack's listing didn't have
+    let recorded_credits: Vec<transactions::Model> = Transactions::find()
+        .filter(transactions::Column::Provider.eq(paystack.name()))
+        .filter(transactions::Column::Category.eq(TrxCategory::Funding.to_string()))
+        .filter(transactions::Column::TrxType.eq(TrxType::Credit))
+        .filter(transactions::Column::Status.eq(Status::Successful))
+        .filter(transactions::Column::CreatedAt.between(request.from, request.to))
+        .all(&app_state.db)
+        .await?
+        .into_iter()
+        .filter(|credit| {
+            !charges
+                .iter()
+                .any(|charge| Some(&charge.reference) == credit.provider_reference.as_ref())
+        })
+        .collect();
+
+    if recorded_credits.is_empty() {
+        return Ok(());
+    }
+
+    let nearby = match paystack
+        .list_charges(widened_from(request), widened_to(request))
+        .await
+    {
+        Ok(nearby) => records_by_reference(nearby),
+        Err(err) => {
+            error!(
+                "Error listing Paystack charges around the period ===> {}",
+                err
+            );
+            HashMap::new()
+        }
+    };
+
+    for credit in recorded_credits {
+        let reference = credit.provider_reference.clone().unwrap_or_default();
+
+        // Only what the wider listing doesn't have is looked up one at a time
+        let charge = match nearby.get(&reference) {
+            Some(charge) => Some(charge.clone()),
+            None => match paystack.verify_charge(&reference).await {
+                Ok(verification) if verification.status == ChargeStatus::Successful => {
+                    Some(SettlementRecord {
+                        reference: reference.to_string(),
+                        amount: verification.amount,
+                        fees: verification.fees,
+                        status: String::from("success"),
+                    })
+                }
+                Ok(_) => None,
+                Err(err) => {
+                    report
+                        .discrepancies
+                        .push(lookup_failed(&credit, &reference, err));
+                    continue;
+                }
+            },
+        };
+
+        match charge {
+            Some(charge) if charge.status == "success" => compare_charge(&credit, &charge, report),
+            _ => report.discrepancies.push(
+                SettlementDiscrepancy::new(DiscrepancyKind::UnknownCredit, &reference)
+                    .with_transaction(&credit)
+                    .recorded(credit.amount),
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+fn compare_charge(
+    credit: &transactions::Model,
+    charge: &SettlementRecord,
+    report: &mut SettlementReport,
+) {
+    if credit.amount != charge.amount {
+        report.discrepancies.push(
+            SettlementDiscrepancy::new(DiscrepancyKind::ChargeAmountMismatch, &charge.reference)
+                .with_transaction(credit)
+                .recorded(credit.amount)
+                .provider(charge.amount),
+        );
+    }
+
+    if credit.provider_fees != charge.fees {
+        report.discrepancies.push(
+            SettlementDiscrepancy::new(DiscrepancyKind::ChargeFeeMismatch, &charge.reference)
+                .with_transaction(credit)
+                .recorded(credit.provider_fees)
+                .provider(charge.fees),
+        );
+    }
+}
+
+async fn reconcile_transfers(
+    paystack: &Paystack,
+    app_state: &AppState,
+    request: &SettlementReconciliation,
+    report: &mut SettlementReport,
+) -> Result<(), SettlementError> {
+    let transfers = paystack.list_transfers(request.from, request.to).await?;
+    report.transfers_checked = transfers.len();
+
+    let debits = transactions_by_reference(
+        &app_state.db,
+        transfers
+            .iter()
+            .map(|transfer| transfer.reference.to_string())
+            .collect(),
+    )
+    .await?;
+
+    for transfer in &transfers {
+        match debits.get(&transfer.reference) {
+            Some(debit) => compare_transfer(debit, transfer, report),
+            None => report.discrepancies.push(
+                SettlementDiscrepancy::new(DiscrepancyKind::UnknownTransfer, &transfer.reference)
+                    .provider(transfer.amount),
+            ),
+        }
+    }
+
+    let recorded_debits: Vec<transactions::Model> = Transactions::find()
+        .filter(transactions::Column::Provider.eq(paystack.name()))
+        .filter(transactions::Column::Category.eq(TrxCategory::Outward.to_string()))
+        .filter(transactions::Column::TrxType.eq(TrxType::Debit))
+        .filter(transactions::Column::CreatedAt.between(request.from, request.to))
+        .all(&app_state.db)
+        .await?
+        .into_iter()
+        .filter(|debit| {
+            !transfers
+                .iter()
+                .any(|transfer| Some(&transfer.reference) == debit.provider_reference.as_ref())
+        })
+        .collect();
+
+    if recorded_debits.is_empty() {
+        return Ok(());
+    }
+
+    let nearby = match paystack
+        .list_transfers(widened_from(request), widened_to(request))
+        .await
+    {
+        Ok(nearby) => records_by_reference(nearby),
+        Err(err) => {
+            error!(
+                "Error listing Paystack transfers around the period ===> {}",
+                err
+            );
+            HashMap::new()
+        }
+    };
+
+    for debit in recorded_debits {
+        let reference = debit.provider_reference.clone().unwrap_or_default();
+
+        let transfer = match nearby.get(&reference) {
+            Some(transfer) => Some(transfer.clone()),
+            None => match paystack.verify_transfer(&reference).await {
+                Ok(transfer) => transfer,
+                Err(err) => {
+                    report
+                        .discrepancies
+                        .push(lookup_failed(&debit, &reference, err));
+                    continue;
+                }
+            },
+        };
+
+        match transfer {
+            Some(transfer) => compare_transfer(&debit, &transfer, report),
+            // Failed before reaching Paystack and already refunded, nothing went out
+            None if matches!(debit.status, Some(Status::Failed) | Some(Status::Reversed)) => {}
+            None => report.discrepancies.push(
+                SettlementDiscrepancy::new(DiscrepancyKind::MissingTransfer, &reference)
+                    .with_transaction(&debit)
+                    .recorded(debit.amount),
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+fn compare_transfer(
+    debit: &transactions::Model,
+    transfer: &SettlementRecord,
+    report: &mut SettlementReport,
+) {
+    if debit.amount != transfer.amount {
+        report.discrepancies.push(
+            SettlementDiscrepancy::new(
+                DiscrepancyKind::TransferAmountMismatch,
+                &transfer.reference,
+            )
+            .with_transaction(debit)
+            .recorded(debit.amount)
+            .provider(transfer.amount),
+        );
+    }
+
+    let paid_out = transfer.status == "success";
+    let refunded = matches!(debit.status, Some(Status::Reversed) | Some(Status::Failed));
+    let provider_failed = matches!(transfer.status.as_str(), "failed" | "reversed");
+
+    if (paid_out && refunded) || (provider_failed && !refunded) {
+        let recorded = debit
+            .status
+            .as_ref()
+            .map(|status| status.to_value())
+            .unwrap_or_default();
+        report.discrepancies.push(
+            SettlementDiscrepancy::new(
+                DiscrepancyKind::TransferStatusMismatch,
+                &transfer.reference,
+            )
+            .with_transaction(debit)
+            .recorded(recorded)
+            .provider(&transfer.status),
+        );
+    }
+}
+
+fn widened_from(request: &SettlementReconciliation) -> DateTime<Utc> {
+    request.from - Duration::days(MATCH_MARGIN_DAYS)
+}
+
+fn widened_to(request: &SettlementReconciliation) -> DateTime<Utc> {
+    request.to + Duration::days(MATCH_MARGIN_DAYS)
+}
+
+fn records_by_reference(records: Vec<SettlementRecord>) -> HashMap<String, SettlementRecord> {
+    records
+        .into_iter()
+        .map(|record| (record.reference.to_string(), record))
+        .collect()
+}
+
+// One reference Paystack couldn't be asked about doesn't cost the rest of the report
+fn lookup_failed(
+    transaction: &transactions::Model,
+    reference: &str,
+    err: ProviderError,
+) -> SettlementDiscrepancy {
+    error!("Error looking up {} on Paystack ===> {}", reference, err);
+    SettlementDiscrepancy::new(DiscrepancyKind::LookupFailed, reference)
+        .with_transaction(transaction)
+        .recorded(transaction.amount)
+        .provider(err)
+}
+
+async fn transactions_by_reference(
+    db: &DatabaseConnection,
+    references: Vec<String>,
+) -> Result<HashMap<String, transactions::Model>, DbErr> {
+    if references.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let transactions = Transactions::find()
+        .filter(transactions::Column::ProviderReference.is_in(references))
+        .all(db)
+        .await?;
+
+    Ok(transactions
+        .into_iter()
+        .filter_map(|transaction| {
+            transaction
+                .provider_reference
+                .clone()
+                .map(|reference| (reference, transaction))
+        })
+        .collect())
+}
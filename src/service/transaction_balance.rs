@@ -2,6 +2,7 @@ use async_trait::async_trait;
 use chrono::Utc;
 use rust_decimal::Decimal;
 use sea_orm::*;
+use std::fmt;
 
 use crate::entities::{
     prelude::Wallets,
@@ -14,6 +15,9 @@ pub enum TrxCategory {
     P2P,
     Funding,
     Outward,
+    MerchantPayment,
+    Escrow,
+    Card,
 }
 
 pub struct TransactionBalance {
@@ -34,18 +38,38 @@ pub struct TransactionBalance {
     pub meta: Option<String>,
 }
 
+// Locks the matching wallets until the transaction ends, so a balance read here is still the
+// balance when it is written back. Every balance writer locks in id order, which keeps two
+// transactions over the same wallets from deadlocking each other
+pub async fn lock_wallets(
+    txn: &DatabaseTransaction,
+    condition: Condition,
+) -> Result<Vec<wallets::Model>, DbErr> {
+    Wallets::find()
+        .filter(condition)
+        .order_by_asc(wallets::Column::Id)
+        .lock_exclusive()
+        .all(txn)
+        .await
+}
+
 #[async_trait]
 pub trait TransactionBalanceTrait {
     async fn save_transaction_update_balance(self, txn: &DatabaseTransaction) -> Result<(), DbErr>;
 }
 
-impl TrxCategory {
-    fn to_string(&self) -> String {
-        match self {
-            TrxCategory::Funding => format!("funding"),
-            TrxCategory::P2P => format!("p2p"),
-            TrxCategory::Outward => format!("outward"),
-        }
+impl fmt::Display for TrxCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let category = match self {
+            TrxCategory::Funding => "funding",
+            TrxCategory::P2P => "p2p",
+            TrxCategory::Outward => "outward",
+            TrxCategory::MerchantPayment => "merchant_payment",
+            TrxCategory::Escrow => "escrow",
+            TrxCategory::Card => "card",
+        };
+
+        write!(f, "{}", category)
     }
 }
 
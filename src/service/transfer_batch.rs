@@ -0,0 +1,651 @@
+use chrono::Utc;
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde::Serialize;
+use std::fmt;
+use thiserror::Error;
+use tracing::{error, info, instrument};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::dto::transfers::BatchTransferItemBody;
+use crate::entities::{
+    prelude::{TransferBatchItems, TransferBatches, Users, Wallets},
+    transfer_batch_items, transfer_batches, users, wallets,
+};
+use crate::utils::config::EnvConfig;
+use crate::AppState;
+
+use super::outward_transfer::{
+    resolve_bank_account, BankAccount, OutwardTransfer, OutwardTransferError, OutwardTransferTrait,
+};
+use super::p2p_transfer::{P2PTransfer, P2PTransferTrait};
+use super::wallet_hold::{available_balance, place_hold, release_hold, release_hold_amount};
+
+pub const MAX_BATCH_ITEMS: usize = 500;
+
+#[derive(Debug, PartialEq)]
+pub enum BatchStatus {
+    Processing,
+    Completed,
+    PartiallyCompleted,
+    Failed,
+}
+
+impl fmt::Display for BatchStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            BatchStatus::Processing => "processing",
+            BatchStatus::Completed => "completed",
+            BatchStatus::PartiallyCompleted => "partially_completed",
+            BatchStatus::Failed => "failed",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BatchItemStatus {
+    Pending,
+    Successful,
+    Failed,
+}
+
+impl fmt::Display for BatchItemStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            BatchItemStatus::Pending => "pending",
+            BatchItemStatus::Successful => "successful",
+            BatchItemStatus::Failed => "failed",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ReceiverType {
+    User,
+    BankAccount,
+}
+
+impl fmt::Display for ReceiverType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let receiver_type = match self {
+            ReceiverType::User => "user",
+            ReceiverType::BankAccount => "bank_account",
+        };
+
+        write!(f, "{}", receiver_type)
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct BatchItemError {
+    pub row: usize,
+    pub message: String,
+}
+
+#[derive(Error, Debug)]
+pub enum TransferBatchError {
+    #[error("Transfer batch not found")]
+    NotFound,
+
+    #[error("Provide either items or csv for the batch")]
+    NoItems,
+
+    #[error("A batch can contain at most {0} items")]
+    TooManyItems(usize),
+
+    #[error("Invalid CSV: {0}")]
+    InvalidCsv(String),
+
+    #[error("Some batch items are invalid")]
+    InvalidItems(Vec<BatchItemError>),
+
+    #[error("You do not seem to have a valid wallet yet. Please contact support")]
+    SenderWalletNotFound,
+
+    #[error("Your wallet is frozen. Please contact support")]
+    WalletFrozen,
+
+    #[error("Insufficient Funds")]
+    InsufficientFunds,
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl TransferBatchError {
+    pub fn is_client_error(&self) -> bool {
+        !matches!(self, TransferBatchError::DatabaseError(_))
+    }
+}
+
+pub struct ValidatedBatchItem {
+    pub receiver_type: ReceiverType,
+    pub receiver_id: Option<String>,
+    pub bank_account: Option<BankAccount>,
+    pub amount: Decimal,
+    pub narration: Option<String>,
+}
+
+// Items come either as a JSON list or as CSV text with the same columns
+pub fn parse_batch_items(
+    items: Option<Vec<BatchTransferItemBody>>,
+    csv_data: Option<String>,
+) -> Result<Vec<BatchTransferItemBody>, TransferBatchError> {
+    let items = match (items, csv_data) {
+        (Some(items), None) => items,
+        (None, Some(csv_data)) => parse_csv(&csv_data)?,
+        _ => return Err(TransferBatchError::NoItems),
+    };
+
+    if items.is_empty() {
+        return Err(TransferBatchError::NoItems);
+    }
+
+    if items.len() > MAX_BATCH_ITEMS {
+        return Err(TransferBatchError::TooManyItems(MAX_BATCH_ITEMS));
+    }
+
+    Ok(items)
+}
+
+fn parse_csv(csv_data: &str) -> Result<Vec<BatchTransferItemBody>, TransferBatchError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(csv_data.as_bytes());
+
+    reader
+        .deserialize()
+        .enumerate()
+        .map(|(index, row)| {
+            row.map_err(|err| TransferBatchError::InvalidCsv(format!("row {}: {}", index + 1, err)))
+        })
+        .collect()
+}
+
+// Checks every item before anything is reserved so the customer gets all problems back at once
+pub async fn validate_batch_items(
+    app_state: &AppState,
+    sender: &users::Model,
+    items: Vec<BatchTransferItemBody>,
+) -> Result<Vec<ValidatedBatchItem>, TransferBatchError> {
+    let mut errors = vec![];
+    let mut validated_items = vec![];
+
+    for (index, item) in items.into_iter().enumerate() {
+        let row = index + 1;
+
+        if let Err(err) = item.validate() {
+            errors.push(BatchItemError {
+                row,
+                message: err.to_string(),
+            });
+            continue;
+        }
+
+        let receivers = [&item.user_id, &item.email, &item.account_number]
+            .iter()
+            .filter(|receiver| receiver.is_some())
+            .count();
+        if receivers != 1 {
+            errors.push(BatchItemError {
+                row,
+                message: String::from("Provide exactly one of user_id, email or account_number"),
+            });
+            continue;
+        }
+
+        match validate_batch_item(app_state, sender, item).await {
+            Ok(validated_item) => validated_items.push(validated_item),
+            Err(message) => errors.push(BatchItemError { row, message }),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(TransferBatchError::InvalidItems(errors));
+    }
+
+    Ok(validated_items)
+}
+
+async fn validate_batch_item(
+    app_state: &AppState,
+    sender: &users::Model,
+    item: BatchTransferItemBody,
+) -> Result<ValidatedBatchItem, String> {
+    if let Some(account_number) = &item.account_number {
+        let bank_code = match &item.bank_code {
+            Some(bank_code) => bank_code,
+            None => return Err(String::from("bank_code is required for bank transfers")),
+        };
+
+        let bank_account = resolve_bank_account(account_number, bank_code, &app_state.env)
+            .await
+            .map_err(|err| match err {
+                OutwardTransferError::AccountResolutionFailed(_) => err.to_string(),
+                err => {
+                    error!("Error resolving batch bank account: {}", err);
+                    String::from("Could not resolve bank account at this time")
+                }
+            })?;
+
+        return Ok(ValidatedBatchItem {
+            receiver_type: ReceiverType::BankAccount,
+            receiver_id: None,
+            bank_account: Some(bank_account),
+            amount: item.amount.into(),
+            narration: item.narration,
+        });
+    }
+
+    let receiver = match (&item.user_id, &item.email) {
+        (Some(user_id), _) => Users::find().filter(users::Column::Uuid.eq(user_id)),
+        (_, Some(email)) => Users::find().filter(users::Column::Email.eq(email)),
+        _ => return Err(String::from("Receiver not found")),
+    }
+    .one(&app_state.db)
+    .await
+    .map_err(|err| {
+        error!("DB error validating batch receiver: {}", err);
+        String::from("Could not validate receiver at this time")
+    })?;
+
+    let receiver = match receiver {
+        Some(receiver) => receiver,
+        None => return Err(String::from("Receiver not found")),
+    };
+
+    if receiver.uuid == sender.uuid {
+        return Err(String::from("Cannot send funds to yourself"));
+    }
+
+    let receiver_name = format!("{} {}", receiver.last_name, receiver.first_name);
+    if !receiver.is_verified {
+        return Err(format!("{} is not yet verified", receiver_name));
+    }
+
+    let receiver_wallet = Wallets::find()
+        .filter(wallets::Column::UserId.eq(&receiver.uuid))
+        .filter(wallets::Column::Default.eq(true))
+        .one(&app_state.db)
+        .await
+        .map_err(|err| {
+            error!("DB error validating batch receiver wallet: {}", err);
+            String::from("Could not validate receiver at this time")
+        })?;
+
+    if receiver_wallet.is_none() {
+        return Err(format!(
+            "{} Does not have a valid wallet yet",
+            receiver_name
+        ));
+    }
+
+    Ok(ValidatedBatchItem {
+        receiver_type: ReceiverType::User,
+        receiver_id: Some(receiver.uuid),
+        bank_account: None,
+        amount: item.amount.into(),
+        narration: item.narration,
+    })
+}
+
+// Saves the batch and reserves its total on the sender's wallet
+pub async fn create_transfer_batch(
+    txn: &DatabaseTransaction,
+    sender: &users::Model,
+    items: Vec<ValidatedBatchItem>,
+) -> Result<(transfer_batches::Model, Vec<transfer_batch_items::Model>), TransferBatchError> {
+    let total_amount: Decimal = items.iter().map(|item| item.amount).sum();
+
+    let sender_wallet = Wallets::find()
+        .filter(wallets::Column::UserId.eq(&sender.uuid))
+        .filter(wallets::Column::Default.eq(true))
+        .one(txn)
+        .await?;
+
+    let sender_wallet = match sender_wallet {
+        Some(sender_wallet) => sender_wallet,
+        None => return Err(TransferBatchError::SenderWalletNotFound),
+    };
+
+    if sender_wallet.is_frozen() {
+        return Err(TransferBatchError::WalletFrozen);
+    }
+
+    if total_amount > available_balance(txn, &sender_wallet).await? {
+        return Err(TransferBatchError::InsufficientFunds);
+    }
+
+    let batch_id = Uuid::new_v4().to_string();
+    let hold = place_hold(
+        txn,
+        &sender_wallet,
+        total_amount,
+        "transfer_batch",
+        &batch_id,
+    )
+    .await?;
+
+    let batch = transfer_batches::ActiveModel {
+        uuid: Set(batch_id.to_string()),
+        user_id: Set(sender.uuid.to_string()),
+        total_amount: Set(total_amount),
+        item_count: Set(items.len() as i32),
+        hold_id: Set(Some(hold.uuid)),
+        status: Set(BatchStatus::Processing.to_string()),
+        ..Default::default()
+    }
+    .insert(txn)
+    .await?;
+
+    let mut batch_items = vec![];
+    for (index, item) in items.into_iter().enumerate() {
+        let bank_account = item.bank_account;
+        let batch_item = transfer_batch_items::ActiveModel {
+            uuid: Set(Uuid::new_v4().to_string()),
+            batch_id: Set(batch_id.to_string()),
+            position: Set(index as i32 + 1),
+            receiver_type: Set(item.receiver_type.to_string()),
+            receiver_id: Set(item.receiver_id),
+            account_number: Set(bank_account
+                .as_ref()
+                .map(|bank| bank.account_number.to_string())),
+            bank_code: Set(bank_account.as_ref().map(|bank| bank.bank_code.to_string())),
+            account_name: Set(bank_account.map(|bank| bank.account_name)),
+            amount: Set(item.amount),
+            narration: Set(item.narration),
+            status: Set(BatchItemStatus::Pending.to_string()),
+            ..Default::default()
+        }
+        .insert(txn)
+        .await?;
+
+        batch_items.push(batch_item);
+    }
+
+    Ok((batch, batch_items))
+}
+
+// Picks up batches that were still running when the server stopped. Spawned once on startup
+pub async fn resume_transfer_batches(app_state: AppState) {
+    let batches = TransferBatches::find()
+        .filter(transfer_batches::Column::Status.eq(BatchStatus::Processing.to_string()))
+        .all(&app_state.db)
+        .await;
+
+    match batches {
+        Ok(batches) => {
+            for batch in batches {
+                info!("Resuming transfer batch {}", batch.uuid);
+                process_transfer_batch(app_state.clone(), batch.uuid).await;
+            }
+        }
+        Err(err) => error!("Error fetching unfinished transfer batches: {}", err),
+    }
+}
+
+#[instrument(skip(app_state))]
+pub async fn process_transfer_batch(app_state: AppState, batch_id: String) {
+    if let Err(err) = execute_transfer_batch(&app_state, &batch_id).await {
+        error!("Error processing transfer batch {}: {}", batch_id, err);
+    }
+}
+
+async fn execute_transfer_batch(app_state: &AppState, batch_id: &String) -> Result<(), DbErr> {
+    let batch = TransferBatches::find()
+        .filter(transfer_batches::Column::Uuid.eq(batch_id))
+        .filter(transfer_batches::Column::Status.eq(BatchStatus::Processing.to_string()))
+        .one(&app_state.db)
+        .await?;
+
+    let batch = match batch {
+        Some(batch) => batch,
+        None => return Ok(()),
+    };
+
+    let sender = Users::find()
+        .filter(users::Column::Uuid.eq(&batch.user_id))
+        .one(&app_state.db)
+        .await?;
+
+    let sender = match sender {
+        Some(sender) => sender,
+        None => {
+            error!(
+                "Sender {} of transfer batch {} not found",
+                batch.user_id, batch.uuid
+            );
+            return Ok(());
+        }
+    };
+
+    let items = TransferBatchItems::find()
+        .filter(transfer_batch_items::Column::BatchId.eq(&batch.uuid))
+        .filter(transfer_batch_items::Column::Status.eq(BatchItemStatus::Pending.to_string()))
+        .order_by_asc(transfer_batch_items::Column::Position)
+        .all(&app_state.db)
+        .await?;
+
+    for item in items {
+        execute_batch_item(app_state, &batch, &sender, item).await?;
+    }
+
+    finalise_transfer_batch(&app_state.db, batch).await
+}
+
+// Each item moves its share out of the hold and runs the transfer in the same DB transaction,
+// so the reserved total always matches the items still waiting to run. The item is claimed first,
+// so a resume overlapping a live run can't pay it twice
+async fn execute_batch_item(
+    app_state: &AppState,
+    batch: &transfer_batches::Model,
+    sender: &users::Model,
+    item: transfer_batch_items::Model,
+) -> Result<(), DbErr> {
+    let hold_id = batch.hold_id.clone().unwrap_or_default();
+    let txn = app_state
+        .db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadWrite),
+        )
+        .await?;
+
+    match run_batch_item(&txn, &app_state.env, &hold_id, sender, item).await {
+        Ok(Some((item, outward_transfer))) => {
+            txn.commit().await?;
+            dispatch_batch_item(app_state, item, outward_transfer).await
+        }
+        Ok(None) => {
+            let _ = txn.rollback().await;
+            Ok(())
+        }
+        Err(err) => {
+            let _ = txn.rollback().await;
+            Err(err)
+        }
+    }
+}
+
+// Returns the settled item and the bank transfer still to send, or None when another run had
+// already claimed the item. A failed transfer only rolls back its own savepoint, so the item is
+// marked failed and its share of the hold released together
+async fn run_batch_item(
+    txn: &DatabaseTransaction,
+    env: &EnvConfig,
+    hold_id: &String,
+    sender: &users::Model,
+    item: transfer_batch_items::Model,
+) -> Result<Option<(transfer_batch_items::Model, Option<OutwardTransfer>)>, DbErr> {
+    // Locks the item for the rest of the transaction. A concurrent run waits here and then finds
+    // it no longer pending
+    let claimed = TransferBatchItems::update_many()
+        .col_expr(
+            transfer_batch_items::Column::UpdatedAt,
+            sea_query::Expr::value(Utc::now()),
+        )
+        .filter(transfer_batch_items::Column::Id.eq(item.id))
+        .filter(transfer_batch_items::Column::Status.eq(BatchItemStatus::Pending.to_string()))
+        .exec(txn)
+        .await?;
+    if claimed.rows_affected == 0 {
+        return Ok(None);
+    }
+
+    release_hold_amount(txn, hold_id, item.amount).await?;
+
+    let outward_transfer = match item.receiver_type == ReceiverType::BankAccount.to_string() {
+        true => Some(OutwardTransfer {
+            sender: sender.clone(),
+            bank_account: BankAccount {
+                account_number: item.account_number.clone().unwrap_or_default(),
+                bank_code: item.bank_code.clone().unwrap_or_default(),
+                account_name: item.account_name.clone().unwrap_or_default(),
+            },
+            amount: item.amount,
+            narration: item.narration.clone(),
+        }),
+        false => None,
+    };
+
+    let transfer_txn = txn.begin().await?;
+    let result = match &outward_transfer {
+        Some(outward_transfer) => outward_transfer
+            .debit_with_txn(&transfer_txn, env)
+            .await
+            .map_err(|err| err.to_string()),
+        None => P2PTransfer {
+            sender: sender.clone(),
+            receiver_id: item.receiver_id.clone().unwrap_or_default(),
+            amount: item.amount,
+            narration: item.narration.clone(),
+        }
+        .transfer_with_txn(&transfer_txn)
+        .await
+        .map(|receipt| receipt.sender_ref)
+        .map_err(|err| err.to_string()),
+    };
+
+    let item = match result {
+        Ok(reference) => {
+            transfer_txn.commit().await?;
+            update_batch_item(
+                txn,
+                item,
+                BatchItemStatus::Successful,
+                Some(reference),
+                None,
+            )
+            .await?
+        }
+        Err(message) => {
+            transfer_txn.rollback().await?;
+            let item =
+                update_batch_item(txn, item, BatchItemStatus::Failed, None, Some(message)).await?;
+            return Ok(Some((item, None)));
+        }
+    };
+
+    Ok(Some((item, outward_transfer)))
+}
+
+async fn dispatch_batch_item(
+    app_state: &AppState,
+    item: transfer_batch_items::Model,
+    outward_transfer: Option<OutwardTransfer>,
+) -> Result<(), DbErr> {
+    let outward_transfer = match outward_transfer {
+        Some(outward_transfer) => outward_transfer,
+        None => return Ok(()),
+    };
+    let reference = item.transaction_reference.clone().unwrap_or_default();
+
+    // A request that may have reached the provider leaves the debit pending, the transfer webhook settles it
+    match outward_transfer.dispatch(&reference, app_state).await {
+        Ok(_) => Ok(()),
+        Err(OutwardTransferError::ProviderUnavailable(err)) => {
+            error!(
+                "Error sending batch item {} to transfer provider: {}",
+                item.uuid, err
+            );
+            Ok(())
+        }
+        Err(err) => {
+            let message = err.to_string();
+            let reference = item.transaction_reference.clone();
+            update_batch_item(
+                &app_state.db,
+                item,
+                BatchItemStatus::Failed,
+                reference,
+                Some(message),
+            )
+            .await?;
+            Ok(())
+        }
+    }
+}
+
+async fn update_batch_item<C: ConnectionTrait>(
+    db: &C,
+    item: transfer_batch_items::Model,
+    status: BatchItemStatus,
+    transaction_reference: Option<String>,
+    error: Option<String>,
+) -> Result<transfer_batch_items::Model, DbErr> {
+    let mut updated_item: transfer_batch_items::ActiveModel = item.into();
+    updated_item.status = Set(status.to_string());
+    updated_item.transaction_reference = Set(transaction_reference);
+    updated_item.error = Set(error);
+    updated_item.updated_at = Set(Utc::now());
+
+    updated_item.update(db).await
+}
+
+async fn finalise_transfer_batch(
+    db: &DatabaseConnection,
+    batch: transfer_batches::Model,
+) -> Result<(), DbErr> {
+    let items = TransferBatchItems::find()
+        .filter(transfer_batch_items::Column::BatchId.eq(&batch.uuid))
+        .all(db)
+        .await?;
+
+    let successful_count = items
+        .iter()
+        .filter(|item| item.status == BatchItemStatus::Successful.to_string())
+        .count() as i32;
+    let failed_count = items
+        .iter()
+        .filter(|item| item.status == BatchItemStatus::Failed.to_string())
+        .count() as i32;
+
+    let status = if failed_count == 0 {
+        BatchStatus::Completed
+    } else if successful_count == 0 {
+        BatchStatus::Failed
+    } else {
+        BatchStatus::PartiallyCompleted
+    };
+
+    // Nothing should be left on the hold at this point, release it regardless
+    if let Some(hold_id) = &batch.hold_id {
+        release_hold(db, hold_id).await?;
+    }
+
+    let now = Utc::now();
+    let mut finished_batch: transfer_batches::ActiveModel = batch.into();
+    finished_batch.successful_count = Set(successful_count);
+    finished_batch.failed_count = Set(failed_count);
+    finished_batch.status = Set(status.to_string());
+    finished_batch.completed_at = Set(Some(now));
+    finished_batch.updated_at = Set(now);
+    finished_batch.update(db).await?;
+
+    Ok(())
+}
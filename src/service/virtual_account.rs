@@ -0,0 +1,106 @@
+use sea_orm::*;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::entities::{
+    prelude::{VirtualAccounts, Wallets},
+    users, virtual_accounts, wallets,
+};
+use crate::utils::payment_provider::{PaymentProvider, ProviderError};
+use crate::utils::paystack::Paystack;
+use crate::AppState;
+
+#[derive(Error, Debug)]
+pub enum VirtualAccountError {
+    #[error("Please verify your account before taking this action")]
+    WalletNotFound,
+
+    #[error(transparent)]
+    ProviderError(#[from] ProviderError),
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl VirtualAccountError {
+    pub fn is_client_error(&self) -> bool {
+        matches!(self, VirtualAccountError::WalletNotFound)
+    }
+}
+
+// Gives the user a bank account that funds their default wallet. Safe to call again, a user who
+// already has one gets it back
+pub async fn ensure_virtual_account(
+    app_state: &AppState,
+    user: &users::Model,
+) -> Result<virtual_accounts::Model, VirtualAccountError> {
+    let paystack = Paystack::new(&app_state.env);
+    if app_state.env.paystack_secret.is_empty() {
+        return Err(VirtualAccountError::ProviderError(
+            ProviderError::NoProviderConfigured,
+        ));
+    }
+
+    let existing = VirtualAccounts::find()
+        .filter(virtual_accounts::Column::UserId.eq(&user.uuid))
+        .filter(virtual_accounts::Column::Provider.eq(paystack.name()))
+        .one(&app_state.db)
+        .await?;
+    if let Some(existing) = existing {
+        return Ok(existing);
+    }
+
+    let wallet = Wallets::find()
+        .filter(wallets::Column::UserId.eq(&user.uuid))
+        .filter(wallets::Column::Default.eq(true))
+        .one(&app_state.db)
+        .await?
+        .ok_or(VirtualAccountError::WalletNotFound)?;
+
+    let customer_code = paystack
+        .create_customer(&user.email, &user.first_name, &user.last_name)
+        .await?;
+    let account = paystack.create_dedicated_account(&customer_code).await?;
+
+    let virtual_account = virtual_accounts::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        user_id: Set(user.uuid.to_string()),
+        wallet_id: Set(wallet.uuid.to_string()),
+        provider: Set(paystack.name().to_string()),
+        customer_code: Set(customer_code),
+        provider_account_id: Set(account.id),
+        account_number: Set(account.account_number),
+        account_name: Set(account.account_name),
+        bank_name: Set(account.bank_name),
+        ..Default::default()
+    }
+    .insert(&app_state.db)
+    .await?;
+
+    Ok(virtual_account)
+}
+
+pub async fn user_virtual_accounts<C: ConnectionTrait>(
+    db: &C,
+    user_id: &String,
+) -> Result<Vec<virtual_accounts::Model>, DbErr> {
+    VirtualAccounts::find()
+        .filter(virtual_accounts::Column::UserId.eq(user_id))
+        .filter(virtual_accounts::Column::DeletedAt.is_null())
+        .all(db)
+        .await
+}
+
+// Transfers into a virtual account carry no metadata, only the provider's customer
+pub async fn find_virtual_account<C: ConnectionTrait>(
+    db: &C,
+    provider: &str,
+    customer_code: &str,
+) -> Result<Option<virtual_accounts::Model>, DbErr> {
+    VirtualAccounts::find()
+        .filter(virtual_accounts::Column::Provider.eq(provider))
+        .filter(virtual_accounts::Column::CustomerCode.eq(customer_code))
+        .filter(virtual_accounts::Column::DeletedAt.is_null())
+        .one(db)
+        .await
+}
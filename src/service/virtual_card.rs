@@ -0,0 +1,753 @@
+use chrono::{DateTime, Datelike, NaiveTime, Utc};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde::Serialize;
+use serde_json::json;
+use std::fmt;
+use thiserror::Error;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::entities::{
+    card_authorizations,
+    prelude::{CardAuthorizations, VirtualCards, Wallets},
+    sea_orm_active_enums::{Status, TrxType},
+    users, virtual_cards, wallets,
+};
+use crate::utils::card_issuer::{CardEvent, CardIssuer, IssueCardRequest, IssuerError};
+
+use super::transaction_balance::{TransactionBalance, TransactionBalanceTrait, TrxCategory};
+use super::wallet_hold::{available_balance, place_hold, release_hold};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CardStatus {
+    Active,
+    Frozen,
+    Terminated,
+}
+
+impl fmt::Display for CardStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            CardStatus::Active => "active",
+            CardStatus::Frozen => "frozen",
+            CardStatus::Terminated => "terminated",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AuthorizationStatus {
+    Approved,
+    Declined,
+    Reversed,
+    Cleared,
+}
+
+impl fmt::Display for AuthorizationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            AuthorizationStatus::Approved => "approved",
+            AuthorizationStatus::Declined => "declined",
+            AuthorizationStatus::Reversed => "reversed",
+            AuthorizationStatus::Cleared => "cleared",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+// What a card's spend limit applies to. Daily and monthly limits reset at midnight UTC
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SpendLimitInterval {
+    Transaction,
+    Daily,
+    Monthly,
+}
+
+impl fmt::Display for SpendLimitInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let interval = match self {
+            SpendLimitInterval::Transaction => "transaction",
+            SpendLimitInterval::Daily => "daily",
+            SpendLimitInterval::Monthly => "monthly",
+        };
+
+        write!(f, "{}", interval)
+    }
+}
+
+impl SpendLimitInterval {
+    pub fn parse(interval: &str) -> Option<SpendLimitInterval> {
+        match interval {
+            "transaction" => Some(SpendLimitInterval::Transaction),
+            "daily" => Some(SpendLimitInterval::Daily),
+            "monthly" => Some(SpendLimitInterval::Monthly),
+            _ => None,
+        }
+    }
+
+    // Start of the period the limit is counted over, None when each charge stands alone
+    fn period_start(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = now.date_naive();
+        let start = match self {
+            SpendLimitInterval::Transaction => return None,
+            SpendLimitInterval::Daily => today,
+            SpendLimitInterval::Monthly => today.with_day(1)?,
+        };
+
+        Some(start.and_time(NaiveTime::MIN).and_utc())
+    }
+}
+
+// Why an authorization was turned down. Sent back to the issuer and kept on the authorization
+#[derive(Debug, PartialEq)]
+pub enum DeclineReason {
+    CardInactive,
+    WalletFrozen,
+    SpendLimitExceeded,
+    InsufficientFunds,
+}
+
+impl fmt::Display for DeclineReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            DeclineReason::CardInactive => "card_inactive",
+            DeclineReason::WalletFrozen => "wallet_frozen",
+            DeclineReason::SpendLimitExceeded => "spend_limit_exceeded",
+            DeclineReason::InsufficientFunds => "insufficient_funds",
+        };
+
+        write!(f, "{}", reason)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum VirtualCardError {
+    #[error("Please verify your account before taking this action")]
+    NotVerified,
+
+    #[error("Wallet not found")]
+    WalletNotFound,
+
+    #[error("Card not found")]
+    CardNotFound,
+
+    #[error("Card authorization not found")]
+    AuthorizationNotFound,
+
+    #[error("This card cannot be {0} while it is {1}")]
+    InvalidState(String, String),
+
+    #[error("Spend limit interval must be transaction, daily or monthly")]
+    InvalidSpendLimitInterval,
+
+    #[error("Card issuing is not available at the moment")]
+    IssuerNotConfigured,
+
+    #[error("Card issuer error: {0}")]
+    IssuerError(#[from] IssuerError),
+
+    #[error("Database error occured")]
+    DatabaseError(#[from] DbErr),
+}
+
+impl VirtualCardError {
+    pub fn is_client_error(&self) -> bool {
+        match self {
+            VirtualCardError::IssuerError(err) => matches!(err, IssuerError::Rejected(_)),
+            VirtualCardError::IssuerNotConfigured | VirtualCardError::DatabaseError(_) => false,
+            _ => true,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(
+            self,
+            VirtualCardError::WalletNotFound
+                | VirtualCardError::CardNotFound
+                | VirtualCardError::AuthorizationNotFound
+        )
+    }
+}
+
+pub struct NewCard {
+    // The user's default wallet when left out
+    pub wallet_id: Option<String>,
+    pub label: Option<String>,
+    pub spend_limit: Option<Decimal>,
+    pub spend_limit_interval: Option<SpendLimitInterval>,
+}
+
+pub struct CardAuthorizationRequest {
+    pub card_reference: String,
+    pub authorization_reference: String,
+    pub amount: Decimal,
+    pub merchant_name: String,
+}
+
+// The answer to an authorization webhook
+#[derive(Serialize, Debug)]
+pub struct AuthorizationDecision {
+    pub approved: bool,
+    pub decline_reason: Option<String>,
+    pub authorization_id: String,
+}
+
+impl From<&card_authorizations::Model> for AuthorizationDecision {
+    fn from(authorization: &card_authorizations::Model) -> Self {
+        AuthorizationDecision {
+            approved: authorization.status != AuthorizationStatus::Declined.to_string(),
+            decline_reason: authorization.decline_reason.clone(),
+            authorization_id: authorization.uuid.to_string(),
+        }
+    }
+}
+
+async fn find_wallet<C: ConnectionTrait>(
+    db: &C,
+    user: &users::Model,
+    wallet_id: Option<&String>,
+) -> Result<wallets::Model, VirtualCardError> {
+    let query = Wallets::find().filter(wallets::Column::UserId.eq(&user.uuid));
+    let query = match wallet_id {
+        Some(wallet_id) => query.filter(wallets::Column::Uuid.eq(wallet_id)),
+        None => query.filter(wallets::Column::Default.eq(true)),
+    };
+
+    query.one(db).await?.ok_or(VirtualCardError::WalletNotFound)
+}
+
+// Issues the card with the issuer first, so a card is only saved once it can be used
+pub async fn create_card<C: ConnectionTrait>(
+    db: &C,
+    issuer: &dyn CardIssuer,
+    user: &users::Model,
+    new_card: NewCard,
+) -> Result<virtual_cards::Model, VirtualCardError> {
+    if !user.is_verified {
+        return Err(VirtualCardError::NotVerified);
+    }
+
+    let wallet = find_wallet(db, user, new_card.wallet_id.as_ref()).await?;
+
+    let card_id = Uuid::new_v4().to_string();
+    let name_on_card = format!("{} {}", user.first_name, user.last_name).to_uppercase();
+    let issued = issuer
+        .issue_card(&IssueCardRequest {
+            reference: card_id.to_string(),
+            name_on_card: name_on_card.to_string(),
+            email: user.email.to_string(),
+        })
+        .await?;
+
+    // A limit without an interval applies to each charge
+    let spend_limit_interval = new_card.spend_limit.map(|_| {
+        new_card
+            .spend_limit_interval
+            .unwrap_or(SpendLimitInterval::Transaction)
+            .to_string()
+    });
+
+    let card = virtual_cards::ActiveModel {
+        uuid: Set(card_id),
+        user_id: Set(user.uuid.to_string()),
+        wallet_id: Set(wallet.uuid),
+        issuer: Set(issuer.name().to_string()),
+        issuer_reference: Set(issued.issuer_reference),
+        label: Set(new_card.label),
+        name_on_card: Set(name_on_card),
+        brand: Set(issued.brand),
+        last4: Set(issued.last4),
+        exp_month: Set(issued.exp_month),
+        exp_year: Set(issued.exp_year),
+        status: Set(CardStatus::Active.to_string()),
+        spend_limit: Set(new_card.spend_limit),
+        spend_limit_interval: Set(spend_limit_interval),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    Ok(card)
+}
+
+pub async fn user_cards<C: ConnectionTrait>(
+    db: &C,
+    user: &users::Model,
+) -> Result<Vec<virtual_cards::Model>, DbErr> {
+    VirtualCards::find()
+        .filter(virtual_cards::Column::UserId.eq(&user.uuid))
+        .order_by_desc(virtual_cards::Column::CreatedAt)
+        .all(db)
+        .await
+}
+
+pub async fn find_user_card<C: ConnectionTrait>(
+    db: &C,
+    user: &users::Model,
+    card_id: &str,
+) -> Result<virtual_cards::Model, VirtualCardError> {
+    VirtualCards::find()
+        .filter(virtual_cards::Column::Uuid.eq(card_id))
+        .filter(virtual_cards::Column::UserId.eq(&user.uuid))
+        .one(db)
+        .await?
+        .ok_or(VirtualCardError::CardNotFound)
+}
+
+pub async fn card_authorizations<C: ConnectionTrait>(
+    db: &C,
+    user: &users::Model,
+    card_id: &str,
+) -> Result<Vec<card_authorizations::Model>, VirtualCardError> {
+    let card = find_user_card(db, user, card_id).await?;
+
+    Ok(CardAuthorizations::find()
+        .filter(card_authorizations::Column::CardId.eq(&card.uuid))
+        .order_by_desc(card_authorizations::Column::Id)
+        .all(db)
+        .await?)
+}
+
+// Moves the card from one of `from` to `to`. The issuer is told first, and the change is
+// conditional on the status so a card terminated meanwhile is never brought back
+async fn change_status<C: ConnectionTrait>(
+    db: &C,
+    card: &virtual_cards::Model,
+    from: &[CardStatus],
+    to: CardStatus,
+) -> Result<virtual_cards::Model, VirtualCardError> {
+    let now = Utc::now();
+    let mut update = VirtualCards::update_many()
+        .col_expr(
+            virtual_cards::Column::Status,
+            sea_query::Expr::value(to.to_string()),
+        )
+        .col_expr(
+            virtual_cards::Column::UpdatedAt,
+            sea_query::Expr::value(now),
+        )
+        .filter(virtual_cards::Column::Id.eq(card.id))
+        .filter(virtual_cards::Column::Status.is_in(from.iter().map(|status| status.to_string())));
+
+    if to == CardStatus::Terminated {
+        update = update.col_expr(
+            virtual_cards::Column::TerminatedAt,
+            sea_query::Expr::value(now),
+        );
+    }
+
+    if update.exec(db).await?.rows_affected == 0 {
+        return Err(VirtualCardError::InvalidState(
+            to.to_string(),
+            card.status.to_string(),
+        ));
+    }
+
+    VirtualCards::find_by_id(&card.uuid)
+        .one(db)
+        .await?
+        .ok_or(VirtualCardError::CardNotFound)
+}
+
+fn ensure_status(
+    card: &virtual_cards::Model,
+    allowed: &[CardStatus],
+    action: CardStatus,
+) -> Result<(), VirtualCardError> {
+    if allowed
+        .iter()
+        .any(|status| card.status == status.to_string())
+    {
+        return Ok(());
+    }
+
+    Err(VirtualCardError::InvalidState(
+        action.to_string(),
+        card.status.to_string(),
+    ))
+}
+
+pub async fn freeze_card<C: ConnectionTrait>(
+    db: &C,
+    issuer: &dyn CardIssuer,
+    user: &users::Model,
+    card_id: &str,
+) -> Result<virtual_cards::Model, VirtualCardError> {
+    let card = find_user_card(db, user, card_id).await?;
+    ensure_status(&card, &[CardStatus::Active], CardStatus::Frozen)?;
+
+    issuer.freeze_card(&card.issuer_reference).await?;
+    change_status(db, &card, &[CardStatus::Active], CardStatus::Frozen).await
+}
+
+pub async fn unfreeze_card<C: ConnectionTrait>(
+    db: &C,
+    issuer: &dyn CardIssuer,
+    user: &users::Model,
+    card_id: &str,
+) -> Result<virtual_cards::Model, VirtualCardError> {
+    let card = find_user_card(db, user, card_id).await?;
+    ensure_status(&card, &[CardStatus::Frozen], CardStatus::Active)?;
+
+    issuer.unfreeze_card(&card.issuer_reference).await?;
+    change_status(db, &card, &[CardStatus::Frozen], CardStatus::Active).await
+}
+
+// Charges already authorized on the card can still clear afterwards, so their holds are kept
+pub async fn terminate_card<C: ConnectionTrait>(
+    db: &C,
+    issuer: &dyn CardIssuer,
+    user: &users::Model,
+    card_id: &str,
+) -> Result<virtual_cards::Model, VirtualCardError> {
+    let card = find_user_card(db, user, card_id).await?;
+    let from = [CardStatus::Active, CardStatus::Frozen];
+    ensure_status(&card, &from, CardStatus::Terminated)?;
+
+    issuer.terminate_card(&card.issuer_reference).await?;
+    change_status(db, &card, &from, CardStatus::Terminated).await
+}
+
+// A None limit takes the limit off the card
+pub async fn set_spend_limit<C: ConnectionTrait>(
+    db: &C,
+    user: &users::Model,
+    card_id: &str,
+    spend_limit: Option<Decimal>,
+    interval: Option<SpendLimitInterval>,
+) -> Result<virtual_cards::Model, VirtualCardError> {
+    let card = find_user_card(db, user, card_id).await?;
+    ensure_status(
+        &card,
+        &[CardStatus::Active, CardStatus::Frozen],
+        CardStatus::Active,
+    )?;
+
+    let interval = spend_limit.map(|_| {
+        interval
+            .unwrap_or(SpendLimitInterval::Transaction)
+            .to_string()
+    });
+
+    let mut limited: virtual_cards::ActiveModel = card.into();
+    limited.spend_limit = Set(spend_limit);
+    limited.spend_limit_interval = Set(interval);
+    limited.updated_at = Set(Utc::now());
+
+    Ok(limited.update(db).await?)
+}
+
+// What the card has spent, or has on hold, in the limit's current period
+async fn spent_in_period<C: ConnectionTrait>(
+    db: &C,
+    card: &virtual_cards::Model,
+    since: DateTime<Utc>,
+) -> Result<Decimal, DbErr> {
+    let authorizations = CardAuthorizations::find()
+        .filter(card_authorizations::Column::CardId.eq(&card.uuid))
+        .filter(card_authorizations::Column::CreatedAt.gte(since))
+        .filter(card_authorizations::Column::Status.is_in([
+            AuthorizationStatus::Approved.to_string(),
+            AuthorizationStatus::Cleared.to_string(),
+        ]))
+        .all(db)
+        .await?;
+
+    Ok(authorizations
+        .iter()
+        .map(|authorization| authorization.cleared_amount.unwrap_or(authorization.amount))
+        .sum())
+}
+
+async fn decline_reason<C: ConnectionTrait>(
+    db: &C,
+    card: &virtual_cards::Model,
+    wallet: &wallets::Model,
+    amount: Decimal,
+) -> Result<Option<DeclineReason>, DbErr> {
+    if card.status != CardStatus::Active.to_string() {
+        return Ok(Some(DeclineReason::CardInactive));
+    }
+
+    if wallet.is_frozen() {
+        return Ok(Some(DeclineReason::WalletFrozen));
+    }
+
+    if let Some(limit) = card.spend_limit {
+        let interval = card
+            .spend_limit_interval
+            .as_deref()
+            .and_then(SpendLimitInterval::parse)
+            .unwrap_or(SpendLimitInterval::Transaction);
+
+        let spent = match interval.period_start(Utc::now()) {
+            Some(since) => spent_in_period(db, card, since).await?,
+            None => Decimal::ZERO,
+        };
+
+        if spent + amount > limit {
+            return Ok(Some(DeclineReason::SpendLimitExceeded));
+        }
+    }
+
+    if amount > available_balance(db, wallet).await? {
+        return Ok(Some(DeclineReason::InsufficientFunds));
+    }
+
+    Ok(None)
+}
+
+// Approves the charge by holding its amount on the card's wallet, or records why it was declined.
+// The issuer may send the same authorization again, in which case the first answer stands
+pub async fn authorize_card_charge(
+    txn: &DatabaseTransaction,
+    issuer: &dyn CardIssuer,
+    request: CardAuthorizationRequest,
+) -> Result<card_authorizations::Model, VirtualCardError> {
+    let existing = CardAuthorizations::find()
+        .filter(card_authorizations::Column::Reference.eq(&request.authorization_reference))
+        .one(txn)
+        .await?;
+
+    if let Some(existing) = existing {
+        return Ok(existing);
+    }
+
+    // The card and its wallet stay locked until the hold is placed, so concurrent authorizations
+    // can't both pass the spend limit and balance checks
+    let card = VirtualCards::find()
+        .filter(virtual_cards::Column::IssuerReference.eq(&request.card_reference))
+        .filter(virtual_cards::Column::Issuer.eq(issuer.name()))
+        .lock_exclusive()
+        .one(txn)
+        .await?
+        .ok_or(VirtualCardError::CardNotFound)?;
+
+    let wallet = Wallets::find_by_id(&card.wallet_id)
+        .lock_exclusive()
+        .one(txn)
+        .await?
+        .ok_or(VirtualCardError::WalletNotFound)?;
+
+    let declined = decline_reason(txn, &card, &wallet, request.amount).await?;
+    let hold_id = match declined {
+        Some(_) => None,
+        None => {
+            let hold = place_hold(
+                txn,
+                &wallet,
+                request.amount,
+                "card_authorization",
+                &request.authorization_reference,
+            )
+            .await?;
+            Some(hold.uuid)
+        }
+    };
+
+    let status = match declined {
+        Some(_) => AuthorizationStatus::Declined,
+        None => AuthorizationStatus::Approved,
+    };
+
+    let authorization = card_authorizations::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        card_id: Set(card.uuid),
+        wallet_id: Set(wallet.uuid),
+        reference: Set(request.authorization_reference),
+        amount: Set(request.amount),
+        merchant_name: Set(request.merchant_name),
+        status: Set(status.to_string()),
+        decline_reason: Set(declined.map(|reason| reason.to_string())),
+        hold_id: Set(hold_id),
+        // Stamped here rather than by the database so spend limit periods compare like with like
+        created_at: Set(Utc::now()),
+        ..Default::default()
+    }
+    .insert(txn)
+    .await?;
+
+    Ok(authorization)
+}
+
+async fn find_approved_authorization<C: ConnectionTrait>(
+    db: &C,
+    reference: &str,
+) -> Result<Option<card_authorizations::Model>, VirtualCardError> {
+    let authorization = CardAuthorizations::find()
+        .filter(card_authorizations::Column::Reference.eq(reference))
+        .one(db)
+        .await?
+        .ok_or(VirtualCardError::AuthorizationNotFound)?;
+
+    // Already reversed or cleared, e.g a repeated webhook
+    if authorization.status != AuthorizationStatus::Approved.to_string() {
+        return Ok(None);
+    }
+
+    Ok(Some(authorization))
+}
+
+// Marks an approved authorization reversed or cleared. Only one webhook can win it
+async fn claim_authorization<C: ConnectionTrait>(
+    db: &C,
+    authorization: &card_authorizations::Model,
+    status: AuthorizationStatus,
+    cleared: Option<(Decimal, &str)>,
+) -> Result<bool, DbErr> {
+    let mut claim = CardAuthorizations::update_many()
+        .col_expr(
+            card_authorizations::Column::Status,
+            sea_query::Expr::value(status.to_string()),
+        )
+        .col_expr(
+            card_authorizations::Column::UpdatedAt,
+            sea_query::Expr::value(Utc::now()),
+        )
+        .filter(card_authorizations::Column::Id.eq(authorization.id))
+        .filter(card_authorizations::Column::Status.eq(AuthorizationStatus::Approved.to_string()));
+
+    if let Some((amount, transaction_id)) = cleared {
+        claim = claim
+            .col_expr(
+                card_authorizations::Column::ClearedAmount,
+                sea_query::Expr::value(amount),
+            )
+            .col_expr(
+                card_authorizations::Column::TransactionId,
+                sea_query::Expr::value(transaction_id.to_string()),
+            );
+    }
+
+    Ok(claim.exec(db).await?.rows_affected > 0)
+}
+
+// The merchant let the authorization go, so the held amount can be spent again
+pub async fn reverse_card_authorization(
+    txn: &DatabaseTransaction,
+    reference: &str,
+) -> Result<(), VirtualCardError> {
+    let authorization = match find_approved_authorization(txn, reference).await? {
+        Some(authorization) => authorization,
+        None => return Ok(()),
+    };
+
+    if claim_authorization(txn, &authorization, AuthorizationStatus::Reversed, None).await? {
+        if let Some(hold_id) = &authorization.hold_id {
+            release_hold(txn, hold_id).await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Swaps the hold for a debit of what the merchant actually charged. This goes through even if it
+// is more than was authorized, the issuer has already paid the merchant
+pub async fn clear_card_charge(
+    txn: &DatabaseTransaction,
+    issuer: &dyn CardIssuer,
+    reference: &str,
+    amount: Decimal,
+) -> Result<(), VirtualCardError> {
+    let authorization = match find_approved_authorization(txn, reference).await? {
+        Some(authorization) => authorization,
+        None => return Ok(()),
+    };
+
+    let transaction_id = Uuid::new_v4().to_string();
+    let cleared = Some((amount, transaction_id.as_str()));
+    if !claim_authorization(txn, &authorization, AuthorizationStatus::Cleared, cleared).await? {
+        return Ok(());
+    }
+
+    if let Some(hold_id) = &authorization.hold_id {
+        release_hold(txn, hold_id).await?;
+    }
+
+    let card = VirtualCards::find_by_id(&authorization.card_id)
+        .one(txn)
+        .await?
+        .ok_or(VirtualCardError::CardNotFound)?;
+    let wallet = Wallets::find_by_id(&authorization.wallet_id)
+        .lock_exclusive()
+        .one(txn)
+        .await?
+        .ok_or(VirtualCardError::WalletNotFound)?;
+
+    TransactionBalance {
+        uuid: transaction_id,
+        amount,
+        trx_type: TrxType::Debit,
+        status: Status::Successful,
+        description: format!("Card - {} - *{}", &authorization.merchant_name, &card.last4),
+        provider_reference: Some(authorization.reference.to_string()),
+        current_balance: wallet.current_balance - amount,
+        previous_balance: wallet.current_balance,
+        user_id: wallet.user_id.to_string(),
+        wallet_id: wallet.uuid.to_string(),
+        provider: issuer.name().to_string(),
+        fees: None,
+        provider_fees: None,
+        category: TrxCategory::Card,
+        meta: Some(
+            json!({
+                "card_id": &card.uuid,
+                "authorization_id": &authorization.uuid,
+                "authorized_amount": authorization.amount,
+                "merchant_name": &authorization.merchant_name,
+            })
+            .to_string(),
+        ),
+    }
+    .save_transaction_update_balance(txn)
+    .await?;
+
+    Ok(())
+}
+
+// Authorization requests are answered with a decision, the other events only need acknowledging
+pub async fn handle_card_event(
+    txn: &DatabaseTransaction,
+    issuer: &dyn CardIssuer,
+    event: CardEvent,
+) -> Result<Option<AuthorizationDecision>, VirtualCardError> {
+    match event {
+        CardEvent::AuthorizationRequest {
+            card_reference,
+            authorization_reference,
+            amount,
+            merchant_name,
+        } => {
+            let request = CardAuthorizationRequest {
+                card_reference,
+                authorization_reference,
+                amount,
+                merchant_name,
+            };
+            let authorization = authorize_card_charge(txn, issuer, request).await?;
+
+            Ok(Some(AuthorizationDecision::from(&authorization)))
+        }
+        CardEvent::AuthorizationReversed {
+            authorization_reference,
+        } => {
+            reverse_card_authorization(txn, &authorization_reference).await?;
+            Ok(None)
+        }
+        CardEvent::Cleared {
+            authorization_reference,
+            amount,
+        } => {
+            clear_card_charge(txn, issuer, &authorization_reference, amount).await?;
+            Ok(None)
+        }
+        CardEvent::Ignored(event_type) => {
+            info!("Ignoring {} card event {}", issuer.name(), event_type);
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use chrono::Utc;
+use rust_decimal::Decimal;
+use sea_orm::*;
+use std::fmt;
+use uuid::Uuid;
+
+use crate::entities::{prelude::WalletHolds, wallet_holds, wallets};
+
+#[derive(Debug, PartialEq)]
+pub enum HoldStatus {
+    Active,
+    Released,
+}
+
+impl fmt::Display for HoldStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            HoldStatus::Active => "active",
+            HoldStatus::Released => "released",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+// Sum of every active hold on the wallet
+pub async fn total_held<C: ConnectionTrait>(db: &C, wallet_id: &String) -> Result<Decimal, DbErr> {
+    let holds = WalletHolds::find()
+        .filter(wallet_holds::Column::WalletId.eq(wallet_id))
+        .filter(wallet_holds::Column::Status.eq(HoldStatus::Active.to_string()))
+        .all(db)
+        .await?;
+
+    Ok(holds.iter().map(|hold| hold.amount).sum())
+}
+
+// Balance that can be spent, i.e the wallet balance less funds reserved by holds
+pub async fn available_balance<C: ConnectionTrait>(
+    db: &C,
+    wallet: &wallets::Model,
+) -> Result<Decimal, DbErr> {
+    let held = total_held(db, &wallet.uuid).await?;
+    Ok(wallet.current_balance - held)
+}
+
+pub async fn place_hold<C: ConnectionTrait>(
+    db: &C,
+    wallet: &wallets::Model,
+    amount: Decimal,
+    reason: &str,
+    reference: &String,
+) -> Result<wallet_holds::Model, DbErr> {
+    let hold = wallet_holds::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        wallet_id: Set(wallet.uuid.to_string()),
+        user_id: Set(wallet.user_id.to_string()),
+        amount: Set(amount),
+        reason: Set(reason.to_string()),
+        reference: Set(reference.to_string()),
+        status: Set(HoldStatus::Active.to_string()),
+        ..Default::default()
+    };
+
+    hold.insert(db).await
+}
+
+// Releases part of a hold so the amount can be spent. The hold is marked released once nothing is left on it
+pub async fn release_hold_amount<C: ConnectionTrait>(
+    db: &C,
+    hold_id: &String,
+    amount: Decimal,
+) -> Result<(), DbErr> {
+    let hold = WalletHolds::find()
+        .filter(wallet_holds::Column::Uuid.eq(hold_id))
+        .filter(wallet_holds::Column::Status.eq(HoldStatus::Active.to_string()))
+        .one(db)
+        .await?;
+
+    let hold = match hold {
+        Some(hold) => hold,
+        None => return Ok(()),
+    };
+
+    let remaining = (hold.amount - amount).max(Decimal::ZERO);
+    let mut updated_hold: wallet_holds::ActiveModel = hold.into();
+    updated_hold.amount = Set(remaining);
+    if remaining == Decimal::ZERO {
+        updated_hold.status = Set(HoldStatus::Released.to_string());
+    }
+    updated_hold.updated_at = Set(Utc::now());
+    updated_hold.update(db).await?;
+
+    Ok(())
+}
+
+pub async fn release_hold<C: ConnectionTrait>(db: &C, hold_id: &String) -> Result<(), DbErr> {
+    WalletHolds::update_many()
+        .col_expr(
+            wallet_holds::Column::Status,
+            sea_query::Expr::value(HoldStatus::Released.to_string()),
+        )
+        .col_expr(
+            wallet_holds::Column::UpdatedAt,
+            sea_query::Expr::value(Utc::now()),
+        )
+        .filter(wallet_holds::Column::Uuid.eq(hold_id))
+        .filter(wallet_holds::Column::Status.eq(HoldStatus::Active.to_string()))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,253 @@
+use chrono::Utc;
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde::Serialize;
+use std::fmt;
+use tracing::{error, info, warn};
+
+use crate::entities::{
+    prelude::{Transactions, Wallets},
+    sea_orm_active_enums::Status,
+    transactions, wallets,
+};
+use crate::AppState;
+
+use super::transaction_balance::TrxCategory;
+
+// Wallets are checked a page at a time so the job never loads the whole table
+const WALLET_PAGE_SIZE: u64 = 100;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MismatchKind {
+    // The wallet balance differs from the balance after its latest transaction
+    BalanceDrift,
+    // A transaction's previous_balance differs from the current_balance of the one before it
+    BrokenChain,
+}
+
+impl fmt::Display for MismatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            MismatchKind::BalanceDrift => "balance_drift",
+            MismatchKind::BrokenChain => "broken_chain",
+        };
+
+        write!(f, "{}", kind)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WalletMismatch {
+    pub wallet_id: String,
+    pub user_id: String,
+    pub kind: MismatchKind,
+    pub transaction_id: Option<String>,
+    pub expected: Decimal,
+    pub actual: Decimal,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ReconciliationReport {
+    pub wallets_checked: usize,
+    pub mismatched_wallets: usize,
+    pub mismatches: Vec<WalletMismatch>,
+    pub frozen_wallets: Vec<String>,
+}
+
+// Background worker that reconciles every wallet against its transactions. Spawned once on startup
+pub async fn run_wallet_reconciliation_worker(app_state: AppState) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        app_state.env.reconciliation_interval_secs,
+    ));
+
+    loop {
+        interval.tick().await;
+
+        match reconcile_wallets(&app_state.db, app_state.env.reconciliation_freeze_wallets).await {
+            Ok(report) if report.mismatches.is_empty() => {
+                info!(
+                    "Reconciled {} wallet(s), no drift found",
+                    report.wallets_checked
+                )
+            }
+            Ok(report) => warn!(
+                "Reconciled {} wallet(s), {} mismatched and {} frozen: {}",
+                report.wallets_checked,
+                report.mismatched_wallets,
+                report.frozen_wallets.len(),
+                serde_json::to_string(&report.mismatches).unwrap_or_default()
+            ),
+            Err(err) => error!("Error reconciling wallets: {}", err),
+        }
+    }
+}
+
+pub async fn reconcile_wallets(
+    db: &DatabaseConnection,
+    freeze_mismatched: bool,
+) -> Result<ReconciliationReport, DbErr> {
+    let mut report = ReconciliationReport::default();
+    let mut wallet_pages = Wallets::find()
+        .filter(wallets::Column::DeletedAt.is_null())
+        .order_by_asc(wallets::Column::Id)
+        .paginate(db, WALLET_PAGE_SIZE);
+
+    while let Some(page) = wallet_pages.fetch_and_next().await? {
+        for wallet in page {
+            let mismatches = reconcile_wallet(db, &wallet.uuid).await?;
+            report.wallets_checked += 1;
+
+            if mismatches.is_empty() {
+                continue;
+            }
+
+            report.mismatched_wallets += 1;
+            if freeze_mismatched && !wallet.is_frozen() {
+                let reason = format!(
+                    "Reconciliation found {}",
+                    mismatches
+                        .iter()
+                        .map(|mismatch| mismatch.kind.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                freeze_wallet(db, &wallet.uuid, &reason).await?;
+                report.frozen_wallets.push(wallet.uuid.to_string());
+            }
+            report.mismatches.extend(mismatches);
+        }
+    }
+
+    Ok(report)
+}
+
+// The wallet and its transactions are read in one snapshot so an in-flight transfer can't show up as drift
+pub async fn reconcile_wallet(
+    db: &DatabaseConnection,
+    wallet_id: &String,
+) -> Result<Vec<WalletMismatch>, DbErr> {
+    let txn = db
+        .begin_with_config(
+            Some(IsolationLevel::RepeatableRead),
+            Some(AccessMode::ReadOnly),
+        )
+        .await?;
+
+    let wallet = Wallets::find()
+        .filter(wallets::Column::Uuid.eq(wallet_id))
+        .one(&txn)
+        .await?;
+
+    let wallet = match wallet {
+        Some(wallet) => wallet,
+        None => {
+            txn.commit().await?;
+            return Ok(vec![]);
+        }
+    };
+
+    // Ids are assigned in insertion order, which is the order balances were applied in
+    let wallet_transactions = Transactions::find()
+        .filter(transactions::Column::WalletId.eq(&wallet.uuid))
+        .order_by_asc(transactions::Column::Id)
+        .all(&txn)
+        .await?;
+
+    txn.commit().await?;
+
+    Ok(find_mismatches(&wallet, &wallet_transactions))
+}
+
+// Wallets open with a zero balance, so the chain starts from zero
+pub fn find_mismatches(
+    wallet: &wallets::Model,
+    wallet_transactions: &[transactions::Model],
+) -> Vec<WalletMismatch> {
+    let mut mismatches = vec![];
+    let mut running_balance = Decimal::ZERO;
+    let mut last_applied = None;
+
+    for transaction in wallet_transactions {
+        // Pending and failed fundings never moved the balance
+        if transaction.category == TrxCategory::Funding.to_string()
+            && transaction.status != Some(Status::Successful)
+        {
+            continue;
+        }
+
+        if transaction.previous_balance != running_balance {
+            mismatches.push(WalletMismatch {
+                wallet_id: wallet.uuid.to_string(),
+                user_id: wallet.user_id.to_string(),
+                kind: MismatchKind::BrokenChain,
+                transaction_id: Some(transaction.uuid.to_string()),
+                expected: running_balance,
+                actual: transaction.previous_balance,
+            });
+        }
+
+        running_balance = transaction.current_balance;
+        last_applied = Some(transaction);
+    }
+
+    if wallet.current_balance != running_balance {
+        mismatches.push(WalletMismatch {
+            wallet_id: wallet.uuid.to_string(),
+            user_id: wallet.user_id.to_string(),
+            kind: MismatchKind::BalanceDrift,
+            transaction_id: last_applied.map(|transaction| transaction.uuid.to_string()),
+            expected: running_balance,
+            actual: wallet.current_balance,
+        });
+    }
+
+    mismatches
+}
+
+pub async fn freeze_wallet<C: ConnectionTrait>(
+    db: &C,
+    wallet_id: &String,
+    reason: &str,
+) -> Result<(), DbErr> {
+    Wallets::update_many()
+        .col_expr(
+            wallets::Column::FrozenAt,
+            sea_query::Expr::value(Utc::now()),
+        )
+        .col_expr(
+            wallets::Column::FrozenReason,
+            sea_query::Expr::value(reason.to_string()),
+        )
+        .col_expr(
+            wallets::Column::UpdatedAt,
+            sea_query::Expr::value(Utc::now()),
+        )
+        .filter(wallets::Column::Uuid.eq(wallet_id))
+        .filter(wallets::Column::FrozenAt.is_null())
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn unfreeze_wallet<C: ConnectionTrait>(db: &C, wallet_id: &String) -> Result<(), DbErr> {
+    Wallets::update_many()
+        .col_expr(
+            wallets::Column::FrozenAt,
+            sea_query::Expr::value(Option::<chrono::DateTime<Utc>>::None),
+        )
+        .col_expr(
+            wallets::Column::FrozenReason,
+            sea_query::Expr::value(Option::<String>::None),
+        )
+        .col_expr(
+            wallets::Column::UpdatedAt,
+            sea_query::Expr::value(Utc::now()),
+        )
+        .filter(wallets::Column::Uuid.eq(wallet_id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,188 @@
+use async_trait::async_trait;
+use chrono::{Datelike, Utc};
+use rust_decimal::Decimal;
+use serde_json::Value;
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::config::EnvConfig;
+use super::helpers::validate_signature;
+
+#[derive(Error, Debug)]
+pub enum IssuerError {
+    #[error("Failed to make API request")]
+    HttpRequestError(#[from] reqwest::Error),
+
+    #[error("{0}")]
+    Rejected(String),
+
+    #[error("Invalid webhook signature")]
+    InvalidSignature,
+
+    #[error("Invalid webhook payload")]
+    InvalidPayload,
+}
+
+pub struct IssueCardRequest {
+    // Our card id, so the issuer's records can be matched back to ours
+    pub reference: String,
+    pub name_on_card: String,
+    pub email: String,
+}
+
+// The issuer keeps the full card number and CVV, we only ever hold what can be shown on a receipt
+#[derive(Debug)]
+pub struct IssuedCard {
+    pub issuer_reference: String,
+    pub brand: String,
+    pub last4: String,
+    pub exp_month: String,
+    pub exp_year: String,
+}
+
+// Card events carry the issuer's card and authorization references. Amounts are in Naira
+#[derive(Debug)]
+pub enum CardEvent {
+    // A merchant asking to charge the card. Answered in the webhook response
+    AuthorizationRequest {
+        card_reference: String,
+        authorization_reference: String,
+        amount: Decimal,
+        merchant_name: String,
+    },
+    // The merchant let the authorization go without charging it
+    AuthorizationReversed {
+        authorization_reference: String,
+    },
+    // The charge settled, possibly for a different amount e.g with a tip added
+    Cleared {
+        authorization_reference: String,
+        amount: Decimal,
+    },
+    Ignored(String),
+}
+
+#[async_trait]
+pub trait CardIssuer: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Header carrying the webhook signature
+    fn signature_header(&self) -> &'static str;
+
+    async fn issue_card(&self, request: &IssueCardRequest) -> Result<IssuedCard, IssuerError>;
+
+    async fn freeze_card(&self, issuer_reference: &str) -> Result<(), IssuerError>;
+
+    async fn unfreeze_card(&self, issuer_reference: &str) -> Result<(), IssuerError>;
+
+    // Permanent, the card can't be used again
+    async fn terminate_card(&self, issuer_reference: &str) -> Result<(), IssuerError>;
+
+    // Checks the signature and turns the payload into an event
+    fn parse_webhook(&self, signature: &str, body: &[u8]) -> Result<CardEvent, IssuerError>;
+}
+
+// Issues cards without calling out anywhere, for development and tests. Its webhooks are signed
+// with an HMAC-SHA512 of the raw body using CARD_ISSUER_SECRET, and look like
+// {"event": "authorization.request", "data": {"card_reference", "authorization_reference", "amount", "merchant_name"}}
+pub struct MockCardIssuer {
+    secret: String,
+}
+
+impl MockCardIssuer {
+    pub fn new(env: &EnvConfig) -> MockCardIssuer {
+        MockCardIssuer {
+            secret: env.card_issuer_secret.to_string(),
+        }
+    }
+}
+
+fn webhook_amount(value: &Value) -> Result<Decimal, IssuerError> {
+    let amount = match value {
+        Value::String(amount) => amount.parse().ok(),
+        Value::Number(amount) => amount.to_string().parse().ok(),
+        _ => None,
+    };
+
+    amount
+        .filter(|amount: &Decimal| amount.is_sign_positive() && !amount.is_zero())
+        .ok_or(IssuerError::InvalidPayload)
+}
+
+#[async_trait]
+impl CardIssuer for MockCardIssuer {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn signature_header(&self) -> &'static str {
+        "x-mock-issuer-signature"
+    }
+
+    async fn issue_card(&self, _request: &IssueCardRequest) -> Result<IssuedCard, IssuerError> {
+        let id = Uuid::new_v4();
+        let expires = Utc::now().date_naive();
+
+        Ok(IssuedCard {
+            issuer_reference: format!("mock_card_{}", id.simple()),
+            brand: String::from("visa"),
+            last4: format!("{:04}", id.as_u128() % 10000),
+            exp_month: format!("{:02}", expires.month()),
+            exp_year: (expires.year() + 3).to_string(),
+        })
+    }
+
+    async fn freeze_card(&self, _issuer_reference: &str) -> Result<(), IssuerError> {
+        Ok(())
+    }
+
+    async fn unfreeze_card(&self, _issuer_reference: &str) -> Result<(), IssuerError> {
+        Ok(())
+    }
+
+    async fn terminate_card(&self, _issuer_reference: &str) -> Result<(), IssuerError> {
+        Ok(())
+    }
+
+    fn parse_webhook(&self, signature: &str, body: &[u8]) -> Result<CardEvent, IssuerError> {
+        let payload = String::from_utf8_lossy(body).to_string();
+        if self.secret.is_empty() || !validate_signature(&payload, signature, &self.secret) {
+            return Err(IssuerError::InvalidSignature);
+        }
+
+        let payload: Value = serde_json::from_str(&payload).unwrap_or_default();
+        let data = &payload["data"];
+        let field = |name: &str| data[name].as_str().unwrap_or_default().to_string();
+
+        let event = match payload["event"].as_str().unwrap_or_default() {
+            "authorization.request" => CardEvent::AuthorizationRequest {
+                card_reference: field("card_reference"),
+                authorization_reference: field("authorization_reference"),
+                amount: webhook_amount(&data["amount"])?,
+                merchant_name: field("merchant_name"),
+            },
+            "authorization.reversal" => CardEvent::AuthorizationReversed {
+                authorization_reference: field("authorization_reference"),
+            },
+            "transaction.cleared" => CardEvent::Cleared {
+                authorization_reference: field("authorization_reference"),
+                amount: webhook_amount(&data["amount"])?,
+            },
+            event_type => CardEvent::Ignored(event_type.to_string()),
+        };
+
+        Ok(event)
+    }
+}
+
+// The configured issuer, None for one we don't have an integration for
+pub fn card_issuer(env: &EnvConfig) -> Option<Box<dyn CardIssuer>> {
+    card_issuer_by_name(&env.card_issuer, env)
+}
+
+pub fn card_issuer_by_name(name: &str, env: &EnvConfig) -> Option<Box<dyn CardIssuer>> {
+    match name {
+        "mock" => Some(Box::new(MockCardIssuer::new(env))),
+        _ => None,
+    }
+}
@@ -1,4 +1,6 @@
+use rust_decimal::Decimal;
 use std::env::var;
+use std::net::IpAddr;
 
 #[derive(Clone, Debug)]
 pub struct EnvConfig {
@@ -13,8 +15,46 @@ pub struct EnvConfig {
     pub smtp_user: String,
     pub smtp_key: String,
     pub from_email: String,
+    pub termii_base_url: String,
+    pub termii_api_key: String,
+    pub termii_sender_id: String,
+    pub fcm_base_url: String,
+    pub fcm_server_key: String,
+    pub notification_sink: String,
     pub paystack_base_url: String,
     pub paystack_secret: String,
+    pub paystack_dva_bank: String,
+    pub flutterwave_base_url: String,
+    pub flutterwave_secret: String,
+    pub flutterwave_secret_hash: String,
+    pub charge_providers: Vec<String>,
+    pub transfer_providers: Vec<String>,
+    pub scheduler_interval_secs: u64,
+    pub reconciliation_interval_secs: u64,
+    pub reconciliation_freeze_wallets: bool,
+    pub funding_poll_interval_secs: u64,
+    pub funding_stale_after_mins: i64,
+    pub funding_abandon_after_mins: i64,
+    pub transfer_poll_interval_secs: u64,
+    pub transfer_stale_after_mins: i64,
+    pub transfer_abandon_after_mins: i64,
+    pub outbox_poll_interval_secs: u64,
+    pub outbox_max_attempts: i32,
+    pub outbox_retry_base_secs: i64,
+    pub webhook_timeout_secs: u64,
+    pub webhook_allow_insecure_urls: bool,
+    pub merchant_fee_percent: Decimal,
+    pub merchant_fee_cap: Decimal,
+    pub invoice_reminder_interval_secs: u64,
+    pub invoice_reminder_every_hours: i64,
+    pub invoice_max_reminders: i32,
+    pub qr_code_ttl_mins: i64,
+    pub escrow_release_interval_secs: u64,
+    pub payment_request_expiry_interval_secs: u64,
+    pub escrow_auto_release_hours: i64,
+    pub card_issuer: String,
+    pub card_issuer_secret: String,
+    pub trusted_proxies: Vec<IpAddr>,
 }
 
 impl EnvConfig {
@@ -31,14 +71,146 @@ impl EnvConfig {
             smtp_user: var("SMTP_USER").unwrap_or_default(),
             smtp_key: var("SMTP_KEY").unwrap_or_default(),
             from_email: var("FROM_EMAIL").unwrap_or(String::from("support@moneytransfer.am")),
+            termii_base_url: var("TERMII_BASE_URL")
+                .unwrap_or(String::from("https://api.ng.termii.com")),
+            termii_api_key: var("TERMII_API_KEY").unwrap_or_default(),
+            termii_sender_id: var("TERMII_SENDER_ID").unwrap_or(String::from("MoneyTrf")),
+            fcm_base_url: var("FCM_BASE_URL").unwrap_or(String::from("https://fcm.googleapis.com")),
+            fcm_server_key: var("FCM_SERVER_KEY").unwrap_or_default(),
+            // "stdout" or a file path to write every notification to instead of sending it
+            notification_sink: var("NOTIFICATION_SINK").unwrap_or_default(),
             paystack_base_url: var("PAYSTACK_BASE_URL")
                 .unwrap_or(String::from("https://api.paystack.co")),
             paystack_secret: var("PAYSTACK_SECRET").expect("Missing env PAYSTACK_SECRET"),
+            // Bank slug for dedicated virtual accounts, "test-bank" on test keys
+            paystack_dva_bank: var("PAYSTACK_DVA_BANK").unwrap_or(String::from("wema-bank")),
+            flutterwave_base_url: var("FLUTTERWAVE_BASE_URL")
+                .unwrap_or(String::from("https://api.flutterwave.com/v3")),
+            flutterwave_secret: var("FLUTTERWAVE_SECRET").unwrap_or_default(),
+            flutterwave_secret_hash: var("FLUTTERWAVE_SECRET_HASH").unwrap_or_default(),
+            charge_providers: provider_list("CHARGE_PROVIDERS"),
+            transfer_providers: provider_list("TRANSFER_PROVIDERS"),
+            scheduler_interval_secs: var("SCHEDULER_INTERVAL_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(60),
+            reconciliation_interval_secs: var("RECONCILIATION_INTERVAL_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(86400),
+            reconciliation_freeze_wallets: var("RECONCILIATION_FREEZE_WALLETS")
+                .map(|freeze| freeze == "true")
+                .unwrap_or(false),
+            funding_poll_interval_secs: var("FUNDING_POLL_INTERVAL_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(300),
+            funding_stale_after_mins: var("FUNDING_STALE_AFTER_MINS")
+                .ok()
+                .and_then(|mins| mins.parse().ok())
+                .unwrap_or(15),
+            funding_abandon_after_mins: var("FUNDING_ABANDON_AFTER_MINS")
+                .ok()
+                .and_then(|mins| mins.parse().ok())
+                .unwrap_or(1440),
+            transfer_poll_interval_secs: var("TRANSFER_POLL_INTERVAL_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(300),
+            transfer_stale_after_mins: var("TRANSFER_STALE_AFTER_MINS")
+                .ok()
+                .and_then(|mins| mins.parse().ok())
+                .unwrap_or(30),
+            transfer_abandon_after_mins: var("TRANSFER_ABANDON_AFTER_MINS")
+                .ok()
+                .and_then(|mins| mins.parse().ok())
+                .unwrap_or(1440),
+            outbox_poll_interval_secs: var("OUTBOX_POLL_INTERVAL_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(10),
+            outbox_max_attempts: var("OUTBOX_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|attempts| attempts.parse().ok())
+                .unwrap_or(8),
+            outbox_retry_base_secs: var("OUTBOX_RETRY_BASE_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(30),
+            webhook_timeout_secs: var("WEBHOOK_TIMEOUT_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(10),
+            // Lets webhook subscriptions use plain http and private addresses. Local development only
+            webhook_allow_insecure_urls: var("WEBHOOK_ALLOW_INSECURE_URLS")
+                .map(|allow| allow == "true")
+                .unwrap_or(false),
+            // Fee on payments to merchants, a percentage of the amount up to the cap in Naira
+            merchant_fee_percent: var("MERCHANT_FEE_PERCENT")
+                .ok()
+                .and_then(|percent| percent.parse().ok())
+                .unwrap_or(Decimal::new(15, 1)),
+            merchant_fee_cap: var("MERCHANT_FEE_CAP")
+                .ok()
+                .and_then(|cap| cap.parse().ok())
+                .unwrap_or(Decimal::from(2000)),
+            invoice_reminder_interval_secs: var("INVOICE_REMINDER_INTERVAL_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(3600),
+            // Overdue invoices are reminded about this often, up to the max
+            invoice_reminder_every_hours: var("INVOICE_REMINDER_EVERY_HOURS")
+                .ok()
+                .and_then(|hours| hours.parse().ok())
+                .unwrap_or(72),
+            invoice_max_reminders: var("INVOICE_MAX_REMINDERS")
+                .ok()
+                .and_then(|reminders| reminders.parse().ok())
+                .unwrap_or(3),
+            // How long a one-time QR can be paid when the payee doesn't say
+            qr_code_ttl_mins: var("QR_CODE_TTL_MINS")
+                .ok()
+                .and_then(|mins| mins.parse().ok())
+                .unwrap_or(15),
+            escrow_release_interval_secs: var("ESCROW_RELEASE_INTERVAL_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(300),
+            payment_request_expiry_interval_secs: var("PAYMENT_REQUEST_EXPIRY_INTERVAL_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(60),
+            // How long a buyer has to release or dispute once the seller marks an escrow delivered
+            escrow_auto_release_hours: var("ESCROW_AUTO_RELEASE_HOURS")
+                .ok()
+                .and_then(|hours| hours.parse().ok())
+                .unwrap_or(72),
+            // Virtual cards are issued through this issuer. "mock" issues cards locally
+            card_issuer: var("CARD_ISSUER").unwrap_or(String::from("mock")),
+            // Signs the issuer's webhooks to us
+            card_issuer_secret: var("CARD_ISSUER_SECRET").unwrap_or_default(),
+            // Comma separated addresses of the load balancers in front of the app. Forwarded
+            // client addresses are only believed on requests coming from one of them
+            trusted_proxies: var("TRUSTED_PROXIES")
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|ip| ip.trim().parse().ok())
+                .collect(),
         }
     }
 
     // For instances where global app_state cannot be passed or will be incovinient to do so
     pub fn get_single_env(env_key: &str) -> String {
-        var(env_key).expect(format!("Missing env {env_key}").as_str())
+        var(env_key).unwrap_or_else(|_| panic!("Missing env {env_key}"))
     }
 }
+
+// Comma separated provider names in order of preference, e.g. "paystack,flutterwave"
+fn provider_list(env_key: &str) -> Vec<String> {
+    var(env_key)
+        .unwrap_or(String::from("paystack"))
+        .split(',')
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
@@ -1,22 +1,187 @@
-use super::config::EnvConfig;
-
-pub fn verify_account_template(first_name: &String, token: &String, env: &EnvConfig) -> String {
-    let verify_account_url = format!(
-        "{}/api/user/verify-account?token={}",
-        env.app_base_url, token
-    );
-
-    format!(
-        r#"
-        <html>
-            <body>
-                <p>Hi, {first_name}</p>
-                <p>Welcome to money transfer,</p>
-                <p>We are delighted to have you.</p>
-                <p>Please verify your email by clicking on the link below:</p>
-                <a href="{verify_account_url}">Click here to verify your account</a>
-            </body>
-        </html>
-        "#
-    )
+use std::sync::OnceLock;
+use tera::{Context, Tera};
+
+pub const DEFAULT_LOCALE: &str = "en";
+pub const SUPPORTED_LOCALES: [&str; 2] = ["en", "fr"];
+
+// Each email has a subject, an HTML body, a plain-text alternative and a short version for SMS and
+// push per locale, all compiled into the binary so a missing translation fails the build rather
+// than a delivery
+macro_rules! template_file {
+    ($dir:literal, $locale:literal, $name:literal, $ext:literal) => {
+        (
+            concat!($dir, "/", $locale, "/", $name, ".", $ext),
+            include_str!(concat!(
+                "../../templates/",
+                $dir,
+                "/",
+                $locale,
+                "/",
+                $name,
+                ".",
+                $ext
+            )),
+        )
+    };
+}
+
+macro_rules! email_locale_templates {
+    ($locale:literal [$($name:literal),+]) => {
+        [
+            template_file!("emails", $locale, "layout", "html"),
+            template_file!("emails", $locale, "layout", "txt"),
+            $(
+                template_file!("emails", $locale, $name, "subject"),
+                template_file!("emails", $locale, $name, "html"),
+                template_file!("emails", $locale, $name, "txt"),
+                template_file!("emails", $locale, $name, "sms"),
+            )+
+        ]
+    };
+}
+
+// Invoices go to a merchant's customers, who may not have an account, so their emails have their
+// own layout and no SMS version. The invoice itself is a document in HTML and plain text
+macro_rules! invoice_locale_templates {
+    ($locale:literal [$($name:literal),+]) => {
+        [
+            template_file!("invoices", $locale, "layout", "html"),
+            template_file!("invoices", $locale, "layout", "txt"),
+            template_file!("invoices", $locale, "document", "html"),
+            template_file!("invoices", $locale, "document", "txt"),
+            $(
+                template_file!("invoices", $locale, $name, "subject"),
+                template_file!("invoices", $locale, $name, "html"),
+                template_file!("invoices", $locale, $name, "txt"),
+            )+
+        ]
+    };
+}
+
+macro_rules! locale_templates {
+    ($templates:ident [$($locale:literal),+] $names:tt) => {{
+        let mut templates = Vec::new();
+        $(templates.extend($templates!($locale $names));)+
+        templates
+    }};
+}
+
+static TEMPLATES: OnceLock<Tera> = OnceLock::new();
+
+fn templates() -> &'static Tera {
+    TEMPLATES.get_or_init(|| {
+        let mut files = locale_templates!(
+            email_locale_templates
+            ["en", "fr"]
+            [
+                "verify_account",
+                "password_reset",
+                "transfer_sent",
+                "transfer_received",
+                "wallet_funded",
+                "pin_changed",
+                "new_login",
+                "scheduled_transfer_failed",
+                "dispute_update",
+                "escrow_update"
+            ]
+        );
+        files.extend(locale_templates!(
+            invoice_locale_templates
+            ["en", "fr"]
+            ["issued", "reminder", "payment_received"]
+        ));
+
+        let mut tera = Tera::default();
+        tera.add_raw_templates(files)
+            .expect("Failed to parse email templates");
+        tera
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+// What fits in an SMS or a push notification, the title is the email's subject
+#[derive(Debug, Clone)]
+pub struct RenderedMessage {
+    pub title: String,
+    pub body: String,
+}
+
+pub fn is_supported_locale(locale: &str) -> bool {
+    SUPPORTED_LOCALES.contains(&locale)
+}
+
+// Users whose locale we don't have templates for get the default one
+fn render(
+    dir: &str,
+    name: &str,
+    locale: &str,
+    ext: &str,
+    context: &Context,
+) -> Result<String, tera::Error> {
+    let locale = if is_supported_locale(locale) {
+        locale
+    } else {
+        DEFAULT_LOCALE
+    };
+
+    templates().render(&format!("{}/{}/{}.{}", dir, locale, name, ext), context)
+}
+
+pub fn render_email(
+    name: &str,
+    locale: &str,
+    context: &Context,
+) -> Result<RenderedEmail, tera::Error> {
+    Ok(RenderedEmail {
+        subject: render("emails", name, locale, "subject", context)?
+            .trim()
+            .to_string(),
+        html: render("emails", name, locale, "html", context)?,
+        text: render("emails", name, locale, "txt", context)?,
+    })
+}
+
+pub fn render_message(
+    name: &str,
+    locale: &str,
+    context: &Context,
+) -> Result<RenderedMessage, tera::Error> {
+    Ok(RenderedMessage {
+        title: render("emails", name, locale, "subject", context)?
+            .trim()
+            .to_string(),
+        body: render("emails", name, locale, "sms", context)?
+            .trim()
+            .to_string(),
+    })
+}
+
+pub fn render_invoice_email(
+    name: &str,
+    locale: &str,
+    context: &Context,
+) -> Result<RenderedEmail, tera::Error> {
+    Ok(RenderedEmail {
+        subject: render("invoices", name, locale, "subject", context)?
+            .trim()
+            .to_string(),
+        html: render("invoices", name, locale, "html", context)?,
+        text: render("invoices", name, locale, "txt", context)?,
+    })
+}
+
+// The invoice as a page, "html", or as plain text, "txt", to lay out in a PDF
+pub fn render_invoice_document(
+    locale: &str,
+    ext: &str,
+    context: &Context,
+) -> Result<String, tera::Error> {
+    render("invoices", "document", locale, ext, context)
 }
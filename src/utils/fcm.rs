@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+use reqwest::{header, Client};
+use serde_json::{json, Value};
+
+use super::config::EnvConfig;
+use super::email_template::RenderedMessage;
+use super::notification_channel::{ChannelError, NotificationChannel};
+
+// Mobile push through Firebase Cloud Messaging's HTTP API
+pub struct FcmPush {
+    base_url: String,
+    server_key: String,
+}
+
+impl FcmPush {
+    pub fn new(env: &EnvConfig) -> FcmPush {
+        FcmPush {
+            base_url: env.fcm_base_url.to_string(),
+            server_key: env.fcm_server_key.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for FcmPush {
+    fn name(&self) -> &'static str {
+        "fcm"
+    }
+
+    async fn send(&self, to: &str, message: &RenderedMessage) -> Result<(), ChannelError> {
+        let url = format!("{}/fcm/send", self.base_url);
+
+        let response = Client::new()
+            .post(&url)
+            .header(header::AUTHORIZATION, format!("key={}", self.server_key))
+            .json(&json!({
+                "to": to,
+                "notification": { "title": message.title, "body": message.body }
+            }))
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status.is_server_error() {
+            return Err(ChannelError::Unavailable(status.to_string()));
+        }
+        if !status.is_success() {
+            return Err(ChannelError::Rejected(format!(
+                "FCM rejected the request with {}",
+                status
+            )));
+        }
+
+        // Failures for a token come back in a 200, one result per token sent to
+        let body = response.json::<Value>().await?;
+        match body["results"][0]["error"].as_str() {
+            None => Ok(()),
+            Some("NotRegistered") | Some("InvalidRegistration") | Some("MismatchSenderId") => {
+                Err(ChannelError::InvalidRecipient(to.to_string()))
+            }
+            Some("Unavailable") | Some("InternalServerError") => Err(ChannelError::Unavailable(
+                String::from("FCM is unavailable"),
+            )),
+            Some(err) => Err(ChannelError::Rejected(err.to_string())),
+        }
+    }
+}
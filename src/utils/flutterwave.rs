@@ -0,0 +1,306 @@
+use async_trait::async_trait;
+use reqwest::{header, Client, RequestBuilder};
+use rust_decimal::{
+    prelude::{FromPrimitive, ToPrimitive},
+    Decimal,
+};
+use serde_json::{json, Value};
+
+use super::config::EnvConfig;
+use super::payment_provider::{
+    ChargeInitialization, ChargeRequest, ChargeStatus, ChargeVerification, PaymentProvider,
+    ProviderError, Refund, ResolvedAccount, TransferInitiation, TransferRequest, TransferStatus,
+    WebhookEvent,
+};
+
+// Flutterwave works in Naira and takes amounts as plain numbers
+fn to_amount(amount: Decimal) -> f64 {
+    amount.to_f64().unwrap_or_default()
+}
+
+fn to_decimal(amount: &Value) -> Decimal {
+    amount
+        .as_f64()
+        .and_then(Decimal::from_f64)
+        .unwrap_or_default()
+        .round_dp(2)
+}
+
+fn rejection(response: &Value, fallback: &str) -> ProviderError {
+    let message = response["message"].as_str().unwrap_or(fallback).to_string();
+    ProviderError::Rejected(message)
+}
+
+fn is_success(response: &Value) -> bool {
+    response["status"].as_str() == Some("success")
+}
+
+pub struct Flutterwave {
+    base_url: String,
+    secret: String,
+    secret_hash: String,
+    redirect_url: String,
+}
+
+impl Flutterwave {
+    pub fn new(env: &EnvConfig) -> Flutterwave {
+        Flutterwave {
+            base_url: env.flutterwave_base_url.to_string(),
+            secret: env.flutterwave_secret.to_string(),
+            secret_hash: env.flutterwave_secret_hash.to_string(),
+            redirect_url: env.app_base_url.to_string(),
+        }
+    }
+
+    fn authorized(&self, request: RequestBuilder) -> RequestBuilder {
+        request
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("Bearer {}", self.secret))
+    }
+
+    async fn find_charge(&self, reference: &str) -> Result<Value, ProviderError> {
+        let url = format!(
+            "{}/transactions/verify_by_reference?tx_ref={}",
+            self.base_url, reference
+        );
+
+        let response = self
+            .authorized(Client::new().get(&url))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for Flutterwave {
+    fn name(&self) -> &'static str {
+        "flutterwave"
+    }
+
+    fn signature_header(&self) -> &'static str {
+        "verif-hash"
+    }
+
+    async fn initialize_charge(
+        &self,
+        charge: &ChargeRequest,
+    ) -> Result<ChargeInitialization, ProviderError> {
+        let url = format!("{}/payments", self.base_url);
+
+        let response = self
+            .authorized(Client::new().post(&url))
+            .json(&json!({
+                "tx_ref": charge.reference,
+                "amount": to_amount(charge.amount),
+                "currency": "NGN",
+                "redirect_url": self.redirect_url,
+                "customer": { "email": charge.email },
+                "meta": { "user_id": charge.user_id }
+            }))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+
+        match response["data"]["link"].as_str() {
+            Some(link) if is_success(&response) => Ok(ChargeInitialization {
+                provider: self.name().to_string(),
+                reference: charge.reference.to_string(),
+                authorization_url: link.to_string(),
+                access_code: None,
+            }),
+            _ => Err(rejection(
+                &response,
+                "Flutterwave could not initialize the charge",
+            )),
+        }
+    }
+
+    async fn verify_charge(&self, reference: &str) -> Result<ChargeVerification, ProviderError> {
+        let response = self.find_charge(reference).await?;
+
+        let data = &response["data"];
+        let status = match data["status"].as_str().unwrap_or_default() {
+            "successful" => ChargeStatus::Successful,
+            "failed" | "cancelled" => ChargeStatus::Failed,
+            _ => ChargeStatus::Pending,
+        };
+
+        Ok(ChargeVerification {
+            reference: reference.to_string(),
+            status,
+            amount: to_decimal(&data["amount"]),
+            fees: to_decimal(&data["app_fee"]),
+            user_id: data["meta"]["user_id"].as_str().map(String::from),
+            customer_email: data["customer"]["email"].as_str().map(String::from),
+            card: None,
+            virtual_account_customer: None,
+            raw: response.clone(),
+        })
+    }
+
+    async fn resolve_account(
+        &self,
+        account_number: &str,
+        bank_code: &str,
+    ) -> Result<ResolvedAccount, ProviderError> {
+        let url = format!("{}/accounts/resolve", self.base_url);
+
+        let response = self
+            .authorized(Client::new().post(&url))
+            .json(&json!({ "account_number": account_number, "account_bank": bank_code }))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+
+        let data = &response["data"];
+        match (
+            data["account_number"].as_str(),
+            data["account_name"].as_str(),
+        ) {
+            (Some(account_number), Some(account_name)) if is_success(&response) => {
+                Ok(ResolvedAccount {
+                    account_number: account_number.to_string(),
+                    account_name: account_name.to_string(),
+                })
+            }
+            _ => Err(rejection(&response, "Could not resolve account")),
+        }
+    }
+
+    async fn transfer(
+        &self,
+        transfer: &TransferRequest,
+    ) -> Result<TransferInitiation, ProviderError> {
+        let url = format!("{}/transfers", self.base_url);
+
+        let response = self
+            .authorized(Client::new().post(&url))
+            .json(&json!({
+                "account_bank": transfer.bank_code,
+                "account_number": transfer.account_number,
+                "amount": to_amount(transfer.amount),
+                "narration": transfer.narration,
+                "currency": "NGN",
+                "debit_currency": "NGN",
+                "reference": transfer.reference
+            }))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+
+        if !is_success(&response) {
+            return Err(rejection(&response, "Flutterwave rejected the transfer"));
+        }
+
+        Ok(TransferInitiation {
+            provider: self.name().to_string(),
+            reference: transfer.reference.to_string(),
+        })
+    }
+
+    // Transfers are listed by our reference, there is at most one for each
+    async fn transfer_status(&self, reference: &str) -> Result<TransferStatus, ProviderError> {
+        let url = format!("{}/transfers?reference={}", self.base_url, reference);
+
+        let response = self
+            .authorized(Client::new().get(&url))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+
+        if !is_success(&response) {
+            return Err(rejection(&response, "Could not fetch Flutterwave transfer"));
+        }
+
+        let transfer = match response["data"].as_array().and_then(|data| data.first()) {
+            Some(transfer) => transfer,
+            None => return Ok(TransferStatus::NotFound),
+        };
+
+        let reason = transfer["complete_message"]
+            .as_str()
+            .unwrap_or("Transfer failed")
+            .to_string();
+        let status = match transfer["status"].as_str() {
+            Some("SUCCESSFUL") => TransferStatus::Successful,
+            Some("FAILED") => TransferStatus::Failed(reason),
+            Some("REVERSED") => TransferStatus::Reversed(reason),
+            _ => TransferStatus::Pending,
+        };
+
+        Ok(status)
+    }
+
+    // Refunds are made against Flutterwave's own transaction id, looked up from our reference
+    async fn refund(&self, reference: &str, amount: Decimal) -> Result<Refund, ProviderError> {
+        let charge = self.find_charge(reference).await?;
+        let transaction_id = match charge["data"]["id"].as_i64() {
+            Some(transaction_id) if is_success(&charge) => transaction_id,
+            _ => return Err(rejection(&charge, "Flutterwave charge not found")),
+        };
+
+        let url = format!("{}/transactions/{}/refund", self.base_url, transaction_id);
+        let response = self
+            .authorized(Client::new().post(&url))
+            .json(&json!({ "amount": to_amount(amount) }))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+
+        if !is_success(&response) {
+            return Err(rejection(&response, "Flutterwave refund was not accepted"));
+        }
+
+        let reference = match &response["data"]["id"] {
+            Value::Null => None,
+            refund_id => Some(refund_id.to_string()),
+        };
+
+        Ok(Refund { reference })
+    }
+
+    // Flutterwave sends the secret hash configured on the dashboard as is
+    fn parse_webhook(&self, signature: &str, body: &[u8]) -> Result<WebhookEvent, ProviderError> {
+        if self.secret_hash.is_empty() || signature != self.secret_hash {
+            return Err(ProviderError::InvalidSignature);
+        }
+
+        let payload: Value = serde_json::from_slice(body).unwrap_or_default();
+        let event_type = payload["event"].as_str().unwrap_or_default();
+        let data = &payload["data"];
+
+        let event = match event_type {
+            "charge.completed" if data["status"].as_str() == Some("successful") => {
+                WebhookEvent::ChargeSuccessful {
+                    reference: data["tx_ref"].as_str().unwrap_or_default().to_string(),
+                }
+            }
+            "transfer.completed" => {
+                let reference = data["reference"].as_str().unwrap_or_default().to_string();
+                match data["status"].as_str() {
+                    Some("SUCCESSFUL") => WebhookEvent::TransferSuccessful { reference },
+                    Some("FAILED") => WebhookEvent::TransferFailed {
+                        reference,
+                        reason: data["complete_message"]
+                            .as_str()
+                            .unwrap_or("Transfer failed")
+                            .to_string(),
+                    },
+                    _ => WebhookEvent::Ignored(event_type.to_string()),
+                }
+            }
+            event_type => WebhookEvent::Ignored(event_type.to_string()),
+        };
+
+        Ok(event)
+    }
+}
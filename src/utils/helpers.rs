@@ -1,8 +1,16 @@
+use actix_web::HttpRequest;
 use argonautica::Verifier;
 use hex;
-use ring::hmac;
+use ring::{
+    digest, hmac,
+    rand::{generate, SystemRandom},
+};
+use serde::Serializer;
+use std::net::IpAddr;
 use tracing::error;
 
+use crate::entities::users;
+
 pub fn validate_password(
     hashed_password: &String,
     compare_password: &String,
@@ -20,10 +28,94 @@ pub fn validate_password(
         })
 }
 
-pub fn validate_signature(payload: &String, signature: &String, secret: &String) -> bool {
+pub fn validate_signature(payload: &str, signature: &str, secret: &str) -> bool {
+    sign_payload(payload, secret) == signature
+}
+
+// Hex encoded HMAC-SHA512 of the payload, the scheme providers sign their webhooks to us with and
+// we sign ours to integrators with
+pub fn sign_payload(payload: &str, secret: &str) -> String {
     let key = hmac::Key::new(hmac::HMAC_SHA512, secret.as_bytes());
-    let calc_signature = hmac::sign(&key, payload.as_bytes());
-    let hash = hex::encode(calc_signature.as_ref());
+    let signature = hmac::sign(&key, payload.as_bytes());
+
+    hex::encode(signature.as_ref())
+}
+
+// A random secret such as "whsec_" followed by 64 hex characters. None if the system RNG fails
+pub fn generate_token(prefix: &str) -> Option<String> {
+    let bytes: [u8; 32] = generate(&SystemRandom::new()).ok()?.expose();
+
+    Some(format!("{}{}", prefix, hex::encode(bytes)))
+}
+
+// Hex encoded SHA-256, for secrets random enough to be looked up by their hash
+pub fn hash_token(token: &str) -> String {
+    hex::encode(digest::digest(&digest::SHA256, token.as_bytes()).as_ref())
+}
+
+// Validates the withdrawal PIN of a user, returning the error message to send to the client on failure
+pub fn validate_user_pin(
+    user: &users::Model,
+    pin: &String,
+    hash_key: &String,
+) -> Result<(), &'static str> {
+    let hashed_pin = match &user.withdrawal_pin {
+        Some(pin) => pin,
+        None => return Err("Please set your pin before taking this action"),
+    };
+
+    if !validate_password(hashed_pin, pin, hash_key) {
+        return Err("Incorrect PIN");
+    }
+
+    Ok(())
+}
+
+// Phone numbers are kept in international format, e.g. +2348012345678. Spaces and dashes are
+// dropped, anything else that isn't a plus and 8 to 15 digits is rejected
+pub fn normalize_phone_number(phone_number: &str) -> Option<String> {
+    let phone_number: String = phone_number
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect();
+    let digits = phone_number.strip_prefix('+')?;
+
+    if (8..=15).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_digit()) {
+        Some(phone_number)
+    } else {
+        None
+    }
+}
+
+// The users.is_verified and wallets.default flags were read as integers before the entities
+// used bool, responses keep returning them as 1/0 for existing clients
+pub fn serialize_flag<S: Serializer>(flag: &bool, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_i8(i8::from(*flag))
+}
+
+// The peer address, unless the peer is one of our proxies. Then X-Forwarded-For is read from the
+// right, each proxy appending the address it got the request from, and the first address that
+// isn't another of our proxies is the client. Anything further left was sent by the client
+pub fn client_ip(req: &HttpRequest, trusted_proxies: &[IpAddr]) -> Option<IpAddr> {
+    let peer = req.peer_addr()?.ip();
+    if !trusted_proxies.contains(&peer) {
+        return Some(peer);
+    }
+
+    let forwarded: Vec<&str> = req
+        .headers()
+        .get_all("x-forwarded-for")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect();
+
+    for address in forwarded.into_iter().rev() {
+        match address.trim().parse::<IpAddr>() {
+            Ok(address) if trusted_proxies.contains(&address) => continue,
+            Ok(address) => return Some(address),
+            Err(_) => return None,
+        }
+    }
 
-    hash == signature.to_string()
+    Some(peer)
 }
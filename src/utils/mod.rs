@@ -1,5 +1,13 @@
+pub mod card_issuer;
 pub mod config;
 pub mod email_template;
+pub mod fcm;
+pub mod flutterwave;
 pub mod helpers;
+pub mod notification_channel;
+pub mod payment_provider;
 pub mod paystack;
+pub mod pdf;
+pub mod qr;
 pub mod send_email;
+pub mod termii;
@@ -0,0 +1,106 @@
+use async_trait::async_trait;
+use serde_json::json;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::Mutex;
+use thiserror::Error;
+
+use super::email_template::RenderedMessage;
+use super::send_email::{EmailError, Mailer, SendEmail};
+
+#[derive(Error, Debug)]
+pub enum ChannelError {
+    #[error("Failed to make API request")]
+    HttpRequestError(#[from] reqwest::Error),
+
+    // A phone number the provider won't take or a device token that is no longer registered
+    #[error("Invalid recipient {0}")]
+    InvalidRecipient(String),
+
+    #[error("{0}")]
+    Rejected(String),
+
+    #[error("Provider unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("Failed to write notification: {0}")]
+    SinkError(#[from] std::io::Error),
+}
+
+impl ChannelError {
+    // Sending the same message again won't fix these
+    pub fn is_permanent(&self) -> bool {
+        matches!(
+            self,
+            ChannelError::InvalidRecipient(_) | ChannelError::Rejected(_)
+        )
+    }
+}
+
+// A way of reaching users other than email, e.g. SMS or mobile push
+#[async_trait]
+pub trait NotificationChannel: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // `to` is a phone number for SMS and a device token for push
+    async fn send(&self, to: &str, message: &RenderedMessage) -> Result<(), ChannelError>;
+}
+
+// Writes notifications as JSON lines to stdout or a file instead of sending them, for development
+// and tests. Stands in for SMTP as well, so every channel can be tried without credentials
+pub struct NotificationSink {
+    channel: &'static str,
+    path: Option<String>,
+    lock: Mutex<()>,
+}
+
+impl NotificationSink {
+    // "stdout", or the path of a file to append to
+    pub fn new(channel: &'static str, target: &str) -> NotificationSink {
+        let path = match target {
+            "stdout" => None,
+            path => Some(path.to_string()),
+        };
+
+        NotificationSink {
+            channel,
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn write(&self, to: &str, title: &str, body: &str) -> Result<(), std::io::Error> {
+        let line = json!({ "channel": self.channel, "to": to, "title": title, "body": body });
+        let _guard = self.lock.lock().unwrap_or_else(|err| err.into_inner());
+
+        match &self.path {
+            Some(path) => {
+                let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+                writeln!(file, "{}", line)
+            }
+            None => writeln!(std::io::stdout(), "{}", line),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for NotificationSink {
+    fn name(&self) -> &'static str {
+        self.channel
+    }
+
+    async fn send(&self, to: &str, message: &RenderedMessage) -> Result<(), ChannelError> {
+        self.write(to, &message.title, &message.body)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Mailer for NotificationSink {
+    async fn send(&self, email: &SendEmail) -> Result<(), EmailError> {
+        let body = email.text.as_ref().unwrap_or(&email.template);
+
+        self.write(&email.to, &email.subject, body)
+            .map_err(|err| EmailError::TransportError(err.to_string()))
+    }
+}
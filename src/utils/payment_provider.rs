@@ -0,0 +1,273 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use serde_json::Value;
+use thiserror::Error;
+use tracing::warn;
+
+use super::config::EnvConfig;
+use super::flutterwave::Flutterwave;
+use super::paystack::Paystack;
+
+#[derive(Error, Debug)]
+pub enum ProviderError {
+    #[error("Failed to make API request")]
+    HttpRequestError(#[from] reqwest::Error),
+
+    #[error("{0}")]
+    Rejected(String),
+
+    #[error("Invalid webhook signature")]
+    InvalidSignature,
+
+    #[error("No payment provider is configured for this operation")]
+    NoProviderConfigured,
+}
+
+impl ProviderError {
+    // Whether the next provider should be tried. Explicit rejections are not retried elsewhere
+    fn should_failover(&self) -> bool {
+        matches!(self, ProviderError::HttpRequestError(_))
+    }
+
+    // True when the request certainly did not move money, so anything debited for it can be
+    // given back. A timeout may still have reached the provider and is left for the webhook
+    pub fn is_definite_failure(&self) -> bool {
+        match self {
+            ProviderError::HttpRequestError(err) => err.is_connect(),
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProviderOperation {
+    Charge,
+    Transfer,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChargeStatus {
+    Successful,
+    Pending,
+    Failed,
+}
+
+pub struct ChargeRequest {
+    pub reference: String,
+    pub email: String,
+    pub user_id: String,
+    pub amount: Decimal,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ChargeInitialization {
+    pub provider: String,
+    pub reference: String,
+    pub authorization_url: String,
+    pub access_code: Option<String>,
+}
+
+// Amounts are in Naira whatever unit the provider works in
+#[derive(Debug)]
+pub struct ChargeVerification {
+    pub reference: String,
+    pub status: ChargeStatus,
+    pub amount: Decimal,
+    pub fees: Decimal,
+    pub user_id: Option<String>,
+    pub customer_email: Option<String>,
+    // Set when the card can be charged again without a checkout
+    pub card: Option<CardAuthorization>,
+    // The provider's customer when the charge was a transfer into their virtual account
+    pub virtual_account_customer: Option<String>,
+    pub raw: Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct CardAuthorization {
+    pub authorization_code: String,
+    // Stays the same for a card across charges, so a card is only saved once
+    pub signature: String,
+    pub brand: Option<String>,
+    pub bank: Option<String>,
+    pub last4: String,
+    pub exp_month: String,
+    pub exp_year: String,
+}
+
+#[derive(Debug)]
+pub struct ResolvedAccount {
+    pub account_number: String,
+    pub account_name: String,
+}
+
+pub struct TransferRequest {
+    pub reference: String,
+    pub amount: Decimal,
+    pub account_number: String,
+    pub bank_code: String,
+    pub account_name: String,
+    pub narration: String,
+}
+
+#[derive(Debug)]
+pub struct TransferInitiation {
+    pub provider: String,
+    pub reference: String,
+}
+
+// Where a transfer stands with the provider that was sent it
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransferStatus {
+    Successful,
+    Pending,
+    Failed(String),
+    // Paid out and then returned by the receiving bank
+    Reversed(String),
+    // The provider has no transfer with the reference, so it never arrived
+    NotFound,
+}
+
+#[derive(Debug)]
+pub struct Refund {
+    pub reference: Option<String>,
+}
+
+// Webhook events are carried by our own references so they can be matched back to a transaction
+#[derive(Debug)]
+pub enum WebhookEvent {
+    ChargeSuccessful { reference: String },
+    TransferSuccessful { reference: String },
+    TransferFailed { reference: String, reason: String },
+    TransferReversed { reference: String, reason: String },
+    Ignored(String),
+}
+
+#[async_trait]
+pub trait PaymentProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Header carrying the webhook signature
+    fn signature_header(&self) -> &'static str;
+
+    async fn initialize_charge(
+        &self,
+        charge: &ChargeRequest,
+    ) -> Result<ChargeInitialization, ProviderError>;
+
+    async fn verify_charge(&self, reference: &str) -> Result<ChargeVerification, ProviderError>;
+
+    async fn resolve_account(
+        &self,
+        account_number: &str,
+        bank_code: &str,
+    ) -> Result<ResolvedAccount, ProviderError>;
+
+    async fn transfer(
+        &self,
+        transfer: &TransferRequest,
+    ) -> Result<TransferInitiation, ProviderError>;
+
+    async fn transfer_status(&self, reference: &str) -> Result<TransferStatus, ProviderError>;
+
+    // Amount may be less than the original charge for a partial refund
+    async fn refund(&self, reference: &str, amount: Decimal) -> Result<Refund, ProviderError>;
+
+    // Checks the signature and turns the payload into an event
+    fn parse_webhook(&self, signature: &str, body: &[u8]) -> Result<WebhookEvent, ProviderError>;
+}
+
+// Providers without credentials are treated as not configured
+pub fn provider_by_name(name: &str, env: &EnvConfig) -> Option<Box<dyn PaymentProvider>> {
+    match name {
+        "paystack" if !env.paystack_secret.is_empty() => Some(Box::new(Paystack::new(env))),
+        "flutterwave" if !env.flutterwave_secret.is_empty() => {
+            Some(Box::new(Flutterwave::new(env)))
+        }
+        _ => None,
+    }
+}
+
+// Providers for an operation, in the configured order of preference
+pub fn providers_for(
+    operation: ProviderOperation,
+    env: &EnvConfig,
+) -> Vec<Box<dyn PaymentProvider>> {
+    let names = match operation {
+        ProviderOperation::Charge => &env.charge_providers,
+        ProviderOperation::Transfer => &env.transfer_providers,
+    };
+
+    names
+        .iter()
+        .filter_map(|name| provider_by_name(name, env))
+        .collect()
+}
+
+pub async fn initialize_charge(
+    charge: &ChargeRequest,
+    env: &EnvConfig,
+) -> Result<ChargeInitialization, ProviderError> {
+    let mut last_error = ProviderError::NoProviderConfigured;
+
+    for provider in providers_for(ProviderOperation::Charge, env) {
+        match provider.initialize_charge(charge).await {
+            Ok(initialization) => return Ok(initialization),
+            Err(err) if err.should_failover() => {
+                warn!("{} failed to initialize charge: {}", provider.name(), err);
+                last_error = err;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    Err(last_error)
+}
+
+pub async fn resolve_account(
+    account_number: &str,
+    bank_code: &str,
+    env: &EnvConfig,
+) -> Result<ResolvedAccount, ProviderError> {
+    let mut last_error = ProviderError::NoProviderConfigured;
+
+    for provider in providers_for(ProviderOperation::Transfer, env) {
+        match provider.resolve_account(account_number, bank_code).await {
+            Ok(account) => return Ok(account),
+            Err(err) if err.should_failover() => {
+                warn!("{} failed to resolve account: {}", provider.name(), err);
+                last_error = err;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    Err(last_error)
+}
+
+// Only fails over when the provider could not be reached at all, so a transfer that may have
+// gone through is never sent a second time
+pub async fn transfer(
+    transfer: &TransferRequest,
+    env: &EnvConfig,
+) -> Result<TransferInitiation, ProviderError> {
+    let mut last_error = ProviderError::NoProviderConfigured;
+
+    for provider in providers_for(ProviderOperation::Transfer, env) {
+        match provider.transfer(transfer).await {
+            Ok(initiation) => return Ok(initiation),
+            Err(err) if err.should_failover() && err.is_definite_failure() => {
+                warn!(
+                    "{} could not be reached for transfer: {}",
+                    provider.name(),
+                    err
+                );
+                last_error = err;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    Err(last_error)
+}
@@ -1,8 +1,17 @@
-use reqwest::{header, Client, Error};
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use reqwest::{header, Client, RequestBuilder};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
 use super::config::EnvConfig;
+use super::helpers::validate_signature;
+use super::payment_provider::{
+    CardAuthorization, ChargeInitialization, ChargeRequest, ChargeStatus, ChargeVerification,
+    PaymentProvider, ProviderError, Refund, ResolvedAccount, TransferInitiation, TransferRequest,
+    TransferStatus, WebhookEvent,
+};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct InitiateFundingResponse {
@@ -18,48 +27,573 @@ pub struct InitiateFundingResponseData {
     pub reference: String,
 }
 
-pub async fn initiate_user_funding(
-    email: &String,
-    user_id: &String,
-    amount: u64,
-    env: &EnvConfig,
-) -> Result<InitiateFundingResponse, Error> {
-    let url = format!("{}/transaction/initialize", env.paystack_base_url);
-
-    let client = Client::new();
-    let response = client
-        .post(&url)
-        .json(&json!({
-            "email": email,
-            "amount": amount * 100,
-            "metadata": { "user_id": user_id, "tokenized_charge": "false" }
-        }))
-        .header(header::CONTENT_TYPE, "application/json")
-        .header(
-            header::AUTHORIZATION,
-            format!("Bearer {}", env.paystack_secret),
-        )
-        .send()
-        .await?;
-
-    let response_body = response.json::<InitiateFundingResponse>().await?;
-    Ok(response_body)
-}
-
-pub async fn verify_transaction(reference: &String, env: &EnvConfig) -> Result<Value, Error> {
-    let url = format!("{}/transaction/verify/{}", env.paystack_base_url, reference);
-
-    let client = Client::new();
-    let response = client
-        .get(&url)
-        .header(header::CONTENT_TYPE, "application/json")
-        .header(
-            header::AUTHORIZATION,
-            format!("Bearer {}", env.paystack_secret),
-        )
-        .send()
-        .await?;
-
-    let response_body = response.json::<Value>().await?;
-    Ok(response_body)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResolveAccountResponse {
+    pub status: bool,
+    pub message: String,
+    pub data: Option<ResolveAccountResponseData>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResolveAccountResponseData {
+    pub account_number: String,
+    pub account_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TransferRecipientResponse {
+    pub status: bool,
+    pub message: String,
+    pub data: Option<TransferRecipientResponseData>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TransferRecipientResponseData {
+    pub recipient_code: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InitiateTransferResponse {
+    pub status: bool,
+    pub message: String,
+    pub data: Option<InitiateTransferResponseData>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InitiateTransferResponseData {
+    pub reference: String,
+    pub transfer_code: String,
+    pub status: String,
+}
+
+// A bank account Paystack assigned to one of our customers
+#[derive(Debug, Clone)]
+pub struct DedicatedAccount {
+    pub id: Option<String>,
+    pub account_number: String,
+    pub account_name: String,
+    pub bank_name: String,
+}
+
+// A charge or transfer as it appears on Paystack's listings, amounts in Naira
+#[derive(Debug, Clone)]
+pub struct SettlementRecord {
+    pub reference: String,
+    pub amount: Decimal,
+    pub fees: Decimal,
+    pub status: String,
+}
+
+impl SettlementRecord {
+    fn from_charge(data: &Value) -> SettlementRecord {
+        SettlementRecord {
+            reference: data["reference"].as_str().unwrap_or_default().to_string(),
+            amount: from_kobo(&data["amount"]),
+            fees: from_kobo(&data["fees"]),
+            status: data["status"].as_str().unwrap_or_default().to_string(),
+        }
+    }
+
+    fn from_transfer(data: &Value) -> SettlementRecord {
+        SettlementRecord {
+            reference: data["reference"].as_str().unwrap_or_default().to_string(),
+            amount: from_kobo(&data["amount"]),
+            fees: from_kobo(&data["fee_charged"]),
+            status: data["status"].as_str().unwrap_or_default().to_string(),
+        }
+    }
+}
+
+// Listings are fetched this many records at a time, the most Paystack allows per page
+const LISTING_PAGE_SIZE: u32 = 100;
+
+// Paystack works in kobo
+fn to_kobo(amount: Decimal) -> u64 {
+    (amount * Decimal::from(100)).to_u64().unwrap_or_default()
+}
+
+fn from_kobo(amount: &Value) -> Decimal {
+    Decimal::from(amount.as_i64().unwrap_or_default()) / Decimal::from(100)
+}
+
+// Verify and charge_authorization respond with the same transaction object
+fn charge_verification(reference: &str, response: Value) -> ChargeVerification {
+    let data = &response["data"];
+    let status = match data["status"].as_str().unwrap_or_default() {
+        "success" => ChargeStatus::Successful,
+        "failed" | "abandoned" | "reversed" => ChargeStatus::Failed,
+        _ => ChargeStatus::Pending,
+    };
+
+    ChargeVerification {
+        reference: reference.to_string(),
+        status,
+        amount: from_kobo(&data["amount"]),
+        fees: from_kobo(&data["fees"]),
+        user_id: data["metadata"]["user_id"].as_str().map(String::from),
+        customer_email: data["customer"]["email"].as_str().map(String::from),
+        card: reusable_card(&data["authorization"]),
+        virtual_account_customer: match data["channel"].as_str() {
+            Some("dedicated_nuban") => data["customer"]["customer_code"].as_str().map(String::from),
+            _ => None,
+        },
+        raw: response.clone(),
+    }
+}
+
+// Only card authorizations Paystack marks reusable can be charged again
+fn reusable_card(authorization: &Value) -> Option<CardAuthorization> {
+    if !authorization["reusable"].as_bool().unwrap_or_default()
+        || authorization["channel"].as_str() != Some("card")
+    {
+        return None;
+    }
+
+    let field = |key: &str| authorization[key].as_str().map(String::from);
+    Some(CardAuthorization {
+        authorization_code: field("authorization_code")?,
+        signature: field("signature")?,
+        brand: field("card_type"),
+        bank: field("bank"),
+        last4: field("last4")?,
+        exp_month: field("exp_month")?,
+        exp_year: field("exp_year")?,
+    })
+}
+
+pub struct Paystack {
+    base_url: String,
+    secret: String,
+    dedicated_account_bank: String,
+}
+
+impl Paystack {
+    pub fn new(env: &EnvConfig) -> Paystack {
+        Paystack {
+            base_url: env.paystack_base_url.to_string(),
+            secret: env.paystack_secret.to_string(),
+            dedicated_account_bank: env.paystack_dva_bank.to_string(),
+        }
+    }
+
+    fn authorized(&self, request: RequestBuilder) -> RequestBuilder {
+        request
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("Bearer {}", self.secret))
+    }
+
+    // Every charge made within the period, whatever its status
+    pub async fn list_charges(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<SettlementRecord>, ProviderError> {
+        let charges = self.list_all("transaction", from, to).await?;
+        Ok(charges.iter().map(SettlementRecord::from_charge).collect())
+    }
+
+    pub async fn list_transfers(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<SettlementRecord>, ProviderError> {
+        let transfers = self.list_all("transfer", from, to).await?;
+        Ok(transfers
+            .iter()
+            .map(SettlementRecord::from_transfer)
+            .collect())
+    }
+
+    // None when Paystack has no transfer with the reference
+    pub async fn verify_transfer(
+        &self,
+        reference: &str,
+    ) -> Result<Option<SettlementRecord>, ProviderError> {
+        let url = format!("{}/transfer/verify/{}", self.base_url, reference);
+
+        let response = self
+            .authorized(Client::new().get(&url))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+
+        if !response["status"].as_bool().unwrap_or_default() || response["data"].is_null() {
+            return Ok(None);
+        }
+
+        Ok(Some(SettlementRecord::from_transfer(&response["data"])))
+    }
+
+    // Paystack returns the existing customer when one already has the email
+    pub async fn create_customer(
+        &self,
+        email: &str,
+        first_name: &str,
+        last_name: &str,
+    ) -> Result<String, ProviderError> {
+        let url = format!("{}/customer", self.base_url);
+
+        let response = self
+            .authorized(Client::new().post(&url))
+            .json(&json!({ "email": email, "first_name": first_name, "last_name": last_name }))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+
+        match response["data"]["customer_code"].as_str() {
+            Some(customer_code) if response["status"].as_bool().unwrap_or_default() => {
+                Ok(customer_code.to_string())
+            }
+            _ => Err(ProviderError::Rejected(
+                response["message"]
+                    .as_str()
+                    .unwrap_or("Customer could not be created")
+                    .to_string(),
+            )),
+        }
+    }
+
+    pub async fn create_dedicated_account(
+        &self,
+        customer_code: &str,
+    ) -> Result<DedicatedAccount, ProviderError> {
+        let url = format!("{}/dedicated_account", self.base_url);
+
+        let response = self
+            .authorized(Client::new().post(&url))
+            .json(&json!({
+                "customer": customer_code,
+                "preferred_bank": self.dedicated_account_bank
+            }))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+
+        let data = &response["data"];
+        let field = |value: &Value| value.as_str().map(String::from);
+        match (
+            response["status"].as_bool().unwrap_or_default(),
+            field(&data["account_number"]),
+            field(&data["account_name"]),
+            field(&data["bank"]["name"]),
+        ) {
+            (true, Some(account_number), Some(account_name), Some(bank_name)) => {
+                Ok(DedicatedAccount {
+                    id: data["id"].as_i64().map(|id| id.to_string()),
+                    account_number,
+                    account_name,
+                    bank_name,
+                })
+            }
+            _ => Err(ProviderError::Rejected(
+                response["message"]
+                    .as_str()
+                    .unwrap_or("Dedicated account could not be created")
+                    .to_string(),
+            )),
+        }
+    }
+
+    // Charges a saved card directly, the customer doesn't go through checkout again
+    pub async fn charge_authorization(
+        &self,
+        charge: &ChargeRequest,
+        authorization_code: &str,
+    ) -> Result<ChargeVerification, ProviderError> {
+        let url = format!("{}/transaction/charge_authorization", self.base_url);
+
+        let response = self
+            .authorized(Client::new().post(&url))
+            .json(&json!({
+                "email": charge.email,
+                "amount": to_kobo(charge.amount),
+                "reference": charge.reference,
+                "authorization_code": authorization_code,
+                "metadata": { "user_id": charge.user_id, "tokenized_charge": "true" }
+            }))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+
+        if !response["status"].as_bool().unwrap_or_default() {
+            let message = response["message"]
+                .as_str()
+                .unwrap_or("Card could not be charged")
+                .to_string();
+            return Err(ProviderError::Rejected(message));
+        }
+
+        Ok(charge_verification(&charge.reference, response))
+    }
+
+    async fn list_all(
+        &self,
+        resource: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Value>, ProviderError> {
+        let mut records = vec![];
+        let mut page = 1;
+
+        loop {
+            let url = format!(
+                "{}/{}?perPage={}&page={}&from={}&to={}",
+                self.base_url,
+                resource,
+                LISTING_PAGE_SIZE,
+                page,
+                from.to_rfc3339_opts(SecondsFormat::Secs, true),
+                to.to_rfc3339_opts(SecondsFormat::Secs, true)
+            );
+
+            let response = self
+                .authorized(Client::new().get(&url))
+                .send()
+                .await?
+                .json::<Value>()
+                .await?;
+
+            if !response["status"].as_bool().unwrap_or_default() {
+                let message = response["message"]
+                    .as_str()
+                    .unwrap_or("Paystack listing was not returned")
+                    .to_string();
+                return Err(ProviderError::Rejected(message));
+            }
+
+            if let Some(data) = response["data"].as_array() {
+                records.extend(data.iter().cloned());
+            }
+
+            let page_count = response["meta"]["pageCount"].as_u64().unwrap_or_default();
+            if page >= page_count {
+                return Ok(records);
+            }
+            page += 1;
+        }
+    }
+
+    async fn create_transfer_recipient(
+        &self,
+        transfer: &TransferRequest,
+    ) -> Result<String, ProviderError> {
+        let url = format!("{}/transferrecipient", self.base_url);
+
+        let response = self
+            .authorized(Client::new().post(&url))
+            .json(&json!({
+                "type": "nuban",
+                "name": transfer.account_name,
+                "account_number": transfer.account_number,
+                "bank_code": transfer.bank_code,
+                "currency": "NGN"
+            }))
+            .send()
+            .await?
+            .json::<TransferRecipientResponse>()
+            .await?;
+
+        match response.data {
+            Some(data) if response.status => Ok(data.recipient_code),
+            _ => Err(ProviderError::Rejected(response.message)),
+        }
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for Paystack {
+    fn name(&self) -> &'static str {
+        "paystack"
+    }
+
+    fn signature_header(&self) -> &'static str {
+        "x-paystack-signature"
+    }
+
+    async fn initialize_charge(
+        &self,
+        charge: &ChargeRequest,
+    ) -> Result<ChargeInitialization, ProviderError> {
+        let url = format!("{}/transaction/initialize", self.base_url);
+
+        let response = self
+            .authorized(Client::new().post(&url))
+            .json(&json!({
+                "email": charge.email,
+                "amount": to_kobo(charge.amount),
+                "reference": charge.reference,
+                "metadata": { "user_id": charge.user_id, "tokenized_charge": "false" }
+            }))
+            .send()
+            .await?
+            .json::<InitiateFundingResponse>()
+            .await?;
+
+        match response.data {
+            Some(data) if response.status => Ok(ChargeInitialization {
+                provider: self.name().to_string(),
+                reference: data.reference,
+                authorization_url: data.authorization_url,
+                access_code: Some(data.access_code),
+            }),
+            _ => Err(ProviderError::Rejected(response.message)),
+        }
+    }
+
+    async fn verify_charge(&self, reference: &str) -> Result<ChargeVerification, ProviderError> {
+        let url = format!("{}/transaction/verify/{}", self.base_url, reference);
+
+        let response = self
+            .authorized(Client::new().get(&url))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+
+        Ok(charge_verification(reference, response))
+    }
+
+    async fn resolve_account(
+        &self,
+        account_number: &str,
+        bank_code: &str,
+    ) -> Result<ResolvedAccount, ProviderError> {
+        let url = format!(
+            "{}/bank/resolve?account_number={}&bank_code={}",
+            self.base_url, account_number, bank_code
+        );
+
+        let response = self
+            .authorized(Client::new().get(&url))
+            .send()
+            .await?
+            .json::<ResolveAccountResponse>()
+            .await?;
+
+        match response.data {
+            Some(data) if response.status => Ok(ResolvedAccount {
+                account_number: data.account_number,
+                account_name: data.account_name,
+            }),
+            _ => Err(ProviderError::Rejected(response.message)),
+        }
+    }
+
+    // The reference is ours so webhook events can be matched back to the debit
+    async fn transfer(
+        &self,
+        transfer: &TransferRequest,
+    ) -> Result<TransferInitiation, ProviderError> {
+        let recipient_code = self.create_transfer_recipient(transfer).await?;
+        let url = format!("{}/transfer", self.base_url);
+
+        let response = self
+            .authorized(Client::new().post(&url))
+            .json(&json!({
+                "source": "balance",
+                "amount": to_kobo(transfer.amount),
+                "recipient": recipient_code,
+                "reference": transfer.reference,
+                "reason": transfer.narration
+            }))
+            .send()
+            .await?
+            .json::<InitiateTransferResponse>()
+            .await?;
+
+        if !response.status {
+            return Err(ProviderError::Rejected(response.message));
+        }
+
+        Ok(TransferInitiation {
+            provider: self.name().to_string(),
+            reference: transfer.reference.to_string(),
+        })
+    }
+
+    async fn transfer_status(&self, reference: &str) -> Result<TransferStatus, ProviderError> {
+        let transfer = match self.verify_transfer(reference).await? {
+            Some(transfer) => transfer,
+            None => return Ok(TransferStatus::NotFound),
+        };
+
+        let status = match transfer.status.as_str() {
+            "success" => TransferStatus::Successful,
+            "failed" | "abandoned" | "rejected" => TransferStatus::Failed(transfer.status),
+            "reversed" => TransferStatus::Reversed(transfer.status),
+            _ => TransferStatus::Pending,
+        };
+
+        Ok(status)
+    }
+
+    async fn refund(&self, reference: &str, amount: Decimal) -> Result<Refund, ProviderError> {
+        let url = format!("{}/refund", self.base_url);
+
+        let response = self
+            .authorized(Client::new().post(&url))
+            .json(&json!({ "transaction": reference, "amount": to_kobo(amount) }))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+
+        if !response["status"].as_bool().unwrap_or_default() {
+            let message = response["message"]
+                .as_str()
+                .unwrap_or("Paystack refund was not accepted")
+                .to_string();
+            return Err(ProviderError::Rejected(message));
+        }
+
+        let reference = match &response["data"]["id"] {
+            Value::Null => None,
+            Value::String(refund_id) => Some(refund_id.to_string()),
+            refund_id => Some(refund_id.to_string()),
+        };
+
+        Ok(Refund { reference })
+    }
+
+    // Signed with an HMAC-SHA512 of the raw body using the secret key
+    fn parse_webhook(&self, signature: &str, body: &[u8]) -> Result<WebhookEvent, ProviderError> {
+        let payload = String::from_utf8_lossy(body).to_string();
+        if !validate_signature(&payload, signature, &self.secret) {
+            return Err(ProviderError::InvalidSignature);
+        }
+
+        let payload: Value = serde_json::from_str(&payload).unwrap_or_default();
+        let event_type = payload["event"].as_str().unwrap_or_default();
+        let reference = payload["data"]["reference"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+
+        let event = match event_type {
+            "charge.success" => WebhookEvent::ChargeSuccessful { reference },
+            "transfer.success" => WebhookEvent::TransferSuccessful { reference },
+            "transfer.failed" => WebhookEvent::TransferFailed {
+                reference,
+                reason: payload["data"]["reason"]
+                    .as_str()
+                    .unwrap_or(event_type)
+                    .to_string(),
+            },
+            // May arrive after transfer.success when the receiving bank returns the money
+            "transfer.reversed" => WebhookEvent::TransferReversed {
+                reference,
+                reason: payload["data"]["reason"]
+                    .as_str()
+                    .unwrap_or(event_type)
+                    .to_string(),
+            },
+            event_type => WebhookEvent::Ignored(event_type.to_string()),
+        };
+
+        Ok(event)
+    }
 }
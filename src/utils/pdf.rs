@@ -0,0 +1,88 @@
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+
+// A4 in points
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+const FONT_SIZE: f32 = 10.0;
+const LEADING: f32 = 14.0;
+// Courier is 0.6em wide, so this is what fits between the margins
+const LINE_WIDTH: usize = 82;
+
+// Lays plain text out one line after the other in Courier, starting a new page when one fills up.
+// The standard fonts need no embedding, which keeps this to the text the templates render
+pub fn text_pdf(title: &str, text: &str) -> Vec<u8> {
+    let lines: Vec<String> = text.lines().flat_map(wrap_line).collect();
+    let lines_per_page = ((PAGE_HEIGHT - 2.0 * MARGIN) / LEADING) as usize;
+    let pages: Vec<&[String]> = if lines.is_empty() {
+        vec![&[]]
+    } else {
+        lines.chunks(lines_per_page).collect()
+    };
+
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let font_id = Ref::new(3);
+    let info_id = Ref::new(4);
+    let font_name = Name(b"F1");
+    // A page and its content stream for each page
+    let page_ids: Vec<(Ref, Ref)> = (0..pages.len() as i32)
+        .map(|page| (Ref::new(5 + page * 2), Ref::new(6 + page * 2)))
+        .collect();
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id)
+        .kids(page_ids.iter().map(|(page_id, _)| *page_id))
+        .count(page_ids.len() as i32);
+    pdf.type1_font(font_id)
+        .base_font(Name(b"Courier"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.document_info(info_id).title(TextStr(title));
+
+    for ((page_id, content_id), lines) in page_ids.into_iter().zip(pages) {
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT));
+        page.parent(page_tree_id);
+        page.contents(content_id);
+        page.resources().fonts().pair(font_name, font_id);
+        page.finish();
+
+        let mut content = Content::new();
+        content.begin_text();
+        content.set_font(font_name, FONT_SIZE);
+        content.set_leading(LEADING);
+        content.next_line(MARGIN, PAGE_HEIGHT - MARGIN);
+        for line in lines {
+            content.show(Str(&win_ansi(line)));
+            content.next_line_using_leading();
+        }
+        content.end_text();
+        pdf.stream(content_id, &content.finish());
+    }
+
+    pdf.finish()
+}
+
+fn wrap_line(line: &str) -> Vec<String> {
+    let chars: Vec<char> = line.trim_end().chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+
+    chars
+        .chunks(LINE_WIDTH)
+        .map(|chunk| chunk.iter().collect())
+        .collect()
+}
+
+// WinAnsi agrees with Latin-1 for the accented letters the templates use, anything outside it
+// can't be shown in a standard font
+fn win_ansi(line: &str) -> Vec<u8> {
+    line.chars()
+        .map(|c| match c as u32 {
+            code @ 0x20..=0x7e | code @ 0xa0..=0xff => code as u8,
+            _ => b'?',
+        })
+        .collect()
+}
@@ -0,0 +1,61 @@
+use qrcode::{render::svg, types::QrError, Color, EcLevel, QrCode};
+use thiserror::Error;
+
+// Pixels per module, so a typical payment QR comes out a little over 400px wide
+const MODULE_SIZE: usize = 8;
+// Scanners need a light border of at least four modules around the code
+const QUIET_ZONE: usize = 4;
+
+#[derive(Error, Debug)]
+pub enum QrImageError {
+    #[error("Failed to encode QR code: {0}")]
+    EncodeError(#[from] QrError),
+
+    #[error("Failed to write PNG: {0}")]
+    PngError(#[from] png::EncodingError),
+}
+
+// Medium error correction survives a scuffed or partly covered sticker at the till
+fn qr_code(data: &str) -> Result<QrCode, QrError> {
+    QrCode::with_error_correction_level(data, EcLevel::M)
+}
+
+pub fn qr_svg(data: &str) -> Result<String, QrImageError> {
+    let code = qr_code(data)?;
+
+    Ok(code
+        .render::<svg::Color>()
+        .module_dimensions(MODULE_SIZE as u32, MODULE_SIZE as u32)
+        .build())
+}
+
+// Black on white 8-bit grayscale
+pub fn qr_png(data: &str) -> Result<Vec<u8>, QrImageError> {
+    let code = qr_code(data)?;
+    let modules = code.width();
+    let colors = code.to_colors();
+    let size = (modules + 2 * QUIET_ZONE) * MODULE_SIZE;
+
+    let mut pixels = vec![255u8; size * size];
+    for (index, color) in colors.iter().enumerate() {
+        if *color != Color::Dark {
+            continue;
+        }
+
+        let left = (index % modules + QUIET_ZONE) * MODULE_SIZE;
+        let top = (index / modules + QUIET_ZONE) * MODULE_SIZE;
+        for row in top..top + MODULE_SIZE {
+            pixels[row * size + left..row * size + left + MODULE_SIZE].fill(0);
+        }
+    }
+
+    let mut image = Vec::new();
+    let mut encoder = png::Encoder::new(&mut image, size as u32, size as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+    writer.finish()?;
+
+    Ok(image)
+}
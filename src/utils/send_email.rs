@@ -1,49 +1,121 @@
 use async_trait::async_trait;
 use lettre::{
-    message::header::ContentType, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
-    AsyncTransport, Message, Tokio1Executor,
+    message::{header::ContentType, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
 };
-use tracing::{error, info, instrument};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{info, instrument};
 
 use super::config::EnvConfig;
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SendEmail {
     pub to: String,
     pub from: String,
     pub subject: String,
     pub template: String,
+    // Plain-text alternative to the HTML template
+    #[serde(default)]
+    pub text: Option<String>,
 }
 
-#[async_trait]
-pub trait SendEmailTrait {
-    async fn send_email(&self, env: &EnvConfig) -> Result<(), ()>;
+#[derive(Error, Debug)]
+pub enum EmailError {
+    #[error("Invalid email address {0}")]
+    InvalidAddress(String),
+
+    #[error("Failed to build email: {0}")]
+    BuildError(String),
+
+    #[error("SMTP is not configured")]
+    NotConfigured,
+
+    #[error("Failed to deliver email: {0}")]
+    TransportError(String),
+}
+
+impl EmailError {
+    // Sending the same email again won't fix these
+    pub fn is_permanent(&self) -> bool {
+        matches!(
+            self,
+            EmailError::InvalidAddress(_) | EmailError::BuildError(_)
+        )
+    }
 }
 
 #[async_trait]
-impl SendEmailTrait for SendEmail {
-    #[instrument(skip(self, env), fields(email = %self.to, subject = %self.subject))]
-    async fn send_email(&self, env: &EnvConfig) -> Result<(), ()> {
-        let email = Message::builder()
-            .to(self.to.parse().unwrap())
-            .from(self.from.parse().unwrap())
-            .subject(format!("{}", self.subject))
-            .header(ContentType::TEXT_HTML)
-            .body(format!("{}", self.template))
-            .expect("Failed to build email");
-
-        let mailer = AsyncSmtpTransport::<Tokio1Executor>::relay(&env.smtp_provider)
-            .unwrap()
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: &SendEmail) -> Result<(), EmailError>;
+}
+
+// Built once and shared, the transport pools its SMTP connections
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    pub fn new(env: &EnvConfig) -> Result<SmtpMailer, EmailError> {
+        if env.smtp_provider.is_empty() {
+            return Err(EmailError::NotConfigured);
+        }
+
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::relay(&env.smtp_provider)
+            .map_err(|err| EmailError::TransportError(err.to_string()))?
             .credentials(Credentials::new(
-                format!("{}", &env.smtp_user),
-                format!("{}", &env.smtp_key),
+                env.smtp_user.to_string(),
+                env.smtp_key.to_string(),
             ))
             .build();
 
-        match mailer.send(email).await {
-            Ok(_) => info!("Email successfully sent"),
-            Err(err) => error!("Failed to deliver email: {}", err),
-        }
+        Ok(SmtpMailer { transport })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    #[instrument(skip(self, email), fields(email = %email.to, subject = %email.subject))]
+    async fn send(&self, email: &SendEmail) -> Result<(), EmailError> {
+        let message = email.message()?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(|err| EmailError::TransportError(err.to_string()))?;
 
+        info!("Email successfully sent");
         Ok(())
     }
 }
+
+impl SendEmail {
+    fn message(&self) -> Result<Message, EmailError> {
+        let to = self
+            .to
+            .parse()
+            .map_err(|_| EmailError::InvalidAddress(self.to.to_string()))?;
+        let from = self
+            .from
+            .parse()
+            .map_err(|_| EmailError::InvalidAddress(self.from.to_string()))?;
+
+        let builder = Message::builder()
+            .to(to)
+            .from(from)
+            .subject(self.subject.to_string());
+
+        let message = match &self.text {
+            Some(text) => builder.multipart(MultiPart::alternative_plain_html(
+                text.to_string(),
+                self.template.to_string(),
+            )),
+            None => builder
+                .header(ContentType::TEXT_HTML)
+                .body(self.template.to_string()),
+        };
+
+        message.map_err(|err| EmailError::BuildError(err.to_string()))
+    }
+}
@@ -0,0 +1,63 @@
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+
+use super::config::EnvConfig;
+use super::email_template::RenderedMessage;
+use super::notification_channel::{ChannelError, NotificationChannel};
+
+// SMS through Termii's messaging API
+pub struct TermiiSms {
+    base_url: String,
+    api_key: String,
+    sender_id: String,
+}
+
+impl TermiiSms {
+    pub fn new(env: &EnvConfig) -> TermiiSms {
+        TermiiSms {
+            base_url: env.termii_base_url.to_string(),
+            api_key: env.termii_api_key.to_string(),
+            sender_id: env.termii_sender_id.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for TermiiSms {
+    fn name(&self) -> &'static str {
+        "termii"
+    }
+
+    async fn send(&self, to: &str, message: &RenderedMessage) -> Result<(), ChannelError> {
+        let url = format!("{}/api/sms/send", self.base_url);
+
+        // Termii takes the number in international format without the plus
+        let response = Client::new()
+            .post(&url)
+            .json(&json!({
+                "api_key": self.api_key,
+                "to": to.trim_start_matches('+'),
+                "from": self.sender_id,
+                "sms": message.body,
+                "type": "plain",
+                "channel": "generic"
+            }))
+            .send()
+            .await?;
+
+        let status = response.status();
+        let body = response.json::<Value>().await.unwrap_or_default();
+        let reason = body["message"]
+            .as_str()
+            .unwrap_or("Termii could not send the SMS")
+            .to_string();
+
+        match status {
+            status if status.is_success() => Ok(()),
+            StatusCode::TOO_MANY_REQUESTS => Err(ChannelError::Unavailable(reason)),
+            status if status.is_server_error() => Err(ChannelError::Unavailable(reason)),
+            _ => Err(ChannelError::Rejected(reason)),
+        }
+    }
+}
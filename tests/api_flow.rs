@@ -0,0 +1,501 @@
+mod common;
+
+use actix_http::Request;
+use actix_web::{
+    body::MessageBody,
+    dev::{Service, ServiceResponse},
+    http::StatusCode,
+    test, web, App,
+};
+use chrono::{Duration, Utc};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use sea_orm::EntityTrait;
+use serde_json::{json, Value};
+
+use common::paystack_mock::MockPaystack;
+use common::{
+    call, charge_success_event, paystack_webhook_request, sqlite_app_state, test_env, PIN,
+};
+use money_transfer::dto::users::TokenClaims;
+use money_transfer::entities::prelude::OutboxMessages;
+use money_transfer::{configure_app, AppState};
+
+const PASSWORD: &str = "secret-password";
+
+fn authorized(request: test::TestRequest, token: &str) -> test::TestRequest {
+    request.insert_header(("Authorization", format!("Bearer {}", token)))
+}
+
+// The verification link is emailed, so the test signs the same token the email would carry
+fn verification_token(app_state: &AppState, email: &str) -> String {
+    let now = Utc::now();
+    let claims = TokenClaims {
+        sub: email.to_string(),
+        auth_type: String::from("ACCOUNT_VERIFICATION"),
+        exp: (now + Duration::days(1)).timestamp() as usize,
+        iat: now.timestamp() as usize,
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(app_state.env.app_key.as_ref()),
+    )
+    .unwrap()
+}
+
+// Signs up, verifies and logs in a user, returning their auth token and uuid
+async fn onboard_user<S, B>(app: &S, app_state: &AppState, first_name: &str) -> (String, String)
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let email = format!("{}@example.com", first_name.to_lowercase());
+
+    let signup = test::TestRequest::post()
+        .uri("/api/user/signup")
+        .set_json(json!({
+            "first_name": first_name,
+            "last_name": "Tester",
+            "email": email,
+            "password": PASSWORD
+        }))
+        .to_request();
+    let (status, _) = call(app, signup).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let verify = test::TestRequest::get()
+        .uri(&format!(
+            "/api/user/verify-account?token={}",
+            verification_token(app_state, &email)
+        ))
+        .to_request();
+    let response = test::call_service(app, verify).await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(
+        response.headers().get("location").unwrap(),
+        "https://github.com/Greatchinex/money-transfer"
+    );
+
+    let login = test::TestRequest::post()
+        .uri("/api/user/login")
+        .set_json(json!({ "email": email, "password": PASSWORD }))
+        .to_request();
+    let (status, body) = call(app, login).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["user"]["is_verified"], json!(1));
+
+    (
+        body["data"]["token"].as_str().unwrap().to_string(),
+        body["data"]["user"]["uuid"].as_str().unwrap().to_string(),
+    )
+}
+
+async fn wallet_balance<S, B>(app: &S, token: &str) -> f64
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let request = authorized(
+        test::TestRequest::get().uri("/api/wallet/my-wallets"),
+        token,
+    )
+    .to_request();
+    let (status, body) = call(app, request).await;
+    assert_eq!(status, StatusCode::OK);
+
+    body["data"]["wallets"][0]["current_balance"]
+        .as_str()
+        .and_then(|balance| balance.parse().ok())
+        .unwrap()
+}
+
+async fn transaction_history<S, B>(app: &S, token: &str) -> Vec<Value>
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let request = authorized(
+        test::TestRequest::get().uri("/api/wallet/transactions"),
+        token,
+    )
+    .to_request();
+    let (status, body) = call(app, request).await;
+    assert_eq!(status, StatusCode::OK);
+
+    body["data"]["transactions"].as_array().unwrap().to_vec()
+}
+
+#[actix_web::test]
+async fn signup_to_funding_to_p2p_to_history() {
+    let mock = MockPaystack::start().await;
+    let app_state = sqlite_app_state(test_env(&mock.base_url)).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+
+    let (ada_token, _) = onboard_user(&app, &app_state, "Ada").await;
+    let (bola_token, bola_id) = onboard_user(&app, &app_state, "Bola").await;
+
+    // Verification emails are queued with the signup for the outbox worker to send
+    let queued = OutboxMessages::find().all(&app_state.db).await.unwrap();
+    let verifications: Vec<_> = queued
+        .iter()
+        .filter(|message| message.payload.contains("verify_account"))
+        .collect();
+    assert_eq!(verifications.len(), 2);
+    assert_eq!(verifications[0].kind, "notification");
+    assert_eq!(verifications[0].status, "pending");
+
+    // Verification also opens a bank account that funds the wallet by transfer
+    let wallets = authorized(
+        test::TestRequest::get().uri("/api/wallet/my-wallets"),
+        &ada_token,
+    )
+    .to_request();
+    let (status, body) = call(&app, wallets).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["wallets"][0]["default"], json!(1));
+    let virtual_account = &body["data"]["wallets"][0]["virtual_accounts"][0];
+    assert_eq!(virtual_account["bank_name"], json!("Test Bank"));
+    assert_eq!(
+        virtual_account["account_number"].as_str().unwrap().len(),
+        10
+    );
+
+    // Bola takes SMS and push alerts, but not pushes for money coming in
+    let phone = authorized(test::TestRequest::put().uri("/api/user/phone"), &bola_token)
+        .set_json(json!({ "phone_number": "0801 234 5678" }))
+        .to_request();
+    let (status, _) = call(&app, phone).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let phone = authorized(test::TestRequest::put().uri("/api/user/phone"), &bola_token)
+        .set_json(json!({ "phone_number": "+234 801 234 5678" }))
+        .to_request();
+    let (status, body) = call(&app, phone).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(
+        body["data"]["user"]["phone_number"],
+        json!("+2348012345678")
+    );
+
+    let device = authorized(
+        test::TestRequest::post().uri("/api/notifications/devices"),
+        &bola_token,
+    )
+    .set_json(json!({ "token": "bola-device-token", "platform": "Android" }))
+    .to_request();
+    let (status, body) = call(&app, device).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    assert_eq!(body["data"]["device"]["platform"], json!("android"));
+
+    let preference = authorized(
+        test::TestRequest::put().uri("/api/notifications/preferences"),
+        &bola_token,
+    )
+    .set_json(json!({ "event": "transfer_received", "channel": "push", "enabled": false }))
+    .to_request();
+    let (status, body) = call(&app, preference).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let preferences = body["data"]["preferences"].as_array().unwrap();
+    let received = preferences
+        .iter()
+        .find(|preference| preference["event"] == json!("transfer_received"))
+        .unwrap();
+    assert_eq!(
+        (&received["email"], &received["sms"], &received["push"]),
+        (&json!(true), &json!(true), &json!(false))
+    );
+
+    // Account emails always go out
+    let preference = authorized(
+        test::TestRequest::put().uri("/api/notifications/preferences"),
+        &bola_token,
+    )
+    .set_json(json!({ "event": "password_reset", "channel": "email", "enabled": false }))
+    .to_request();
+    let (status, _) = call(&app, preference).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let subscription = authorized(
+        test::TestRequest::post().uri("/api/webhook-subscriptions"),
+        &ada_token,
+    )
+    .set_json(json!({ "url": "https://127.0.0.1/hooks", "event_types": ["wallet.emptied"] }))
+    .to_request();
+    let (status, _) = call(&app, subscription).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let subscription = authorized(
+        test::TestRequest::post().uri("/api/webhook-subscriptions"),
+        &ada_token,
+    )
+    .set_json(json!({ "url": "https://127.0.0.1/hooks", "event_types": ["transfer.completed"] }))
+    .to_request();
+    let (status, body) = call(&app, subscription).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    assert!(body["data"]["secret"]
+        .as_str()
+        .unwrap()
+        .starts_with("whsec_"));
+    let subscription_id = body["data"]["subscription"]["uuid"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let subscriptions = authorized(
+        test::TestRequest::get().uri("/api/webhook-subscriptions"),
+        &ada_token,
+    )
+    .to_request();
+    let (status, body) = call(&app, subscriptions).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let subscriptions = body["data"]["subscriptions"].as_array().unwrap();
+    assert_eq!(subscriptions.len(), 1);
+    assert!(subscriptions[0].get("secret").is_none());
+
+    let api_key = authorized(test::TestRequest::post().uri("/api/api-keys"), &ada_token)
+        .set_json(
+            json!({ "name": "Payouts", "scopes": ["wallets:read"], "password": "wrong-password" }),
+        )
+        .to_request();
+    let (status, _) = call(&app, api_key).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let api_key = authorized(test::TestRequest::post().uri("/api/api-keys"), &ada_token)
+        .set_json(json!({ "name": "Payouts", "scopes": ["wallets:read"], "password": PASSWORD }))
+        .to_request();
+    let (status, body) = call(&app, api_key).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    let secret_key = body["data"]["secret_key"].as_str().unwrap().to_string();
+    let api_key_id = body["data"]["api_key"]["uuid"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // The secret key works wherever the wallet routes take a JWT
+    let wallets = authorized(
+        test::TestRequest::get().uri("/api/wallet/my-wallets"),
+        &secret_key,
+    )
+    .to_request();
+    let (status, body) = call(&app, wallets).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let api_keys =
+        authorized(test::TestRequest::get().uri("/api/api-keys"), &ada_token).to_request();
+    let (status, body) = call(&app, api_keys).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let api_keys = body["data"]["api_keys"].as_array().unwrap();
+    assert_eq!(api_keys.len(), 1);
+    assert!(api_keys[0].get("secret_hash").is_none());
+    assert!(api_keys[0]["last_used_at"].is_string());
+
+    let revoke_uri = format!("/api/api-keys/{}", api_key_id);
+    let revoke = authorized(test::TestRequest::delete().uri(&revoke_uri), &ada_token).to_request();
+    let (status, body) = call(&app, revoke).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let set_pin = authorized(
+        test::TestRequest::post().uri("/api/user/set-pin"),
+        &ada_token,
+    )
+    .set_json(json!({ "new_pin": PIN, "password": PASSWORD }))
+    .to_request();
+    let (status, body) = call(&app, set_pin).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    // Funding hands back a checkout link, the wallet is only credited once the charge is verified
+    let fund = authorized(
+        test::TestRequest::post().uri("/api/transfer/fund-account"),
+        &ada_token,
+    )
+    .set_json(json!({ "amount": 5000, "password": PASSWORD }))
+    .to_request();
+    let (status, body) = call(&app, fund).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["provider"], json!("paystack"));
+    let reference = body["data"]["reference"].as_str().unwrap().to_string();
+    assert_eq!(wallet_balance(&app, &ada_token).await, 0.0);
+
+    let verify_uri = format!("/api/transfer/fund-account/{}", reference);
+    let verify = authorized(test::TestRequest::get().uri(&verify_uri), &ada_token).to_request();
+    let (status, body) = call(&app, verify).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["transaction"]["status"], json!("pending"));
+    let funding_id = body["data"]["transaction"]["uuid"].clone();
+
+    // Only the user who started the funding can look it up
+    let verify = authorized(test::TestRequest::get().uri(&verify_uri), &bola_token).to_request();
+    let (status, _) = call(&app, verify).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Paid at checkout, the app verifies before the webhook arrives
+    mock.complete_charge(&reference);
+    let verify = authorized(test::TestRequest::get().uri(&verify_uri), &ada_token).to_request();
+    let (status, body) = call(&app, verify).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["transaction"]["status"], json!("successful"));
+    assert_eq!(body["data"]["transaction"]["uuid"], funding_id);
+    assert_eq!(wallet_balance(&app, &ada_token).await, 5000.0);
+
+    // The webhook arriving afterwards must not credit again
+    let webhook = charge_success_event(&reference, 500000);
+    let request = paystack_webhook_request(common::PAYSTACK_SECRET, &webhook).to_request();
+    let (status, body) = call(&app, request).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(wallet_balance(&app, &ada_token).await, 5000.0);
+
+    // Paystack retries webhooks, a replay must not credit twice
+    let request = paystack_webhook_request(common::PAYSTACK_SECRET, &webhook).to_request();
+    let (status, _) = call(&app, request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(wallet_balance(&app, &ada_token).await, 5000.0);
+
+    // The card used at checkout is saved, without its authorization code
+    let cards = authorized(
+        test::TestRequest::get().uri("/api/payment-methods"),
+        &ada_token,
+    )
+    .to_request();
+    let (status, body) = call(&app, cards).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let cards = body["data"]["payment_methods"].as_array().unwrap();
+    assert_eq!(cards.len(), 1);
+    assert_eq!(cards[0]["last4"], json!("4081"));
+    assert!(cards[0].get("authorization_code").is_none());
+    let card_id = cards[0]["uuid"].as_str().unwrap().to_string();
+
+    let fund_uri = format!("/api/payment-methods/{}/fund", card_id);
+    let fund = authorized(test::TestRequest::post().uri(&fund_uri), &ada_token)
+        .set_json(json!({ "amount": 1000, "pin": "654321" }))
+        .to_request();
+    let (status, _) = call(&app, fund).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let fund = authorized(test::TestRequest::post().uri(&fund_uri), &ada_token)
+        .set_json(json!({ "amount": 1000, "pin": PIN }))
+        .to_request();
+    let (status, body) = call(&app, fund).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["transaction"]["status"], json!("successful"));
+    assert_eq!(wallet_balance(&app, &ada_token).await, 6000.0);
+
+    let p2p = authorized(
+        test::TestRequest::post().uri("/api/transfer/p2p"),
+        &ada_token,
+    )
+    .set_json(json!({
+        "amount": 1500,
+        "pin": PIN,
+        "receiver_id": bola_id,
+        "narration": "Lunch"
+    }))
+    .to_request();
+    let (status, body) = call(&app, p2p).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    assert_eq!(wallet_balance(&app, &ada_token).await, 4500.0);
+    assert_eq!(wallet_balance(&app, &bola_token).await, 1500.0);
+
+    let ada_history = transaction_history(&app, &ada_token).await;
+    assert_eq!(ada_history.len(), 3);
+    let funding = ada_history
+        .iter()
+        .find(|transaction| transaction["provider_reference"] == json!(reference))
+        .unwrap();
+    assert_eq!(funding["category"], json!("funding"));
+    assert_eq!(funding["trx_type"], json!("credit"));
+    assert_eq!(funding["uuid"], funding_id);
+    let debit = ada_history
+        .iter()
+        .find(|transaction| transaction["category"] == json!("p2p"))
+        .unwrap();
+    assert_eq!(debit["trx_type"], json!("debit"));
+
+    let bola_history = transaction_history(&app, &bola_token).await;
+    assert_eq!(bola_history.len(), 1);
+    assert_eq!(bola_history[0]["trx_type"], json!("credit"));
+
+    // Ada's transfer is queued for her webhook endpoint
+    let deliveries_uri = format!("/api/webhook-subscriptions/{}/deliveries", subscription_id);
+    let deliveries =
+        authorized(test::TestRequest::get().uri(&deliveries_uri), &ada_token).to_request();
+    let (status, body) = call(&app, deliveries).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let deliveries = body["data"]["deliveries"].as_array().unwrap();
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0]["event_type"], json!("transfer.completed"));
+    assert_eq!(deliveries[0]["status"], json!("pending"));
+    let redeliver_uri = format!(
+        "/api/webhook-subscriptions/deliveries/{}/redeliver",
+        deliveries[0]["uuid"].as_str().unwrap()
+    );
+
+    let deliveries =
+        authorized(test::TestRequest::get().uri(&deliveries_uri), &bola_token).to_request();
+    let (status, _) = call(&app, deliveries).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let redelivery =
+        authorized(test::TestRequest::post().uri(&redeliver_uri), &ada_token).to_request();
+    let (status, body) = call(&app, redelivery).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["delivery"]["status"], json!("pending"));
+
+    let subscription_uri = format!("/api/webhook-subscriptions/{}", subscription_id);
+    let unsubscribe = authorized(
+        test::TestRequest::delete().uri(&subscription_uri),
+        &ada_token,
+    )
+    .to_request();
+    let (status, body) = call(&app, unsubscribe).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let redelivery =
+        authorized(test::TestRequest::post().uri(&redeliver_uri), &ada_token).to_request();
+    let (status, _) = call(&app, redelivery).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn p2p_without_funds_is_rejected() {
+    let mock = MockPaystack::start().await;
+    let app_state = sqlite_app_state(test_env(&mock.base_url)).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+
+    let (ada_token, _) = onboard_user(&app, &app_state, "Ada").await;
+    let (_, bola_id) = onboard_user(&app, &app_state, "Bola").await;
+
+    let set_pin = authorized(
+        test::TestRequest::post().uri("/api/user/set-pin"),
+        &ada_token,
+    )
+    .set_json(json!({ "new_pin": PIN, "password": PASSWORD }))
+    .to_request();
+    let (status, _) = call(&app, set_pin).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let p2p = authorized(
+        test::TestRequest::post().uri("/api/transfer/p2p"),
+        &ada_token,
+    )
+    .set_json(json!({ "amount": 1500, "pin": PIN, "receiver_id": bola_id }))
+    .to_request();
+    let (status, body) = call(&app, p2p).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], json!("Insufficient Funds"));
+    assert!(transaction_history(&app, &ada_token).await.is_empty());
+
+    mock.stop().await;
+}
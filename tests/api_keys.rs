@@ -0,0 +1,337 @@
+mod common;
+
+use actix_http::Request;
+use actix_web::{http::StatusCode, test, web, App};
+use argonautica::Hasher;
+use chrono::Utc;
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::json;
+
+use common::{call, seed_user, sqlite_app_state, test_env, PIN};
+use money_transfer::entities::{prelude::ApiKeys, users, wallets};
+use money_transfer::service::api_key::{
+    create_api_key, revoke_api_key, rotate_api_key, sign_request, ApiKeyCredentials,
+    API_KEY_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER,
+};
+use money_transfer::{configure_app, AppState};
+
+fn with_secret_key(request: test::TestRequest, credentials: &ApiKeyCredentials) -> Request {
+    request
+        .insert_header((
+            "Authorization",
+            format!("Bearer {}", credentials.secret_key),
+        ))
+        .to_request()
+}
+
+// Signs `signed_body` but sends `body`, so tests can tamper with a signed request
+fn signed_with(
+    method: &str,
+    path: &str,
+    signed_body: &str,
+    body: &str,
+    credentials: &ApiKeyCredentials,
+    timestamp: i64,
+) -> Request {
+    let timestamp = timestamp.to_string();
+    let signature = sign_request(
+        &credentials.signing_secret,
+        &timestamp,
+        method,
+        path,
+        signed_body.as_bytes(),
+    );
+
+    let request = match method {
+        "GET" => test::TestRequest::get(),
+        _ => test::TestRequest::post(),
+    };
+    request
+        .uri(path)
+        .insert_header(("content-type", "application/json"))
+        .insert_header((API_KEY_HEADER, credentials.api_key.publishable_key.as_str()))
+        .insert_header((TIMESTAMP_HEADER, timestamp))
+        .insert_header((SIGNATURE_HEADER, signature))
+        .set_payload(body.to_string())
+        .to_request()
+}
+
+fn signed(
+    method: &str,
+    path: &str,
+    body: &str,
+    credentials: &ApiKeyCredentials,
+    timestamp: i64,
+) -> Request {
+    signed_with(method, path, body, body, credentials, timestamp)
+}
+
+fn scopes(scopes: &[&str]) -> Vec<String> {
+    scopes.iter().map(|scope| scope.to_string()).collect()
+}
+
+async fn seed_funded_user(app_state: &AppState, first_name: &str) -> users::Model {
+    let (user, wallet) = seed_user(&app_state.db, first_name).await;
+
+    let mut funded: wallets::ActiveModel = wallet.into();
+    funded.current_balance = Set(Decimal::from(5000));
+    funded.update(&app_state.db).await.unwrap();
+
+    let pin = Hasher::default()
+        .with_password(PIN)
+        .with_secret_key(&app_state.env.hash_key)
+        .hash()
+        .unwrap();
+    let mut with_pin: users::ActiveModel = user.into();
+    with_pin.withdrawal_pin = Set(Some(pin));
+    with_pin.update(&app_state.db).await.unwrap()
+}
+
+#[actix_web::test]
+async fn secret_keys_act_for_their_owner_within_their_scopes() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let (ada, _) = seed_user(&app_state.db, "Ada").await;
+    let credentials = create_api_key(
+        &app_state.db,
+        &ada.uuid,
+        "Reporting",
+        &scopes(&["wallets:read", "transfers:read"]),
+        &[],
+        false,
+    )
+    .await
+    .unwrap();
+    assert!(credentials.secret_key.starts_with("sk_"));
+    assert!(credentials.api_key.publishable_key.starts_with("pk_"));
+
+    let wallets = test::TestRequest::get().uri("/api/wallet/my-wallets");
+    let (status, body) = call(&app, with_secret_key(wallets, &credentials)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["wallets"][0]["user_id"], json!(ada.uuid));
+
+    let transfer = test::TestRequest::post()
+        .uri("/api/transfer/p2p")
+        .set_json(json!({ "amount": 100, "pin": PIN, "receiver_id": "someone" }));
+    let (status, body) = call(&app, with_secret_key(transfer, &credentials)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(
+        body["message"],
+        json!("This API key doesn't have the transfers:write scope")
+    );
+
+    // Only the wallet and transfer routes take API keys
+    let preferences = test::TestRequest::get().uri("/api/notifications/preferences");
+    let (status, _) = call(&app, with_secret_key(preferences, &credentials)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let stored = ApiKeys::find().one(&app_state.db).await.unwrap().unwrap();
+    assert_ne!(stored.secret_hash, credentials.secret_key);
+    assert!(stored.last_used_at.is_some());
+
+    // Rotating retires the old secret, revoking retires the key
+    let rotated = rotate_api_key(&app_state.db, &ada.uuid, &credentials.api_key.uuid)
+        .await
+        .unwrap();
+    assert_eq!(
+        rotated.api_key.publishable_key,
+        credentials.api_key.publishable_key
+    );
+    let wallets = test::TestRequest::get().uri("/api/wallet/my-wallets");
+    let (status, body) = call(&app, with_secret_key(wallets, &credentials)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["message"], json!("Invalid API key"));
+    let wallets = test::TestRequest::get().uri("/api/wallet/my-wallets");
+    let (status, _) = call(&app, with_secret_key(wallets, &rotated)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    revoke_api_key(&app_state.db, &ada.uuid, &rotated.api_key.uuid)
+        .await
+        .unwrap();
+    let wallets = test::TestRequest::get().uri("/api/wallet/my-wallets");
+    let (status, _) = call(&app, with_secret_key(wallets, &rotated)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn keys_requiring_signatures_only_take_signed_requests() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let ada = seed_funded_user(&app_state, "Ada").await;
+    let (bola, _) = seed_user(&app_state.db, "Bola").await;
+    let credentials = create_api_key(
+        &app_state.db,
+        &ada.uuid,
+        "Payouts",
+        &scopes(&["transfers:write", "wallets:read"]),
+        &[],
+        true,
+    )
+    .await
+    .unwrap();
+
+    let wallets = test::TestRequest::get().uri("/api/wallet/my-wallets");
+    let (status, body) = call(&app, with_secret_key(wallets, &credentials)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        body["message"],
+        json!("This API key only accepts signed requests")
+    );
+
+    let transfer = json!({ "amount": 1500, "pin": PIN, "receiver_id": bola.uuid }).to_string();
+    let now = Utc::now().timestamp();
+
+    // The signature has to cover the body that is sent
+    let larger = json!({ "amount": 4000, "pin": PIN, "receiver_id": bola.uuid }).to_string();
+    let tampered = signed_with(
+        "POST",
+        "/api/transfer/p2p",
+        &transfer,
+        &larger,
+        &credentials,
+        now,
+    );
+    let (status, _) = call(&app, tampered).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let stale = signed(
+        "POST",
+        "/api/transfer/p2p",
+        &transfer,
+        &credentials,
+        now - 600,
+    );
+    let (status, body) = call(&app, stale).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        body["message"],
+        json!("Invalid or expired request signature")
+    );
+
+    let request = signed("POST", "/api/transfer/p2p", &transfer, &credentials, now);
+    let (status, body) = call(&app, request).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let request = signed("GET", "/api/wallet/my-wallets", "", &credentials, now);
+    let (status, body) = call(&app, request).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let balance: f64 = body["data"]["wallets"][0]["current_balance"]
+        .as_str()
+        .and_then(|balance| balance.parse().ok())
+        .unwrap();
+    assert_eq!(balance, 3500.0);
+}
+
+#[actix_web::test]
+async fn allowlisted_keys_are_refused_from_other_addresses() {
+    let mut env = test_env("http://127.0.0.1:1");
+    env.trusted_proxies = vec!["10.0.0.1".parse().unwrap()];
+    let app_state = sqlite_app_state(env).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let (ada, _) = seed_user(&app_state.db, "Ada").await;
+
+    let invalid = create_api_key(
+        &app_state.db,
+        &ada.uuid,
+        "Office",
+        &scopes(&["wallets:read"]),
+        &scopes(&["10.0.0.300"]),
+        false,
+    )
+    .await;
+    assert_eq!(
+        invalid.unwrap_err().to_string(),
+        "Invalid IP address 10.0.0.300"
+    );
+    let invalid = create_api_key(
+        &app_state.db,
+        &ada.uuid,
+        "Office",
+        &scopes(&["wallets:delete"]),
+        &[],
+        false,
+    )
+    .await;
+    assert_eq!(
+        invalid.unwrap_err().to_string(),
+        "Unknown scope wallets:delete"
+    );
+
+    let credentials = create_api_key(
+        &app_state.db,
+        &ada.uuid,
+        "Office",
+        &scopes(&["wallets:read"]),
+        &scopes(&["10.0.0.7"]),
+        false,
+    )
+    .await
+    .unwrap();
+    assert_eq!(credentials.api_key.allowed_ips, vec!["10.0.0.7"]);
+
+    let wallets = test::TestRequest::get()
+        .uri("/api/wallet/my-wallets")
+        .peer_addr("10.0.0.8:50000".parse().unwrap());
+    let (status, body) = call(&app, with_secret_key(wallets, &credentials)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(
+        body["message"],
+        json!("Requests from 10.0.0.8 are not allowed with this API key")
+    );
+
+    let wallets = test::TestRequest::get()
+        .uri("/api/wallet/my-wallets")
+        .peer_addr("10.0.0.7:50000".parse().unwrap());
+    let (status, body) = call(&app, with_secret_key(wallets, &credentials)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let stored = ApiKeys::find().one(&app_state.db).await.unwrap().unwrap();
+    assert_eq!(stored.last_used_ip.unwrap(), "10.0.0.7");
+
+    // A forwarded address is ignored unless the request came through our proxy
+    let wallets = test::TestRequest::get()
+        .uri("/api/wallet/my-wallets")
+        .peer_addr("10.0.0.8:50000".parse().unwrap())
+        .insert_header(("X-Forwarded-For", "10.0.0.7"));
+    let (status, body) = call(&app, with_secret_key(wallets, &credentials)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(
+        body["message"],
+        json!("Requests from 10.0.0.8 are not allowed with this API key")
+    );
+
+    // Through the proxy only the address it appended counts, not what the client sent before it
+    let wallets = test::TestRequest::get()
+        .uri("/api/wallet/my-wallets")
+        .peer_addr("10.0.0.1:50000".parse().unwrap())
+        .insert_header(("X-Forwarded-For", "10.0.0.7, 10.0.0.8"));
+    let (status, body) = call(&app, with_secret_key(wallets, &credentials)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(
+        body["message"],
+        json!("Requests from 10.0.0.8 are not allowed with this API key")
+    );
+
+    let wallets = test::TestRequest::get()
+        .uri("/api/wallet/my-wallets")
+        .peer_addr("10.0.0.1:50000".parse().unwrap())
+        .insert_header(("X-Forwarded-For", "10.0.0.7"));
+    let (status, body) = call(&app, with_secret_key(wallets, &credentials)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+}
@@ -0,0 +1,190 @@
+mod common;
+
+use actix_http::Request;
+use actix_web::{
+    body::MessageBody,
+    dev::{Service, ServiceResponse},
+    http::StatusCode,
+    test, web, App,
+};
+use chrono::{Duration, Utc};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::{json, Value};
+
+use common::{amount, authorized, call, seed_payer, sqlite_app_state, test_env, wallet_of, PIN};
+use money_transfer::entities::{payment_requests, prelude::PaymentRequests, users};
+use money_transfer::service::payment_request::expire_stale_requests;
+use money_transfer::{configure_app, AppState};
+
+// Splits `total` equally and returns the bill along with each participant's payment request
+async fn split_bill<S, B>(
+    app: &S,
+    app_state: &AppState,
+    creator: &users::Model,
+    total: u64,
+    participants: &[&users::Model],
+) -> (String, Vec<String>)
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let participants: Vec<Value> = participants
+        .iter()
+        .map(|participant| json!({ "user_id": &participant.uuid }))
+        .collect();
+    let request = test::TestRequest::post().uri("/api/bill").set_json(json!({
+        "title": "Dinner",
+        "total_amount": total,
+        "split_type": "equal",
+        "participants": participants,
+    }));
+    let (status, body) = call(app, authorized(request, app_state, creator)).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+
+    let request_ids = body["data"]["participants"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|participant| {
+            participant["payment_request_id"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect();
+
+    (
+        body["data"]["bill"]["uuid"].as_str().unwrap().to_string(),
+        request_ids,
+    )
+}
+
+async fn respond<S, B>(
+    app: &S,
+    app_state: &AppState,
+    payer: &users::Model,
+    request_id: &str,
+    accept: bool,
+) where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let request = match accept {
+        true => test::TestRequest::post()
+            .uri(&format!("/api/payment-request/{}/accept", request_id))
+            .set_json(json!({ "pin": PIN })),
+        false => {
+            test::TestRequest::patch().uri(&format!("/api/payment-request/{}/decline", request_id))
+        }
+    };
+    let (status, body) = call(app, authorized(request, app_state, payer)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+}
+
+async fn fetch_bill<S, B>(
+    app: &S,
+    app_state: &AppState,
+    creator: &users::Model,
+    bill_id: &str,
+) -> Value
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let request = test::TestRequest::get().uri(&format!("/api/bill/{}", bill_id));
+    let (status, body) = call(app, authorized(request, app_state, creator)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    body["data"].clone()
+}
+
+#[actix_web::test]
+async fn bills_settle_once_every_participant_pays() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let ada = seed_payer(&app_state, "Ada", 0).await;
+    let bola = seed_payer(&app_state, "Bola", 10000).await;
+    let chi = seed_payer(&app_state, "Chi", 10000).await;
+
+    let (bill_id, request_ids) = split_bill(&app, &app_state, &ada, 9000, &[&bola, &chi]).await;
+
+    respond(&app, &app_state, &bola, &request_ids[0], true).await;
+    let bill = fetch_bill(&app, &app_state, &ada, &bill_id).await;
+    assert_eq!(bill["bill"]["status"], "open");
+    assert_eq!(amount(&bill["bill"]["amount_paid"]), Decimal::from(4500));
+
+    respond(&app, &app_state, &chi, &request_ids[1], true).await;
+    let bill = fetch_bill(&app, &app_state, &ada, &bill_id).await;
+    assert_eq!(bill["bill"]["status"], "settled");
+    assert_eq!(amount(&bill["bill"]["amount_paid"]), Decimal::from(9000));
+
+    assert_eq!(
+        wallet_of(&app_state.db, &ada).await.current_balance,
+        Decimal::from(9000)
+    );
+    assert_eq!(
+        wallet_of(&app_state.db, &bola).await.current_balance,
+        Decimal::from(5500)
+    );
+}
+
+#[actix_web::test]
+async fn bills_close_out_when_participants_decline_or_let_requests_expire() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let ada = seed_payer(&app_state, "Ada", 0).await;
+    let bola = seed_payer(&app_state, "Bola", 10000).await;
+    let chi = seed_payer(&app_state, "Chi", 10000).await;
+    let dayo = seed_payer(&app_state, "Dayo", 10000).await;
+
+    let (bill_id, request_ids) =
+        split_bill(&app, &app_state, &ada, 9000, &[&bola, &chi, &dayo]).await;
+
+    respond(&app, &app_state, &bola, &request_ids[0], true).await;
+    respond(&app, &app_state, &chi, &request_ids[1], false).await;
+
+    // Dayo never responds
+    let request = PaymentRequests::find_by_id(&request_ids[2])
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut expired: payment_requests::ActiveModel = request.into();
+    expired.expires_at = Set(Utc::now() - Duration::minutes(1));
+    expired.update(&app_state.db).await.unwrap();
+    expire_stale_requests(&app_state.db).await.unwrap();
+
+    let bill = fetch_bill(&app, &app_state, &ada, &bill_id).await;
+    assert_eq!(bill["bill"]["status"], "partially_settled");
+    assert_eq!(amount(&bill["bill"]["amount_paid"]), Decimal::from(3000));
+    let statuses: Vec<_> = bill["participants"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|participant| participant["participant"]["status"].as_str().unwrap())
+        .collect();
+    assert_eq!(statuses, ["paid", "declined", "expired"]);
+
+    // Nobody paid this one
+    let (bill_id, request_ids) = split_bill(&app, &app_state, &ada, 2000, &[&chi]).await;
+    respond(&app, &app_state, &chi, &request_ids[0], false).await;
+
+    let bill = fetch_bill(&app, &app_state, &ada, &bill_id).await;
+    assert_eq!(bill["bill"]["status"], "closed");
+
+    let request = test::TestRequest::patch().uri(&format!("/api/bill/{}/cancel", bill_id));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "Bill is already closed");
+}
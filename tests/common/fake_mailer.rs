@@ -0,0 +1,41 @@
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use money_transfer::utils::send_email::{EmailError, Mailer, SendEmail};
+
+// Stands in for SMTP. Fails with the queued errors first, then records what it was asked to send
+#[derive(Default)]
+pub struct FakeMailer {
+    failures: Mutex<VecDeque<EmailError>>,
+    sent: Mutex<Vec<SendEmail>>,
+}
+
+impl FakeMailer {
+    pub fn fail_with(&self, err: EmailError) {
+        self.failures.lock().unwrap().push_back(err);
+    }
+
+    pub fn sent(&self) -> Vec<SendEmail> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    pub fn sent_to(&self) -> Vec<String> {
+        self.sent()
+            .iter()
+            .map(|email| email.to.to_string())
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Mailer for FakeMailer {
+    async fn send(&self, email: &SendEmail) -> Result<(), EmailError> {
+        if let Some(err) = self.failures.lock().unwrap().pop_front() {
+            return Err(err);
+        }
+
+        self.sent.lock().unwrap().push(email.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+use actix_web::{dev::ServerHandle, web, App, HttpRequest, HttpResponse, HttpServer};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+pub const FLUTTERWAVE_SECRET: &str = "FLWSECK_TEST-mock";
+pub const FLUTTERWAVE_SECRET_HASH: &str = "flw-mock-hash";
+
+#[derive(Default)]
+struct MockState {
+    // Charges by tx_ref, shaped like the data of a verify response
+    charges: HashMap<String, Value>,
+    // Transfers by our reference, shaped like an entry of the transfer listing
+    transfers: HashMap<String, Value>,
+    accounts: HashMap<(String, String), String>,
+    next_id: i64,
+}
+
+type SharedState = web::Data<Mutex<MockState>>;
+
+// A local stand-in for the parts of the Flutterwave v3 API the adapter uses
+pub struct MockFlutterwave {
+    pub base_url: String,
+    state: SharedState,
+    handle: ServerHandle,
+}
+
+impl MockFlutterwave {
+    pub async fn start() -> MockFlutterwave {
+        let state: SharedState = web::Data::new(Mutex::new(MockState::default()));
+        let server_state = state.clone();
+
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(server_state.clone())
+                .route("/payments", web::post().to(initialize_payment))
+                .route(
+                    "/transactions/verify_by_reference",
+                    web::get().to(verify_by_reference),
+                )
+                .route("/accounts/resolve", web::post().to(resolve_account))
+                .route("/transfers", web::post().to(transfer))
+                .route("/transfers", web::get().to(list_transfers))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .expect("Failed to bind mock Flutterwave server");
+
+        let address = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        MockFlutterwave {
+            base_url: format!("http://{}", address),
+            state,
+            handle,
+        }
+    }
+
+    pub async fn stop(self) {
+        self.handle.stop(false).await;
+    }
+
+    pub fn add_account(&self, account_number: &str, bank_code: &str, account_name: &str) {
+        self.state.lock().unwrap().accounts.insert(
+            (account_number.to_string(), bank_code.to_string()),
+            account_name.to_string(),
+        );
+    }
+
+    // Simulates the customer paying on the hosted checkout
+    pub fn complete_charge(&self, tx_ref: &str) {
+        let mut state = self.state.lock().unwrap();
+        let charge = state
+            .charges
+            .get_mut(tx_ref)
+            .unwrap_or_else(|| panic!("No mock charge with tx_ref {}", tx_ref));
+        charge["status"] = json!("successful");
+    }
+
+    // One of NEW, PENDING, SUCCESSFUL or FAILED
+    pub fn set_transfer_status(&self, reference: &str, status: &str) {
+        let mut state = self.state.lock().unwrap();
+        let transfer = state
+            .transfers
+            .get_mut(reference)
+            .unwrap_or_else(|| panic!("No mock transfer with reference {}", reference));
+        transfer["status"] = json!(status);
+    }
+
+    pub fn transfer_count(&self) -> usize {
+        self.state.lock().unwrap().transfers.len()
+    }
+}
+
+fn next_id(state: &mut MockState) -> i64 {
+    state.next_id += 1;
+    state.next_id
+}
+
+async fn initialize_payment(state: SharedState, body: web::Json<Value>) -> HttpResponse {
+    let tx_ref = body["tx_ref"].as_str().unwrap_or_default().to_string();
+    let mut state = state.lock().unwrap();
+    let id = next_id(&mut state);
+    state.charges.insert(
+        tx_ref.to_string(),
+        json!({
+            "id": id,
+            "tx_ref": tx_ref,
+            "status": "pending",
+            "amount": body["amount"],
+            "app_fee": 0,
+            "currency": "NGN",
+            "meta": body["meta"],
+            "customer": body["customer"],
+        }),
+    );
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "message": "Hosted Link",
+        "data": { "link": format!("https://checkout.flutterwave.test/pay/{}", tx_ref) }
+    }))
+}
+
+async fn verify_by_reference(state: SharedState, req: HttpRequest) -> HttpResponse {
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .map(|query| query.into_inner())
+        .unwrap_or_default();
+    let tx_ref = query.get("tx_ref").cloned().unwrap_or_default();
+
+    match state.lock().unwrap().charges.get(&tx_ref) {
+        Some(charge) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Transaction fetched successfully",
+            "data": charge,
+        })),
+        None => HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "No transaction was found for this id",
+            "data": null,
+        })),
+    }
+}
+
+async fn resolve_account(state: SharedState, body: web::Json<Value>) -> HttpResponse {
+    let account_number = body["account_number"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let bank_code = body["account_bank"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+
+    match state
+        .lock()
+        .unwrap()
+        .accounts
+        .get(&(account_number.to_string(), bank_code))
+    {
+        Some(account_name) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Account details fetched",
+            "data": { "account_number": account_number, "account_name": account_name }
+        })),
+        None => HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "Sorry, that account number is invalid",
+            "data": null,
+        })),
+    }
+}
+
+async fn transfer(state: SharedState, body: web::Json<Value>) -> HttpResponse {
+    let reference = body["reference"].as_str().unwrap_or_default().to_string();
+    let mut state = state.lock().unwrap();
+    let id = next_id(&mut state);
+    let transfer = json!({
+        "id": id,
+        "reference": reference,
+        "amount": body["amount"],
+        "status": "NEW",
+        "complete_message": "",
+    });
+    state.transfers.insert(reference, transfer.clone());
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "message": "Transfer Queued Successfully",
+        "data": transfer,
+    }))
+}
+
+async fn list_transfers(state: SharedState, req: HttpRequest) -> HttpResponse {
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .map(|query| query.into_inner())
+        .unwrap_or_default();
+    let data: Vec<Value> = query
+        .get("reference")
+        .and_then(|reference| state.lock().unwrap().transfers.get(reference).cloned())
+        .into_iter()
+        .collect();
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "message": "Transfers fetched",
+        "data": data,
+    }))
+}
@@ -0,0 +1,112 @@
+use actix_web::{dev::ServerHandle, web, App, HttpResponse, HttpServer};
+use serde_json::{json, Value};
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+
+#[derive(Default)]
+struct MockState {
+    // Status codes to answer the next SMS requests with
+    sms_failures: VecDeque<u16>,
+    // Device tokens FCM reports as no longer registered
+    unregistered_tokens: HashSet<String>,
+    sms: Vec<Value>,
+    pushes: Vec<Value>,
+}
+
+type SharedState = web::Data<Mutex<MockState>>;
+
+// A local stand-in for Termii's SMS API and FCM's push API, recording what is sent to each
+pub struct MockMessaging {
+    pub base_url: String,
+    state: SharedState,
+    handle: ServerHandle,
+}
+
+impl MockMessaging {
+    pub async fn start() -> MockMessaging {
+        let state: SharedState = web::Data::new(Mutex::new(MockState::default()));
+        let server_state = state.clone();
+
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(server_state.clone())
+                .route("/api/sms/send", web::post().to(send_sms))
+                .route("/fcm/send", web::post().to(send_push))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .expect("Failed to bind mock messaging server");
+
+        let address = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        MockMessaging {
+            base_url: format!("http://{}", address),
+            state,
+            handle,
+        }
+    }
+
+    pub async fn stop(self) {
+        self.handle.stop(false).await;
+    }
+
+    pub fn fail_next_sms(&self, status: u16) {
+        self.state.lock().unwrap().sms_failures.push_back(status);
+    }
+
+    pub fn unregister(&self, token: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .unregistered_tokens
+            .insert(token.to_string());
+    }
+
+    pub fn sms(&self) -> Vec<Value> {
+        self.state.lock().unwrap().sms.clone()
+    }
+
+    pub fn pushes(&self) -> Vec<Value> {
+        self.state.lock().unwrap().pushes.clone()
+    }
+}
+
+async fn send_sms(state: SharedState, body: web::Json<Value>) -> HttpResponse {
+    let mut state = state.lock().unwrap();
+
+    if let Some(status) = state.sms_failures.pop_front() {
+        let status =
+            actix_web::http::StatusCode::from_u16(status).expect("Invalid scripted status code");
+        return HttpResponse::build(status).json(json!({ "message": "Mock SMS failure" }));
+    }
+
+    state.sms.push(body.0);
+    HttpResponse::Ok().json(json!({
+        "code": "ok",
+        "message_id": format!("termii-{}", state.sms.len()),
+        "message": "Successfully Sent"
+    }))
+}
+
+async fn send_push(state: SharedState, body: web::Json<Value>) -> HttpResponse {
+    let mut state = state.lock().unwrap();
+    let token = body["to"].as_str().unwrap_or_default();
+
+    if state.unregistered_tokens.contains(token) {
+        return HttpResponse::Ok().json(json!({
+            "success": 0,
+            "failure": 1,
+            "results": [{ "error": "NotRegistered" }]
+        }));
+    }
+
+    state.pushes.push(body.0);
+    HttpResponse::Ok().json(json!({
+        "success": 1,
+        "failure": 0,
+        "results": [{ "message_id": format!("fcm-{}", state.pushes.len()) }]
+    }))
+}
@@ -0,0 +1,267 @@
+// Not every test binary uses every helper
+#![allow(dead_code)]
+
+pub mod fake_mailer;
+pub mod flutterwave_mock;
+pub mod messaging_mock;
+pub mod paystack_mock;
+pub mod webhook_receiver;
+
+use actix_http::Request;
+use actix_web::{
+    body::MessageBody,
+    dev::{Service, ServiceResponse},
+    http::StatusCode,
+    test::{self, TestRequest},
+};
+use argonautica::Hasher;
+use chrono::{Duration, Utc};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use migration::{Migrator, MigratorTrait};
+use money_transfer::dto::users::TokenClaims;
+use money_transfer::entities::{
+    prelude::{Transactions, Wallets},
+    transactions, users, wallets,
+};
+use money_transfer::utils::config::EnvConfig;
+use money_transfer::AppState;
+use ring::hmac;
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+pub const PAYSTACK_SECRET: &str = "sk_test_mock_paystack";
+pub const PIN: &str = "123456";
+
+pub fn test_env(paystack_base_url: &str) -> EnvConfig {
+    EnvConfig {
+        app_name: String::from("money-transfer-test"),
+        port: String::from("0"),
+        host: String::from("127.0.0.1"),
+        database_url: String::new(),
+        app_key: String::from("test-app-key"),
+        hash_key: String::from("test-hash-key-that-is-long-enough"),
+        app_base_url: String::from("http://localhost"),
+        // Nothing listens here, emails fail to send and are only logged
+        smtp_provider: String::from("localhost"),
+        smtp_user: String::new(),
+        smtp_key: String::new(),
+        from_email: String::from("support@moneytransfer.am"),
+        termii_base_url: String::from("http://127.0.0.1:1"),
+        termii_api_key: String::new(),
+        termii_sender_id: String::from("MoneyTrf"),
+        fcm_base_url: String::from("http://127.0.0.1:1"),
+        fcm_server_key: String::new(),
+        notification_sink: String::new(),
+        paystack_base_url: paystack_base_url.to_string(),
+        paystack_secret: PAYSTACK_SECRET.to_string(),
+        paystack_dva_bank: String::from("test-bank"),
+        flutterwave_base_url: String::new(),
+        flutterwave_secret: String::new(),
+        flutterwave_secret_hash: String::new(),
+        charge_providers: vec![String::from("paystack")],
+        transfer_providers: vec![String::from("paystack")],
+        scheduler_interval_secs: 60,
+        reconciliation_interval_secs: 86400,
+        reconciliation_freeze_wallets: false,
+        funding_poll_interval_secs: 300,
+        funding_stale_after_mins: 15,
+        funding_abandon_after_mins: 1440,
+        transfer_poll_interval_secs: 300,
+        transfer_stale_after_mins: 30,
+        transfer_abandon_after_mins: 1440,
+        outbox_poll_interval_secs: 10,
+        outbox_max_attempts: 8,
+        outbox_retry_base_secs: 30,
+        webhook_timeout_secs: 5,
+        // The test receivers listen on plain http on localhost
+        webhook_allow_insecure_urls: true,
+        merchant_fee_percent: Decimal::new(15, 1),
+        merchant_fee_cap: Decimal::from(2000),
+        invoice_reminder_interval_secs: 3600,
+        invoice_reminder_every_hours: 72,
+        invoice_max_reminders: 3,
+        qr_code_ttl_mins: 15,
+        escrow_release_interval_secs: 300,
+        payment_request_expiry_interval_secs: 60,
+        escrow_auto_release_hours: 72,
+        card_issuer: String::from("mock"),
+        card_issuer_secret: String::from("card-issuer-secret"),
+        trusted_proxies: vec![],
+    }
+}
+
+// For tests that never reach the database
+pub fn app_state_without_db(env: EnvConfig) -> AppState {
+    AppState {
+        db: DatabaseConnection::Disconnected,
+        env,
+    }
+}
+
+// A fresh in-memory SQLite database with every migration applied
+pub async fn sqlite_app_state(env: EnvConfig) -> AppState {
+    let db = Database::connect("sqlite::memory:")
+        .await
+        .expect("Failed to open in-memory SQLite database");
+    Migrator::up(&db, None)
+        .await
+        .expect("Failed to run migrations against SQLite");
+
+    AppState { db, env }
+}
+
+// A verified user with an empty default wallet, for tests that skip signup
+pub async fn seed_user(
+    db: &DatabaseConnection,
+    first_name: &str,
+) -> (users::Model, wallets::Model) {
+    let user = users::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        first_name: Set(first_name.to_string()),
+        last_name: Set(String::from("Tester")),
+        email: Set(format!("{}@example.com", first_name.to_lowercase())),
+        password: Set(String::from("not-a-real-hash")),
+        is_verified: Set(true),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("Failed to seed user");
+
+    let wallet = wallets::ActiveModel {
+        uuid: Set(Uuid::new_v4().to_string()),
+        user_id: Set(user.uuid.to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("Failed to seed wallet");
+
+    (user, wallet)
+}
+
+// A seeded user with a funded wallet and PIN set, ready to pay for things
+pub async fn seed_payer(app_state: &AppState, first_name: &str, balance: i64) -> users::Model {
+    let (user, wallet) = seed_user(&app_state.db, first_name).await;
+
+    let mut funded: wallets::ActiveModel = wallet.into();
+    funded.current_balance = Set(Decimal::from(balance));
+    funded.update(&app_state.db).await.unwrap();
+
+    let pin = Hasher::default()
+        .with_password(PIN)
+        .with_secret_key(&app_state.env.hash_key)
+        .hash()
+        .unwrap();
+    let mut with_pin: users::ActiveModel = user.into();
+    with_pin.withdrawal_pin = Set(Some(pin));
+    with_pin.update(&app_state.db).await.unwrap()
+}
+
+pub async fn wallet_of(db: &DatabaseConnection, user: &users::Model) -> wallets::Model {
+    Wallets::find()
+        .filter(wallets::Column::UserId.eq(&user.uuid))
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+pub async fn wallet_transactions(
+    db: &DatabaseConnection,
+    wallet: &wallets::Model,
+) -> Vec<transactions::Model> {
+    Transactions::find()
+        .filter(transactions::Column::WalletId.eq(&wallet.uuid))
+        .order_by_asc(transactions::Column::Id)
+        .all(db)
+        .await
+        .unwrap()
+}
+
+// Middleware rejections come back as errors rather than responses
+pub async fn call<S, B>(app: &S, request: Request) -> (StatusCode, Value)
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    match test::try_call_service(app, request).await {
+        Ok(response) => {
+            let status = response.status();
+            let body = test::read_body(response).await;
+            (status, serde_json::from_slice(&body).unwrap_or_default())
+        }
+        Err(err) => (
+            err.as_response_error().status_code(),
+            serde_json::from_str(&err.to_string()).unwrap_or_default(),
+        ),
+    }
+}
+
+// Signs the same token logging in would hand out
+pub fn authorized(request: TestRequest, app_state: &AppState, user: &users::Model) -> Request {
+    let now = Utc::now();
+    let claims = TokenClaims {
+        sub: user.uuid.to_string(),
+        auth_type: String::from("USER_AUTH"),
+        exp: (now + Duration::hours(1)).timestamp() as usize,
+        iat: now.timestamp() as usize,
+    };
+    let token = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(app_state.env.app_key.as_ref()),
+    )
+    .unwrap();
+
+    request
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request()
+}
+
+// Decimals are serialized as strings
+pub fn amount(value: &Value) -> Decimal {
+    value.as_str().unwrap().parse().unwrap()
+}
+
+// Paystack signs the raw body with an HMAC-SHA512 of the secret key
+pub fn paystack_signature(secret: &str, body: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA512, secret.as_bytes());
+    hex::encode(hmac::sign(&key, body.as_bytes()).as_ref())
+}
+
+pub fn paystack_webhook_request(secret: &str, payload: &Value) -> TestRequest {
+    let body = payload.to_string();
+
+    TestRequest::post()
+        .uri("/api/webhook/paystack")
+        .insert_header(("content-type", "application/json"))
+        .insert_header(("x-paystack-signature", paystack_signature(secret, &body)))
+        .set_payload(body)
+}
+
+// Flutterwave sends the secret hash from the dashboard as is
+pub fn flutterwave_webhook_request(secret_hash: &str, payload: &Value) -> TestRequest {
+    TestRequest::post()
+        .uri("/api/webhook/flutterwave")
+        .insert_header(("content-type", "application/json"))
+        .insert_header(("verif-hash", secret_hash))
+        .set_payload(payload.to_string())
+}
+
+pub fn charge_success_event(reference: &str, amount_in_kobo: i64) -> Value {
+    json!({
+        "event": "charge.success",
+        "data": { "reference": reference, "amount": amount_in_kobo, "status": "success" }
+    })
+}
+
+// event is one of transfer.success, transfer.failed or transfer.reversed
+pub fn transfer_event(event: &str, reference: &str, reason: &str) -> Value {
+    json!({
+        "event": event,
+        "data": { "reference": reference, "reason": reason }
+    })
+}
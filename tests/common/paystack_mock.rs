@@ -0,0 +1,600 @@
+use actix_web::{dev::ServerHandle, web, App, HttpRequest, HttpResponse, HttpServer};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PaystackRoute {
+    InitializeTransaction,
+    VerifyTransaction,
+    ChargeAuthorization,
+    CreateCustomer,
+    CreateDedicatedAccount,
+    TransferRecipient,
+    Transfer,
+    ResolveAccount,
+    Refund,
+    ListTransactions,
+    ListTransfers,
+    VerifyTransfer,
+}
+
+struct ScriptedResponse {
+    status: u16,
+    body: Value,
+    // Sent as is rather than as JSON, like the HTML error pages in front of the API
+    raw: bool,
+}
+
+#[derive(Default)]
+struct MockState {
+    // Charges by reference, shaped like the data of a verify response
+    charges: HashMap<String, Value>,
+    // Transfers by reference, shaped like the data of a transfer verify response
+    transfers: HashMap<String, Value>,
+    accounts: HashMap<(String, String), String>,
+    // Customer codes by email
+    customers: HashMap<String, String>,
+    dedicated_accounts: usize,
+    // Authorization codes whose charges the card issuer declines
+    declined_authorizations: HashSet<String>,
+    scripted: HashMap<PaystackRoute, VecDeque<ScriptedResponse>>,
+    requests: HashMap<PaystackRoute, Vec<Value>>,
+}
+
+type SharedState = web::Data<Mutex<MockState>>;
+
+// A local stand-in for the Paystack API. Each route answers like Paystack would unless a
+// response has been scripted for it, in which case the scripted ones are used first
+pub struct MockPaystack {
+    pub base_url: String,
+    state: SharedState,
+    handle: ServerHandle,
+}
+
+impl MockPaystack {
+    pub async fn start() -> MockPaystack {
+        let state: SharedState = web::Data::new(Mutex::new(MockState::default()));
+        let server_state = state.clone();
+
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(server_state.clone())
+                .route(
+                    "/transaction/initialize",
+                    web::post().to(initialize_transaction),
+                )
+                .route(
+                    "/transaction/verify/{reference}",
+                    web::get().to(verify_transaction),
+                )
+                .route(
+                    "/transaction/charge_authorization",
+                    web::post().to(charge_authorization),
+                )
+                .route("/customer", web::post().to(create_customer))
+                .route(
+                    "/dedicated_account",
+                    web::post().to(create_dedicated_account),
+                )
+                .route("/transferrecipient", web::post().to(transfer_recipient))
+                .route("/transaction", web::get().to(list_transactions))
+                .route("/transfer", web::post().to(transfer))
+                .route("/transfer", web::get().to(list_transfers))
+                .route(
+                    "/transfer/verify/{reference}",
+                    web::get().to(verify_transfer),
+                )
+                .route("/bank/resolve", web::get().to(resolve_account))
+                .route("/refund", web::post().to(refund))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .expect("Failed to bind mock Paystack server");
+
+        let address = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        MockPaystack {
+            base_url: format!("http://{}", address),
+            state,
+            handle,
+        }
+    }
+
+    pub async fn stop(self) {
+        self.handle.stop(false).await;
+    }
+
+    // Queues a response for the next call to a route
+    pub fn script(&self, route: PaystackRoute, status: u16, body: Value) {
+        self.state
+            .lock()
+            .unwrap()
+            .scripted
+            .entry(route)
+            .or_default()
+            .push_back(ScriptedResponse {
+                status,
+                body,
+                raw: false,
+            });
+    }
+
+    // Queues a response that isn't JSON for the next call to a route
+    pub fn script_raw(&self, route: PaystackRoute, status: u16, body: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .scripted
+            .entry(route)
+            .or_default()
+            .push_back(ScriptedResponse {
+                status,
+                body: Value::String(body.to_string()),
+                raw: true,
+            });
+    }
+
+    pub fn add_account(&self, account_number: &str, bank_code: &str, account_name: &str) {
+        self.state.lock().unwrap().accounts.insert(
+            (account_number.to_string(), bank_code.to_string()),
+            account_name.to_string(),
+        );
+    }
+
+    // Registers a charge that was paid outside of /transaction/initialize
+    pub fn add_charge(&self, reference: &str, amount_in_kobo: i64, user_id: &str, email: &str) {
+        let charge = charge_data(reference, amount_in_kobo, user_id, email);
+        self.state
+            .lock()
+            .unwrap()
+            .charges
+            .insert(reference.to_string(), charge);
+        self.set_charge_status(reference, "success");
+    }
+
+    // Registers a charge that was initialized elsewhere and is still awaiting payment
+    pub fn add_pending_charge(
+        &self,
+        reference: &str,
+        amount_in_kobo: i64,
+        user_id: &str,
+        email: &str,
+    ) {
+        let charge = charge_data(reference, amount_in_kobo, user_id, email);
+        self.state
+            .lock()
+            .unwrap()
+            .charges
+            .insert(reference.to_string(), charge);
+    }
+
+    // A bank transfer into a customer's dedicated account, already settled
+    pub fn add_virtual_account_charge(&self, reference: &str, amount_in_kobo: i64, email: &str) {
+        let mut state = self.state.lock().unwrap();
+        let customer_code = state
+            .customers
+            .get(email)
+            .cloned()
+            .expect("Customer has no dedicated account");
+
+        let mut charge = charge_data(reference, amount_in_kobo, "", email);
+        charge["status"] = json!("success");
+        charge["channel"] = json!("dedicated_nuban");
+        charge["metadata"] = json!({});
+        charge["customer"]["customer_code"] = json!(customer_code);
+        charge["authorization"] = json!({ "channel": "dedicated_nuban", "reusable": false });
+        state.charges.insert(reference.to_string(), charge);
+    }
+
+    // Charges on the card saved from this authorization are declined from now on
+    pub fn decline_authorization(&self, authorization_code: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .declined_authorizations
+            .insert(authorization_code.to_string());
+    }
+
+    // Simulates the customer completing payment on the checkout page
+    pub fn complete_charge(&self, reference: &str) {
+        self.set_charge_status(reference, "success");
+    }
+
+    pub fn fail_charge(&self, reference: &str) {
+        self.set_charge_status(reference, "failed");
+    }
+
+    // Registers a transfer that was made outside of /transfer
+    pub fn add_transfer(&self, reference: &str, amount_in_kobo: i64, status: &str) {
+        self.state.lock().unwrap().transfers.insert(
+            reference.to_string(),
+            transfer_data(reference, amount_in_kobo, status),
+        );
+    }
+
+    pub fn set_transfer_status(&self, reference: &str, status: &str) {
+        let mut state = self.state.lock().unwrap();
+        let transfer = state
+            .transfers
+            .get_mut(reference)
+            .unwrap_or_else(|| panic!("No mock transfer with reference {}", reference));
+        transfer["status"] = json!(status);
+    }
+
+    fn set_charge_status(&self, reference: &str, status: &str) {
+        let mut state = self.state.lock().unwrap();
+        let charge = state
+            .charges
+            .get_mut(reference)
+            .unwrap_or_else(|| panic!("No mock charge with reference {}", reference));
+        charge["status"] = json!(status);
+        if status == "success" {
+            let amount = charge["amount"].as_i64().unwrap_or_default();
+            charge["fees"] = json!((amount * 15 / 1000).min(200000));
+        }
+    }
+
+    // Request bodies received on a route. GET routes record their path and query parameters
+    pub fn requests(&self, route: PaystackRoute) -> Vec<Value> {
+        self.state
+            .lock()
+            .unwrap()
+            .requests
+            .get(&route)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+fn charge_data(reference: &str, amount_in_kobo: i64, user_id: &str, email: &str) -> Value {
+    json!({
+        "reference": reference,
+        "status": "ongoing",
+        "amount": amount_in_kobo,
+        "fees": 0,
+        "currency": "NGN",
+        "metadata": { "user_id": user_id },
+        "customer": { "email": email },
+        // Every customer pays with the same card, so its signature is derived from the email
+        "authorization": {
+            "authorization_code": format!("AUTH_{}", reference),
+            "signature": format!("SIG_{}", email),
+            "channel": "card",
+            "reusable": true,
+            "card_type": "visa",
+            "bank": "Test Bank",
+            "last4": "4081",
+            "exp_month": "12",
+            "exp_year": "2030",
+        },
+    })
+}
+
+fn transfer_data(reference: &str, amount_in_kobo: i64, status: &str) -> Value {
+    json!({
+        "reference": reference,
+        "status": status,
+        "amount": amount_in_kobo,
+        "fee_charged": 1000,
+        "currency": "NGN",
+    })
+}
+
+fn query_params(req: &HttpRequest) -> HashMap<String, String> {
+    web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .map(|query| query.into_inner())
+        .unwrap_or_default()
+}
+
+// Pages through records like Paystack's list endpoints. The from/to filters are not applied
+fn listing(mut records: Vec<Value>, query: &HashMap<String, String>) -> HttpResponse {
+    records.sort_by_key(|record| record["reference"].as_str().unwrap_or_default().to_string());
+    let per_page: usize = query
+        .get("perPage")
+        .and_then(|per_page| per_page.parse().ok())
+        .unwrap_or(50);
+    let page: usize = query
+        .get("page")
+        .and_then(|page| page.parse().ok())
+        .unwrap_or(1);
+    let page_count = records.len().div_ceil(per_page).max(1);
+    let data: Vec<Value> = records
+        .iter()
+        .skip((page - 1) * per_page)
+        .take(per_page)
+        .cloned()
+        .collect();
+
+    HttpResponse::Ok().json(json!({
+        "status": true,
+        "message": "Records retrieved",
+        "data": data,
+        "meta": { "total": records.len(), "page": page, "pageCount": page_count, "perPage": per_page }
+    }))
+}
+
+// Records the request and returns a scripted response if one is queued for the route
+fn intercept(state: &SharedState, route: PaystackRoute, request: Value) -> Option<HttpResponse> {
+    let mut state = state.lock().unwrap();
+    state.requests.entry(route).or_default().push(request);
+
+    let scripted = state.scripted.get_mut(&route)?.pop_front()?;
+    let status = actix_web::http::StatusCode::from_u16(scripted.status)
+        .expect("Invalid scripted status code");
+    match (scripted.raw, scripted.body) {
+        (true, Value::String(body)) => Some(HttpResponse::build(status).body(body)),
+        (_, body) => Some(HttpResponse::build(status).json(body)),
+    }
+}
+
+async fn initialize_transaction(state: SharedState, body: web::Json<Value>) -> HttpResponse {
+    if let Some(response) = intercept(&state, PaystackRoute::InitializeTransaction, body.0.clone())
+    {
+        return response;
+    }
+
+    let reference = body["reference"]
+        .as_str()
+        .map(String::from)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let charge = charge_data(
+        &reference,
+        body["amount"].as_i64().unwrap_or_default(),
+        body["metadata"]["user_id"].as_str().unwrap_or_default(),
+        body["email"].as_str().unwrap_or_default(),
+    );
+    state
+        .lock()
+        .unwrap()
+        .charges
+        .insert(reference.to_string(), charge);
+
+    HttpResponse::Ok().json(json!({
+        "status": true,
+        "message": "Authorization URL created",
+        "data": {
+            "authorization_url": format!("https://checkout.paystack.com/{}", reference),
+            "access_code": format!("access_{}", reference),
+            "reference": reference,
+        }
+    }))
+}
+
+async fn verify_transaction(state: SharedState, path: web::Path<String>) -> HttpResponse {
+    let reference = path.into_inner();
+    let request = json!({ "reference": reference });
+    if let Some(response) = intercept(&state, PaystackRoute::VerifyTransaction, request) {
+        return response;
+    }
+
+    match state.lock().unwrap().charges.get(&reference) {
+        Some(charge) => HttpResponse::Ok().json(json!({
+            "status": true,
+            "message": "Verification successful",
+            "data": charge,
+        })),
+        None => HttpResponse::BadRequest()
+            .json(json!({ "status": false, "message": "Transaction reference not found" })),
+    }
+}
+
+async fn create_customer(state: SharedState, body: web::Json<Value>) -> HttpResponse {
+    if let Some(response) = intercept(&state, PaystackRoute::CreateCustomer, body.0.clone()) {
+        return response;
+    }
+
+    let email = body["email"].as_str().unwrap_or_default().to_string();
+    let mut state = state.lock().unwrap();
+    let next_code = format!("CUS_{}", state.customers.len() + 1);
+    let customer_code = state
+        .customers
+        .entry(email.to_string())
+        .or_insert(next_code);
+
+    HttpResponse::Ok().json(json!({
+        "status": true,
+        "message": "Customer created",
+        "data": { "email": email, "customer_code": customer_code }
+    }))
+}
+
+async fn create_dedicated_account(state: SharedState, body: web::Json<Value>) -> HttpResponse {
+    if let Some(response) = intercept(
+        &state,
+        PaystackRoute::CreateDedicatedAccount,
+        body.0.clone(),
+    ) {
+        return response;
+    }
+
+    let mut state = state.lock().unwrap();
+    let customer_code = body["customer"].as_str().unwrap_or_default();
+    if !state.customers.values().any(|code| code == customer_code) {
+        return HttpResponse::BadRequest()
+            .json(json!({ "status": false, "message": "Customer not found" }));
+    }
+
+    state.dedicated_accounts += 1;
+    let id = state.dedicated_accounts;
+
+    HttpResponse::Ok().json(json!({
+        "status": true,
+        "message": "NUBAN successfully created",
+        "data": {
+            "id": id,
+            "account_name": format!("MONEY TRANSFER/{}", customer_code),
+            "account_number": format!("{:010}", 9100000000_u64 + id as u64),
+            "bank": { "name": "Test Bank", "slug": body["preferred_bank"] },
+            "customer": { "customer_code": customer_code },
+        }
+    }))
+}
+
+// Charges the card behind an authorization from an earlier charge, settling immediately
+async fn charge_authorization(state: SharedState, body: web::Json<Value>) -> HttpResponse {
+    if let Some(response) = intercept(&state, PaystackRoute::ChargeAuthorization, body.0.clone()) {
+        return response;
+    }
+
+    let authorization_code = body["authorization_code"].as_str().unwrap_or_default();
+    let mut state = state.lock().unwrap();
+    let authorization = state
+        .charges
+        .values()
+        .map(|charge| charge["authorization"].clone())
+        .find(|authorization| authorization["authorization_code"] == json!(authorization_code));
+
+    let authorization = match authorization {
+        Some(authorization) => authorization,
+        None => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "status": false, "message": "Invalid authorization code" }))
+        }
+    };
+
+    let reference = body["reference"]
+        .as_str()
+        .map(String::from)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let mut charge = charge_data(
+        &reference,
+        body["amount"].as_i64().unwrap_or_default(),
+        body["metadata"]["user_id"].as_str().unwrap_or_default(),
+        body["email"].as_str().unwrap_or_default(),
+    );
+    charge["authorization"] = authorization;
+    if state.declined_authorizations.contains(authorization_code) {
+        charge["status"] = json!("failed");
+        charge["gateway_response"] = json!("Declined");
+    } else {
+        charge["status"] = json!("success");
+        charge["gateway_response"] = json!("Approved");
+    }
+    state.charges.insert(reference, charge.clone());
+
+    HttpResponse::Ok()
+        .json(json!({ "status": true, "message": "Charge attempted", "data": charge }))
+}
+
+async fn list_transactions(state: SharedState, req: HttpRequest) -> HttpResponse {
+    let query = query_params(&req);
+    if let Some(response) = intercept(&state, PaystackRoute::ListTransactions, json!(query)) {
+        return response;
+    }
+
+    let charges = state.lock().unwrap().charges.values().cloned().collect();
+    listing(charges, &query)
+}
+
+async fn list_transfers(state: SharedState, req: HttpRequest) -> HttpResponse {
+    let query = query_params(&req);
+    if let Some(response) = intercept(&state, PaystackRoute::ListTransfers, json!(query)) {
+        return response;
+    }
+
+    let transfers = state.lock().unwrap().transfers.values().cloned().collect();
+    listing(transfers, &query)
+}
+
+async fn verify_transfer(state: SharedState, path: web::Path<String>) -> HttpResponse {
+    let reference = path.into_inner();
+    let request = json!({ "reference": reference });
+    if let Some(response) = intercept(&state, PaystackRoute::VerifyTransfer, request) {
+        return response;
+    }
+
+    match state.lock().unwrap().transfers.get(&reference) {
+        Some(transfer) => HttpResponse::Ok().json(json!({
+            "status": true,
+            "message": "Transfer retrieved",
+            "data": transfer,
+        })),
+        None => HttpResponse::BadRequest()
+            .json(json!({ "status": false, "message": "Transfer not found" })),
+    }
+}
+
+async fn transfer_recipient(state: SharedState, body: web::Json<Value>) -> HttpResponse {
+    if let Some(response) = intercept(&state, PaystackRoute::TransferRecipient, body.0.clone()) {
+        return response;
+    }
+
+    HttpResponse::Created().json(json!({
+        "status": true,
+        "message": "Transfer recipient created successfully",
+        "data": { "recipient_code": format!("RCP_{}", body["account_number"].as_str().unwrap_or_default()) }
+    }))
+}
+
+async fn transfer(state: SharedState, body: web::Json<Value>) -> HttpResponse {
+    if let Some(response) = intercept(&state, PaystackRoute::Transfer, body.0.clone()) {
+        return response;
+    }
+
+    let reference = body["reference"].as_str().unwrap_or_default().to_string();
+    state.lock().unwrap().transfers.insert(
+        reference.to_string(),
+        transfer_data(
+            &reference,
+            body["amount"].as_i64().unwrap_or_default(),
+            "pending",
+        ),
+    );
+
+    HttpResponse::Ok().json(json!({
+        "status": true,
+        "message": "Transfer has been queued",
+        "data": {
+            "reference": body["reference"],
+            "transfer_code": format!("TRF_{}", Uuid::new_v4().simple()),
+            "status": "pending",
+        }
+    }))
+}
+
+async fn resolve_account(state: SharedState, req: HttpRequest) -> HttpResponse {
+    let query = query_params(&req);
+    let account_number = query.get("account_number").cloned().unwrap_or_default();
+    let bank_code = query.get("bank_code").cloned().unwrap_or_default();
+
+    let request = json!({ "account_number": account_number, "bank_code": bank_code });
+    if let Some(response) = intercept(&state, PaystackRoute::ResolveAccount, request) {
+        return response;
+    }
+
+    let account_name = state
+        .lock()
+        .unwrap()
+        .accounts
+        .get(&(account_number.to_string(), bank_code))
+        .cloned();
+
+    match account_name {
+        Some(account_name) => HttpResponse::Ok().json(json!({
+            "status": true,
+            "message": "Account number resolved",
+            "data": { "account_number": account_number, "account_name": account_name }
+        })),
+        None => HttpResponse::UnprocessableEntity().json(json!({
+            "status": false,
+            "message": "Could not resolve account name. Check parameters or try again."
+        })),
+    }
+}
+
+async fn refund(state: SharedState, body: web::Json<Value>) -> HttpResponse {
+    if let Some(response) = intercept(&state, PaystackRoute::Refund, body.0.clone()) {
+        return response;
+    }
+
+    HttpResponse::Ok().json(json!({
+        "status": true,
+        "message": "Refund has been queued for processing",
+        "data": { "id": 3018284, "transaction": { "reference": body["transaction"] } }
+    }))
+}
@@ -0,0 +1,87 @@
+use actix_web::{dev::ServerHandle, web, App, HttpRequest, HttpResponse, HttpServer};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+#[derive(Clone, Debug)]
+pub struct ReceivedWebhook {
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+#[derive(Default)]
+struct ReceiverState {
+    // Status codes to answer the next requests with
+    failures: VecDeque<u16>,
+    received: Vec<ReceivedWebhook>,
+}
+
+type SharedState = web::Data<Mutex<ReceiverState>>;
+
+// An integrator's endpoint, recording every webhook that reaches it
+pub struct MockWebhookReceiver {
+    pub url: String,
+    state: SharedState,
+    handle: ServerHandle,
+}
+
+impl MockWebhookReceiver {
+    pub async fn start() -> MockWebhookReceiver {
+        let state: SharedState = web::Data::new(Mutex::new(ReceiverState::default()));
+        let server_state = state.clone();
+
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(server_state.clone())
+                .route("/hooks", web::post().to(receive))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .expect("Failed to bind mock webhook receiver");
+
+        let address = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        MockWebhookReceiver {
+            url: format!("http://{}/hooks", address),
+            state,
+            handle,
+        }
+    }
+
+    pub async fn stop(self) {
+        self.handle.stop(false).await;
+    }
+
+    pub fn fail_next(&self, status: u16) {
+        self.state.lock().unwrap().failures.push_back(status);
+    }
+
+    pub fn received(&self) -> Vec<ReceivedWebhook> {
+        self.state.lock().unwrap().received.clone()
+    }
+}
+
+async fn receive(state: SharedState, req: HttpRequest, body: String) -> HttpResponse {
+    let mut state = state.lock().unwrap();
+
+    let headers = req
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            let value = value.to_str().unwrap_or_default().to_string();
+            (name.as_str().to_string(), value)
+        })
+        .collect();
+    state.received.push(ReceivedWebhook { headers, body });
+
+    match state.failures.pop_front() {
+        Some(status) => {
+            let status = actix_web::http::StatusCode::from_u16(status)
+                .expect("Invalid scripted status code");
+            HttpResponse::build(status).body("Mock webhook failure")
+        }
+        None => HttpResponse::Ok().body("ok"),
+    }
+}
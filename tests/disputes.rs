@@ -0,0 +1,271 @@
+mod common;
+
+use actix_http::Request;
+use actix_web::{
+    body::MessageBody,
+    dev::{Service, ServiceResponse},
+    http::StatusCode,
+    test, web, App,
+};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::{json, Value};
+
+use common::{
+    authorized, call, seed_payer, sqlite_app_state, test_env, wallet_of, wallet_transactions, PIN,
+};
+use money_transfer::entities::{
+    prelude::Transactions,
+    sea_orm_active_enums::Status,
+    transactions,
+    users::{self, UserRole},
+};
+use money_transfer::{configure_app, AppState};
+
+async fn seed_support(app_state: &AppState) -> users::Model {
+    let support = seed_payer(app_state, "Sam", 0).await;
+    let mut support: users::ActiveModel = support.into();
+    support.role = Set(UserRole::Support.to_string());
+    support.update(&app_state.db).await.unwrap()
+}
+
+async fn send<S, B>(
+    app: &S,
+    app_state: &AppState,
+    sender: &users::Model,
+    receiver: &users::Model,
+    amount: u64,
+) -> StatusCode
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let request = test::TestRequest::post()
+        .uri("/api/transfer/p2p")
+        .set_json(json!({ "amount": amount, "pin": PIN, "receiver_id": &receiver.uuid }));
+
+    call(app, authorized(request, app_state, sender)).await.0
+}
+
+// Sends money to the counterparty and disputes the transfer
+async fn disputed_transfer<S, B>(
+    app: &S,
+    app_state: &AppState,
+    customer: &users::Model,
+    counterparty: &users::Model,
+    amount: u64,
+) -> (transactions::Model, String)
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    assert_eq!(
+        send(app, app_state, customer, counterparty, amount).await,
+        StatusCode::OK
+    );
+    let wallet = wallet_of(&app_state.db, customer).await;
+    let debit = wallet_transactions(&app_state.db, &wallet).await.remove(0);
+
+    let request = test::TestRequest::post()
+        .uri("/api/dispute")
+        .set_json(json!({
+            "transaction_id": &debit.uuid,
+            "reason": "I was charged for goods that never arrived",
+        }));
+    let (status, body) = call(app, authorized(request, app_state, customer)).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    assert_eq!(body["data"]["dispute"]["status"], "open");
+
+    let dispute_id = body["data"]["dispute"]["uuid"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    (debit, dispute_id)
+}
+
+async fn support_action<S, B>(
+    app: &S,
+    app_state: &AppState,
+    support: &users::Model,
+    dispute_id: &str,
+    action: &str,
+    body: Value,
+) -> (StatusCode, Value)
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let request = match action {
+        "investigate" => test::TestRequest::patch(),
+        _ => test::TestRequest::post(),
+    }
+    .uri(&format!("/api/support/disputes/{}/{}", dispute_id, action))
+    .set_json(body);
+
+    call(app, authorized(request, app_state, support)).await
+}
+
+#[actix_web::test]
+async fn disputes_resolved_for_the_customer_reverse_the_held_transfer() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let support = seed_support(&app_state).await;
+    let ada = seed_payer(&app_state, "Ada", 10000).await;
+    let bola = seed_payer(&app_state, "Bola", 0).await;
+    let chi = seed_payer(&app_state, "Chi", 0).await;
+
+    let (debit, dispute_id) = disputed_transfer(&app, &app_state, &ada, &bola, 4000).await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/dispute")
+        .set_json(json!({
+            "transaction_id": &debit.uuid,
+            "reason": "Opening the same dispute twice",
+        }));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body["message"],
+        "There is already an open dispute on this transaction"
+    );
+
+    // Customers can't act on their own disputes, and others can't see them
+    let (status, _) = support_action(
+        &app,
+        &app_state,
+        &ada,
+        &dispute_id,
+        "investigate",
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let request = test::TestRequest::get().uri(&format!("/api/dispute/{}", dispute_id));
+    let (status, _) = call(&app, authorized(request, &app_state, &chi)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = support_action(
+        &app,
+        &app_state,
+        &support,
+        &dispute_id,
+        "investigate",
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["dispute"]["status"], "investigating");
+    assert_eq!(body["data"]["dispute"]["assigned_to"], json!(&support.uuid));
+
+    // Bola can't move the disputed money while it is held
+    let (status, body) =
+        support_action(&app, &app_state, &support, &dispute_id, "hold", json!({})).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(
+        send(&app, &app_state, &bola, &chi, 1000).await,
+        StatusCode::BAD_REQUEST
+    );
+
+    let (status, body) = support_action(
+        &app,
+        &app_state,
+        &support,
+        &dispute_id,
+        "resolve",
+        json!({ "in_favour_of": "customer", "note": "Seller could not show delivery" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["dispute"]["status"], "resolved");
+    assert_eq!(body["data"]["dispute"]["resolved_in_favour_of"], "customer");
+    assert!(body["data"]["dispute"]["reversal_id"].is_string());
+
+    assert_eq!(
+        wallet_of(&app_state.db, &ada).await.current_balance,
+        Decimal::from(10000)
+    );
+    assert_eq!(
+        wallet_of(&app_state.db, &bola).await.current_balance,
+        Decimal::ZERO
+    );
+    let reversed = Transactions::find()
+        .filter(transactions::Column::Uuid.eq(&debit.uuid))
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reversed.status, Some(Status::Reversed));
+
+    let (status, body) = support_action(
+        &app,
+        &app_state,
+        &support,
+        &dispute_id,
+        "reject",
+        json!({ "note": "Too late to change our minds" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "Dispute is already resolved");
+}
+
+#[actix_web::test]
+async fn rejected_disputes_release_the_hold_and_leave_the_transfer_alone() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let support = seed_support(&app_state).await;
+    let ada = seed_payer(&app_state, "Ada", 10000).await;
+    let bola = seed_payer(&app_state, "Bola", 0).await;
+    let chi = seed_payer(&app_state, "Chi", 0).await;
+
+    let (debit, dispute_id) = disputed_transfer(&app, &app_state, &ada, &bola, 4000).await;
+
+    let (status, _) =
+        support_action(&app, &app_state, &support, &dispute_id, "hold", json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) =
+        support_action(&app, &app_state, &support, &dispute_id, "hold", json!({})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body["message"],
+        "Funds have already been held for this dispute"
+    );
+
+    let (status, body) = support_action(
+        &app,
+        &app_state,
+        &support,
+        &dispute_id,
+        "reject",
+        json!({ "note": "Delivery was confirmed by the courier" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["dispute"]["status"], "rejected");
+
+    assert_eq!(
+        send(&app, &app_state, &bola, &chi, 1000).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        wallet_of(&app_state.db, &ada).await.current_balance,
+        Decimal::from(6000)
+    );
+    let request = test::TestRequest::get().uri(&format!("/api/dispute/{}", dispute_id));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body["data"]["dispute"]["transaction_id"],
+        json!(&debit.uuid)
+    );
+}
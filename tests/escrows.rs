@@ -0,0 +1,293 @@
+mod common;
+
+use actix_http::Request;
+use actix_web::{
+    body::MessageBody,
+    dev::{Service, ServiceResponse},
+    http::StatusCode,
+    test, web, App,
+};
+use chrono::{Duration, Utc};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::json;
+
+use common::{
+    amount, authorized, call, seed_payer, seed_user, sqlite_app_state, test_env, wallet_of,
+    wallet_transactions, PIN,
+};
+use money_transfer::entities::{
+    escrows,
+    prelude::{Escrows, Wallets},
+    users::{self, UserRole},
+};
+use money_transfer::service::escrow::{process_due_escrows, ESCROW_WALLET_ID};
+use money_transfer::service::wallet_reconciliation::find_mismatches;
+use money_transfer::{configure_app, AppState};
+
+async fn escrow_balance(db: &DatabaseConnection) -> Decimal {
+    Wallets::find_by_id(ESCROW_WALLET_ID)
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+        .current_balance
+}
+
+async fn open_escrow<S, B>(
+    app: &S,
+    app_state: &AppState,
+    buyer: &users::Model,
+    seller: &users::Model,
+    amount: u64,
+) -> String
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let request = test::TestRequest::post()
+        .uri("/api/escrows")
+        .set_json(json!({
+            "amount": amount,
+            "pin": PIN,
+            "seller_id": &seller.uuid,
+            "description": "Used laptop",
+        }));
+    let (status, body) = call(app, authorized(request, app_state, buyer)).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+
+    body["data"]["escrow"]["uuid"].as_str().unwrap().to_string()
+}
+
+#[actix_web::test]
+async fn escrows_hold_funds_until_the_buyer_releases_them() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let buyer = seed_payer(&app_state, "Ada", 10000).await;
+    let seller = seed_payer(&app_state, "Bola", 0).await;
+
+    let escrow_id = open_escrow(&app, &app_state, &buyer, &seller, 4000).await;
+
+    // The money has left the buyer without reaching the seller
+    assert_eq!(
+        wallet_of(&app_state.db, &buyer).await.current_balance,
+        Decimal::from(6000)
+    );
+    assert_eq!(
+        wallet_of(&app_state.db, &seller).await.current_balance,
+        Decimal::ZERO
+    );
+    assert_eq!(escrow_balance(&app_state.db).await, Decimal::from(4000));
+
+    // Only the seller can mark it delivered, and only the buyer can release it
+    let request = test::TestRequest::patch().uri(&format!("/api/escrows/{}/deliver", escrow_id));
+    let (status, _) = call(&app, authorized(request, &app_state, &buyer)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let request = test::TestRequest::patch().uri(&format!("/api/escrows/{}/deliver", escrow_id));
+    let (status, body) = call(&app, authorized(request, &app_state, &seller)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["escrow"]["status"], "delivered");
+    assert!(body["data"]["escrow"]["auto_release_at"].is_string());
+
+    let release = || {
+        test::TestRequest::patch()
+            .uri(&format!("/api/escrows/{}/release", escrow_id))
+            .set_json(json!({ "pin": PIN }))
+    };
+    let (status, _) = call(&app, authorized(release(), &app_state, &seller)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = call(&app, authorized(release(), &app_state, &buyer)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["escrow"]["status"], "released");
+
+    // A released escrow can't be released or refunded again
+    let (status, _) = call(&app, authorized(release(), &app_state, &buyer)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let request = test::TestRequest::patch().uri(&format!("/api/escrows/{}/refund", escrow_id));
+    let (status, _) = call(&app, authorized(request, &app_state, &seller)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let seller_wallet = wallet_of(&app_state.db, &seller).await;
+    assert_eq!(seller_wallet.current_balance, Decimal::from(4000));
+    assert_eq!(
+        wallet_of(&app_state.db, &buyer).await.current_balance,
+        Decimal::from(6000)
+    );
+    assert_eq!(escrow_balance(&app_state.db).await, Decimal::ZERO);
+
+    // Each leg is posted to the ledger, debit and credit referencing each other
+    let escrow = Escrows::find_by_id(&escrow_id)
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let credits = wallet_transactions(&app_state.db, &seller_wallet).await;
+    assert_eq!(credits.len(), 1);
+    assert_eq!(credits[0].category, "escrow");
+    assert_eq!(
+        Some(credits[0].uuid.clone()),
+        escrow.settlement_transaction_id
+    );
+
+    let escrow_wallet = Wallets::find_by_id(ESCROW_WALLET_ID)
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let escrow_legs = wallet_transactions(&app_state.db, &escrow_wallet).await;
+    assert_eq!(escrow_legs.len(), 2);
+    assert_eq!(
+        escrow_legs[1].provider_reference,
+        Some(credits[0].uuid.clone())
+    );
+    assert!(find_mismatches(&seller_wallet, &credits).is_empty());
+    assert!(find_mismatches(&escrow_wallet, &escrow_legs).is_empty());
+
+    // Both sides can see the escrow
+    let request = test::TestRequest::get().uri("/api/escrows");
+    let (status, body) = call(&app, authorized(request, &app_state, &seller)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["escrows"].as_array().unwrap().len(), 1);
+    assert_eq!(
+        amount(&body["data"]["escrows"][0]["amount"]),
+        Decimal::from(4000)
+    );
+
+    // More than the buyer has left can't be put in escrow
+    let request = test::TestRequest::post()
+        .uri("/api/escrows")
+        .set_json(json!({
+            "amount": 7000,
+            "pin": PIN,
+            "seller_id": &seller.uuid,
+            "description": "Another laptop",
+        }));
+    let (status, body) = call(&app, authorized(request, &app_state, &buyer)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "Insufficient funds");
+}
+
+#[actix_web::test]
+async fn disputed_escrows_are_settled_by_support() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let buyer = seed_payer(&app_state, "Ada", 10000).await;
+    let seller = seed_payer(&app_state, "Bola", 0).await;
+    let (support, _) = seed_user(&app_state.db, "Sam").await;
+    let mut support: users::ActiveModel = support.into();
+    support.role = Set(UserRole::Support.to_string());
+    let support = support.update(&app_state.db).await.unwrap();
+
+    let escrow_id = open_escrow(&app, &app_state, &buyer, &seller, 2500).await;
+
+    let request = test::TestRequest::patch().uri(&format!("/api/escrows/{}/deliver", escrow_id));
+    call(&app, authorized(request, &app_state, &seller)).await;
+
+    let request = test::TestRequest::patch()
+        .uri(&format!("/api/escrows/{}/dispute", escrow_id))
+        .set_json(json!({ "reason": "The laptop never arrived" }));
+    let (status, body) = call(&app, authorized(request, &app_state, &buyer)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["escrow"]["status"], "disputed");
+    assert!(body["data"]["escrow"]["auto_release_at"].is_null());
+
+    // Disputed funds stay put until support steps in
+    let request = test::TestRequest::patch()
+        .uri(&format!("/api/escrows/{}/release", escrow_id))
+        .set_json(json!({ "pin": PIN }));
+    let (status, _) = call(&app, authorized(request, &app_state, &buyer)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let resolve = || {
+        test::TestRequest::post()
+            .uri(&format!("/api/support/escrows/{}/resolve", escrow_id))
+            .set_json(json!({ "in_favour_of": "buyer", "note": "Courier confirmed it was lost" }))
+    };
+    let (status, body) = call(&app, authorized(resolve(), &app_state, &support)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["escrow"]["status"], "refunded");
+    assert_eq!(body["data"]["escrow"]["resolved_by"], support.uuid.as_str());
+
+    assert_eq!(
+        wallet_of(&app_state.db, &buyer).await.current_balance,
+        Decimal::from(10000)
+    );
+    assert_eq!(
+        wallet_of(&app_state.db, &seller).await.current_balance,
+        Decimal::ZERO
+    );
+    assert_eq!(escrow_balance(&app_state.db).await, Decimal::ZERO);
+
+    let (status, _) = call(&app, authorized(resolve(), &app_state, &support)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn delivered_escrows_are_released_once_the_buyer_runs_out_of_time() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let buyer = seed_payer(&app_state, "Ada", 10000).await;
+    let seller = seed_payer(&app_state, "Bola", 0).await;
+
+    let delivered = open_escrow(&app, &app_state, &buyer, &seller, 3000).await;
+    let undelivered = open_escrow(&app, &app_state, &buyer, &seller, 1000).await;
+
+    let request = test::TestRequest::patch().uri(&format!("/api/escrows/{}/deliver", delivered));
+    call(&app, authorized(request, &app_state, &seller)).await;
+
+    // Nothing is due before the auto-release time
+    let summary = process_due_escrows(&app_state).await.unwrap();
+    assert_eq!(summary.released, 0);
+
+    let escrow = Escrows::find_by_id(&delivered)
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut due: escrows::ActiveModel = escrow.into();
+    due.auto_release_at = Set(Some(Utc::now() - Duration::minutes(1)));
+    due.update(&app_state.db).await.unwrap();
+
+    let summary = process_due_escrows(&app_state).await.unwrap();
+    assert_eq!((summary.released, summary.failed), (1, 0));
+
+    let released = Escrows::find_by_id(&delivered)
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(released.status, "released");
+    assert_eq!(
+        wallet_of(&app_state.db, &seller).await.current_balance,
+        Decimal::from(3000)
+    );
+    assert_eq!(escrow_balance(&app_state.db).await, Decimal::from(1000));
+
+    // Already settled, so the next run leaves it alone
+    let summary = process_due_escrows(&app_state).await.unwrap();
+    assert_eq!(summary.released, 0);
+
+    let pending = Escrows::find_by_id(&undelivered)
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(pending.status, "funded");
+}
@@ -0,0 +1,213 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, web, App};
+use argonautica::Hasher;
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::json;
+
+use common::flutterwave_mock::{MockFlutterwave, FLUTTERWAVE_SECRET, FLUTTERWAVE_SECRET_HASH};
+use common::{
+    authorized, call, flutterwave_webhook_request, seed_payer, sqlite_app_state, test_env,
+    wallet_of, PIN,
+};
+use money_transfer::entities::{
+    prelude::Transactions, sea_orm_active_enums::Status, transactions, users,
+};
+use money_transfer::service::funding::find_funding;
+use money_transfer::utils::config::EnvConfig;
+use money_transfer::{configure_app, AppState};
+
+const PASSWORD: &str = "correct-horse";
+
+// Paystack can't be reached, Flutterwave is the fallback
+fn failover_env(flutterwave: &MockFlutterwave) -> EnvConfig {
+    let mut env = test_env("http://127.0.0.1:1");
+    env.flutterwave_base_url = flutterwave.base_url.to_string();
+    env.flutterwave_secret = String::from(FLUTTERWAVE_SECRET);
+    env.flutterwave_secret_hash = String::from(FLUTTERWAVE_SECRET_HASH);
+    env.charge_providers = vec![String::from("paystack"), String::from("flutterwave")];
+    env.transfer_providers = vec![String::from("paystack"), String::from("flutterwave")];
+    env
+}
+
+async fn with_password(app_state: &AppState, user: users::Model) -> users::Model {
+    let password = Hasher::default()
+        .with_password(PASSWORD)
+        .with_secret_key(&app_state.env.hash_key)
+        .hash()
+        .unwrap();
+    let mut user: users::ActiveModel = user.into();
+    user.password = Set(password);
+    user.update(&app_state.db).await.unwrap()
+}
+
+fn charge_completed(tx_ref: &str) -> serde_json::Value {
+    json!({
+        "event": "charge.completed",
+        "data": { "tx_ref": tx_ref, "status": "successful" }
+    })
+}
+
+#[actix_web::test]
+async fn fundings_fail_over_to_flutterwave_and_are_credited_from_its_webhook() {
+    let flutterwave = MockFlutterwave::start().await;
+    let app_state = sqlite_app_state(failover_env(&flutterwave)).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let ada = seed_payer(&app_state, "Ada", 0).await;
+    let ada = with_password(&app_state, ada).await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/transfer/fund-account")
+        .set_json(json!({ "amount": 5000, "password": PASSWORD }));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["provider"], "flutterwave");
+    let reference = body["data"]["reference"].as_str().unwrap().to_string();
+
+    let pending = find_funding(&app_state.db, &reference)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(pending.provider, "flutterwave");
+    assert_eq!(pending.status, Some(Status::Pending));
+
+    // A webhook without the dashboard hash is acknowledged and ignored
+    let (status, _) = call(
+        &app,
+        flutterwave_webhook_request("not-the-hash", &charge_completed(&reference)).to_request(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The payload alone is not trusted, the charge is verified with Flutterwave first
+    let (status, _) = call(
+        &app,
+        flutterwave_webhook_request(FLUTTERWAVE_SECRET_HASH, &charge_completed(&reference))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        wallet_of(&app_state.db, &ada).await.current_balance,
+        Decimal::ZERO
+    );
+
+    flutterwave.complete_charge(&reference);
+    for _ in 0..2 {
+        let (status, body) = call(
+            &app,
+            flutterwave_webhook_request(FLUTTERWAVE_SECRET_HASH, &charge_completed(&reference))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+    }
+
+    assert_eq!(
+        wallet_of(&app_state.db, &ada).await.current_balance,
+        Decimal::from(5000)
+    );
+    let funding = find_funding(&app_state.db, &reference)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(funding.status, Some(Status::Successful));
+
+    flutterwave.stop().await;
+}
+
+#[actix_web::test]
+async fn transfers_sent_through_flutterwave_are_settled_by_its_webhook() {
+    let flutterwave = MockFlutterwave::start().await;
+    flutterwave.add_account("0123456789", "058", "ADA OKAFOR");
+    let app_state = sqlite_app_state(failover_env(&flutterwave)).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let ada = seed_payer(&app_state, "Ada", 10000).await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/transfer/batch")
+        .set_json(json!({
+            "pin": PIN,
+            "items": [
+                { "account_number": "0123456789", "bank_code": "058", "amount": 3000 },
+                { "account_number": "0123456789", "bank_code": "058", "amount": 2000 },
+            ]
+        }));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{}", body);
+
+    // Wait for the background run to hand both transfers to Flutterwave
+    let batch_id = body["data"]["batch"]["uuid"].as_str().unwrap().to_string();
+    for _ in 0..100 {
+        let request = test::TestRequest::get().uri(&format!("/api/transfer/batch/{}", batch_id));
+        let (_, body) = call(&app, authorized(request, &app_state, &ada)).await;
+        if body["data"]["batch"]["status"] != "processing" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert_eq!(flutterwave.transfer_count(), 2);
+    let debits = Transactions::find()
+        .filter(transactions::Column::UserId.eq(&ada.uuid))
+        .filter(transactions::Column::Provider.eq("flutterwave"))
+        .order_by_asc(transactions::Column::Id)
+        .all(&app_state.db)
+        .await
+        .unwrap();
+    assert_eq!(debits.len(), 2);
+
+    for (debit, status) in debits.iter().zip(["SUCCESSFUL", "FAILED"]) {
+        let payload = json!({
+            "event": "transfer.completed",
+            "data": {
+                "reference": &debit.uuid,
+                "status": status,
+                "complete_message": "Beneficiary bank unavailable",
+            }
+        });
+        let (code, body) = call(
+            &app,
+            flutterwave_webhook_request(FLUTTERWAVE_SECRET_HASH, &payload).to_request(),
+        )
+        .await;
+        assert_eq!(code, StatusCode::OK, "{}", body);
+    }
+
+    let settled = |uuid: String| {
+        let db = app_state.db.clone();
+        async move {
+            Transactions::find()
+                .filter(transactions::Column::Uuid.eq(uuid))
+                .one(&db)
+                .await
+                .unwrap()
+                .unwrap()
+                .status
+        }
+    };
+    assert_eq!(
+        settled(debits[0].uuid.to_string()).await,
+        Some(Status::Successful)
+    );
+    assert_eq!(
+        settled(debits[1].uuid.to_string()).await,
+        Some(Status::Failed)
+    );
+    assert_eq!(
+        wallet_of(&app_state.db, &ada).await.current_balance,
+        Decimal::from(7000)
+    );
+
+    flutterwave.stop().await;
+}
@@ -0,0 +1,390 @@
+mod common;
+
+use actix_http::Request;
+use actix_web::{
+    body::MessageBody,
+    dev::{Service, ServiceResponse},
+    http::StatusCode,
+    test, web, App,
+};
+use chrono::{Duration, Utc};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::{json, Value};
+
+use common::paystack_mock::MockPaystack;
+use common::{
+    amount, authorized, call, charge_success_event, paystack_webhook_request, seed_payer,
+    seed_user, sqlite_app_state, test_env, wallet_of, PIN,
+};
+use money_transfer::entities::{
+    invoices, outbox_messages,
+    prelude::{Invoices, OutboxMessages},
+    users,
+};
+use money_transfer::service::invoice::process_overdue_invoices;
+use money_transfer::service::merchant::merchant_fee;
+use money_transfer::utils::send_email::SendEmail;
+use money_transfer::{configure_app, AppState};
+
+// Invoice emails go through the outbox since customers don't need an account
+async fn queued_emails(db: &DatabaseConnection) -> Vec<SendEmail> {
+    OutboxMessages::find()
+        .filter(outbox_messages::Column::Kind.eq("email"))
+        .order_by_asc(outbox_messages::Column::Id)
+        .all(db)
+        .await
+        .unwrap()
+        .iter()
+        .map(|message| serde_json::from_str(&message.payload).unwrap())
+        .collect()
+}
+
+// Returns the invoice's slug
+async fn issue_invoice<S, B>(
+    app: &S,
+    app_state: &AppState,
+    merchant: &users::Model,
+    invoice: Value,
+) -> String
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let request = test::TestRequest::post()
+        .uri("/api/merchant")
+        .set_json(json!({ "business_name": "Shop Ventures" }));
+    call(app, authorized(request, app_state, merchant)).await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/merchant/invoices")
+        .set_json(invoice);
+    let (status, body) = call(app, authorized(request, app_state, merchant)).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+
+    body["data"]["invoice"]["slug"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+fn consulting_invoice(due_date: chrono::NaiveDate) -> Value {
+    json!({
+        "customer_name": "Ada Customer",
+        "customer_email": "ada@example.com",
+        "tax_rate": 7.5,
+        "due_date": due_date,
+        "notes": "Thank you for your business",
+        "items": [
+            { "description": "Consulting", "quantity": 2, "unit_price": 3000 },
+            { "description": "Setup", "quantity": 1, "unit_price": 4000 }
+        ]
+    })
+}
+
+#[actix_web::test]
+async fn invoices_are_emailed_and_paid_in_parts_from_a_wallet() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let shop = seed_payer(&app_state, "Shop", 0).await;
+    let ada = seed_payer(&app_state, "Ada", 20000).await;
+    let due_date = Utc::now().date_naive() + Duration::days(14);
+
+    let request = test::TestRequest::post()
+        .uri("/api/merchant")
+        .set_json(json!({ "business_name": "Shop Ventures" }));
+    call(&app, authorized(request, &app_state, &shop)).await;
+    let mut in_dollars = consulting_invoice(due_date);
+    in_dollars["currency"] = json!("USD");
+    let request = test::TestRequest::post()
+        .uri("/api/merchant/invoices")
+        .set_json(in_dollars);
+    let (status, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], json!("Invoices can only be issued in NGN"));
+
+    let slug = issue_invoice(&app, &app_state, &shop, consulting_invoice(due_date)).await;
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/invoices/{}", slug))
+        .to_request();
+    let (status, body) = call(&app, request).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let invoice = &body["data"]["invoice"];
+    assert_eq!(invoice["number"], json!("INV-000001"));
+    assert_eq!(invoice["business_name"], json!("Shop Ventures"));
+    assert_eq!(invoice["items"].as_array().unwrap().len(), 2);
+    assert_eq!(amount(&invoice["subtotal"]), Decimal::from(10000));
+    assert_eq!(amount(&invoice["tax_amount"]), Decimal::from(750));
+    assert_eq!(amount(&invoice["total"]), Decimal::from(10750));
+    assert_eq!(invoice["overdue"], json!(false));
+
+    let emails = queued_emails(&app_state.db).await;
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].to, "ada@example.com");
+    assert_eq!(emails[0].subject, "Invoice INV-000001 from Shop Ventures");
+    assert!(emails[0]
+        .text
+        .as_ref()
+        .unwrap()
+        .contains(&format!("/api/invoices/{}/document", slug)));
+
+    // Part of it first, then whatever is left
+    let pay_uri = format!("/api/invoices/{}/wallet", slug);
+    let request = test::TestRequest::post()
+        .uri(&pay_uri)
+        .set_json(json!({ "pin": PIN, "amount": 4000 }));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["invoice_status"], json!("partially_paid"));
+    assert_eq!(amount(&body["data"]["balance_due"]), Decimal::from(6750));
+
+    let request = test::TestRequest::post()
+        .uri(&pay_uri)
+        .set_json(json!({ "pin": PIN, "amount": 7000 }));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Amount is more than the 6750"));
+
+    let request = test::TestRequest::post()
+        .uri(&pay_uri)
+        .set_json(json!({ "pin": PIN }));
+    let (status, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], json!("Cannot pay your own invoice"));
+
+    let request = test::TestRequest::post()
+        .uri(&pay_uri)
+        .set_json(json!({ "pin": PIN }));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["invoice_status"], json!("paid"));
+
+    let request = test::TestRequest::post()
+        .uri(&pay_uri)
+        .set_json(json!({ "pin": PIN }));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], json!("Invoice has already been paid"));
+
+    let ada_wallet = wallet_of(&app_state.db, &ada).await;
+    assert_eq!(ada_wallet.current_balance, Decimal::from(20000 - 10750));
+    let fees = merchant_fee(&app_state.env, Decimal::from(4000))
+        + merchant_fee(&app_state.env, Decimal::from(6750));
+    let shop_wallet = wallet_of(&app_state.db, &shop).await;
+    assert_eq!(shop_wallet.current_balance, Decimal::from(10750) - fees);
+
+    // A receipt for each payment
+    let emails = queued_emails(&app_state.db).await;
+    assert_eq!(emails.len(), 3);
+    assert_eq!(emails[2].subject, "Payment received for invoice INV-000001");
+    assert!(emails[2]
+        .text
+        .as_ref()
+        .unwrap()
+        .contains("The invoice is now fully paid"));
+
+    let stored = Invoices::find().one(&app_state.db).await.unwrap().unwrap();
+    let request = test::TestRequest::get().uri("/api/merchant/invoices");
+    let (_, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(body["data"]["invoices"].as_array().unwrap().len(), 1);
+    let request = test::TestRequest::get().uri(&format!("/api/merchant/invoices/{}", stored.uuid));
+    let (_, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(body["data"]["payments"].as_array().unwrap().len(), 2);
+
+    let request =
+        test::TestRequest::patch().uri(&format!("/api/merchant/invoices/{}/cancel", stored.uuid));
+    let (status, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body["message"],
+        json!("Only invoices without payments can be cancelled")
+    );
+}
+
+#[actix_web::test]
+async fn invoices_render_as_a_page_and_a_pdf() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let (shop, _) = seed_user(&app_state.db, "Shop").await;
+    let mut in_french = consulting_invoice(Utc::now().date_naive());
+    in_french["locale"] = json!("fr");
+    in_french["items"][1]["description"] = json!("<script>Setup</script>");
+    let slug = issue_invoice(&app, &app_state, &shop, in_french).await;
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/invoices/{}/document", slug))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let page = String::from_utf8(test::read_body(response).await.to_vec()).unwrap();
+    assert!(page.contains("Facture INV-000001"));
+    assert!(page.contains("Consulting"));
+    assert!(page.contains("&lt;script&gt;Setup&lt;&#x2F;script&gt;"));
+    assert!(page.contains("NGN 10750.00"));
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/invoices/{}/pdf", slug))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/pdf"
+    );
+    let pdf = test::read_body(response).await;
+    assert!(pdf.starts_with(b"%PDF-"));
+    let pdf = String::from_utf8_lossy(&pdf);
+    assert!(pdf.contains("Consulting"));
+
+    let request = test::TestRequest::get()
+        .uri("/api/invoices/unknown/pdf")
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Cancelled invoices can still be looked at but not paid
+    let invoice = Invoices::find().one(&app_state.db).await.unwrap().unwrap();
+    let request =
+        test::TestRequest::patch().uri(&format!("/api/merchant/invoices/{}/cancel", invoice.uuid));
+    let (status, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["invoice"]["status"], json!("cancelled"));
+
+    let request = test::TestRequest::post()
+        .uri(&format!("/api/invoices/{}/card", slug))
+        .set_json(json!({}))
+        .to_request();
+    let (status, body) = call(&app, request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], json!("Invoice has been cancelled"));
+}
+
+#[actix_web::test]
+async fn card_payments_settle_against_the_invoice() {
+    let mock = MockPaystack::start().await;
+    let app_state = sqlite_app_state(test_env(&mock.base_url)).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let (shop, _) = seed_user(&app_state.db, "Shop").await;
+    let due_date = Utc::now().date_naive() + Duration::days(7);
+    let slug = issue_invoice(&app, &app_state, &shop, consulting_invoice(due_date)).await;
+
+    let request = test::TestRequest::post()
+        .uri(&format!("/api/invoices/{}/card", slug))
+        .set_json(json!({ "amount": 5000 }))
+        .to_request();
+    let (status, body) = call(&app, request).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(
+        body["data"]["payment"]["payer_email"],
+        json!("ada@example.com")
+    );
+    let reference = body["data"]["payment"]["reference"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    mock.complete_charge(&reference);
+    let webhook = charge_success_event(&reference, 500000);
+    for _ in 0..2 {
+        let request = paystack_webhook_request(common::PAYSTACK_SECRET, &webhook).to_request();
+        let (status, _) = call(&app, request).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/invoices/{}/payments/{}", slug, reference))
+        .to_request();
+    let (_, body) = call(&app, request).await;
+    assert_eq!(body["data"]["payment"]["status"], json!("successful"));
+
+    let invoice = Invoices::find().one(&app_state.db).await.unwrap().unwrap();
+    assert_eq!(invoice.status, "partially_paid");
+    assert_eq!(invoice.amount_paid, Decimal::from(5000));
+    assert_eq!(invoice.balance_due(), Decimal::from(5750));
+
+    let fee = merchant_fee(&app_state.env, Decimal::from(5000));
+    let shop_wallet = wallet_of(&app_state.db, &shop).await;
+    assert_eq!(shop_wallet.current_balance, Decimal::from(5000) - fee);
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn overdue_invoices_get_a_limited_number_of_reminders() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let (shop, _) = seed_user(&app_state.db, "Shop").await;
+    let today = Utc::now().date_naive();
+    issue_invoice(&app, &app_state, &shop, consulting_invoice(today)).await;
+
+    // Not overdue until the due date has passed
+    let summary = process_overdue_invoices(&app_state).await.unwrap();
+    assert_eq!(summary.reminded, 0);
+
+    let invoice = Invoices::find().one(&app_state.db).await.unwrap().unwrap();
+    let mut overdue: invoices::ActiveModel = invoice.into();
+    overdue.due_date = Set(today - Duration::days(1));
+    overdue.update(&app_state.db).await.unwrap();
+
+    let summary = process_overdue_invoices(&app_state).await.unwrap();
+    assert_eq!(summary.reminded, 1);
+    let emails = queued_emails(&app_state.db).await;
+    assert_eq!(
+        emails.last().unwrap().subject,
+        "Reminder: invoice INV-000001 from Shop Ventures is overdue"
+    );
+
+    // Only once per reminder interval, up to the max
+    let summary = process_overdue_invoices(&app_state).await.unwrap();
+    assert_eq!(summary.reminded, 0);
+
+    for _ in 1..app_state.env.invoice_max_reminders {
+        let invoice = Invoices::find().one(&app_state.db).await.unwrap().unwrap();
+        let mut reminded_earlier: invoices::ActiveModel = invoice.into();
+        reminded_earlier.last_reminded_at = Set(Some(
+            Utc::now() - Duration::hours(app_state.env.invoice_reminder_every_hours + 1),
+        ));
+        reminded_earlier.update(&app_state.db).await.unwrap();
+
+        let summary = process_overdue_invoices(&app_state).await.unwrap();
+        assert_eq!(summary.reminded, 1);
+    }
+
+    let invoice = Invoices::find().one(&app_state.db).await.unwrap().unwrap();
+    assert_eq!(invoice.reminder_count, app_state.env.invoice_max_reminders);
+    let mut reminded_earlier: invoices::ActiveModel = invoice.into();
+    reminded_earlier.last_reminded_at = Set(Some(Utc::now() - Duration::days(30)));
+    reminded_earlier.update(&app_state.db).await.unwrap();
+
+    let summary = process_overdue_invoices(&app_state).await.unwrap();
+    assert_eq!(summary.reminded, 0);
+    // The invoice itself and a reminder each time
+    assert_eq!(
+        queued_emails(&app_state.db).await.len(),
+        1 + app_state.env.invoice_max_reminders as usize
+    );
+}
@@ -0,0 +1,370 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, web, App};
+use chrono::{Duration, Utc};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::json;
+
+use common::paystack_mock::MockPaystack;
+use common::{
+    amount, authorized, call, charge_success_event, paystack_webhook_request, seed_payer,
+    seed_user, sqlite_app_state, test_env, wallet_of, wallet_transactions, PIN,
+};
+use money_transfer::configure_app;
+use money_transfer::entities::{payment_links, prelude::PaymentLinks};
+use money_transfer::service::card_payment::process_stale_card_payments;
+use money_transfer::service::merchant::merchant_fee;
+use money_transfer::service::transaction_balance::TrxCategory;
+use money_transfer::service::wallet_reconciliation::find_mismatches;
+
+#[actix_web::test]
+async fn wallet_payments_credit_the_merchant_less_fees_within_the_link_limits() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let shop = seed_payer(&app_state, "Shop", 0).await;
+    let ada = seed_payer(&app_state, "Ada", 10000).await;
+    let bola = seed_payer(&app_state, "Bola", 10000).await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/merchant")
+        .set_json(json!({ "business_name": "Shop Ventures" }));
+    let (status, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+
+    let request = test::TestRequest::post()
+        .uri("/api/merchant/payment-links")
+        .set_json(json!({ "title": "Sneakers", "amount": 4000, "max_uses": 1 }));
+    let (status, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    let link_id = body["data"]["payment_link"]["uuid"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let slug = body["data"]["payment_link"]["slug"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/pay/{}", slug))
+        .to_request();
+    let (status, body) = call(&app, request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body["data"]["payment_link"]["business_name"],
+        json!("Shop Ventures")
+    );
+
+    // The merchant can't pay their own link, and fixed amounts ignore the payer's amount
+    let request = test::TestRequest::post()
+        .uri(&format!("/api/pay/{}/wallet", slug))
+        .set_json(json!({ "pin": PIN }));
+    let (status, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], json!("Cannot pay your own payment link"));
+
+    let request = test::TestRequest::post()
+        .uri(&format!("/api/pay/{}/wallet", slug))
+        .set_json(json!({ "pin": PIN, "amount": 100 }));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(
+        amount(&body["data"]["payment"]["amount"]),
+        Decimal::from(4000)
+    );
+    assert_eq!(amount(&body["data"]["payment"]["fee"]), Decimal::from(60));
+
+    let request = test::TestRequest::post()
+        .uri(&format!("/api/pay/{}/wallet", slug))
+        .set_json(json!({ "pin": PIN }));
+    let (status, body) = call(&app, authorized(request, &app_state, &bola)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body["message"],
+        json!("Payment link has reached its usage limit")
+    );
+
+    let ada_wallet = wallet_of(&app_state.db, &ada).await;
+    let shop_wallet = wallet_of(&app_state.db, &shop).await;
+    assert_eq!(ada_wallet.current_balance, Decimal::from(6000));
+    assert_eq!(shop_wallet.current_balance, Decimal::from(3940));
+    assert_eq!(
+        wallet_of(&app_state.db, &bola).await.current_balance,
+        Decimal::from(10000)
+    );
+
+    let credits = wallet_transactions(&app_state.db, &shop_wallet).await;
+    assert_eq!(credits.len(), 1);
+    assert_eq!(
+        credits[0].category,
+        TrxCategory::MerchantPayment.to_string()
+    );
+    assert_eq!(
+        (credits[0].amount, credits[0].fees),
+        (Decimal::from(4000), Decimal::from(60))
+    );
+    assert!(find_mismatches(&shop_wallet, &credits).is_empty());
+    let debits = wallet_transactions(&app_state.db, &ada_wallet).await;
+    assert_eq!(
+        (debits[0].amount, debits[0].current_balance),
+        (Decimal::from(4000), Decimal::from(6000))
+    );
+    assert_eq!(
+        debits[0].provider_reference,
+        Some(credits[0].uuid.to_string())
+    );
+
+    let request =
+        test::TestRequest::get().uri(&format!("/api/merchant/payment-links/{}/report", link_id));
+    let (status, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(status, StatusCode::OK);
+    let report = &body["data"]["report"];
+    assert_eq!(report["successful_payments"], json!(1));
+    assert_eq!(report["payment_link"]["use_count"], json!(1));
+    assert_eq!(amount(&report["gross_amount"]), Decimal::from(4000));
+    assert_eq!(amount(&report["net_amount"]), Decimal::from(3940));
+
+    // Someone else's link is not found rather than reported on
+    let request = test::TestRequest::post()
+        .uri("/api/merchant")
+        .set_json(json!({ "business_name": "Bola Stores" }));
+    call(&app, authorized(request, &app_state, &bola)).await;
+    let request =
+        test::TestRequest::get().uri(&format!("/api/merchant/payment-links/{}/report", link_id));
+    let (status, _) = call(&app, authorized(request, &app_state, &bola)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn expired_and_disabled_links_refuse_payments() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let (shop, _) = seed_user(&app_state.db, "Shop").await;
+    let ada = seed_payer(&app_state, "Ada", 10000).await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/merchant")
+        .set_json(json!({ "business_name": "Shop Ventures" }));
+    call(&app, authorized(request, &app_state, &shop)).await;
+
+    let mut slugs = vec![];
+    for title in ["Donations", "Tickets"] {
+        let request = test::TestRequest::post()
+            .uri("/api/merchant/payment-links")
+            .set_json(json!({ "title": title, "expires_in_hours": 24 }));
+        let (_, body) = call(&app, authorized(request, &app_state, &shop)).await;
+        slugs.push((
+            body["data"]["payment_link"]["uuid"]
+                .as_str()
+                .unwrap()
+                .to_string(),
+            body["data"]["payment_link"]["slug"]
+                .as_str()
+                .unwrap()
+                .to_string(),
+        ));
+    }
+
+    // Open amount links need the payer to say how much
+    let request = test::TestRequest::post()
+        .uri(&format!("/api/pay/{}/wallet", slugs[0].1))
+        .set_json(json!({ "pin": PIN }));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body["message"],
+        json!("Please enter the amount you want to pay")
+    );
+
+    let link = PaymentLinks::find()
+        .filter(payment_links::Column::Uuid.eq(&slugs[0].0))
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut expired: payment_links::ActiveModel = link.into();
+    expired.expires_at = Set(Some(Utc::now() - Duration::minutes(1)));
+    expired.update(&app_state.db).await.unwrap();
+
+    let request = test::TestRequest::post()
+        .uri(&format!("/api/pay/{}/wallet", slugs[0].1))
+        .set_json(json!({ "pin": PIN, "amount": 500 }));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], json!("Payment link has expired"));
+
+    let request = test::TestRequest::patch().uri(&format!(
+        "/api/merchant/payment-links/{}/disable",
+        slugs[1].0
+    ));
+    let (status, _) = call(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let request = test::TestRequest::post()
+        .uri(&format!("/api/pay/{}/card", slugs[1].1))
+        .set_json(json!({ "email": "guest@example.com", "amount": 500 }))
+        .to_request();
+    let (status, body) = call(&app, request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], json!("Payment link has been disabled"));
+
+    assert_eq!(
+        wallet_of(&app_state.db, &ada).await.current_balance,
+        Decimal::from(10000)
+    );
+}
+
+#[actix_web::test]
+async fn card_payments_credit_the_merchant_once_the_charge_settles() {
+    let mock = MockPaystack::start().await;
+    let app_state = sqlite_app_state(test_env(&mock.base_url)).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let (shop, _) = seed_user(&app_state.db, "Shop").await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/merchant")
+        .set_json(json!({ "business_name": "Shop Ventures" }));
+    call(&app, authorized(request, &app_state, &shop)).await;
+    let request = test::TestRequest::post()
+        .uri("/api/merchant/payment-links")
+        .set_json(json!({ "title": "Donations" }));
+    let (_, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    let slug = body["data"]["payment_link"]["slug"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let request = test::TestRequest::post()
+        .uri(&format!("/api/pay/{}/card", slug))
+        .set_json(json!({ "email": "guest@example.com", "amount": 10000 }))
+        .to_request();
+    let (status, body) = call(&app, request).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert!(body["data"]["authorization"]["authorization_url"].is_string());
+    let reference = body["data"]["payment"]["reference"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let status_uri = format!("/api/pay/{}/payments/{}", slug, reference);
+    let request = test::TestRequest::get().uri(&status_uri).to_request();
+    let (_, body) = call(&app, request).await;
+    assert_eq!(body["data"]["payment"]["status"], json!("pending"));
+
+    mock.complete_charge(&reference);
+    let webhook = charge_success_event(&reference, 1000000);
+    for _ in 0..2 {
+        let request = paystack_webhook_request(common::PAYSTACK_SECRET, &webhook).to_request();
+        let (status, _) = call(&app, request).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let request = test::TestRequest::get().uri(&status_uri).to_request();
+    let (_, body) = call(&app, request).await;
+    assert_eq!(body["data"]["payment"]["status"], json!("successful"));
+
+    let fee = merchant_fee(&app_state.env, Decimal::from(10000));
+    assert_eq!(fee, Decimal::from(150));
+    let shop_wallet = wallet_of(&app_state.db, &shop).await;
+    assert_eq!(shop_wallet.current_balance, Decimal::from(10000) - fee);
+
+    let credits = wallet_transactions(&app_state.db, &shop_wallet).await;
+    assert_eq!(credits.len(), 1);
+    assert_eq!(credits[0].provider, "paystack");
+    assert_eq!(credits[0].provider_reference, Some(reference));
+    assert_eq!(credits[0].amount, Decimal::from(10000));
+    assert!(find_mismatches(&shop_wallet, &credits).is_empty());
+
+    // Large payments pay no more than the capped fee
+    assert_eq!(
+        merchant_fee(&app_state.env, Decimal::from(1000000)),
+        Decimal::from(2000)
+    );
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn card_payments_without_a_webhook_are_settled_by_the_poller() {
+    let mock = MockPaystack::start().await;
+    let mut env = test_env(&mock.base_url);
+    env.funding_stale_after_mins = 0;
+    let app_state = sqlite_app_state(env).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let (shop, _) = seed_user(&app_state.db, "Shop").await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/merchant")
+        .set_json(json!({ "business_name": "Shop Ventures" }));
+    call(&app, authorized(request, &app_state, &shop)).await;
+    let request = test::TestRequest::post()
+        .uri("/api/merchant/payment-links")
+        .set_json(json!({ "title": "Donations" }));
+    let (_, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    let slug = body["data"]["payment_link"]["slug"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let mut references = vec![];
+    for amount in [10000, 4000] {
+        let request = test::TestRequest::post()
+            .uri(&format!("/api/pay/{}/card", slug))
+            .set_json(json!({ "email": "guest@example.com", "amount": amount }))
+            .to_request();
+        let (status, body) = call(&app, request).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        references.push(
+            body["data"]["payment"]["reference"]
+                .as_str()
+                .unwrap()
+                .to_string(),
+        );
+    }
+
+    // Neither webhook arrives
+    mock.complete_charge(&references[0]);
+    mock.fail_charge(&references[1]);
+    let summary = process_stale_card_payments(&app_state).await.unwrap();
+    assert_eq!(summary.checked, 2);
+    assert_eq!(summary.credited, 1);
+    assert_eq!(summary.failed, 1);
+
+    for (reference, status) in references.iter().zip(["successful", "failed"]) {
+        let uri = format!("/api/pay/{}/payments/{}", slug, reference);
+        let (_, body) = call(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(body["data"]["payment"]["status"], json!(status));
+    }
+
+    let fee = merchant_fee(&app_state.env, Decimal::from(10000));
+    assert_eq!(
+        wallet_of(&app_state.db, &shop).await.current_balance,
+        Decimal::from(10000) - fee
+    );
+
+    let summary = process_stale_card_payments(&app_state).await.unwrap();
+    assert_eq!(summary.checked, 0);
+
+    mock.stop().await;
+}
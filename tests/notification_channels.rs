@@ -0,0 +1,274 @@
+mod common;
+
+use chrono::Utc;
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use common::fake_mailer::FakeMailer;
+use common::messaging_mock::MockMessaging;
+use common::{seed_user, sqlite_app_state, test_env};
+use money_transfer::entities::{
+    device_tokens, outbox_messages,
+    prelude::{DeviceTokens, OutboxMessages},
+    users, wallets,
+};
+use money_transfer::service::notification::{
+    notify, register_device, set_preference, Channel, Notification, NotificationEvent, Notifier,
+};
+use money_transfer::service::outbox::process_outbox;
+use money_transfer::service::p2p_transfer::{P2PTransfer, P2PTransferTrait};
+use money_transfer::utils::{config::EnvConfig, fcm::FcmPush, termii::TermiiSms};
+use money_transfer::AppState;
+
+fn messaging_env(base_url: &str) -> EnvConfig {
+    let mut env = test_env("http://127.0.0.1:1");
+    env.termii_base_url = base_url.to_string();
+    env.termii_api_key = String::from("termii-test-key");
+    env.fcm_base_url = base_url.to_string();
+    env.fcm_server_key = String::from("fcm-test-key");
+    env
+}
+
+fn notifier(env: &EnvConfig, mailer: Arc<FakeMailer>) -> Notifier {
+    Notifier::new(mailer)
+        .with_sms(Arc::new(TermiiSms::new(env)))
+        .with_push(Arc::new(FcmPush::new(env)))
+}
+
+async fn set_phone_number(db: &DatabaseConnection, user: users::Model, phone_number: &str) {
+    let mut user: users::ActiveModel = user.into();
+    user.phone_number = Set(Some(phone_number.to_string()));
+    user.update(db).await.unwrap();
+}
+
+async fn queued(db: &DatabaseConnection) -> Vec<(outbox_messages::Model, Notification)> {
+    OutboxMessages::find()
+        .order_by_asc(outbox_messages::Column::Id)
+        .all(db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|message| {
+            let notification = serde_json::from_str(&message.payload).unwrap();
+            (message, notification)
+        })
+        .collect()
+}
+
+async fn make_all_due(app_state: &AppState) {
+    OutboxMessages::update_many()
+        .col_expr(
+            outbox_messages::Column::NextAttemptAt,
+            sea_query::Expr::value(Utc::now()),
+        )
+        .exec(&app_state.db)
+        .await
+        .unwrap();
+}
+
+#[actix_web::test]
+async fn events_go_out_on_the_channels_each_user_chose() {
+    let mock = MockMessaging::start().await;
+    let env = messaging_env(&mock.base_url);
+    let app_state = sqlite_app_state(env.clone()).await;
+    let (ada, ada_wallet) = seed_user(&app_state.db, "Ada").await;
+    let (bola, _) = seed_user(&app_state.db, "Bola").await;
+
+    set_phone_number(&app_state.db, ada.clone(), "+2348012345678").await;
+    register_device(&app_state.db, &ada.uuid, "ada-phone", "ios")
+        .await
+        .unwrap();
+    set_preference(&app_state.db, &ada.uuid, "transfer_sent", "push", false)
+        .await
+        .unwrap();
+
+    let mut funded: wallets::ActiveModel = ada_wallet.into();
+    funded.current_balance = Set(Decimal::from(5000));
+    funded.update(&app_state.db).await.unwrap();
+
+    let transfer = P2PTransfer {
+        sender: ada.clone(),
+        receiver_id: bola.uuid.to_string(),
+        amount: Decimal::from(1500),
+        narration: None,
+    };
+    transfer.transfer(&app_state.db).await.unwrap();
+
+    // Ada turned pushes for this off, Bola has no phone or device to reach
+    let channels: Vec<_> = queued(&app_state.db)
+        .await
+        .into_iter()
+        .map(|(_, notification)| (notification.event, notification.channel))
+        .collect();
+    assert_eq!(
+        channels,
+        vec![
+            (NotificationEvent::TransferSent, Channel::Email),
+            (NotificationEvent::TransferSent, Channel::Sms),
+            (NotificationEvent::TransferReceived, Channel::Email),
+        ]
+    );
+
+    let mailer = Arc::new(FakeMailer::default());
+    let summary = process_outbox(&app_state, &notifier(&env, mailer.clone()))
+        .await
+        .unwrap();
+    assert_eq!(summary.delivered, 3);
+    assert_eq!(
+        mailer.sent_to(),
+        vec!["ada@example.com", "bola@example.com"]
+    );
+
+    let sms = mock.sms();
+    assert_eq!(sms.len(), 1);
+    assert_eq!(sms[0]["to"], json!("2348012345678"));
+    assert_eq!(sms[0]["api_key"], json!("termii-test-key"));
+    let text = sms[0]["sms"].as_str().unwrap();
+    assert!(
+        text.starts_with("You sent NGN 1500 to Tester Bola."),
+        "{}",
+        text
+    );
+    assert!(mock.pushes().is_empty());
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn failed_sms_is_retried_and_unregistered_devices_are_dropped() {
+    let mock = MockMessaging::start().await;
+    let env = messaging_env(&mock.base_url);
+    let app_state = sqlite_app_state(env.clone()).await;
+    let (ada, _) = seed_user(&app_state.db, "Ada").await;
+
+    set_phone_number(&app_state.db, ada.clone(), "+2348012345678").await;
+    register_device(&app_state.db, &ada.uuid, "old-phone", "android")
+        .await
+        .unwrap();
+    register_device(&app_state.db, &ada.uuid, "new-phone", "android")
+        .await
+        .unwrap();
+    mock.unregister("old-phone");
+    mock.fail_next_sms(503);
+
+    notify(
+        &app_state.db,
+        &ada.uuid,
+        NotificationEvent::PinChanged,
+        json!({ "changed_at": "19 Oct 2026, 10:00 UTC" }),
+    )
+    .await
+    .unwrap();
+
+    let mailer = Arc::new(FakeMailer::default());
+    let notifier = notifier(&env, mailer.clone());
+    let summary = process_outbox(&app_state, &notifier).await.unwrap();
+    assert_eq!(
+        (summary.delivered, summary.retried, summary.dead),
+        (2, 1, 0)
+    );
+
+    let pushes = mock.pushes();
+    assert_eq!(pushes.len(), 1);
+    assert_eq!(pushes[0]["to"], json!("new-phone"));
+    assert_eq!(
+        pushes[0]["notification"]["title"],
+        json!("Your withdrawal PIN was changed")
+    );
+    let devices: Vec<String> = DeviceTokens::find()
+        .filter(device_tokens::Column::UserId.eq(&ada.uuid))
+        .all(&app_state.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|device| device.token)
+        .collect();
+    assert_eq!(devices, vec!["new-phone"]);
+
+    make_all_due(&app_state).await;
+    let summary = process_outbox(&app_state, &notifier).await.unwrap();
+    assert_eq!(summary.delivered, 1);
+    assert_eq!(mock.sms().len(), 1);
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn messages_for_unconfigured_channels_are_dead_lettered() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let (ada, _) = seed_user(&app_state.db, "Ada").await;
+    set_phone_number(&app_state.db, ada.clone(), "+2348012345678").await;
+
+    notify(
+        &app_state.db,
+        &ada.uuid,
+        NotificationEvent::WalletFunded,
+        json!({ "amount": "500", "reference": "ref-1", "balance": "500" }),
+    )
+    .await
+    .unwrap();
+
+    let mailer = Arc::new(FakeMailer::default());
+    let summary = process_outbox(&app_state, &Notifier::new(mailer.clone()))
+        .await
+        .unwrap();
+    assert_eq!((summary.delivered, summary.dead), (1, 1));
+
+    let (dead, notification) = queued(&app_state.db)
+        .await
+        .into_iter()
+        .find(|(message, _)| message.status == "dead")
+        .unwrap();
+    assert_eq!(notification.channel, Channel::Sms);
+    assert_eq!(dead.last_error.unwrap(), "No sms channel is configured");
+}
+
+#[actix_web::test]
+async fn the_sink_writes_every_channel_to_a_file() {
+    let path = std::env::temp_dir().join(format!("notifications-{}.jsonl", uuid::Uuid::new_v4()));
+    let mut env = test_env("http://127.0.0.1:1");
+    env.notification_sink = path.to_string_lossy().to_string();
+    let app_state = sqlite_app_state(env.clone()).await;
+    let (ada, _) = seed_user(&app_state.db, "Ada").await;
+    register_device(&app_state.db, &ada.uuid, "ada-phone", "ios")
+        .await
+        .unwrap();
+
+    notify(
+        &app_state.db,
+        &ada.uuid,
+        NotificationEvent::NewLogin,
+        json!({
+            "logged_in_at": "19 Oct 2026, 10:00 UTC",
+            "ip_address": "127.0.0.1",
+            "user_agent": "curl/8.0"
+        }),
+    )
+    .await
+    .unwrap();
+
+    let summary = process_outbox(&app_state, &Notifier::from_env(&env))
+        .await
+        .unwrap();
+    assert_eq!(summary.delivered, 2);
+
+    let written: Vec<Value> = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(written.len(), 2);
+    assert_eq!(written[0]["channel"], json!("email"));
+    assert_eq!(written[0]["to"], json!("ada@example.com"));
+    assert!(written[0]["body"]
+        .as_str()
+        .unwrap()
+        .contains("IP address: 127.0.0.1"));
+    assert_eq!(written[1]["channel"], json!("push"));
+    assert_eq!(written[1]["to"], json!("ada-phone"));
+    assert_eq!(written[1]["title"], json!("New login to your account"));
+}
@@ -0,0 +1,247 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, web, App};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tera::Context;
+
+use common::fake_mailer::FakeMailer;
+use common::{seed_user, sqlite_app_state, test_env};
+use money_transfer::entities::{outbox_messages, prelude::OutboxMessages, users};
+use money_transfer::service::notification::{notify, Notification, NotificationEvent, Notifier};
+use money_transfer::service::outbox::process_outbox;
+use money_transfer::service::p2p_transfer::{P2PTransfer, P2PTransferTrait};
+use money_transfer::utils::email_template::{render_email, SUPPORTED_LOCALES};
+use money_transfer::{configure_app, AppState};
+
+const EVENTS: [NotificationEvent; 10] = [
+    NotificationEvent::VerifyAccount,
+    NotificationEvent::PasswordReset,
+    NotificationEvent::TransferSent,
+    NotificationEvent::TransferReceived,
+    NotificationEvent::WalletFunded,
+    NotificationEvent::PinChanged,
+    NotificationEvent::NewLogin,
+    NotificationEvent::ScheduledTransferFailed,
+    NotificationEvent::DisputeUpdate,
+    NotificationEvent::EscrowUpdate,
+];
+
+// Every variable any template uses
+fn sample_context() -> Context {
+    let mut context = Context::from_value(json!({
+        "first_name": "Ada",
+        "app_base_url": "https://moneytransfer.am",
+        "token": "token-value",
+        "amount": "1500.00",
+        "balance": "3500.00",
+        "reference": "ref-123",
+        "narration": "Lunch",
+        "sender_name": "Tester Bola",
+        "receiver_name": "Tester Bola",
+        "changed_at": "19 Oct 2026, 10:00 UTC",
+        "logged_in_at": "19 Oct 2026, 10:00 UTC",
+        "ip_address": "127.0.0.1",
+        "user_agent": "curl/8.0",
+        "reason": "Insufficient Funds",
+        "schedule_id": "schedule-1",
+        "message": "Your dispute has been opened",
+        "dispute_id": "dispute-1",
+        "transaction_id": "transaction-1",
+        "status": "open",
+        "escrow_id": "escrow-1",
+        "description": "Used laptop",
+        "role": "buyer",
+        "counterparty_name": "Tester Bola",
+        "auto_release_at": "22 Oct 2026, 10:00 UTC",
+    }))
+    .unwrap();
+    context.insert("first_name", "<b>Ada</b>");
+    context
+}
+
+async fn queued_notifications(db: &DatabaseConnection) -> Vec<Notification> {
+    OutboxMessages::find()
+        .filter(outbox_messages::Column::Kind.eq("notification"))
+        .order_by_asc(outbox_messages::Column::Id)
+        .all(db)
+        .await
+        .unwrap()
+        .iter()
+        .map(|message| serde_json::from_str(&message.payload).unwrap())
+        .collect()
+}
+
+async fn set_locale(db: &DatabaseConnection, user: users::Model, locale: &str) -> users::Model {
+    let mut user: users::ActiveModel = user.into();
+    user.locale = Set(locale.to_string());
+    user.update(db).await.unwrap()
+}
+
+#[actix_web::test]
+async fn every_email_renders_in_every_locale() {
+    let context = sample_context();
+
+    for locale in SUPPORTED_LOCALES {
+        for event in EVENTS {
+            let email = render_email(&event.to_string(), locale, &context)
+                .unwrap_or_else(|err| panic!("{} in {}: {:?}", event, locale, err));
+            assert!(!email.subject.is_empty());
+            assert!(!email.subject.contains('\n'));
+            // Only the HTML part is escaped
+            assert!(
+                email.html.contains("&lt;b&gt;Ada&lt;&#x2F;b&gt;"),
+                "{}",
+                email.html
+            );
+            assert!(email.text.contains("<b>Ada</b>"));
+        }
+    }
+
+    let french = render_email("transfer_sent", "fr", &context).unwrap();
+    assert_eq!(french.subject, "Vous avez envoyé NGN 1500.00 à Tester Bola");
+
+    // Locales without templates fall back to English
+    let german = render_email("transfer_sent", "de", &context).unwrap();
+    assert_eq!(german.subject, "You sent NGN 1500.00 to Tester Bola");
+}
+
+#[actix_web::test]
+async fn transfers_notify_both_parties_in_their_own_language() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let (ada, ada_wallet) = seed_user(&app_state.db, "Ada").await;
+    let (bola, _) = seed_user(&app_state.db, "Bola").await;
+    set_locale(&app_state.db, bola.clone(), "fr").await;
+
+    let mut funded: money_transfer::entities::wallets::ActiveModel = ada_wallet.into();
+    funded.current_balance = Set(Decimal::from(5000));
+    funded.update(&app_state.db).await.unwrap();
+
+    let transfer = P2PTransfer {
+        sender: ada.clone(),
+        receiver_id: bola.uuid.to_string(),
+        amount: Decimal::from(1500),
+        narration: Some(String::from("Lunch")),
+    };
+    transfer.transfer(&app_state.db).await.unwrap();
+
+    // A failed transfer rolls its notifications back with it
+    let overdraft = P2PTransfer {
+        sender: ada.clone(),
+        receiver_id: bola.uuid.to_string(),
+        amount: Decimal::from(10000),
+        narration: None,
+    };
+    assert!(overdraft.transfer(&app_state.db).await.is_err());
+
+    let queued = queued_notifications(&app_state.db).await;
+    assert_eq!(queued.len(), 2);
+    assert_eq!(queued[0].event, NotificationEvent::TransferSent);
+    assert_eq!(queued[0].user_id, ada.uuid);
+    assert_eq!(queued[1].event, NotificationEvent::TransferReceived);
+    assert_eq!(queued[1].data["sender_name"], "Tester Ada");
+
+    let mailer = Arc::new(FakeMailer::default());
+    let notifier = Notifier::new(mailer.clone());
+    let summary = process_outbox(&app_state, &notifier).await.unwrap();
+    assert_eq!(summary.delivered, 2);
+
+    let sent = mailer.sent();
+    assert_eq!(sent[0].to, "ada@example.com");
+    assert_eq!(sent[0].subject, "You sent NGN 1500 to Tester Bola");
+    let text = sent[0].text.as_ref().unwrap();
+    assert!(text.contains("Your balance is now NGN 3500."), "{}", text);
+
+    assert_eq!(sent[1].to, "bola@example.com");
+    assert_eq!(sent[1].subject, "Vous avez reçu NGN 1500 de Tester Ada");
+    assert!(sent[1].template.contains("<html lang=\"fr\">"));
+}
+
+#[actix_web::test]
+async fn notifications_for_missing_users_are_dead_lettered() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+
+    notify(
+        &app_state.db,
+        "no-such-user",
+        NotificationEvent::PinChanged,
+        json!({ "changed_at": "now" }),
+    )
+    .await
+    .unwrap();
+
+    let mailer = Arc::new(FakeMailer::default());
+    let notifier = Notifier::new(mailer.clone());
+    let summary = process_outbox(&app_state, &notifier).await.unwrap();
+    assert_eq!(summary.dead, 1);
+    assert!(mailer.sent().is_empty());
+}
+
+async fn post(app_state: &AppState, uri: &str, body: Value) -> (StatusCode, Value) {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let request = test::TestRequest::post()
+        .uri(uri)
+        .set_json(body)
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    let status = response.status();
+    let body = test::read_body(response).await;
+
+    (status, serde_json::from_slice(&body).unwrap_or_default())
+}
+
+#[actix_web::test]
+async fn password_reset_links_work_once() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let (ada, _) = seed_user(&app_state.db, "Ada").await;
+
+    // Unknown emails get the same answer and no email
+    let (status, unknown) = post(
+        &app_state,
+        "/api/user/forgot-password",
+        json!({ "email": "nobody@example.com" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, known) = post(
+        &app_state,
+        "/api/user/forgot-password",
+        json!({ "email": "ADA@example.com" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(unknown, known);
+
+    let queued = queued_notifications(&app_state.db).await;
+    assert_eq!(queued.len(), 1);
+    assert_eq!(queued[0].event, NotificationEvent::PasswordReset);
+    assert_eq!(queued[0].user_id, ada.uuid);
+    let token = queued[0].data["token"].as_str().unwrap().to_string();
+
+    let reset = json!({ "token": token, "password": "new-password" });
+    let (status, body) = post(&app_state, "/api/user/reset-password", reset.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, body) = post(
+        &app_state,
+        "/api/user/login",
+        json!({ "email": "ada@example.com", "password": "new-password" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    // The password it was signed with is gone, so the link is spent
+    let (status, body) = post(&app_state, "/api/user/reset-password", reset).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "Invalid or expired reset token");
+
+    let queued = queued_notifications(&app_state.db).await;
+    assert_eq!(queued.last().unwrap().event, NotificationEvent::NewLogin);
+}
@@ -0,0 +1,385 @@
+mod common;
+
+use chrono::Utc;
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use common::fake_mailer::FakeMailer;
+use common::paystack_mock::MockPaystack;
+use common::webhook_receiver::{MockWebhookReceiver, ReceivedWebhook};
+use common::{seed_user, sqlite_app_state, test_env};
+use money_transfer::entities::{
+    outbox_messages,
+    prelude::{OutboxMessages, WebhookDeliveries},
+    sea_orm_active_enums::{Status, TrxType},
+    users, wallets, webhook_deliveries, webhook_subscriptions,
+};
+use money_transfer::service::funding::{record_pending_funding, settle_funding, FundingOutcome};
+use money_transfer::service::notification::Notifier;
+use money_transfer::service::outbound_webhook::{
+    create_subscription, delete_subscription, emit_webhook_event, redeliver, WebhookError,
+    WebhookEventType, EVENT_HEADER, SIGNATURE_HEADER,
+};
+use money_transfer::service::outbox::process_outbox;
+use money_transfer::service::outward_transfer::reverse_outward_transfer;
+use money_transfer::service::p2p_transfer::{P2PTransfer, P2PTransferTrait};
+use money_transfer::service::transaction_balance::{
+    TransactionBalance, TransactionBalanceTrait, TrxCategory,
+};
+use money_transfer::utils::helpers::sign_payload;
+use money_transfer::utils::paystack::Paystack;
+use money_transfer::AppState;
+
+async fn subscribe(
+    app_state: &AppState,
+    user: &users::Model,
+    url: &str,
+    event_types: &[&str],
+) -> webhook_subscriptions::Model {
+    let event_types: Vec<String> = event_types.iter().map(|event| event.to_string()).collect();
+    create_subscription(&app_state.db, &app_state.env, &user.uuid, url, &event_types)
+        .await
+        .unwrap()
+}
+
+async fn deliveries(db: &DatabaseConnection) -> Vec<webhook_deliveries::Model> {
+    WebhookDeliveries::find()
+        .order_by_asc(webhook_deliveries::Column::Id)
+        .all(db)
+        .await
+        .unwrap()
+}
+
+async fn make_all_due(app_state: &AppState) {
+    OutboxMessages::update_many()
+        .col_expr(
+            outbox_messages::Column::NextAttemptAt,
+            sea_query::Expr::value(Utc::now()),
+        )
+        .exec(&app_state.db)
+        .await
+        .unwrap();
+}
+
+async fn fund_wallet(db: &DatabaseConnection, wallet: wallets::Model, amount: i64) {
+    let mut funded: wallets::ActiveModel = wallet.into();
+    funded.current_balance = Set(Decimal::from(amount));
+    funded.update(db).await.unwrap();
+}
+
+// Checks the signature the way an integrator would, then hands back the event
+fn verified_event(webhook: &ReceivedWebhook, secret: &str) -> Value {
+    assert_eq!(
+        webhook.headers[SIGNATURE_HEADER],
+        sign_payload(&webhook.body, secret)
+    );
+    serde_json::from_str(&webhook.body).unwrap()
+}
+
+fn notifier() -> Notifier {
+    Notifier::new(Arc::new(FakeMailer::default()))
+}
+
+#[actix_web::test]
+async fn committed_changes_are_sent_signed_to_subscribers() {
+    let receiver = MockWebhookReceiver::start().await;
+    let paystack_mock = MockPaystack::start().await;
+    let app_state = sqlite_app_state(test_env(&paystack_mock.base_url)).await;
+    let (ada, ada_wallet) = seed_user(&app_state.db, "Ada").await;
+    let (bola, _) = seed_user(&app_state.db, "Bola").await;
+
+    let ada_hooks = subscribe(
+        &app_state,
+        &ada,
+        &receiver.url,
+        &["transfer.completed", "withdrawal.failed"],
+    )
+    .await;
+    let bola_hooks = subscribe(&app_state, &bola, &receiver.url, &["wallet.funded"]).await;
+
+    fund_wallet(&app_state.db, ada_wallet.clone(), 5000).await;
+    let transfer = P2PTransfer {
+        sender: ada.clone(),
+        receiver_id: bola.uuid.to_string(),
+        amount: Decimal::from(1500),
+        narration: Some(String::from("Lunch")),
+    };
+    transfer.transfer(&app_state.db).await.unwrap();
+
+    paystack_mock.add_pending_charge("ref-bola", 200000, &bola.uuid, &bola.email);
+    record_pending_funding(
+        &app_state.db,
+        &bola,
+        "paystack",
+        "ref-bola",
+        Decimal::from(2000),
+    )
+    .await
+    .unwrap();
+    paystack_mock.complete_charge("ref-bola");
+    let outcome = settle_funding(&Paystack::new(&app_state.env), "ref-bola", &app_state.db).await;
+    assert_eq!(outcome.unwrap(), FundingOutcome::Credited);
+
+    // An outward transfer Paystack later reports as failed
+    let txn = app_state.db.begin().await.unwrap();
+    TransactionBalance {
+        uuid: String::from("out-1"),
+        amount: Decimal::from(1000),
+        trx_type: TrxType::Debit,
+        status: Status::Pending,
+        description: String::from("Withdrawal"),
+        provider_reference: Some(String::from("out-1")),
+        current_balance: Decimal::from(2500),
+        previous_balance: Decimal::from(3500),
+        user_id: ada.uuid.to_string(),
+        wallet_id: ada_wallet.uuid.to_string(),
+        provider: String::from("paystack"),
+        fees: None,
+        provider_fees: None,
+        category: TrxCategory::Outward,
+        meta: None,
+    }
+    .save_transaction_update_balance(&txn)
+    .await
+    .unwrap();
+    txn.commit().await.unwrap();
+    assert!(
+        reverse_outward_transfer(&app_state.db, "out-1", "Account closed")
+            .await
+            .unwrap()
+    );
+
+    // Bola only asked for fundings, so the credit side of the transfer isn't sent
+    let summary = process_outbox(&app_state, &notifier()).await.unwrap();
+    assert_eq!(summary.dead, 0);
+
+    let received = receiver.received();
+    assert_eq!(received.len(), 3);
+
+    assert_eq!(received[0].headers[EVENT_HEADER], "transfer.completed");
+    let transfer = verified_event(&received[0], &ada_hooks.secret);
+    assert_eq!(transfer["event"], json!("transfer.completed"));
+    assert_eq!(transfer["data"]["direction"], json!("debit"));
+    assert_eq!(transfer["data"]["amount"], json!("1500"));
+    assert_eq!(transfer["data"]["balance"], json!("3500"));
+    assert_eq!(transfer["data"]["narration"], json!("Lunch"));
+
+    let funding = verified_event(&received[1], &bola_hooks.secret);
+    assert_eq!(funding["event"], json!("wallet.funded"));
+    assert_eq!(funding["data"]["reference"], json!("ref-bola"));
+    assert_eq!(funding["data"]["balance"], json!("3500"));
+
+    let withdrawal = verified_event(&received[2], &ada_hooks.secret);
+    assert_eq!(withdrawal["event"], json!("withdrawal.failed"));
+    assert_eq!(withdrawal["data"]["reference"], json!("out-1"));
+    assert_eq!(withdrawal["data"]["reason"], json!("Account closed"));
+    assert_eq!(withdrawal["data"]["balance"], json!("3500"));
+
+    for delivery in deliveries(&app_state.db).await {
+        assert_eq!(delivery.status, "delivered");
+        assert_eq!(delivery.attempts, 1);
+        assert_eq!(delivery.response_status, Some(200));
+        assert!(delivery.delivered_at.is_some());
+    }
+
+    receiver.stop().await;
+    paystack_mock.stop().await;
+}
+
+#[actix_web::test]
+async fn failing_endpoints_are_retried_and_can_be_redelivered() {
+    let receiver = MockWebhookReceiver::start().await;
+    let mut env = test_env("http://127.0.0.1:1");
+    env.outbox_max_attempts = 2;
+    let app_state = sqlite_app_state(env).await;
+    let (ada, _) = seed_user(&app_state.db, "Ada").await;
+    subscribe(&app_state, &ada, &receiver.url, &["transfer.completed"]).await;
+
+    emit_webhook_event(
+        &app_state.db,
+        &ada.uuid,
+        WebhookEventType::TransferCompleted,
+        json!({ "reference": "ref-1" }),
+    )
+    .await
+    .unwrap();
+    receiver.fail_next(500);
+    receiver.fail_next(503);
+
+    let notifier = notifier();
+    let summary = process_outbox(&app_state, &notifier).await.unwrap();
+    assert_eq!((summary.retried, summary.dead), (1, 0));
+    let delivery = deliveries(&app_state.db).await.remove(0);
+    assert_eq!(delivery.status, "retrying");
+    assert_eq!(delivery.response_status, Some(500));
+    assert_eq!(
+        delivery.last_error.unwrap(),
+        "Endpoint responded with 500 Internal Server Error"
+    );
+
+    // Backing off, nothing is due yet
+    let summary = process_outbox(&app_state, &notifier).await.unwrap();
+    assert_eq!(summary.retried + summary.delivered + summary.dead, 0);
+
+    make_all_due(&app_state).await;
+    let summary = process_outbox(&app_state, &notifier).await.unwrap();
+    assert_eq!(summary.dead, 1);
+    let delivery = deliveries(&app_state.db).await.remove(0);
+    assert_eq!(delivery.status, "failed");
+    assert_eq!(delivery.attempts, 2);
+    assert_eq!(delivery.response_status, Some(503));
+
+    let (bola, _) = seed_user(&app_state.db, "Bola").await;
+    let not_theirs = redeliver(&app_state.db, &bola.uuid, &delivery.uuid).await;
+    assert!(matches!(not_theirs, Err(WebhookError::DeliveryNotFound)));
+
+    let pending = redeliver(&app_state.db, &ada.uuid, &delivery.uuid)
+        .await
+        .unwrap();
+    assert_eq!(pending.status, "pending");
+    let summary = process_outbox(&app_state, &notifier).await.unwrap();
+    assert_eq!(summary.delivered, 1);
+
+    let delivery = deliveries(&app_state.db).await.remove(0);
+    assert_eq!(delivery.status, "delivered");
+    assert_eq!(delivery.attempts, 3);
+    assert_eq!(delivery.last_error, None);
+
+    // Every attempt carries the same event, so the integrator can deduplicate
+    let event_ids: Vec<Value> = receiver
+        .received()
+        .iter()
+        .map(|webhook| serde_json::from_str::<Value>(&webhook.body).unwrap()["id"].clone())
+        .collect();
+    assert_eq!(event_ids.len(), 3);
+    assert!(event_ids.iter().all(|id| *id == json!(delivery.event_id)));
+
+    receiver.stop().await;
+}
+
+#[actix_web::test]
+async fn deleted_subscriptions_stop_receiving_events() {
+    let receiver = MockWebhookReceiver::start().await;
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let (ada, _) = seed_user(&app_state.db, "Ada").await;
+    let subscription = subscribe(&app_state, &ada, &receiver.url, &["wallet.funded"]).await;
+
+    emit_webhook_event(
+        &app_state.db,
+        &ada.uuid,
+        WebhookEventType::WalletFunded,
+        json!({ "reference": "ref-1" }),
+    )
+    .await
+    .unwrap();
+    delete_subscription(&app_state.db, &ada.uuid, &subscription.uuid)
+        .await
+        .unwrap();
+
+    process_outbox(&app_state, &notifier()).await.unwrap();
+    assert!(receiver.received().is_empty());
+    let delivery = deliveries(&app_state.db).await.remove(0);
+    assert_eq!(delivery.status, "cancelled");
+
+    // Nothing new is queued once the subscription is gone
+    emit_webhook_event(
+        &app_state.db,
+        &ada.uuid,
+        WebhookEventType::WalletFunded,
+        json!({ "reference": "ref-2" }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(deliveries(&app_state.db).await.len(), 1);
+
+    let redelivered = redeliver(&app_state.db, &ada.uuid, &delivery.uuid).await;
+    assert!(matches!(redelivered, Err(WebhookError::DeliveryNotFound)));
+
+    receiver.stop().await;
+}
+
+#[actix_web::test]
+async fn subscriptions_need_a_url_and_known_event_types() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let (ada, _) = seed_user(&app_state.db, "Ada").await;
+    let events =
+        |events: &[&str]| -> Vec<String> { events.iter().map(|event| event.to_string()).collect() };
+
+    let created = create_subscription(
+        &app_state.db,
+        &app_state.env,
+        &ada.uuid,
+        "ftp://example.com/hooks",
+        &events(&["wallet.funded"]),
+    )
+    .await;
+    assert!(matches!(created, Err(WebhookError::InvalidUrl)));
+
+    let created = create_subscription(
+        &app_state.db,
+        &app_state.env,
+        &ada.uuid,
+        "https://127.0.0.1/hooks",
+        &events(&["wallet.emptied"]),
+    )
+    .await;
+    assert!(matches!(created, Err(WebhookError::UnknownEventType(_))));
+
+    let created = create_subscription(
+        &app_state.db,
+        &app_state.env,
+        &ada.uuid,
+        "https://127.0.0.1/hooks",
+        &[],
+    )
+    .await;
+    assert!(matches!(created, Err(WebhookError::NoEventTypes)));
+
+    let subscription = create_subscription(
+        &app_state.db,
+        &app_state.env,
+        &ada.uuid,
+        "https://127.0.0.1/hooks",
+        &events(&["wallet.funded", "transfer.completed", "wallet.funded"]),
+    )
+    .await
+    .unwrap();
+    assert!(subscription.secret.starts_with("whsec_"));
+    assert_eq!(
+        subscription.filter_response().event_types,
+        vec!["wallet.funded", "transfer.completed"]
+    );
+}
+
+#[actix_web::test]
+async fn subscriptions_need_an_https_url_on_a_public_address() {
+    let mut env = test_env("http://127.0.0.1:1");
+    env.webhook_allow_insecure_urls = false;
+    let app_state = sqlite_app_state(env).await;
+    let (ada, _) = seed_user(&app_state.db, "Ada").await;
+    let events = vec![String::from("wallet.funded")];
+
+    for (url, insecure) in [
+        ("http://203.0.113.7/hooks", true),
+        ("https://127.0.0.1/hooks", false),
+        ("https://10.0.0.5/hooks", false),
+        ("https://169.254.169.254/latest/meta-data", false),
+        ("https://[::1]/hooks", false),
+        ("https://[::ffff:192.168.1.1]/hooks", false),
+        ("https://localhost:8443/hooks", false),
+    ] {
+        let created =
+            create_subscription(&app_state.db, &app_state.env, &ada.uuid, url, &events).await;
+        if insecure {
+            assert!(matches!(created, Err(WebhookError::InvalidUrl)), "{}", url);
+        } else {
+            assert!(
+                matches!(created, Err(WebhookError::PrivateAddress)),
+                "{}",
+                url
+            );
+        }
+    }
+}
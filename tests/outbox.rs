@@ -0,0 +1,141 @@
+mod common;
+
+use chrono::Utc;
+use sea_orm::*;
+use std::sync::Arc;
+
+use common::fake_mailer::FakeMailer;
+use common::{sqlite_app_state, test_env};
+use money_transfer::entities::{outbox_messages, prelude::OutboxMessages};
+use money_transfer::service::notification::Notifier;
+use money_transfer::service::outbox::{
+    dead_messages, enqueue_email, process_outbox, retry_dead_message, OutboxError,
+};
+use money_transfer::utils::send_email::{EmailError, SendEmail};
+
+fn email(to: &str) -> SendEmail {
+    SendEmail {
+        to: to.to_string(),
+        from: String::from("support@moneytransfer.am"),
+        subject: String::from("HELLO"),
+        template: String::from("<p>Hello</p>"),
+        text: None,
+    }
+}
+
+async fn fetch_message(db: &DatabaseConnection, uuid: &String) -> outbox_messages::Model {
+    OutboxMessages::find()
+        .filter(outbox_messages::Column::Uuid.eq(uuid))
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+// Skips the backoff so the next run picks the message up
+async fn make_due(db: &DatabaseConnection, message: outbox_messages::Model) {
+    let mut due: outbox_messages::ActiveModel = message.into();
+    due.next_attempt_at = Set(Utc::now());
+    due.update(db).await.unwrap();
+}
+
+#[actix_web::test]
+async fn failed_deliveries_are_retried_with_backoff() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let mailer = Arc::new(FakeMailer::default());
+    let notifier = Notifier::new(mailer.clone());
+
+    let queued = enqueue_email(&app_state.db, &email("ada@example.com"))
+        .await
+        .unwrap();
+    mailer.fail_with(EmailError::TransportError(String::from(
+        "Connection refused",
+    )));
+
+    let summary = process_outbox(&app_state, &notifier).await.unwrap();
+    assert_eq!(
+        (summary.delivered, summary.retried, summary.dead),
+        (0, 1, 0)
+    );
+
+    let message = fetch_message(&app_state.db, &queued.uuid).await;
+    assert_eq!(message.status, "pending");
+    assert_eq!(message.attempts, 1);
+    assert!(message.next_attempt_at > Utc::now());
+    assert!(message.last_error.unwrap().contains("Connection refused"));
+
+    // Not due yet, so nothing is sent
+    let summary = process_outbox(&app_state, &notifier).await.unwrap();
+    assert_eq!(summary.delivered, 0);
+    assert!(mailer.sent_to().is_empty());
+
+    make_due(
+        &app_state.db,
+        fetch_message(&app_state.db, &queued.uuid).await,
+    )
+    .await;
+    let summary = process_outbox(&app_state, &notifier).await.unwrap();
+    assert_eq!(summary.delivered, 1);
+    assert_eq!(mailer.sent_to(), vec!["ada@example.com"]);
+
+    let message = fetch_message(&app_state.db, &queued.uuid).await;
+    assert_eq!(message.status, "delivered");
+    assert_eq!(message.attempts, 2);
+    assert!(message.delivered_at.is_some());
+    assert!(message.last_error.is_none());
+
+    // Delivered messages are never sent again
+    process_outbox(&app_state, &notifier).await.unwrap();
+    assert_eq!(mailer.sent_to().len(), 1);
+}
+
+#[actix_web::test]
+async fn undeliverable_messages_are_dead_lettered_and_can_be_retried() {
+    let mut env = test_env("http://127.0.0.1:1");
+    env.outbox_max_attempts = 2;
+    let app_state = sqlite_app_state(env).await;
+    let mailer = Arc::new(FakeMailer::default());
+    let notifier = Notifier::new(mailer.clone());
+
+    let exhausted = enqueue_email(&app_state.db, &email("ada@example.com"))
+        .await
+        .unwrap();
+    let invalid = enqueue_email(&app_state.db, &email("not-an-email"))
+        .await
+        .unwrap();
+
+    // A bad address is dead straight away, a transient failure only once out of attempts
+    mailer.fail_with(EmailError::TransportError(String::from("Timed out")));
+    mailer.fail_with(EmailError::InvalidAddress(String::from("not-an-email")));
+    let summary = process_outbox(&app_state, &notifier).await.unwrap();
+    assert_eq!((summary.retried, summary.dead), (1, 1));
+
+    mailer.fail_with(EmailError::TransportError(String::from("Timed out")));
+    make_due(
+        &app_state.db,
+        fetch_message(&app_state.db, &exhausted.uuid).await,
+    )
+    .await;
+    let summary = process_outbox(&app_state, &notifier).await.unwrap();
+    assert_eq!((summary.retried, summary.dead), (0, 1));
+
+    let dead = dead_messages(&app_state.db).await.unwrap();
+    assert_eq!(dead.len(), 2);
+    assert!(dead.iter().all(|message| message.status == "dead"));
+
+    let retried = retry_dead_message(&app_state.db, &exhausted.uuid)
+        .await
+        .unwrap();
+    assert_eq!(retried.status, "pending");
+    assert_eq!(retried.attempts, 0);
+    let again = retry_dead_message(&app_state.db, &exhausted.uuid).await;
+    assert!(matches!(again, Err(OutboxError::NotDead)));
+
+    let summary = process_outbox(&app_state, &notifier).await.unwrap();
+    assert_eq!(summary.delivered, 1);
+    assert_eq!(mailer.sent_to(), vec!["ada@example.com"]);
+    assert_eq!(
+        fetch_message(&app_state.db, &invalid.uuid).await.status,
+        "dead"
+    );
+}
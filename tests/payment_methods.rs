@@ -0,0 +1,145 @@
+mod common;
+
+use rust_decimal::Decimal;
+use sea_orm::*;
+
+use common::paystack_mock::MockPaystack;
+use common::{seed_user, sqlite_app_state, test_env};
+use money_transfer::entities::{
+    payment_methods, prelude::Wallets, sea_orm_active_enums::Status, users, wallets,
+};
+use money_transfer::service::funding::{
+    find_funding, record_pending_funding, settle_funding, FundingOutcome,
+};
+use money_transfer::service::payment_method::{
+    delete_payment_method, fund_with_payment_method, user_payment_methods, PaymentMethodError,
+};
+use money_transfer::utils::paystack::Paystack;
+use money_transfer::AppState;
+
+// Funds through checkout, which leaves the card behind for later charges
+async fn checkout_funding(
+    app_state: &AppState,
+    mock: &MockPaystack,
+    user: &users::Model,
+    reference: &str,
+    amount: i64,
+) {
+    mock.add_pending_charge(reference, amount * 100, &user.uuid, &user.email);
+    record_pending_funding(
+        &app_state.db,
+        user,
+        "paystack",
+        reference,
+        Decimal::from(amount),
+    )
+    .await
+    .unwrap();
+    mock.complete_charge(reference);
+
+    let paystack = Paystack::new(&app_state.env);
+    let outcome = settle_funding(&paystack, reference, &app_state.db).await;
+    assert_eq!(outcome.unwrap(), FundingOutcome::Credited);
+}
+
+async fn saved_card(app_state: &AppState, user: &users::Model) -> payment_methods::Model {
+    let mut cards = user_payment_methods(&app_state.db, &user.uuid)
+        .await
+        .unwrap();
+    assert_eq!(cards.len(), 1);
+    cards.remove(0)
+}
+
+async fn balance(db: &DatabaseConnection, wallet_id: &String) -> Decimal {
+    Wallets::find()
+        .filter(wallets::Column::Uuid.eq(wallet_id))
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+        .current_balance
+}
+
+#[actix_web::test]
+async fn cards_are_saved_once_and_can_be_charged() {
+    let mock = MockPaystack::start().await;
+    let app_state = sqlite_app_state(test_env(&mock.base_url)).await;
+    let (ada, wallet) = seed_user(&app_state.db, "Ada").await;
+
+    checkout_funding(&app_state, &mock, &ada, "ref-first", 2000).await;
+    checkout_funding(&app_state, &mock, &ada, "ref-second", 1000).await;
+
+    // Same signature, so the card is refreshed rather than added twice
+    let card = saved_card(&app_state, &ada).await;
+    assert_eq!(card.authorization_code, "AUTH_ref-second");
+    assert_eq!(card.last4, "4081");
+    assert_eq!(card.brand.as_deref(), Some("visa"));
+    let response = serde_json::to_value(card.filter_response()).unwrap();
+    assert!(response.get("authorization_code").is_none());
+
+    let funding = fund_with_payment_method(&app_state, &ada, &card.uuid, Decimal::from(4000))
+        .await
+        .unwrap();
+    assert_eq!(funding.status, Some(Status::Successful));
+    assert_eq!(funding.amount, Decimal::from(4000));
+    assert_eq!(
+        balance(&app_state.db, &wallet.uuid).await,
+        Decimal::from(7000)
+    );
+
+    let request = &mock.requests(common::paystack_mock::PaystackRoute::ChargeAuthorization)[0];
+    assert_eq!(request["authorization_code"], "AUTH_ref-second");
+    assert_eq!(request["amount"], 400000);
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn declined_and_deleted_cards_do_not_fund() {
+    let mock = MockPaystack::start().await;
+    let app_state = sqlite_app_state(test_env(&mock.base_url)).await;
+    let (ada, wallet) = seed_user(&app_state.db, "Ada").await;
+    let (bola, _) = seed_user(&app_state.db, "Bola").await;
+
+    checkout_funding(&app_state, &mock, &ada, "ref-first", 2000).await;
+    let card = saved_card(&app_state, &ada).await;
+
+    // Cards belong to the user who paid with them
+    let stolen = fund_with_payment_method(&app_state, &bola, &card.uuid, Decimal::from(500)).await;
+    assert!(matches!(stolen, Err(PaymentMethodError::NotFound)));
+
+    mock.decline_authorization(&card.authorization_code);
+    let declined = fund_with_payment_method(&app_state, &ada, &card.uuid, Decimal::from(500)).await;
+    match declined {
+        Err(PaymentMethodError::Declined(reason)) => assert_eq!(reason, "Declined"),
+        other => panic!("Expected a declined charge, got {:?}", other),
+    }
+    assert_eq!(
+        balance(&app_state.db, &wallet.uuid).await,
+        Decimal::from(2000)
+    );
+
+    let request = &mock.requests(common::paystack_mock::PaystackRoute::ChargeAuthorization)[0];
+    let reference = request["reference"].as_str().unwrap();
+    let failed = find_funding(&app_state.db, reference)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(failed.status, Some(Status::Failed));
+
+    delete_payment_method(&app_state.db, &ada.uuid, &card.uuid)
+        .await
+        .unwrap();
+    assert!(user_payment_methods(&app_state.db, &ada.uuid)
+        .await
+        .unwrap()
+        .is_empty());
+    let deleted = fund_with_payment_method(&app_state, &ada, &card.uuid, Decimal::from(500)).await;
+    assert!(matches!(deleted, Err(PaymentMethodError::NotFound)));
+
+    // Paying with the card again brings it back
+    checkout_funding(&app_state, &mock, &ada, "ref-second", 1000).await;
+    assert_eq!(saved_card(&app_state, &ada).await.uuid, card.uuid);
+
+    mock.stop().await;
+}
@@ -0,0 +1,189 @@
+mod common;
+
+use actix_http::Request;
+use actix_web::{
+    body::MessageBody,
+    dev::{Service, ServiceResponse},
+    http::StatusCode,
+    test, web, App,
+};
+use chrono::{Duration, Utc};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::json;
+
+use common::{authorized, call, seed_payer, sqlite_app_state, test_env, wallet_of, PIN};
+use money_transfer::entities::{payment_requests, prelude::PaymentRequests, users};
+use money_transfer::service::payment_request::expire_stale_requests;
+use money_transfer::{configure_app, AppState};
+
+async fn request_money<S, B>(
+    app: &S,
+    app_state: &AppState,
+    requester: &users::Model,
+    payer: &users::Model,
+    amount: u64,
+) -> String
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let request = test::TestRequest::post()
+        .uri("/api/payment-request")
+        .set_json(json!({ "amount": amount, "payer_id": &payer.uuid, "narration": "Lunch" }));
+    let (status, body) = call(app, authorized(request, app_state, requester)).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+
+    body["data"]["payment_request"]["uuid"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+fn accept(request_id: &str, app_state: &AppState, payer: &users::Model) -> Request {
+    let request = test::TestRequest::post()
+        .uri(&format!("/api/payment-request/{}/accept", request_id))
+        .set_json(json!({ "pin": PIN }));
+
+    authorized(request, app_state, payer)
+}
+
+async fn payment_request(app_state: &AppState, request_id: &str) -> payment_requests::Model {
+    PaymentRequests::find_by_id(request_id)
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn requests_are_paid_once_however_often_they_are_accepted() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let ada = seed_payer(&app_state, "Ada", 10000).await;
+    let bola = seed_payer(&app_state, "Bola", 0).await;
+
+    let request_id = request_money(&app, &app_state, &bola, &ada, 3000).await;
+
+    let (first, second) = tokio::join!(
+        call(&app, accept(&request_id, &app_state, &ada)),
+        call(&app, accept(&request_id, &app_state, &ada))
+    );
+    let mut statuses = [first.0, second.0];
+    statuses.sort();
+    assert_eq!(statuses, [StatusCode::OK, StatusCode::BAD_REQUEST]);
+
+    let (status, body) = call(&app, accept(&request_id, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "Payment request has already been accepted");
+
+    assert_eq!(
+        wallet_of(&app_state.db, &ada).await.current_balance,
+        Decimal::from(7000)
+    );
+    assert_eq!(
+        wallet_of(&app_state.db, &bola).await.current_balance,
+        Decimal::from(3000)
+    );
+
+    let accepted = payment_request(&app_state, &request_id).await;
+    assert_eq!(accepted.status, "accepted");
+    assert!(accepted.transaction_reference.is_some());
+    assert!(accepted.responded_at.is_some());
+
+    // Too late to take it back
+    let request =
+        test::TestRequest::patch().uri(&format!("/api/payment-request/{}/cancel", request_id));
+    let (status, _) = call(&app, authorized(request, &app_state, &bola)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn expired_requests_stay_expired_and_cannot_be_paid() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let ada = seed_payer(&app_state, "Ada", 10000).await;
+    let bola = seed_payer(&app_state, "Bola", 0).await;
+
+    let request_id = request_money(&app, &app_state, &bola, &ada, 3000).await;
+    let mut expired: payment_requests::ActiveModel =
+        payment_request(&app_state, &request_id).await.into();
+    expired.expires_at = Set(Utc::now() - Duration::minutes(1));
+    expired.update(&app_state.db).await.unwrap();
+
+    let (status, body) = call(&app, accept(&request_id, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    assert_eq!(body["message"], "Payment request has expired");
+
+    // The expiry worker closes it out
+    assert_eq!(expire_stale_requests(&app_state.db).await.unwrap(), 1);
+    assert_eq!(
+        payment_request(&app_state, &request_id).await.status,
+        "expired"
+    );
+    assert_eq!(
+        wallet_of(&app_state.db, &ada).await.current_balance,
+        Decimal::from(10000)
+    );
+
+    let request =
+        test::TestRequest::patch().uri(&format!("/api/payment-request/{}/decline", request_id));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "Payment request has expired");
+
+    let request = test::TestRequest::get().uri("/api/payment-request/incoming");
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["payment_requests"][0]["status"], "expired");
+}
+
+#[actix_web::test]
+async fn a_cancel_that_fails_part_way_leaves_the_request_pending() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let ada = seed_payer(&app_state, "Ada", 10000).await;
+    let bola = seed_payer(&app_state, "Bola", 0).await;
+
+    let request_id = request_money(&app, &app_state, &bola, &ada, 3000).await;
+
+    // Closing the bill participant is the second write, make it fail
+    app_state
+        .db
+        .execute_unprepared("DROP TABLE bill_participants")
+        .await
+        .unwrap();
+
+    let request =
+        test::TestRequest::patch().uri(&format!("/api/payment-request/{}/cancel", request_id));
+    let (status, _) = call(&app, authorized(request, &app_state, &bola)).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(
+        payment_request(&app_state, &request_id).await.status,
+        "pending"
+    );
+
+    let request =
+        test::TestRequest::patch().uri(&format!("/api/payment-request/{}/decline", request_id));
+    let (status, _) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(
+        payment_request(&app_state, &request_id).await.status,
+        "pending"
+    );
+}
@@ -0,0 +1,192 @@
+mod common;
+
+use rust_decimal::Decimal;
+use serde_json::json;
+
+use common::paystack_mock::{MockPaystack, PaystackRoute};
+use common::test_env;
+use money_transfer::utils::payment_provider::{
+    self, ChargeRequest, ChargeStatus, PaymentProvider, ProviderError, TransferRequest,
+};
+use money_transfer::utils::paystack::Paystack;
+
+fn charge_request(reference: &str, amount: u64) -> ChargeRequest {
+    ChargeRequest {
+        reference: reference.to_string(),
+        email: String::from("ada@example.com"),
+        user_id: String::from("user-1"),
+        amount: amount.into(),
+    }
+}
+
+fn transfer_request(reference: &str, amount: u64) -> TransferRequest {
+    TransferRequest {
+        reference: reference.to_string(),
+        amount: amount.into(),
+        account_number: String::from("0123456789"),
+        bank_code: String::from("058"),
+        account_name: String::from("ADA LOVELACE"),
+        narration: String::from("Rent"),
+    }
+}
+
+#[actix_web::test]
+async fn charge_is_initialized_in_kobo_and_verified_in_naira() {
+    let mock = MockPaystack::start().await;
+    let paystack = Paystack::new(&test_env(&mock.base_url));
+
+    let initialization = paystack
+        .initialize_charge(&charge_request("ref-fund-1", 5000))
+        .await
+        .unwrap();
+    assert_eq!(initialization.provider, "paystack");
+    assert_eq!(initialization.reference, "ref-fund-1");
+    assert!(initialization.authorization_url.ends_with("ref-fund-1"));
+
+    let requests = mock.requests(PaystackRoute::InitializeTransaction);
+    assert_eq!(requests[0]["amount"], json!(500000));
+    assert_eq!(requests[0]["metadata"]["user_id"], json!("user-1"));
+
+    let pending = paystack.verify_charge("ref-fund-1").await.unwrap();
+    assert_eq!(pending.status, ChargeStatus::Pending);
+
+    mock.complete_charge("ref-fund-1");
+    let verification = paystack.verify_charge("ref-fund-1").await.unwrap();
+    assert_eq!(verification.status, ChargeStatus::Successful);
+    assert_eq!(verification.amount, Decimal::from(5000));
+    assert_eq!(verification.fees, Decimal::from(75));
+    assert_eq!(verification.user_id.as_deref(), Some("user-1"));
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn unknown_charge_is_not_successful() {
+    let mock = MockPaystack::start().await;
+    let paystack = Paystack::new(&test_env(&mock.base_url));
+
+    let verification = paystack.verify_charge("missing").await.unwrap();
+    assert_eq!(verification.status, ChargeStatus::Pending);
+    assert_eq!(verification.amount, Decimal::ZERO);
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn only_known_accounts_resolve() {
+    let mock = MockPaystack::start().await;
+    mock.add_account("0123456789", "058", "ADA LOVELACE");
+    let paystack = Paystack::new(&test_env(&mock.base_url));
+
+    let account = paystack.resolve_account("0123456789", "058").await.unwrap();
+    assert_eq!(account.account_name, "ADA LOVELACE");
+
+    let unknown = paystack.resolve_account("9999999999", "058").await;
+    assert!(matches!(unknown, Err(ProviderError::Rejected(_))));
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn transfer_creates_a_recipient_and_sends_our_reference() {
+    let mock = MockPaystack::start().await;
+    let paystack = Paystack::new(&test_env(&mock.base_url));
+
+    let initiation = paystack
+        .transfer(&transfer_request("ref-out-1", 2500))
+        .await
+        .unwrap();
+    assert_eq!(initiation.reference, "ref-out-1");
+
+    let transfers = mock.requests(PaystackRoute::Transfer);
+    assert_eq!(transfers.len(), 1);
+    assert_eq!(transfers[0]["amount"], json!(250000));
+    assert_eq!(transfers[0]["reference"], json!("ref-out-1"));
+    assert_eq!(transfers[0]["recipient"], json!("RCP_0123456789"));
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn scripted_rejection_is_reported_as_rejected() {
+    let mock = MockPaystack::start().await;
+    mock.script(
+        PaystackRoute::Transfer,
+        400,
+        json!({ "status": false, "message": "Your balance is not enough to fulfil this request" }),
+    );
+    let paystack = Paystack::new(&test_env(&mock.base_url));
+
+    let rejected = paystack
+        .transfer(&transfer_request("ref-out-2", 2500))
+        .await;
+    match rejected {
+        Err(ProviderError::Rejected(message)) => {
+            assert_eq!(message, "Your balance is not enough to fulfil this request")
+        }
+        other => panic!("Expected a rejection, got {:?}", other),
+    }
+
+    // Only the next call is scripted
+    assert!(paystack
+        .transfer(&transfer_request("ref-out-3", 2500))
+        .await
+        .is_ok());
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn refund_returns_the_refund_id() {
+    let mock = MockPaystack::start().await;
+    let paystack = Paystack::new(&test_env(&mock.base_url));
+
+    let refund = paystack
+        .refund("ref-fund-2", Decimal::from(120))
+        .await
+        .unwrap();
+    assert_eq!(refund.reference.as_deref(), Some("3018284"));
+    assert_eq!(
+        mock.requests(PaystackRoute::Refund)[0],
+        json!({ "transaction": "ref-fund-2", "amount": 12000 })
+    );
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn charge_fails_over_when_a_provider_is_unreachable() {
+    let mock = MockPaystack::start().await;
+    let mut env = test_env(&mock.base_url);
+    env.flutterwave_base_url = String::from("http://127.0.0.1:1");
+    env.flutterwave_secret = String::from("FLWSECK_TEST-unreachable");
+    env.charge_providers = vec![String::from("flutterwave"), String::from("paystack")];
+
+    let initialization =
+        payment_provider::initialize_charge(&charge_request("ref-fund-3", 1000), &env)
+            .await
+            .unwrap();
+    assert_eq!(initialization.provider, "paystack");
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn rejected_transfer_is_not_retried_with_another_provider() {
+    let mock = MockPaystack::start().await;
+    mock.script(
+        PaystackRoute::Transfer,
+        400,
+        json!({ "status": false, "message": "Transfer limit exceeded" }),
+    );
+    let mut env = test_env(&mock.base_url);
+    env.flutterwave_base_url = mock.base_url.to_string();
+    env.flutterwave_secret = String::from("FLWSECK_TEST-mock");
+    env.transfer_providers = vec![String::from("paystack"), String::from("flutterwave")];
+
+    let result = payment_provider::transfer(&transfer_request("ref-out-4", 2500), &env).await;
+    assert!(matches!(result, Err(ProviderError::Rejected(_))));
+    assert_eq!(mock.requests(PaystackRoute::Transfer).len(), 1);
+
+    mock.stop().await;
+}
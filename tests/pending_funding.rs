@@ -0,0 +1,174 @@
+mod common;
+
+use rust_decimal::Decimal;
+use sea_orm::*;
+
+use common::paystack_mock::MockPaystack;
+use common::{seed_user, sqlite_app_state, test_env};
+use money_transfer::entities::{
+    prelude::Wallets, sea_orm_active_enums::Status, transactions, users, wallets,
+};
+use money_transfer::service::funding::{
+    find_funding, process_stale_fundings, record_pending_funding, settle_funding, FundingOutcome,
+};
+use money_transfer::service::p2p_transfer::{P2PTransfer, P2PTransferTrait};
+use money_transfer::service::wallet_reconciliation::reconcile_wallet;
+use money_transfer::utils::paystack::Paystack;
+use money_transfer::AppState;
+
+async fn start_funding(
+    app_state: &AppState,
+    mock: &MockPaystack,
+    user: &users::Model,
+    reference: &str,
+    amount: i64,
+) -> transactions::Model {
+    mock.add_pending_charge(reference, amount * 100, &user.uuid, &user.email);
+    record_pending_funding(
+        &app_state.db,
+        user,
+        "paystack",
+        reference,
+        Decimal::from(amount),
+    )
+    .await
+    .unwrap()
+}
+
+async fn fetch_wallet(db: &DatabaseConnection, wallet_id: &String) -> wallets::Model {
+    Wallets::find()
+        .filter(wallets::Column::Uuid.eq(wallet_id))
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+async fn funding_status(db: &DatabaseConnection, reference: &str) -> Option<Status> {
+    find_funding(db, reference).await.unwrap().unwrap().status
+}
+
+#[actix_web::test]
+async fn settled_funding_keeps_the_ledger_in_order() {
+    let mock = MockPaystack::start().await;
+    let app_state = sqlite_app_state(test_env(&mock.base_url)).await;
+    let (ada, ada_wallet) = seed_user(&app_state.db, "Ada").await;
+    let paystack = Paystack::new(&app_state.env);
+
+    let first = start_funding(&app_state, &mock, &ada, "ref-first", 5000).await;
+    assert_eq!(first.status, Some(Status::Pending));
+    assert_eq!(
+        fetch_wallet(&app_state.db, &ada_wallet.uuid)
+            .await
+            .current_balance,
+        Decimal::ZERO
+    );
+
+    // Not paid yet, nothing to credit
+    let outcome = settle_funding(&paystack, "ref-first", &app_state.db).await;
+    assert_eq!(outcome.unwrap(), FundingOutcome::Pending);
+
+    // A second funding is paid and spent while the first is still pending
+    start_funding(&app_state, &mock, &ada, "ref-second", 2000).await;
+    mock.complete_charge("ref-second");
+    let outcome = settle_funding(&paystack, "ref-second", &app_state.db).await;
+    assert_eq!(outcome.unwrap(), FundingOutcome::Credited);
+
+    let (bola, _) = seed_user(&app_state.db, "Bola").await;
+    let transfer = P2PTransfer {
+        sender: ada.clone(),
+        receiver_id: bola.uuid.to_string(),
+        amount: Decimal::from(500),
+        narration: None,
+    };
+    transfer.transfer(&app_state.db).await.unwrap();
+
+    mock.complete_charge("ref-first");
+    let outcome = settle_funding(&paystack, "ref-first", &app_state.db).await;
+    assert_eq!(outcome.unwrap(), FundingOutcome::Credited);
+    let outcome = settle_funding(&paystack, "ref-first", &app_state.db).await;
+    assert_eq!(outcome.unwrap(), FundingOutcome::AlreadySettled);
+
+    let credited = find_funding(&app_state.db, "ref-first")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(credited.uuid, first.uuid);
+    assert_eq!(credited.status, Some(Status::Successful));
+    assert_eq!(credited.previous_balance, Decimal::from(1500));
+    assert_eq!(credited.current_balance, Decimal::from(6500));
+    assert_eq!(
+        fetch_wallet(&app_state.db, &ada_wallet.uuid)
+            .await
+            .current_balance,
+        Decimal::from(6500)
+    );
+
+    let mismatches = reconcile_wallet(&app_state.db, &ada_wallet.uuid)
+        .await
+        .unwrap();
+    assert!(mismatches.is_empty(), "{:?}", mismatches);
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn stale_fundings_are_polled_then_abandoned() {
+    let mock = MockPaystack::start().await;
+    let mut env = test_env(&mock.base_url);
+    env.funding_stale_after_mins = 0;
+    let mut app_state = sqlite_app_state(env).await;
+    let (ada, ada_wallet) = seed_user(&app_state.db, "Ada").await;
+
+    start_funding(&app_state, &mock, &ada, "ref-paid", 3000).await;
+    start_funding(&app_state, &mock, &ada, "ref-unpaid", 4000).await;
+    start_funding(&app_state, &mock, &ada, "ref-declined", 2500).await;
+    mock.complete_charge("ref-paid");
+    mock.fail_charge("ref-declined");
+
+    // The webhook for ref-paid never arrived, and a declined charge doesn't wait for the abandon window
+    let summary = process_stale_fundings(&app_state).await.unwrap();
+    assert_eq!(summary.checked, 3);
+    assert_eq!(summary.credited, 1);
+    assert_eq!(summary.failed, 1);
+    assert_eq!(summary.abandoned, 0);
+    assert_eq!(
+        funding_status(&app_state.db, "ref-declined").await,
+        Some(Status::Failed)
+    );
+    assert_eq!(
+        funding_status(&app_state.db, "ref-paid").await,
+        Some(Status::Successful)
+    );
+    assert_eq!(
+        funding_status(&app_state.db, "ref-unpaid").await,
+        Some(Status::Pending)
+    );
+
+    // Past the abandon window the unpaid funding is given up on
+    app_state.env.funding_abandon_after_mins = 0;
+    let summary = process_stale_fundings(&app_state).await.unwrap();
+    assert_eq!(summary.checked, 1);
+    assert_eq!(summary.abandoned, 1);
+    assert_eq!(
+        funding_status(&app_state.db, "ref-unpaid").await,
+        Some(Status::Failed)
+    );
+
+    let summary = process_stale_fundings(&app_state).await.unwrap();
+    assert_eq!(summary.checked, 0);
+
+    // Paid after all, a late webhook still credits it
+    mock.complete_charge("ref-unpaid");
+    let paystack = Paystack::new(&app_state.env);
+    let outcome = settle_funding(&paystack, "ref-unpaid", &app_state.db).await;
+    assert_eq!(outcome.unwrap(), FundingOutcome::Credited);
+    assert_eq!(
+        fetch_wallet(&app_state.db, &ada_wallet.uuid)
+            .await
+            .current_balance,
+        Decimal::from(7000)
+    );
+
+    mock.stop().await;
+}
@@ -0,0 +1,329 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, web, App};
+use chrono::{Duration, Utc};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::{json, Value};
+
+use common::{
+    amount, authorized, call, seed_payer, seed_user, sqlite_app_state, test_env, wallet_of,
+    wallet_transactions, PIN,
+};
+use money_transfer::configure_app;
+use money_transfer::entities::{
+    prelude::{QrCodes, Transactions},
+    qr_codes,
+};
+use money_transfer::service::wallet_reconciliation::find_mismatches;
+use money_transfer::utils::helpers::sign_payload;
+
+fn pay(payload: &str, amount: Option<u64>) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/api/qr/pay")
+        .set_json(json!({
+            "payload": payload,
+            "pin": PIN,
+            "amount": amount,
+        }))
+}
+
+#[actix_web::test]
+async fn static_qr_codes_pay_the_wallet_behind_them() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let shop = seed_payer(&app_state, "Shop", 0).await;
+    let ada = seed_payer(&app_state, "Ada", 10000).await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/merchant")
+        .set_json(json!({ "business_name": "Shop Ventures" }));
+    call(&app, authorized(request, &app_state, &shop)).await;
+
+    let request = test::TestRequest::get().uri("/api/qr/static");
+    let (status, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let open_amount = body["data"]["qr_code"]["payload"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // The payer sees who they are paying before confirming
+    let request = test::TestRequest::post()
+        .uri("/api/qr/decode")
+        .set_json(json!({ "payload": open_amount }));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["qr_code"]["kind"], json!("static"));
+    assert_eq!(
+        body["data"]["qr_code"]["payee_name"],
+        json!("Shop Ventures")
+    );
+    assert_eq!(body["data"]["qr_code"]["amount"], Value::Null);
+
+    let (status, body) = call(&app, authorized(pay(&open_amount, None), &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body["message"],
+        json!("Please enter the amount you want to pay")
+    );
+
+    let (status, body) = call(
+        &app,
+        authorized(pay(&open_amount, Some(2500)), &app_state, &ada),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(
+        body["data"]["receipt"]["payee_name"],
+        json!("Shop Ventures")
+    );
+
+    let (status, body) = call(
+        &app,
+        authorized(pay(&open_amount, Some(2500)), &app_state, &shop),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], json!("Cannot send funds to yourself"));
+
+    let request = test::TestRequest::get().uri("/api/qr/static?amount=1500");
+    let (_, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    let fixed_amount = body["data"]["qr_code"]["payload"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let (status, body) = call(
+        &app,
+        authorized(pay(&fixed_amount, Some(1000)), &app_state, &ada),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], json!("This QR code is for 1500"));
+
+    // Changing the amount breaks the signature
+    let tampered = fixed_amount.replace("|1500|", "|15|");
+    let (status, body) = call(&app, authorized(pay(&tampered, None), &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], json!("This QR code is not valid"));
+
+    // The app key signs the JWTs, not the QR codes
+    let (content, _) = fixed_amount.rsplit_once('|').unwrap();
+    let signed_with_app_key = format!(
+        "{}|{}",
+        content,
+        sign_payload(content, &app_state.env.app_key)
+    );
+    let (status, body) = call(
+        &app,
+        authorized(pay(&signed_with_app_key, None), &app_state, &ada),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], json!("This QR code is not valid"));
+
+    let (status, body) = call(&app, authorized(pay(&fixed_amount, None), &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(
+        amount(&body["data"]["receipt"]["amount"]),
+        Decimal::from(1500)
+    );
+
+    // Plain P2P transfers, no merchant fee
+    let shop_wallet = wallet_of(&app_state.db, &shop).await;
+    assert_eq!(shop_wallet.current_balance, Decimal::from(4000));
+    let ada_wallet = wallet_of(&app_state.db, &ada).await;
+    assert_eq!(ada_wallet.current_balance, Decimal::from(6000));
+    let txns = wallet_transactions(&app_state.db, &ada_wallet).await;
+    assert_eq!(txns.len(), 2);
+    assert!(txns.iter().all(|txn| txn.category == "p2p"));
+    let txns = wallet_transactions(&app_state.db, &shop_wallet).await;
+    assert!(find_mismatches(&shop_wallet, &txns).is_empty());
+}
+
+#[actix_web::test]
+async fn qr_codes_render_as_png_and_svg() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let (shop, _) = seed_user(&app_state.db, "Shop").await;
+
+    let request = test::TestRequest::get().uri("/api/qr/static/image?amount=2000");
+    let response = test::call_service(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("content-type").unwrap(), "image/png");
+    let png = test::read_body(response).await;
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+    let request = test::TestRequest::post()
+        .uri("/api/qr/dynamic")
+        .set_json(json!({ "amount": 3000 }));
+    let (_, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    let qr_code_id = body["data"]["qr_code"]["uuid"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let request =
+        test::TestRequest::get().uri(&format!("/api/qr/dynamic/{}/image?format=svg", qr_code_id));
+    let response = test::call_service(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "image/svg+xml"
+    );
+    let svg = String::from_utf8(test::read_body(response).await.to_vec()).unwrap();
+    assert!(svg.contains("<svg"));
+
+    let request = test::TestRequest::get().uri("/api/qr/static/image?format=gif");
+    let (status, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], json!("Image format must be png or svg"));
+
+    // Someone else's code
+    let (ada, _) = seed_user(&app_state.db, "Ada").await;
+    let request = test::TestRequest::get().uri(&format!("/api/qr/dynamic/{}/image", qr_code_id));
+    let (status, _) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn dynamic_qr_codes_can_only_be_paid_once() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let shop = seed_payer(&app_state, "Shop", 0).await;
+    let ada = seed_payer(&app_state, "Ada", 10000).await;
+    let bola = seed_payer(&app_state, "Bola", 10000).await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/qr/dynamic")
+        .set_json(json!({ "amount": 3000, "description": "Table 4" }));
+    let (status, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    assert_eq!(body["data"]["qr_code"]["status"], json!("active"));
+    let qr_code_id = body["data"]["qr_code"]["uuid"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let payload = body["data"]["payload"].as_str().unwrap().to_string();
+
+    // The amount is fixed, the payer's is only checked against it
+    let (status, body) = call(&app, authorized(pay(&payload, None), &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(
+        amount(&body["data"]["receipt"]["amount"]),
+        Decimal::from(3000)
+    );
+
+    let (status, body) = call(&app, authorized(pay(&payload, None), &app_state, &bola)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], json!("This QR code has already been used"));
+
+    let qr_code = QrCodes::find_by_id(&qr_code_id)
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(qr_code.status, "used");
+    assert_eq!(qr_code.paid_by, Some(ada.uuid.to_string()));
+    let debit = Transactions::find_by_id(qr_code.transaction_id.unwrap())
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(debit.user_id, ada.uuid);
+    assert!(debit.description.starts_with("Table 4"));
+
+    let request = test::TestRequest::patch().uri(&format!("/api/qr/dynamic/{}/cancel", qr_code_id));
+    let (status, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body["message"],
+        json!("Only unused QR codes can be cancelled")
+    );
+
+    // A failed payment gives the code back
+    let request = test::TestRequest::post()
+        .uri("/api/qr/dynamic")
+        .set_json(json!({ "amount": 8000 }));
+    let (_, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    let too_much = body["data"]["payload"].as_str().unwrap().to_string();
+    let too_much_id = body["data"]["qr_code"]["uuid"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let (status, body) = call(&app, authorized(pay(&too_much, None), &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], json!("Insufficient Funds"));
+    let (status, body) = call(&app, authorized(pay(&too_much, None), &app_state, &bola)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let request = test::TestRequest::get().uri(&format!("/api/qr/dynamic/{}", too_much_id));
+    let (_, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(body["data"]["qr_code"]["paid_by"], json!(bola.uuid));
+
+    let request = test::TestRequest::post()
+        .uri("/api/qr/dynamic")
+        .set_json(json!({ "amount": 500 }));
+    let (_, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    let cancelled = body["data"]["payload"].as_str().unwrap().to_string();
+    let cancelled_id = body["data"]["qr_code"]["uuid"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let request =
+        test::TestRequest::patch().uri(&format!("/api/qr/dynamic/{}/cancel", cancelled_id));
+    let (status, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (status, body) = call(&app, authorized(pay(&cancelled, None), &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], json!("This QR code has been cancelled"));
+
+    let request = test::TestRequest::post()
+        .uri("/api/qr/dynamic")
+        .set_json(json!({ "amount": 500, "expires_in_mins": 5 }));
+    let (_, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    let expired = body["data"]["payload"].as_str().unwrap().to_string();
+    let expired_id = body["data"]["qr_code"]["uuid"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let qr_code = QrCodes::find_by_id(expired_id)
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut past: qr_codes::ActiveModel = qr_code.into();
+    past.expires_at = Set(Utc::now() - Duration::minutes(1));
+    past.update(&app_state.db).await.unwrap();
+
+    let request = test::TestRequest::post()
+        .uri("/api/qr/decode")
+        .set_json(json!({ "payload": expired }));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], json!("This QR code has expired"));
+
+    let request = test::TestRequest::get().uri("/api/qr/dynamic");
+    let (_, body) = call(&app, authorized(request, &app_state, &shop)).await;
+    assert_eq!(body["data"]["qr_codes"].as_array().unwrap().len(), 4);
+
+    let shop_wallet = wallet_of(&app_state.db, &shop).await;
+    assert_eq!(shop_wallet.current_balance, Decimal::from(11000));
+    let txns = wallet_transactions(&app_state.db, &shop_wallet).await;
+    assert!(find_mismatches(&shop_wallet, &txns).is_empty());
+}
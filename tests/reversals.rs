@@ -0,0 +1,240 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, web, App};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use common::fake_mailer::FakeMailer;
+use common::paystack_mock::{MockPaystack, PaystackRoute};
+use common::{
+    amount, authorized, call, charge_success_event, paystack_webhook_request, seed_payer,
+    sqlite_app_state, test_env, wallet_of, wallet_transactions, PAYSTACK_SECRET, PIN,
+};
+use money_transfer::entities::{
+    prelude::{TransactionReversals, Transactions},
+    sea_orm_active_enums::{Status, TrxType},
+    transaction_reversals, transactions,
+    users::{self, UserRole},
+};
+use money_transfer::service::notification::Notifier;
+use money_transfer::service::outbox::{dead_messages, process_outbox};
+use money_transfer::service::reversal::{refund_reversal, RefundJob};
+use money_transfer::AppState;
+
+async fn seed_admin(app_state: &AppState) -> users::Model {
+    let admin = seed_payer(app_state, "Admin", 0).await;
+    let mut admin: users::ActiveModel = admin.into();
+    admin.role = Set(UserRole::Admin.to_string());
+    admin.update(&app_state.db).await.unwrap()
+}
+
+async fn transaction(app_state: &AppState, uuid: &str) -> transactions::Model {
+    Transactions::find()
+        .filter(transactions::Column::Uuid.eq(uuid))
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+async fn reversal(app_state: &AppState, uuid: &str) -> transaction_reversals::Model {
+    TransactionReversals::find()
+        .filter(transaction_reversals::Column::Uuid.eq(uuid))
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+fn reverse_request(transaction_id: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(&format!(
+            "/api/admin/transactions/{}/reverse",
+            transaction_id
+        ))
+        .set_json(json!({ "reason": "Charged back by the issuer" }))
+}
+
+fn p2p_request(receiver: &users::Model, amount: u64) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/api/transfer/p2p")
+        .set_json(json!({ "amount": amount, "pin": PIN, "receiver_id": &receiver.uuid }))
+}
+
+#[actix_web::test]
+async fn funding_reversals_refund_the_card_only_after_the_ledger_is_committed() {
+    let mock = MockPaystack::start().await;
+    let app_state = sqlite_app_state(test_env(&mock.base_url)).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(money_transfer::configure_app),
+    )
+    .await;
+    let notifier = Notifier::new(Arc::new(FakeMailer::default()));
+    let admin = seed_admin(&app_state).await;
+    let ada = seed_payer(&app_state, "Ada", 0).await;
+    let bola = seed_payer(&app_state, "Bola", 0).await;
+
+    mock.add_charge("ref-fund-1", 500000, &ada.uuid, &ada.email);
+    let webhook =
+        paystack_webhook_request(PAYSTACK_SECRET, &charge_success_event("ref-fund-1", 500000));
+    let (status, _) = call(&app, webhook.to_request()).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Ada spends most of the funding before it is charged back
+    let (status, body) = call(&app, authorized(p2p_request(&bola, 3000), &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let ada_wallet = wallet_of(&app_state.db, &ada).await;
+    let funding = wallet_transactions(&app_state.db, &ada_wallet)
+        .await
+        .into_iter()
+        .find(|transaction| transaction.trx_type == Some(TrxType::Credit))
+        .unwrap();
+
+    let (status, body) = call(
+        &app,
+        authorized(reverse_request(&funding.uuid), &app_state, &admin),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let created: &Value = &body["data"]["reversal"];
+    assert_eq!(created["status"], "refund_pending");
+    assert_eq!(amount(&created["recovered_amount"]), Decimal::from(2000));
+    assert_eq!(amount(&created["shortfall_amount"]), Decimal::from(3000));
+    let reversal_id = created["uuid"].as_str().unwrap().to_string();
+
+    // The wallet side is committed and nothing has been sent to Paystack yet
+    assert!(mock.requests(PaystackRoute::Refund).is_empty());
+    assert_eq!(
+        wallet_of(&app_state.db, &ada).await.current_balance,
+        Decimal::ZERO
+    );
+    assert_eq!(
+        transaction(&app_state, &funding.uuid).await.status,
+        Some(Status::PartiallyReversed)
+    );
+
+    let (status, body) = call(
+        &app,
+        authorized(reverse_request(&funding.uuid), &app_state, &admin),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "Transaction has already been reversed");
+
+    // Paystack turns the first attempt down, the message is left for an admin to retry
+    mock.script(
+        PaystackRoute::Refund,
+        400,
+        json!({ "status": false, "message": "Transaction has been fully reversed" }),
+    );
+    process_outbox(&app_state, &notifier).await.unwrap();
+    assert_eq!(
+        reversal(&app_state, &reversal_id).await.status,
+        "refund_failed"
+    );
+
+    let dead = dead_messages(&app_state.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|message| message.kind == "refund")
+        .unwrap();
+    let retry = test::TestRequest::post().uri(&format!("/api/admin/outbox/{}/retry", dead.uuid));
+    let (status, body) = call(&app, authorized(retry, &app_state, &admin)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    process_outbox(&app_state, &notifier).await.unwrap();
+
+    let refunded = reversal(&app_state, &reversal_id).await;
+    assert_eq!(refunded.status, "partial");
+    assert_eq!(
+        refunded.provider_refund_reference.as_deref(),
+        Some("3018284")
+    );
+
+    // Only what was taken back from the wallet goes back to the card
+    let refunds = mock.requests(PaystackRoute::Refund);
+    assert_eq!(refunds.len(), 2);
+    assert_eq!(refunds[1]["transaction"], "ref-fund-1");
+    assert_eq!(refunds[1]["amount"], 200000);
+
+    // A refund interrupted after it was submitted is not sent again when redelivered
+    let mut submitted: transaction_reversals::ActiveModel = refunded.into();
+    submitted.status = Set(String::from("refund_submitted"));
+    submitted.update(&app_state.db).await.unwrap();
+    let job = RefundJob {
+        reversal_id: reversal_id.to_string(),
+    };
+    refund_reversal(&app_state, &job).await.unwrap();
+    assert_eq!(mock.requests(PaystackRoute::Refund).len(), 2);
+    assert_eq!(
+        reversal(&app_state, &reversal_id).await.status,
+        "refund_submitted"
+    );
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn p2p_reversals_recover_what_the_receiver_still_holds() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(money_transfer::configure_app),
+    )
+    .await;
+    let admin = seed_admin(&app_state).await;
+    let ada = seed_payer(&app_state, "Ada", 10000).await;
+    let bola = seed_payer(&app_state, "Bola", 0).await;
+    let chi = seed_payer(&app_state, "Chi", 0).await;
+
+    let (status, body) = call(&app, authorized(p2p_request(&bola, 4000), &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (status, body) = call(&app, authorized(p2p_request(&chi, 3000), &app_state, &bola)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let ada_wallet = wallet_of(&app_state.db, &ada).await;
+    let debit = wallet_transactions(&app_state.db, &ada_wallet)
+        .await
+        .remove(0);
+
+    let (status, body) = call(
+        &app,
+        authorized(reverse_request(&debit.uuid), &app_state, &admin),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["reversal"]["status"], "partial");
+    assert_eq!(
+        amount(&body["data"]["reversal"]["recovered_amount"]),
+        Decimal::from(1000)
+    );
+
+    assert_eq!(
+        wallet_of(&app_state.db, &ada).await.current_balance,
+        Decimal::from(7000)
+    );
+    assert_eq!(
+        wallet_of(&app_state.db, &bola).await.current_balance,
+        Decimal::ZERO
+    );
+    let credit = transaction(&app_state, debit.provider_reference.as_deref().unwrap()).await;
+    assert_eq!(
+        transaction(&app_state, &debit.uuid).await.status,
+        Some(Status::PartiallyReversed)
+    );
+    assert_eq!(credit.status, Some(Status::PartiallyReversed));
+
+    // Only admins can reverse
+    let (status, _) = call(
+        &app,
+        authorized(reverse_request(&debit.uuid), &app_state, &ada),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
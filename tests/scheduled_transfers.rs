@@ -0,0 +1,162 @@
+mod common;
+
+use actix_http::Request;
+use actix_web::{
+    body::MessageBody,
+    dev::{Service, ServiceResponse},
+    http::StatusCode,
+    test, web, App,
+};
+use chrono::{Duration, Utc};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::{json, Value};
+
+use common::{
+    authorized, call, seed_payer, sqlite_app_state, test_env, wallet_of, wallet_transactions, PIN,
+};
+use money_transfer::entities::{prelude::ScheduledTransfers, scheduled_transfers, users};
+use money_transfer::service::scheduled_transfer::process_due_transfers;
+use money_transfer::{configure_app, AppState};
+
+async fn schedule_transfer<S, B>(
+    app: &S,
+    app_state: &AppState,
+    sender: &users::Model,
+    body: Value,
+) -> scheduled_transfers::Model
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let request = test::TestRequest::post()
+        .uri("/api/transfer/scheduled")
+        .set_json(body);
+    let (status, body) = call(app, authorized(request, app_state, sender)).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+
+    let uuid = body["data"]["scheduled_transfer"]["uuid"].as_str().unwrap();
+    ScheduledTransfers::find_by_id(uuid)
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+// Moves the next run into the past so the worker picks it up
+async fn make_due(app_state: &AppState, schedule: scheduled_transfers::Model) {
+    let mut due: scheduled_transfers::ActiveModel = schedule.into();
+    due.next_run_at = Set(Some(Utc::now() - Duration::minutes(1)));
+    due.update(&app_state.db).await.unwrap();
+}
+
+async fn reload(
+    app_state: &AppState,
+    schedule: &scheduled_transfers::Model,
+) -> scheduled_transfers::Model {
+    ScheduledTransfers::find_by_id(&schedule.uuid)
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn due_transfers_run_once_even_when_workers_overlap() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let ada = seed_payer(&app_state, "Ada", 10000).await;
+    let bola = seed_payer(&app_state, "Bola", 0).await;
+
+    let schedule = schedule_transfer(
+        &app,
+        &app_state,
+        &ada,
+        json!({
+            "amount": 2500,
+            "pin": PIN,
+            "receiver_id": &bola.uuid,
+            "run_at": Utc::now() + Duration::hours(1),
+        }),
+    )
+    .await;
+    make_due(&app_state, schedule.clone()).await;
+
+    // Two workers polling the same due row
+    let (first, second) = tokio::join!(
+        process_due_transfers(&app_state),
+        process_due_transfers(&app_state)
+    );
+    assert_eq!(first.unwrap() + second.unwrap(), 1);
+    assert_eq!(process_due_transfers(&app_state).await.unwrap(), 0);
+
+    let ada_wallet = wallet_of(&app_state.db, &ada).await;
+    assert_eq!(ada_wallet.current_balance, Decimal::from(7500));
+    assert_eq!(
+        wallet_transactions(&app_state.db, &ada_wallet).await.len(),
+        1
+    );
+    let bola_wallet = wallet_of(&app_state.db, &bola).await;
+    assert_eq!(bola_wallet.current_balance, Decimal::from(2500));
+
+    let schedule = reload(&app_state, &schedule).await;
+    assert_eq!(schedule.status, "completed");
+    assert_eq!(schedule.run_count, 1);
+    assert!(schedule.next_run_at.is_none());
+}
+
+#[actix_web::test]
+async fn recurring_transfers_move_on_and_retry_when_short_of_funds() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let ada = seed_payer(&app_state, "Ada", 3000).await;
+    let bola = seed_payer(&app_state, "Bola", 0).await;
+
+    let schedule = schedule_transfer(
+        &app,
+        &app_state,
+        &ada,
+        json!({
+            "amount": 2000,
+            "pin": PIN,
+            "receiver_id": &bola.uuid,
+            "cron_expression": "0 9 * * *",
+        }),
+    )
+    .await;
+
+    make_due(&app_state, schedule.clone()).await;
+    assert_eq!(process_due_transfers(&app_state).await.unwrap(), 1);
+
+    let schedule = reload(&app_state, &schedule).await;
+    assert_eq!(schedule.status, "active");
+    assert_eq!(schedule.run_count, 1);
+    assert!(schedule.next_run_at.unwrap() > Utc::now());
+
+    // Not due yet, nothing happens
+    process_due_transfers(&app_state).await.unwrap();
+    let ada_wallet = wallet_of(&app_state.db, &ada).await;
+    assert_eq!(ada_wallet.current_balance, Decimal::from(1000));
+
+    // The next run can't be covered, so it's pushed back and counted as a retry
+    make_due(&app_state, schedule.clone()).await;
+    assert_eq!(process_due_transfers(&app_state).await.unwrap(), 0);
+
+    let schedule = reload(&app_state, &schedule).await;
+    assert_eq!(schedule.status, "active");
+    assert_eq!(schedule.run_count, 1);
+    assert_eq!(schedule.retry_count, 1);
+    assert_eq!(schedule.last_error.as_deref(), Some("Insufficient Funds"));
+    let ada_wallet = wallet_of(&app_state.db, &ada).await;
+    assert_eq!(ada_wallet.current_balance, Decimal::from(1000));
+}
@@ -0,0 +1,296 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use rust_decimal::Decimal;
+use sea_orm::*;
+
+use common::paystack_mock::{MockPaystack, PaystackRoute};
+use common::{seed_user, sqlite_app_state, test_env};
+use money_transfer::entities::{
+    prelude::Wallets,
+    sea_orm_active_enums::{Status, TrxType},
+    wallets,
+};
+use money_transfer::service::settlement_reconciliation::{
+    reconcile_paystack_settlements, DiscrepancyKind, SettlementError, SettlementReconciliation,
+    SettlementReport,
+};
+use money_transfer::service::transaction_balance::{
+    TransactionBalance, TransactionBalanceTrait, TrxCategory,
+};
+use money_transfer::AppState;
+
+// Applies a transaction on top of the wallet's current balance, the way transfers do
+async fn record(
+    db: &DatabaseConnection,
+    wallet_id: &String,
+    reference: &str,
+    trx_type: TrxType,
+    status: Status,
+    amount: i64,
+    provider_fees: i64,
+) {
+    let wallet = fetch_wallet(db, wallet_id).await;
+    let amount = Decimal::from(amount);
+    let (current_balance, category) = match trx_type {
+        TrxType::Credit => (wallet.current_balance + amount, TrxCategory::Funding),
+        TrxType::Debit => (wallet.current_balance - amount, TrxCategory::Outward),
+    };
+
+    let txn = db.begin().await.unwrap();
+    TransactionBalance {
+        uuid: reference.to_string(),
+        amount,
+        trx_type,
+        status,
+        description: String::from("Test transaction"),
+        provider_reference: Some(reference.to_string()),
+        current_balance,
+        previous_balance: wallet.current_balance,
+        user_id: wallet.user_id.to_string(),
+        wallet_id: wallet.uuid.to_string(),
+        provider: String::from("paystack"),
+        fees: None,
+        provider_fees: Some(Decimal::from(provider_fees)),
+        category,
+        meta: None,
+    }
+    .save_transaction_update_balance(&txn)
+    .await
+    .unwrap();
+    txn.commit().await.unwrap();
+}
+
+async fn record_funding(
+    db: &DatabaseConnection,
+    wallet_id: &String,
+    reference: &str,
+    amount: i64,
+    provider_fees: i64,
+) {
+    record(
+        db,
+        wallet_id,
+        reference,
+        TrxType::Credit,
+        Status::Successful,
+        amount,
+        provider_fees,
+    )
+    .await
+}
+
+async fn record_outward(
+    db: &DatabaseConnection,
+    wallet_id: &String,
+    reference: &str,
+    status: Status,
+    amount: i64,
+) {
+    record(db, wallet_id, reference, TrxType::Debit, status, amount, 0).await
+}
+
+async fn fetch_wallet(db: &DatabaseConnection, wallet_id: &String) -> wallets::Model {
+    Wallets::find()
+        .filter(wallets::Column::Uuid.eq(wallet_id))
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+// SQLite compares timestamps as text, so the period spans whole days either side of now
+async fn reconcile(app_state: &AppState, auto_credit: bool) -> SettlementReport {
+    reconcile_paystack_settlements(
+        app_state,
+        SettlementReconciliation {
+            from: Utc::now() - Duration::days(1),
+            to: Utc::now() + Duration::days(1),
+            auto_credit,
+        },
+    )
+    .await
+    .unwrap()
+}
+
+fn kinds(report: &SettlementReport, reference: &str) -> Vec<DiscrepancyKind> {
+    report
+        .discrepancies
+        .iter()
+        .filter(|discrepancy| discrepancy.reference == reference)
+        .map(|discrepancy| discrepancy.kind.clone())
+        .collect()
+}
+
+#[actix_web::test]
+async fn charges_and_transfers_are_matched_against_paystack() {
+    let mock = MockPaystack::start().await;
+    let app_state = sqlite_app_state(test_env(&mock.base_url)).await;
+    let (ada, wallet) = seed_user(&app_state.db, "Ada").await;
+    let db = &app_state.db;
+
+    // Paystack takes 1.5% on charges
+    mock.add_charge("ref-ok", 500000, &ada.uuid, &ada.email);
+    record_funding(db, &wallet.uuid, "ref-ok", 5000, 75).await;
+    mock.add_charge("ref-fee", 200000, &ada.uuid, &ada.email);
+    record_funding(db, &wallet.uuid, "ref-fee", 2000, 0).await;
+    mock.add_charge("ref-missed", 300000, &ada.uuid, &ada.email);
+    record_funding(db, &wallet.uuid, "ref-unknown", 1000, 15).await;
+
+    mock.add_transfer("trf-ok", 100000, "success");
+    record_outward(db, &wallet.uuid, "trf-ok", Status::Successful, 1000).await;
+    record_outward(db, &wallet.uuid, "trf-missing", Status::Pending, 500).await;
+    mock.add_transfer("trf-failed", 50000, "failed");
+    record_outward(db, &wallet.uuid, "trf-failed", Status::Successful, 500).await;
+    mock.add_transfer("trf-unknown", 70000, "success");
+
+    let report = reconcile(&app_state, false).await;
+    assert_eq!(report.charges_checked, 3);
+    assert_eq!(report.transfers_checked, 3);
+    assert_eq!(report.credited, 0);
+    assert_eq!(report.discrepancies.len(), 6);
+
+    assert!(kinds(&report, "ref-ok").is_empty());
+    assert!(kinds(&report, "trf-ok").is_empty());
+    assert_eq!(
+        kinds(&report, "ref-fee"),
+        vec![DiscrepancyKind::ChargeFeeMismatch]
+    );
+    assert_eq!(
+        kinds(&report, "ref-missed"),
+        vec![DiscrepancyKind::MissedCharge]
+    );
+    assert_eq!(
+        kinds(&report, "ref-unknown"),
+        vec![DiscrepancyKind::UnknownCredit]
+    );
+    assert_eq!(
+        kinds(&report, "trf-missing"),
+        vec![DiscrepancyKind::MissingTransfer]
+    );
+    assert_eq!(
+        kinds(&report, "trf-failed"),
+        vec![DiscrepancyKind::TransferStatusMismatch]
+    );
+    assert_eq!(
+        kinds(&report, "trf-unknown"),
+        vec![DiscrepancyKind::UnknownTransfer]
+    );
+
+    let fee_mismatch = report
+        .discrepancies
+        .iter()
+        .find(|discrepancy| discrepancy.reference == "ref-fee")
+        .unwrap();
+    assert_eq!(fee_mismatch.recorded.as_deref(), Some("0"));
+    assert_eq!(fee_mismatch.provider.as_deref(), Some("30"));
+
+    // Report only, nothing was credited
+    assert_eq!(
+        fetch_wallet(db, &wallet.uuid).await.current_balance,
+        Decimal::from(6000)
+    );
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn missed_charges_can_be_auto_credited() {
+    let mock = MockPaystack::start().await;
+    let app_state = sqlite_app_state(test_env(&mock.base_url)).await;
+    let (ada, wallet) = seed_user(&app_state.db, "Ada").await;
+
+    mock.add_charge("ref-missed", 300000, &ada.uuid, &ada.email);
+
+    let report = reconcile(&app_state, true).await;
+    assert_eq!(report.credited, 1);
+    assert_eq!(report.discrepancies.len(), 1);
+    assert_eq!(report.discrepancies[0].kind, DiscrepancyKind::MissedCharge);
+    assert!(report.discrepancies[0].credited);
+    assert_eq!(
+        fetch_wallet(&app_state.db, &wallet.uuid)
+            .await
+            .current_balance,
+        Decimal::from(3000)
+    );
+
+    // Credited with Paystack's fees, so a second run finds nothing
+    let report = reconcile(&app_state, true).await;
+    assert_eq!(report.credited, 0);
+    assert!(report.discrepancies.is_empty());
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn a_failed_lookup_is_reported_without_losing_the_rest() {
+    let mock = MockPaystack::start().await;
+    let app_state = sqlite_app_state(test_env(&mock.base_url)).await;
+    let (ada, wallet) = seed_user(&app_state.db, "Ada").await;
+    let db = &app_state.db;
+
+    mock.add_charge("ref-missed", 300000, &ada.uuid, &ada.email);
+    record_funding(db, &wallet.uuid, "ref-flaky", 1000, 15).await;
+    record_funding(db, &wallet.uuid, "ref-unknown", 2000, 30).await;
+    record_outward(db, &wallet.uuid, "trf-missing", Status::Pending, 500).await;
+
+    // Paystack's gateway fails the first lookup, the others go through
+    mock.script_raw(
+        PaystackRoute::VerifyTransaction,
+        502,
+        "<html>Bad Gateway</html>",
+    );
+
+    let report = reconcile(&app_state, false).await;
+    assert_eq!(report.discrepancies.len(), 4);
+    assert_eq!(
+        kinds(&report, "ref-flaky"),
+        vec![DiscrepancyKind::LookupFailed]
+    );
+    assert_eq!(
+        kinds(&report, "ref-unknown"),
+        vec![DiscrepancyKind::UnknownCredit]
+    );
+    assert_eq!(
+        kinds(&report, "ref-missed"),
+        vec![DiscrepancyKind::MissedCharge]
+    );
+    assert_eq!(
+        kinds(&report, "trf-missing"),
+        vec![DiscrepancyKind::MissingTransfer]
+    );
+
+    // Each listing is fetched once for the period and once around it
+    assert_eq!(mock.requests(PaystackRoute::ListTransactions).len(), 2);
+    assert_eq!(mock.requests(PaystackRoute::ListTransfers).len(), 2);
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn period_must_be_valid() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let now = Utc::now();
+
+    let reversed = reconcile_paystack_settlements(
+        &app_state,
+        SettlementReconciliation {
+            from: now,
+            to: now - Duration::days(1),
+            auto_credit: false,
+        },
+    )
+    .await;
+    assert!(matches!(reversed, Err(SettlementError::InvalidRange)));
+
+    let too_long = reconcile_paystack_settlements(
+        &app_state,
+        SettlementReconciliation {
+            from: now - Duration::days(60),
+            to: now,
+            auto_credit: false,
+        },
+    )
+    .await;
+    assert!(matches!(too_long, Err(SettlementError::RangeTooLong(31))));
+}
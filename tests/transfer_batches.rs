@@ -0,0 +1,217 @@
+mod common;
+
+use actix_http::Request;
+use actix_web::{
+    body::MessageBody,
+    dev::{Service, ServiceResponse},
+    http::StatusCode,
+    test, web, App,
+};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::{json, Value};
+
+use common::paystack_mock::{MockPaystack, PaystackRoute};
+use common::{
+    authorized, call, paystack_webhook_request, seed_payer, sqlite_app_state, test_env,
+    transfer_event, wallet_of, wallet_transactions, PAYSTACK_SECRET, PIN,
+};
+use money_transfer::entities::{
+    prelude::Transactions, sea_orm_active_enums::Status, transactions, users,
+};
+use money_transfer::service::outward_transfer::process_stale_transfers;
+use money_transfer::{configure_app, AppState};
+
+// Sends the batch and waits for the background run to finish it
+async fn run_batch<S, B>(
+    app: &S,
+    app_state: &AppState,
+    sender: &users::Model,
+    items: Value,
+) -> Value
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let request = test::TestRequest::post()
+        .uri("/api/transfer/batch")
+        .set_json(json!({ "pin": PIN, "items": items }));
+    let (status, body) = call(app, authorized(request, app_state, sender)).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{}", body);
+    let batch_id = body["data"]["batch"]["uuid"].as_str().unwrap().to_string();
+
+    for _ in 0..100 {
+        let request = test::TestRequest::get().uri(&format!("/api/transfer/batch/{}", batch_id));
+        let (status, body) = call(app, authorized(request, app_state, sender)).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        if body["data"]["batch"]["status"] != "processing" {
+            return body["data"].clone();
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+
+    panic!("Transfer batch {} did not finish", batch_id)
+}
+
+async fn outward_debit(app_state: &AppState, reference: &str) -> transactions::Model {
+    Transactions::find()
+        .filter(transactions::Column::Uuid.eq(reference))
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn batch_with_a_rejected_bank_transfer_refunds_that_item_only() {
+    let mock = MockPaystack::start().await;
+    mock.add_account("0123456789", "058", "ADA OKAFOR");
+    mock.add_account("9876543210", "044", "CHI EZE");
+    let app_state = sqlite_app_state(test_env(&mock.base_url)).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let ada = seed_payer(&app_state, "Ada", 10000).await;
+    let bola = seed_payer(&app_state, "Bola", 0).await;
+
+    // The first bank transfer is turned down, the second goes through
+    mock.script(
+        PaystackRoute::Transfer,
+        400,
+        json!({ "status": false, "message": "Your balance is not enough to fulfil this request" }),
+    );
+
+    let report = run_batch(
+        &app,
+        &app_state,
+        &ada,
+        json!([
+            { "user_id": &bola.uuid, "amount": 1000 },
+            { "account_number": "0123456789", "bank_code": "058", "amount": 2000 },
+            { "account_number": "9876543210", "bank_code": "044", "amount": 1500 },
+        ]),
+    )
+    .await;
+
+    assert_eq!(report["batch"]["status"], "partially_completed");
+    assert_eq!(report["batch"]["successful_count"], 2);
+    assert_eq!(report["batch"]["failed_count"], 1);
+
+    let items = report["items"].as_array().unwrap();
+    assert_eq!(items[0]["item"]["status"], "successful");
+    assert_eq!(items[0]["transaction_status"], "successful");
+    assert_eq!(items[1]["item"]["status"], "failed");
+    assert_eq!(
+        items[1]["item"]["error"],
+        "Transfer provider error: Your balance is not enough to fulfil this request"
+    );
+    assert_eq!(items[1]["transaction_status"], "failed");
+    assert_eq!(items[2]["item"]["status"], "successful");
+    assert_eq!(items[2]["transaction_status"], "pending");
+
+    // Only the p2p transfer and the bank transfer still in flight have left the wallet
+    let ada_wallet = wallet_of(&app_state.db, &ada).await;
+    assert_eq!(ada_wallet.current_balance, Decimal::from(7500));
+    let refunds: Vec<_> = wallet_transactions(&app_state.db, &ada_wallet)
+        .await
+        .into_iter()
+        .filter(|transaction| transaction.description.starts_with("Reversal"))
+        .collect();
+    assert_eq!(refunds.len(), 1);
+    assert_eq!(refunds[0].amount, Decimal::from(2000));
+    assert_eq!(
+        wallet_of(&app_state.db, &bola).await.current_balance,
+        Decimal::from(1000)
+    );
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn pending_transfers_are_settled_by_polling_and_refunded_when_reversed_later() {
+    let mock = MockPaystack::start().await;
+    mock.add_account("0123456789", "058", "ADA OKAFOR");
+    let mut env = test_env(&mock.base_url);
+    env.transfer_stale_after_mins = 0;
+    let app_state = sqlite_app_state(env).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let ada = seed_payer(&app_state, "Ada", 10000).await;
+
+    let report = run_batch(
+        &app,
+        &app_state,
+        &ada,
+        json!([
+            { "account_number": "0123456789", "bank_code": "058", "amount": 3000 },
+            { "account_number": "0123456789", "bank_code": "058", "amount": 2000 },
+        ]),
+    )
+    .await;
+    let references: Vec<String> = report["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| {
+            item["item"]["transaction_reference"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect();
+
+    // Paystack is still working on them
+    let summary = process_stale_transfers(&app_state).await.unwrap();
+    assert_eq!(summary.checked, 2);
+    assert_eq!(summary.confirmed + summary.reversed, 0);
+
+    // No webhook arrives for either transfer
+    mock.set_transfer_status(&references[0], "success");
+    mock.set_transfer_status(&references[1], "failed");
+    let summary = process_stale_transfers(&app_state).await.unwrap();
+    assert_eq!(summary.checked, 2);
+    assert_eq!(summary.confirmed, 1);
+    assert_eq!(summary.reversed, 1);
+
+    assert_eq!(
+        outward_debit(&app_state, &references[0]).await.status,
+        Some(Status::Successful)
+    );
+    assert_eq!(
+        outward_debit(&app_state, &references[1]).await.status,
+        Some(Status::Failed)
+    );
+    assert_eq!(
+        wallet_of(&app_state.db, &ada).await.current_balance,
+        Decimal::from(7000)
+    );
+
+    // The bank sends the successful transfer back, and Paystack may repeat the webhook
+    let reversed = transfer_event("transfer.reversed", &references[0], "Account closed");
+    for _ in 0..2 {
+        let (status, _) = call(
+            &app,
+            paystack_webhook_request(PAYSTACK_SECRET, &reversed).to_request(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    assert_eq!(
+        outward_debit(&app_state, &references[0]).await.status,
+        Some(Status::Reversed)
+    );
+    assert_eq!(
+        wallet_of(&app_state.db, &ada).await.current_balance,
+        Decimal::from(10000)
+    );
+
+    mock.stop().await;
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::json;
+
+use common::paystack_mock::{MockPaystack, PaystackRoute};
+use common::{seed_user, sqlite_app_state, test_env};
+use money_transfer::entities::{prelude::Wallets, sea_orm_active_enums::Status, wallets};
+use money_transfer::service::funding::{find_funding, settle_funding, FundingOutcome};
+use money_transfer::service::virtual_account::{ensure_virtual_account, user_virtual_accounts};
+use money_transfer::utils::paystack::Paystack;
+
+async fn balance(db: &DatabaseConnection, wallet_id: &String) -> Decimal {
+    Wallets::find()
+        .filter(wallets::Column::Uuid.eq(wallet_id))
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+        .current_balance
+}
+
+#[actix_web::test]
+async fn each_user_gets_one_virtual_account() {
+    let mock = MockPaystack::start().await;
+    let app_state = sqlite_app_state(test_env(&mock.base_url)).await;
+    let (ada, wallet) = seed_user(&app_state.db, "Ada").await;
+
+    // A failed request leaves nothing behind, so it can be tried again
+    mock.script(
+        PaystackRoute::CreateDedicatedAccount,
+        400,
+        json!({ "status": false, "message": "Dedicated NUBAN not available" }),
+    );
+    assert!(ensure_virtual_account(&app_state, &ada).await.is_err());
+    assert!(user_virtual_accounts(&app_state.db, &ada.uuid)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let account = ensure_virtual_account(&app_state, &ada).await.unwrap();
+    assert_eq!(account.wallet_id, wallet.uuid);
+    assert_eq!(account.bank_name, "Test Bank");
+    assert_eq!(account.account_number.len(), 10);
+
+    let again = ensure_virtual_account(&app_state, &ada).await.unwrap();
+    assert_eq!(again.uuid, account.uuid);
+    assert_eq!(
+        user_virtual_accounts(&app_state.db, &ada.uuid)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    let request = &mock.requests(PaystackRoute::CreateDedicatedAccount)[1];
+    assert_eq!(request["customer"], account.customer_code.as_str());
+    assert_eq!(request["preferred_bank"], "test-bank");
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn transfers_into_a_virtual_account_fund_its_wallet() {
+    let mock = MockPaystack::start().await;
+    let app_state = sqlite_app_state(test_env(&mock.base_url)).await;
+    let (ada, wallet) = seed_user(&app_state.db, "Ada").await;
+    ensure_virtual_account(&app_state, &ada).await.unwrap();
+    let paystack = Paystack::new(&app_state.env);
+
+    // Nothing was initialized on our side, the customer code is all there is to go on
+    mock.add_virtual_account_charge("ref-transfer", 250000, &ada.email);
+    let outcome = settle_funding(&paystack, "ref-transfer", &app_state.db).await;
+    assert_eq!(outcome.unwrap(), FundingOutcome::Credited);
+    let outcome = settle_funding(&paystack, "ref-transfer", &app_state.db).await;
+    assert_eq!(outcome.unwrap(), FundingOutcome::AlreadySettled);
+
+    let funding = find_funding(&app_state.db, "ref-transfer")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(funding.status, Some(Status::Successful));
+    assert_eq!(funding.user_id, ada.uuid);
+    assert_eq!(funding.wallet_id, wallet.uuid);
+    assert_eq!(
+        balance(&app_state.db, &wallet.uuid).await,
+        Decimal::from(2500)
+    );
+
+    mock.stop().await;
+}
@@ -0,0 +1,249 @@
+mod common;
+
+use actix_http::Request;
+use actix_web::{
+    body::MessageBody,
+    dev::{Service, ServiceResponse},
+    http::StatusCode,
+    test, web, App,
+};
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+
+use common::{
+    amount, authorized, call, seed_payer, seed_user, sqlite_app_state, test_env, wallet_of,
+    wallet_transactions,
+};
+use money_transfer::entities::users;
+use money_transfer::service::wallet_hold::available_balance;
+use money_transfer::utils::helpers::sign_payload;
+use money_transfer::{configure_app, AppState};
+
+const ISSUER_SECRET: &str = "card-issuer-secret";
+
+fn card_event(event: &str, data: Value) -> Request {
+    let body = json!({ "event": event, "data": data }).to_string();
+
+    test::TestRequest::post()
+        .uri("/api/webhook/cards/mock")
+        .insert_header((
+            "x-mock-issuer-signature",
+            sign_payload(&body, ISSUER_SECRET),
+        ))
+        .set_payload(body)
+        .to_request()
+}
+
+fn authorization(card_reference: &str, reference: &str, amount: u64) -> Request {
+    card_event(
+        "authorization.request",
+        json!({
+            "card_reference": card_reference,
+            "authorization_reference": reference,
+            "amount": amount,
+            "merchant_name": "Jumia",
+        }),
+    )
+}
+
+async fn create_card<S, B>(app: &S, app_state: &AppState, user: &users::Model, body: Value) -> Value
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let request = test::TestRequest::post().uri("/api/cards").set_json(body);
+    let (status, body) = call(app, authorized(request, app_state, user)).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+
+    body["data"]["card"].clone()
+}
+
+#[actix_web::test]
+async fn card_spending_is_held_on_authorization_and_debited_on_clearing() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let ada = seed_payer(&app_state, "Ada", 10000).await;
+
+    let card = create_card(&app, &app_state, &ada, json!({ "label": "Shopping" })).await;
+    assert_eq!(card["status"], "active");
+    assert_eq!(card["name_on_card"], "ADA TESTER");
+    assert_eq!(card["last4"].as_str().unwrap().len(), 4);
+    let card_id = card["uuid"].as_str().unwrap();
+    let card_reference = card["issuer_reference"].as_str().unwrap();
+
+    // Approved charges reserve the amount without touching the balance
+    let (status, body) = call(&app, authorization(card_reference, "auth_1", 3000)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["approved"], true);
+
+    let wallet = wallet_of(&app_state.db, &ada).await;
+    assert_eq!(wallet.current_balance, Decimal::from(10000));
+    assert_eq!(
+        available_balance(&app_state.db, &wallet).await.unwrap(),
+        Decimal::from(7000)
+    );
+
+    // A repeated webhook gets the same answer and holds nothing more
+    let (_, repeated) = call(&app, authorization(card_reference, "auth_1", 3000)).await;
+    assert_eq!(repeated["data"], body["data"]);
+
+    let (_, body) = call(&app, authorization(card_reference, "auth_2", 7500)).await;
+    assert_eq!(body["data"]["approved"], false);
+    assert_eq!(body["data"]["decline_reason"], "insufficient_funds");
+
+    let (_, body) = call(&app, authorization(card_reference, "auth_3", 2000)).await;
+    assert_eq!(body["data"]["approved"], true);
+    assert_eq!(
+        available_balance(&app_state.db, &wallet).await.unwrap(),
+        Decimal::from(5000)
+    );
+
+    // The merchant added a tip before clearing
+    let cleared = json!({ "authorization_reference": "auth_1", "amount": "3200.00" });
+    let (status, body) = call(&app, card_event("transaction.cleared", cleared.clone())).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (status, _) = call(&app, card_event("transaction.cleared", cleared)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let reversal = json!({ "authorization_reference": "auth_3" });
+    let (status, _) = call(&app, card_event("authorization.reversal", reversal)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let wallet = wallet_of(&app_state.db, &ada).await;
+    assert_eq!(wallet.current_balance, Decimal::from(6800));
+    assert_eq!(
+        available_balance(&app_state.db, &wallet).await.unwrap(),
+        Decimal::from(6800)
+    );
+
+    let txns = wallet_transactions(&app_state.db, &wallet).await;
+    assert_eq!(txns.len(), 1);
+    assert_eq!(txns[0].category, "card");
+    assert_eq!(txns[0].provider, "mock");
+    assert_eq!(txns[0].provider_reference.as_deref(), Some("auth_1"));
+    assert_eq!(txns[0].amount, Decimal::from(3200));
+
+    let request = test::TestRequest::get().uri(&format!("/api/cards/{}/authorizations", card_id));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let statuses: Vec<_> = body["data"]["authorizations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|authorization| authorization["status"].as_str().unwrap())
+        .collect();
+    assert_eq!(statuses, ["reversed", "declined", "cleared"]);
+    assert_eq!(
+        amount(&body["data"]["authorizations"][2]["cleared_amount"]),
+        Decimal::from(3200)
+    );
+
+    // Unsigned webhooks are refused
+    let request = test::TestRequest::post()
+        .uri("/api/webhook/cards/mock")
+        .insert_header(("x-mock-issuer-signature", "forged"))
+        .set_json(json!({ "event": "authorization.request", "data": {} }))
+        .to_request();
+    let (status, _) = call(&app, request).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // A negative charge would turn the hold into a credit
+    let negative = card_event(
+        "authorization.request",
+        json!({
+            "card_reference": card_reference,
+            "authorization_reference": "auth_negative",
+            "amount": -5000,
+            "merchant_name": "Jumia",
+        }),
+    );
+    let (status, _) = call(&app, negative).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn frozen_terminated_and_over_limit_cards_are_declined() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let ada = seed_payer(&app_state, "Ada", 50000).await;
+
+    let card = create_card(
+        &app,
+        &app_state,
+        &ada,
+        json!({ "spend_limit": 5000, "spend_limit_interval": "daily" }),
+    )
+    .await;
+    assert_eq!(card["spend_limit_interval"], "daily");
+    let card_id = card["uuid"].as_str().unwrap();
+    let card_reference = card["issuer_reference"].as_str().unwrap();
+
+    let (_, body) = call(&app, authorization(card_reference, "auth_1", 3000)).await;
+    assert_eq!(body["data"]["approved"], true);
+    let (_, body) = call(&app, authorization(card_reference, "auth_2", 2500)).await;
+    assert_eq!(body["data"]["decline_reason"], "spend_limit_exceeded");
+
+    let card_action = |action: &str| {
+        test::TestRequest::patch().uri(&format!("/api/cards/{}/{}", card_id, action))
+    };
+
+    let (status, body) = call(&app, authorized(card_action("freeze"), &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["card"]["status"], "frozen");
+    let (_, body) = call(&app, authorization(card_reference, "auth_3", 1000)).await;
+    assert_eq!(body["data"]["decline_reason"], "card_inactive");
+
+    let (status, _) = call(&app, authorized(card_action("unfreeze"), &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = call(&app, authorization(card_reference, "auth_4", 2000)).await;
+    assert_eq!(body["data"]["approved"], true);
+
+    // Raising the limit takes effect on the next charge, removing it leaves only the balance
+    let request = card_action("spend-limit").set_json(json!({ "spend_limit": 8000 }));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["card"]["spend_limit_interval"], "transaction");
+    let (_, body) = call(&app, authorization(card_reference, "auth_5", 8500)).await;
+    assert_eq!(body["data"]["decline_reason"], "spend_limit_exceeded");
+
+    let request = card_action("spend-limit").set_json(json!({ "spend_limit": null }));
+    call(&app, authorized(request, &app_state, &ada)).await;
+    let (_, body) = call(&app, authorization(card_reference, "auth_6", 8500)).await;
+    assert_eq!(body["data"]["approved"], true);
+
+    let request = card_action("spend-limit")
+        .set_json(json!({ "spend_limit": 1000, "spend_limit_interval": "weekly" }));
+    let (status, _) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = call(&app, authorized(card_action("terminate"), &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["card"]["status"], "terminated");
+    assert!(body["data"]["card"]["terminated_at"].is_string());
+
+    let (_, body) = call(&app, authorization(card_reference, "auth_7", 100)).await;
+    assert_eq!(body["data"]["decline_reason"], "card_inactive");
+    let (status, _) = call(&app, authorized(card_action("unfreeze"), &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Charges authorized before termination can still clear
+    let cleared = json!({ "authorization_reference": "auth_6", "amount": 8500 });
+    call(&app, card_event("transaction.cleared", cleared)).await;
+    let wallet = wallet_of(&app_state.db, &ada).await;
+    assert_eq!(wallet.current_balance, Decimal::from(41500));
+
+    // Other users can't see or touch the card
+    let (bola, _) = seed_user(&app_state.db, "Bola").await;
+    let (status, _) = call(&app, authorized(card_action("freeze"), &app_state, &bola)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use rust_decimal::Decimal;
+use sea_orm::*;
+use uuid::Uuid;
+
+use common::{seed_user, sqlite_app_state, test_env};
+use money_transfer::entities::{
+    prelude::Wallets,
+    sea_orm_active_enums::{Status, TrxType},
+    wallets,
+};
+use money_transfer::service::p2p_transfer::{P2PTransfer, P2PTransferError, P2PTransferTrait};
+use money_transfer::service::transaction_balance::{
+    TransactionBalance, TransactionBalanceTrait, TrxCategory,
+};
+use money_transfer::service::wallet_reconciliation::{
+    reconcile_wallets, unfreeze_wallet, MismatchKind,
+};
+
+async fn fetch_wallet(db: &DatabaseConnection, wallet_id: &String) -> wallets::Model {
+    Wallets::find()
+        .filter(wallets::Column::Uuid.eq(wallet_id))
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+// Writes a transaction the same way transfers do, with the balances given
+async fn record_credit(
+    db: &DatabaseConnection,
+    wallet: &wallets::Model,
+    previous_balance: i64,
+    current_balance: i64,
+) {
+    let txn = db.begin().await.unwrap();
+    TransactionBalance {
+        uuid: Uuid::new_v4().to_string(),
+        amount: Decimal::from(current_balance - previous_balance),
+        trx_type: TrxType::Credit,
+        status: Status::Successful,
+        description: String::from("Test credit"),
+        provider_reference: Some(Uuid::new_v4().to_string()),
+        current_balance: Decimal::from(current_balance),
+        previous_balance: Decimal::from(previous_balance),
+        user_id: wallet.user_id.to_string(),
+        wallet_id: wallet.uuid.to_string(),
+        provider: String::from("paystack"),
+        fees: None,
+        provider_fees: None,
+        category: TrxCategory::Funding,
+        meta: None,
+    }
+    .save_transaction_update_balance(&txn)
+    .await
+    .unwrap();
+    txn.commit().await.unwrap();
+}
+
+#[actix_web::test]
+async fn consistent_wallets_reconcile_cleanly() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let (_, ada_wallet) = seed_user(&app_state.db, "Ada").await;
+    seed_user(&app_state.db, "Bola").await;
+
+    record_credit(&app_state.db, &ada_wallet, 0, 5000).await;
+    record_credit(&app_state.db, &ada_wallet, 5000, 7500).await;
+
+    let report = reconcile_wallets(&app_state.db, true).await.unwrap();
+    // Ada, Bola and the escrow account's wallet
+    assert_eq!(report.wallets_checked, 3);
+    assert_eq!(report.mismatched_wallets, 0);
+    assert!(report.mismatches.is_empty());
+    assert!(report.frozen_wallets.is_empty());
+}
+
+#[actix_web::test]
+async fn drifted_wallet_is_frozen_and_cannot_send() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let (ada, ada_wallet) = seed_user(&app_state.db, "Ada").await;
+    let (bola, bola_wallet) = seed_user(&app_state.db, "Bola").await;
+
+    record_credit(&app_state.db, &ada_wallet, 0, 5000).await;
+    record_credit(&app_state.db, &bola_wallet, 0, 2000).await;
+
+    // Balance changed without a matching transaction
+    let mut drifted: wallets::ActiveModel =
+        fetch_wallet(&app_state.db, &ada_wallet.uuid).await.into();
+    drifted.current_balance = Set(Decimal::from(9000));
+    drifted.update(&app_state.db).await.unwrap();
+
+    let report = reconcile_wallets(&app_state.db, true).await.unwrap();
+    assert_eq!(report.mismatched_wallets, 1);
+    assert_eq!(report.mismatches.len(), 1);
+    assert_eq!(report.mismatches[0].kind, MismatchKind::BalanceDrift);
+    assert_eq!(report.mismatches[0].expected, Decimal::from(5000));
+    assert_eq!(report.mismatches[0].actual, Decimal::from(9000));
+    assert_eq!(report.frozen_wallets, vec![ada_wallet.uuid.to_string()]);
+
+    let frozen = fetch_wallet(&app_state.db, &ada_wallet.uuid).await;
+    assert!(frozen.is_frozen());
+    assert_eq!(
+        frozen.frozen_reason.as_deref(),
+        Some("Reconciliation found balance_drift")
+    );
+
+    let transfer = P2PTransfer {
+        sender: ada.clone(),
+        receiver_id: bola.uuid.to_string(),
+        amount: Decimal::from(1000),
+        narration: None,
+    };
+    assert!(matches!(
+        transfer.transfer(&app_state.db).await,
+        Err(P2PTransferError::WalletFrozen)
+    ));
+
+    // Frozen wallets still receive funds
+    let transfer = P2PTransfer {
+        sender: bola.clone(),
+        receiver_id: ada.uuid.to_string(),
+        amount: Decimal::from(1000),
+        narration: None,
+    };
+    assert!(transfer.transfer(&app_state.db).await.is_ok());
+
+    unfreeze_wallet(&app_state.db, &ada_wallet.uuid)
+        .await
+        .unwrap();
+    let transfer = P2PTransfer {
+        sender: ada,
+        receiver_id: bola.uuid.to_string(),
+        amount: Decimal::from(1000),
+        narration: None,
+    };
+    assert!(transfer.transfer(&app_state.db).await.is_ok());
+}
+
+#[actix_web::test]
+async fn broken_chain_is_reported_without_freezing() {
+    let app_state = sqlite_app_state(test_env("http://127.0.0.1:1")).await;
+    let (_, ada_wallet) = seed_user(&app_state.db, "Ada").await;
+
+    record_credit(&app_state.db, &ada_wallet, 0, 5000).await;
+    // Should have started from 5000
+    record_credit(&app_state.db, &ada_wallet, 4000, 6000).await;
+
+    let report = reconcile_wallets(&app_state.db, false).await.unwrap();
+    assert_eq!(report.mismatched_wallets, 1);
+    assert_eq!(report.mismatches.len(), 1);
+    assert_eq!(report.mismatches[0].kind, MismatchKind::BrokenChain);
+    assert_eq!(report.mismatches[0].expected, Decimal::from(5000));
+    assert_eq!(report.mismatches[0].actual, Decimal::from(4000));
+    assert!(report.frozen_wallets.is_empty());
+    assert!(!fetch_wallet(&app_state.db, &ada_wallet.uuid)
+        .await
+        .is_frozen());
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use actix_web::{test, web, App};
+use serde_json::{json, Value};
+
+use common::{app_state_without_db, paystack_webhook_request, test_env, PAYSTACK_SECRET};
+use money_transfer::configure_app;
+
+#[actix_web::test]
+async fn webhook_with_a_bad_signature_is_ignored() {
+    let app_state = app_state_without_db(test_env("http://127.0.0.1:1"));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .configure(configure_app),
+    )
+    .await;
+
+    let payload = common::charge_success_event("ref-1", 500000);
+    let request = paystack_webhook_request("not-the-secret", &payload).to_request();
+    let response: Value = test::call_and_read_body_json(&app, request).await;
+
+    assert_eq!(response["message"], json!("Invlaid signature"));
+}
+
+#[actix_web::test]
+async fn signed_webhook_for_an_unhandled_event_is_acknowledged() {
+    let app_state = app_state_without_db(test_env("http://127.0.0.1:1"));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .configure(configure_app),
+    )
+    .await;
+
+    let payload = json!({ "event": "subscription.create", "data": {} });
+    let request = paystack_webhook_request(PAYSTACK_SECRET, &payload).to_request();
+    let response = test::call_service(&app, request).await;
+
+    assert!(response.status().is_success());
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["status"], json!("success"));
+}
+
+#[actix_web::test]
+async fn webhook_for_an_unconfigured_provider_is_not_found() {
+    let app_state = app_state_without_db(test_env("http://127.0.0.1:1"));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .configure(configure_app),
+    )
+    .await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/webhook/flutterwave")
+        .set_payload("{}")
+        .to_request();
+    let response = test::call_service(&app, request).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
@@ -0,0 +1,205 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, web, App};
+use rust_decimal::Decimal;
+use sea_orm::*;
+use serde_json::json;
+
+use common::paystack_mock::{MockPaystack, PaystackRoute};
+use common::{
+    authorized, call, paystack_webhook_request, seed_payer, sqlite_app_state, test_env,
+    transfer_event, wallet_of, PAYSTACK_SECRET, PIN,
+};
+use money_transfer::entities::{
+    prelude::Transactions,
+    sea_orm_active_enums::{Status, TrxType},
+    transactions, users,
+};
+use money_transfer::service::outward_transfer::{
+    resolve_bank_account, OutwardTransfer, OutwardTransferError, OutwardTransferTrait,
+};
+use money_transfer::{configure_app, AppState};
+
+async fn withdrawal(app_state: &AppState, sender: &users::Model, amount: i64) -> OutwardTransfer {
+    let bank_account = resolve_bank_account(
+        &String::from("0123456789"),
+        &String::from("058"),
+        &app_state.env,
+    )
+    .await
+    .unwrap();
+
+    OutwardTransfer {
+        sender: sender.clone(),
+        bank_account,
+        amount: Decimal::from(amount),
+        narration: None,
+    }
+}
+
+async fn outward_debit(app_state: &AppState, reference: &str) -> transactions::Model {
+    Transactions::find()
+        .filter(transactions::Column::Uuid.eq(reference))
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn withdrawal_debits_the_wallet_and_is_settled_by_the_transfer_webhook() {
+    let mock = MockPaystack::start().await;
+    mock.add_account("0123456789", "058", "ADA OKAFOR");
+    let app_state = sqlite_app_state(test_env(&mock.base_url)).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let ada = seed_payer(&app_state, "Ada", 10000).await;
+
+    let reference = withdrawal(&app_state, &ada, 3000)
+        .await
+        .transfer(&app_state)
+        .await
+        .unwrap();
+
+    let transfers = mock.requests(PaystackRoute::Transfer);
+    assert_eq!(transfers.len(), 1);
+    assert_eq!(transfers[0]["reference"], reference.as_str());
+    assert_eq!(transfers[0]["amount"], 300000);
+
+    let debit = outward_debit(&app_state, &reference).await;
+    assert_eq!(debit.status, Some(Status::Pending));
+    assert_eq!(debit.amount, Decimal::from(3000));
+    assert_eq!(
+        wallet_of(&app_state.db, &ada).await.current_balance,
+        Decimal::from(7000)
+    );
+
+    let success = transfer_event("transfer.success", &reference, "");
+    let (status, _) = call(
+        &app,
+        paystack_webhook_request(PAYSTACK_SECRET, &success).to_request(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    assert_eq!(
+        outward_debit(&app_state, &reference).await.status,
+        Some(Status::Successful)
+    );
+    assert_eq!(
+        wallet_of(&app_state.db, &ada).await.current_balance,
+        Decimal::from(7000)
+    );
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn withdrawal_rejected_by_paystack_is_refunded() {
+    let mock = MockPaystack::start().await;
+    mock.add_account("0123456789", "058", "ADA OKAFOR");
+    let app_state = sqlite_app_state(test_env(&mock.base_url)).await;
+    let ada = seed_payer(&app_state, "Ada", 10000).await;
+
+    mock.script(
+        PaystackRoute::Transfer,
+        400,
+        json!({ "status": false, "message": "Your balance is not enough to fulfil this request" }),
+    );
+
+    let result = withdrawal(&app_state, &ada, 3000)
+        .await
+        .transfer(&app_state)
+        .await;
+    assert!(
+        matches!(result, Err(OutwardTransferError::ProviderError(_))),
+        "{:?}",
+        result
+    );
+
+    let ada_wallet = wallet_of(&app_state.db, &ada).await;
+    assert_eq!(ada_wallet.current_balance, Decimal::from(10000));
+    let debit = Transactions::find()
+        .filter(transactions::Column::WalletId.eq(&ada_wallet.uuid))
+        .filter(transactions::Column::TrxType.eq(TrxType::Debit))
+        .one(&app_state.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(debit.status, Some(Status::Failed));
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn withdrawal_above_the_balance_never_reaches_paystack() {
+    let mock = MockPaystack::start().await;
+    mock.add_account("0123456789", "058", "ADA OKAFOR");
+    let app_state = sqlite_app_state(test_env(&mock.base_url)).await;
+    let ada = seed_payer(&app_state, "Ada", 1000).await;
+
+    let result = withdrawal(&app_state, &ada, 3000)
+        .await
+        .transfer(&app_state)
+        .await;
+    assert!(
+        matches!(result, Err(OutwardTransferError::InsufficientFunds)),
+        "{:?}",
+        result
+    );
+
+    assert!(mock.requests(PaystackRoute::Transfer).is_empty());
+    assert_eq!(
+        wallet_of(&app_state.db, &ada).await.current_balance,
+        Decimal::from(1000)
+    );
+
+    mock.stop().await;
+}
+
+#[actix_web::test]
+async fn withdraw_endpoint_sends_the_transfer_to_a_resolved_account() {
+    let mock = MockPaystack::start().await;
+    mock.add_account("0123456789", "058", "ADA OKAFOR");
+    let app_state = sqlite_app_state(test_env(&mock.base_url)).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .configure(configure_app),
+    )
+    .await;
+    let ada = seed_payer(&app_state, "Ada", 10000).await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/transfer/withdraw")
+        .set_json(json!({
+            "amount": 2500, "pin": PIN, "account_number": "9999999999", "bank_code": "058"
+        }));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    assert!(mock.requests(PaystackRoute::Transfer).is_empty());
+
+    let request = test::TestRequest::post()
+        .uri("/api/transfer/withdraw")
+        .set_json(json!({
+            "amount": 2500, "pin": PIN, "account_number": "0123456789", "bank_code": "058"
+        }));
+    let (status, body) = call(&app, authorized(request, &app_state, &ada)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let reference = body["data"]["reference"].as_str().unwrap();
+
+    assert_eq!(
+        outward_debit(&app_state, reference).await.status,
+        Some(Status::Pending)
+    );
+    assert_eq!(
+        wallet_of(&app_state.db, &ada).await.current_balance,
+        Decimal::from(7500)
+    );
+
+    mock.stop().await;
+}